@@ -1,7 +1,7 @@
 use proc_macro::TokenStream;
 use proc_macro2::{Ident, TokenStream as TokenStream2};
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Generics, Type, TypePath};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Generics, LitStr, Type, TypePath};
 
 /// Derives TagValue implementation for structs where all fields are `Option<Tag<'a, ValueType, TagName>>`
 ///
@@ -27,6 +27,91 @@ pub fn derive_simple_xml_deserialize(input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Derives `FromPsObject` (and `FromPsValue`) for structs with named fields
+///
+/// Each field is read from the property of the same name, ignoring case and the underscores of
+/// the field name. `#[ps(rename = "PropertyName")]` reads a field from another property.
+#[proc_macro_derive(FromPsObject, attributes(ps))]
+pub fn derive_from_ps_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let expanded = impl_from_ps_object(&input);
+    TokenStream::from(expanded)
+}
+
+fn impl_from_ps_object(input: &DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => panic!("FromPsObject can only be derived for structs with named fields"),
+        },
+        _ => panic!("FromPsObject can only be derived for structs"),
+    };
+
+    let field_conversions: Vec<TokenStream2> = fields
+        .iter()
+        .map(|field| {
+            let field_name = field.ident.as_ref().unwrap();
+            let field_type = &field.ty;
+            let property_name = ps_rename(field).unwrap_or_else(|| {
+                field_name.to_string().trim_start_matches("r#").to_string()
+            });
+            quote! {
+                #field_name: ::protocol_powershell_remoting::from_ps_object::convert_property::<#field_type>(
+                    object,
+                    #property_name,
+                    stringify!(#name),
+                )?
+            }
+        })
+        .collect();
+
+    quote! {
+        impl #impl_generics ::protocol_powershell_remoting::FromPsObject for #name #ty_generics #where_clause {
+            fn from_ps_object(
+                object: &::protocol_powershell_remoting::ComplexObject,
+            ) -> Result<Self, ::protocol_powershell_remoting::PowerShellRemotingError> {
+                Ok(Self {
+                    #(#field_conversions),*
+                })
+            }
+        }
+
+        impl #impl_generics ::protocol_powershell_remoting::FromPsValue for #name #ty_generics #where_clause {
+            fn from_ps_value(
+                value: &::protocol_powershell_remoting::PsValue,
+            ) -> Result<Self, ::protocol_powershell_remoting::PowerShellRemotingError> {
+                match value.as_object() {
+                    Some(object) => <Self as ::protocol_powershell_remoting::FromPsObject>::from_ps_object(object),
+                    None => Err(::protocol_powershell_remoting::PowerShellRemotingError::ConversionError(
+                        format!("Expected an object for {}, got {:?}", stringify!(#name), value),
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// The property name of `#[ps(rename = "...")]`
+fn ps_rename(field: &syn::Field) -> Option<String> {
+    let mut rename = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("ps")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let name: LitStr = meta.value()?.parse()?;
+                rename = Some(name.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported ps attribute, expected `rename`"))
+            }
+        })
+        .unwrap_or_else(|error| panic!("Invalid ps attribute: {error}"));
+    }
+    rename
+}
+
 fn impl_simple_tag_value(input: &DeriveInput) -> TokenStream2 {
     let name = &input.ident;
     let generics = &input.generics;
@@ -191,16 +276,16 @@ fn generate_simple_xml_visitor_impl(
 ) -> TokenStream2 {
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    // Generate match arms for each field
-    let match_arms: Vec<TokenStream2> = field_entries
+    // Generate a branch for each field, children are matched by namespace URI and local name
+    let match_branches: Vec<TokenStream2> = field_entries
         .iter()
         .filter_map(|entry| {
             if let Some(tag_name_type) = &entry.tag_name_type {
                 let field_name = &entry.field_name;
                 Some(quote! {
-                    crate::cores::#tag_name_type::TAG_NAME => {
+                    if <crate::cores::#tag_name_type as crate::cores::TagName>::matches_node(&child) {
                         self.#field_name = Some(xml::parser::XmlDeserialize::from_node(child)?);
-                    }
+                    } else
                 })
             } else {
                 None
@@ -208,6 +293,26 @@ fn generate_simple_xml_visitor_impl(
         })
         .collect();
 
+    // Tag names the struct knows how to handle, so callers can route other children elsewhere
+    let known_tag_names: Vec<TokenStream2> = field_entries
+        .iter()
+        .filter_map(|entry| {
+            entry
+                .tag_name_type
+                .as_ref()
+                .map(|tag_name_type| quote! { crate::cores::#tag_name_type::TAG_NAME })
+        })
+        .collect();
+
+    let known_tag_checks: Vec<TokenStream2> = field_entries
+        .iter()
+        .filter_map(|entry| {
+            entry.tag_name_type.as_ref().map(|tag_name_type| {
+                quote! { <crate::cores::#tag_name_type as crate::cores::TagName>::matches_node(node) }
+            })
+        })
+        .collect();
+
     // Generate field list for finish method
     let field_names: Vec<&Ident> = field_entries.iter().map(|f| &f.field_name).collect();
     let field_list = quote! { #(#field_names),* };
@@ -256,6 +361,14 @@ fn generate_simple_xml_visitor_impl(
     let final_field_list: Vec<&Ident> = field_entries.iter().map(|f| &f.field_name).collect();
 
     quote! {
+        impl #impl_generics crate::cores::KnownTagNames for #struct_name #ty_generics #where_clause {
+            const KNOWN_TAG_NAMES: &'static [&'static str] = &[#(#known_tag_names),*];
+
+            fn is_known_node(node: &xml::parser::Node<'_, '_>) -> bool {
+                false #(|| #known_tag_checks)*
+            }
+        }
+
         impl #impl_generics xml::parser::XmlVisitor<'a> for #visitor_name #ty_generics #where_clause {
             type Value = #struct_name #ty_generics;
 
@@ -268,16 +381,8 @@ fn generate_simple_xml_visitor_impl(
                         continue; // Skip non-element nodes like text/whitespace
                     }
 
-                    let tag_name = child.tag_name().name();
-                    let namespace = child.tag_name().namespace();
-
-                    match tag_name {
-                        #(#match_arms)*
-                        _ => {
-                            return Err(xml::XmlError::InvalidXml(format!(
-                                "Unknown tag in {}: {tag_name}", stringify!(#struct_name)
-                            )));
-                        }
+                    #(#match_branches)* {
+                        crate::cores::UnknownTagPolicy::handle(stringify!(#struct_name), child)?;
                     }
                 }
 
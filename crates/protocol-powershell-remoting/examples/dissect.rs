@@ -0,0 +1,36 @@
+use base64::Engine;
+use protocol_powershell_remoting::Dissector;
+use std::io::{self, BufRead};
+
+/// Print the fragments and messages of base64 shell streams, the text of the `rsp:Stream` of
+/// `Receive` responses or the arguments of `Send` requests, in the order they were exchanged.
+///
+/// Usage:
+///   dissect <stream> [<stream> ...]
+///   dissect < streams.txt       (one stream per line)
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut streams: Vec<String> = std::env::args().skip(1).collect();
+    if streams.is_empty() {
+        for line in io::stdin().lock().lines() {
+            streams.push(line?);
+        }
+    }
+
+    let mut dissector = Dissector::new();
+    for (index, stream) in streams.iter().map(|s| s.trim()).enumerate() {
+        if stream.is_empty() {
+            continue;
+        }
+
+        println!("=== Stream {}", index + 1);
+        match base64::engine::general_purpose::STANDARD.decode(stream) {
+            Ok(bytes) => print!("{}", dissector.dissect(&bytes)),
+            Err(error) => println!("! not base64: {error}"),
+        }
+    }
+
+    if dissector.has_pending() {
+        println!("! some messages did not receive their end fragment");
+    }
+    Ok(())
+}
@@ -9,7 +9,7 @@ use crate::{
     deserialize::{DeserializationContext, PsXmlDeserialize},
 };
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Destination {
     Client = 0x0000_0001,
     Server = 0x0000_0002,
@@ -29,7 +29,8 @@ impl TryFrom<u32> for Destination {
     }
 }
 
-#[derive(Debug, Clone)]
+/// https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-psrp/b59ac9eb-9595-4bbc-9c5f-512f1e7b6422
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MessageType {
     SessionCapability,
     InitRunspacepool,
@@ -65,6 +66,41 @@ pub enum MessageType {
 }
 
 impl MessageType {
+    /// Every message type of MS-PSRP 2.2.1
+    pub const ALL: [MessageType; 31] = [
+        MessageType::SessionCapability,
+        MessageType::InitRunspacepool,
+        MessageType::PublicKey,
+        MessageType::EncryptedSessionKey,
+        MessageType::PublicKeyRequest,
+        MessageType::ConnectRunspacepool,
+        MessageType::RunspacepoolInitData,
+        MessageType::ResetRunspaceState,
+        MessageType::SetMaxRunspaces,
+        MessageType::SetMinRunspaces,
+        MessageType::RunspaceAvailability,
+        MessageType::RunspacepoolState,
+        MessageType::CreatePipeline,
+        MessageType::GetAvailableRunspaces,
+        MessageType::UserEvent,
+        MessageType::ApplicationPrivateData,
+        MessageType::GetCommandMetadata,
+        MessageType::RunspacepoolHostCall,
+        MessageType::RunspacepoolHostResponse,
+        MessageType::PipelineInput,
+        MessageType::EndOfPipelineInput,
+        MessageType::PipelineOutput,
+        MessageType::ErrorRecord,
+        MessageType::PipelineState,
+        MessageType::DebugRecord,
+        MessageType::VerboseRecord,
+        MessageType::WarningRecord,
+        MessageType::ProgressRecord,
+        MessageType::InformationRecord,
+        MessageType::PipelineHostCall,
+        MessageType::PipelineHostResponse,
+    ];
+
     pub fn value(&self) -> u32 {
         match self {
             MessageType::SessionCapability => 0x00010002,
@@ -155,8 +191,17 @@ pub struct PID {
     pub value: [u8; 16],
 }
 
+/// The length of the message header, the destination, the message type, the RPID and the PID
+pub const MESSAGE_HEADER_LEN: usize = 40;
+
+/// The UTF-8 byte order mark the data of a message starts with, 43 bytes in with the header
+pub const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
 #[derive(Debug, Clone)]
 ///https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-psrp/497ac440-89fb-4cb3-9cc1-3434c1aa74c3
+///
+/// The GUIDs are written in the little endian layout of Windows, the one of a `ShellId`
+/// read as bytes.
 pub struct PowerShellRemotingMessage {
     pub destination: Destination,
     pub message_type: MessageType,
@@ -164,7 +209,7 @@ pub struct PowerShellRemotingMessage {
     pub rpid: Uuid,
     /// PowerShell Process ID (PID)
     pub pid: Option<Uuid>,
-    pub data: Vec<u8>, // This will hold the serialized PsObject data
+    pub data: Vec<u8>, // This will hold the serialized PsObject data, without the BOM
 }
 
 impl PowerShellRemotingMessage {
@@ -185,6 +230,9 @@ impl PowerShellRemotingMessage {
 
         let mut rest = vec![];
         cursor.read_to_end(&mut rest)?;
+        if rest.starts_with(UTF8_BOM) {
+            rest.drain(..UTF8_BOM.len());
+        }
 
         Ok(Self {
             destination: Destination::try_from(destination).map_err(|e| {
@@ -193,11 +241,8 @@ impl PowerShellRemotingMessage {
                 ))
             })?,
             message_type,
-            rpid: Uuid::from_bytes(rpid_bytes),
-            pid: pid_bytes
-                .iter()
-                .all(|&b| b == 0)
-                .then_some(Uuid::from_bytes(pid_bytes)),
+            rpid: Uuid::from_bytes_le(rpid_bytes),
+            pid: Some(Uuid::from_bytes_le(pid_bytes)).filter(|pid| !pid.is_nil()),
             data: rest,
         })
     }
@@ -208,8 +253,17 @@ impl PowerShellRemotingMessage {
         pid: Option<Uuid>,
     ) -> Result<PowerShellRemotingMessage, crate::PowerShellRemotingError> {
         let message_type = message.message_type();
-        let data = message.to_ps_object();
+        if message_type == MessageType::EndOfPipelineInput {
+            return Ok(Self {
+                destination: Destination::Server,
+                message_type,
+                rpid,
+                pid,
+                data: Vec::new(),
+            });
+        }
 
+        let data = message.to_ps_object();
         Self::new(Destination::Server, message_type, rpid, pid, &data)
     }
 
@@ -230,15 +284,19 @@ impl PowerShellRemotingMessage {
     }
 
     pub fn pack(self) -> Vec<u8> {
-        let mut buffer = Vec::new();
+        let mut buffer = Vec::with_capacity(MESSAGE_HEADER_LEN + UTF8_BOM.len() + self.data.len());
         buffer
             .write_u32::<LittleEndian>(self.destination as u32)
             .unwrap();
         buffer
             .write_u32::<LittleEndian>(self.message_type.value())
             .unwrap();
-        buffer.extend_from_slice(self.rpid.as_bytes());
-        buffer.extend_from_slice(self.pid.unwrap_or_default().as_bytes());
+        buffer.extend_from_slice(&self.rpid.to_bytes_le());
+        buffer.extend_from_slice(&self.pid.unwrap_or_default().to_bytes_le());
+        // A message without data, as END_OF_PIPELINE_INPUT, carries no BOM either
+        if !self.data.is_empty() {
+            buffer.extend_from_slice(UTF8_BOM);
+        }
         buffer.extend_from_slice(&self.data);
         buffer
     }
@@ -310,14 +368,14 @@ impl PowerShellFragment {
         start_of_fragment: bool,
         blob: PowerShellRemotingMessage,
     ) -> Self {
-        let blob_length = blob.data.len() as u32;
+        let blob = blob.pack();
         Self {
             object_id,
             fragment_id,
             end_of_fragment,
             start_of_fragment,
-            blob_length,
-            blob: blob.pack(),
+            blob_length: blob.len() as u32,
+            blob,
         }
     }
 
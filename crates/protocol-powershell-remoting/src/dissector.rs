@@ -0,0 +1,226 @@
+use std::{collections::HashMap, fmt::Write};
+
+use byteorder::{ByteOrder, LittleEndian};
+use uuid::Uuid;
+use xml::builder::escape;
+
+use crate::{Destination, Fragment, MESSAGE_HEADER_LEN, MessageType, UTF8_BOM};
+
+/// Turns the streams of a shell, the decoded `rsp:Stream` of a `Receive` or the arguments of
+/// a `Send`, into a report to read when a real host does not answer as expected: each
+/// fragment with its ids and flags, then each message once complete with its type, RPID, PID
+/// and its CLIXML indented.
+///
+/// What can't be decoded is reported instead of failing, a message of an unknown type is
+/// still shown. The fragments of a message sent across several streams are kept between the
+/// calls of [`dissect`](Self::dissect), as the [`Defragmenter`](crate::Defragmenter) does.
+#[derive(Debug, Default)]
+pub struct Dissector {
+    /// The object ids of the messages started, with their next fragment id and their data
+    pending: HashMap<u64, (u64, Vec<u8>)>,
+}
+
+impl Dissector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The report of the fragments of `stream` and of the messages they complete
+    pub fn dissect(&mut self, stream: &[u8]) -> String {
+        let mut report = String::new();
+        self.write_stream(&mut report, stream)
+            .expect("writing to a String does not fail");
+        report
+    }
+
+    /// Whether messages were started but not ended, e.g. their other fragments are in the
+    /// next stream
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    fn write_stream(&mut self, report: &mut String, stream: &[u8]) -> std::fmt::Result {
+        let mut remaining = stream;
+        while !remaining.is_empty() {
+            let (fragment, rest) = match Fragment::unpack(remaining) {
+                Ok(unpacked) => unpacked,
+                Err(error) => {
+                    writeln!(report, "! {} bytes not decoded: {error}", remaining.len())?;
+                    return writeln!(report, "  {}", hex(remaining));
+                }
+            };
+            remaining = rest;
+
+            let flags = match (fragment.start, fragment.end) {
+                (true, true) => "start end",
+                (true, false) => "start",
+                (false, true) => "end",
+                (false, false) => "middle",
+            };
+            writeln!(
+                report,
+                "Fragment object {} fragment {} [{flags}] {} bytes",
+                fragment.object_id,
+                fragment.fragment_id,
+                fragment.data.len()
+            )?;
+
+            let Fragment {
+                object_id,
+                fragment_id,
+                start,
+                end,
+                data,
+            } = fragment;
+            let message = if start {
+                if self.pending.remove(&object_id).is_some() {
+                    writeln!(report, "! object {object_id} started again before its end")?;
+                }
+                data
+            } else {
+                match self.pending.remove(&object_id) {
+                    Some((next, mut message)) if next == fragment_id => {
+                        message.extend_from_slice(&data);
+                        message
+                    }
+                    Some((next, _)) => {
+                        writeln!(
+                            report,
+                            "! fragment {fragment_id} out of order, expected {next}, object {object_id} dropped"
+                        )?;
+                        continue;
+                    }
+                    None => {
+                        writeln!(report, "! object {object_id} was not started")?;
+                        continue;
+                    }
+                }
+            };
+
+            if end {
+                write_message(report, &message)?;
+            } else {
+                self.pending.insert(object_id, (fragment_id + 1, message));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The header of a complete message then its data
+fn write_message(report: &mut String, message: &[u8]) -> std::fmt::Result {
+    if message.len() < MESSAGE_HEADER_LEN {
+        writeln!(
+            report,
+            "! message of {} bytes, shorter than its header",
+            message.len()
+        )?;
+        return writeln!(report, "  {}", hex(message));
+    }
+
+    let destination = LittleEndian::read_u32(&message[0..4]);
+    let destination = match Destination::try_from(destination) {
+        Ok(destination) => format!("{destination:?}"),
+        Err(_) => format!("{destination:#010x}"),
+    };
+    let message_type = LittleEndian::read_u32(&message[4..8]);
+    let name = MessageType::try_from(message_type)
+        .map_or_else(|_| "Unknown".to_string(), |known| format!("{known:?}"));
+    let guid = |bytes: &[u8]| Uuid::from_slice_le(bytes).expect("a GUID is 16 bytes");
+    let rpid = guid(&message[8..24]);
+    let pid = guid(&message[24..40]);
+
+    writeln!(
+        report,
+        "  Message {name} ({message_type:#010x}) to {destination}"
+    )?;
+    writeln!(report, "    RPID {rpid}")?;
+    if !pid.is_nil() {
+        writeln!(report, "    PID  {pid}")?;
+    }
+
+    let data = &message[MESSAGE_HEADER_LEN..];
+    let data = data.strip_prefix(UTF8_BOM).unwrap_or(data);
+    if data.is_empty() {
+        return writeln!(report, "    (no data)");
+    }
+
+    let Ok(text) = std::str::from_utf8(data) else {
+        writeln!(report, "    ! {} bytes of data not UTF-8", data.len())?;
+        return writeln!(report, "    {}", hex(data));
+    };
+    match xml::parser::parse(text) {
+        Ok(document) => write_element(report, document.root_element(), 2),
+        Err(error) => {
+            writeln!(report, "    ! data not XML: {error}")?;
+            writeln!(report, "    {text}")
+        }
+    }
+}
+
+/// `element` and its children, one per line indented by their depth. An element of text only
+/// stays on its line, e.g. `<S N="Name">WinRM</S>`.
+fn write_element(
+    report: &mut String,
+    element: xml::parser::Node<'_, '_>,
+    depth: usize,
+) -> std::fmt::Result {
+    let indent = "  ".repeat(depth);
+    let name = element.tag_name().name();
+    write!(report, "{indent}<{name}")?;
+    for attribute in element.attributes() {
+        write!(
+            report,
+            " {}=\"{}\"",
+            attribute.name(),
+            escape(attribute.value())
+        )?;
+    }
+
+    let children: Vec<_> = element
+        .children()
+        .filter(|child| {
+            child.is_element()
+                || (child.is_text() && !child.text().unwrap_or_default().trim().is_empty())
+        })
+        .collect();
+    match children[..] {
+        [] => return writeln!(report, " />"),
+        [text] if text.is_text() => {
+            return writeln!(
+                report,
+                ">{}</{name}>",
+                escape(text.text().unwrap_or_default())
+            );
+        }
+        _ => writeln!(report, ">")?,
+    }
+
+    for child in children {
+        if child.is_element() {
+            write_element(report, child, depth + 1)?;
+        } else {
+            writeln!(
+                report,
+                "{indent}  {}",
+                escape(child.text().unwrap_or_default().trim())
+            )?;
+        }
+    }
+    writeln!(report, "{indent}</{name}>")
+}
+
+/// The bytes as hexadecimal pairs, the first 64 of them
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = bytes
+        .iter()
+        .take(64)
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if bytes.len() > 64 {
+        hex.push_str(" ...");
+    }
+    hex
+}
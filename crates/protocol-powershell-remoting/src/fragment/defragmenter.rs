@@ -7,45 +7,45 @@ use std::collections::HashMap;
 /// Buffer for accumulating fragments during defragmentation
 #[derive(Debug)]
 struct FragmentBuffer {
-    fragments: Vec<Fragment>,
+    data: Vec<u8>,
+    next_fragment_id: u64,
     is_complete: bool,
 }
 
 impl FragmentBuffer {
     fn new() -> Self {
         Self {
-            fragments: Vec::new(),
+            data: Vec::new(),
+            next_fragment_id: 0,
             is_complete: false,
         }
     }
 
     /// Add a fragment to this buffer if it's the expected next fragment
     fn add_fragment(&mut self, fragment: Fragment) -> Result<(), PowerShellRemotingError> {
+        if fragment.fragment_id != self.next_fragment_id {
+            return Err(PowerShellRemotingError::InvalidMessage(format!(
+                "Fragment {} of object {} is out of order, expected fragment {}",
+                fragment.fragment_id, fragment.object_id, self.next_fragment_id
+            )));
+        }
+
+        self.next_fragment_id += 1;
         if fragment.end {
             self.is_complete = true;
         }
 
-        self.fragments.push(fragment);
+        self.data.extend_from_slice(&fragment.data);
 
         Ok(())
     }
-
-    /// Reassemble all fragments into complete message data
-    fn reassemble(&self) -> Vec<u8> {
-        let mut frags = self.fragments.clone();
-        frags.sort_by(|a, b| a.fragment_id.cmp(&b.fragment_id));
-        let total_len: usize = frags.iter().map(|f| f.data.len()).sum();
-        let mut out = Vec::with_capacity(total_len);
-
-        for f in frags {
-            out.extend_from_slice(&f.data);
-        }
-        out
-    }
 }
 
 /// Defragmenter handles defragmentation of incoming PowerShell remoting message fragments
-/// with internal state management
+/// with internal state management.
+///
+/// The messages being reassembled are kept between calls, the fragments of one message may
+/// come in several `Receive` responses and interleave with the fragments of other messages.
 #[derive(Debug)]
 pub struct Defragmenter {
     buffers: HashMap<u64, FragmentBuffer>,
@@ -91,26 +91,30 @@ impl Defragmenter {
                 continue;
             }
 
-            // Get or create buffer for this object
-            let buffer = self
-                .buffers
-                .entry(object_id)
-                .or_insert_with(FragmentBuffer::new);
-
-            // Handle start fragment - reset buffer
-            if fragment.start {
-                *buffer = FragmentBuffer::new();
-            }
+            // Handle start fragment - reset buffer, the others continue a started message
+            let buffer = if fragment.start {
+                self.buffers.insert(object_id, FragmentBuffer::new());
+                self.buffers
+                    .get_mut(&object_id)
+                    .expect("buffer just inserted")
+            } else {
+                self.buffers.get_mut(&object_id).ok_or_else(|| {
+                    PowerShellRemotingError::InvalidMessage(format!(
+                        "Fragment {} of object {object_id} arrived without its start fragment",
+                        fragment.fragment_id
+                    ))
+                })?
+            };
 
             // Add fragment to buffer
             buffer.add_fragment(fragment)?;
 
             // Check if message is complete
             if buffer.is_complete {
-                let complete_data = buffer.reassemble();
+                let complete_data = std::mem::take(&mut buffer.data);
+                self.buffers.remove(&object_id);
                 let message = self.parse_message(complete_data)?;
                 completed_messages.push(message);
-                self.buffers.remove(&object_id);
             }
         }
 
@@ -5,6 +5,10 @@ use base64::Engine;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use tracing::trace;
 
+/// The length of the fragment header, the object ID, the fragment ID, the flags and the blob
+/// length
+pub const HEADER_LEN: usize = 21;
+
 /// Fragment represents a single fragment of a PowerShell remoting message
 #[derive(Debug, Clone)]
 pub struct Fragment {
@@ -72,10 +76,10 @@ impl Fragment {
 
     /// Unpack a fragment from wire format bytes
     pub fn unpack(data: &[u8]) -> Result<(Self, &[u8]), PowerShellRemotingError> {
-        if data.len() < 21 {
-            return Err(PowerShellRemotingError::InvalidMessage(
-                "Fragment too short, need at least 21 bytes".to_string(),
-            ));
+        if data.len() < HEADER_LEN {
+            return Err(PowerShellRemotingError::InvalidMessage(format!(
+                "Fragment too short, need at least {HEADER_LEN} bytes"
+            )));
         }
 
         let mut cursor = Cursor::new(data);
@@ -98,16 +102,16 @@ impl Fragment {
         let length = cursor.read_u32::<BigEndian>()? as usize;
 
         trace!(length, "Unpacking fragment with data length");
-        if data.len() < 21 + length {
+        if data.len() < HEADER_LEN + length {
             return Err(PowerShellRemotingError::InvalidMessage(format!(
                 "Fragment data truncated: expected {} bytes, got {}",
-                21 + length,
+                HEADER_LEN + length,
                 data.len()
             )));
         }
 
-        let fragment_data = data[21..21 + length].to_vec();
-        let remaining = &data[21 + length..];
+        let fragment_data = data[HEADER_LEN..HEADER_LEN + length].to_vec();
+        let remaining = &data[HEADER_LEN + length..];
 
         let fragment = Fragment::new(object_id, fragment_id, fragment_data, start, end);
 
@@ -1,9 +1,13 @@
-use tracing::{debug, trace};
+use tracing::trace;
 use uuid::Uuid;
 
-use super::fragment::Fragment;
+use super::fragment::{Fragment, HEADER_LEN};
 use crate::{PowerShellRemotingError, PowerShellRemotingMessage, PsObjectWithType};
 
+/// The room of the SOAP envelope around the fragments of a `wsman:Send` or a `creationXml`,
+/// its headers, selectors and options
+pub const ENVELOPE_OVERHEAD: usize = 2048;
+
 /// Fragmenter handles fragmentation of outgoing PowerShell remoting messages
 #[derive(Debug)]
 pub struct Fragmenter {
@@ -22,7 +26,7 @@ fn safe_split_at<'a>(data: &'a [u8], size: usize) -> (&'a [u8], &'a [u8]) {
 impl Fragmenter {
     pub fn new(max_fragment_size: usize) -> Self {
         // Subtract header size (21 bytes) from max fragment size
-        let actual_max_size = max_fragment_size.saturating_sub(21);
+        let actual_max_size = max_fragment_size.saturating_sub(HEADER_LEN).max(1);
 
         Self {
             max_fragment_size: actual_max_size,
@@ -30,7 +34,20 @@ impl Fragmenter {
         }
     }
 
-    /// Fragment a single message into multiple fragments
+    /// A fragmenter whose requests fit in envelopes of `max_envelope_size` bytes, the
+    /// `MaxEnvelopeSize` of the shell, once the fragments are base64 encoded
+    pub fn for_envelope_size(max_envelope_size: usize) -> Self {
+        Self::new(max_envelope_size.saturating_sub(ENVELOPE_OVERHEAD) / 4 * 3)
+    }
+
+    /// The size of the fragments, header included, and of the requests of
+    /// [`fragment_multiple`](Self::fragment_multiple)
+    pub fn max_fragment_size(&self) -> usize {
+        self.max_fragment_size + HEADER_LEN
+    }
+
+    /// Fragment a single message into multiple fragments, the first one carries at most
+    /// `remaining_size` bytes of the message
     pub fn fragment(
         &mut self,
         ps_object: &dyn PsObjectWithType,
@@ -39,53 +56,36 @@ impl Fragmenter {
         remaining_size: Option<usize>,
     ) -> Result<Vec<Vec<u8>>, PowerShellRemotingError> {
         let message = PowerShellRemotingMessage::from_ps_message(ps_object, rpid, pid)?;
-        let message_bytes_source = message.pack();
-        let mut remaining_bytes = message_bytes_source.as_slice();
-        let max_size = self.max_fragment_size;
-        let mut start = true;
-        let mut fragment_id = 0;
-        let mut fragments = Vec::new();
+        let message_bytes = message.pack();
+        let mut remaining_bytes = message_bytes.as_slice();
+        let mut blobs = Vec::new();
 
         if let Some(remaining_size) = remaining_size {
-            let (fragment1, remaining) = safe_split_at(remaining_bytes, remaining_size);
-            let end = remaining.is_empty();
-
+            let (first, remaining) = safe_split_at(remaining_bytes, remaining_size);
+            blobs.push(first);
             remaining_bytes = remaining;
-
-            let fragment = Fragment::new(
-                self.outgoing_counter,
-                fragment_id,
-                fragment1.to_vec(),
-                start,
-                end,
-            );
-
-            fragments.push(fragment.pack());
-            fragment_id += 1;
-            start = false;
-        }
-
-        for chunk in remaining_bytes.chunks(max_size) {
-            let end = chunk.len() < max_size;
-            let fragment = Fragment::new(
-                self.outgoing_counter,
-                fragment_id,
-                chunk.to_vec(),
-                start,
-                end,
-            );
-
-            fragments.push(fragment.pack());
-            fragment_id += 1;
-            start = false;
-
-            if end {
-                break;
-            }
         }
+        blobs.extend(remaining_bytes.chunks(self.max_fragment_size));
 
+        let object_id = self.outgoing_counter;
         self.outgoing_counter += 1;
 
+        let last = blobs.len() - 1;
+        let fragments = blobs
+            .into_iter()
+            .enumerate()
+            .map(|(fragment_id, blob)| {
+                Fragment::new(
+                    object_id,
+                    fragment_id as u64,
+                    blob.to_vec(),
+                    fragment_id == 0,
+                    fragment_id == last,
+                )
+                .pack()
+            })
+            .collect();
+
         Ok(fragments)
     }
 
@@ -97,39 +97,30 @@ impl Fragmenter {
         rpid: Uuid,
         pid: Option<Uuid>,
     ) -> Result<Vec<Vec<u8>>, PowerShellRemotingError> {
-        let mut remaing_size = self.max_fragment_size;
-        // Here we perhaps should not call it fragments anymore
-        // Because we are grouping multiple fragments together into one Vec<u8>
-        let mut fragements = Vec::new();
+        let request_size = self.max_fragment_size();
+        let mut requests: Vec<Vec<u8>> = Vec::new();
 
         for message in messages {
-            let mut message_fragments = self.fragment(*message, rpid, pid, Some(remaing_size))?;
+            // The start of the message fills the last request if a fragment still fits
+            let room = requests
+                .last()
+                .map_or(0, |last| request_size - last.len())
+                .saturating_sub(HEADER_LEN);
+            let remaining_size = (room > 0).then_some(room);
+
+            let mut fragments = self.fragment(*message, rpid, pid, remaining_size)?;
             trace!(
                 "Fragmented message {:?} into {} fragments",
                 message.message_type(),
-                message_fragments.len()
+                fragments.len()
             );
 
-            // If we have remaining space, append the next message to the last fragment
-            // This can save some space if the last fragment is not full
-            if remaing_size != self.max_fragment_size && fragements.len() > 0 {
-                debug!(
-                    "Appending to last fragment, remaining size: {}",
-                    remaing_size
-                );
-                fragements.last_mut().map(|last: &mut Vec<u8>| {
-                    last.extend(message_fragments.remove(0));
-                });
-            }
-
-            fragements.extend(message_fragments);
-
-            remaing_size = self.max_fragment_size - fragements.last().map_or(0, |last| last.len());
-            if remaing_size <= 0 {
-                remaing_size = self.max_fragment_size; // Reset for next message
+            if let Some(last) = requests.last_mut().filter(|_| remaining_size.is_some()) {
+                last.extend(fragments.remove(0));
             }
+            requests.extend(fragments);
         }
 
-        Ok(fragements)
+        Ok(requests)
     }
 }
@@ -1,6 +1,9 @@
 use super::*;
-use crate::messages::{ApartmentState, InitRunspacePool, PSThreadOptions, SessionCapability};
-use std::collections::BTreeMap;
+use crate::PowerShellRemotingError;
+use crate::messages::{
+    ApartmentState, InitRunspacePool, PSThreadOptions, PsPrimitiveDictionary, SessionCapability,
+};
+use base64::Engine;
 use tracing::info;
 use tracing_test::traced_test;
 use uuid::Uuid;
@@ -58,7 +61,7 @@ mod tests {
             thread_options: PSThreadOptions::Default,
             apartment_state: ApartmentState::Unknown,
             host_info: HostInfo::builder().build(),
-            application_arguments: BTreeMap::new(),
+            application_arguments: PsPrimitiveDictionary::new(),
         };
 
         let runspace_id = Uuid::new_v4();
@@ -116,7 +119,7 @@ mod tests {
             thread_options: PSThreadOptions::Default,
             apartment_state: ApartmentState::Unknown,
             host_info: HostInfo::builder().build(),
-            application_arguments: BTreeMap::new(),
+            application_arguments: PsPrimitiveDictionary::new(),
         };
 
         let runspace_id = Uuid::new_v4();
@@ -184,7 +187,7 @@ mod tests {
             thread_options: PSThreadOptions::Default,
             apartment_state: ApartmentState::Unknown,
             host_info: HostInfo::builder().build(),
-            application_arguments: BTreeMap::new(),
+            application_arguments: PsPrimitiveDictionary::new(),
         };
 
         // This mimics the exact call in RunspacePool::open()
@@ -230,4 +233,164 @@ mod tests {
             }
         }
     }
+
+    fn init_runspace_pool() -> InitRunspacePool {
+        InitRunspacePool {
+            min_runspaces: 1,
+            max_runspaces: 1,
+            thread_options: PSThreadOptions::Default,
+            apartment_state: ApartmentState::Unknown,
+            host_info: HostInfo::builder().build(),
+            application_arguments: PsPrimitiveDictionary::new(),
+        }
+    }
+
+    fn defragment_all(defragmenter: &mut Defragmenter, fragments: &[Vec<u8>]) -> usize {
+        let mut completed = 0;
+        for fragment in fragments {
+            if let DefragmentResult::Complete(messages) = defragmenter.defragment(fragment).unwrap()
+            {
+                completed += messages.len();
+            }
+        }
+        completed
+    }
+
+    /// A message filling its last fragment exactly still ends there
+    #[test]
+    fn test_message_filling_fragments() {
+        let runspace_id = Uuid::new_v4();
+        let message = init_runspace_pool();
+        let whole = Fragmenter::new(usize::MAX)
+            .fragment(&message, runspace_id, None, None)
+            .unwrap();
+        let message_len = whole[0].len() - HEADER_LEN;
+
+        let mut fragmenter = Fragmenter::new(message_len + HEADER_LEN);
+        let fragments = fragmenter
+            .fragment(&message, runspace_id, None, None)
+            .unwrap();
+        assert_eq!(fragments.len(), 1);
+        let (fragment, rest) = Fragment::unpack(&fragments[0]).unwrap();
+        assert!(fragment.start && fragment.end);
+        assert!(rest.is_empty());
+
+        let mut fragmenter = Fragmenter::new(message_len / 4 + HEADER_LEN);
+        let fragments = fragmenter
+            .fragment(&message, runspace_id, None, None)
+            .unwrap();
+        let flags: Vec<_> = fragments
+            .iter()
+            .map(|bytes| {
+                let (fragment, _) = Fragment::unpack(bytes).unwrap();
+                (fragment.fragment_id, fragment.start, fragment.end)
+            })
+            .collect();
+        assert_eq!(flags.first(), Some(&(0, true, false)));
+        assert_eq!(flags.last().map(|flags| flags.2), Some(true));
+        assert_eq!(flags.iter().filter(|flags| flags.2).count(), 1);
+        assert_eq!(defragment_all(&mut Defragmenter::new(), &fragments), 1);
+    }
+
+    /// The base64 requests fit in the envelopes of the shell
+    #[test]
+    fn test_envelope_size() {
+        let max_envelope_size = 3072;
+        let mut fragmenter = Fragmenter::for_envelope_size(max_envelope_size);
+        let session_capability = SessionCapability {
+            protocol_version: "2.3".to_string(),
+            ps_version: "2.0".to_string(),
+            serialization_version: "1.1.0.1".to_string(),
+            time_zone: None,
+        };
+        let init_runspace_pool = init_runspace_pool();
+        let messages = [
+            &session_capability as &dyn crate::PsObjectWithType,
+            &init_runspace_pool,
+        ];
+        let requests = fragmenter
+            .fragment_multiple(&messages, Uuid::new_v4(), None)
+            .unwrap();
+        assert!(requests.len() > 1);
+
+        for request in &requests {
+            assert!(request.len() <= fragmenter.max_fragment_size());
+            let encoded = base64::engine::general_purpose::STANDARD.encode(request);
+            assert!(encoded.len() + ENVELOPE_OVERHEAD <= max_envelope_size);
+        }
+
+        let wire_data = requests.concat();
+        match Defragmenter::new().defragment(&wire_data).unwrap() {
+            DefragmentResult::Complete(messages) => assert_eq!(messages.len(), 2),
+            DefragmentResult::Incomplete => panic!("All fragments should be complete"),
+        }
+    }
+
+    /// The fragments of two messages interleaved over several receives
+    #[test]
+    fn test_interleaved_fragments_across_receives() {
+        let runspace_id = Uuid::new_v4();
+        let mut fragmenter = Fragmenter::new(300);
+        let first = fragmenter
+            .fragment(&init_runspace_pool(), runspace_id, None, None)
+            .unwrap();
+        let second = fragmenter
+            .fragment(
+                &init_runspace_pool(),
+                runspace_id,
+                Some(Uuid::new_v4()),
+                None,
+            )
+            .unwrap();
+        assert!(first.len() > 2 && second.len() > 2);
+
+        let mut defragmenter = Defragmenter::new();
+        let mut completed = Vec::new();
+        for (first, second) in first.iter().zip(&second) {
+            let receive = [first.as_slice(), second.as_slice()].concat();
+            if let DefragmentResult::Complete(messages) = defragmenter.defragment(&receive).unwrap()
+            {
+                completed.extend(messages);
+            }
+        }
+        completed.extend(
+            first
+                .iter()
+                .chain(&second)
+                .skip(first.len().min(second.len()) * 2)
+                .filter_map(
+                    |fragment| match defragmenter.defragment(fragment).unwrap() {
+                        DefragmentResult::Complete(messages) => Some(messages),
+                        DefragmentResult::Incomplete => None,
+                    },
+                )
+                .flatten(),
+        );
+
+        assert_eq!(completed.len(), 2);
+        assert_eq!(defragmenter.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_fragments_out_of_order() {
+        let mut fragmenter = Fragmenter::new(200);
+        let fragments = fragmenter
+            .fragment(&init_runspace_pool(), Uuid::new_v4(), None, None)
+            .unwrap();
+        assert!(fragments.len() > 2);
+
+        // A fragment is missing
+        let mut defragmenter = Defragmenter::new();
+        defragmenter.defragment(&fragments[0]).unwrap();
+        assert!(matches!(
+            defragmenter.defragment(&fragments[2]),
+            Err(PowerShellRemotingError::InvalidMessage(_))
+        ));
+
+        // A message without its start
+        assert!(matches!(
+            Defragmenter::new().defragment(&fragments[1]),
+            Err(PowerShellRemotingError::InvalidMessage(_))
+        ));
+    }
 }
@@ -1,4 +1,5 @@
 pub mod cores;
+pub mod dissector;
 pub mod fragment;
 pub mod messages;
 
@@ -6,8 +7,13 @@ use core::error;
 use std::str::Utf8Error;
 
 pub use cores::*;
+pub use dissector::*;
 pub use fragment::*;
 pub use messages::*;
+pub use protocol_macros::FromPsObject;
+
+// The code `#[derive(FromPsObject)]` generates names this crate by its path
+extern crate self as protocol_powershell_remoting;
 
 #[cfg(test)]
 mod tests;
@@ -23,6 +29,19 @@ pub enum PowerShellRemotingError {
     #[error("IO Error: {0}")]
     IoError(String),
 
+    #[error("The server advertises {name} {version}, this client supports {expected}")]
+    UnsupportedVersion {
+        name: &'static str,
+        version: String,
+        expected: &'static str,
+    },
+
+    #[error("Cannot convert the PowerShell value: {0}")]
+    ConversionError(String),
+
+    #[error("SecureString encryption failed: {0}")]
+    CryptoError(String),
+
     #[error("Serialization Error: {0}")]
     SerializationError(&'static str),
 
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use super::{ComplexObject, FromPsValue, PsObjectWithType, PsPrimitiveValue, PsProperty, PsValue};
+use crate::{MessageType, PowerShellRemotingError};
+
+/// CONNECT_RUNSPACEPOOL is the message a client sends in the `connectXml` of a `rsp:Connect`
+/// to attach to a disconnected pool, after its `SESSION_CAPABILITY`.
+///
+/// MessageType value: 0x00010008
+/// Direction: Client to Server
+/// Target: RunspacePool
+///
+/// Without runspace counts the server keeps the ones of the pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectRunspacePool {
+    pub min_runspaces: Option<i32>,
+    pub max_runspaces: Option<i32>,
+}
+
+impl PsObjectWithType for ConnectRunspacePool {
+    fn message_type(&self) -> MessageType {
+        MessageType::ConnectRunspacepool
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        let extended_properties = [
+            ("MinRunspaces", self.min_runspaces),
+            ("MaxRunspaces", self.max_runspaces),
+        ]
+        .into_iter()
+        .filter_map(|(name, runspaces)| {
+            runspaces.map(|runspaces| {
+                (
+                    name.to_string(),
+                    PsProperty {
+                        name: name.to_string(),
+                        value: PsValue::Primitive(PsPrimitiveValue::I32(runspaces)),
+                    },
+                )
+            })
+        })
+        .collect::<BTreeMap<_, _>>();
+
+        PsValue::Object(ComplexObject {
+            extended_properties,
+            ..Default::default()
+        })
+    }
+}
+
+/// RUNSPACEPOOL_INIT_DATA is the message the server answers a [`ConnectRunspacePool`] with in
+/// the `connectResponseXml`, with the runspace counts of the pool.
+///
+/// MessageType value: 0x0002100B
+/// Direction: Server to Client
+/// Target: RunspacePool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunspacePoolInitData {
+    pub min_runspaces: i32,
+    pub max_runspaces: i32,
+}
+
+impl TryFrom<ComplexObject> for RunspacePoolInitData {
+    type Error = PowerShellRemotingError;
+
+    fn try_from(value: ComplexObject) -> Result<Self, Self::Error> {
+        let runspaces = |name: &str| {
+            value
+                .property(name)
+                .ok_or_else(|| {
+                    PowerShellRemotingError::InvalidMessage(format!("Missing {name} property"))
+                })
+                .and_then(i32::from_ps_value)
+        };
+
+        Ok(RunspacePoolInitData {
+            min_runspaces: runspaces("MinRunspaces")?,
+            max_runspaces: runspaces("MaxRunspaces")?,
+        })
+    }
+}
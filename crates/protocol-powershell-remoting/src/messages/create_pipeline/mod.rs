@@ -1,6 +1,7 @@
 mod command;
 mod command_parameter;
 mod pipeline_result_types;
+mod powershell;
 mod powershell_pipeline;
 mod remote_stream_options;
 #[cfg(test)]
@@ -9,6 +10,7 @@ mod test;
 pub use command::{Command, Commands};
 pub use command_parameter::CommandParameter;
 pub use pipeline_result_types::PipelineResultTypes;
+pub use powershell::PowerShell;
 pub use powershell_pipeline::PowerShellPipeline;
 pub use remote_stream_options::RemoteStreamOptions;
 
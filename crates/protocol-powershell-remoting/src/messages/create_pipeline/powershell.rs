@@ -0,0 +1,131 @@
+use super::super::ToPsObject;
+use super::{Command, CommandParameter, Commands, PipelineResultTypes, PowerShellPipeline};
+
+/// A pipeline built one command at a time, as with the `PowerShell` class of .NET:
+///
+/// ```
+/// # use protocol_powershell_remoting::PowerShell;
+/// let pipeline = PowerShell::new()
+///     .add_command("Get-Process")
+///     .add_parameter("Name", "w3wp")
+///     .add_command("Select-Object")
+///     .add_argument("Id")
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// The parameters, arguments and merges apply to the last command added.
+#[derive(Debug, Clone, Default)]
+pub struct PowerShell {
+    commands: Vec<Command>,
+    is_nested: bool,
+    /// A parameter or setting came before any command
+    without_command: bool,
+}
+
+impl PowerShell {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the cmdlet, function or executable `command`, its output goes to the next one
+    pub fn add_command(mut self, command: impl Into<String>) -> Self {
+        self.commands.push(Command::builder().cmd(command).build());
+        self
+    }
+
+    /// Add `script` as a script block, run in a scope of its own
+    pub fn add_script(mut self, script: impl Into<String>) -> Self {
+        self.commands
+            .push(Command::builder().cmd(script).is_script(true).build());
+        self
+    }
+
+    /// Add `-name value` to the last command
+    pub fn add_parameter(self, name: impl Into<String>, value: impl ToPsObject) -> Self {
+        let parameter = CommandParameter::builder()
+            .name(name)
+            .value(value.to_ps_value())
+            .build();
+        self.with_last(|command| command.args.push(parameter))
+    }
+
+    /// Add the switch `-name` to the last command
+    pub fn add_switch(self, name: impl Into<String>) -> Self {
+        self.add_parameter(name, true)
+    }
+
+    /// Add the positional argument `value` to the last command
+    pub fn add_argument(self, value: impl ToPsObject) -> Self {
+        let argument = CommandParameter::builder()
+            .value(value.to_ps_value())
+            .build();
+        self.with_last(|command| command.args.push(argument))
+    }
+
+    /// Run the last command in the scope of the caller instead of its own, what dot-sourcing
+    /// does to a script
+    pub fn use_local_scope(self, use_local_scope: bool) -> Self {
+        self.with_last(|command| command.use_local_scope = Some(use_local_scope))
+    }
+
+    /// Merge the error stream of the last command into its output, what `2>&1` does
+    pub fn merge_error_to_output(self) -> Self {
+        self.with_last(|command| {
+            command.merge_my_result = PipelineResultTypes::Error;
+            command.merge_to_result = PipelineResultTypes::Output;
+            command.merge_error = PipelineResultTypes::Output;
+        })
+    }
+
+    /// Merge every stream of the last command into its output, what `*>&1` does
+    pub fn merge_all_to_output(self) -> Self {
+        self.with_last(|command| {
+            command.merge_my_result = PipelineResultTypes::All;
+            command.merge_to_result = PipelineResultTypes::Output;
+            command.merge_error = PipelineResultTypes::Output;
+            command.merge_warning = PipelineResultTypes::Output;
+            command.merge_verbose = PipelineResultTypes::Output;
+            command.merge_debug = PipelineResultTypes::Output;
+            command.merge_information = PipelineResultTypes::Output;
+        })
+    }
+
+    /// Run the pipeline nested in the one running, for a host call of a pipeline
+    pub fn is_nested(mut self, is_nested: bool) -> Self {
+        self.is_nested = is_nested;
+        self
+    }
+
+    fn with_last(mut self, change: impl FnOnce(&mut Command)) -> Self {
+        match self.commands.last_mut() {
+            Some(command) => change(command),
+            None => self.without_command = true,
+        }
+        self
+    }
+
+    /// The pipeline of the commands, it fails without any or with a parameter added before
+    /// the first
+    pub fn build(self) -> Result<PowerShellPipeline, crate::PowerShellRemotingError> {
+        if self.without_command {
+            return Err(crate::PowerShellRemotingError::InvalidMessage(
+                "A parameter or setting was added before any command".to_string(),
+            ));
+        }
+
+        Ok(PowerShellPipeline::builder()
+            .is_nested(self.is_nested)
+            .redirect_shell_error_output_pipe(true)
+            .cmds(Commands::try_from(self.commands)?)
+            .build())
+    }
+}
+
+impl TryFrom<PowerShell> for PowerShellPipeline {
+    type Error = crate::PowerShellRemotingError;
+
+    fn try_from(power_shell: PowerShell) -> Result<Self, Self::Error> {
+        power_shell.build()
+    }
+}
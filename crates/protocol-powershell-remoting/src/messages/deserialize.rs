@@ -11,6 +11,51 @@ use xml::parser::{XmlDeserialize, XmlVisitor};
 
 type Result<T> = std::result::Result<T, xml::XmlError>;
 
+/// The header `powershell -OutputFormat xml` writes before its CLIXML
+const CLIXML_HEADER: &str = "#< CLIXML";
+
+/// Decode the text of a `<S>` element, the reverse of
+/// [`encode_string`](super::serialize::encode_string)
+pub fn decode_string(value: &str) -> String {
+    if !value.contains("_x") {
+        return value.to_string();
+    }
+
+    let mut units = Vec::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("_x") {
+        units.extend(rest[..start].encode_utf16());
+        let escaped = rest[start + 2..]
+            .get(..5)
+            .filter(|escaped| escaped.ends_with('_'))
+            .and_then(|escaped| u16::from_str_radix(&escaped[..4], 16).ok());
+
+        match escaped {
+            Some(unit) => {
+                units.push(unit);
+                rest = &rest[start + 7..];
+            }
+            None => {
+                units.extend("_x".encode_utf16());
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+    units.extend(rest.encode_utf16());
+
+    String::from_utf16_lossy(&units)
+}
+
+fn parse_text<T: std::str::FromStr>(node: xml::parser::Node<'_, '_>, kind: &str) -> Result<T> {
+    let text = node.text().unwrap_or("0");
+    text.parse::<T>()
+        .map_err(|_| xml::XmlError::GenericError(format!("Invalid {kind} value: {text}")))
+}
+
+fn owned_text(node: xml::parser::Node<'_, '_>) -> String {
+    node.text().unwrap_or("").to_string()
+}
+
 /// ================================================================================================
 /// PsPrimitiveValue Visitor and XmlDeserialize Implementation
 /// (Kept for backwards compatibility - primitives don't need context)
@@ -48,7 +93,7 @@ impl<'a> XmlVisitor<'a> for PsPrimitiveValueVisitor<'a> {
 
         match tag_name {
             "S" => {
-                let text = node.text().unwrap_or("").to_string();
+                let text = decode_string(node.text().unwrap_or(""));
                 self.value = Some(PsPrimitiveValue::Str(text));
             }
             "B" => {
@@ -97,6 +142,36 @@ impl<'a> XmlVisitor<'a> for PsPrimitiveValueVisitor<'a> {
                 let text = node.text().unwrap_or("").to_string();
                 self.value = Some(PsPrimitiveValue::Version(text));
             }
+            "C" => {
+                let unit: u16 = parse_text(node, "char")?;
+                let c = char::from_u32(unit.into()).ok_or_else(|| {
+                    xml::XmlError::GenericError(format!("Invalid char value: {unit}"))
+                })?;
+                self.value = Some(PsPrimitiveValue::Char(c));
+            }
+            "UB" => self.value = Some(PsPrimitiveValue::U8(parse_text(node, "u8")?)),
+            "SB" => self.value = Some(PsPrimitiveValue::I8(parse_text(node, "i8")?)),
+            "I16" => self.value = Some(PsPrimitiveValue::I16(parse_text(node, "i16")?)),
+            "U16" => self.value = Some(PsPrimitiveValue::U16(parse_text(node, "u16")?)),
+            "U64" => self.value = Some(PsPrimitiveValue::U64(parse_text(node, "u64")?)),
+            "Db" => self.value = Some(PsPrimitiveValue::Double(owned_text(node))),
+            "Sg" => self.value = Some(PsPrimitiveValue::Single(owned_text(node))),
+            "D" => self.value = Some(PsPrimitiveValue::Decimal(owned_text(node))),
+            "DT" => self.value = Some(PsPrimitiveValue::DateTime(owned_text(node))),
+            "TS" => self.value = Some(PsPrimitiveValue::Duration(owned_text(node))),
+            "URI" => {
+                let text = decode_string(node.text().unwrap_or(""));
+                self.value = Some(PsPrimitiveValue::Uri(text));
+            }
+            "XD" => {
+                let text = decode_string(node.text().unwrap_or(""));
+                self.value = Some(PsPrimitiveValue::XmlDocument(text));
+            }
+            "SBK" => {
+                let text = decode_string(node.text().unwrap_or(""));
+                self.value = Some(PsPrimitiveValue::ScriptBlock(text));
+            }
+            "SS" => self.value = Some(PsPrimitiveValue::SecureString(owned_text(node))),
             _ => {
                 return Err(xml::XmlError::UnexpectedTag(tag_name.to_string()));
             }
@@ -430,12 +505,12 @@ impl<'a> PsXmlVisitor<'a> for ComplexObjectContextVisitor<'a> {
                     }
                 }
                 // Handle primitive content for ExtendedPrimitive objects
-                "S" | "B" | "I32" | "U32" | "I64" | "G" | "Nil" | "BA" | "Version" => {
+                tag if PsPrimitiveValue::is_tag(tag) => {
                     let primitive = PsPrimitiveValue::from_node(child)?;
                     self.content = ComplexObjectContent::ExtendedPrimitive(primitive);
                 }
                 // Handle containers with context
-                "STK" | "QUE" | "LST" | "DCT" => {
+                "STK" | "QUE" | "LST" | "IE" | "DCT" => {
                     let container = Container::from_node_with_context(child, context)?;
                     self.content = ComplexObjectContent::Container(container);
                 }
@@ -464,14 +539,18 @@ impl<'a> PsXmlVisitor<'a> for ComplexObjectContextVisitor<'a> {
             }
         }
 
-        // Post-process to detect enum content
-        if let Some(type_def) = &self.type_def {
-            if type_def.type_names.iter().any(|name| name.contains("Enum")) {
-                if let ComplexObjectContent::ExtendedPrimitive(PsPrimitiveValue::I32(value)) =
-                    &self.content
-                {
-                    self.content = ComplexObjectContent::PsEnums(PsEnums { value: *value });
-                }
+        // Post-process to detect enum content, whatever integer type underlies the enum
+        let is_enum = self
+            .type_def
+            .as_ref()
+            .is_some_and(|ty| ty.type_names.iter().any(|name| name == "System.Enum"));
+        if is_enum {
+            let value = match &self.content {
+                ComplexObjectContent::ExtendedPrimitive(primitive) => primitive.as_i64(),
+                _ => None,
+            };
+            if let Some(value) = value.and_then(|v| i32::try_from(v).ok()) {
+                self.content = ComplexObjectContent::PsEnums(PsEnums { value });
             }
         }
 
@@ -527,8 +606,8 @@ impl<'a> PsXmlVisitor<'a> for PsValueContextVisitor<'a> {
         let tag_name = node.tag_name().name();
 
         match tag_name {
-            // Handle primitive values
-            "S" | "B" | "I32" | "U32" | "I64" | "G" | "Nil" | "BA" | "Version" => {
+            // Handle primitive values, a null Nullable<T> is a <Nil>
+            tag if PsPrimitiveValue::is_tag(tag) => {
                 let primitive = PsPrimitiveValue::from_node(node)?;
                 self.value = Some(PsValue::Primitive(primitive));
             }
@@ -591,6 +670,32 @@ impl<'a> PsXmlDeserialize<'a> for PsValue {
     }
 }
 
+impl PsValue {
+    /// Parse a CLIXML document, the values of its `<Objs>` root or its single value.
+    ///
+    /// The values share one context, a `<Ref>` or `<TNRef>` may point back to an earlier value
+    /// of the document.
+    pub fn from_clixml(clixml: &str) -> Result<Vec<PsValue>> {
+        let clixml = clixml
+            .trim_start_matches('\u{FEFF}')
+            .trim_start()
+            .trim_start_matches(CLIXML_HEADER);
+
+        let parsed = xml::parser::parse(clixml)?;
+        let root = parsed.root_element();
+        let mut context = DeserializationContext::new();
+
+        if root.tag_name().name() != "Objs" {
+            return Ok(vec![PsValue::from_node_with_context(root, &mut context)?]);
+        }
+
+        root.children()
+            .filter(|child| child.is_element())
+            .map(|child| PsValue::from_node_with_context(child, &mut context))
+            .collect()
+    }
+}
+
 /// Context-aware Container visitor
 pub struct ContainerContextVisitor<'a> {
     container: Option<Container>,
@@ -651,6 +756,16 @@ impl<'a> PsXmlVisitor<'a> for ContainerContextVisitor<'a> {
                 }
                 self.container = Some(Container::List(values));
             }
+            "IE" => {
+                let mut values = Vec::new();
+                for child in node.children() {
+                    if child.is_element() {
+                        let value = PsValue::from_node_with_context(child, context)?;
+                        values.push(value);
+                    }
+                }
+                self.container = Some(Container::Enumerable(values));
+            }
             "DCT" => {
                 let mut map = BTreeMap::new();
                 for en_child in node.children() {
@@ -0,0 +1,222 @@
+use super::{ComplexObject, FromPsValue, PsValue};
+use crate::{FromPsObject, PowerShellRemotingError};
+
+/// `System.Management.Automation.ErrorCategory`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorCategory {
+    #[default]
+    NotSpecified = 0,
+    OpenError = 1,
+    CloseError = 2,
+    DeviceError = 3,
+    DeadlockDetected = 4,
+    InvalidArgument = 5,
+    InvalidData = 6,
+    InvalidOperation = 7,
+    InvalidResult = 8,
+    InvalidType = 9,
+    MetadataError = 10,
+    NotImplemented = 11,
+    NotInstalled = 12,
+    ObjectNotFound = 13,
+    OperationStopped = 14,
+    OperationTimeout = 15,
+    SyntaxError = 16,
+    ParserError = 17,
+    PermissionDenied = 18,
+    ResourceBusy = 19,
+    ResourceExists = 20,
+    ResourceUnavailable = 21,
+    ReadError = 22,
+    WriteError = 23,
+    FromStdErr = 24,
+    SecurityError = 25,
+    ProtocolError = 26,
+    ConnectionError = 27,
+    AuthenticationError = 28,
+    LimitsExceeded = 29,
+    QuotaExceeded = 30,
+    NotEnabled = 31,
+}
+
+impl TryFrom<i32> for ErrorCategory {
+    type Error = PowerShellRemotingError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        use ErrorCategory::*;
+
+        const CATEGORIES: [ErrorCategory; 32] = [
+            NotSpecified,
+            OpenError,
+            CloseError,
+            DeviceError,
+            DeadlockDetected,
+            InvalidArgument,
+            InvalidData,
+            InvalidOperation,
+            InvalidResult,
+            InvalidType,
+            MetadataError,
+            NotImplemented,
+            NotInstalled,
+            ObjectNotFound,
+            OperationStopped,
+            OperationTimeout,
+            SyntaxError,
+            ParserError,
+            PermissionDenied,
+            ResourceBusy,
+            ResourceExists,
+            ResourceUnavailable,
+            ReadError,
+            WriteError,
+            FromStdErr,
+            SecurityError,
+            ProtocolError,
+            ConnectionError,
+            AuthenticationError,
+            LimitsExceeded,
+            QuotaExceeded,
+            NotEnabled,
+        ];
+
+        usize::try_from(value)
+            .ok()
+            .and_then(|index| CATEGORIES.get(index).copied())
+            .ok_or_else(|| {
+                PowerShellRemotingError::ConversionError(format!("Invalid ErrorCategory: {value}"))
+            })
+    }
+}
+
+impl FromPsValue for ErrorCategory {
+    fn from_ps_value(value: &PsValue) -> Result<Self, PowerShellRemotingError> {
+        ErrorCategory::try_from(i32::from_ps_value(value)?)
+    }
+}
+
+/// The `ErrorCategory_*` properties of an error record, its `CategoryInfo`
+#[derive(Debug, Clone, PartialEq, Eq, Default, FromPsObject)]
+pub struct ErrorCategoryInfo {
+    #[ps(rename = "ErrorCategory_Category")]
+    pub category: Option<ErrorCategory>,
+    #[ps(rename = "ErrorCategory_Activity")]
+    pub activity: Option<String>,
+    #[ps(rename = "ErrorCategory_Reason")]
+    pub reason: Option<String>,
+    #[ps(rename = "ErrorCategory_TargetName")]
+    pub target_name: Option<String>,
+    #[ps(rename = "ErrorCategory_TargetType")]
+    pub target_type: Option<String>,
+    /// The category line `Write-Error` prints, e.g.
+    /// `ObjectNotFound: (C:\nope:String) [Get-Item], ItemNotFoundException`
+    #[ps(rename = "ErrorCategory_Message")]
+    pub message: Option<String>,
+}
+
+/// The `InvocationInfo_*` properties of an error record, where in the script the error
+/// happened. The server only writes them when `SerializeExtendedInfo` is set.
+#[derive(Debug, Clone, PartialEq, Eq, Default, FromPsObject)]
+pub struct InvocationInfo {
+    #[ps(rename = "CommandInfo_Name")]
+    pub command_name: Option<String>,
+    #[ps(rename = "InvocationInfo_InvocationName")]
+    pub invocation_name: Option<String>,
+    #[ps(rename = "InvocationInfo_ScriptName")]
+    pub script_name: Option<String>,
+    #[ps(rename = "InvocationInfo_ScriptLineNumber")]
+    pub script_line_number: Option<i32>,
+    #[ps(rename = "InvocationInfo_OffsetInLine")]
+    pub offset_in_line: Option<i32>,
+    #[ps(rename = "InvocationInfo_Line")]
+    pub line: Option<String>,
+    #[ps(rename = "InvocationInfo_PositionMessage")]
+    pub position_message: Option<String>,
+}
+
+/// An `ErrorRecord`, of an `ERROR_RECORD` message or of the `ExceptionAsErrorRecord` of a
+/// pipeline or pool that failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PsErrorRecord {
+    /// The message of the `ErrorDetails` if any, else the one of the exception
+    pub message: String,
+    /// The most specific type of the exception, e.g. `System.Management.Automation.ItemNotFoundException`
+    pub exception_type: Option<String>,
+    pub fully_qualified_error_id: Option<String>,
+    pub category: ErrorCategoryInfo,
+    pub target_object: Option<PsValue>,
+    pub script_stack_trace: Option<String>,
+    pub recommended_action: Option<String>,
+    pub invocation_info: Option<InvocationInfo>,
+}
+
+impl std::fmt::Display for PsErrorRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(id) = &self.fully_qualified_error_id {
+            write!(f, " ({id})")?;
+        }
+        Ok(())
+    }
+}
+
+fn text_property(object: &ComplexObject, name: &str) -> Option<String> {
+    object
+        .property(name)
+        .and_then(|value| Option::<String>::from_ps_value(value).ok().flatten())
+}
+
+impl FromPsObject for PsErrorRecord {
+    fn from_ps_object(record: &ComplexObject) -> Result<Self, PowerShellRemotingError> {
+        let exception = record.property("Exception").and_then(PsValue::as_object);
+
+        let message = text_property(record, "ErrorDetails_Message")
+            .or_else(|| exception.and_then(|exception| text_property(exception, "Message")))
+            .or_else(|| exception.and_then(|exception| exception.to_string.clone()))
+            .or_else(|| record.to_string.clone())
+            .ok_or_else(|| {
+                PowerShellRemotingError::ConversionError(
+                    "The error record has no message".to_string(),
+                )
+            })?;
+
+        let extended_info = record
+            .property("SerializeExtendedInfo")
+            .and_then(|value| bool::from_ps_value(value).ok())
+            .unwrap_or(false);
+        let invocation_info = if extended_info {
+            Some(InvocationInfo::from_ps_object(record)?)
+        } else {
+            None
+        };
+
+        Ok(PsErrorRecord {
+            message,
+            exception_type: exception
+                .and_then(|exception| exception.type_names().next())
+                .map(|name| name.trim_start_matches("Deserialized.").to_string()),
+            fully_qualified_error_id: text_property(record, "FullyQualifiedErrorId"),
+            category: ErrorCategoryInfo::from_ps_object(record)?,
+            target_object: record
+                .property("TargetObject")
+                .filter(|value| !value.is_nil())
+                .cloned(),
+            script_stack_trace: text_property(record, "ErrorDetails_ScriptStackTrace"),
+            recommended_action: text_property(record, "ErrorDetails_RecommendedAction"),
+            invocation_info,
+        })
+    }
+}
+
+impl FromPsValue for PsErrorRecord {
+    fn from_ps_value(value: &PsValue) -> Result<Self, PowerShellRemotingError> {
+        value
+            .as_object()
+            .ok_or_else(|| {
+                PowerShellRemotingError::ConversionError(format!(
+                    "Expected an ErrorRecord object, got {value:?}"
+                ))
+            })
+            .and_then(PsErrorRecord::from_ps_object)
+    }
+}
@@ -0,0 +1,293 @@
+use super::{ComplexObject, ComplexObjectContent, Container, PsPrimitiveValue, PsValue};
+use crate::PowerShellRemotingError;
+
+type Result<T> = std::result::Result<T, PowerShellRemotingError>;
+
+/// A Rust value a [`PsValue`] can be converted into, with the lenient coercions PowerShell
+/// itself applies: numbers from any integer type or from their text, strings from any
+/// primitive or from the `ToString` of an object, a single value as a one element `Vec`.
+pub trait FromPsValue: Sized {
+    fn from_ps_value(value: &PsValue) -> Result<Self>;
+
+    /// The value of a property the object does not have, `None` makes it an error
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+/// A Rust struct built from the properties of a PSObject, usually through
+/// `#[derive(FromPsObject)]`.
+///
+/// The derive looks each field up by name, ignoring the case and the underscores of the field
+/// name so that `display_name` reads `DisplayName`. `#[ps(rename = "...")]` names the property
+/// of a field explicitly, and `Option` fields may be missing.
+///
+/// ```
+/// use protocol_powershell_remoting::{FromPsObject, PsValue};
+///
+/// #[derive(FromPsObject)]
+/// struct ServiceInfo {
+///     name: String,
+///     status: String,
+///     display_name: Option<String>,
+/// }
+///
+/// let clixml = r#"<Obj RefId="0"><MS><S N="Name">WinRM</S><S N="Status">Running</S></MS></Obj>"#;
+/// let values = PsValue::from_clixml(clixml).unwrap();
+/// let service = ServiceInfo::from_ps_object(values[0].as_object().unwrap()).unwrap();
+/// assert_eq!(service.name, "WinRM");
+/// ```
+pub trait FromPsObject: Sized {
+    fn from_ps_object(object: &ComplexObject) -> Result<Self>;
+}
+
+fn conversion_error(expected: &str, value: &PsValue) -> PowerShellRemotingError {
+    PowerShellRemotingError::ConversionError(format!("Expected {expected}, got {value:?}"))
+}
+
+/// The field `name` of `type_name`, the code `#[derive(FromPsObject)]` generates calls it for
+/// every field
+#[doc(hidden)]
+pub fn convert_property<T: FromPsValue>(
+    object: &ComplexObject,
+    name: &str,
+    type_name: &str,
+) -> Result<T> {
+    let Some(value) = object.property_ignore_case(name) else {
+        return T::from_missing().ok_or_else(|| {
+            PowerShellRemotingError::ConversionError(format!(
+                "{type_name} needs the property {name}"
+            ))
+        });
+    };
+
+    T::from_ps_value(value).map_err(|error| match error {
+        PowerShellRemotingError::ConversionError(reason) => {
+            PowerShellRemotingError::ConversionError(format!("{type_name}.{name}: {reason}"))
+        }
+        error => error,
+    })
+}
+
+impl ComplexObject {
+    /// The value of the property whose name matches `name` regardless of case and of the
+    /// underscores in `name`, see [`property`](Self::property) for the exact lookup
+    pub fn property_ignore_case(&self, name: &str) -> Option<&PsValue> {
+        self.property(name).or_else(|| {
+            self.extended_properties
+                .values()
+                .chain(self.adapted_properties.values())
+                .find(|prop| property_name_matches(&prop.name, name))
+                .map(|prop| &prop.value)
+        })
+    }
+
+    /// Convert the object with [`FromPsObject`]
+    pub fn to_typed<T: FromPsObject>(&self) -> Result<T> {
+        T::from_ps_object(self)
+    }
+}
+
+fn property_name_matches(property: &str, name: &str) -> bool {
+    let mut property = property.chars();
+    let mut name = name.chars().filter(|c| *c != '_');
+    loop {
+        match (property.next(), name.next()) {
+            (Some(p), Some(n)) if p.eq_ignore_ascii_case(&n) => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+impl PsValue {
+    /// Convert the value with [`FromPsValue`]
+    pub fn to_typed<T: FromPsValue>(&self) -> Result<T> {
+        T::from_ps_value(self)
+    }
+
+    /// The text of a primitive value, or the `ToString` of an object
+    fn as_text(&self) -> Option<String> {
+        if let Some(primitive) = self.as_primitive() {
+            return primitive_text(primitive);
+        }
+
+        match self {
+            PsValue::Object(object) => object.to_string.clone(),
+            PsValue::Primitive(_) => None,
+        }
+    }
+
+    fn as_integer(&self) -> Option<i128> {
+        if let PsValue::Object(ComplexObject {
+            content: ComplexObjectContent::PsEnums(e),
+            ..
+        }) = self
+        {
+            return Some(e.value.into());
+        }
+
+        match self.as_primitive()? {
+            PsPrimitiveValue::U64(u) => Some((*u).into()),
+            PsPrimitiveValue::Str(s) => s.trim().parse().ok(),
+            primitive => primitive.as_i64().map(Into::into),
+        }
+    }
+}
+
+fn primitive_text(primitive: &PsPrimitiveValue) -> Option<String> {
+    Some(match primitive {
+        PsPrimitiveValue::Str(s)
+        | PsPrimitiveValue::Guid(s)
+        | PsPrimitiveValue::Version(s)
+        | PsPrimitiveValue::Double(s)
+        | PsPrimitiveValue::Single(s)
+        | PsPrimitiveValue::Decimal(s)
+        | PsPrimitiveValue::DateTime(s)
+        | PsPrimitiveValue::Duration(s)
+        | PsPrimitiveValue::Uri(s)
+        | PsPrimitiveValue::XmlDocument(s)
+        | PsPrimitiveValue::ScriptBlock(s) => s.clone(),
+        PsPrimitiveValue::Bool(b) => b.to_string(),
+        PsPrimitiveValue::Char(c) => c.to_string(),
+        PsPrimitiveValue::Nil | PsPrimitiveValue::Bytes(_) | PsPrimitiveValue::SecureString(_) => {
+            return None;
+        }
+        PsPrimitiveValue::U64(u) => u.to_string(),
+        integer => integer.as_i64()?.to_string(),
+    })
+}
+
+impl FromPsValue for PsValue {
+    fn from_ps_value(value: &PsValue) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl FromPsValue for ComplexObject {
+    fn from_ps_value(value: &PsValue) -> Result<Self> {
+        value
+            .as_object()
+            .cloned()
+            .ok_or_else(|| conversion_error("an object", value))
+    }
+}
+
+impl FromPsValue for String {
+    fn from_ps_value(value: &PsValue) -> Result<Self> {
+        value
+            .as_text()
+            .ok_or_else(|| conversion_error("a string", value))
+    }
+}
+
+impl FromPsValue for bool {
+    fn from_ps_value(value: &PsValue) -> Result<Self> {
+        match value.as_primitive() {
+            Some(PsPrimitiveValue::Bool(b)) => Ok(*b),
+            Some(PsPrimitiveValue::Str(s)) if s.eq_ignore_ascii_case("true") => Ok(true),
+            Some(PsPrimitiveValue::Str(s)) if s.eq_ignore_ascii_case("false") => Ok(false),
+            _ => value
+                .as_integer()
+                .map(|i| i != 0)
+                .ok_or_else(|| conversion_error("a boolean", value)),
+        }
+    }
+}
+
+impl FromPsValue for char {
+    fn from_ps_value(value: &PsValue) -> Result<Self> {
+        match value.as_primitive() {
+            Some(PsPrimitiveValue::Char(c)) => Ok(*c),
+            Some(PsPrimitiveValue::Str(s)) if s.chars().count() == 1 => {
+                Ok(s.chars().next().expect("one char"))
+            }
+            _ => Err(conversion_error("a char", value)),
+        }
+    }
+}
+
+macro_rules! integer_from_ps_value {
+    ($($ty:ty),*) => {
+        $(
+            impl FromPsValue for $ty {
+                fn from_ps_value(value: &PsValue) -> Result<Self> {
+                    value
+                        .as_integer()
+                        .and_then(|i| <$ty>::try_from(i).ok())
+                        .ok_or_else(|| conversion_error(stringify!($ty), value))
+                }
+            }
+        )*
+    };
+}
+
+integer_from_ps_value!(i8, u8, i16, u16, i32, u32, i64, u64);
+
+macro_rules! float_from_ps_value {
+    ($($ty:ty),*) => {
+        $(
+            impl FromPsValue for $ty {
+                fn from_ps_value(value: &PsValue) -> Result<Self> {
+                    if let Some(i) = value.as_integer() {
+                        return Ok(i as $ty);
+                    }
+
+                    value
+                        .as_text()
+                        .and_then(|text| text.trim().parse::<$ty>().ok())
+                        .ok_or_else(|| conversion_error(stringify!($ty), value))
+                }
+            }
+        )*
+    };
+}
+
+float_from_ps_value!(f32, f64);
+
+impl FromPsValue for uuid::Uuid {
+    fn from_ps_value(value: &PsValue) -> Result<Self> {
+        value
+            .as_text()
+            .and_then(|text| uuid::Uuid::parse_str(&text).ok())
+            .ok_or_else(|| conversion_error("a GUID", value))
+    }
+}
+
+impl<T: FromPsValue> FromPsValue for Option<T> {
+    fn from_ps_value(value: &PsValue) -> Result<Self> {
+        if value.is_nil() {
+            Ok(None)
+        } else {
+            T::from_ps_value(value).map(Some)
+        }
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: FromPsValue> FromPsValue for Vec<T> {
+    fn from_ps_value(value: &PsValue) -> Result<Self> {
+        match value {
+            PsValue::Primitive(PsPrimitiveValue::Nil) => Ok(Vec::new()),
+            PsValue::Object(ComplexObject {
+                content:
+                    ComplexObjectContent::Container(
+                        Container::List(values)
+                        | Container::Stack(values)
+                        | Container::Queue(values)
+                        | Container::Enumerable(values),
+                    ),
+                ..
+            }) => values.iter().map(T::from_ps_value).collect(),
+            // PowerShell unrolls a collection of one element
+            value => Ok(vec![T::from_ps_value(value)?]),
+        }
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(Vec::new())
+    }
+}
@@ -0,0 +1,486 @@
+use std::borrow::Cow;
+
+use super::{
+    BufferCell, BufferCellArray, Coordinates, FromPsValue, ProgressRecord, PsType, PsValue,
+    ReadKeyOptions, Rectangle, RunspacePoolHostCall, RunspacePoolHostResponse, ScrollBuffer, Size,
+    ToPsObject, host_ui::host_object,
+};
+use crate::{FromPsObject, PowerShellRemotingError};
+
+macro_rules! host_method_ids {
+    ($($name:ident = $value:literal),* $(,)?) => {
+        /// `System.Management.Automation.Remoting.RemoteHostMethodId`, the `mi` of a host call
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum HostMethodId {
+            $($name = $value),*
+        }
+
+        impl HostMethodId {
+            /// The name of the method, the `ToString` of its `mi`
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(HostMethodId::$name => stringify!($name)),*
+                }
+            }
+        }
+
+        impl TryFrom<i32> for HostMethodId {
+            type Error = PowerShellRemotingError;
+
+            fn try_from(value: i32) -> Result<Self, Self::Error> {
+                match value {
+                    $($value => Ok(HostMethodId::$name),)*
+                    _ => Err(PowerShellRemotingError::InvalidMessage(format!(
+                        "Unknown host method identifier: {value}"
+                    ))),
+                }
+            }
+        }
+    };
+}
+
+host_method_ids!(
+    GetName = 1,
+    GetVersion = 2,
+    GetInstanceId = 3,
+    GetCurrentCulture = 4,
+    GetCurrentUICulture = 5,
+    SetShouldExit = 6,
+    EnterNestedPrompt = 7,
+    ExitNestedPrompt = 8,
+    NotifyBeginApplication = 9,
+    NotifyEndApplication = 10,
+    ReadLine = 11,
+    ReadLineAsSecureString = 12,
+    Write1 = 13,
+    Write2 = 14,
+    WriteLine1 = 15,
+    WriteLine2 = 16,
+    WriteLine3 = 17,
+    WriteErrorLine = 18,
+    WriteDebugLine = 19,
+    WriteProgress = 20,
+    WriteVerboseLine = 21,
+    WriteWarningLine = 22,
+    Prompt = 23,
+    PromptForCredential1 = 24,
+    PromptForCredential2 = 25,
+    PromptForChoice = 26,
+    GetForegroundColor = 27,
+    SetForegroundColor = 28,
+    GetBackgroundColor = 29,
+    SetBackgroundColor = 30,
+    GetCursorPosition = 31,
+    SetCursorPosition = 32,
+    GetWindowPosition = 33,
+    SetWindowPosition = 34,
+    GetCursorSize = 35,
+    SetCursorSize = 36,
+    GetBufferSize = 37,
+    SetBufferSize = 38,
+    GetWindowSize = 39,
+    SetWindowSize = 40,
+    GetWindowTitle = 41,
+    SetWindowTitle = 42,
+    GetMaxWindowSize = 43,
+    GetMaxPhysicalWindowSize = 44,
+    GetKeyAvailable = 45,
+    ReadKey = 46,
+    FlushInputBuffer = 47,
+    SetBufferContents1 = 48,
+    SetBufferContents2 = 49,
+    GetBufferContents = 50,
+    ScrollBufferContents = 51,
+    PushRunspace = 52,
+    PopRunspace = 53,
+    GetIsRunspacePushed = 54,
+    GetRunspace = 55,
+    PromptForChoiceMultipleSelection = 56,
+);
+
+impl HostMethodId {
+    /// Whether the server waits for a host response. Void methods, e.g. the writes, get none.
+    pub fn returns_value(&self) -> bool {
+        let name = self.name();
+        name.starts_with("Get") || name.starts_with("Read") || name.starts_with("Prompt")
+    }
+}
+
+/// `System.ConsoleColor`, the colors of `Write-Host -ForegroundColor -BackgroundColor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConsoleColor {
+    Black = 0,
+    DarkBlue = 1,
+    DarkGreen = 2,
+    DarkCyan = 3,
+    DarkRed = 4,
+    DarkMagenta = 5,
+    DarkYellow = 6,
+    Gray = 7,
+    DarkGray = 8,
+    Blue = 9,
+    Green = 10,
+    Cyan = 11,
+    Red = 12,
+    Magenta = 13,
+    Yellow = 14,
+    White = 15,
+}
+
+impl TryFrom<i32> for ConsoleColor {
+    type Error = PowerShellRemotingError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        use ConsoleColor::*;
+
+        const COLORS: [ConsoleColor; 16] = [
+            Black,
+            DarkBlue,
+            DarkGreen,
+            DarkCyan,
+            DarkRed,
+            DarkMagenta,
+            DarkYellow,
+            Gray,
+            DarkGray,
+            Blue,
+            Green,
+            Cyan,
+            Red,
+            Magenta,
+            Yellow,
+            White,
+        ];
+
+        usize::try_from(value)
+            .ok()
+            .and_then(|index| COLORS.get(index).copied())
+            .ok_or_else(|| {
+                PowerShellRemotingError::ConversionError(format!("Invalid ConsoleColor: {value}"))
+            })
+    }
+}
+
+impl FromPsValue for ConsoleColor {
+    fn from_ps_value(value: &PsValue) -> Result<Self, PowerShellRemotingError> {
+        ConsoleColor::try_from(i32::from_ps_value(value)?)
+    }
+}
+
+impl ToPsObject for ConsoleColor {
+    fn to_ps_value(&self) -> PsValue {
+        (*self as i32).to_ps_value()
+    }
+}
+
+/// A field of a `Read-Host` or of the prompt of the mandatory parameters of a command
+#[derive(Debug, Clone, PartialEq, Eq, FromPsObject)]
+pub struct FieldDescription {
+    pub name: String,
+    pub label: Option<String>,
+    /// The type the answer is converted into, e.g. `System.Security.SecureString`
+    pub parameter_type_full_name: Option<String>,
+    pub help_message: Option<String>,
+    #[ps(rename = "isMandatory")]
+    pub mandatory: Option<bool>,
+    pub default_value: Option<PsValue>,
+}
+
+impl ToPsObject for FieldDescription {
+    fn to_ps_value(&self) -> PsValue {
+        host_object([
+            ("name", self.name.to_ps_value()),
+            ("label", self.label.to_ps_value()),
+            (
+                "parameterTypeFullName",
+                self.parameter_type_full_name.to_ps_value(),
+            ),
+            ("helpMessage", self.help_message.to_ps_value()),
+            ("isMandatory", self.mandatory.to_ps_value()),
+            ("defaultValue", self.default_value.to_ps_value()),
+        ])
+    }
+}
+
+/// A choice of a `PromptForChoice`, its label marks the hot key with `&`, e.g. `&Yes`
+#[derive(Debug, Clone, PartialEq, Eq, FromPsObject)]
+pub struct ChoiceDescription {
+    pub label: String,
+    pub help_message: Option<String>,
+}
+
+impl ToPsObject for ChoiceDescription {
+    fn to_ps_value(&self) -> PsValue {
+        host_object([
+            ("label", self.label.to_ps_value()),
+            ("helpMessage", self.help_message.to_ps_value()),
+        ])
+    }
+}
+
+/// A host call decoded from its method identifier and parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostMethodCall {
+    ReadLine,
+    ReadLineAsSecureString,
+    /// `Write1`, `Write2` and the `WriteLine*`, the text of `Write-Host`
+    Write {
+        foreground: Option<ConsoleColor>,
+        background: Option<ConsoleColor>,
+        text: String,
+        new_line: bool,
+    },
+    WriteErrorLine(String),
+    WriteDebugLine(String),
+    WriteVerboseLine(String),
+    WriteWarningLine(String),
+    WriteProgress {
+        source_id: i64,
+        record: ProgressRecord,
+    },
+    Prompt {
+        caption: String,
+        message: String,
+        descriptions: Vec<FieldDescription>,
+    },
+    /// `PromptForCredential1` and `PromptForCredential2`
+    PromptForCredential {
+        caption: String,
+        message: String,
+        user_name: Option<String>,
+        target_name: Option<String>,
+    },
+    PromptForChoice {
+        caption: String,
+        message: String,
+        choices: Vec<ChoiceDescription>,
+        default_choice: i32,
+    },
+    /// A `PromptForChoice` of which several choices can be made, answered with their indexes
+    PromptForChoiceMultipleSelection {
+        caption: String,
+        message: String,
+        choices: Vec<ChoiceDescription>,
+        default_choices: Vec<i32>,
+    },
+    SetShouldExit(i32),
+    /// The setters of `$Host.UI.RawUI`
+    SetForegroundColor(ConsoleColor),
+    SetBackgroundColor(ConsoleColor),
+    SetCursorPosition(Coordinates),
+    SetWindowPosition(Coordinates),
+    SetCursorSize(i32),
+    SetBufferSize(Size),
+    SetWindowSize(Size),
+    SetWindowTitle(String),
+    ReadKey(ReadKeyOptions),
+    /// `SetBufferContents1`, every cell of `rectangle` becomes `fill`
+    FillBufferContents {
+        rectangle: Rectangle,
+        fill: BufferCell,
+    },
+    /// `SetBufferContents2`, the cells from `origin` become `contents`
+    SetBufferContents {
+        origin: Coordinates,
+        contents: BufferCellArray,
+    },
+    GetBufferContents(Rectangle),
+    ScrollBufferContents(ScrollBuffer),
+    /// A method of the host not decoded, its parameters stay in the call. The getters, e.g. of
+    /// `$Host.UI.RawUI`, have none.
+    Other(HostMethodId),
+}
+
+fn parameter<T: FromPsValue>(
+    parameters: &[PsValue],
+    index: usize,
+) -> Result<T, PowerShellRemotingError> {
+    let value = parameters.get(index).ok_or_else(|| {
+        PowerShellRemotingError::InvalidMessage(format!("Missing host call parameter {index}"))
+    })?;
+    T::from_ps_value(value)
+}
+
+/// A text parameter, `$null` is empty
+fn text(parameters: &[PsValue], index: usize) -> Result<String, PowerShellRemotingError> {
+    Ok(parameter::<Option<String>>(parameters, index)?.unwrap_or_default())
+}
+
+impl HostMethodCall {
+    pub fn decode(
+        method: HostMethodId,
+        parameters: &[PsValue],
+    ) -> Result<Self, PowerShellRemotingError> {
+        let write = |foreground, background, text, new_line| HostMethodCall::Write {
+            foreground,
+            background,
+            text,
+            new_line,
+        };
+
+        Ok(match method {
+            HostMethodId::ReadLine => HostMethodCall::ReadLine,
+            HostMethodId::ReadLineAsSecureString => HostMethodCall::ReadLineAsSecureString,
+            HostMethodId::Write1 => write(None, None, text(parameters, 0)?, false),
+            HostMethodId::Write2 => write(
+                Some(parameter(parameters, 0)?),
+                Some(parameter(parameters, 1)?),
+                text(parameters, 2)?,
+                false,
+            ),
+            HostMethodId::WriteLine1 => write(None, None, String::new(), true),
+            HostMethodId::WriteLine2 => write(None, None, text(parameters, 0)?, true),
+            HostMethodId::WriteLine3 => write(
+                Some(parameter(parameters, 0)?),
+                Some(parameter(parameters, 1)?),
+                text(parameters, 2)?,
+                true,
+            ),
+            HostMethodId::WriteErrorLine => HostMethodCall::WriteErrorLine(text(parameters, 0)?),
+            HostMethodId::WriteDebugLine => HostMethodCall::WriteDebugLine(text(parameters, 0)?),
+            HostMethodId::WriteVerboseLine => {
+                HostMethodCall::WriteVerboseLine(text(parameters, 0)?)
+            }
+            HostMethodId::WriteWarningLine => {
+                HostMethodCall::WriteWarningLine(text(parameters, 0)?)
+            }
+            HostMethodId::WriteProgress => HostMethodCall::WriteProgress {
+                source_id: parameter(parameters, 0)?,
+                record: parameter(parameters, 1)?,
+            },
+            HostMethodId::Prompt => HostMethodCall::Prompt {
+                caption: text(parameters, 0)?,
+                message: text(parameters, 1)?,
+                descriptions: parameter(parameters, 2)?,
+            },
+            HostMethodId::PromptForCredential1 | HostMethodId::PromptForCredential2 => {
+                HostMethodCall::PromptForCredential {
+                    caption: text(parameters, 0)?,
+                    message: text(parameters, 1)?,
+                    user_name: parameter(parameters, 2)?,
+                    target_name: parameter(parameters, 3)?,
+                }
+            }
+            HostMethodId::PromptForChoice => HostMethodCall::PromptForChoice {
+                caption: text(parameters, 0)?,
+                message: text(parameters, 1)?,
+                choices: parameter(parameters, 2)?,
+                default_choice: parameter(parameters, 3)?,
+            },
+            HostMethodId::PromptForChoiceMultipleSelection => {
+                HostMethodCall::PromptForChoiceMultipleSelection {
+                    caption: text(parameters, 0)?,
+                    message: text(parameters, 1)?,
+                    choices: parameter(parameters, 2)?,
+                    default_choices: parameter::<Option<_>>(parameters, 3)?.unwrap_or_default(),
+                }
+            }
+            HostMethodId::SetShouldExit => HostMethodCall::SetShouldExit(parameter(parameters, 0)?),
+            HostMethodId::SetForegroundColor => {
+                HostMethodCall::SetForegroundColor(parameter(parameters, 0)?)
+            }
+            HostMethodId::SetBackgroundColor => {
+                HostMethodCall::SetBackgroundColor(parameter(parameters, 0)?)
+            }
+            HostMethodId::SetCursorPosition => {
+                HostMethodCall::SetCursorPosition(parameter(parameters, 0)?)
+            }
+            HostMethodId::SetWindowPosition => {
+                HostMethodCall::SetWindowPosition(parameter(parameters, 0)?)
+            }
+            HostMethodId::SetCursorSize => HostMethodCall::SetCursorSize(parameter(parameters, 0)?),
+            HostMethodId::SetBufferSize => HostMethodCall::SetBufferSize(parameter(parameters, 0)?),
+            HostMethodId::SetWindowSize => HostMethodCall::SetWindowSize(parameter(parameters, 0)?),
+            HostMethodId::SetWindowTitle => HostMethodCall::SetWindowTitle(text(parameters, 0)?),
+            HostMethodId::ReadKey => HostMethodCall::ReadKey(parameter(parameters, 0)?),
+            HostMethodId::SetBufferContents1 => HostMethodCall::FillBufferContents {
+                rectangle: parameter(parameters, 0)?,
+                fill: parameter(parameters, 1)?,
+            },
+            HostMethodId::SetBufferContents2 => HostMethodCall::SetBufferContents {
+                origin: parameter(parameters, 0)?,
+                contents: parameter(parameters, 1)?,
+            },
+            HostMethodId::GetBufferContents => {
+                HostMethodCall::GetBufferContents(parameter(parameters, 0)?)
+            }
+            HostMethodId::ScrollBufferContents => {
+                HostMethodCall::ScrollBufferContents(ScrollBuffer {
+                    source: parameter(parameters, 0)?,
+                    destination: parameter(parameters, 1)?,
+                    clip: parameter(parameters, 2)?,
+                    fill: parameter(parameters, 3)?,
+                })
+            }
+            other => HostMethodCall::Other(other),
+        })
+    }
+}
+
+impl RunspacePoolHostCall {
+    pub fn method(&self) -> Result<HostMethodId, PowerShellRemotingError> {
+        HostMethodId::try_from(self.method_id)
+    }
+
+    /// The method and the parameters of the call, see [`HostMethodCall::decode`]
+    pub fn decode(&self) -> Result<HostMethodCall, PowerShellRemotingError> {
+        HostMethodCall::decode(self.method()?, &self.parameters)
+    }
+
+    /// The response of the call: what the host returned, or the exception it threw, see
+    /// [`host_exception`]
+    pub fn response(&self, result: Result<PsValue, PsValue>) -> RunspacePoolHostResponse {
+        let (method_result, method_exception) = match result {
+            Ok(value) => (Some(value), None),
+            Err(exception) => (None, Some(exception)),
+        };
+
+        RunspacePoolHostResponse {
+            call_id: self.call_id,
+            method_id: self.method_id,
+            method_name: self.method_name.clone(),
+            method_result,
+            method_exception,
+        }
+    }
+}
+
+/// The `ErrorRecord` of a `HostException`, what a host that cannot answer a call throws
+pub fn host_exception(message: &str) -> PsValue {
+    let PsValue::Object(mut exception) =
+        PsValue::custom_object([("Message", message.to_ps_value())])
+    else {
+        unreachable!("A custom object is an object");
+    };
+    exception.type_def = Some(PsType {
+        type_names: vec![
+            Cow::Borrowed("System.Management.Automation.Host.HostException"),
+            Cow::Borrowed("System.Exception"),
+            Cow::Borrowed("System.Object"),
+        ],
+    });
+    exception.to_string = Some(message.to_string());
+
+    let PsValue::Object(mut record) = PsValue::custom_object([
+        ("Exception", PsValue::Object(exception)),
+        ("TargetObject", None::<i32>.to_ps_value()),
+        (
+            "FullyQualifiedErrorId",
+            "HostFunctionNotImplemented".to_ps_value(),
+        ),
+        // NotImplemented
+        ("ErrorCategory_Category", 11.to_ps_value()),
+        ("SerializeExtendedInfo", false.to_ps_value()),
+    ]) else {
+        unreachable!("A custom object is an object");
+    };
+    record.type_def = Some(PsType {
+        type_names: vec![
+            Cow::Borrowed("System.Management.Automation.ErrorRecord"),
+            Cow::Borrowed("System.Object"),
+        ],
+    });
+    record.to_string = Some(message.to_string());
+
+    PsValue::Object(record)
+}
@@ -0,0 +1,253 @@
+use super::{
+    ComplexObject, ConsoleColor, Coordinates, FromPsValue, PsProperty, PsValue, ToPsObject,
+};
+use crate::{FromPsObject, PowerShellRemotingError};
+
+/// An object of the host, as the remote host encoder of PowerShell writes it: the fields of
+/// the .NET struct are the properties of an object without type names
+pub(crate) fn host_object<const N: usize>(properties: [(&str, PsValue); N]) -> PsValue {
+    let extended_properties = properties
+        .into_iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                PsProperty {
+                    name: name.to_string(),
+                    value,
+                },
+            )
+        })
+        .collect();
+
+    PsValue::Object(ComplexObject {
+        extended_properties,
+        ..Default::default()
+    })
+}
+
+macro_rules! flags {
+    ($(#[$meta:meta])* $name:ident { $($(#[$flag_meta:meta])* $flag:ident = $value:literal),* $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+        pub struct $name(pub i32);
+
+        impl $name {
+            $($(#[$flag_meta])* pub const $flag: Self = Self($value);)*
+
+            pub fn contains(&self, flags: Self) -> bool {
+                self.0 & flags.0 == flags.0
+            }
+        }
+
+        impl std::ops::BitOr for $name {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                Self(self.0 | rhs.0)
+            }
+        }
+
+        impl FromPsValue for $name {
+            fn from_ps_value(value: &PsValue) -> Result<Self, PowerShellRemotingError> {
+                i32::from_ps_value(value).map(Self)
+            }
+        }
+
+        impl ToPsObject for $name {
+            fn to_ps_value(&self) -> PsValue {
+                self.0.to_ps_value()
+            }
+        }
+    };
+}
+
+flags!(
+    /// `System.Management.Automation.Host.ReadKeyOptions`, how a `ReadKey` reads the key
+    ReadKeyOptions {
+        /// Ctrl+C is read as a key instead of stopping the pipeline
+        ALLOW_CTRL_C = 1,
+        NO_ECHO = 2,
+        INCLUDE_KEY_DOWN = 4,
+        INCLUDE_KEY_UP = 8,
+    }
+);
+
+flags!(
+    /// `System.Management.Automation.Host.ControlKeyStates`, the modifiers of a key
+    ControlKeyStates {
+        RIGHT_ALT_PRESSED = 0x1,
+        LEFT_ALT_PRESSED = 0x2,
+        RIGHT_CTRL_PRESSED = 0x4,
+        LEFT_CTRL_PRESSED = 0x8,
+        SHIFT_PRESSED = 0x10,
+        NUM_LOCK_ON = 0x20,
+        SCROLL_LOCK_ON = 0x40,
+        CAPS_LOCK_ON = 0x80,
+        ENHANCED_KEY = 0x100,
+    }
+);
+
+/// `System.Management.Automation.Host.KeyInfo`, the key a `ReadKey` returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPsObject)]
+pub struct KeyInfo {
+    pub virtual_key_code: i32,
+    pub character: char,
+    pub control_key_state: ControlKeyStates,
+    pub key_down: bool,
+}
+
+impl ToPsObject for KeyInfo {
+    fn to_ps_value(&self) -> PsValue {
+        host_object([
+            ("virtualKeyCode", self.virtual_key_code.to_ps_value()),
+            ("character", self.character.to_ps_value()),
+            ("controlKeyState", self.control_key_state.to_ps_value()),
+            ("keyDown", self.key_down.to_ps_value()),
+        ])
+    }
+}
+
+/// `System.Management.Automation.Host.Rectangle`, a region of the screen buffer, its right
+/// and bottom included
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, FromPsObject)]
+pub struct Rectangle {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl ToPsObject for Rectangle {
+    fn to_ps_value(&self) -> PsValue {
+        host_object([
+            ("left", self.left.to_ps_value()),
+            ("top", self.top.to_ps_value()),
+            ("right", self.right.to_ps_value()),
+            ("bottom", self.bottom.to_ps_value()),
+        ])
+    }
+}
+
+/// `System.Management.Automation.Host.BufferCellType`, which half of a wide character a cell
+/// holds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BufferCellType {
+    #[default]
+    Complete = 0,
+    Leading = 1,
+    Trailing = 2,
+}
+
+impl TryFrom<i32> for BufferCellType {
+    type Error = PowerShellRemotingError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(BufferCellType::Complete),
+            1 => Ok(BufferCellType::Leading),
+            2 => Ok(BufferCellType::Trailing),
+            _ => Err(PowerShellRemotingError::ConversionError(format!(
+                "Invalid BufferCellType: {value}"
+            ))),
+        }
+    }
+}
+
+impl FromPsValue for BufferCellType {
+    fn from_ps_value(value: &PsValue) -> Result<Self, PowerShellRemotingError> {
+        BufferCellType::try_from(i32::from_ps_value(value)?)
+    }
+}
+
+impl ToPsObject for BufferCellType {
+    fn to_ps_value(&self) -> PsValue {
+        (*self as i32).to_ps_value()
+    }
+}
+
+/// `System.Management.Automation.Host.BufferCell`, a character of the screen buffer and its
+/// colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPsObject)]
+pub struct BufferCell {
+    pub character: char,
+    pub foreground_color: ConsoleColor,
+    pub background_color: ConsoleColor,
+    pub buffer_cell_type: BufferCellType,
+}
+
+impl ToPsObject for BufferCell {
+    fn to_ps_value(&self) -> PsValue {
+        host_object([
+            ("character", self.character.to_ps_value()),
+            ("foregroundColor", self.foreground_color.to_ps_value()),
+            ("backgroundColor", self.background_color.to_ps_value()),
+            ("bufferCellType", self.buffer_cell_type.to_ps_value()),
+        ])
+    }
+}
+
+/// A `BufferCell[,]`, the cells of a region of the screen buffer row by row. It is written
+/// as its elements `mae` in row order and its lengths `mal`, the rows then the columns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferCellArray {
+    pub rows: Vec<Vec<BufferCell>>,
+}
+
+impl ToPsObject for BufferCellArray {
+    fn to_ps_value(&self) -> PsValue {
+        let columns = self.rows.first().map_or(0, Vec::len);
+        let lengths = [self.rows.len() as i32, columns as i32];
+        let elements: Vec<_> = self.rows.iter().flatten().copied().collect();
+
+        host_object([
+            ("mae", elements.to_ps_value()),
+            ("mal", lengths.to_ps_value()),
+        ])
+    }
+}
+
+impl FromPsValue for BufferCellArray {
+    fn from_ps_value(value: &PsValue) -> Result<Self, PowerShellRemotingError> {
+        let invalid = |what: &str| {
+            PowerShellRemotingError::ConversionError(format!("Invalid BufferCell[,]: {what}"))
+        };
+        let object = value.as_object().ok_or_else(|| invalid("not an object"))?;
+
+        let lengths = object
+            .property("mal")
+            .ok_or_else(|| invalid("no lengths"))
+            .and_then(Vec::<i32>::from_ps_value)?;
+        let [rows, columns] = lengths[..] else {
+            return Err(invalid("not two dimensions"));
+        };
+        let (Ok(rows), Ok(columns)) = (usize::try_from(rows), usize::try_from(columns)) else {
+            return Err(invalid("a negative length"));
+        };
+
+        let elements = object
+            .property("mae")
+            .ok_or_else(|| invalid("no elements"))
+            .and_then(Vec::<BufferCell>::from_ps_value)?;
+        if elements.len() != rows * columns {
+            return Err(invalid("elements not as many as its lengths"));
+        }
+
+        Ok(BufferCellArray {
+            rows: elements
+                .chunks(columns.max(1))
+                .take(rows)
+                .map(<[BufferCell]>::to_vec)
+                .collect(),
+        })
+    }
+}
+
+/// The arguments of a `ScrollBufferContents`: `source` moves to `destination`, what it
+/// leaves is filled with `fill`, nothing outside of `clip` changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrollBuffer {
+    pub source: Rectangle,
+    pub destination: Coordinates,
+    pub clip: Rectangle,
+    pub fill: BufferCell,
+}
@@ -1,7 +1,11 @@
-use super::super::{ComplexObject, ComplexObjectContent, PsPrimitiveValue, PsProperty, PsValue};
+use super::super::{
+    ComplexObject, ComplexObjectContent, PsPrimitiveValue, PsProperty, PsValue, ToPsObject,
+};
+use crate::FromPsObject;
 use std::{borrow::Cow, collections::BTreeMap};
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+/// `System.Management.Automation.Host.Coordinates`, a position of the screen buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, FromPsObject)]
 pub struct Coordinates {
     pub x: i32,
     pub y: i32,
@@ -34,7 +38,14 @@ impl From<Coordinates> for ComplexObject {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+impl ToPsObject for Coordinates {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Object(ComplexObject::from(*self))
+    }
+}
+
+/// `System.Management.Automation.Host.Size`, of the window or of the screen buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, FromPsObject)]
 pub struct Size {
     pub width: i32,
     pub height: i32,
@@ -67,6 +78,12 @@ impl From<Size> for ComplexObject {
     }
 }
 
+impl ToPsObject for Size {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Object(ComplexObject::from(*self))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct HostDefaultData {
     pub foreground_color: i32,          // Key 0: System.ConsoleColor
@@ -11,8 +11,8 @@ pub use host_info::HostInfo;
 pub use ps_thread_options::PSThreadOptions;
 
 use super::{
-    ComplexObject, ComplexObjectContent, Container, PsObjectWithType, PsPrimitiveValue, PsProperty,
-    PsType, PsValue,
+    ComplexObject, ComplexObjectContent, PsObjectWithType, PsPrimitiveDictionary,
+    PsPrimitiveValue, PsProperty, PsValue, ToPsObject,
 };
 use crate::MessageType;
 use std::collections::BTreeMap;
@@ -24,7 +24,8 @@ pub struct InitRunspacePool {
     pub thread_options: PSThreadOptions,
     pub apartment_state: ApartmentState,
     pub host_info: HostInfo,
-    pub application_arguments: BTreeMap<PsValue, PsValue>,
+    /// What the session configuration reads from `$PSSenderInfo.ApplicationArguments`
+    pub application_arguments: PsPrimitiveDictionary,
 }
 
 impl From<InitRunspacePool> for ComplexObject {
@@ -80,19 +81,11 @@ impl From<InitRunspacePool> for ComplexObject {
                 },
             );
         } else {
-            let app_args_obj = ComplexObject {
-                type_def: Some(PsType::ps_primitive_dictionary()),
-                content: ComplexObjectContent::Container(Container::Dictionary(
-                    init.application_arguments,
-                )),
-                ..Default::default()
-            };
-
             extended_properties.insert(
                 "ApplicationArguments".to_string(),
                 PsProperty {
                     name: "ApplicationArguments".to_string(),
-                    value: PsValue::Object(app_args_obj),
+                    value: init.application_arguments.to_ps_value(),
                 },
             );
         }
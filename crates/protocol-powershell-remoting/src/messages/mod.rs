@@ -1,19 +1,48 @@
+pub mod connect_runspace_pool;
 pub mod create_pipeline;
 pub mod deserialize;
+pub mod error_record;
+pub mod from_ps_object;
+pub mod host_method;
+pub mod host_ui;
 pub mod init_runspace_pool;
+pub mod pipeline_host_call;
 pub mod pipeline_input;
+pub mod pipeline_state;
+pub mod primitive_dictionary;
+pub mod ps_credential;
+pub mod runspace_availability;
 pub mod runspace_pool_host_call;
 pub mod runspace_pool_host_response;
 pub mod runspace_pool_state;
 pub mod serialize;
 pub mod session_capability;
+pub mod session_key;
+pub mod stream_records;
+pub mod to_ps_object;
+pub mod user_event;
 
+pub use connect_runspace_pool::*;
 pub use create_pipeline::*;
+pub use error_record::*;
+pub use from_ps_object::*;
+pub use host_method::*;
+pub use host_ui::*;
 pub use init_runspace_pool::*;
+pub use pipeline_host_call::*;
+pub use pipeline_input::*;
+pub use pipeline_state::*;
+pub use primitive_dictionary::*;
+pub use ps_credential::*;
+pub use runspace_availability::*;
 pub use runspace_pool_host_call::*;
 pub use runspace_pool_host_response::*;
 pub use runspace_pool_state::*;
 pub use session_capability::*;
+pub use session_key::*;
+pub use stream_records::*;
+pub use to_ps_object::*;
+pub use user_event::*;
 
 use std::{
     borrow::Cow,
@@ -40,7 +69,70 @@ pub enum PsPrimitiveValue {
     Nil,
     Bytes(Vec<u8>),
     Version(String),
-    // Add more primitive types as needed
+    /// `<C>`, a UTF-16 code unit written as its number
+    Char(char),
+    /// `<UB>`
+    U8(u8),
+    /// `<SB>`
+    I8(i8),
+    /// `<I16>`
+    I16(i16),
+    /// `<U16>`
+    U16(u16),
+    /// `<U64>`
+    U64(u64),
+    /// `<Db>`, kept as written so the values stay `Eq` and `Ord`
+    Double(String),
+    /// `<Sg>`
+    Single(String),
+    /// `<D>`
+    Decimal(String),
+    /// `<DT>`, an ISO 8601 date and time
+    DateTime(String),
+    /// `<TS>`, an ISO 8601 duration
+    Duration(String),
+    /// `<URI>`
+    Uri(String),
+    /// `<XD>`
+    XmlDocument(String),
+    /// `<SBK>`
+    ScriptBlock(String),
+    /// `<SS>`, the base64 of the string encrypted with the session key
+    SecureString(String),
+}
+
+impl PsPrimitiveValue {
+    /// The element names of the primitive types
+    pub const TAGS: [&'static str; 24] = [
+        "S", "B", "I32", "U32", "I64", "G", "Nil", "BA", "Version", "C", "UB", "SB", "I16",
+        "U16", "U64", "Db", "Sg", "D", "DT", "TS", "URI", "XD", "SBK", "SS",
+    ];
+
+    pub fn is_tag(tag_name: &str) -> bool {
+        Self::TAGS.contains(&tag_name)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PsPrimitiveValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The value of any integer type that fits in an `i64`
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            PsPrimitiveValue::I32(i) => Some(i.into()),
+            PsPrimitiveValue::U32(u) => Some(u.into()),
+            PsPrimitiveValue::I64(i) => Some(i),
+            PsPrimitiveValue::U8(u) => Some(u.into()),
+            PsPrimitiveValue::I8(i) => Some(i.into()),
+            PsPrimitiveValue::I16(i) => Some(i.into()),
+            PsPrimitiveValue::U16(u) => Some(u.into()),
+            PsPrimitiveValue::U64(u) => i64::try_from(u).ok(),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -57,6 +149,26 @@ impl PsValue {
             None
         }
     }
+
+    /// The primitive value, or the one an Extended Primitive Object wraps
+    pub fn as_primitive(&self) -> Option<&PsPrimitiveValue> {
+        match self {
+            PsValue::Primitive(primitive) => Some(primitive),
+            PsValue::Object(ComplexObject {
+                content: ComplexObjectContent::ExtendedPrimitive(primitive),
+                ..
+            }) => Some(primitive),
+            PsValue::Object(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_primitive().and_then(PsPrimitiveValue::as_str)
+    }
+
+    pub fn is_nil(&self) -> bool {
+        matches!(self, PsValue::Primitive(PsPrimitiveValue::Nil))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -86,6 +198,15 @@ impl PsType {
         }
     }
 
+    pub fn ps_credential() -> Self {
+        PsType {
+            type_names: vec![
+                Cow::Borrowed("System.Management.Automation.PSCredential"),
+                Cow::Borrowed("System.Object"),
+            ],
+        }
+    }
+
     pub fn remote_host_method_id() -> Self {
         PsType {
             type_names: vec![
@@ -151,6 +272,29 @@ pub struct ComplexObject {
     pub extended_properties: BTreeMap<String, PsProperty>,
 }
 
+impl ComplexObject {
+    /// The value of the property `name`, the extended properties (NoteProperties) are looked
+    /// up before the adapted ones
+    pub fn property(&self, name: &str) -> Option<&PsValue> {
+        self.extended_properties
+            .get(name)
+            .or_else(|| self.adapted_properties.get(name))
+            .map(|prop| &prop.value)
+    }
+
+    /// The type names of the object, from the most specific one
+    pub fn type_names(&self) -> impl Iterator<Item = &str> {
+        self.type_def
+            .iter()
+            .flat_map(|ty| ty.type_names.iter().map(AsRef::as_ref))
+    }
+
+    /// Whether the object is of the type `type_name`, or derives from it
+    pub fn is_type(&self, type_name: &str) -> bool {
+        self.type_names().any(|name| name == type_name)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ComplexObjectContent {
     /// If the Complex Object being serialized is an Extended Primitive Object, then the value of the primitive type is serialized as described in section 2.2.5.1.
@@ -182,6 +326,8 @@ pub enum Container {
     Queue(Vec<PsValue>),
     ///https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-psrp/f4bdb166-cefc-4d49-848c-7d08680ae0a7
     List(Vec<PsValue>),
+    /// Any other enumerable, `<IE>`
+    Enumerable(Vec<PsValue>),
     /// https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-psrp/c4e000a2-21d8-46c0-a71b-0051365d8273
     Dictionary(BTreeMap<PsValue, PsValue>),
 }
@@ -0,0 +1,62 @@
+use super::{
+    ComplexObject, PsObjectWithType, PsValue, RunspacePoolHostCall, RunspacePoolHostResponse,
+};
+use crate::MessageType;
+
+/// PipelineHostCall is a message sent from the server to the client to perform a method call
+/// on the host associated with a pipeline, e.g. the `ReadLine` of a `Read-Host`.
+///
+/// MessageType value: 0x00041100
+/// Direction: Server to Client
+/// Target: PowerShell
+///
+/// Its data is the one of a [`RunspacePoolHostCall`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineHostCall(pub RunspacePoolHostCall);
+
+impl PsObjectWithType for PipelineHostCall {
+    fn message_type(&self) -> MessageType {
+        MessageType::PipelineHostCall
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        self.0.to_ps_object()
+    }
+}
+
+impl TryFrom<ComplexObject> for PipelineHostCall {
+    type Error = crate::PowerShellRemotingError;
+
+    fn try_from(value: ComplexObject) -> Result<Self, Self::Error> {
+        RunspacePoolHostCall::try_from(value).map(PipelineHostCall)
+    }
+}
+
+/// PipelineHostResponse is a message sent from the client to the server as a response from a
+/// host call executed on the client host of a pipeline.
+///
+/// MessageType value: 0x00041101
+/// Direction: Client to Server
+/// Target: PowerShell
+///
+/// Its data is the one of a [`RunspacePoolHostResponse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineHostResponse(pub RunspacePoolHostResponse);
+
+impl PsObjectWithType for PipelineHostResponse {
+    fn message_type(&self) -> MessageType {
+        MessageType::PipelineHostResponse
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        self.0.to_ps_object()
+    }
+}
+
+impl TryFrom<ComplexObject> for PipelineHostResponse {
+    type Error = crate::PowerShellRemotingError;
+
+    fn try_from(value: ComplexObject) -> Result<Self, Self::Error> {
+        RunspacePoolHostResponse::try_from(value).map(PipelineHostResponse)
+    }
+}
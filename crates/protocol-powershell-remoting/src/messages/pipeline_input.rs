@@ -1,5 +1,8 @@
-use super::PsValue;
+use super::{PsObjectWithType, PsPrimitiveValue, PsValue};
+use crate::MessageType;
 
+/// A `PIPELINE_INPUT`, one object of the input of a pipeline created without `NoInput`
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PipelineInput {
     pub data: PsValue,
 }
@@ -10,15 +13,26 @@ impl PipelineInput {
     }
 }
 
-// impl From<PipelineInput> for PsObject {
-//     fn from(input: PipelineInput) -> Self {
-//         PsObject {
-//             ms: vec![PsProperty {
-//                 name: Some("Data".to_string()),
-//                 ref_id: None,
-//                 value: input.data,
-//             }],
-//             ..Default::default()
-//         }
-//     }
-// }
+impl PsObjectWithType for PipelineInput {
+    fn message_type(&self) -> MessageType {
+        MessageType::PipelineInput
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        self.data.clone()
+    }
+}
+
+/// The `END_OF_PIPELINE_INPUT` closing the input of a pipeline, the message carries no data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EndOfPipelineInput;
+
+impl PsObjectWithType for EndOfPipelineInput {
+    fn message_type(&self) -> MessageType {
+        MessageType::EndOfPipelineInput
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        PsValue::Primitive(PsPrimitiveValue::Nil)
+    }
+}
@@ -0,0 +1,172 @@
+use super::{
+    ComplexObject, ComplexObjectContent, PsObjectWithType, PsPrimitiveValue, PsProperty, PsValue,
+};
+use crate::MessageType;
+use std::collections::BTreeMap;
+
+/// https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-psrp/acaa253a-29be-45fd-911c-6715515a28b9
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineStateValue {
+    NotStarted = 0,
+    Running = 1,
+    Stopping = 2,
+    Stopped = 3,
+    Completed = 4,
+    Failed = 5,
+    Disconnected = 6,
+}
+
+impl PipelineStateValue {
+    pub fn as_i32(&self) -> i32 {
+        *self as i32
+    }
+
+    /// Whether the pipeline is over, it sends no more output
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            PipelineStateValue::Stopped
+                | PipelineStateValue::Completed
+                | PipelineStateValue::Failed
+        )
+    }
+}
+
+impl TryFrom<i32> for PipelineStateValue {
+    type Error = crate::PowerShellRemotingError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PipelineStateValue::NotStarted),
+            1 => Ok(PipelineStateValue::Running),
+            2 => Ok(PipelineStateValue::Stopping),
+            3 => Ok(PipelineStateValue::Stopped),
+            4 => Ok(PipelineStateValue::Completed),
+            5 => Ok(PipelineStateValue::Failed),
+            6 => Ok(PipelineStateValue::Disconnected),
+            _ => Err(crate::PowerShellRemotingError::InvalidMessage(format!(
+                "Invalid PipelineState value: {value}"
+            ))),
+        }
+    }
+}
+
+/// The `PIPELINE_STATE` the server sends as a pipeline runs, the error record explains why it
+/// failed or was stopped
+#[derive(Debug, Clone, PartialEq, Eq, typed_builder::TypedBuilder)]
+pub struct PipelineStateMessage {
+    pub pipeline_state: PipelineStateValue,
+    #[builder(default)]
+    pub exception_as_error_record: Option<PsValue>,
+}
+
+impl PsObjectWithType for PipelineStateMessage {
+    fn message_type(&self) -> MessageType {
+        MessageType::PipelineState
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        PsValue::Object(ComplexObject::from(self.clone()))
+    }
+}
+
+impl From<PipelineStateMessage> for ComplexObject {
+    fn from(state: PipelineStateMessage) -> Self {
+        let mut extended_properties = BTreeMap::new();
+
+        extended_properties.insert(
+            "PipelineState".to_string(),
+            PsProperty {
+                name: "PipelineState".to_string(),
+                value: PsValue::Primitive(PsPrimitiveValue::I32(state.pipeline_state.as_i32())),
+            },
+        );
+
+        if let Some(exception) = state.exception_as_error_record {
+            extended_properties.insert(
+                "ExceptionAsErrorRecord".to_string(),
+                PsProperty {
+                    name: "ExceptionAsErrorRecord".to_string(),
+                    value: exception,
+                },
+            );
+        }
+
+        ComplexObject {
+            type_def: None,
+            to_string: None,
+            content: ComplexObjectContent::Standard,
+            adapted_properties: BTreeMap::new(),
+            extended_properties,
+        }
+    }
+}
+
+impl TryFrom<ComplexObject> for PipelineStateMessage {
+    type Error = crate::PowerShellRemotingError;
+
+    fn try_from(value: ComplexObject) -> Result<Self, Self::Error> {
+        let pipeline_state = match value
+            .extended_properties
+            .get("PipelineState")
+            .map(|prop| &prop.value)
+        {
+            Some(PsValue::Primitive(PsPrimitiveValue::I32(state))) => {
+                PipelineStateValue::try_from(*state)?
+            }
+            Some(_) => {
+                return Err(Self::Error::InvalidMessage(
+                    "PipelineState property is not an I32".to_string(),
+                ));
+            }
+            None => {
+                return Err(Self::Error::InvalidMessage(
+                    "Missing PipelineState property".to_string(),
+                ));
+            }
+        };
+
+        let exception_as_error_record = value
+            .extended_properties
+            .get("ExceptionAsErrorRecord")
+            .map(|prop| prop.value.clone());
+
+        Ok(PipelineStateMessage {
+            pipeline_state,
+            exception_as_error_record,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_state_round_trip() {
+        let state = PipelineStateMessage::builder()
+            .pipeline_state(PipelineStateValue::Completed)
+            .build();
+
+        let complex_obj = ComplexObject::from(state.clone());
+        let roundtrip = PipelineStateMessage::try_from(complex_obj).unwrap();
+
+        assert_eq!(state, roundtrip);
+        assert!(roundtrip.pipeline_state.is_finished());
+        assert_eq!(state.message_type().value(), 0x00041006);
+    }
+
+    #[test]
+    fn test_pipeline_state_failed_with_exception() {
+        let exception = PsValue::Primitive(PsPrimitiveValue::Str("Test error".to_string()));
+        let state = PipelineStateMessage::builder()
+            .pipeline_state(PipelineStateValue::Failed)
+            .exception_as_error_record(Some(exception))
+            .build();
+
+        let complex_obj = ComplexObject::from(state.clone());
+        assert_eq!(PipelineStateMessage::try_from(complex_obj).unwrap(), state);
+        assert!(PipelineStateValue::try_from(7).is_err());
+        assert!(!PipelineStateValue::Running.is_finished());
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use super::{
+    ComplexObject, ComplexObjectContent, Container, FromPsValue, PsPrimitiveValue, PsType, PsValue,
+    ToPsObject,
+};
+use crate::PowerShellRemotingError;
+
+/// A `System.Management.Automation.PSPrimitiveDictionary`, a hashtable of string keys whose
+/// values are primitives, arrays of primitives or other primitive dictionaries.
+///
+/// It is the `ApplicationArguments` of an `INIT_RUNSPACEPOOL`, which a session configuration
+/// reads from `$PSSenderInfo.ApplicationArguments`, and the `ApplicationPrivateData` of the
+/// server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PsPrimitiveDictionary {
+    entries: BTreeMap<String, PsValue>,
+}
+
+impl PsPrimitiveDictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `key` to `value`, the previous value of `key` is returned. The key is replaced
+    /// whatever its case, as PowerShell compares the keys without it.
+    pub fn insert(
+        &mut self,
+        key: impl Into<String>,
+        value: impl ToPsObject,
+    ) -> Result<Option<PsValue>, PowerShellRemotingError> {
+        let key = key.into();
+        let value = value.to_ps_value();
+        if !is_primitive_value(&value) {
+            return Err(PowerShellRemotingError::ConversionError(format!(
+                "The value of {key} is not allowed in a PSPrimitiveDictionary: {value:?}"
+            )));
+        }
+
+        let previous = self
+            .key_of(&key)
+            .map(str::to_owned)
+            .and_then(|existing| self.entries.remove(&existing));
+        self.entries.insert(key, value);
+        Ok(previous)
+    }
+
+    /// The value of `key`, whatever its case
+    pub fn get(&self, key: &str) -> Option<&PsValue> {
+        self.key_of(key).and_then(|key| self.entries.get(key))
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<PsValue> {
+        let key = self.key_of(key)?.to_owned();
+        self.entries.remove(&key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PsValue)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn key_of(&self, key: &str) -> Option<&str> {
+        self.entries
+            .keys()
+            .find(|existing| existing.eq_ignore_ascii_case(key))
+            .map(String::as_str)
+    }
+}
+
+/// Whether `value` can be in a `PSPrimitiveDictionary`
+fn is_primitive_value(value: &PsValue) -> bool {
+    match value {
+        PsValue::Primitive(PsPrimitiveValue::ScriptBlock(_) | PsPrimitiveValue::XmlDocument(_)) => {
+            false
+        }
+        PsValue::Primitive(_) => true,
+        PsValue::Object(ComplexObject {
+            content: ComplexObjectContent::Container(Container::List(values)),
+            ..
+        }) => values.iter().all(is_primitive_value),
+        PsValue::Object(ComplexObject {
+            content: ComplexObjectContent::Container(Container::Dictionary(entries)),
+            ..
+        }) => entries
+            .iter()
+            .all(|(key, value)| key.as_str().is_some() && is_primitive_value(value)),
+        PsValue::Object(_) => false,
+    }
+}
+
+impl ToPsObject for PsPrimitiveDictionary {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Object(ComplexObject {
+            type_def: Some(PsType::ps_primitive_dictionary()),
+            content: ComplexObjectContent::Container(Container::Dictionary(
+                self.entries
+                    .iter()
+                    .map(|(key, value)| (key.to_ps_value(), value.clone()))
+                    .collect(),
+            )),
+            ..Default::default()
+        })
+    }
+}
+
+impl FromPsValue for PsPrimitiveDictionary {
+    fn from_ps_value(value: &PsValue) -> Result<Self, PowerShellRemotingError> {
+        let entries = match value {
+            PsValue::Primitive(PsPrimitiveValue::Nil) => return Ok(Self::new()),
+            PsValue::Object(ComplexObject {
+                content: ComplexObjectContent::Container(Container::Dictionary(entries)),
+                ..
+            }) => entries,
+            value => {
+                return Err(PowerShellRemotingError::ConversionError(format!(
+                    "Expected a PSPrimitiveDictionary, got {value:?}"
+                )));
+            }
+        };
+
+        let entries = entries
+            .iter()
+            .map(|(key, value)| {
+                let key = key.as_str().ok_or_else(|| {
+                    PowerShellRemotingError::ConversionError(format!(
+                        "A key of a PSPrimitiveDictionary is not a string: {key:?}"
+                    ))
+                })?;
+                Ok((key.to_owned(), value.clone()))
+            })
+            .collect::<Result<_, PowerShellRemotingError>>()?;
+
+        Ok(PsPrimitiveDictionary { entries })
+    }
+}
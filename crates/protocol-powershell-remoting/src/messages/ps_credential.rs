@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+
+use super::{ComplexObject, PsProperty, PsType, PsValue, SessionKey, ToPsObject};
+use crate::PowerShellRemotingError;
+
+/// A `System.Management.Automation.PSCredential`, what a `-Credential` parameter takes and
+/// a `PromptForCredential` returns.
+///
+/// Its password is sent as a `SecureString`, so its CLIXML needs the session key of the pool.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PsCredential {
+    user_name: String,
+    password: String,
+}
+
+impl std::fmt::Debug for PsCredential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PsCredential")
+            .field("user_name", &self.user_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PsCredential {
+    /// The credential of `user_name`, e.g. `DOMAIN\user` or `user@domain`
+    pub fn new(user_name: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            user_name: user_name.into(),
+            password: password.into(),
+        }
+    }
+
+    pub fn user_name(&self) -> &str {
+        &self.user_name
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    /// The object of the credential: its `UserName` and its `Password` encrypted with
+    /// `session_key`
+    pub fn to_ps_value(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<PsValue, PowerShellRemotingError> {
+        let property = |name: &str, value| {
+            (
+                name.to_string(),
+                PsProperty {
+                    name: name.to_string(),
+                    value,
+                },
+            )
+        };
+        let adapted_properties = BTreeMap::from([
+            property("UserName", self.user_name.to_ps_value()),
+            property("Password", session_key.encrypt(&self.password)?),
+        ]);
+
+        Ok(PsValue::Object(ComplexObject {
+            type_def: Some(PsType::ps_credential()),
+            to_string: Some("System.Management.Automation.PSCredential".to_string()),
+            adapted_properties,
+            ..Default::default()
+        }))
+    }
+
+    /// The credential of an object the server sent, its `Password` decrypted with
+    /// `session_key`
+    pub fn from_ps_value(
+        value: &PsValue,
+        session_key: &SessionKey,
+    ) -> Result<Self, PowerShellRemotingError> {
+        let object = value.as_object().ok_or_else(|| {
+            PowerShellRemotingError::ConversionError(format!(
+                "Expected a PSCredential, got {value:?}"
+            ))
+        })?;
+        let property = |name: &str| {
+            object.property(name).ok_or_else(|| {
+                PowerShellRemotingError::ConversionError(format!("The PSCredential has no {name}"))
+            })
+        };
+
+        let user_name = property("UserName")?
+            .as_str()
+            .ok_or_else(|| {
+                PowerShellRemotingError::ConversionError(
+                    "The UserName of the PSCredential is not a string".to_string(),
+                )
+            })?
+            .to_string();
+        let password = session_key.decrypt(property("Password")?)?;
+
+        Ok(Self {
+            user_name,
+            password,
+        })
+    }
+}
@@ -0,0 +1,146 @@
+use super::{ComplexObject, FromPsValue, PsObjectWithType, PsPrimitiveValue, PsProperty, PsValue};
+use crate::{MessageType, PowerShellRemotingError};
+
+/// The object of a message whose data is only `properties`
+fn message_object<const N: usize>(properties: [(&str, PsPrimitiveValue); N]) -> PsValue {
+    let extended_properties = properties
+        .into_iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                PsProperty {
+                    name: name.to_string(),
+                    value: PsValue::Primitive(value),
+                },
+            )
+        })
+        .collect();
+
+    PsValue::Object(ComplexObject {
+        extended_properties,
+        ..Default::default()
+    })
+}
+
+/// SET_MAX_RUNSPACES asks the server to change the maximum number of runspaces of the pool,
+/// it answers with a [`RunspaceAvailability`] of the same call ID.
+///
+/// MessageType value: 0x00021002
+/// Direction: Client to Server
+/// Target: RunspacePool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetMaxRunspaces {
+    pub max_runspaces: i32,
+    pub call_id: i64,
+}
+
+impl PsObjectWithType for SetMaxRunspaces {
+    fn message_type(&self) -> MessageType {
+        MessageType::SetMaxRunspaces
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        message_object([
+            ("MaxRunspaces", PsPrimitiveValue::I32(self.max_runspaces)),
+            ("ci", PsPrimitiveValue::I64(self.call_id)),
+        ])
+    }
+}
+
+/// SET_MIN_RUNSPACES asks the server to change the minimum number of runspaces of the pool,
+/// it answers with a [`RunspaceAvailability`] of the same call ID.
+///
+/// MessageType value: 0x00021003
+/// Direction: Client to Server
+/// Target: RunspacePool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetMinRunspaces {
+    pub min_runspaces: i32,
+    pub call_id: i64,
+}
+
+impl PsObjectWithType for SetMinRunspaces {
+    fn message_type(&self) -> MessageType {
+        MessageType::SetMinRunspaces
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        message_object([
+            ("MinRunspaces", PsPrimitiveValue::I32(self.min_runspaces)),
+            ("ci", PsPrimitiveValue::I64(self.call_id)),
+        ])
+    }
+}
+
+/// GET_AVAILABLE_RUNSPACES asks the server how many runspaces of the pool are free, it answers
+/// with a [`RunspaceAvailability`] of the same call ID.
+///
+/// MessageType value: 0x00021007
+/// Direction: Client to Server
+/// Target: RunspacePool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetAvailableRunspaces {
+    pub call_id: i64,
+}
+
+impl PsObjectWithType for GetAvailableRunspaces {
+    fn message_type(&self) -> MessageType {
+        MessageType::GetAvailableRunspaces
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        message_object([("ci", PsPrimitiveValue::I64(self.call_id))])
+    }
+}
+
+/// The answer of a [`RunspaceAvailability`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Availability {
+    /// Whether the server accepted a `SET_MAX_RUNSPACES` or `SET_MIN_RUNSPACES`
+    Changed(bool),
+    /// The free runspaces of a `GET_AVAILABLE_RUNSPACES`
+    Available(i64),
+}
+
+/// RUNSPACE_AVAILABILITY is the answer of the server to a [`SetMaxRunspaces`],
+/// [`SetMinRunspaces`] or [`GetAvailableRunspaces`].
+///
+/// MessageType value: 0x00021004
+/// Direction: Server to Client
+/// Target: RunspacePool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunspaceAvailability {
+    pub call_id: i64,
+    pub availability: Availability,
+}
+
+impl TryFrom<ComplexObject> for RunspaceAvailability {
+    type Error = PowerShellRemotingError;
+
+    fn try_from(value: ComplexObject) -> Result<Self, Self::Error> {
+        let call_id = value
+            .property("ci")
+            .ok_or_else(|| {
+                PowerShellRemotingError::InvalidMessage("Missing call ID (ci) property".to_string())
+            })
+            .and_then(i64::from_ps_value)?;
+
+        let availability = match value
+            .property("SetMinMaxRunspacesResponse")
+            .and_then(PsValue::as_primitive)
+        {
+            Some(PsPrimitiveValue::Bool(changed)) => Availability::Changed(*changed),
+            Some(PsPrimitiveValue::I64(available)) => Availability::Available(*available),
+            other => {
+                return Err(PowerShellRemotingError::InvalidMessage(format!(
+                    "Invalid SetMinMaxRunspacesResponse: {other:?}"
+                )));
+            }
+        };
+
+        Ok(RunspaceAvailability {
+            call_id,
+            availability,
+        })
+    }
+}
@@ -1,5 +1,5 @@
 use super::super::{
-    ComplexObject, ComplexObjectContent, Container, PsObjectWithType, PsPrimitiveValue, PsProperty,
+    ComplexObject, ComplexObjectContent, Container, PsEnums, PsObjectWithType, PsPrimitiveValue, PsProperty,
     PsType, PsValue,
 };
 use crate::MessageType;
@@ -122,10 +122,15 @@ impl TryFrom<ComplexObject> for RunspacePoolHostCall {
             ));
         };
 
-        let ComplexObjectContent::ExtendedPrimitive(PsPrimitiveValue::I32(method_id)) = &mi_obj.content else {
-            return Err(Self::Error::InvalidMessage(
-                "Method identifier content is not an I32".to_string(),
-            ));
+        // A `RemoteHostMethodId` is a `System.Enum`, deserialized as such
+        let method_id = match &mi_obj.content {
+            ComplexObjectContent::ExtendedPrimitive(PsPrimitiveValue::I32(method_id)) => method_id,
+            ComplexObjectContent::PsEnums(PsEnums { value }) => value,
+            _ => {
+                return Err(Self::Error::InvalidMessage(
+                    "Method identifier content is not an I32".to_string(),
+                ));
+            }
         };
 
         let method_name = mi_obj.to_string.clone().unwrap_or_default();
@@ -49,13 +49,34 @@ where
         }
     }
 }
+/// Encode `value` as the text of a `<S>` element: the characters XML cannot carry as they are
+/// become `_xHHHH_`, and so does the `_` starting what would read as one (section 2.2.5.3.2)
+pub fn encode_string(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        let needs_encoding = c.is_control()
+            || matches!(c, '\u{FFFE}' | '\u{FFFF}')
+            || (c == '_' && chars.peek() == Some(&'x'));
+        if needs_encoding {
+            let mut units = [0u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                encoded.push_str(&format!("_x{unit:04X}_"));
+            }
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
 /// ------------------------------------------------------------------------------------------------
 /// 1.  PsValue → <xml> element
 /// ------------------------------------------------------------------------------------------------
 impl<'a> PsPrimitiveValue {
     pub fn to_element(&'a self) -> Result<Element<'a>> {
         Ok(match self {
-            PsPrimitiveValue::Str(s) => Element::new("S").set_text_owned(s.clone()),
+            PsPrimitiveValue::Str(s) => Element::new("S").set_text_owned(encode_string(s)),
             PsPrimitiveValue::Bool(b) => Element::new("B").set_text_owned(b.to_string()),
             PsPrimitiveValue::I32(i) => Element::new("I32").set_text_owned(i.to_string()),
             PsPrimitiveValue::U32(u) => Element::new("U32").set_text_owned(u.to_string()),
@@ -64,6 +85,29 @@ impl<'a> PsPrimitiveValue {
             PsPrimitiveValue::Nil => Element::new("Nil"), // empty tag
             PsPrimitiveValue::Bytes(b) => Element::new("BA").set_text_owned(B64.encode(b)),
             PsPrimitiveValue::Version(v) => Element::new("Version").set_text_owned(v.clone()),
+            PsPrimitiveValue::Char(c) => {
+                let mut units = [0u16; 2];
+                let unit = c.encode_utf16(&mut units)[0];
+                Element::new("C").set_text_owned(unit.to_string())
+            }
+            PsPrimitiveValue::U8(u) => Element::new("UB").set_text_owned(u.to_string()),
+            PsPrimitiveValue::I8(i) => Element::new("SB").set_text_owned(i.to_string()),
+            PsPrimitiveValue::I16(i) => Element::new("I16").set_text_owned(i.to_string()),
+            PsPrimitiveValue::U16(u) => Element::new("U16").set_text_owned(u.to_string()),
+            PsPrimitiveValue::U64(u) => Element::new("U64").set_text_owned(u.to_string()),
+            PsPrimitiveValue::Double(d) => Element::new("Db").set_text_owned(d.clone()),
+            PsPrimitiveValue::Single(f) => Element::new("Sg").set_text_owned(f.clone()),
+            PsPrimitiveValue::Decimal(d) => Element::new("D").set_text_owned(d.clone()),
+            PsPrimitiveValue::DateTime(dt) => Element::new("DT").set_text_owned(dt.clone()),
+            PsPrimitiveValue::Duration(ts) => Element::new("TS").set_text_owned(ts.clone()),
+            PsPrimitiveValue::Uri(uri) => Element::new("URI").set_text_owned(encode_string(uri)),
+            PsPrimitiveValue::XmlDocument(xd) => {
+                Element::new("XD").set_text_owned(encode_string(xd))
+            }
+            PsPrimitiveValue::ScriptBlock(sbk) => {
+                Element::new("SBK").set_text_owned(encode_string(sbk))
+            }
+            PsPrimitiveValue::SecureString(ss) => Element::new("SS").set_text_owned(ss.clone()),
         })
     }
 }
@@ -157,6 +201,13 @@ impl<'a> Container {
                 }
                 element
             }
+            Container::Enumerable(values) => {
+                let mut element = Element::new("IE");
+                for value in values {
+                    element = element.add_child(value.to_element(objects_map, types_map)?);
+                }
+                element
+            }
             // Dictionaries serialize to a <DCT> tag with <En> entries.
             Container::Dictionary(map) => {
                 let mut element = Element::new("DCT");
@@ -3,7 +3,67 @@ use super::{
     PsValue,
 };
 use crate::MessageType;
-use std::{borrow::Cow, collections::BTreeMap};
+use std::{borrow::Cow, collections::BTreeMap, fmt, str::FromStr};
+
+/// A version of the protocol: a server speaks 2.1 with Windows PowerShell 2.0, e.g. of
+/// Windows Server 2008 R2, 2.2 with Windows PowerShell 3.0 and 4.0, and 2.3 from Windows
+/// PowerShell 5.1.
+///
+/// Client and server speak the older of their versions, which gates what the pool can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ProtocolVersion {
+    pub const V2_1: Self = Self::new(2, 1);
+    pub const V2_2: Self = Self::new(2, 2);
+    pub const V2_3: Self = Self::new(2, 3);
+    /// The version this client speaks by default
+    pub const LATEST: Self = Self::V2_3;
+
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Whether a pool can be disconnected from and connected to again, from 2.2
+    pub fn supports_disconnect(&self) -> bool {
+        *self >= Self::V2_2
+    }
+
+    /// Whether the state of the runspace of a pool can be reset between pipelines, from 2.3
+    pub fn supports_reset_runspace_state(&self) -> bool {
+        *self >= Self::V2_3
+    }
+}
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = crate::PowerShellRemotingError;
+
+    /// The major and minor of a version, e.g. `2.3`, its build and revision are ignored
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        major_minor(version)
+            .map(|(major, minor)| Self::new(major, minor))
+            .ok_or_else(|| {
+                crate::PowerShellRemotingError::InvalidMessage(format!(
+                    "protocolversion {version} is not a version"
+                ))
+            })
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SessionCapability {
@@ -13,6 +73,94 @@ pub struct SessionCapability {
     pub time_zone: Option<String>,
 }
 
+impl SessionCapability {
+    /// The protocol version this client speaks, the `protocolversion` option of the shell
+    pub const PROTOCOL_VERSION: &'static str = "2.3";
+    pub const PS_VERSION: &'static str = "2.0";
+    pub const SERIALIZATION_VERSION: &'static str = "1.1.0.1";
+
+    /// The capability this client sends with the `INIT_RUNSPACEPOOL` of the `creationXml`
+    pub fn client() -> Self {
+        Self::client_with(ProtocolVersion::LATEST)
+    }
+
+    /// The capability of a client speaking `protocol_version`, e.g. an older one for a server
+    /// that does not negotiate
+    pub fn client_with(protocol_version: ProtocolVersion) -> Self {
+        Self {
+            protocol_version: protocol_version.to_string(),
+            ps_version: Self::PS_VERSION.to_string(),
+            serialization_version: Self::SERIALIZATION_VERSION.to_string(),
+            time_zone: None,
+        }
+    }
+
+    /// Check the capability the server answered with against the versions this client
+    /// supports: a 2.x protocol, PowerShell 2.0 or later and the 1.1 serialization
+    pub fn check_server(&self) -> Result<(), crate::PowerShellRemotingError> {
+        check_version(
+            "protocolversion",
+            &self.protocol_version,
+            |version| version.0 == 2,
+            "2.x",
+        )?;
+        check_version(
+            "PSVersion",
+            &self.ps_version,
+            |version| version >= (2, 0),
+            "2.0 or later",
+        )?;
+        check_version(
+            "SerializationVersion",
+            &self.serialization_version,
+            |version| version == (1, 1),
+            "1.1.x.x",
+        )
+    }
+}
+
+impl SessionCapability {
+    /// The version both sides speak, the older of the one of this capability and `client`
+    pub fn negotiate(
+        &self,
+        client: ProtocolVersion,
+    ) -> Result<ProtocolVersion, crate::PowerShellRemotingError> {
+        let server = self.protocol_version.parse::<ProtocolVersion>()?;
+        Ok(server.min(client))
+    }
+}
+
+fn major_minor(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.').map(str::parse::<u32>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => Some((major, minor)),
+        _ => None,
+    }
+}
+
+fn check_version(
+    name: &'static str,
+    version: &str,
+    supported: impl Fn((u32, u32)) -> bool,
+    expected: &'static str,
+) -> Result<(), crate::PowerShellRemotingError> {
+    let Some(major_minor) = major_minor(version) else {
+        return Err(crate::PowerShellRemotingError::InvalidMessage(format!(
+            "{name} {version} is not a version"
+        )));
+    };
+
+    if supported(major_minor) {
+        Ok(())
+    } else {
+        Err(crate::PowerShellRemotingError::UnsupportedVersion {
+            name,
+            version: version.to_string(),
+            expected,
+        })
+    }
+}
+
 impl PsObjectWithType for SessionCapability {
     fn message_type(&self) -> MessageType {
         MessageType::SessionCapability
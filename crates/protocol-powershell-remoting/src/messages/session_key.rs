@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use openssl::{
+    pkey::Private,
+    rsa::{Padding, Rsa},
+    symm::{Cipher, decrypt, encrypt},
+};
+
+use super::{ComplexObject, PsObjectWithType, PsPrimitiveValue, PsProperty, PsValue};
+use crate::{MessageType, PowerShellRemotingError};
+
+/// `CALG_RSA_KEYX`, the key of a `PUBLICKEYBLOB` and the one a `SIMPLEBLOB` is encrypted with
+const CALG_RSA_KEYX: u32 = 0x0000_A400;
+/// `CALG_AES_256`, the session key of a `SIMPLEBLOB`
+const CALG_AES_256: u32 = 0x0000_6610;
+const PUBLIC_KEY_BLOB: u8 = 0x06;
+const SIMPLE_BLOB: u8 = 0x01;
+const CUR_BLOB_VERSION: u8 = 0x02;
+/// `RSA1`, the magic of a public `RSAPUBKEY`
+const RSA1: u32 = 0x3141_5352;
+const RSA_KEY_BITS: u32 = 2048;
+
+fn crypto_error(error: openssl::error::ErrorStack) -> PowerShellRemotingError {
+    PowerShellRemotingError::CryptoError(error.to_string())
+}
+
+/// PUBLIC_KEY is the message the client sends the RSA public key the server encrypts the
+/// session key with.
+///
+/// MessageType value: 0x00010005
+/// Direction: Client to Server
+/// Target: RunspacePool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    /// The base64 of the CAPI `PUBLICKEYBLOB` of the key
+    pub public_key: String,
+}
+
+impl PsObjectWithType for PublicKey {
+    fn message_type(&self) -> MessageType {
+        MessageType::PublicKey
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        let mut extended_properties = BTreeMap::new();
+        extended_properties.insert(
+            "PublicKey".to_string(),
+            PsProperty {
+                name: "PublicKey".to_string(),
+                value: PsValue::Primitive(PsPrimitiveValue::Str(self.public_key.clone())),
+            },
+        );
+
+        PsValue::Object(ComplexObject {
+            extended_properties,
+            ..Default::default()
+        })
+    }
+}
+
+/// ENCRYPTED_SESSION_KEY is the message the server answers a [`PublicKey`] with, the
+/// AES-256 key of the `SecureString`s of the pool encrypted with it.
+///
+/// MessageType value: 0x00010006
+/// Direction: Server to Client
+/// Target: RunspacePool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedSessionKey {
+    /// The base64 of the CAPI `SIMPLEBLOB` of the key
+    pub encrypted_session_key: String,
+}
+
+impl TryFrom<ComplexObject> for EncryptedSessionKey {
+    type Error = PowerShellRemotingError;
+
+    fn try_from(value: ComplexObject) -> Result<Self, Self::Error> {
+        let encrypted_session_key = value
+            .property("EncryptedSessionKey")
+            .and_then(PsValue::as_str)
+            .ok_or_else(|| {
+                PowerShellRemotingError::InvalidMessage(
+                    "Missing EncryptedSessionKey property".to_string(),
+                )
+            })?;
+
+        Ok(EncryptedSessionKey {
+            encrypted_session_key: encrypted_session_key.to_string(),
+        })
+    }
+}
+
+/// The RSA key pair of the client side of the session key exchange.
+///
+/// The client sends its [`public_key`](Self::public_key) when it first needs a
+/// `SecureString`, or when the server asks for it with a `PUBLIC_KEY_REQUEST`, then
+/// [`session_key`](Self::session_key) decrypts the key of the `ENCRYPTED_SESSION_KEY`.
+pub struct KeyExchange {
+    rsa: Rsa<Private>,
+}
+
+impl std::fmt::Debug for KeyExchange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyExchange").finish_non_exhaustive()
+    }
+}
+
+impl KeyExchange {
+    pub fn new() -> Result<Self, PowerShellRemotingError> {
+        Ok(KeyExchange {
+            rsa: Rsa::generate(RSA_KEY_BITS).map_err(crypto_error)?,
+        })
+    }
+
+    /// The `PUBLIC_KEY` message of the key pair
+    pub fn public_key(&self) -> Result<PublicKey, PowerShellRemotingError> {
+        // 65537, the exponent OpenSSL generates
+        let exponent = self
+            .rsa
+            .e()
+            .to_vec()
+            .iter()
+            .fold(0u32, |exponent, byte| (exponent << 8) | u32::from(*byte));
+
+        let mut modulus = self
+            .rsa
+            .n()
+            .to_vec_padded(self.rsa.size() as i32)
+            .map_err(crypto_error)?;
+        modulus.reverse();
+
+        let mut blob = vec![PUBLIC_KEY_BLOB, CUR_BLOB_VERSION, 0, 0];
+        blob.extend_from_slice(&CALG_RSA_KEYX.to_le_bytes());
+        blob.extend_from_slice(&RSA1.to_le_bytes());
+        blob.extend_from_slice(&(self.rsa.size() * 8).to_le_bytes());
+        blob.extend_from_slice(&exponent.to_le_bytes());
+        blob.extend_from_slice(&modulus);
+
+        Ok(PublicKey {
+            public_key: base64::engine::general_purpose::STANDARD.encode(blob),
+        })
+    }
+
+    /// Decrypt the session key of the `ENCRYPTED_SESSION_KEY` of the server
+    pub fn session_key(
+        &self,
+        encrypted: &EncryptedSessionKey,
+    ) -> Result<SessionKey, PowerShellRemotingError> {
+        let invalid = |reason: &str| {
+            PowerShellRemotingError::InvalidMessage(format!(
+                "Invalid EncryptedSessionKey: {reason}"
+            ))
+        };
+
+        let blob = base64::engine::general_purpose::STANDARD
+            .decode(&encrypted.encrypted_session_key)
+            .map_err(|_| invalid("not base64"))?;
+        if blob.len() < 12 {
+            return Err(invalid("too short"));
+        }
+
+        let (header, key) = blob.split_at(12);
+        let algorithm = |offset: usize| {
+            u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+        if header[0] != SIMPLE_BLOB || algorithm(4) != CALG_AES_256 || algorithm(8) != CALG_RSA_KEYX
+        {
+            return Err(invalid("not an AES-256 SIMPLEBLOB"));
+        }
+
+        // CAPI writes the encrypted key little-endian
+        let mut key = key.to_vec();
+        key.reverse();
+
+        let mut decrypted = vec![0; self.rsa.size() as usize];
+        let length = self
+            .rsa
+            .private_decrypt(&key, &mut decrypted, Padding::PKCS1)
+            .map_err(crypto_error)?;
+        let key = decrypted[..length]
+            .try_into()
+            .map_err(|_| invalid("the session key is not 32 bytes"))?;
+
+        Ok(SessionKey { key })
+    }
+}
+
+/// The AES-256 session key of a pool, which encrypts its `SecureString`s
+#[derive(Clone, PartialEq, Eq)]
+pub struct SessionKey {
+    key: [u8; 32],
+}
+
+impl std::fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionKey").finish_non_exhaustive()
+    }
+}
+
+impl SessionKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        SessionKey { key }
+    }
+
+    /// AES-256-CBC with a zero IV and PKCS#7 padding, the cipher of PSRP
+    fn cipher() -> (Cipher, [u8; 16]) {
+        (Cipher::aes_256_cbc(), [0; 16])
+    }
+
+    /// The `SecureString` of `text`: its UTF-16 encrypted with the key
+    pub fn encrypt(&self, text: &str) -> Result<PsValue, PowerShellRemotingError> {
+        let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let (cipher, iv) = Self::cipher();
+        let encrypted = encrypt(cipher, &self.key, Some(&iv), &utf16).map_err(crypto_error)?;
+
+        Ok(PsValue::Primitive(PsPrimitiveValue::SecureString(
+            base64::engine::general_purpose::STANDARD.encode(encrypted),
+        )))
+    }
+
+    /// The text of a `SecureString` the server sent
+    pub fn decrypt(&self, secure_string: &PsValue) -> Result<String, PowerShellRemotingError> {
+        let Some(PsPrimitiveValue::SecureString(encrypted)) = secure_string.as_primitive() else {
+            return Err(PowerShellRemotingError::ConversionError(format!(
+                "Expected a SecureString, got {secure_string:?}"
+            )));
+        };
+
+        let encrypted = base64::engine::general_purpose::STANDARD
+            .decode(encrypted)
+            .map_err(|_| {
+                PowerShellRemotingError::ConversionError(
+                    "The SecureString is not base64".to_string(),
+                )
+            })?;
+        let (cipher, iv) = Self::cipher();
+        let utf16 = decrypt(cipher, &self.key, Some(&iv), &encrypted).map_err(crypto_error)?;
+
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        String::from_utf16(&units).map_err(|_| {
+            PowerShellRemotingError::ConversionError(
+                "The SecureString is not valid UTF-16".to_string(),
+            )
+        })
+    }
+}
@@ -0,0 +1,118 @@
+use super::{ComplexObject, FromPsValue, InvocationInfo, PsValue};
+use crate::{FromPsObject, PowerShellRemotingError};
+
+/// The record of a `DEBUG_RECORD`, `VERBOSE_RECORD` or `WARNING_RECORD` message, what
+/// `Write-Debug`, `Write-Verbose` and `Write-Warning` write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InformationalRecord {
+    pub message: String,
+    /// Where the record was written, only serialized when `InformationalRecord_SerializeInvocationInfo` is set
+    pub invocation_info: Option<InvocationInfo>,
+}
+
+impl std::fmt::Display for InformationalRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl FromPsObject for InformationalRecord {
+    fn from_ps_object(record: &ComplexObject) -> Result<Self, PowerShellRemotingError> {
+        let message = match record.property("InformationalRecord_Message") {
+            Some(message) => String::from_ps_value(message)?,
+            None => record.to_string.clone().ok_or_else(|| {
+                PowerShellRemotingError::ConversionError(
+                    "The informational record has no message".to_string(),
+                )
+            })?,
+        };
+
+        let with_invocation_info = record
+            .property("InformationalRecord_SerializeInvocationInfo")
+            .and_then(|value| bool::from_ps_value(value).ok())
+            .unwrap_or(false);
+        let invocation_info = if with_invocation_info {
+            Some(InvocationInfo::from_ps_object(record)?)
+        } else {
+            None
+        };
+
+        Ok(InformationalRecord {
+            message,
+            invocation_info,
+        })
+    }
+}
+
+impl FromPsValue for InformationalRecord {
+    fn from_ps_value(value: &PsValue) -> Result<Self, PowerShellRemotingError> {
+        match value.as_object() {
+            Some(record) => InformationalRecord::from_ps_object(record),
+            // Some hosts write the bare message
+            None => Ok(InformationalRecord {
+                message: String::from_ps_value(value)?,
+                invocation_info: None,
+            }),
+        }
+    }
+}
+
+/// The `System.Management.Automation.InformationRecord` of an `INFORMATION_RECORD` message,
+/// what `Write-Information` writes and, since PowerShell 5, `Write-Host` too
+#[derive(Debug, Clone, PartialEq, Eq, FromPsObject)]
+pub struct InformationRecord {
+    /// The object written, a `HostInformationMessage` for `Write-Host`
+    pub message_data: Option<PsValue>,
+    pub source: Option<String>,
+    pub time_generated: Option<String>,
+    pub tags: Vec<String>,
+    pub user: Option<String>,
+    pub computer: Option<String>,
+    pub process_id: Option<u32>,
+}
+
+impl InformationRecord {
+    /// The text of the record: the `Message` of a `HostInformationMessage`, else the text of
+    /// the data
+    pub fn message(&self) -> Option<String> {
+        let data = self.message_data.as_ref()?;
+        data.as_object()
+            .and_then(|data| data.property("Message"))
+            .unwrap_or(data)
+            .to_typed::<String>()
+            .ok()
+    }
+}
+
+/// `System.Management.Automation.ProgressRecordType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressRecordType {
+    Processing = 0,
+    Completed = 1,
+}
+
+impl FromPsValue for ProgressRecordType {
+    fn from_ps_value(value: &PsValue) -> Result<Self, PowerShellRemotingError> {
+        match i32::from_ps_value(value)? {
+            0 => Ok(ProgressRecordType::Processing),
+            1 => Ok(ProgressRecordType::Completed),
+            other => Err(PowerShellRemotingError::ConversionError(format!(
+                "Invalid ProgressRecordType: {other}"
+            ))),
+        }
+    }
+}
+
+/// The record of a `PROGRESS_RECORD` message, what `Write-Progress` writes
+#[derive(Debug, Clone, PartialEq, Eq, FromPsObject)]
+pub struct ProgressRecord {
+    pub activity: String,
+    pub activity_id: i32,
+    pub status_description: Option<String>,
+    pub current_operation: Option<String>,
+    pub parent_activity_id: Option<i32>,
+    pub percent_complete: Option<i32>,
+    pub seconds_remaining: Option<i32>,
+    #[ps(rename = "Type")]
+    pub record_type: Option<ProgressRecordType>,
+}
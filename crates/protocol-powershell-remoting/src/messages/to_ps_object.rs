@@ -0,0 +1,188 @@
+use super::{
+    ComplexObject, ComplexObjectContent, Container, PsPrimitiveValue, PsProperty, PsType, PsValue,
+};
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// A Rust value that serializes as a [`PsValue`], e.g. an object of the input of a pipeline.
+///
+/// Strings, booleans, numbers and GUIDs become primitives, `None` is `$null`, a `Vec` is an
+/// `Object[]` and a `BTreeMap` a `Hashtable`. [`PsValue::custom_object`] builds the
+/// `PSCustomObject` of a struct.
+pub trait ToPsObject {
+    fn to_ps_value(&self) -> PsValue;
+}
+
+impl PsType {
+    pub fn ps_custom_object() -> Self {
+        PsType {
+            type_names: vec![
+                Cow::Borrowed("System.Management.Automation.PSCustomObject"),
+                Cow::Borrowed("System.Object"),
+            ],
+        }
+    }
+
+    pub fn object_array() -> Self {
+        PsType {
+            type_names: vec![
+                Cow::Borrowed("System.Object[]"),
+                Cow::Borrowed("System.Array"),
+                Cow::Borrowed("System.Object"),
+            ],
+        }
+    }
+
+    pub fn hashtable() -> Self {
+        PsType {
+            type_names: vec![
+                Cow::Borrowed("System.Collections.Hashtable"),
+                Cow::Borrowed("System.Object"),
+            ],
+        }
+    }
+}
+
+impl PsValue {
+    /// A `PSCustomObject` whose NoteProperties are `properties`, what `[pscustomobject]@{...}`
+    /// makes in a script
+    pub fn custom_object<N: Into<String>>(
+        properties: impl IntoIterator<Item = (N, PsValue)>,
+    ) -> PsValue {
+        let extended_properties = properties
+            .into_iter()
+            .map(|(name, value)| {
+                let name = name.into();
+                (name.clone(), PsProperty { name, value })
+            })
+            .collect();
+
+        PsValue::Object(ComplexObject {
+            type_def: Some(PsType::ps_custom_object()),
+            extended_properties,
+            ..Default::default()
+        })
+    }
+}
+
+impl ToPsObject for PsValue {
+    fn to_ps_value(&self) -> PsValue {
+        self.clone()
+    }
+}
+
+impl ToPsObject for ComplexObject {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Object(self.clone())
+    }
+}
+
+impl ToPsObject for PsPrimitiveValue {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Primitive(self.clone())
+    }
+}
+
+impl ToPsObject for str {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Primitive(PsPrimitiveValue::Str(self.to_string()))
+    }
+}
+
+impl ToPsObject for String {
+    fn to_ps_value(&self) -> PsValue {
+        self.as_str().to_ps_value()
+    }
+}
+
+impl ToPsObject for bool {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Primitive(PsPrimitiveValue::Bool(*self))
+    }
+}
+
+impl ToPsObject for char {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Primitive(PsPrimitiveValue::Char(*self))
+    }
+}
+
+macro_rules! primitive_to_ps_object {
+    ($($ty:ty => $variant:ident),*) => {
+        $(
+            impl ToPsObject for $ty {
+                fn to_ps_value(&self) -> PsValue {
+                    PsValue::Primitive(PsPrimitiveValue::$variant(*self))
+                }
+            }
+        )*
+    };
+}
+
+primitive_to_ps_object!(
+    i8 => I8, u8 => U8, i16 => I16, u16 => U16, i32 => I32, u32 => U32, i64 => I64, u64 => U64
+);
+
+impl ToPsObject for f64 {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Primitive(PsPrimitiveValue::Double(self.to_string()))
+    }
+}
+
+impl ToPsObject for f32 {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Primitive(PsPrimitiveValue::Single(self.to_string()))
+    }
+}
+
+impl ToPsObject for uuid::Uuid {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Primitive(PsPrimitiveValue::Guid(self.to_string()))
+    }
+}
+
+impl<T: ToPsObject + ?Sized> ToPsObject for &T {
+    fn to_ps_value(&self) -> PsValue {
+        (**self).to_ps_value()
+    }
+}
+
+impl<T: ToPsObject> ToPsObject for Option<T> {
+    fn to_ps_value(&self) -> PsValue {
+        match self {
+            Some(value) => value.to_ps_value(),
+            None => PsValue::Primitive(PsPrimitiveValue::Nil),
+        }
+    }
+}
+
+impl<T: ToPsObject> ToPsObject for [T] {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Object(ComplexObject {
+            type_def: Some(PsType::object_array()),
+            content: ComplexObjectContent::Container(Container::List(
+                self.iter().map(ToPsObject::to_ps_value).collect(),
+            )),
+            ..Default::default()
+        })
+    }
+}
+
+impl<T: ToPsObject> ToPsObject for Vec<T> {
+    fn to_ps_value(&self) -> PsValue {
+        self.as_slice().to_ps_value()
+    }
+}
+
+impl<K: ToPsObject, V: ToPsObject> ToPsObject for BTreeMap<K, V> {
+    fn to_ps_value(&self) -> PsValue {
+        PsValue::Object(ComplexObject {
+            type_def: Some(PsType::hashtable()),
+            content: ComplexObjectContent::Container(Container::Dictionary(
+                self.iter()
+                    .map(|(key, value)| (key.to_ps_value(), value.to_ps_value()))
+                    .collect(),
+            )),
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,30 @@
+use super::PsValue;
+use crate::FromPsObject;
+
+/// USER_EVENT is the message of an event of the server forwarded to the client: of a
+/// `New-Event`, or of a `Register-EngineEvent -Forward` or `Register-ObjectEvent -Forward`.
+///
+/// MessageType value: 0x00021008
+/// Direction: Server to Client
+/// Target: RunspacePool
+#[derive(Debug, Clone, PartialEq, Eq, FromPsObject)]
+pub struct UserEvent {
+    #[ps(rename = "PSEventArgs.EventIdentifier")]
+    pub event_identifier: i32,
+    /// The name of the event, the `-SourceIdentifier` of `New-Event`
+    #[ps(rename = "PSEventArgs.SourceIdentifier")]
+    pub source_identifier: String,
+    #[ps(rename = "PSEventArgs.TimeGenerated")]
+    pub time_generated: Option<String>,
+    #[ps(rename = "PSEventArgs.Sender")]
+    pub sender: Option<PsValue>,
+    #[ps(rename = "PSEventArgs.SourceArgs")]
+    pub source_args: Vec<PsValue>,
+    /// The `-MessageData` of the event
+    #[ps(rename = "PSEventArgs.MessageData")]
+    pub message_data: Option<PsValue>,
+    #[ps(rename = "PSEventArgs.ComputerName")]
+    pub computer_name: Option<String>,
+    #[ps(rename = "PSEventArgs.RunspaceId")]
+    pub runspace_id: Option<uuid::Uuid>,
+}
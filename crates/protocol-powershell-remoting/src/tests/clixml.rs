@@ -0,0 +1,184 @@
+use crate::{
+    ComplexObjectContent, Container, PsPrimitiveValue, PsValue, deserialize::decode_string,
+    serialize::encode_string,
+};
+
+const PROCESS_CLIXML: &str = r#"#< CLIXML
+<Objs Version="1.1.0.1" xmlns="http://schemas.microsoft.com/powershell/2004/04">
+  <Obj RefId="0">
+    <TN RefId="0">
+      <T>System.Diagnostics.Process</T>
+      <T>System.ComponentModel.Component</T>
+      <T>System.Object</T>
+    </TN>
+    <ToString>System.Diagnostics.Process (pwsh)</ToString>
+    <Props>
+      <I32 N="Id">4242</I32>
+      <S N="Name">pwsh</S>
+      <Nil N="ExitCode" />
+      <Obj N="PriorityClass" RefId="1">
+        <TN RefId="1">
+          <T>System.Diagnostics.ProcessPriorityClass</T>
+          <T>System.Enum</T>
+          <T>System.ValueType</T>
+          <T>System.Object</T>
+        </TN>
+        <ToString>Normal</ToString>
+        <I32>32</I32>
+      </Obj>
+      <DT N="StartTime">2024-05-01T10:42:32.2731993+02:00</DT>
+      <TS N="TotalProcessorTime">PT9.0269026S</TS>
+    </Props>
+    <MS>
+      <S N="Note">line one_x000D__x000A_line two</S>
+      <Ref N="Self" RefId="1" />
+    </MS>
+  </Obj>
+  <Obj RefId="2">
+    <TNRef RefId="0" />
+    <ToString>System.Diagnostics.Process (idle)</ToString>
+    <Props>
+      <I32 N="Id">0</I32>
+    </Props>
+  </Obj>
+  <Obj RefId="3">
+    <TN RefId="2">
+      <T>System.Object[]</T>
+      <T>System.Array</T>
+      <T>System.Object</T>
+    </TN>
+    <LST>
+      <C>97</C>
+      <UB>255</UB>
+      <I16>-3</I16>
+      <U64>18446744073709551615</U64>
+      <Db>1.5</Db>
+    </LST>
+  </Obj>
+</Objs>"#;
+
+#[test]
+fn test_clixml_objects() {
+    let values = PsValue::from_clixml(PROCESS_CLIXML).unwrap();
+    assert_eq!(values.len(), 3);
+
+    let process = values[0].as_object().unwrap();
+    assert!(process.is_type("System.ComponentModel.Component"));
+    assert_eq!(
+        process.to_string.as_deref(),
+        Some("System.Diagnostics.Process (pwsh)")
+    );
+    assert_eq!(
+        process.property("Id").and_then(PsValue::as_primitive),
+        Some(&PsPrimitiveValue::I32(4242))
+    );
+    assert_eq!(
+        process.property("Name").and_then(PsValue::as_str),
+        Some("pwsh")
+    );
+    assert!(process.property("ExitCode").unwrap().is_nil());
+    assert_eq!(
+        process
+            .property("StartTime")
+            .and_then(PsValue::as_primitive),
+        Some(&PsPrimitiveValue::DateTime(
+            "2024-05-01T10:42:32.2731993+02:00".to_string()
+        ))
+    );
+    assert_eq!(
+        process.property("Note").and_then(PsValue::as_str),
+        Some("line one\r\nline two")
+    );
+
+    // The enum, and the note property referring back to it
+    let priority = process
+        .property("PriorityClass")
+        .unwrap()
+        .as_object()
+        .unwrap();
+    assert!(matches!(
+        priority.content,
+        ComplexObjectContent::PsEnums(ref e) if e.value == 32
+    ));
+    assert_eq!(
+        process.property("Self").unwrap().as_object(),
+        Some(priority)
+    );
+
+    // The second process refers back to the type names of the first
+    let idle = values[1].as_object().unwrap();
+    assert_eq!(idle.type_def, process.type_def);
+
+    let array = values[2].as_object().unwrap();
+    let ComplexObjectContent::Container(Container::List(items)) = &array.content else {
+        panic!("Expected a list");
+    };
+    assert_eq!(
+        items,
+        &vec![
+            PsValue::Primitive(PsPrimitiveValue::Char('a')),
+            PsValue::Primitive(PsPrimitiveValue::U8(255)),
+            PsValue::Primitive(PsPrimitiveValue::I16(-3)),
+            PsValue::Primitive(PsPrimitiveValue::U64(u64::MAX)),
+            PsValue::Primitive(PsPrimitiveValue::Double("1.5".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn test_clixml_single_value_and_unknown_reference() {
+    let values = PsValue::from_clixml(r#"<IE><S>a</S><Nil /></IE>"#);
+    assert!(values.is_err(), "A bare container is not a value");
+
+    let values = PsValue::from_clixml(r#"<S>plain</S>"#).unwrap();
+    assert_eq!(
+        values,
+        vec![PsValue::Primitive(PsPrimitiveValue::Str(
+            "plain".to_string()
+        ))]
+    );
+
+    let enumerable = PsValue::from_clixml(r#"<Obj RefId="0"><IE><S>a</S><Nil /></IE></Obj>"#)
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        enumerable.as_object().unwrap().content,
+        ComplexObjectContent::Container(Container::Enumerable(vec![
+            PsValue::Primitive(PsPrimitiveValue::Str("a".to_string())),
+            PsValue::Primitive(PsPrimitiveValue::Nil),
+        ]))
+    );
+
+    assert!(PsValue::from_clixml(r#"<Objs><Ref RefId="7" /></Objs>"#).is_err());
+}
+
+#[test]
+fn test_clixml_primitives_round_trip() {
+    let primitives = [
+        PsPrimitiveValue::Str("tab\there_x_under".to_string()),
+        PsPrimitiveValue::Char('é'),
+        PsPrimitiveValue::I8(-8),
+        PsPrimitiveValue::U16(65535),
+        PsPrimitiveValue::Decimal("79228162514264337593543950335".to_string()),
+        PsPrimitiveValue::Duration("P1DT2H".to_string()),
+        PsPrimitiveValue::Uri("http://localhost:5985/wsman".to_string()),
+        PsPrimitiveValue::ScriptBlock("Get-Process\n| Select-Object -First 1".to_string()),
+    ];
+
+    for primitive in primitives {
+        let xml = primitive.to_element().unwrap().to_string();
+        let parsed = PsValue::from_clixml(&xml).unwrap();
+        assert_eq!(parsed, vec![PsValue::Primitive(primitive)], "{xml}");
+    }
+}
+
+#[test]
+fn test_string_encoding() {
+    assert_eq!(encode_string("a\nb"), "a_x000A_b");
+    assert_eq!(encode_string("_x0041_"), "_x005F_x0041_");
+    assert_eq!(encode_string("snake_case"), "snake_case");
+
+    assert_eq!(decode_string("_x005F_x0041_"), "_x0041_");
+    assert_eq!(decode_string("_xD83D__xDE00_"), "😀");
+    assert_eq!(decode_string("broken _x12 escape"), "broken _x12 escape");
+}
@@ -0,0 +1,68 @@
+use crate::{
+    ConnectRunspacePool, MessageType, PsObjectWithType, PsPrimitiveValue, PsValue,
+    RunspacePoolInitData,
+};
+
+fn object(message: &PsValue) -> &crate::ComplexObject {
+    let PsValue::Object(object) = message else {
+        panic!("A pool message is an object");
+    };
+    object
+}
+
+#[test]
+fn test_connect_runspace_pool_properties() {
+    let connect = ConnectRunspacePool::default();
+    assert_eq!(connect.message_type(), MessageType::ConnectRunspacepool);
+    let message = connect.to_ps_object();
+    assert!(object(&message).extended_properties.is_empty());
+
+    let connect = ConnectRunspacePool {
+        min_runspaces: Some(1),
+        max_runspaces: Some(4),
+    };
+    let message = connect.to_ps_object();
+    let connect = object(&message);
+    assert_eq!(
+        connect
+            .property("MinRunspaces")
+            .and_then(PsValue::as_primitive),
+        Some(&PsPrimitiveValue::I32(1))
+    );
+    assert_eq!(
+        connect
+            .property("MaxRunspaces")
+            .and_then(PsValue::as_primitive),
+        Some(&PsPrimitiveValue::I32(4))
+    );
+}
+
+#[test]
+fn test_runspace_pool_init_data() {
+    let clixml = r#"<Obj RefId="0">
+      <MS>
+        <I32 N="MinRunspaces">1</I32>
+        <I32 N="MaxRunspaces">3</I32>
+      </MS>
+    </Obj>"#;
+
+    let PsValue::Object(init_data) = PsValue::from_clixml(clixml).unwrap().remove(0) else {
+        panic!("A RUNSPACEPOOL_INIT_DATA is an object");
+    };
+    assert_eq!(
+        RunspacePoolInitData::try_from(init_data).unwrap(),
+        RunspacePoolInitData {
+            min_runspaces: 1,
+            max_runspaces: 3,
+        }
+    );
+
+    let PsValue::Object(missing) =
+        PsValue::from_clixml(r#"<Obj RefId="0"><MS><I32 N="MinRunspaces">1</I32></MS></Obj>"#)
+            .unwrap()
+            .remove(0)
+    else {
+        panic!("A RUNSPACEPOOL_INIT_DATA is an object");
+    };
+    assert!(RunspacePoolInitData::try_from(missing).is_err());
+}
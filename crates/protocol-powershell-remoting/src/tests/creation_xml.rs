@@ -1,9 +1,10 @@
 use crate::{
     HostInfo,
     fragment::{DefragmentResult, Defragmenter, Fragmenter},
-    messages::{ApartmentState, InitRunspacePool, PSThreadOptions, SessionCapability},
+    messages::{
+        ApartmentState, InitRunspacePool, PSThreadOptions, PsPrimitiveDictionary, SessionCapability,
+    },
 };
-use std::collections::BTreeMap;
 use tracing::info;
 use tracing_test::traced_test;
 use uuid::Uuid;
@@ -25,7 +26,7 @@ fn test_combined_messages_like_runspace_open() {
         thread_options: PSThreadOptions::Default,
         apartment_state: ApartmentState::Unknown,
         host_info: HostInfo::builder().build(),
-        application_arguments: BTreeMap::new(),
+        application_arguments: PsPrimitiveDictionary::new(),
     };
 
     let runspace_id = Uuid::parse_str("d034652d-126b-e340-b773-cba26459cfa8").unwrap();
@@ -0,0 +1,64 @@
+use uuid::Uuid;
+
+use crate::{Dissector, EndOfPipelineInput, Fragmenter, SessionCapability};
+
+#[test]
+fn test_dissect_fragments_across_streams() {
+    let rpid = Uuid::new_v4();
+    let mut fragmenter = Fragmenter::new(64);
+    let fragments = fragmenter
+        .fragment(&SessionCapability::client(), rpid, None, None)
+        .unwrap();
+    assert!(fragments.len() > 2);
+
+    let (first, rest) = fragments.split_at(1);
+    let mut dissector = Dissector::new();
+
+    let report = dissector.dissect(&first.concat());
+    assert!(report.contains("Fragment object 1 fragment 0 [start]"));
+    assert!(!report.contains("Message"), "{report}");
+    assert!(dissector.has_pending());
+
+    let report = dissector.dissect(&rest.concat());
+    assert!(!dissector.has_pending());
+    assert!(report.contains("[end]"));
+    assert!(
+        report.contains("  Message SessionCapability (0x00010002) to Server"),
+        "{report}"
+    );
+    assert!(report.contains(&format!("    RPID {rpid}")));
+    assert!(!report.contains("PID  "));
+    assert!(
+        report.contains("    <Obj RefId=\"0\">\n      <MS>\n"),
+        "{report}"
+    );
+    assert!(report.contains("        <Version N=\"protocolversion\">2.3</Version>\n"));
+}
+
+#[test]
+fn test_dissect_reports_what_it_can_not_decode() {
+    let rpid = Uuid::new_v4();
+    let pid = Uuid::new_v4();
+    let mut fragmenter = Fragmenter::new(1024);
+    let mut stream = fragmenter
+        .fragment(&EndOfPipelineInput, rpid, Some(pid), None)
+        .unwrap()
+        .concat();
+    stream.extend_from_slice(&[0xAB; 5]);
+
+    let report = Dissector::new().dissect(&stream);
+    assert!(report.contains("  Message EndOfPipelineInput (0x00041003) to Server"));
+    assert!(report.contains(&format!("    PID  {pid}")));
+    assert!(report.contains("    (no data)"));
+    assert!(
+        report.contains("! 5 bytes not decoded: ") && report.contains("  AB AB AB AB AB\n"),
+        "{report}"
+    );
+
+    let mut fragmenter = Fragmenter::new(1024);
+    let fragments = fragmenter
+        .fragment(&SessionCapability::client(), rpid, None, Some(30))
+        .unwrap();
+    let report = Dissector::new().dissect(&fragments[1]);
+    assert!(report.contains("! object 1 was not started"), "{report}");
+}
@@ -0,0 +1,137 @@
+use crate::{
+    ErrorCategory, FromPsValue, PipelineStateMessage, PipelineStateValue, PsErrorRecord, PsValue,
+};
+
+/// The `ERROR_RECORD` of `Get-Item C:\nope`
+const GET_ITEM_ERROR: &str = r#"<Obj RefId="0">
+  <TN RefId="0">
+    <T>System.Management.Automation.ErrorRecord</T>
+    <T>System.Object</T>
+  </TN>
+  <ToString>Cannot find path 'C:\nope' because it does not exist.</ToString>
+  <MS>
+    <Obj N="Exception" RefId="1">
+      <TN RefId="1">
+        <T>System.Management.Automation.ItemNotFoundException</T>
+        <T>System.Management.Automation.SessionStateException</T>
+        <T>System.Exception</T>
+        <T>System.Object</T>
+      </TN>
+      <ToString>System.Management.Automation.ItemNotFoundException: Cannot find path 'C:\nope' because it does not exist.</ToString>
+      <Props>
+        <S N="Message">Cannot find path 'C:\nope' because it does not exist.</S>
+        <Nil N="InnerException" />
+      </Props>
+    </Obj>
+    <S N="TargetObject">C:\nope</S>
+    <S N="FullyQualifiedErrorId">PathNotFound,Microsoft.PowerShell.Commands.GetItemCommand</S>
+    <Obj N="InvocationInfo" RefId="2">
+      <TN RefId="2">
+        <T>System.Management.Automation.InvocationInfo</T>
+        <T>System.Object</T>
+      </TN>
+      <ToString>System.Management.Automation.InvocationInfo</ToString>
+    </Obj>
+    <I32 N="ErrorCategory_Category">13</I32>
+    <S N="ErrorCategory_Activity">Get-Item</S>
+    <S N="ErrorCategory_Reason">ItemNotFoundException</S>
+    <S N="ErrorCategory_TargetName">C:\nope</S>
+    <S N="ErrorCategory_TargetType">String</S>
+    <S N="ErrorCategory_Message">ObjectNotFound: (C:\nope:String) [Get-Item], ItemNotFoundException</S>
+    <B N="SerializeExtendedInfo">true</B>
+    <S N="CommandInfo_Name">Get-Item</S>
+    <S N="InvocationInfo_InvocationName">Get-Item</S>
+    <S N="InvocationInfo_Line">Get-Item C:\nope_x000D__x000A_</S>
+    <I32 N="InvocationInfo_OffsetInLine">1</I32>
+    <I32 N="InvocationInfo_ScriptLineNumber">1</I32>
+    <S N="InvocationInfo_PositionMessage">At line:1 char:1_x000A_+ Get-Item C:\nope</S>
+    <S N="ErrorDetails_ScriptStackTrace">at &lt;ScriptBlock&gt;, &lt;No file&gt;: line 1</S>
+  </MS>
+</Obj>"#;
+
+#[test]
+fn test_error_record() {
+    let value = PsValue::from_clixml(GET_ITEM_ERROR).unwrap().remove(0);
+    let record = PsErrorRecord::from_ps_value(&value).unwrap();
+
+    assert_eq!(
+        record.message,
+        r"Cannot find path 'C:\nope' because it does not exist."
+    );
+    assert_eq!(
+        record.exception_type.as_deref(),
+        Some("System.Management.Automation.ItemNotFoundException")
+    );
+    assert_eq!(
+        record.fully_qualified_error_id.as_deref(),
+        Some("PathNotFound,Microsoft.PowerShell.Commands.GetItemCommand")
+    );
+    assert_eq!(
+        record.category.category,
+        Some(ErrorCategory::ObjectNotFound)
+    );
+    assert_eq!(record.category.activity.as_deref(), Some("Get-Item"));
+    assert_eq!(
+        record.target_object.as_ref().and_then(PsValue::as_str),
+        Some(r"C:\nope")
+    );
+    assert_eq!(
+        record.script_stack_trace.as_deref(),
+        Some("at <ScriptBlock>, <No file>: line 1")
+    );
+
+    let invocation_info = record.invocation_info.as_ref().unwrap();
+    assert_eq!(invocation_info.command_name.as_deref(), Some("Get-Item"));
+    assert_eq!(invocation_info.script_line_number, Some(1));
+    assert_eq!(
+        invocation_info.line.as_deref(),
+        Some("Get-Item C:\\nope\r\n")
+    );
+
+    assert_eq!(
+        record.to_string(),
+        r"Cannot find path 'C:\nope' because it does not exist. (PathNotFound,Microsoft.PowerShell.Commands.GetItemCommand)"
+    );
+}
+
+#[test]
+fn test_error_record_of_failed_pipeline() {
+    // `throw "boom"`, without the extended info
+    let clixml = r#"<Obj RefId="0">
+      <MS>
+        <I32 N="PipelineState">5</I32>
+        <Obj N="ExceptionAsErrorRecord" RefId="1">
+          <TN RefId="0"><T>System.Management.Automation.ErrorRecord</T><T>System.Object</T></TN>
+          <ToString>boom</ToString>
+          <MS>
+            <Obj N="Exception" RefId="2">
+              <TN RefId="1"><T>System.Management.Automation.RuntimeException</T><T>System.Exception</T><T>System.Object</T></TN>
+              <Props><S N="Message">boom</S></Props>
+            </Obj>
+            <Nil N="TargetObject" />
+            <S N="FullyQualifiedErrorId">boom</S>
+            <I32 N="ErrorCategory_Category">14</I32>
+            <B N="SerializeExtendedInfo">false</B>
+          </MS>
+        </Obj>
+      </MS>
+    </Obj>"#;
+
+    let PsValue::Object(state) = PsValue::from_clixml(clixml).unwrap().remove(0) else {
+        panic!("PIPELINE_STATE is an object");
+    };
+    let state = PipelineStateMessage::try_from(state).unwrap();
+    assert_eq!(state.pipeline_state, PipelineStateValue::Failed);
+
+    let record = PsErrorRecord::from_ps_value(&state.exception_as_error_record.unwrap()).unwrap();
+    assert_eq!(record.message, "boom");
+    assert_eq!(
+        record.category.category,
+        Some(ErrorCategory::OperationStopped)
+    );
+    assert_eq!(record.target_object, None);
+    assert_eq!(record.invocation_info, None);
+
+    let no_message = PsValue::from_clixml(r#"<Obj RefId="0"><MS /></Obj>"#).unwrap();
+    assert!(PsErrorRecord::from_ps_value(&no_message[0]).is_err());
+}
@@ -0,0 +1,140 @@
+use crate::{FromPsObject, FromPsValue, PowerShellRemotingError, PsValue};
+
+/// `Get-Service WinRM, Spooler | Select-Object Name, Status, DisplayName, DependentServices`
+const SERVICES_CLIXML: &str = r#"<Objs Version="1.1.0.1" xmlns="http://schemas.microsoft.com/powershell/2004/04">
+  <Obj RefId="0">
+    <TN RefId="0">
+      <T>Selected.System.ServiceProcess.ServiceController</T>
+      <T>System.Management.Automation.PSCustomObject</T>
+      <T>System.Object</T>
+    </TN>
+    <MS>
+      <S N="Name">WinRM</S>
+      <Obj N="Status" RefId="1">
+        <TN RefId="1">
+          <T>System.ServiceProcess.ServiceControllerStatus</T>
+          <T>System.Enum</T>
+          <T>System.ValueType</T>
+          <T>System.Object</T>
+        </TN>
+        <ToString>Running</ToString>
+        <I32>4</I32>
+      </Obj>
+      <S N="DisplayName">Windows Remote Management (WS-Management)</S>
+      <Obj N="DependentServices" RefId="2">
+        <TN RefId="2">
+          <T>System.ServiceProcess.ServiceController[]</T>
+          <T>System.Array</T>
+          <T>System.Object</T>
+        </TN>
+        <LST>
+          <S>HTTP</S>
+          <S>RPCSS</S>
+        </LST>
+      </Obj>
+    </MS>
+  </Obj>
+  <Obj RefId="3">
+    <TNRef RefId="0" />
+    <MS>
+      <S N="Name">Spooler</S>
+      <Ref N="Status" RefId="1" />
+      <Nil N="DisplayName" />
+      <S N="DependentServices">RPCSS</S>
+    </MS>
+  </Obj>
+</Objs>"#;
+
+#[derive(Debug, PartialEq, FromPsObject)]
+struct ServiceInfo {
+    name: String,
+    status: String,
+    #[ps(rename = "Status")]
+    status_code: i32,
+    display_name: Option<String>,
+    dependent_services: Vec<String>,
+    start_type: Option<String>,
+}
+
+#[test]
+fn test_derive_from_ps_object() {
+    let services = PsValue::from_clixml(SERVICES_CLIXML)
+        .unwrap()
+        .iter()
+        .map(ServiceInfo::from_ps_value)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        services,
+        vec![
+            ServiceInfo {
+                name: "WinRM".to_string(),
+                status: "Running".to_string(),
+                status_code: 4,
+                display_name: Some("Windows Remote Management (WS-Management)".to_string()),
+                dependent_services: vec!["HTTP".to_string(), "RPCSS".to_string()],
+                start_type: None,
+            },
+            ServiceInfo {
+                name: "Spooler".to_string(),
+                status: "Running".to_string(),
+                status_code: 4,
+                display_name: None,
+                dependent_services: vec!["RPCSS".to_string()],
+                start_type: None,
+            },
+        ]
+    );
+}
+
+#[derive(Debug, FromPsObject)]
+struct Process {
+    #[allow(dead_code)]
+    id: u16,
+}
+
+#[test]
+fn test_derive_from_ps_object_errors() {
+    let missing = PsValue::from_clixml(r#"<Obj RefId="0"><MS><S N="Name">pwsh</S></MS></Obj>"#)
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        Process::from_ps_value(&missing).unwrap_err(),
+        PowerShellRemotingError::ConversionError("Process needs the property id".to_string())
+    );
+
+    let too_large =
+        PsValue::from_clixml(r#"<Obj RefId="0"><MS><I32 N="Id">70000</I32></MS></Obj>"#)
+            .unwrap()
+            .remove(0);
+    assert!(matches!(
+        Process::from_ps_value(&too_large),
+        Err(PowerShellRemotingError::ConversionError(reason)) if reason.starts_with("Process.id: Expected u16")
+    ));
+
+    let primitive = PsValue::from_clixml(r#"<S>pwsh</S>"#).unwrap().remove(0);
+    assert!(Process::from_ps_value(&primitive).is_err());
+}
+
+#[test]
+fn test_primitive_coercions() {
+    let values = PsValue::from_clixml(
+        r#"<Objs><S>42</S><I64>7</I64><B>true</B><Db>2.5</Db><G>3fc3d0c4-95e5-4b1b-9a1e-4e5d1d0e7c6a</G><Nil /></Objs>"#,
+    )
+    .unwrap();
+
+    assert_eq!(u8::from_ps_value(&values[0]).unwrap(), 42);
+    assert_eq!(String::from_ps_value(&values[1]).unwrap(), "7");
+    assert_eq!(f64::from_ps_value(&values[1]).unwrap(), 7.0);
+    assert!(bool::from_ps_value(&values[2]).unwrap());
+    assert_eq!(f32::from_ps_value(&values[3]).unwrap(), 2.5);
+    assert!(i32::from_ps_value(&values[3]).is_err());
+    assert_eq!(
+        uuid::Uuid::from_ps_value(&values[4]).unwrap().to_string(),
+        "3fc3d0c4-95e5-4b1b-9a1e-4e5d1d0e7c6a"
+    );
+    assert_eq!(Option::<i32>::from_ps_value(&values[5]).unwrap(), None);
+    assert!(Vec::<i32>::from_ps_value(&values[5]).unwrap().is_empty());
+    assert!(String::from_ps_value(&values[5]).is_err());
+}
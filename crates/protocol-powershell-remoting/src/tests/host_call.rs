@@ -0,0 +1,165 @@
+use uuid::Uuid;
+
+use crate::{
+    ConsoleColor, FromPsValue, HostMethodCall, HostMethodId, MessageType, PipelineHostCall,
+    PipelineHostResponse, PowerShellRemotingMessage, PsErrorRecord, PsValue,
+    RunspacePoolHostResponse, ToPsObject, host_exception,
+};
+
+fn host_call(method_id: i32, method_name: &str, parameters: &str) -> PipelineHostCall {
+    let clixml = format!(
+        r#"<Obj RefId="0">
+          <MS>
+            <I64 N="ci">3</I64>
+            <Obj N="mi" RefId="1">
+              <TN RefId="0"><T>System.Management.Automation.Remoting.RemoteHostMethodId</T><T>System.Enum</T><T>System.ValueType</T><T>System.Object</T></TN>
+              <ToString>{method_name}</ToString>
+              <I32>{method_id}</I32>
+            </Obj>
+            <Obj N="mp" RefId="2">
+              <TN RefId="1"><T>System.Collections.ArrayList</T><T>System.Object</T></TN>
+              <LST>{parameters}</LST>
+            </Obj>
+          </MS>
+        </Obj>"#
+    );
+
+    let PsValue::Object(call) = PsValue::from_clixml(&clixml).unwrap().remove(0) else {
+        panic!("A host call is an object");
+    };
+    PipelineHostCall::try_from(call).unwrap()
+}
+
+#[test]
+fn test_read_line_host_call() {
+    let PipelineHostCall(call) = host_call(11, "ReadLine", "");
+    assert_eq!(call.call_id, 3);
+    assert_eq!(call.method().unwrap(), HostMethodId::ReadLine);
+    assert!(call.method().unwrap().returns_value());
+    assert_eq!(call.decode().unwrap(), HostMethodCall::ReadLine);
+
+    let response = PipelineHostResponse(call.response(Ok("typed".to_ps_value())));
+    let rpid = Uuid::new_v4();
+    let message =
+        PowerShellRemotingMessage::from_ps_message(&response, rpid, Some(Uuid::new_v4())).unwrap();
+    assert_eq!(message.message_type, MessageType::PipelineHostResponse);
+
+    let PsValue::Object(received) = message.parse_ps_message().unwrap() else {
+        panic!("A host response is an object");
+    };
+    let received = RunspacePoolHostResponse::try_from(received).unwrap();
+    assert_eq!(received.call_id, 3);
+    assert_eq!(received.method_id, 11);
+    assert_eq!(received.method_name, "ReadLine");
+    assert_eq!(received.method_result, Some("typed".to_ps_value()));
+    assert_eq!(received.method_exception, None);
+}
+
+#[test]
+fn test_write_host_call_with_colors() {
+    let color = |name: &str, value: i32| {
+        format!(
+            r#"<Obj RefId="{value}"><TN RefId="{value}"><T>System.ConsoleColor</T><T>System.Enum</T><T>System.ValueType</T><T>System.Object</T></TN><ToString>{name}</ToString><I32>{value}</I32></Obj>"#
+        )
+    };
+    let parameters = format!("{}{}<S>done</S>", color("Green", 10), color("Black", 0));
+
+    let PipelineHostCall(call) = host_call(17, "WriteLine3", &parameters);
+    assert!(!call.method().unwrap().returns_value());
+    assert_eq!(
+        call.decode().unwrap(),
+        HostMethodCall::Write {
+            foreground: Some(ConsoleColor::Green),
+            background: Some(ConsoleColor::Black),
+            text: "done".to_string(),
+            new_line: true,
+        }
+    );
+}
+
+#[test]
+fn test_prompt_host_calls() {
+    let fields = r#"<S>Login</S><S>Who are you?</S>
+      <Obj RefId="3">
+        <TN RefId="2"><T>System.Collections.ObjectModel.Collection`1[[System.Management.Automation.Host.FieldDescription]]</T><T>System.Object</T></TN>
+        <LST>
+          <Obj RefId="4">
+            <MS>
+              <S N="name">User</S>
+              <S N="label">&amp;User</S>
+              <S N="parameterTypeName">String</S>
+              <S N="parameterTypeFullName">System.String</S>
+              <S N="helpMessage"></S>
+              <B N="isMandatory">true</B>
+              <Nil N="defaultValue" />
+            </MS>
+          </Obj>
+        </LST>
+      </Obj>"#;
+
+    let PipelineHostCall(call) = host_call(23, "Prompt", fields);
+    let HostMethodCall::Prompt {
+        caption,
+        message,
+        descriptions,
+    } = call.decode().unwrap()
+    else {
+        panic!("Expected a Prompt");
+    };
+    assert_eq!(caption, "Login");
+    assert_eq!(message, "Who are you?");
+    assert_eq!(descriptions.len(), 1);
+    assert_eq!(descriptions[0].name, "User");
+    assert_eq!(descriptions[0].label.as_deref(), Some("&User"));
+    assert_eq!(
+        descriptions[0].parameter_type_full_name.as_deref(),
+        Some("System.String")
+    );
+    assert_eq!(descriptions[0].mandatory, Some(true));
+    assert_eq!(descriptions[0].default_value, None);
+
+    let choices = r#"<Nil /><S>Continue?</S>
+      <Obj RefId="3">
+        <TN RefId="2"><T>System.Collections.ObjectModel.Collection`1[[System.Management.Automation.Host.ChoiceDescription]]</T><T>System.Object</T></TN>
+        <LST>
+          <Obj RefId="4"><MS><S N="label">&amp;Yes</S><S N="helpMessage">Go on</S></MS></Obj>
+          <Obj RefId="5"><MS><S N="label">&amp;No</S><Nil N="helpMessage" /></MS></Obj>
+        </LST>
+      </Obj>
+      <I32>1</I32>"#;
+
+    let PipelineHostCall(call) = host_call(26, "PromptForChoice", choices);
+    let HostMethodCall::PromptForChoice {
+        caption,
+        choices,
+        default_choice,
+        ..
+    } = call.decode().unwrap()
+    else {
+        panic!("Expected a PromptForChoice");
+    };
+    assert_eq!(caption, "");
+    assert_eq!(choices.len(), 2);
+    assert_eq!(choices[0].help_message.as_deref(), Some("Go on"));
+    assert_eq!(choices[1].label, "&No");
+    assert_eq!(default_choice, 1);
+}
+
+#[test]
+fn test_host_exception() {
+    let exception = host_exception("The host does not implement ReadLine");
+    let record = PsErrorRecord::from_ps_value(&exception).unwrap();
+    assert_eq!(record.message, "The host does not implement ReadLine");
+    assert_eq!(
+        record.exception_type.as_deref(),
+        Some("System.Management.Automation.Host.HostException")
+    );
+
+    let PipelineHostCall(call) = host_call(11, "ReadLine", "");
+    let response = call.response(Err(exception.clone()));
+    assert_eq!(response.method_result, None);
+    assert_eq!(response.method_exception, Some(exception));
+
+    let unknown = host_call(99, "Unknown", "").0;
+    assert!(unknown.method().is_err());
+}
@@ -0,0 +1,189 @@
+use uuid::Uuid;
+
+use crate::{
+    BufferCell, BufferCellArray, BufferCellType, ConsoleColor, ControlKeyStates, Coordinates,
+    FromPsValue, HostMethodCall, KeyInfo, PipelineHostCall, PipelineHostResponse,
+    PowerShellRemotingMessage, PsValue, ReadKeyOptions, Rectangle, RunspacePoolHostResponse,
+    ScrollBuffer, ToPsObject,
+};
+
+fn host_call(method_id: i32, method_name: &str, parameters: &str) -> HostMethodCall {
+    let clixml = format!(
+        r#"<Obj RefId="0">
+          <MS>
+            <I64 N="ci">5</I64>
+            <Obj N="mi" RefId="1">
+              <TN RefId="0"><T>System.Management.Automation.Remoting.RemoteHostMethodId</T><T>System.Enum</T><T>System.ValueType</T><T>System.Object</T></TN>
+              <ToString>{method_name}</ToString>
+              <I32>{method_id}</I32>
+            </Obj>
+            <Obj N="mp" RefId="2">
+              <TN RefId="1"><T>System.Collections.ArrayList</T><T>System.Object</T></TN>
+              <LST>{parameters}</LST>
+            </Obj>
+          </MS>
+        </Obj>"#
+    );
+
+    let PsValue::Object(call) = PsValue::from_clixml(&clixml).unwrap().remove(0) else {
+        panic!("A host call is an object");
+    };
+    PipelineHostCall::try_from(call)
+        .unwrap()
+        .0
+        .decode()
+        .unwrap()
+}
+
+fn rectangle(left: i32, top: i32, right: i32, bottom: i32) -> String {
+    format!(
+        r#"<Obj RefId="{left}{top}"><MS><I32 N="left">{left}</I32><I32 N="top">{top}</I32><I32 N="right">{right}</I32><I32 N="bottom">{bottom}</I32></MS></Obj>"#
+    )
+}
+
+const FILL: &str = r#"<Obj RefId="9"><MS><C N="character">32</C><I32 N="foregroundColor">7</I32><I32 N="backgroundColor">0</I32><I32 N="bufferCellType">0</I32></MS></Obj>"#;
+
+fn cell(character: char) -> BufferCell {
+    BufferCell {
+        character,
+        foreground_color: ConsoleColor::Gray,
+        background_color: ConsoleColor::Black,
+        buffer_cell_type: BufferCellType::Complete,
+    }
+}
+
+#[test]
+fn test_raw_ui_setters() {
+    let position = r#"<Obj RefId="3"><MS><I32 N="x">4</I32><I32 N="y">12</I32></MS></Obj>"#;
+    assert_eq!(
+        host_call(32, "SetCursorPosition", position),
+        HostMethodCall::SetCursorPosition(Coordinates { x: 4, y: 12 })
+    );
+
+    assert_eq!(
+        host_call(42, "SetWindowTitle", "<S>Build</S>"),
+        HostMethodCall::SetWindowTitle("Build".to_string())
+    );
+
+    let parameters = format!(
+        "{}<Obj RefId=\"4\"><MS><I32 N=\"x\">0</I32><I32 N=\"y\">0</I32></MS></Obj>{}{FILL}",
+        rectangle(0, 1, 79, 24),
+        rectangle(0, 0, 79, 24)
+    );
+    assert_eq!(
+        host_call(51, "ScrollBufferContents", &parameters),
+        HostMethodCall::ScrollBufferContents(ScrollBuffer {
+            source: Rectangle {
+                left: 0,
+                top: 1,
+                right: 79,
+                bottom: 24,
+            },
+            destination: Coordinates { x: 0, y: 0 },
+            clip: Rectangle {
+                left: 0,
+                top: 0,
+                right: 79,
+                bottom: 24,
+            },
+            fill: cell(' '),
+        })
+    );
+}
+
+#[test]
+fn test_read_key() {
+    assert_eq!(
+        host_call(46, "ReadKey", "<I32>6</I32>"),
+        HostMethodCall::ReadKey(ReadKeyOptions::NO_ECHO | ReadKeyOptions::INCLUDE_KEY_DOWN)
+    );
+
+    let key = KeyInfo {
+        virtual_key_code: 65,
+        character: 'A',
+        control_key_state: ControlKeyStates::SHIFT_PRESSED | ControlKeyStates::NUM_LOCK_ON,
+        key_down: true,
+    };
+    let value = key.to_ps_value();
+    let PsValue::Object(object) = &value else {
+        panic!("A KeyInfo is an object");
+    };
+    assert!(object.type_def.is_none());
+    assert_eq!(object.property("virtualKeyCode"), Some(&65.to_ps_value()));
+    assert_eq!(KeyInfo::from_ps_value(&value).unwrap(), key);
+    assert!(
+        key.control_key_state
+            .contains(ControlKeyStates::SHIFT_PRESSED)
+    );
+    assert!(
+        !key.control_key_state
+            .contains(ControlKeyStates::CAPS_LOCK_ON)
+    );
+}
+
+#[test]
+fn test_buffer_contents() {
+    let contents = format!(
+        r#"<Obj RefId="5"><MS>
+          <Obj N="mae" RefId="6"><TN RefId="2"><T>System.Collections.ArrayList</T><T>System.Object</T></TN><LST>{FILL}{FILL}</LST></Obj>
+          <Obj N="mal" RefId="7"><TN RefId="3"><T>System.Collections.ArrayList</T><T>System.Object</T></TN><LST><I32>1</I32><I32>2</I32></LST></Obj>
+        </MS></Obj>"#
+    );
+    let parameters =
+        format!(r#"<Obj RefId="4"><MS><I32 N="x">2</I32><I32 N="y">3</I32></MS></Obj>{contents}"#);
+    assert_eq!(
+        host_call(49, "SetBufferContents2", &parameters),
+        HostMethodCall::SetBufferContents {
+            origin: Coordinates { x: 2, y: 3 },
+            contents: BufferCellArray {
+                rows: vec![vec![cell(' '), cell(' ')]],
+            },
+        }
+    );
+
+    let PipelineHostCall(call) = {
+        let clixml = format!(
+            r#"<Obj RefId="0"><MS><I64 N="ci">8</I64><Obj N="mi" RefId="1"><I32>50</I32></Obj><Obj N="mp" RefId="2"><LST>{}</LST></Obj></MS></Obj>"#,
+            rectangle(0, 0, 1, 1)
+        );
+        let PsValue::Object(call) = PsValue::from_clixml(&clixml).unwrap().remove(0) else {
+            panic!("A host call is an object");
+        };
+        PipelineHostCall::try_from(call).unwrap()
+    };
+    assert_eq!(
+        call.decode().unwrap(),
+        HostMethodCall::GetBufferContents(Rectangle {
+            left: 0,
+            top: 0,
+            right: 1,
+            bottom: 1,
+        })
+    );
+
+    let screen = BufferCellArray {
+        rows: vec![vec![cell('a'), cell('b')], vec![cell('c'), cell('d')]],
+    };
+    let response = PipelineHostResponse(call.response(Ok(screen.to_ps_value())));
+    let message =
+        PowerShellRemotingMessage::from_ps_message(&response, Uuid::new_v4(), Some(Uuid::new_v4()))
+            .unwrap();
+    let PsValue::Object(received) = message.parse_ps_message().unwrap() else {
+        panic!("A host response is an object");
+    };
+    let received = RunspacePoolHostResponse::try_from(received).unwrap();
+    assert_eq!(
+        BufferCellArray::from_ps_value(&received.method_result.unwrap()).unwrap(),
+        screen
+    );
+
+    let mismatched = BufferCellArray {
+        rows: vec![vec![cell('a')]],
+    }
+    .to_ps_value();
+    let PsValue::Object(mut mismatched) = mismatched else {
+        panic!("A BufferCell[,] is an object");
+    };
+    mismatched.extended_properties.get_mut("mal").unwrap().value = [2, 2].to_ps_value();
+    assert!(BufferCellArray::from_ps_value(&PsValue::Object(mismatched)).is_err());
+}
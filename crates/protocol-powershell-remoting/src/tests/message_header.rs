@@ -0,0 +1,98 @@
+use crate::{
+    Destination, Fragment, MESSAGE_HEADER_LEN, MessageType, PowerShellRemotingError,
+    PowerShellRemotingMessage, PsObjectWithType, UTF8_BOM, messages::SessionCapability,
+};
+use base64::Engine;
+use std::{collections::HashSet, io::Cursor};
+use uuid::Uuid;
+
+fn session_capability() -> SessionCapability {
+    SessionCapability {
+        protocol_version: "2.3".to_string(),
+        ps_version: "2.0".to_string(),
+        serialization_version: "1.1.0.1".to_string(),
+        time_zone: None,
+    }
+}
+
+#[test]
+fn test_message_type_round_trip() {
+    let values: HashSet<_> = MessageType::ALL.iter().map(MessageType::value).collect();
+    assert_eq!(values.len(), MessageType::ALL.len());
+
+    for message_type in MessageType::ALL {
+        assert_eq!(
+            MessageType::try_from(message_type.value()),
+            Ok(message_type)
+        );
+    }
+    assert_eq!(MessageType::SessionCapability.value(), 0x00010002);
+    assert_eq!(MessageType::PipelineOutput.value(), 0x00041004);
+
+    assert!(matches!(
+        MessageType::try_from(0x00041012),
+        Err(PowerShellRemotingError::InvalidMessage(_))
+    ));
+    assert!(Destination::try_from(3).is_err());
+}
+
+#[test]
+fn test_message_header_round_trip() {
+    // The ShellId of the MS-PSRP examples and the bytes of its RPID
+    let rpid = Uuid::parse_str("2D6534D0-6B12-40E3-B773-CBA26459CFA8").unwrap();
+    let pid = Uuid::new_v4();
+    let message = PowerShellRemotingMessage::new(
+        Destination::Server,
+        MessageType::CreatePipeline,
+        rpid,
+        Some(pid),
+        &session_capability().to_ps_object(),
+    )
+    .unwrap();
+    let data = message.data.clone();
+
+    let packed = message.pack();
+    assert_eq!(&packed[..4], &[0x02, 0x00, 0x00, 0x00]);
+    assert_eq!(&packed[4..8], &[0x06, 0x10, 0x02, 0x00]);
+    assert_eq!(
+        &packed[8..24],
+        &[
+            0xD0, 0x34, 0x65, 0x2D, 0x12, 0x6B, 0xE3, 0x40, 0xB7, 0x73, 0xCB, 0xA2, 0x64, 0x59,
+            0xCF, 0xA8
+        ]
+    );
+    assert_eq!(&packed[24..MESSAGE_HEADER_LEN], &pid.to_bytes_le());
+    assert_eq!(&packed[MESSAGE_HEADER_LEN..][..UTF8_BOM.len()], UTF8_BOM);
+
+    let parsed = PowerShellRemotingMessage::parse(&mut Cursor::new(&packed)).unwrap();
+    assert_eq!(parsed.destination, Destination::Server);
+    assert_eq!(parsed.message_type, MessageType::CreatePipeline);
+    assert_eq!(parsed.rpid, rpid);
+    assert_eq!(parsed.pid, Some(pid));
+    assert_eq!(parsed.data, data);
+
+    let without_pid = PowerShellRemotingMessage::from_ps_message(&session_capability(), rpid, None)
+        .unwrap()
+        .pack();
+    let parsed = PowerShellRemotingMessage::parse(&mut Cursor::new(&without_pid)).unwrap();
+    assert_eq!(parsed.pid, None);
+
+    assert!(PowerShellRemotingMessage::parse(&mut Cursor::new(&packed[..30])).is_err());
+}
+
+#[test]
+fn test_message_header_from_server() {
+    let fragment = base64::engine::general_purpose::STANDARD
+        .decode("AAAAAAAAAAEAAAAAAAAAAAMAAADKAQAAAAIAAQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAO+7vzxPYmogUmVmSWQ9IjAiPjxNUz48VmVyc2lvbiBOPSJwcm90b2NvbHZlcnNpb24iPjIuMzwvVmVyc2lvbj48VmVyc2lvbiBOPSJQU1ZlcnNpb24iPjIuMDwvVmVyc2lvbj48VmVyc2lvbiBOPSJTZXJpYWxpemF0aW9uVmVyc2lvbiI+MS4xLjAuMTwvVmVyc2lvbj48L01TPjwvT2JqPg==")
+        .unwrap();
+    let (fragment, rest) = Fragment::unpack(&fragment).unwrap();
+    assert!(rest.is_empty());
+
+    let message = PowerShellRemotingMessage::parse(&mut Cursor::new(&fragment.data)).unwrap();
+    assert_eq!(message.destination, Destination::Client);
+    assert_eq!(message.message_type, MessageType::SessionCapability);
+    assert!(message.rpid.is_nil());
+    assert_eq!(message.pid, None);
+    assert!(message.data.starts_with(b"<Obj RefId=\"0\">"));
+    assert!(message.parse_ps_message().is_ok());
+}
@@ -1,3 +1,20 @@
+pub mod clixml;
+pub mod connect_runspace_pool;
 pub mod creation_xml;
 pub mod creation_xml_roundtrip;
+pub mod dissector;
+pub mod error_record;
 pub mod exact_xml_tests;
+pub mod from_ps_object;
+pub mod host_call;
+pub mod host_ui;
+pub mod message_header;
+pub mod pipeline_input;
+pub mod powershell_builder;
+pub mod primitive_dictionary;
+pub mod ps_credential;
+pub mod runspace_availability;
+pub mod session_capability;
+pub mod session_key;
+pub mod stream_records;
+pub mod user_event;
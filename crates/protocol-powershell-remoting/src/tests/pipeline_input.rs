@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use uuid::Uuid;
+
+use crate::{
+    ComplexObjectContent, Container, DefragmentResult, Defragmenter, EndOfPipelineInput,
+    Fragmenter, FromPsValue, MESSAGE_HEADER_LEN, MessageType, PipelineInput,
+    PowerShellRemotingMessage, PsObjectWithType, PsPrimitiveValue, PsValue, ToPsObject,
+};
+
+#[test]
+fn test_rust_values_to_ps_objects() {
+    assert_eq!(
+        "dir".to_ps_value(),
+        PsValue::Primitive(PsPrimitiveValue::Str("dir".to_string()))
+    );
+    assert_eq!(
+        7u16.to_ps_value(),
+        PsValue::Primitive(PsPrimitiveValue::U16(7))
+    );
+    assert!(None::<i32>.to_ps_value().is_nil());
+
+    let list = vec![1, 2, 3].to_ps_value();
+    let object = list.as_object().unwrap();
+    assert!(object.is_type("System.Object[]"));
+    assert_eq!(Vec::<i32>::from_ps_value(&list).unwrap(), vec![1, 2, 3]);
+
+    let table = BTreeMap::from([("Name", "WinRM")]).to_ps_value();
+    assert!(matches!(
+        &table.as_object().unwrap().content,
+        ComplexObjectContent::Container(Container::Dictionary(entries)) if entries.len() == 1
+    ));
+
+    let custom = PsValue::custom_object([("Name", "WinRM".to_ps_value()), ("Id", 1.to_ps_value())]);
+    let custom = custom.as_object().unwrap();
+    assert!(custom.is_type("System.Management.Automation.PSCustomObject"));
+    assert_eq!(
+        custom.property("Name").and_then(PsValue::as_str),
+        Some("WinRM")
+    );
+}
+
+#[test]
+fn test_pipeline_input_messages() {
+    let rpid = Uuid::new_v4();
+    let pid = Uuid::new_v4();
+    let inputs = ["first", "second"].map(|item| PipelineInput::new(item.to_ps_value()));
+
+    let end = PowerShellRemotingMessage::from_ps_message(&EndOfPipelineInput, rpid, Some(pid))
+        .unwrap()
+        .pack();
+    assert_eq!(
+        end.len(),
+        MESSAGE_HEADER_LEN,
+        "END_OF_PIPELINE_INPUT has no data"
+    );
+
+    let mut messages: Vec<&dyn PsObjectWithType> = inputs
+        .iter()
+        .map(|input| input as &dyn PsObjectWithType)
+        .collect();
+    messages.push(&EndOfPipelineInput);
+
+    let requests = Fragmenter::new(32 * 1024)
+        .fragment_multiple(&messages, rpid, Some(pid))
+        .unwrap();
+    assert_eq!(requests.len(), 1, "The whole input fits in one Send");
+
+    let mut defragmenter = Defragmenter::new();
+    let DefragmentResult::Complete(received) = defragmenter.defragment(&requests[0]).unwrap()
+    else {
+        panic!("The input should be complete");
+    };
+    assert_eq!(received.len(), 3);
+    assert!(received.iter().all(|message| message.pid == Some(pid)));
+
+    assert_eq!(received[0].message_type, MessageType::PipelineInput);
+    assert_eq!(
+        received[1].parse_ps_message().unwrap(),
+        "second".to_ps_value()
+    );
+    assert_eq!(received[2].message_type, MessageType::EndOfPipelineInput);
+    assert!(received[2].data.is_empty());
+}
@@ -0,0 +1,71 @@
+use crate::{PipelineResultTypes, PowerShell, PsPrimitiveValue, PsValue};
+
+#[test]
+fn test_commands_and_parameters() {
+    let pipeline = PowerShell::new()
+        .add_command("Get-Process")
+        .add_parameter("Name", "w3wp")
+        .add_switch("IncludeUserName")
+        .add_command("Select-Object")
+        .add_argument("Id")
+        .build()
+        .unwrap();
+
+    assert!(!pipeline.is_nested);
+    assert_eq!(pipeline.cmds.len(), 2);
+
+    let get_process = &pipeline.cmds[0];
+    assert_eq!(get_process.cmd, "Get-Process");
+    assert!(!get_process.is_script);
+    assert_eq!(get_process.args.len(), 2);
+    assert_eq!(get_process.args[0].name.as_deref(), Some("Name"));
+    assert_eq!(get_process.args[0].value.as_str(), Some("w3wp"));
+    assert_eq!(get_process.args[1].name.as_deref(), Some("IncludeUserName"));
+    assert_eq!(
+        get_process.args[1].value,
+        PsValue::Primitive(PsPrimitiveValue::Bool(true))
+    );
+
+    let select_object = &pipeline.cmds[1];
+    assert_eq!(select_object.args.len(), 1);
+    assert_eq!(select_object.args[0].name, None);
+    assert_eq!(select_object.args[0].value.as_str(), Some("Id"));
+}
+
+#[test]
+fn test_script_scope_and_merges() {
+    let pipeline = PowerShell::new()
+        .add_script("param($Path) Get-Item $Path")
+        .add_parameter("Path", "C:\\")
+        .use_local_scope(true)
+        .merge_error_to_output()
+        .add_command("Out-String")
+        .merge_all_to_output()
+        .build()
+        .unwrap();
+
+    let script = &pipeline.cmds[0];
+    assert!(script.is_script);
+    assert_eq!(script.use_local_scope, Some(true));
+    assert_eq!(script.merge_my_result, PipelineResultTypes::Error);
+    assert_eq!(script.merge_to_result, PipelineResultTypes::Output);
+    assert_eq!(script.merge_error, PipelineResultTypes::Output);
+    assert_eq!(script.merge_warning, PipelineResultTypes::None);
+
+    let out_string = &pipeline.cmds[1];
+    assert_eq!(out_string.use_local_scope, None);
+    assert_eq!(out_string.merge_my_result, PipelineResultTypes::All);
+    assert_eq!(out_string.merge_information, PipelineResultTypes::Output);
+}
+
+#[test]
+fn test_parameter_without_command() {
+    assert!(PowerShell::new().build().is_err());
+    assert!(
+        PowerShell::new()
+            .add_parameter("Name", "w3wp")
+            .add_command("Get-Process")
+            .build()
+            .is_err()
+    );
+}
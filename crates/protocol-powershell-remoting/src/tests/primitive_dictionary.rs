@@ -0,0 +1,140 @@
+use crate::{
+    ApartmentState, FromPsValue, HostInfo, InitRunspacePool, PSThreadOptions, PsObjectWithType,
+    PsPrimitiveDictionary, PsPrimitiveValue, PsValue, ToPsObject,
+};
+
+#[test]
+fn test_primitive_dictionary_keys_ignore_case() {
+    let mut arguments = PsPrimitiveDictionary::new();
+    assert_eq!(arguments.insert("Role", "Operator").unwrap(), None);
+    assert_eq!(arguments.insert("Retries", 3).unwrap(), None);
+
+    let previous = arguments.insert("role", "Admin").unwrap();
+    assert_eq!(
+        previous.as_ref().and_then(PsValue::as_str),
+        Some("Operator")
+    );
+    assert_eq!(arguments.len(), 2);
+    assert_eq!(
+        arguments.get("ROLE").and_then(PsValue::as_str),
+        Some("Admin")
+    );
+
+    assert_eq!(
+        arguments.remove("retries"),
+        Some(PsValue::Primitive(PsPrimitiveValue::I32(3)))
+    );
+    assert_eq!(arguments.len(), 1);
+}
+
+#[test]
+fn test_primitive_dictionary_values() {
+    let mut nested = PsPrimitiveDictionary::new();
+    nested.insert("Depth", 2).unwrap();
+
+    let mut arguments = PsPrimitiveDictionary::new();
+    arguments.insert("Names", vec!["a", "b"]).unwrap();
+    arguments.insert("Nested", &nested).unwrap();
+    arguments.insert("Nothing", None::<i32>).unwrap();
+
+    assert!(
+        arguments
+            .insert(
+                "Script",
+                PsValue::Primitive(PsPrimitiveValue::ScriptBlock("Get-Date".to_string()))
+            )
+            .is_err()
+    );
+    assert!(
+        arguments
+            .insert(
+                "Object",
+                PsValue::custom_object([("Name", "WinRM".to_ps_value())])
+            )
+            .is_err()
+    );
+    assert_eq!(arguments.len(), 3);
+
+    let value = arguments.to_ps_value();
+    assert!(
+        value
+            .as_object()
+            .unwrap()
+            .is_type("System.Management.Automation.PSPrimitiveDictionary")
+    );
+    assert_eq!(
+        PsPrimitiveDictionary::from_ps_value(&value).unwrap(),
+        arguments
+    );
+    assert_eq!(
+        PsPrimitiveDictionary::from_ps_value(arguments.get("Nested").unwrap()).unwrap(),
+        nested
+    );
+}
+
+#[test]
+fn test_application_arguments_of_init_runspace_pool() {
+    let init = |application_arguments| InitRunspacePool {
+        min_runspaces: 1,
+        max_runspaces: 1,
+        thread_options: PSThreadOptions::Default,
+        apartment_state: ApartmentState::Unknown,
+        host_info: HostInfo::builder().build(),
+        application_arguments,
+    };
+
+    let empty = init(PsPrimitiveDictionary::new()).to_ps_object();
+    assert!(
+        empty
+            .as_object()
+            .and_then(|object| object.property("ApplicationArguments"))
+            .is_some_and(PsValue::is_nil)
+    );
+
+    let mut arguments = PsPrimitiveDictionary::new();
+    arguments.insert("Role", "Admin").unwrap();
+    let message = init(arguments.clone()).to_ps_object();
+    let sent = message
+        .as_object()
+        .and_then(|object| object.property("ApplicationArguments"))
+        .unwrap();
+    assert_eq!(
+        PsPrimitiveDictionary::from_ps_value(sent).unwrap(),
+        arguments
+    );
+}
+
+#[test]
+fn test_primitive_dictionary_from_clixml() {
+    let clixml = r#"<Obj RefId="0">
+      <TN RefId="0">
+        <T>System.Management.Automation.PSPrimitiveDictionary</T>
+        <T>System.Collections.Hashtable</T>
+        <T>System.Object</T>
+      </TN>
+      <DCT>
+        <En><S N="Key">Role</S><S N="Value">Admin</S></En>
+        <En><S N="Key">Retries</S><I32 N="Value">3</I32></En>
+      </DCT>
+    </Obj>"#;
+
+    let value = PsValue::from_clixml(clixml).unwrap().remove(0);
+    let arguments = PsPrimitiveDictionary::from_ps_value(&value).unwrap();
+    assert_eq!(
+        arguments.get("role").and_then(PsValue::as_str),
+        Some("Admin")
+    );
+    assert_eq!(
+        arguments
+            .get("Retries")
+            .and_then(|retries| i32::from_ps_value(retries).ok()),
+        Some(3)
+    );
+
+    let nil = PsValue::Primitive(PsPrimitiveValue::Nil);
+    assert!(
+        PsPrimitiveDictionary::from_ps_value(&nil)
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,44 @@
+use crate::{PsCredential, PsPrimitiveValue, PsValue, SessionKey, ToPsObject};
+
+#[test]
+fn test_credential_clixml() {
+    let key = SessionKey::new([3; 32]);
+    let credential = PsCredential::new("CONTOSO\\admin", "P@ssw0rd");
+
+    let PsValue::Object(object) = credential.to_ps_value(&key).unwrap() else {
+        panic!("A PSCredential is an object");
+    };
+    assert!(object.is_type("System.Management.Automation.PSCredential"));
+
+    let xml = object.to_element_as_root().unwrap().to_string();
+    assert!(xml.contains("<T>System.Management.Automation.PSCredential</T>"));
+    assert!(xml.contains("<Props>"));
+    assert!(xml.contains(r#"<S N="UserName">CONTOSO\admin</S>"#));
+    assert!(xml.contains(r#"<SS N="Password">"#));
+    assert!(!xml.contains("P@ssw0rd"));
+
+    let received = PsValue::from_clixml(&xml).unwrap().remove(0);
+    let Some(PsPrimitiveValue::SecureString(_)) = received
+        .as_object()
+        .and_then(|object| object.property("Password"))
+        .and_then(PsValue::as_primitive)
+    else {
+        panic!("The password of a PSCredential is a SecureString");
+    };
+    assert_eq!(
+        PsCredential::from_ps_value(&received, &key).unwrap(),
+        credential
+    );
+    assert!(!format!("{credential:?}").contains("P@ssw0rd"));
+}
+
+#[test]
+fn test_credential_of_another_key() {
+    let credential = PsCredential::new("admin", "secret")
+        .to_ps_value(&SessionKey::new([3; 32]))
+        .unwrap();
+    assert!(PsCredential::from_ps_value(&credential, &SessionKey::new([4; 32])).is_err());
+    assert!(
+        PsCredential::from_ps_value(&"admin".to_ps_value(), &SessionKey::new([3; 32])).is_err()
+    );
+}
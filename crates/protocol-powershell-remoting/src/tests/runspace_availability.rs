@@ -0,0 +1,81 @@
+use crate::{
+    Availability, GetAvailableRunspaces, MessageType, PsObjectWithType, PsPrimitiveValue, PsValue,
+    RunspaceAvailability, SetMaxRunspaces, SetMinRunspaces,
+};
+
+fn primitive<'a>(message: &'a PsValue, name: &str) -> Option<&'a PsPrimitiveValue> {
+    let PsValue::Object(object) = message else {
+        panic!("A pool message is an object");
+    };
+    object.property(name).and_then(PsValue::as_primitive)
+}
+
+fn runspace_availability(response: &str) -> RunspaceAvailability {
+    let clixml = format!(
+        r#"<Obj RefId="0">
+          <MS>
+            {response}
+            <I64 N="ci">7</I64>
+          </MS>
+        </Obj>"#
+    );
+
+    let PsValue::Object(availability) = PsValue::from_clixml(&clixml).unwrap().remove(0) else {
+        panic!("A RUNSPACE_AVAILABILITY is an object");
+    };
+    RunspaceAvailability::try_from(availability).unwrap()
+}
+
+#[test]
+fn test_set_min_max_runspaces() {
+    let max = SetMaxRunspaces {
+        max_runspaces: 5,
+        call_id: 1,
+    };
+    assert_eq!(max.message_type(), MessageType::SetMaxRunspaces);
+    let object = max.to_ps_object();
+    assert_eq!(
+        primitive(&object, "MaxRunspaces"),
+        Some(&PsPrimitiveValue::I32(5))
+    );
+    assert_eq!(primitive(&object, "ci"), Some(&PsPrimitiveValue::I64(1)));
+
+    let min = SetMinRunspaces {
+        min_runspaces: 2,
+        call_id: 2,
+    };
+    assert_eq!(min.message_type(), MessageType::SetMinRunspaces);
+    let object = min.to_ps_object();
+    assert_eq!(
+        primitive(&object, "MinRunspaces"),
+        Some(&PsPrimitiveValue::I32(2))
+    );
+    assert_eq!(primitive(&object, "ci"), Some(&PsPrimitiveValue::I64(2)));
+
+    let available = GetAvailableRunspaces { call_id: 3 };
+    assert_eq!(available.message_type(), MessageType::GetAvailableRunspaces);
+    let object = available.to_ps_object();
+    assert_eq!(primitive(&object, "ci"), Some(&PsPrimitiveValue::I64(3)));
+}
+
+#[test]
+fn test_runspace_availability() {
+    let changed = runspace_availability(r#"<B N="SetMinMaxRunspacesResponse">true</B>"#);
+    assert_eq!(changed.call_id, 7);
+    assert_eq!(changed.availability, Availability::Changed(true));
+
+    let available = runspace_availability(r#"<I64 N="SetMinMaxRunspacesResponse">4</I64>"#);
+    assert_eq!(available.availability, Availability::Available(4));
+}
+
+#[test]
+fn test_runspace_availability_without_response() {
+    let PsValue::Object(availability) =
+        PsValue::from_clixml(r#"<Obj RefId="0"><MS><I64 N="ci">7</I64></MS></Obj>"#)
+            .unwrap()
+            .remove(0)
+    else {
+        panic!("A RUNSPACE_AVAILABILITY is an object");
+    };
+    assert!(RunspaceAvailability::try_from(availability).is_err());
+}
@@ -0,0 +1,126 @@
+use crate::{
+    ComplexObject, PowerShellRemotingError, PsValue,
+    deserialize::{DeserializationContext, PsXmlDeserialize},
+    messages::{ProtocolVersion, SessionCapability},
+};
+
+fn server_capability(
+    protocol_version: &str,
+    ps_version: &str,
+    serialization_version: &str,
+) -> SessionCapability {
+    let xml = format!(
+        r#"<Obj RefId="0"><MS><Version N="protocolversion">{protocol_version}</Version><Version N="PSVersion">{ps_version}</Version><Version N="SerializationVersion">{serialization_version}</Version></MS></Obj>"#
+    );
+    let parsed = xml::parser::parse(&xml).unwrap();
+    let value = PsValue::from_node_with_context(
+        parsed.root_element(),
+        &mut DeserializationContext::default(),
+    )
+    .unwrap();
+    let PsValue::Object(object) = value else {
+        panic!("SessionCapability should be an object");
+    };
+    SessionCapability::try_from(object).unwrap()
+}
+
+#[test]
+fn test_client_session_capability() {
+    let client = SessionCapability::client();
+    assert_eq!(client.protocol_version, SessionCapability::PROTOCOL_VERSION);
+    assert_eq!(client.ps_version, "2.0");
+    assert_eq!(client.serialization_version, "1.1.0.1");
+
+    let object = ComplexObject::from(client.clone());
+    assert_eq!(SessionCapability::try_from(object).unwrap(), client);
+    assert!(client.check_server().is_ok());
+}
+
+#[test]
+fn test_server_session_capability() {
+    let windows_powershell = server_capability("2.3", "2.0", "1.1.0.1");
+    assert_eq!(windows_powershell.protocol_version, "2.3");
+    assert!(windows_powershell.check_server().is_ok());
+
+    // PowerShell 7 and the older protocols of Windows PowerShell 2.0
+    assert!(
+        server_capability("2.3", "7.4", "1.1.0.1")
+            .check_server()
+            .is_ok()
+    );
+    assert!(
+        server_capability("2.1", "2.0", "1.1.0.1")
+            .check_server()
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_unsupported_session_capability() {
+    assert_eq!(
+        server_capability("3.0", "2.0", "1.1.0.1").check_server(),
+        Err(PowerShellRemotingError::UnsupportedVersion {
+            name: "protocolversion",
+            version: "3.0".to_string(),
+            expected: "2.x",
+        })
+    );
+    assert!(matches!(
+        server_capability("2.3", "1.0", "1.1.0.1").check_server(),
+        Err(PowerShellRemotingError::UnsupportedVersion {
+            name: "PSVersion",
+            ..
+        })
+    ));
+    assert!(matches!(
+        server_capability("2.3", "2.0", "1.2.0.0").check_server(),
+        Err(PowerShellRemotingError::UnsupportedVersion {
+            name: "SerializationVersion",
+            ..
+        })
+    ));
+
+    let mut invalid = SessionCapability::client();
+    invalid.protocol_version = "two".to_string();
+    assert!(matches!(
+        invalid.check_server(),
+        Err(PowerShellRemotingError::InvalidMessage(_))
+    ));
+}
+
+#[test]
+fn test_negotiated_protocol_version() {
+    // Windows Server 2008 R2 speaks 2.1, without disconnected sessions
+    let downlevel = server_capability("2.1", "2.0", "1.1.0.1")
+        .negotiate(ProtocolVersion::LATEST)
+        .unwrap();
+    assert_eq!(downlevel, ProtocolVersion::V2_1);
+    assert!(!downlevel.supports_disconnect());
+
+    let current = server_capability("2.3", "5.1", "1.1.0.1")
+        .negotiate(ProtocolVersion::LATEST)
+        .unwrap();
+    assert_eq!(current, ProtocolVersion::V2_3);
+    assert!(current.supports_disconnect());
+    assert!(current.supports_reset_runspace_state());
+
+    // The client speaks the older version it asked for
+    let client = SessionCapability::client_with(ProtocolVersion::V2_2);
+    assert_eq!(client.protocol_version, "2.2");
+    let negotiated = server_capability("2.3", "5.1", "1.1.0.1")
+        .negotiate(ProtocolVersion::V2_2)
+        .unwrap();
+    assert_eq!(negotiated, ProtocolVersion::V2_2);
+    assert!(!negotiated.supports_reset_runspace_state());
+
+    assert_eq!(
+        "2.3.0.0".parse::<ProtocolVersion>().unwrap(),
+        ProtocolVersion::new(2, 3)
+    );
+    assert_eq!(ProtocolVersion::V2_1.to_string(), "2.1");
+    assert_eq!(
+        SessionCapability::PROTOCOL_VERSION,
+        ProtocolVersion::LATEST.to_string()
+    );
+    assert!("2".parse::<ProtocolVersion>().is_err());
+}
@@ -0,0 +1,102 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use openssl::{
+    bn::BigNum,
+    rsa::{Padding, Rsa},
+};
+
+use crate::{
+    ComplexObject, EncryptedSessionKey, KeyExchange, PsObjectWithType, PsPrimitiveValue, PsValue,
+    SessionKey,
+};
+
+/// What the server does with the `PUBLIC_KEY` of the client: encrypt `session_key` with it
+/// into the `SIMPLEBLOB` of an `ENCRYPTED_SESSION_KEY`
+fn encrypt_session_key(public_key: &str, session_key: &[u8; 32]) -> EncryptedSessionKey {
+    let blob = B64.decode(public_key).unwrap();
+    assert_eq!(&blob[..8], &[0x06, 0x02, 0, 0, 0x00, 0xA4, 0, 0]);
+    assert_eq!(&blob[8..12], b"RSA1");
+    assert_eq!(u32::from_le_bytes(blob[12..16].try_into().unwrap()), 2048);
+    let exponent = u32::from_le_bytes(blob[16..20].try_into().unwrap());
+    let mut modulus = blob[20..].to_vec();
+    assert_eq!(modulus.len(), 256);
+    modulus.reverse();
+
+    let rsa = Rsa::from_public_components(
+        BigNum::from_slice(&modulus).unwrap(),
+        BigNum::from_u32(exponent).unwrap(),
+    )
+    .unwrap();
+    let mut encrypted = vec![0; rsa.size() as usize];
+    let length = rsa
+        .public_encrypt(session_key, &mut encrypted, Padding::PKCS1)
+        .unwrap();
+    encrypted.truncate(length);
+    encrypted.reverse();
+
+    let mut simple_blob = vec![0x01, 0x02, 0, 0, 0x10, 0x66, 0, 0, 0x00, 0xA4, 0, 0];
+    simple_blob.extend(encrypted);
+    EncryptedSessionKey {
+        encrypted_session_key: B64.encode(simple_blob),
+    }
+}
+
+#[test]
+fn test_session_key_exchange() {
+    let exchange = KeyExchange::new().unwrap();
+    let public_key = exchange.public_key().unwrap();
+
+    let PsValue::Object(message) = public_key.to_ps_object() else {
+        panic!("PUBLIC_KEY is an object");
+    };
+    assert_eq!(
+        message.property("PublicKey").and_then(PsValue::as_str),
+        Some(public_key.public_key.as_str())
+    );
+
+    let expected = SessionKey::new([7; 32]);
+    let encrypted = encrypt_session_key(&public_key.public_key, &[7; 32]);
+    let session_key = exchange.session_key(&encrypted).unwrap();
+    assert_eq!(session_key, expected);
+
+    let tampered = EncryptedSessionKey {
+        encrypted_session_key: B64.encode([0x01, 0x02, 0, 0, 0x0E, 0x66, 0, 0, 0, 0xA4, 0, 0]),
+    };
+    assert!(exchange.session_key(&tampered).is_err());
+}
+
+#[test]
+fn test_encrypted_session_key_message() {
+    let value = PsValue::from_clixml(
+        r#"<Obj RefId="0"><MS><S N="EncryptedSessionKey">AQIAABBmAAAApAAA</S></MS></Obj>"#,
+    )
+    .unwrap()
+    .remove(0);
+    let PsValue::Object(object) = value else {
+        panic!("ENCRYPTED_SESSION_KEY is an object");
+    };
+    let message = EncryptedSessionKey::try_from(object).unwrap();
+    assert_eq!(message.encrypted_session_key, "AQIAABBmAAAApAAA");
+
+    assert!(EncryptedSessionKey::try_from(ComplexObject::default()).is_err());
+}
+
+#[test]
+fn test_secure_string() {
+    let key = SessionKey::new(*b"0123456789abcdef0123456789abcdef");
+
+    let secure = key.encrypt("P@ssw0rd é").unwrap();
+    let PsValue::Primitive(PsPrimitiveValue::SecureString(encrypted)) = &secure else {
+        panic!("Expected a SecureString");
+    };
+    // 20 bytes of UTF-16 padded to two AES blocks
+    assert_eq!(B64.decode(encrypted).unwrap().len(), 32);
+    assert_eq!(key.decrypt(&secure).unwrap(), "P@ssw0rd é");
+
+    let other = SessionKey::new([1; 32]);
+    assert_ne!(other.decrypt(&secure).ok().as_deref(), Some("P@ssw0rd é"));
+    assert!(
+        key.decrypt(&PsValue::Primitive(PsPrimitiveValue::Str("plain".into())))
+            .is_err()
+    );
+}
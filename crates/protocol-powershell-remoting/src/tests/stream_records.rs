@@ -0,0 +1,109 @@
+use crate::{
+    FromPsValue, InformationRecord, InformationalRecord, ProgressRecord, ProgressRecordType,
+    PsValue,
+};
+
+#[test]
+fn test_informational_record() {
+    // `Write-Verbose "Loading" -Verbose`
+    let clixml = r#"<Obj RefId="0">
+      <TN RefId="0"><T>System.Management.Automation.VerboseRecord</T><T>System.Management.Automation.InformationalRecord</T><T>System.Object</T></TN>
+      <ToString>Loading</ToString>
+      <MS>
+        <S N="InformationalRecord_Message">Loading</S>
+        <B N="InformationalRecord_SerializeInvocationInfo">true</B>
+        <S N="CommandInfo_Name">Write-Verbose</S>
+        <I32 N="InvocationInfo_ScriptLineNumber">3</I32>
+      </MS>
+    </Obj>"#;
+
+    let value = PsValue::from_clixml(clixml).unwrap().remove(0);
+    let record = InformationalRecord::from_ps_value(&value).unwrap();
+    assert_eq!(record.message, "Loading");
+    let invocation_info = record.invocation_info.unwrap();
+    assert_eq!(
+        invocation_info.command_name.as_deref(),
+        Some("Write-Verbose")
+    );
+    assert_eq!(invocation_info.script_line_number, Some(3));
+
+    let clixml = r#"<Obj RefId="0">
+      <ToString>Careful</ToString>
+      <MS><B N="InformationalRecord_SerializeInvocationInfo">false</B></MS>
+    </Obj>"#;
+    let value = PsValue::from_clixml(clixml).unwrap().remove(0);
+    let record = InformationalRecord::from_ps_value(&value).unwrap();
+    assert_eq!(record.to_string(), "Careful");
+    assert_eq!(record.invocation_info, None);
+}
+
+#[test]
+fn test_information_record_of_write_host() {
+    let clixml = r#"<Obj RefId="0">
+      <TN RefId="0"><T>System.Management.Automation.InformationRecord</T><T>System.Object</T></TN>
+      <ToString>hello</ToString>
+      <Props>
+        <Obj N="MessageData" RefId="1">
+          <TN RefId="1"><T>System.Management.Automation.HostInformationMessage</T><T>System.Object</T></TN>
+          <ToString>hello</ToString>
+          <Props>
+            <S N="Message">hello</S>
+            <B N="NoNewLine">false</B>
+          </Props>
+        </Obj>
+        <S N="Source">Write-Host</S>
+        <DT N="TimeGenerated">2024-05-01T10:00:00.1234567+02:00</DT>
+        <Obj N="Tags" RefId="2">
+          <TN RefId="2"><T>System.Collections.Generic.List`1[[System.String]]</T><T>System.Object</T></TN>
+          <LST><S>PSHOST</S></LST>
+        </Obj>
+        <S N="User">CONTOSO\admin</S>
+        <S N="Computer">server</S>
+        <U32 N="ProcessId">4242</U32>
+      </Props>
+    </Obj>"#;
+
+    let value = PsValue::from_clixml(clixml).unwrap().remove(0);
+    let record = InformationRecord::from_ps_value(&value).unwrap();
+    assert_eq!(record.message().as_deref(), Some("hello"));
+    assert_eq!(record.source.as_deref(), Some("Write-Host"));
+    assert_eq!(record.tags, vec!["PSHOST".to_string()]);
+    assert_eq!(record.process_id, Some(4242));
+
+    let clixml = r#"<Obj RefId="0">
+      <Props><S N="MessageData">plain</S></Props>
+    </Obj>"#;
+    let value = PsValue::from_clixml(clixml).unwrap().remove(0);
+    let record = InformationRecord::from_ps_value(&value).unwrap();
+    assert_eq!(record.message().as_deref(), Some("plain"));
+    assert!(record.tags.is_empty());
+}
+
+#[test]
+fn test_progress_record() {
+    let clixml = r#"<Obj RefId="0">
+      <TN RefId="0"><T>System.Management.Automation.ProgressRecord</T><T>System.Object</T></TN>
+      <MS>
+        <S N="Activity">Copying</S>
+        <I32 N="ActivityId">1</I32>
+        <S N="StatusDescription">3 of 4</S>
+        <Nil N="CurrentOperation" />
+        <I32 N="ParentActivityId">-1</I32>
+        <I32 N="PercentComplete">75</I32>
+        <Obj N="Type" RefId="1">
+          <TN RefId="1"><T>System.Management.Automation.ProgressRecordType</T><T>System.Enum</T><T>System.ValueType</T><T>System.Object</T></TN>
+          <ToString>Processing</ToString>
+          <I32>0</I32>
+        </Obj>
+        <I32 N="SecondsRemaining">-1</I32>
+      </MS>
+    </Obj>"#;
+
+    let value = PsValue::from_clixml(clixml).unwrap().remove(0);
+    let record = ProgressRecord::from_ps_value(&value).unwrap();
+    assert_eq!(record.activity, "Copying");
+    assert_eq!(record.status_description.as_deref(), Some("3 of 4"));
+    assert_eq!(record.current_operation, None);
+    assert_eq!(record.percent_complete, Some(75));
+    assert_eq!(record.record_type, Some(ProgressRecordType::Processing));
+}
@@ -0,0 +1,48 @@
+use crate::{FromPsValue, PsValue, UserEvent};
+
+#[test]
+fn test_user_event() {
+    let clixml = r#"<Obj RefId="0">
+      <MS>
+        <I32 N="PSEventArgs.EventIdentifier">1</I32>
+        <S N="PSEventArgs.SourceIdentifier">Build.Progress</S>
+        <DT N="PSEventArgs.TimeGenerated">2009-06-17T10:57:23.1578277-07:00</DT>
+        <Nil N="PSEventArgs.Sender" />
+        <Obj N="PSEventArgs.SourceArgs" RefId="1">
+          <TN RefId="0"><T>System.Object[]</T><T>System.Array</T><T>System.Object</T></TN>
+          <LST><S>step</S><I32>3</I32></LST>
+        </Obj>
+        <S N="PSEventArgs.MessageData">compiled</S>
+        <S N="PSEventArgs.ComputerName">server01</S>
+        <G N="PSEventArgs.RunspaceId">fb9c87e8-1190-40a7-a681-6fc9b9f84a17</G>
+      </MS>
+    </Obj>"#;
+
+    let value = PsValue::from_clixml(clixml).unwrap().remove(0);
+    let user_event = UserEvent::from_ps_value(&value).unwrap();
+    assert_eq!(user_event.event_identifier, 1);
+    assert_eq!(user_event.source_identifier, "Build.Progress");
+    assert_eq!(
+        user_event.time_generated.as_deref(),
+        Some("2009-06-17T10:57:23.1578277-07:00")
+    );
+    assert_eq!(user_event.sender, None);
+    assert_eq!(user_event.source_args.len(), 2);
+    assert_eq!(user_event.source_args[0].as_str(), Some("step"));
+    assert_eq!(
+        user_event.message_data.as_ref().and_then(PsValue::as_str),
+        Some("compiled")
+    );
+    assert_eq!(user_event.computer_name.as_deref(), Some("server01"));
+    assert_eq!(
+        user_event.runspace_id.unwrap().to_string(),
+        "fb9c87e8-1190-40a7-a681-6fc9b9f84a17"
+    );
+}
+
+#[test]
+fn test_user_event_without_source_identifier() {
+    let clixml = r#"<Obj RefId="0"><MS><I32 N="PSEventArgs.EventIdentifier">2</I32></MS></Obj>"#;
+    let value = PsValue::from_clixml(clixml).unwrap().remove(0);
+    assert!(UserEvent::from_ps_value(&value).is_err());
+}
@@ -37,6 +37,11 @@ macro_rules! define_any_tag {
 
 
         impl<'a> $enum_name<'a> {
+            /// Whether `node` can be parsed into a variant, by namespace URI and local name
+            pub fn is_known_node(node: &xml::parser::Node<'_, '_>) -> bool {
+                false $(|| <$tag_name>::matches_node(node))*
+            }
+
             pub fn into_element(self) -> xml::builder::Element<'a> {
                 match self {
                     $($enum_name::$variant(tag) => tag.into_element(),)*
@@ -62,22 +67,19 @@ macro_rules! define_any_tag {
             }
 
             fn visit_node(&mut self, node: xml::parser::Node<'a, 'a>) -> Result<(), xml::XmlError> {
-                match node.tag_name().name() {
-                    $(
-                        <$tag_name>::TAG_NAME => {
-                            let tag = <$tag_type>::from_node(node)?;
-                            self.tag = Some($enum_name::$variant(tag));
-                        }
-                    )*
-                    _ => {
-                        return Err(xml::XmlError::InvalidXml(format!(
-                            "Unknown tag: {}",
-                            node.tag_name().name()
-                        )));
+                $(
+                    if <$tag_name>::matches_node(&node) {
+                        let tag = <$tag_type>::from_node(node)?;
+                        self.tag = Some($enum_name::$variant(tag));
+                        return Ok(());
                     }
-                };
+                )*
 
-                Ok(())
+                Err(xml::XmlError::InvalidXml(format!(
+                    "Unknown tag: {{{}}}{}",
+                    node.tag_name().namespace().unwrap_or_default(),
+                    node.tag_name().name()
+                )))
             }
 
             fn finish(self) -> Result<Self::Value, xml::XmlError> {
@@ -33,6 +33,21 @@ macro_rules! define_attributes {
                 }
             }
 
+            /// Like [`Self::from_name_and_value`], but the attribute must also be in the
+            /// variant's namespace, whatever prefix it was received with
+            fn from_attribute(attribute: &xml::parser::Attribute<'a, 'a>) -> Result<Option<Self>, xml::XmlError> {
+                let namespace = attribute.namespace();
+                match attribute.name() {
+                    $(
+                        $attr_name if crate::cores::namespace::namespaces_match(
+                            namespace,
+                            $namespace.map(|ns: crate::cores::namespace::Namespace| ns.uri()),
+                        ) => Self::from_name_and_value($attr_name, attribute.value()),
+                    )*
+                    _ => Ok(None),
+                }
+            }
+
             /// Get the attribute name for this enum variant
             /// This is automatically generated to be exhaustive
             pub fn attribute_name(&self) -> &'static str {
@@ -94,6 +109,8 @@ define_attributes!(
     Unit(Cow<'a, str>) => (None, "Unit"), |v: &str| -> Result<Cow<'a, str>, String> { Ok(Cow::Owned(v.to_string())) },
     EndUnit(bool) => (None, "EndUnit"), |v: &str| v.parse::<bool>().map_err(|e| e.to_string()),
     SequenceID(u64) => (None, "SequenceID"), |v: &str| v.parse::<u64>().map_err(|e| e.to_string()),
+    Dialect(Cow<'a, str>) => (None, "Dialect"), |v: &str| -> Result<Cow<'a, str>, String> { Ok(Cow::Owned(v.to_string())) },
+    Type(Cow<'a, str>) => (None, "Type"), |v: &str| -> Result<Cow<'a, str>, String> { Ok(Cow::Owned(v.to_string())) },
     // Add new attributes here and they automatically get handled everywhere!
 );
 
@@ -122,9 +139,7 @@ impl<'a> xml::parser::XmlVisitor<'a> for AttributeVisitor<'a> {
             );
 
             // Try to parse this attribute using our compile-time safe method
-            if let Some(parsed_attr) =
-                Attribute::from_name_and_value(attribute.name(), attribute.value())?
-            {
+            if let Some(parsed_attr) = Attribute::from_attribute(&attribute)? {
                 self.attribute = Some(parsed_attr);
                 tracing::trace!("Successfully parsed attribute: {:?}", self.attribute);
                 return Ok(()); // Take the first recognized attribute
@@ -0,0 +1,245 @@
+use tracing::{debug, trace};
+use xml::{
+    builder::Element,
+    parser::{Node, XmlDeserialize, XmlVisitor},
+};
+
+use crate::cores::{
+    Attribute, Namespace, NamespaceDeclaration, TagName, TagValue, namespaces_match,
+    tag::build_element,
+};
+
+/// Name and namespace of an element known only at runtime, e.g. a WMI class instance like `p:Win32_Process`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DynamicTagName<'a> {
+    pub name: &'a str,
+    pub namespace: Option<&'a str>,
+}
+
+impl<'a> DynamicTagName<'a> {
+    pub fn new(name: &'a str, namespace: Option<&'a str>) -> Self {
+        Self { name, namespace }
+    }
+
+    /// The runtime equivalent of a static [`TagName`]
+    pub fn of<N: TagName>() -> Self {
+        Self {
+            name: N::TAG_NAME,
+            namespace: N::NAMESPACE,
+        }
+    }
+
+    pub fn from_node(node: Node<'a, 'a>) -> Self {
+        Self {
+            name: node.tag_name().name(),
+            namespace: node.tag_name().namespace(),
+        }
+    }
+
+    pub fn matches(&self, node: Node<'_, '_>) -> bool {
+        node.is_element()
+            && node.tag_name().name() == self.name
+            && namespaces_match(node.tag_name().namespace(), self.namespace)
+    }
+}
+
+/// Same as [`super::Tag`] but the name is a runtime value instead of a [`TagName`] type
+#[derive(Debug, Clone)]
+pub struct DynTag<'a, V>
+where
+    V: TagValue<'a>,
+{
+    pub name: DynamicTagName<'a>,
+    pub value: V,
+    pub attributes: Vec<Attribute<'a>>,
+    pub namespaces_declaration: NamespaceDeclaration,
+}
+
+impl<'a, V> DynTag<'a, V>
+where
+    V: TagValue<'a>,
+{
+    pub fn new(name: DynamicTagName<'a>, value: impl Into<V>) -> Self {
+        Self {
+            name,
+            value: value.into(),
+            attributes: Vec::new(),
+            namespaces_declaration: NamespaceDeclaration::new(),
+        }
+    }
+
+    pub fn with_attribute(mut self, attribute: Attribute<'a>) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn with_declaration(mut self, declaration: Namespace) -> Self {
+        self.namespaces_declaration.push(declaration);
+        self
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.name.name
+    }
+
+    pub fn namespace(&self) -> Option<&'a str> {
+        self.name.namespace
+    }
+
+    pub fn into_element(self) -> Element<'a> {
+        let element = build_element(
+            self.name.name,
+            self.name.namespace,
+            &self.namespaces_declaration,
+            self.attributes,
+        );
+
+        self.value.append_to_element(element)
+    }
+
+    /// Parse `node`, failing if it doesn't have the expected name
+    pub fn from_node_named(
+        node: Node<'a, 'a>,
+        name: DynamicTagName<'a>,
+    ) -> Result<Self, xml::XmlError>
+    where
+        V: XmlDeserialize<'a>,
+    {
+        if !name.matches(node) {
+            return Err(xml::XmlError::XmlInvalidTag {
+                expected: name.name.to_string(),
+                found: node.tag_name().name().to_string(),
+            });
+        }
+
+        Self::from_node(node)
+    }
+}
+
+impl<'a, V> From<DynTag<'a, V>> for Element<'a>
+where
+    V: TagValue<'a>,
+{
+    fn from(val: DynTag<'a, V>) -> Self {
+        val.into_element()
+    }
+}
+
+impl<'a, V> TagValue<'a> for DynTag<'a, V>
+where
+    V: TagValue<'a>,
+{
+    fn append_to_element(self, element: Element<'a>) -> Element<'a> {
+        element.add_child(self.into_element())
+    }
+}
+
+/// Takes the name from the visited node, any element is accepted
+pub struct DynTagVisitor<'a, V>
+where
+    V: TagValue<'a>,
+{
+    tag: Option<DynTag<'a, V>>,
+}
+
+impl<'a, V> XmlVisitor<'a> for DynTagVisitor<'a, V>
+where
+    V: TagValue<'a> + XmlDeserialize<'a>,
+{
+    type Value = DynTag<'a, V>;
+
+    fn visit_node(&mut self, node: Node<'a, 'a>) -> Result<(), xml::XmlError> {
+        if !node.is_element() {
+            return Err(xml::XmlError::InvalidXml(
+                "Expected an element for a dynamic tag".to_string(),
+            ));
+        }
+
+        let name = DynamicTagName::from_node(node);
+        trace!(?name, "DynTagVisitor visiting node");
+
+        let value = V::from_children(node.children().filter(|c| c.is_element() || c.is_text()))?;
+        let mut tag = DynTag::new(name, value);
+
+        if let Ok(attribute) = Attribute::from_node(node) {
+            tag.attributes.push(attribute);
+        } else {
+            debug!(name = name.name, "No known attribute on dynamic tag");
+        }
+
+        tag.namespaces_declaration = NamespaceDeclaration::from_node(node)?;
+        self.tag = Some(tag);
+
+        Ok(())
+    }
+
+    fn visit_children(
+        &mut self,
+        mut children: impl Iterator<Item = Node<'a, 'a>>,
+    ) -> Result<(), xml::XmlError> {
+        match children.find(|child| child.is_element()) {
+            Some(child) => self.visit_node(child),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(self) -> Result<Self::Value, xml::XmlError> {
+        self.tag.ok_or_else(|| {
+            xml::XmlError::InvalidXml("No element found for dynamic tag".to_string())
+        })
+    }
+}
+
+impl<'a, V> XmlDeserialize<'a> for DynTag<'a, V>
+where
+    V: TagValue<'a> + XmlDeserialize<'a>,
+{
+    type Visitor = DynTagVisitor<'a, V>;
+
+    fn visitor() -> Self::Visitor {
+        DynTagVisitor { tag: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cores::{ShellId, Text};
+
+    const WMI_NAMESPACE: &str =
+        "http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Process";
+
+    #[test]
+    fn test_dyn_tag_round_trip() {
+        let name = DynamicTagName::new("Name", Some(WMI_NAMESPACE));
+        let element = Element::new("Win32_Process")
+            .set_namespace(WMI_NAMESPACE)
+            .add_namespace_declaration(WMI_NAMESPACE, Some("p"))
+            .add_child(DynTag::<Text>::new(name, "notepad.exe").into_element());
+
+        let xml = element.to_string();
+        assert_eq!(
+            xml,
+            r#"<p:Win32_Process xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Process"><p:Name>notepad.exe</p:Name></p:Win32_Process>"#
+        );
+
+        let doc = xml::parser::parse(&xml).unwrap();
+        let process = doc.root_element();
+        let child = process.first_element_child().unwrap();
+
+        let tag: DynTag<Text> = DynTag::from_node_named(child, name).unwrap();
+        assert_eq!(tag.name(), "Name");
+        assert_eq!(tag.namespace(), Some(WMI_NAMESPACE));
+        assert_eq!(tag.value.as_ref(), "notepad.exe");
+
+        let other = DynamicTagName::new("Handle", Some(WMI_NAMESPACE));
+        assert!(DynTag::<Text>::from_node_named(child, other).is_err());
+    }
+
+    #[test]
+    fn test_dynamic_name_of_static_tag() {
+        let name = DynamicTagName::of::<ShellId>();
+        assert_eq!(name.name, "ShellId");
+        assert_eq!(name.namespace, Some(Namespace::WsmanShell.uri()));
+    }
+}
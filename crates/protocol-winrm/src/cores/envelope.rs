@@ -0,0 +1,389 @@
+use xml::{
+    builder::Element,
+    parser::{XmlDeserialize, XmlVisitor},
+};
+
+use crate::{
+    cores::{
+        Attribute, Body, Empty, Header, KnownTagNames, Namespace, NamespaceDeclaration, Tag,
+        TagName, TagValue, Text, UnknownTagPolicy, WsUuid,
+    },
+    rsp::compression::{StreamCompression, compression_type},
+    soap::{CustomHeaders, SoapHeader, SoapVersion, header::SoapHeaders},
+    ws_addressing::{self, AddressValue},
+    ws_management::{
+        OptionSetValue, SelectorSetValue, body::ReferenceParametersValue,
+        fragment::fragment_transfer,
+    },
+};
+
+/// A whole SOAP envelope, generic over the header and body values.
+///
+/// Operations build their request with [`Envelope::builder`] and parse the response
+/// with [`XmlDeserialize::from_node`] on the `s:Envelope` node, using their own body type.
+#[derive(Debug, Clone)]
+pub struct Envelope<'a, H, B>
+where
+    H: TagValue<'a>,
+    B: TagValue<'a>,
+{
+    pub header: Tag<'a, H, Header>,
+    pub body: Tag<'a, B, Body>,
+    /// Headers that are not part of the header value, see [`SoapHeader`]
+    pub custom_headers: CustomHeaders<'a>,
+    /// Namespaces declared on the `s:Envelope` element
+    pub namespaces_declaration: NamespaceDeclaration,
+    /// The envelope is always built against SOAP 1.2 and moved to this version on serialization
+    pub soap_version: SoapVersion,
+}
+
+impl<'a, H, B> Envelope<'a, H, B>
+where
+    H: TagValue<'a>,
+    B: TagValue<'a>,
+{
+    pub fn new(header: impl Into<Tag<'a, H, Header>>, body: impl Into<Tag<'a, B, Body>>) -> Self {
+        Self {
+            header: header.into(),
+            body: body.into(),
+            custom_headers: CustomHeaders::new(),
+            namespaces_declaration: NamespaceDeclaration::new(),
+            soap_version: SoapVersion::default(),
+        }
+    }
+
+    pub fn with_declaration(mut self, declaration: Namespace) -> Self {
+        self.namespaces_declaration.push(declaration);
+        self
+    }
+
+    pub fn into_element(self) -> Element<'a> {
+        let mut element = Element::new(crate::cores::Envelope::TAG_NAME)
+            .set_namespace_optional(crate::cores::Envelope::NAMESPACE);
+
+        for namespace in self.namespaces_declaration.namespaces() {
+            let (url, alias) = namespace.as_tuple();
+            element = element.add_namespace_declaration(url, alias);
+        }
+
+        let header = self
+            .header
+            .into_element()
+            .add_children(self.custom_headers.into_elements());
+
+        let element = element
+            .add_child(header)
+            .add_child(self.body.into_element());
+
+        self.soap_version.apply(element)
+    }
+
+    /// Approximate size of the serialized envelope, see [`Element::estimated_size`]
+    pub fn estimated_size(&self) -> usize
+    where
+        H: Clone,
+        B: Clone,
+    {
+        self.clone().into_element().estimated_size()
+    }
+
+    /// How many bytes can still be added to the envelope as base64 text, e.g. a `rsp:Stream`,
+    /// without exceeding `max_envelope_size`
+    pub fn base64_capacity(&self, max_envelope_size: usize) -> usize
+    where
+        H: Clone,
+        B: Clone,
+    {
+        xml::builder::base64_capacity(max_envelope_size.saturating_sub(self.estimated_size()))
+    }
+}
+
+impl<'a> Envelope<'a, SoapHeaders<'a>, Empty> {
+    /// Start building a request envelope with the standard WS-Management headers.
+    /// The body is empty until [`EnvelopeBuilder::body`] is called.
+    pub fn builder() -> EnvelopeBuilder<'a, Empty> {
+        EnvelopeBuilder {
+            header: SoapHeaders::builder().build(),
+            body: Empty,
+            custom_headers: CustomHeaders::new(),
+            soap_version: SoapVersion::default(),
+            namespaces_declaration: vec![
+                Namespace::SoapEnvelope2003,
+                Namespace::WsAddressing2004,
+                Namespace::DmtfWsmanSchema,
+                Namespace::MsWsmanSchema,
+                Namespace::WsmanIdentity,
+            ],
+        }
+    }
+}
+
+impl<'a, H, B> From<Envelope<'a, H, B>> for Element<'a>
+where
+    H: TagValue<'a>,
+    B: TagValue<'a>,
+{
+    fn from(val: Envelope<'a, H, B>) -> Self {
+        val.into_element()
+    }
+}
+
+/// Fluent builder for [`Envelope`], see [`Envelope::builder`]
+#[derive(Debug, Clone)]
+pub struct EnvelopeBuilder<'a, B> {
+    header: SoapHeaders<'a>,
+    body: B,
+    custom_headers: CustomHeaders<'a>,
+    soap_version: SoapVersion,
+    namespaces_declaration: Vec<Namespace>,
+}
+
+impl<'a, B> EnvelopeBuilder<'a, B>
+where
+    B: TagValue<'a>,
+{
+    pub fn to(mut self, to: impl Into<Text<'a>>) -> Self {
+        self.header.to = Some(Tag::new(to.into()));
+        self
+    }
+
+    pub fn action(mut self, action: impl Into<Text<'a>>) -> Self {
+        self.header.action =
+            Some(Tag::new(action.into()).with_attribute(Attribute::MustUnderstand(true)));
+        self
+    }
+
+    pub fn resource_uri(mut self, resource_uri: impl Into<Text<'a>>) -> Self {
+        self.header.resource_uri =
+            Some(Tag::new(resource_uri.into()).with_attribute(Attribute::MustUnderstand(true)));
+        self
+    }
+
+    pub fn reply_to(mut self, address: AddressValue<'a>) -> Self {
+        self.header.reply_to = Some(ws_addressing::reply_to(address));
+        self
+    }
+
+    /// Route faults to another endpoint than [`EnvelopeBuilder::reply_to`]
+    pub fn fault_to(mut self, address: AddressValue<'a>) -> Self {
+        self.header.fault_to = Some(ws_addressing::fault_to(address));
+        self
+    }
+
+    pub fn from(mut self, address: AddressValue<'a>) -> Self {
+        self.header.from = Some(ws_addressing::from(address));
+        self
+    }
+
+    pub fn message_id(mut self, message_id: uuid::Uuid) -> Self {
+        self.header.message_id = Some(Tag::new(WsUuid(message_id)));
+        self
+    }
+
+    /// Correlates all envelopes of a client session (MS-WSMV `p:SessionId`)
+    pub fn session_id(mut self, session_id: uuid::Uuid) -> Self {
+        self.header.session_id =
+            Some(Tag::new(WsUuid(session_id)).with_attribute(Attribute::MustUnderstand(false)));
+        self
+    }
+
+    pub fn operation_id(mut self, operation_id: uuid::Uuid) -> Self {
+        self.header.operation_id =
+            Some(Tag::new(WsUuid(operation_id)).with_attribute(Attribute::MustUnderstand(false)));
+        self
+    }
+
+    pub fn sequence_id(mut self, sequence_id: u64) -> Self {
+        self.header.sequence_id = Some(
+            Tag::new(Text::from(sequence_id.to_string()))
+                .with_attribute(Attribute::MustUnderstand(false)),
+        );
+        self
+    }
+
+    pub fn options(mut self, options: OptionSetValue) -> Self {
+        self.header.option_set = Some(
+            Tag::new(options)
+                .with_declaration(Namespace::XmlSchemaInstance)
+                .with_attribute(Attribute::MustUnderstand(true)),
+        );
+        self
+    }
+
+    pub fn selectors(mut self, selectors: SelectorSetValue) -> Self {
+        self.header.selector_set = Some(Tag::new(selectors));
+        self
+    }
+
+    /// Address the endpoint of a received EPR, all its reference parameters are echoed as headers
+    pub fn reference_parameters(mut self, parameters: &'a ReferenceParametersValue<'_>) -> Self {
+        self.header.resource_uri = Some(
+            Tag::new(Text::from(
+                parameters.resource_uri.value.as_ref().to_owned(),
+            ))
+            .with_attribute(Attribute::MustUnderstand(true)),
+        );
+        self.header.selector_set = Some(Tag::new(parameters.selector_set.value.clone()));
+        parameters.echo(&mut self.custom_headers);
+        self
+    }
+
+    /// Select a fragment of the resource, see [`fragment_transfer`]
+    pub fn fragment(mut self, expression: impl Into<Text<'a>>) -> Self {
+        self.header.fragment_transfer = Some(fragment_transfer(expression));
+        self
+    }
+
+    /// Advertise a stream compression on shell creation, see [`compression_type`]
+    pub fn compression(mut self, compression: StreamCompression) -> Self {
+        self.header.compression_type = Some(compression_type(compression));
+        self
+    }
+
+    /// Gives access to the remaining headers that have no dedicated setter
+    pub fn headers(mut self, f: impl FnOnce(&mut SoapHeaders<'a>)) -> Self {
+        f(&mut self.header);
+        self
+    }
+
+    pub fn custom_header<C: SoapHeader<'a>>(mut self, header: &C) -> Self {
+        self.custom_headers.push(header);
+        self
+    }
+
+    /// Send the envelope as SOAP 1.1 instead of SOAP 1.2
+    pub fn soap_version(mut self, soap_version: SoapVersion) -> Self {
+        self.soap_version = soap_version;
+        self
+    }
+
+    /// Declare an additional namespace on the envelope, needed when the body uses it
+    pub fn with_declaration(mut self, declaration: Namespace) -> Self {
+        if !self.namespaces_declaration.contains(&declaration) {
+            self.namespaces_declaration.push(declaration);
+        }
+        self
+    }
+
+    pub fn body<T: TagValue<'a>>(self, body: T) -> EnvelopeBuilder<'a, T> {
+        EnvelopeBuilder {
+            header: self.header,
+            body,
+            custom_headers: self.custom_headers,
+            soap_version: self.soap_version,
+            namespaces_declaration: self.namespaces_declaration,
+        }
+    }
+
+    pub fn build(self) -> Envelope<'a, SoapHeaders<'a>, B> {
+        let mut envelope = Envelope::new(self.header, self.body);
+        envelope.custom_headers = self.custom_headers;
+        envelope.soap_version = self.soap_version;
+        for declaration in self.namespaces_declaration {
+            envelope = envelope.with_declaration(declaration);
+        }
+        envelope
+    }
+}
+
+/// Parse a `s:Header` node, known children go into `H` and the rest into `custom_headers`
+pub(crate) fn parse_header<'a, H>(
+    node: xml::parser::Node<'a, 'a>,
+    custom_headers: &mut CustomHeaders<'a>,
+) -> Result<Tag<'a, H, Header>, xml::XmlError>
+where
+    H: TagValue<'a> + XmlDeserialize<'a> + KnownTagNames,
+{
+    let (known, custom): (Vec<_>, Vec<_>) = node
+        .children()
+        .filter(|child| child.is_element())
+        .partition(|child| H::is_known_node(child));
+
+    for child in custom {
+        custom_headers.push_received(child);
+    }
+
+    let mut header = Tag::new(H::from_children(known.into_iter())?);
+    header.namespaces_declaration = NamespaceDeclaration::from_node(node)?;
+    if let Ok(attribute) = Attribute::from_node(node) {
+        header.attributes.push(attribute);
+    }
+
+    Ok(header)
+}
+
+pub struct EnvelopeVisitor<'a, H, B>
+where
+    H: TagValue<'a>,
+    B: TagValue<'a>,
+{
+    header: Option<Tag<'a, H, Header>>,
+    body: Option<Tag<'a, B, Body>>,
+    custom_headers: CustomHeaders<'a>,
+    namespaces_declaration: NamespaceDeclaration,
+    soap_version: SoapVersion,
+}
+
+impl<'a, H, B> XmlVisitor<'a> for EnvelopeVisitor<'a, H, B>
+where
+    H: TagValue<'a> + XmlDeserialize<'a> + KnownTagNames + 'a,
+    B: TagValue<'a> + XmlDeserialize<'a> + 'a,
+{
+    type Value = Envelope<'a, H, B>;
+
+    fn visit_node(&mut self, node: xml::parser::Node<'a, 'a>) -> Result<(), xml::XmlError> {
+        if !crate::cores::Envelope::matches_node(&node) {
+            return Err(xml::XmlError::XmlInvalidTag {
+                expected: crate::cores::Envelope::TAG_NAME.to_string(),
+                found: node.tag_name().name().to_string(),
+            });
+        }
+
+        self.namespaces_declaration = NamespaceDeclaration::from_node(node)?;
+        self.soap_version = SoapVersion::from_node(node);
+
+        for child in node.children().filter(|child| child.is_element()) {
+            if Header::matches_node(&child) {
+                self.header = Some(parse_header(child, &mut self.custom_headers)?);
+            } else if Body::matches_node(&child) {
+                self.body = Some(Tag::from_node(child)?);
+            } else {
+                UnknownTagPolicy::handle(crate::cores::Envelope::TAG_NAME, child)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Value, xml::XmlError> {
+        Ok(Envelope {
+            header: self
+                .header
+                .ok_or_else(|| xml::XmlError::InvalidXml("Missing Soap Header".to_string()))?,
+            body: self
+                .body
+                .ok_or_else(|| xml::XmlError::InvalidXml("Missing Soap Body".to_string()))?,
+            custom_headers: self.custom_headers,
+            namespaces_declaration: self.namespaces_declaration,
+            soap_version: self.soap_version,
+        })
+    }
+}
+
+impl<'a, H, B> XmlDeserialize<'a> for Envelope<'a, H, B>
+where
+    H: TagValue<'a> + XmlDeserialize<'a> + KnownTagNames + 'a,
+    B: TagValue<'a> + XmlDeserialize<'a> + 'a,
+{
+    type Visitor = EnvelopeVisitor<'a, H, B>;
+
+    fn visitor() -> Self::Visitor {
+        EnvelopeVisitor {
+            header: None,
+            body: None,
+            custom_headers: CustomHeaders::new(),
+            namespaces_declaration: NamespaceDeclaration::new(),
+            soap_version: SoapVersion::default(),
+        }
+    }
+}
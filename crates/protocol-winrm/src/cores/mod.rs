@@ -1,14 +1,23 @@
 pub mod anytag;
 pub mod attribute;
+pub mod dyn_tag;
+pub mod envelope;
 pub mod namespace;
+pub mod owned_element;
+pub mod qname;
 pub mod tag;
 pub mod tag_list;
 pub mod tag_name;
 pub mod tag_value;
+pub mod unknown_tag;
 
 pub use attribute::*;
+pub use dyn_tag::*;
 pub use namespace::*;
+pub use owned_element::*;
+pub use qname::*;
 pub use tag::*;
 pub use tag_list::*;
 pub use tag_name::*;
 pub use tag_value::*;
+pub use unknown_tag::*;
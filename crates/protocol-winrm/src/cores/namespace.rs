@@ -90,13 +90,57 @@ define_namespaces! {
     WsmanShell        => { alias: Some("rsp") , uri: "http://schemas.microsoft.com/wbem/wsman/1/windows/shell" },
     WsAddressing2004  => { alias: Some("a")   , uri: "http://schemas.xmlsoap.org/ws/2004/08/addressing" },
     SoapEnvelope2003  => { alias: Some("s")   , uri: "http://www.w3.org/2003/05/soap-envelope" },
+    SoapEnvelope2000  => { alias: Some("s")   , uri: "http://schemas.xmlsoap.org/soap/envelope/" },
     MsWsmanSchema     => { alias: Some("p")   , uri: "http://schemas.microsoft.com/wbem/wsman/1/wsman.xsd" },
     DmtfWsmanSchema   => { alias: Some("w")   , uri: "http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" },
     WsTransfer2004    => { alias: Some("x")   , uri: "http://schemas.xmlsoap.org/ws/2004/09/transfer" },
+    WsEnumeration2004 => { alias: Some("n")   , uri: "http://schemas.xmlsoap.org/ws/2004/09/enumeration" },
+    WsmanCimBinding   => { alias: Some("b")   , uri: "http://schemas.dmtf.org/wbem/wsman/1/cimbinding.xsd" },
+    WsEventing2004    => { alias: Some("e")   , uri: "http://schemas.xmlsoap.org/ws/2004/08/eventing" },
+    WsmanIdentity     => { alias: Some("wsmid"), uri: "http://schemas.dmtf.org/wbem/wsman/identity/1/wsmanidentity.xsd" },
+    WsmanFault        => { alias: Some("f")   , uri: "http://schemas.microsoft.com/wbem/wsman/1/wsmanfault" },
     PowerShellRemoting=> { alias: None        , uri: "http://schemas.microsoft.com/powershell" },
     XmlSchemaInstance => { alias: Some("xsi") , uri: "http://www.w3.org/2001/XMLSchema-instance" },
 }
 
+// -----------------------------------------------------------------------------
+//                           EQUIVALENT NAMESPACES
+// -----------------------------------------------------------------------------
+/// Namespaces servers use interchangeably for the same elements, e.g. Windows sends some
+/// DMTF WS-Management elements in the Microsoft namespace. The first URI of a group stands
+/// for the whole group.
+///
+/// Both SOAP envelope namespaces are grouped too: the SOAP elements are defined against
+/// SOAP 1.2 and a SOAP 1.1 envelope only differs by its namespace, see
+/// [`SoapVersion`](crate::soap::SoapVersion).
+pub const EQUIVALENT_NAMESPACES: &[&[&str]] = &[
+    &[
+        Namespace::DmtfWsmanSchema.uri(),
+        Namespace::MsWsmanSchema.uri(),
+    ],
+    &[
+        Namespace::SoapEnvelope2003.uri(),
+        Namespace::SoapEnvelope2000.uri(),
+    ],
+];
+
+/// The namespace `uri` is matched as, see [`EQUIVALENT_NAMESPACES`]
+pub fn logical_namespace(uri: &str) -> &str {
+    EQUIVALENT_NAMESPACES
+        .iter()
+        .find(|group| group.contains(&uri))
+        .map_or(uri, |group| group[0])
+}
+
+/// Whether a received namespace is the expected one, up to [`EQUIVALENT_NAMESPACES`]
+pub fn namespaces_match(found: Option<&str>, expected: Option<&str>) -> bool {
+    match (found, expected) {
+        (Some(found), Some(expected)) => logical_namespace(found) == logical_namespace(expected),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
 // -----------------------------------------------------------------------------
 //                   OPTIONAL GROUPING / DECLARATION TYPES
 // -----------------------------------------------------------------------------
@@ -143,10 +187,11 @@ impl<'a> xml::parser::XmlVisitor<'a> for NamespaceDeclarationVisitor {
 
     fn visit_node(&mut self, node: xml::parser::Node<'a, 'a>) -> Result<(), xml::XmlError> {
         for ns in node.namespaces() {
-            self.namespaces
-                .push(Namespace::try_from(ns).map_err(|_| {
-                    xml::XmlError::InvalidXml(format!("Unknown namespace: {ns:?}"))
-                })?);
+            // Vendor namespaces (e.g. for custom headers) are in scope too, they are just not ours
+            match Namespace::try_from(ns) {
+                Ok(namespace) => self.namespaces.push(namespace),
+                Err(_) => tracing::debug!(?ns, "Skipping unknown namespace declaration"),
+            }
         }
         Ok(())
     }
@@ -0,0 +1,159 @@
+use xml::{
+    builder::{Attribute, Element},
+    parser::Node,
+};
+
+use crate::cores::{TagName, namespaces_match};
+
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// An attribute of an [`OwnedElement`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedAttribute {
+    pub namespace: Option<String>,
+    pub prefix: Option<String>,
+    pub name: String,
+    pub value: String,
+}
+
+/// A received element copied out of the document, so it can outlive the response it came from.
+///
+/// Opaque content, such as the unknown reference parameters of an endpoint reference, is kept
+/// this way and rebuilt with [`OwnedElement::to_element`] when it has to be sent back.
+/// Mixed content is not supported, the text is dropped when the element has children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedElement {
+    pub namespace: Option<String>,
+    /// Prefix the element was received with, `None` for the default namespace
+    pub prefix: Option<String>,
+    pub name: String,
+    pub attributes: Vec<OwnedAttribute>,
+    pub children: Vec<OwnedElement>,
+    pub text: Option<String>,
+}
+
+impl OwnedElement {
+    pub fn from_node(node: Node<'_, '_>) -> Self {
+        let namespace = node.tag_name().namespace();
+
+        let attributes = node
+            .attributes()
+            .map(|attribute| match attribute.namespace() {
+                // The xml prefix is bound by definition and must not be declared again
+                Some(XML_NAMESPACE) => OwnedAttribute {
+                    namespace: None,
+                    prefix: None,
+                    name: format!("xml:{}", attribute.name()),
+                    value: attribute.value().to_owned(),
+                },
+                namespace => OwnedAttribute {
+                    namespace: namespace.map(str::to_owned),
+                    prefix: namespace
+                        .and_then(|namespace| node.lookup_prefix(namespace))
+                        .map(str::to_owned),
+                    name: attribute.name().to_owned(),
+                    value: attribute.value().to_owned(),
+                },
+            })
+            .collect();
+
+        let children: Vec<_> = node
+            .children()
+            .filter(|child| child.is_element())
+            .map(Self::from_node)
+            .collect();
+
+        let text = if children.is_empty() {
+            node.text().map(str::to_owned)
+        } else {
+            None
+        };
+
+        Self {
+            namespace: namespace.map(str::to_owned),
+            prefix: namespace
+                .and_then(|namespace| node.lookup_prefix(namespace))
+                .map(str::to_owned),
+            name: node.tag_name().name().to_owned(),
+            attributes,
+            children,
+            text,
+        }
+    }
+
+    /// Whether the element has the qualified name `namespace`:`name`
+    pub fn matches(&self, namespace: Option<&str>, name: &str) -> bool {
+        self.namespace.as_deref() == namespace && self.name == name
+    }
+
+    /// Whether the element is the tag `N`, up to equivalent namespaces
+    pub fn is<N: TagName>(&self) -> bool {
+        self.name == N::TAG_NAME && namespaces_match(self.namespace.as_deref(), N::NAMESPACE)
+    }
+
+    /// The first child that is the tag `N`
+    pub fn child<N: TagName>(&self) -> Option<&OwnedElement> {
+        self.children.iter().find(|child| child.is::<N>())
+    }
+
+    /// Rebuild the element, each element declares the namespaces it uses.
+    /// A default namespace is given the `ns0` prefix since the builder needs one.
+    pub fn to_element(&self) -> Element<'_> {
+        let mut element = Element::new(&self.name);
+
+        if let Some(namespace) = &self.namespace {
+            element = element
+                .set_namespace(namespace.as_str())
+                .add_namespace_declaration(
+                    namespace,
+                    Some(self.prefix.as_deref().unwrap_or("ns0")),
+                );
+        }
+
+        for attribute in &self.attributes {
+            let xml_attribute = Attribute::new(&attribute.name, attribute.value.as_str());
+            element = match &attribute.namespace {
+                Some(namespace) if Some(namespace) != self.namespace.as_ref() => element
+                    .add_namespace_declaration(
+                        namespace,
+                        Some(attribute.prefix.as_deref().unwrap_or("ns1")),
+                    )
+                    .add_attribute(xml_attribute.set_namespace(namespace.as_str())),
+                Some(namespace) => {
+                    element.add_attribute(xml_attribute.set_namespace(namespace.as_str()))
+                }
+                None => element.add_attribute(xml_attribute),
+            };
+        }
+
+        if self.children.is_empty() {
+            match &self.text {
+                Some(text) => element.set_text(text.as_str()),
+                None => element,
+            }
+        } else {
+            element.add_children(self.children.iter().map(Self::to_element).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owned_element_round_trip() {
+        let xml = r#"<v:Route xmlns:v="urn:example:vendor" xmlns:o="urn:example:other" o:Hop="1" xml:lang="en-US"><v:Node>east</v:Node><Plain>text</Plain></v:Route>"#;
+        let owned = {
+            let doc = xml::parser::parse(xml).unwrap();
+            OwnedElement::from_node(doc.root_element())
+        };
+
+        assert!(owned.matches(Some("urn:example:vendor"), "Route"));
+        assert_eq!(owned.children.len(), 2);
+
+        let rebuilt = owned.to_element().to_string();
+        let doc = xml::parser::parse(&rebuilt).unwrap();
+        assert_eq!(OwnedElement::from_node(doc.root_element()), owned);
+    }
+}
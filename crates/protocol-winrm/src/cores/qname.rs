@@ -0,0 +1,222 @@
+use xml::{
+    builder::Element,
+    parser::{Node, XmlDeserialize, XmlVisitor},
+};
+
+use crate::cores::{Namespace, TagValue, namespaces_match};
+
+/// A qualified name such as `w:TimedOut` or `xsi:type="xs:int"`, resolved to its namespace URI.
+///
+/// The prefix only means something against the declarations in scope of the node it was read from,
+/// so it is resolved at parse time. When serialized, the well known alias of the namespace is
+/// preferred and the namespace is declared on the element, the received prefix is only a fallback.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QName<'a> {
+    namespace: Option<&'a str>,
+    local_name: &'a str,
+    prefix: Option<&'a str>,
+}
+
+impl<'a> QName<'a> {
+    pub fn new(namespace: Option<&'a str>, local_name: &'a str) -> Self {
+        Self {
+            namespace,
+            local_name,
+            prefix: None,
+        }
+    }
+
+    pub fn from_namespace(namespace: &Namespace, local_name: &'a str) -> Self {
+        Self {
+            namespace: Some(namespace.uri()),
+            local_name,
+            prefix: namespace.alias(),
+        }
+    }
+
+    /// Resolve a `prefix:local` value against the namespaces in scope of `node`.
+    /// An unprefixed value belongs to the default namespace, if any.
+    pub fn resolve(value: &'a str, node: Node<'a, 'a>) -> Result<Self, xml::XmlError> {
+        // Namespaces are only in scope of elements, a text node uses its parent's
+        let node = if node.is_element() {
+            node
+        } else {
+            node.parent_element().unwrap_or(node)
+        };
+
+        let value = value.trim();
+        let (prefix, local_name) = match value.split_once(':') {
+            Some((prefix, local_name)) => (Some(prefix), local_name),
+            None => (None, value),
+        };
+
+        if local_name.is_empty() || local_name.contains(':') || prefix.is_some_and(str::is_empty) {
+            return Err(xml::XmlError::InvalidXml(format!("Invalid QName: {value}")));
+        }
+
+        let namespace = match prefix {
+            Some(prefix) => Some(node.lookup_namespace_uri(Some(prefix)).ok_or_else(|| {
+                xml::XmlError::InvalidXml(format!("Undeclared prefix {prefix} in QName {value}"))
+            })?),
+            None => node.lookup_namespace_uri(None),
+        };
+
+        Ok(Self {
+            namespace,
+            local_name,
+            prefix,
+        })
+    }
+
+    pub fn namespace(&self) -> Option<&'a str> {
+        self.namespace
+    }
+
+    pub fn local_name(&self) -> &'a str {
+        self.local_name
+    }
+
+    /// The prefix the name was received with
+    pub fn prefix(&self) -> Option<&'a str> {
+        self.prefix
+    }
+
+    /// Compare by namespace and local name, prefixes don't matter
+    pub fn matches(&self, namespace: Option<&str>, local_name: &str) -> bool {
+        namespaces_match(self.namespace(), namespace) && self.local_name() == local_name
+    }
+
+    /// The prefix used when serializing
+    fn output_prefix(&self) -> Option<&'a str> {
+        let namespace = self.namespace?;
+        Namespace::try_from(namespace)
+            .ok()
+            .and_then(|namespace| namespace.alias())
+            .or(self.prefix)
+            .or(Some("ns0"))
+    }
+
+    /// Serialize into `prefix:local` and the `(uri, prefix)` declaration it needs
+    pub fn to_prefixed(&self) -> (String, Option<(&'a str, &'a str)>) {
+        match (self.namespace, self.output_prefix()) {
+            (Some(namespace), Some(prefix)) => (
+                format!("{prefix}:{}", self.local_name),
+                Some((namespace, prefix)),
+            ),
+            _ => (self.local_name.to_string(), None),
+        }
+    }
+}
+
+impl std::fmt::Display for QName<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_prefixed().0)
+    }
+}
+
+impl<'a> TagValue<'a> for QName<'a> {
+    fn append_to_element(self, element: Element<'a>) -> Element<'a> {
+        let (text, declaration) = self.to_prefixed();
+        let element = match declaration {
+            Some((namespace, prefix)) => element.add_namespace_declaration(namespace, Some(prefix)),
+            None => element,
+        };
+        element.set_text(text)
+    }
+}
+
+pub struct QNameVisitor<'a> {
+    value: Option<QName<'a>>,
+}
+
+impl<'a> XmlVisitor<'a> for QNameVisitor<'a> {
+    type Value = QName<'a>;
+
+    fn visit_node(&mut self, node: Node<'a, 'a>) -> Result<(), xml::XmlError> {
+        self.visit_children(node.children())
+    }
+
+    fn visit_children(
+        &mut self,
+        children: impl Iterator<Item = Node<'a, 'a>>,
+    ) -> Result<(), xml::XmlError> {
+        for child in children {
+            if child.is_element() {
+                return Err(xml::XmlError::InvalidXml(
+                    "Expected a QName, found an element".to_string(),
+                ));
+            }
+
+            if let Some(text) = child.text()
+                && !text.trim().is_empty()
+            {
+                self.value = Some(QName::resolve(text, child)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Value, xml::XmlError> {
+        self.value
+            .ok_or_else(|| xml::XmlError::InvalidXml("No QName found in the node".to_string()))
+    }
+}
+
+impl<'a> XmlDeserialize<'a> for QName<'a> {
+    type Visitor = QNameVisitor<'a>;
+
+    fn visitor() -> Self::Visitor {
+        QNameVisitor { value: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUBCODE: &str = r#"<s:Value xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:wsman="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">wsman:TimedOut</s:Value>"#;
+
+    #[test]
+    fn test_qname_resolves_prefix() {
+        let doc = xml::parser::parse(SUBCODE).unwrap();
+        let qname = QName::from_node(doc.root_element()).unwrap();
+
+        assert!(qname.matches(Some(Namespace::DmtfWsmanSchema.uri()), "TimedOut"));
+        assert_eq!(qname.prefix(), Some("wsman"));
+        // Re-emitted with the alias of the namespace
+        assert_eq!(qname.to_string(), "w:TimedOut");
+    }
+
+    #[test]
+    fn test_qname_undeclared_prefix() {
+        let xml = r#"<s:Value xmlns:s="http://www.w3.org/2003/05/soap-envelope">wsman:TimedOut</s:Value>"#;
+        let doc = xml::parser::parse(xml).unwrap();
+        assert!(QName::from_node(doc.root_element()).is_err());
+    }
+
+    #[test]
+    fn test_qname_attribute_value() {
+        let xml = r#"<w:Option xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xs="http://www.w3.org/2001/XMLSchema" xsi:type="xs:int">1</w:Option>"#;
+        let doc = xml::parser::parse(xml).unwrap();
+        let node = doc.root_element();
+        let value = node
+            .attribute((Namespace::XmlSchemaInstance.uri(), "type"))
+            .unwrap();
+
+        let qname = QName::resolve(value, node).unwrap();
+        assert!(qname.matches(Some("http://www.w3.org/2001/XMLSchema"), "int"));
+        // Unknown namespace, the received prefix is kept
+        assert_eq!(qname.to_string(), "xs:int");
+    }
+
+    #[test]
+    fn test_qname_serializes_declaration() {
+        let element = QName::from_namespace(&Namespace::DmtfWsmanSchema, "InvalidOptions")
+            .append_to_element(Element::new("Value"));
+        assert_eq!(
+            element.to_string(),
+            r#"<Value xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">w:InvalidOptions</Value>"#
+        );
+    }
+}
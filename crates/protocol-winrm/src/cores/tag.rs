@@ -4,7 +4,7 @@ use xml::parser::{XmlDeserialize, XmlVisitor};
 
 use crate::cores::namespace::NamespaceDeclaration;
 use crate::cores::tag_value::{Text, U32};
-use crate::cores::{Namespace, WsUuid};
+use crate::cores::{Namespace, UnknownTagPolicy, WsUuid};
 use crate::impl_tag_from;
 
 use super::attribute::Attribute;
@@ -29,6 +29,31 @@ where
     __phantom_name: std::marker::PhantomData<N>,
 }
 
+/// Create the element of a tag without its value, shared by [`Tag`] and [`super::DynTag`]
+pub(crate) fn build_element<'a>(
+    name: &'a str,
+    namespace: Option<&'a str>,
+    namespaces_declaration: &NamespaceDeclaration,
+    attributes: Vec<Attribute<'a>>,
+) -> Element<'a> {
+    let mut element = Element::new(name);
+    if let Some(ns) = namespace {
+        element = element.set_namespace(ns);
+    }
+
+    // Add namespace declarations to the element
+    for namespace in namespaces_declaration.namespaces() {
+        let (url, alias) = namespace.as_tuple();
+        element = element.add_namespace_declaration(url, alias);
+    }
+
+    for attribute in attributes {
+        element = element.add_attribute(attribute.into());
+    }
+
+    element
+}
+
 pub struct TagNameHolder<'a, N, V>
 where
     N: TagName,
@@ -120,20 +145,12 @@ where
     }
 
     pub fn into_element(self) -> Element<'a> {
-        let mut element = Element::new(N::TAG_NAME);
-        if let Some(ns) = N::NAMESPACE {
-            element = element.set_namespace(ns);
-        }
-
-        // Add namespace declarations to the element
-        for namespace in self.namespaces_declaration.namespaces() {
-            let (url, alias) = namespace.as_tuple();
-            element = element.add_namespace_declaration(url, alias);
-        }
-
-        for attribute in self.attributes {
-            element = element.add_attribute(attribute.into());
-        }
+        let element = build_element(
+            N::TAG_NAME,
+            N::NAMESPACE,
+            &self.namespaces_declaration,
+            self.attributes,
+        );
 
         self.value.append_to_element(element)
     }
@@ -160,6 +177,60 @@ where
     }
 }
 
+/// Where [`TagVisitor`] looks for its tag in the visited node
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagSearch {
+    /// The visited node itself must be the tag
+    #[default]
+    Root,
+    /// The first descendant (in document order) with the tag name and namespace,
+    /// at most `max_depth` levels below the visited node
+    Descendant { max_depth: usize },
+}
+
+impl TagSearch {
+    /// Deep enough for any body nested under `s:Envelope/s:Body`
+    pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+    pub fn descendant() -> Self {
+        TagSearch::Descendant {
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+/// First node of the subtree that is `N`, the visited node included
+fn find_tag<'a, N: TagName>(
+    node: xml::parser::Node<'a, 'a>,
+    max_depth: usize,
+) -> Option<xml::parser::Node<'a, 'a>> {
+    fn descend<'a, N: TagName>(
+        node: xml::parser::Node<'a, 'a>,
+        depth: usize,
+    ) -> Option<xml::parser::Node<'a, 'a>> {
+        if depth == 0 {
+            return None;
+        }
+
+        for child in node.children().filter(|child| child.is_element()) {
+            if N::matches_node(&child) {
+                return Some(child);
+            }
+            if let Some(found) = descend::<N>(child, depth - 1) {
+                return Some(found);
+            }
+        }
+
+        None
+    }
+
+    if N::matches_node(&node) {
+        return Some(node);
+    }
+
+    descend::<N>(node, max_depth)
+}
+
 pub struct TagVisitor<'a, V, N>
 where
     V: TagValue<'a>,
@@ -169,9 +240,21 @@ where
     pub attributes: Vec<Attribute<'a>>,
     pub namespaces: NamespaceDeclaration,
     pub namespace: Option<Namespace>,
+    pub search: TagSearch,
     __phantom: std::marker::PhantomData<&'a N>,
 }
 
+impl<'a, V, N> TagVisitor<'a, V, N>
+where
+    V: TagValue<'a>,
+    N: TagName,
+{
+    pub fn with_search(mut self, search: TagSearch) -> Self {
+        self.search = search;
+        self
+    }
+}
+
 pub struct NodeDeserializer<'a> {
     root: xml::parser::Node<'a, 'a>,
 }
@@ -199,6 +282,19 @@ where
     type Value = Tag<'a, V, N>;
 
     fn visit_node(&mut self, node: xml::parser::Node<'a, 'a>) -> Result<(), xml::XmlError> {
+        let node = match self.search {
+            TagSearch::Root => node,
+            TagSearch::Descendant { max_depth } => {
+                find_tag::<N>(node, max_depth).ok_or_else(|| {
+                    xml::XmlError::InvalidXml(format!(
+                        "No {} tag found within {max_depth} levels of {}",
+                        N::TAG_NAME,
+                        node.tag_name().name()
+                    ))
+                })?
+            }
+        };
+
         trace!(
             expected_tag_name = N::TAG_NAME,
             actual_tag_name = node.tag_name().name(),
@@ -206,7 +302,7 @@ where
             "TagVisitor visiting node",
         );
 
-        if node.is_element() && node.tag_name().name() == N::TAG_NAME {
+        if N::matches_node(&node) {
             let value =
                 V::from_children(node.children().filter(|c| c.is_element() || c.is_text()))?;
             self.tag = Some(value);
@@ -215,7 +311,9 @@ where
             warn!(
                 actual_tag_name = node.tag_name().name(),
                 expected_tag_name = N::TAG_NAME,
-                "Tag name doesn't match or node is not an element"
+                actual_namespace = ?node.tag_name().namespace(),
+                expected_namespace = ?N::NAMESPACE,
+                "Tag name or namespace doesn't match or node is not an element"
             );
         }
 
@@ -245,12 +343,11 @@ where
         children: impl Iterator<Item = xml::parser::Node<'a, 'a>>,
     ) -> Result<(), xml::XmlError> {
         for child in children {
-            if child.is_element()
-                && child.tag_name().name() == N::TAG_NAME
-                && child.tag_name().namespace() == N::NAMESPACE
-            {
+            if N::matches_node(&child) {
                 debug!("Visiting child node: {}", child.tag_name().name());
                 self.visit_node(child)?;
+            } else if child.is_element() {
+                UnknownTagPolicy::handle(N::TAG_NAME, child)?;
             } else {
                 warn!(
                     "Skipping child node: {} (namespace: {:?})",
@@ -298,6 +395,7 @@ where
             attributes: Vec::new(),
             namespaces: NamespaceDeclaration::new(),
             namespace: None,
+            search: TagSearch::Root,
             __phantom: std::marker::PhantomData,
         }
     }
@@ -307,6 +405,22 @@ where
     }
 }
 
+impl<'a, V, N> Tag<'a, V, N>
+where
+    V: TagValue<'a> + XmlDeserialize<'a>,
+    N: TagName + 'a,
+{
+    /// Parse the first `N` tag found under `node`, e.g. a body value straight from `s:Envelope`.
+    /// See [`TagSearch::Descendant`].
+    pub fn from_descendant(
+        node: xml::parser::Node<'a, 'a>,
+        max_depth: usize,
+    ) -> Result<Self, xml::XmlError> {
+        NodeDeserializer::new(node)
+            .deserialize(Self::visitor().with_search(TagSearch::Descendant { max_depth }))
+    }
+}
+
 impl<'a, V, N> AsRef<V> for Tag<'a, V, N>
 where
     V: TagValue<'a>,
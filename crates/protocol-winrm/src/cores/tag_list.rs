@@ -3,7 +3,7 @@ use xml::{
     parser::{XmlDeserialize, XmlVisitor},
 };
 
-use crate::cores::{TagValue, anytag::AnyTag};
+use crate::cores::{TagValue, UnknownTagPolicy, anytag::AnyTag};
 
 #[derive(Debug, Clone)]
 pub struct TagList<'a> {
@@ -59,6 +59,11 @@ impl<'a> XmlVisitor<'a> for TagListVisitor<'a> {
     ) -> Result<(), xml::XmlError> {
         for child in children {
             if child.is_element() {
+                if !AnyTag::is_known_node(&child) {
+                    UnknownTagPolicy::handle("TagList", child)?;
+                    continue;
+                }
+
                 let tag = AnyTag::from_node(child)?;
                 self.items.push(tag);
             } else {
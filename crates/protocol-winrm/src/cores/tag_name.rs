@@ -12,6 +12,25 @@ pub trait TagName {
     fn namespace(&self) -> Option<&'static str> {
         Self::NAMESPACE
     }
+
+    /// Whether `node` is this tag, the namespace is compared with [`namespaces_match`]
+    fn matches_node(node: &xml::parser::Node<'_, '_>) -> bool
+    where
+        Self: Sized,
+    {
+        node.is_element()
+            && node.tag_name().name() == Self::TAG_NAME
+            && namespaces_match(node.tag_name().namespace(), Self::NAMESPACE)
+    }
+}
+
+/// Implemented by tag values made of a fixed set of child tags,
+/// lets a parent route children the value doesn't know about somewhere else.
+pub trait KnownTagNames {
+    const KNOWN_TAG_NAMES: &'static [&'static str];
+
+    /// Whether `node` is one of the known child tags, by namespace URI and local name
+    fn is_known_node(node: &xml::parser::Node<'_, '_>) -> bool;
 }
 
 // ==========================
@@ -23,6 +42,10 @@ define_tagname!(ResourceUri, Some(Namespace::WsmanShell.uri()));
 define_tagname!(Owner, Some(Namespace::WsmanShell.uri()));
 define_tagname!(ClientIP, Some(Namespace::WsmanShell.uri()));
 define_tagname!(ProcessId, Some(Namespace::WsmanShell.uri()));
+define_tagname!(Environment, Some(Namespace::WsmanShell.uri()));
+define_tagname!(Variable, Some(Namespace::WsmanShell.uri()));
+define_tagname!(WorkingDirectory, Some(Namespace::WsmanShell.uri()));
+define_tagname!(Lifetime, Some(Namespace::WsmanShell.uri()));
 define_tagname!(IdleTimeOut, Some(Namespace::WsmanShell.uri()));
 define_tagname!(InputStreams, Some(Namespace::WsmanShell.uri()));
 define_tagname!(OutputStreams, Some(Namespace::WsmanShell.uri()));
@@ -38,6 +61,8 @@ define_tagname!(CompressionType, Some(Namespace::WsmanShell.uri()));
 define_tagname!(DesiredStream, Some(Namespace::WsmanShell.uri()));
 
 define_custom_tagname!(CreationXml, "creationXml", None);
+define_custom_tagname!(ConnectXml, "connectXml", None);
+define_custom_tagname!(ConnectResponseXml, "connectResponseXml", None);
 
 define_tagname!(CommandLine, Some(Namespace::WsmanShell.uri()));
 define_tagname!(Shell, Some(Namespace::WsmanShell.uri()));
@@ -51,7 +76,14 @@ define_tagname!(CommandState, Some(Namespace::WsmanShell.uri()));
 define_tagname!(ExitCode, Some(Namespace::WsmanShell.uri()));
 define_tagname!(Send, Some(Namespace::WsmanShell.uri()));
 define_tagname!(Signal, Some(Namespace::WsmanShell.uri()));
+define_custom_tagname!(SignalCodeTag, "Code", Some(Namespace::WsmanShell.uri()));
 define_tagname!(Arguments, Some(Namespace::WsmanShell.uri()));
+define_tagname!(Disconnect, Some(Namespace::WsmanShell.uri()));
+define_tagname!(DisconnectResponse, Some(Namespace::WsmanShell.uri()));
+define_tagname!(Reconnect, Some(Namespace::WsmanShell.uri()));
+define_tagname!(ReconnectResponse, Some(Namespace::WsmanShell.uri()));
+define_tagname!(Connect, Some(Namespace::WsmanShell.uri()));
+define_tagname!(ConnectResponse, Some(Namespace::WsmanShell.uri()));
 
 // ====================
 // WS-Addressing (a namespace)
@@ -65,6 +97,7 @@ define_tagname!(FaultTo, Some(Namespace::WsAddressing2004.uri()));
 define_tagname!(From, Some(Namespace::WsAddressing2004.uri()));
 define_tagname!(Address, Some(Namespace::WsAddressing2004.uri()));
 define_tagname!(ReferenceParameters, Some(Namespace::WsAddressing2004.uri()));
+define_tagname!(EndpointReference, Some(Namespace::WsAddressing2004.uri()));
 
 // =============
 // SOAP (s namespace)
@@ -76,14 +109,9 @@ define_tagname!(Body, Some(Namespace::SoapEnvelope2003.uri()));
 // ===============================
 // WS-Management DMTF (w namespace)
 // ===============================
-define_tagname!(Identify, Some(Namespace::DmtfWsmanSchema.uri()));
 define_tagname!(Get, Some(Namespace::DmtfWsmanSchema.uri()));
 define_tagname!(Put, Some(Namespace::DmtfWsmanSchema.uri()));
 define_tagname!(Delete, Some(Namespace::DmtfWsmanSchema.uri()));
-define_tagname!(Enumerate, Some(Namespace::DmtfWsmanSchema.uri()));
-define_tagname!(Pull, Some(Namespace::DmtfWsmanSchema.uri()));
-define_tagname!(Release, Some(Namespace::DmtfWsmanSchema.uri()));
-define_tagname!(GetStatus, Some(Namespace::DmtfWsmanSchema.uri()));
 
 // WS-Management DMTF Headers (w namespace)
 define_tagname!(ResourceURI, Some(Namespace::DmtfWsmanSchema.uri()));
@@ -94,12 +122,105 @@ define_tagname!(SelectorSet, Some(Namespace::DmtfWsmanSchema.uri()));
 define_tagname!(OptionSet, Some(Namespace::DmtfWsmanSchema.uri()));
 define_tagname!(Locale, Some(Namespace::DmtfWsmanSchema.uri()));
 define_tagname!(Selector, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(XmlFragment, Some(Namespace::DmtfWsmanSchema.uri()));
 define_custom_tagname!(
     OptionTagName,
     "Option",
     Some(Namespace::DmtfWsmanSchema.uri())
 );
 
+// WS-Management DMTF enumeration extensions (w namespace)
+define_tagname!(EnumerationMode, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(OptimizeEnumeration, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(Item, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(Filter, Some(Namespace::DmtfWsmanSchema.uri()));
+define_custom_tagname!(
+    WsmanMaxElements,
+    "MaxElements",
+    Some(Namespace::DmtfWsmanSchema.uri())
+);
+define_custom_tagname!(WsmanItems, "Items", Some(Namespace::DmtfWsmanSchema.uri()));
+define_custom_tagname!(
+    WsmanEndOfSequence,
+    "EndOfSequence",
+    Some(Namespace::DmtfWsmanSchema.uri())
+);
+
+// WS-Management DMTF eventing extensions (w namespace)
+define_tagname!(Bookmark, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(SendBookmarks, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(Heartbeats, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(AckRequested, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(MaxTime, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(Events, Some(Namespace::DmtfWsmanSchema.uri()));
+define_tagname!(Event, Some(Namespace::DmtfWsmanSchema.uri()));
+
+// ===================================
+// WS-Enumeration (n namespace)
+// ===================================
+define_tagname!(Enumerate, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(EnumerateResponse, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(EnumerationContext, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(Pull, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(PullResponse, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(MaxElements, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(MaxCharacters, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(Release, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(GetStatus, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(Items, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(EndOfSequence, Some(Namespace::WsEnumeration2004.uri()));
+define_tagname!(EnumerationEnd, Some(Namespace::WsEnumeration2004.uri()));
+define_custom_tagname!(
+    EnumerationCode,
+    "Code",
+    Some(Namespace::WsEnumeration2004.uri())
+);
+define_custom_tagname!(
+    EnumerationReason,
+    "Reason",
+    Some(Namespace::WsEnumeration2004.uri())
+);
+
+// ===================================
+// WS-Management Identify (wsmid namespace)
+// ===================================
+define_tagname!(Identify, Some(Namespace::WsmanIdentity.uri()));
+define_tagname!(IdentifyResponse, Some(Namespace::WsmanIdentity.uri()));
+define_tagname!(ProtocolVersion, Some(Namespace::WsmanIdentity.uri()));
+define_tagname!(ProductVendor, Some(Namespace::WsmanIdentity.uri()));
+define_tagname!(ProductVersion, Some(Namespace::WsmanIdentity.uri()));
+define_tagname!(SecurityProfiles, Some(Namespace::WsmanIdentity.uri()));
+define_tagname!(SecurityProfileName, Some(Namespace::WsmanIdentity.uri()));
+
+// ===================================
+// WS-Eventing (e namespace)
+// ===================================
+define_tagname!(Subscribe, Some(Namespace::WsEventing2004.uri()));
+define_tagname!(SubscribeResponse, Some(Namespace::WsEventing2004.uri()));
+define_tagname!(SubscriptionManager, Some(Namespace::WsEventing2004.uri()));
+define_tagname!(Delivery, Some(Namespace::WsEventing2004.uri()));
+define_tagname!(NotifyTo, Some(Namespace::WsEventing2004.uri()));
+define_tagname!(Expires, Some(Namespace::WsEventing2004.uri()));
+define_tagname!(Identifier, Some(Namespace::WsEventing2004.uri()));
+define_tagname!(Renew, Some(Namespace::WsEventing2004.uri()));
+define_tagname!(RenewResponse, Some(Namespace::WsEventing2004.uri()));
+define_tagname!(Unsubscribe, Some(Namespace::WsEventing2004.uri()));
+
+// ===================================
+// WS-Management CIM binding (b namespace)
+// ===================================
+define_tagname!(AssociatedInstances, Some(Namespace::WsmanCimBinding.uri()));
+define_tagname!(AssociationInstances, Some(Namespace::WsmanCimBinding.uri()));
+define_tagname!(Object, Some(Namespace::WsmanCimBinding.uri()));
+define_tagname!(AssociationClassName, Some(Namespace::WsmanCimBinding.uri()));
+define_tagname!(Role, Some(Namespace::WsmanCimBinding.uri()));
+define_tagname!(ResultClassName, Some(Namespace::WsmanCimBinding.uri()));
+define_tagname!(ResultRole, Some(Namespace::WsmanCimBinding.uri()));
+define_tagname!(
+    IncludeResultProperty,
+    Some(Namespace::WsmanCimBinding.uri())
+);
+
 // ===================================
 // WS-Transfer (x namespace)
 // ===================================
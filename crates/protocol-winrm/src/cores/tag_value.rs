@@ -11,42 +11,102 @@ pub trait TagValue<'a> {
     fn append_to_element(self, element: Element<'a>) -> Element<'a>;
 }
 
+/// Namespace of the `xml:` prefix, bound by definition
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// A text value, optionally tagged with the `xml:lang` of its element
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Text<'a>(Cow<'a, str>);
+pub struct Text<'a> {
+    value: Cow<'a, str>,
+    lang: Option<Cow<'a, str>>,
+}
+
+impl<'a> Text<'a> {
+    pub fn with_lang(mut self, lang: impl Into<Cow<'a, str>>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// The `xml:lang` of the text, e.g. `en-US`
+    pub fn lang(&self) -> Option<&str> {
+        self.lang.as_deref()
+    }
+
+    /// Pick the text matching `language` among localized variants, e.g. the `s:Text` of a fault reason.
+    ///
+    /// Falls back to the same primary language (`en` for `en-US`), then to a text without
+    /// language, then to the first one.
+    pub fn select_localized<'t>(
+        texts: impl IntoIterator<Item = &'t Text<'a>>,
+        language: &str,
+    ) -> Option<&'t Text<'a>>
+    where
+        'a: 't,
+    {
+        let texts: Vec<_> = texts.into_iter().collect();
+        let primary = |lang: &str| lang.split('-').next().unwrap_or(lang).to_ascii_lowercase();
+
+        texts
+            .iter()
+            .find(|text| {
+                text.lang()
+                    .is_some_and(|lang| lang.eq_ignore_ascii_case(language))
+            })
+            .or_else(|| {
+                texts.iter().find(|text| {
+                    text.lang()
+                        .is_some_and(|lang| primary(lang) == primary(language))
+                })
+            })
+            .or_else(|| texts.iter().find(|text| text.lang().is_none()))
+            .or_else(|| texts.first())
+            .copied()
+    }
+}
 
 impl<'a> std::convert::From<&'a str> for Text<'a> {
     fn from(value: &'a str) -> Self {
-        Text(value.into())
+        Text {
+            value: value.into(),
+            lang: None,
+        }
     }
 }
 
 impl<'a> std::convert::From<String> for Text<'a> {
     fn from(value: String) -> Self {
-        Text(value.into())
+        Text {
+            value: value.into(),
+            lang: None,
+        }
     }
 }
 
 impl<'a> AsRef<str> for Text<'a> {
     fn as_ref(&self) -> &str {
-        self.0.as_ref()
+        self.value.as_ref()
     }
 }
 
 impl<'a> From<Text<'a>> for Cow<'a, str> {
     fn from(val: Text<'a>) -> Self {
-        val.0
+        val.value
     }
 }
 
 impl<'a> From<&'a Text<'a>> for &'a str {
     fn from(val: &'a Text<'a>) -> Self {
-        val.0.as_ref()
+        val.value.as_ref()
     }
 }
 
 impl<'a> TagValue<'a> for Text<'a> {
     fn append_to_element(self, element: Element<'a>) -> Element<'a> {
-        element.set_text(self.0)
+        let element = match self.lang {
+            Some(lang) => element.add_attribute(xml::builder::Attribute::new("xml:lang", lang)),
+            None => element,
+        };
+        element.set_text(self.value)
     }
 }
 
@@ -93,7 +153,13 @@ impl<'a> XmlVisitor<'a> for TextVisitor<'a> {
         }
 
         if let Some(text) = child.text() {
-            self.value = Some(Text(text.trim().into()));
+            self.value = Some(Text {
+                value: text.trim().into(),
+                lang: child
+                    .parent_element()
+                    .and_then(|parent| parent.attribute((XML_NAMESPACE, "lang")))
+                    .map(Cow::Borrowed),
+            });
         }
 
         Ok(())
@@ -0,0 +1,112 @@
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use tracing::{trace, warn};
+
+/// What protocol visitors do with a child tag they don't know.
+///
+/// The crate-wide policy is set with [`UnknownTagPolicy::set_global`] and can be overridden
+/// for one deserialization with [`UnknownTagPolicy::scope`], e.g. strict conformance tests
+/// against lenient production parsing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum UnknownTagPolicy {
+    /// Skip the tag silently
+    Ignore = 0,
+    /// Skip the tag and log it with `tracing::warn!`
+    #[default]
+    Warn = 1,
+    /// Fail the deserialization
+    Error = 2,
+}
+
+static GLOBAL_POLICY: AtomicU8 = AtomicU8::new(UnknownTagPolicy::Warn as u8);
+
+thread_local! {
+    static SCOPED_POLICY: Cell<Option<UnknownTagPolicy>> = const { Cell::new(None) };
+}
+
+impl UnknownTagPolicy {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => UnknownTagPolicy::Ignore,
+            2 => UnknownTagPolicy::Error,
+            _ => UnknownTagPolicy::Warn,
+        }
+    }
+
+    /// The policy in effect on this thread
+    pub fn current() -> Self {
+        SCOPED_POLICY
+            .with(Cell::get)
+            .unwrap_or_else(|| Self::from_u8(GLOBAL_POLICY.load(Ordering::Relaxed)))
+    }
+
+    pub fn set_global(policy: UnknownTagPolicy) {
+        GLOBAL_POLICY.store(policy as u8, Ordering::Relaxed);
+    }
+
+    /// Run `f` with this policy on the current thread, the previous policy is restored afterwards
+    pub fn scope<R>(self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<UnknownTagPolicy>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SCOPED_POLICY.with(|policy| policy.set(self.0));
+            }
+        }
+
+        let _restore = Restore(SCOPED_POLICY.with(|policy| policy.replace(Some(self))));
+        f()
+    }
+
+    /// Apply the current policy to `node`, an unknown child of `context`
+    pub fn handle(context: &str, node: xml::parser::Node<'_, '_>) -> Result<(), xml::XmlError> {
+        let name = node.tag_name().name();
+        let namespace = node.tag_name().namespace();
+
+        match Self::current() {
+            UnknownTagPolicy::Ignore => {
+                trace!(context, name, ?namespace, "Ignoring unknown tag");
+                Ok(())
+            }
+            UnknownTagPolicy::Warn => {
+                warn!(context, name, ?namespace, "Skipping unknown tag");
+                Ok(())
+            }
+            UnknownTagPolicy::Error => Err(xml::XmlError::InvalidXml(format!(
+                "Unknown tag in {context}: {name} (namespace: {namespace:?})"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scoped_policy() {
+        let doc = xml::parser::parse("<Root><Unknown/></Root>").unwrap();
+        let unknown = doc.root_element().first_element_child().unwrap();
+
+        assert!(
+            UnknownTagPolicy::Error
+                .scope(|| UnknownTagPolicy::handle("Root", unknown))
+                .is_err()
+        );
+        assert!(
+            UnknownTagPolicy::Ignore
+                .scope(|| UnknownTagPolicy::handle("Root", unknown))
+                .is_ok()
+        );
+
+        let nested = UnknownTagPolicy::Error.scope(|| {
+            UnknownTagPolicy::Warn.scope(UnknownTagPolicy::current);
+            UnknownTagPolicy::current()
+        });
+        assert_eq!(nested, UnknownTagPolicy::Error);
+    }
+}
@@ -18,6 +18,50 @@ pub enum ProtocolError {
     #[error("Unexpected error: {0}")]
     Unexpected(String),
 
+    #[error("WS-Management fault ({kind:?}): {reason}")]
+    WsmanFault {
+        kind: crate::ws_management::WsmanFaultKind,
+        code: Option<u32>,
+        subcode: Option<String>,
+        reason: String,
+    },
+
+    #[error("Response OperationID {found} does not match the request operation {expected}")]
+    OperationIdMismatch {
+        expected: uuid::Uuid,
+        found: uuid::Uuid,
+    },
+
+    #[error("Response SequenceId {received} was never sent, last sent is {sent}")]
+    SequenceIdMismatch { sent: u64, received: u64 },
+
+    #[error("The server rejected MustComply options {options:?}: {reason}")]
+    MustComplyOptionRejected {
+        options: Vec<String>,
+        reason: String,
+    },
+
+    #[error("Headers marked mustUnderstand are not understood: {headers:?}")]
+    NotUnderstood { headers: Vec<String> },
+
+    #[error("Expected a response with action {expected}, found {found}")]
+    UnexpectedAction { expected: String, found: String },
+
+    #[error("Invalid resource URI: {0}")]
+    InvalidResourceUri(String),
+
+    #[error("Codepage {0} is not supported")]
+    UnsupportedCodepage(u32),
+
+    #[error("Invalid compressed stream data: {0}")]
+    InvalidCompressedData(String),
+
+    #[error("Shell {shell_id} no longer exists on the server, its lifetime or idle timeout elapsed")]
+    ShellExpired { shell_id: String },
+
     #[error("IO Error: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[error("Transport error: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
 }
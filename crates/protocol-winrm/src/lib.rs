@@ -5,6 +5,7 @@ pub(crate) mod macros;
 pub mod rsp;
 pub mod soap;
 pub mod test_macro;
+pub mod transport;
 pub mod ws_addressing;
 pub mod ws_management;
 
@@ -163,12 +163,7 @@ macro_rules! impl_xml_deserialize {
                         )*
 
                         if !matched {
-                            return Err(xml::XmlError::InvalidXml(format!(
-                                "Unknown tag in {}: {} (namespace: {:?})",
-                                stringify!($struct_name),
-                                tag_name,
-                                namespace
-                            )));
+                            $crate::cores::UnknownTagPolicy::handle(stringify!($struct_name), child)?;
                         }
                     }
 
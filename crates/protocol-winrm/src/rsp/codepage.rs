@@ -0,0 +1,111 @@
+use encoding_rs::{CoderResult, Decoder, Encoding};
+
+use crate::error::ProtocolError;
+
+/// UTF-8, the `WINRS_CODEPAGE` shells are created with unless told otherwise
+pub const CODEPAGE_UTF8: u32 = 65001;
+
+/// The encoding of a Windows codepage, `None` for the ones without a decoder, e.g. the OEM
+/// codepages 437 and 850 of `cmd.exe`
+pub fn encoding(codepage: u32) -> Option<&'static Encoding> {
+    let encoding = match codepage {
+        CODEPAGE_UTF8 => encoding_rs::UTF_8,
+        1200 => encoding_rs::UTF_16LE,
+        1201 => encoding_rs::UTF_16BE,
+        866 => encoding_rs::IBM866,
+        874 => encoding_rs::WINDOWS_874,
+        932 => encoding_rs::SHIFT_JIS,
+        936 => encoding_rs::GBK,
+        949 => encoding_rs::EUC_KR,
+        950 => encoding_rs::BIG5,
+        1250 => encoding_rs::WINDOWS_1250,
+        1251 => encoding_rs::WINDOWS_1251,
+        1252 => encoding_rs::WINDOWS_1252,
+        1253 => encoding_rs::WINDOWS_1253,
+        1254 => encoding_rs::WINDOWS_1254,
+        1255 => encoding_rs::WINDOWS_1255,
+        1256 => encoding_rs::WINDOWS_1256,
+        1257 => encoding_rs::WINDOWS_1257,
+        1258 => encoding_rs::WINDOWS_1258,
+        10000 => encoding_rs::MACINTOSH,
+        20866 => encoding_rs::KOI8_R,
+        20932 => encoding_rs::EUC_JP,
+        21866 => encoding_rs::KOI8_U,
+        28592 => encoding_rs::ISO_8859_2,
+        28593 => encoding_rs::ISO_8859_3,
+        28594 => encoding_rs::ISO_8859_4,
+        28595 => encoding_rs::ISO_8859_5,
+        28596 => encoding_rs::ISO_8859_6,
+        28597 => encoding_rs::ISO_8859_7,
+        28598 => encoding_rs::ISO_8859_8,
+        28603 => encoding_rs::ISO_8859_13,
+        28605 => encoding_rs::ISO_8859_15,
+        50220 => encoding_rs::ISO_2022_JP,
+        54936 => encoding_rs::GB18030,
+        _ => return None,
+    };
+
+    Some(encoding)
+}
+
+/// Decode the whole output of a stream, invalid sequences become U+FFFD
+pub fn decode(codepage: u32, bytes: &[u8]) -> Result<String, ProtocolError> {
+    let mut decoder = OutputDecoder::new(codepage)?;
+    let mut text = decoder.decode(bytes);
+    text.push_str(&decoder.finish());
+    Ok(text)
+}
+
+/// Decodes the chunks of one output stream, e.g. the stdout of a command, as they are
+/// received.
+///
+/// A character split across chunks is kept until the chunk completing it, so each stream
+/// needs its own decoder.
+pub struct OutputDecoder {
+    decoder: Decoder,
+}
+
+impl OutputDecoder {
+    pub fn new(codepage: u32) -> Result<Self, ProtocolError> {
+        let encoding = encoding(codepage).ok_or(ProtocolError::UnsupportedCodepage(codepage))?;
+
+        Ok(Self {
+            decoder: encoding.new_decoder_without_bom_handling(),
+        })
+    }
+
+    /// The text of `bytes`, without a trailing incomplete character
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        self.decode_chunk(bytes, false)
+    }
+
+    /// The text of the incomplete character left at the end of the stream, if any
+    pub fn finish(&mut self) -> String {
+        self.decode_chunk(&[], true)
+    }
+
+    fn decode_chunk(&mut self, mut bytes: &[u8], last: bool) -> String {
+        let capacity = self
+            .decoder
+            .max_utf8_buffer_length(bytes.len())
+            .unwrap_or(bytes.len() * 3 + 4);
+        let mut text = String::with_capacity(capacity);
+
+        loop {
+            let (result, read, _) = self.decoder.decode_to_string(bytes, &mut text, last);
+            bytes = &bytes[read..];
+            match result {
+                CoderResult::InputEmpty => return text,
+                CoderResult::OutputFull => text.reserve(capacity.max(4)),
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for OutputDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutputDecoder")
+            .field("encoding", &self.decoder.encoding().name())
+            .finish()
+    }
+}
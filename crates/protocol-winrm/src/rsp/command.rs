@@ -0,0 +1,139 @@
+use xml::parser::{Node, XmlDeserialize};
+
+use crate::{
+    cores::{
+        Attribute, CommandId, CommandResponse as CommandResponseTag, Envelope, Tag, TagName, WsUuid,
+    },
+    error::ProtocolError,
+    rsp::commandline::{CommandLineValue, escape_for_cmd, quote_argument},
+    soap::{SoapEnvelope, body::SoapBody},
+    ws_management::{
+        OptionSetValue, ResourceUri, SelectorSetValue, WsAction, WsMan, body::UnknownParameters,
+        transfer::response_body,
+    },
+};
+
+/// How the remote process sees its stdin, sent as `WINRS_CONSOLEMODE_STDIN`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StdinMode {
+    /// An interactive console, the default of winrs
+    #[default]
+    Console,
+    /// A pipe, for input fed with `rsp:Send` by a program
+    Pipeline,
+}
+
+/// A `rsp:Command` starting a process in a shell.
+///
+/// The arguments are quoted for `CommandLineToArgvW` and, unless the command skips
+/// `cmd.exe`, escaped for it, see [`quote_argument`] and [`escape_for_cmd`].
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct CommandRequest<'a> {
+    /// The `ShellId` of the shell, see
+    /// [`CreateShellResponse::shell_id`](crate::rsp::shell::CreateShellResponse::shell_id)
+    shell_id: &'a str,
+    #[builder(default = ResourceUri::WINDOWS_SHELL_CMD)]
+    resource_uri: &'a str,
+    command: &'a str,
+    #[builder(default, setter(into))]
+    arguments: Vec<&'a str>,
+    /// Sent as the `CommandId` attribute, the server picks one when absent
+    #[builder(default, setter(strip_option))]
+    command_id: Option<uuid::Uuid>,
+    #[builder(default)]
+    stdin: StdinMode,
+    /// Start the process directly instead of through `cmd /c`
+    #[builder(default)]
+    skip_cmd_shell: bool,
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: UnknownParameters<'a>,
+}
+
+impl CommandRequest<'_> {
+    fn quote(&self, argument: &str) -> String {
+        let quoted = quote_argument(argument);
+        if self.skip_cmd_shell {
+            quoted.into_owned()
+        } else {
+            escape_for_cmd(&quoted).into_owned()
+        }
+    }
+}
+
+impl WsMan {
+    /// Build the `rsp:Command` request
+    pub fn command<'a>(
+        &'a self,
+        request: CommandRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let command_line = CommandLineValue {
+            command: Some(request.quote(request.command)),
+            arguments: request
+                .arguments
+                .iter()
+                .map(|argument| request.quote(argument))
+                .collect(),
+        };
+
+        let mut command_line = Tag::new(command_line);
+        if let Some(command_id) = request.command_id {
+            command_line = command_line.with_attribute(Attribute::CommandId(
+                command_id.to_string().to_uppercase().into(),
+            ));
+        }
+
+        let option_set = OptionSetValue::new()
+            .add_option(
+                "WINRS_CONSOLEMODE_STDIN",
+                bool_option(request.stdin == StdinMode::Console),
+            )
+            .add_option("WINRS_SKIP_CMD_SHELL", bool_option(request.skip_cmd_shell));
+
+        self.invoke(
+            WsAction::Command,
+            Some(request.resource_uri),
+            SoapBody::builder().command_line(command_line).build(),
+            Some(option_set),
+            Some(SelectorSetValue::new().add_selector("ShellId", request.shell_id)),
+        )
+        .echo_parameters(request.reference_parameters)
+    }
+}
+
+/// The value of a boolean WinRS option
+pub(crate) fn bool_option(value: bool) -> &'static str {
+    if value { "TRUE" } else { "FALSE" }
+}
+
+/// The response to a [`CommandRequest`], the `CommandId` of the started process
+#[derive(Debug, Clone, Copy)]
+pub struct CommandResponse {
+    command_id: uuid::Uuid,
+}
+
+impl CommandResponse {
+    /// Parse a `rsp:CommandResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node<'a>(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::CommandResponse, language)?;
+
+        let response = body
+            .children()
+            .find(CommandResponseTag::matches_node)
+            .ok_or_else(|| {
+                ProtocolError::XmlParsingError("No CommandResponse found in body".to_owned())
+            })?;
+        let response: Tag<'a, Tag<'a, WsUuid, CommandId>, CommandResponseTag> =
+            Tag::from_node(response).map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+
+        Ok(Self {
+            command_id: response.value.value.0,
+        })
+    }
+
+    /// Identifies the process in the Send, Receive and Signal requests that follow
+    pub fn command_id(&self) -> uuid::Uuid {
+        self.command_id
+    }
+}
@@ -1,7 +1,7 @@
-use tracing::warn;
+use std::borrow::Cow;
 
 use crate::cores::{
-    Tag, TagName, TagValue, Text,
+    Tag, TagName, TagValue, Text, UnknownTagPolicy, namespaces_match,
     tag_name::{Arguments, Command},
 };
 
@@ -47,23 +47,22 @@ impl<'a> xml::parser::XmlVisitor<'a> for CommandLineValueVisitor {
         &mut self,
         nodes: impl Iterator<Item = xml::parser::Node<'a, 'a>>,
     ) -> Result<(), xml::XmlError> {
-        for node in nodes {
+        for node in nodes.filter(|node| node.is_element()) {
             match (node.tag_name().name(), node.tag_name().namespace()) {
-                (Command::TAG_NAME, Command::NAMESPACE) => {
+                (Command::TAG_NAME, namespace)
+                    if namespaces_match(namespace, Command::NAMESPACE) =>
+                {
                     let cmd_text = node.text().map(|t| t.to_string());
                     self.command_line = cmd_text;
                 }
-                (Arguments::TAG_NAME, Arguments::NAMESPACE) => {
+                (Arguments::TAG_NAME, namespace)
+                    if namespaces_match(namespace, Arguments::NAMESPACE) =>
+                {
                     if let Some(text) = node.text() {
                         self.arguments.push(text.to_string());
                     }
                 }
-                _ => {
-                    warn!(
-                        "Unexpected tag in CommandLineValue: {}",
-                        node.tag_name().name()
-                    );
-                }
+                _ => UnknownTagPolicy::handle("CommandLineValue", node)?,
             }
         }
         Ok(())
@@ -88,3 +87,97 @@ impl<'a> xml::parser::XmlDeserialize<'a> for CommandLineValue {
     }
 }
 
+
+/// The characters `cmd.exe` interprets outside double quotes
+const CMD_METACHARACTERS: [char; 9] = ['(', ')', '%', '!', '^', '<', '>', '&', '|'];
+
+/// Quote an argument so the `CommandLineToArgvW` parsing of the remote process gets it back
+/// as-is, arguments without whitespace or quotes are left alone.
+/// Example:
+/// quote_argument(r#"say "hi""#)
+/// Generates: "say \"hi\""
+pub fn quote_argument(argument: &str) -> Cow<'_, str> {
+    if !argument.is_empty() && !argument.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return Cow::Borrowed(argument);
+    }
+
+    let mut quoted = String::with_capacity(argument.len() + 2);
+    quoted.push('"');
+
+    // Backslashes are only special in front of a quote, including the closing one
+    let mut backslashes = 0;
+    for c in argument.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+
+    Cow::Owned(quoted)
+}
+
+/// Escape the metacharacters `cmd.exe` would interpret with `^`, for commands run through
+/// it, that is without `WINRS_SKIP_CMD_SHELL`.
+///
+/// `cmd.exe` leaves everything between double quotes alone, so only the unquoted parts of an
+/// already quoted argument are escaped. It still expands `%VARIABLE%` inside quotes.
+pub fn escape_for_cmd(argument: &str) -> Cow<'_, str> {
+    if !argument.contains(CMD_METACHARACTERS) {
+        return Cow::Borrowed(argument);
+    }
+
+    let mut escaped = String::with_capacity(argument.len() * 2);
+    let mut quoted = false;
+    for c in argument.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if !quoted && CMD_METACHARACTERS.contains(&c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_argument() {
+        assert_eq!(quote_argument("plain"), "plain");
+        assert_eq!(quote_argument(r"C:\Windows\"), r"C:\Windows\");
+        assert_eq!(quote_argument(""), r#""""#);
+        assert_eq!(quote_argument("two words"), r#""two words""#);
+        assert_eq!(quote_argument(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(
+            quote_argument(r"C:\Program Files\"),
+            r#""C:\Program Files\\""#
+        );
+        assert_eq!(quote_argument(r#"a\"b"#), r#""a\\\"b""#);
+    }
+
+    #[test]
+    fn test_escape_for_cmd() {
+        assert_eq!(escape_for_cmd("plain"), "plain");
+        assert_eq!(escape_for_cmd("a&b|c"), "a^&b^|c");
+        assert_eq!(escape_for_cmd("100%"), "100^%");
+        assert_eq!(escape_for_cmd(r#""a & b"&c"#), r#""a & b"^&c"#);
+        assert_eq!(
+            escape_for_cmd(&quote_argument(r#"x" & echo pwned"#)),
+            r#""x\" ^& echo pwned""#
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    cores::{Attribute, CompressionType, Tag, Text},
+    error::ProtocolError,
+    rsp::xpress,
+};
+
+/// Compression of the shell streams, requested with `rsp:CompressionType` when the shell is created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StreamCompression {
+    Xpress,
+}
+
+/// Compressions the stream decoder is able to undo.
+/// A compression missing here is never advertised to the server.
+pub const SUPPORTED_COMPRESSIONS: &[StreamCompression] = &[StreamCompression::Xpress];
+
+/// The largest block of a compressed stream, before compression
+const BLOCK_SIZE: usize = 65536;
+const BLOCK_HEADER_SIZE: usize = 4;
+
+impl StreamCompression {
+    /// Value of the `rsp:CompressionType` request header
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamCompression::Xpress => "xpress",
+        }
+    }
+
+    /// Parse the `rsp:CompressionMode` the server reports in the created shell
+    pub fn from_compression_mode(value: &str) -> Option<Self> {
+        match value.trim() {
+            "XpressCompression" => Some(StreamCompression::Xpress),
+            _ => None,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_COMPRESSIONS.contains(self)
+    }
+
+    /// Undo the compression of the data of a `rsp:Stream`.
+    ///
+    /// The data is a sequence of blocks, each after its original and compressed sizes less one
+    /// as little-endian `u16`. A block that doesn't get smaller is stored as is, with both
+    /// sizes equal.
+    pub fn decompress(&self, mut data: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut output = Vec::new();
+        while !data.is_empty() {
+            let (header, rest) = data.split_at_checked(BLOCK_HEADER_SIZE).ok_or_else(|| {
+                ProtocolError::InvalidCompressedData("truncated block header".to_owned())
+            })?;
+            let original_size = usize::from(u16::from_le_bytes([header[0], header[1]])) + 1;
+            let compressed_size = usize::from(u16::from_le_bytes([header[2], header[3]])) + 1;
+            let (block, rest) = rest.split_at_checked(compressed_size).ok_or_else(|| {
+                ProtocolError::InvalidCompressedData(format!(
+                    "block of {compressed_size} bytes is truncated"
+                ))
+            })?;
+
+            if compressed_size == original_size {
+                output.extend_from_slice(block);
+            } else {
+                let block = match self {
+                    StreamCompression::Xpress => xpress::decompress(block, original_size)?,
+                };
+                if block.len() != original_size {
+                    return Err(ProtocolError::InvalidCompressedData(format!(
+                        "block of {original_size} bytes decompressed to {}",
+                        block.len()
+                    )));
+                }
+                output.extend(block);
+            }
+
+            data = rest;
+        }
+
+        Ok(output)
+    }
+
+    /// Compress the data of a `rsp:Stream`, see [`StreamCompression::decompress`]
+    pub fn compress(&self, data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(data.len() + BLOCK_HEADER_SIZE);
+        for block in data.chunks(BLOCK_SIZE) {
+            let compressed = match self {
+                StreamCompression::Xpress => xpress::compress(block),
+            };
+            let stored = if compressed.len() < block.len() {
+                &compressed[..]
+            } else {
+                block
+            };
+
+            output.extend_from_slice(&((block.len() - 1) as u16).to_le_bytes());
+            output.extend_from_slice(&((stored.len() - 1) as u16).to_le_bytes());
+            output.extend_from_slice(stored);
+        }
+
+        output
+    }
+
+    /// The most bytes whose compressed data fits in `capacity` bytes, since the blocks that
+    /// don't get smaller are stored as is
+    pub fn input_capacity(&self, capacity: usize) -> usize {
+        capacity.saturating_sub(capacity.div_ceil(BLOCK_SIZE) * BLOCK_HEADER_SIZE)
+    }
+}
+
+/// Keep the requested compression only if the stream decoder supports it
+pub fn negotiate(requested: Option<StreamCompression>) -> Option<StreamCompression> {
+    requested.filter(StreamCompression::is_supported)
+}
+
+/// Build a `rsp:CompressionType` header.
+/// Example:
+/// compression_type(StreamCompression::Xpress)
+/// Generates: <rsp:CompressionType s:mustUnderstand="true">xpress</rsp:CompressionType>
+pub fn compression_type<'a>(compression: StreamCompression) -> Tag<'a, Text<'a>, CompressionType> {
+    Tag::new(Text::from(compression.as_str())).with_attribute(Attribute::MustUnderstand(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_mode_parsing() {
+        assert_eq!(
+            StreamCompression::from_compression_mode("\n XpressCompression\n "),
+            Some(StreamCompression::Xpress)
+        );
+        assert_eq!(
+            StreamCompression::from_compression_mode("NoCompression"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unsupported_compression_is_not_negotiated() {
+        assert_eq!(
+            negotiate(Some(StreamCompression::Xpress)).is_some(),
+            StreamCompression::Xpress.is_supported()
+        );
+        assert_eq!(negotiate(None), None);
+    }
+
+    #[test]
+    fn test_stream_round_trip() {
+        let compression = StreamCompression::Xpress;
+        let data: Vec<u8> = b"C:\\Users\\Administrator>dir\r\n"
+            .repeat(5000)
+            .into_iter()
+            .chain((0u32..1000).map(|i| (i.wrapping_mul(2654435761) >> 11) as u8))
+            .collect();
+
+        let compressed = compression.compress(&data);
+        assert!(compressed.len() < data.len() / 4);
+        assert_eq!(compression.decompress(&compressed).unwrap(), data);
+        assert!(compression.decompress(&[]).unwrap().is_empty());
+
+        // Incompressible blocks are stored with both sizes equal
+        let stored = compression.compress(&[1, 2, 3]);
+        assert_eq!(stored, [2, 0, 2, 0, 1, 2, 3]);
+        assert_eq!(compression.decompress(&stored).unwrap(), [1, 2, 3]);
+        assert!(compression.decompress(&stored[..5]).is_err());
+
+        let capacity = compression.input_capacity(BLOCK_SIZE * 2);
+        let noise: Vec<u8> = (0..capacity as u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 11) as u8)
+            .collect();
+        assert!(compression.compress(&noise).len() <= BLOCK_SIZE * 2);
+    }
+
+    #[test]
+    fn test_compression_type_header() {
+        let header = compression_type(StreamCompression::Xpress);
+        assert_eq!(header.value.as_ref(), "xpress");
+        assert!(matches!(
+            header.attributes.as_slice(),
+            [Attribute::MustUnderstand(true)]
+        ));
+    }
+}
@@ -0,0 +1,213 @@
+use std::time::Duration;
+
+use protocol_macros::{SimpleTagValue, SimpleXmlDeserialize};
+use xml::parser::Node;
+
+use crate::{
+    cores::{
+        Attribute, Connect, ConnectResponse as ConnectResponseTag, ConnectResponseXml, ConnectXml,
+        Disconnect, Empty, Envelope, IdleTimeOut, Namespace, Reconnect, Tag, TagName, Text, Time,
+    },
+    error::ProtocolError,
+    soap::{SoapEnvelope, body::SoapBody},
+    ws_management::{
+        ResourceUri, SelectorSetValue, WsAction, WsMan, body::UnknownParameters,
+        transfer::response_body,
+    },
+};
+
+/// The content of a `rsp:Disconnect`
+#[derive(Debug, Clone, typed_builder::TypedBuilder, SimpleTagValue, SimpleXmlDeserialize)]
+pub struct DisconnectValue<'a> {
+    #[builder(default, setter(strip_option(fallback_suffix = "_opt"), into))]
+    pub idle_time_out: Option<Tag<'a, Time, IdleTimeOut>>,
+}
+
+/// The content of a `rsp:Connect`, PowerShell Remoting shells need their `connectXml`
+#[derive(Debug, Clone, typed_builder::TypedBuilder, SimpleTagValue, SimpleXmlDeserialize)]
+pub struct ConnectValue<'a> {
+    #[builder(default, setter(strip_option(fallback_suffix = "_opt"), into))]
+    pub connect_xml: Option<Tag<'a, Text<'a>, ConnectXml>>,
+}
+
+/// A `rsp:Disconnect` detaching the client from a shell.
+///
+/// The commands keep running and the server buffers their output until the shell is
+/// reconnected, with [`ReconnectRequest`] from the same client or [`ConnectRequest`] from
+/// another one, or until `idle_time_out` elapses.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct DisconnectRequest<'a> {
+    shell_id: &'a str,
+    #[builder(default = ResourceUri::WINDOWS_SHELL_CMD)]
+    resource_uri: &'a str,
+    /// How long the server keeps the disconnected shell, capped by its `MaxIdleTimeOut`
+    #[builder(default, setter(strip_option))]
+    idle_time_out: Option<Duration>,
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: UnknownParameters<'a>,
+}
+
+/// A `rsp:Reconnect` resuming a shell this client disconnected.
+///
+/// The commands of the shell are reattached by receiving their output again, the server
+/// returns what it buffered in the meantime.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct ReconnectRequest<'a> {
+    shell_id: &'a str,
+    #[builder(default = ResourceUri::WINDOWS_SHELL_CMD)]
+    resource_uri: &'a str,
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: UnknownParameters<'a>,
+}
+
+/// A `rsp:Connect` attaching to a disconnected shell from another client, e.g. another
+/// process that only persisted the `ShellId` and the command IDs.
+///
+/// With a `command_id` it attaches to one command of a shell already connected, the
+/// `ReconnectShellCommand` of the WinRM client API.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct ConnectRequest<'a> {
+    shell_id: &'a str,
+    #[builder(default = ResourceUri::WINDOWS_SHELL_CMD)]
+    resource_uri: &'a str,
+    #[builder(default, setter(strip_option))]
+    command_id: Option<uuid::Uuid>,
+    /// The base64 PSRP messages of a PowerShell Remoting shell
+    #[builder(default, setter(strip_option))]
+    connect_xml: Option<&'a str>,
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: UnknownParameters<'a>,
+}
+
+impl WsMan {
+    /// Build the `rsp:Disconnect` request
+    pub fn disconnect<'a>(
+        &'a self,
+        request: DisconnectRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let value = DisconnectValue::builder()
+            .idle_time_out_opt(
+                request
+                    .idle_time_out
+                    .map(|idle_time_out| Tag::new(Time(idle_time_out.as_secs_f64()))),
+            )
+            .build();
+        let disconnect =
+            Tag::<DisconnectValue, Disconnect>::new(value).with_declaration(Namespace::WsmanShell);
+
+        self.invoke(
+            WsAction::ShellDisconnect,
+            Some(request.resource_uri),
+            SoapBody::builder().disconnect(disconnect).build(),
+            None,
+            Some(SelectorSetValue::new().add_selector("ShellId", request.shell_id)),
+        )
+        .echo_parameters(request.reference_parameters)
+    }
+
+    /// Build the `rsp:Reconnect` request, the body is empty
+    pub fn reconnect<'a>(
+        &'a self,
+        request: ReconnectRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let reconnect = Tag::<Empty, Reconnect>::new(Empty).with_declaration(Namespace::WsmanShell);
+
+        self.invoke(
+            WsAction::ShellReconnect,
+            Some(request.resource_uri),
+            SoapBody::builder().reconnect(reconnect).build(),
+            None,
+            Some(SelectorSetValue::new().add_selector("ShellId", request.shell_id)),
+        )
+        .echo_parameters(request.reference_parameters)
+    }
+
+    /// Build the `rsp:Connect` request
+    pub fn connect<'a>(
+        &'a self,
+        request: ConnectRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let value = ConnectValue::builder()
+            .connect_xml_opt(request.connect_xml.map(|connect_xml| {
+                Tag::new(connect_xml).with_declaration(Namespace::PowerShellRemoting)
+            }))
+            .build();
+        let mut connect =
+            Tag::<ConnectValue, Connect>::new(value).with_declaration(Namespace::WsmanShell);
+        if let Some(command_id) = request.command_id {
+            connect = connect.with_attribute(Attribute::CommandId(
+                command_id.to_string().to_uppercase().into(),
+            ));
+        }
+
+        self.invoke(
+            WsAction::ShellConnect,
+            Some(request.resource_uri),
+            SoapBody::builder().connect(connect).build(),
+            None,
+            Some(SelectorSetValue::new().add_selector("ShellId", request.shell_id)),
+        )
+        .echo_parameters(request.reference_parameters)
+    }
+}
+
+/// The response to a [`DisconnectRequest`], the body carries nothing of use
+#[derive(Debug, Clone, Copy)]
+pub struct DisconnectResponse;
+
+impl DisconnectResponse {
+    pub fn from_node(envelope: Node<'_, '_>, language: &str) -> Result<Self, ProtocolError> {
+        response_body(envelope, WsAction::ShellDisconnectResponse, language)?;
+        Ok(Self)
+    }
+}
+
+/// The response to a [`ReconnectRequest`], the body carries nothing of use
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectResponse;
+
+impl ReconnectResponse {
+    pub fn from_node(envelope: Node<'_, '_>, language: &str) -> Result<Self, ProtocolError> {
+        response_body(envelope, WsAction::ShellReconnectResponse, language)?;
+        Ok(Self)
+    }
+}
+
+/// The response to a [`ConnectRequest`]
+#[derive(Debug, Clone)]
+pub struct ConnectResponse {
+    connect_response_xml: Option<String>,
+}
+
+impl ConnectResponse {
+    /// Parse a `rsp:ConnectResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(envelope: Node<'_, '_>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::ShellConnectResponse, language)?;
+
+        let connect_response_xml = body
+            .children()
+            .find(ConnectResponseTag::matches_node)
+            .and_then(|response| {
+                response
+                    .children()
+                    .find(|child| child.tag_name().name() == ConnectResponseXml::TAG_NAME)
+            })
+            .and_then(|xml| xml.text())
+            .map(str::trim)
+            .filter(|xml| !xml.is_empty())
+            .map(str::to_owned);
+
+        Ok(Self {
+            connect_response_xml,
+        })
+    }
+
+    /// The base64 PSRP messages answering the `connectXml` of a PowerShell Remoting shell
+    pub fn connect_response_xml(&self) -> Option<&str> {
+        self.connect_response_xml.as_deref()
+    }
+}
@@ -0,0 +1,124 @@
+use std::{future::poll_fn, pin::Pin, time::Duration};
+
+use futures_core::Stream;
+
+use crate::{
+    cores::{
+        ClientIP, IdleTimeOut, InputStreams, Name, OutputStreams, OwnedElement, Owner, ProcessId,
+        ResourceUri as ResourceUriTag, ShellId, ShellInactivity, ShellRunTime, State, TagName,
+    },
+    error::ProtocolError,
+    rsp::shell::{DeleteShellRequest, DisconnectedShell},
+    ws_management::{EnumerateRequest, ResourceUri, WsMan, eventing::parse_duration},
+};
+
+/// A shell running on the server, an item of the enumeration of [`ResourceUri::WINDOWS_SHELL`].
+///
+/// Users see their own shells, administrators the shells of every user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellInfo {
+    pub shell_id: String,
+    pub name: Option<String>,
+    pub resource_uri: Option<String>,
+    pub owner: Option<String>,
+    pub client_ip: Option<String>,
+    /// The process hosting the shell on the server
+    pub process_id: Option<u32>,
+    /// `Connected` or `Disconnected`
+    pub state: Option<String>,
+    pub idle_time_out: Option<Duration>,
+    pub input_streams: Option<String>,
+    pub output_streams: Option<String>,
+    /// How long the shell has been running
+    pub shell_run_time: Option<Duration>,
+    /// How long the shell has been idle
+    pub shell_inactivity: Option<Duration>,
+}
+
+impl ShellInfo {
+    /// Read an enumerated `rsp:Shell`, `None` when it has no `ShellId`
+    pub fn from_element(shell: &OwnedElement) -> Option<Self> {
+        let text = |name: &str, namespace: Option<&str>| {
+            shell
+                .children
+                .iter()
+                .find(|child| child.matches(namespace, name))?
+                .text
+                .as_deref()
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_owned)
+        };
+        let duration = |name: &str, namespace: Option<&str>| {
+            text(name, namespace).and_then(|duration| parse_duration(&duration))
+        };
+
+        Some(Self {
+            shell_id: text(ShellId::TAG_NAME, ShellId::NAMESPACE)?,
+            name: text(Name::TAG_NAME, Name::NAMESPACE),
+            resource_uri: text(ResourceUriTag::TAG_NAME, ResourceUriTag::NAMESPACE),
+            owner: text(Owner::TAG_NAME, Owner::NAMESPACE),
+            client_ip: text(ClientIP::TAG_NAME, ClientIP::NAMESPACE),
+            process_id: text(ProcessId::TAG_NAME, ProcessId::NAMESPACE)
+                .and_then(|process_id| process_id.parse().ok()),
+            state: text(State::TAG_NAME, State::NAMESPACE),
+            idle_time_out: duration(IdleTimeOut::TAG_NAME, IdleTimeOut::NAMESPACE),
+            input_streams: text(InputStreams::TAG_NAME, InputStreams::NAMESPACE),
+            output_streams: text(OutputStreams::TAG_NAME, OutputStreams::NAMESPACE),
+            shell_run_time: duration(ShellRunTime::TAG_NAME, ShellRunTime::NAMESPACE),
+            shell_inactivity: duration(ShellInactivity::TAG_NAME, ShellInactivity::NAMESPACE),
+        })
+    }
+
+    fn resource_uri(&self) -> &str {
+        self.resource_uri
+            .as_deref()
+            .unwrap_or(ResourceUri::WINDOWS_SHELL_CMD)
+    }
+
+    /// Attach to the shell with [`WsMan::connect_shell`], e.g. to resume a disconnected one
+    pub fn disconnected(&self) -> DisconnectedShell {
+        DisconnectedShell {
+            shell_id: self.shell_id.clone(),
+            resource_uri: self.resource_uri().to_owned(),
+            command_ids: Vec::new(),
+            reference_parameters: Vec::new(),
+        }
+    }
+
+    /// Delete the shell, see [`WsMan::delete_shell`]
+    pub fn delete_request(&self) -> DeleteShellRequest<'_> {
+        DeleteShellRequest::builder()
+            .shell_id(&self.shell_id)
+            .resource_uri(self.resource_uri())
+            .build()
+    }
+}
+
+impl WsMan {
+    /// List the shells running on the server, enumerating [`ResourceUri::WINDOWS_SHELL`].
+    ///
+    /// `exchange` sends a request envelope to the server and resolves to the response
+    /// envelope, faults are returned as errors with their reason in `language`.
+    pub async fn enumerate_shells<'a, F, Fut>(
+        &'a self,
+        language: &'a str,
+        exchange: F,
+    ) -> Result<Vec<ShellInfo>, ProtocolError>
+    where
+        F: FnMut(String) -> Fut + 'a,
+        Fut: Future<Output = Result<String, ProtocolError>> + 'a,
+    {
+        let request = EnumerateRequest::builder()
+            .resource_uri(ResourceUri::WINDOWS_SHELL)
+            .build();
+        let mut items = self.enumerate_items(request, language, exchange);
+
+        let mut shells = Vec::new();
+        while let Some(item) = poll_fn(|cx| Pin::new(&mut items).poll_next(cx)).await {
+            shells.extend(ShellInfo::from_element(&item?));
+        }
+
+        Ok(shells)
+    }
+}
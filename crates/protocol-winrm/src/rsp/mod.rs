@@ -1,3 +1,13 @@
 pub mod receive;
 pub mod rsp;
-pub mod commandline;
\ No newline at end of file
+pub mod codepage;
+pub mod command;
+pub mod commandline;
+pub mod compression;
+pub mod disconnect;
+pub mod enumerate;
+pub mod process;
+pub mod send;
+pub mod shell;
+pub mod xpress;
+pub mod signal;
\ No newline at end of file
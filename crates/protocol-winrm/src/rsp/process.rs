@@ -0,0 +1,830 @@
+use std::{
+    collections::{HashMap, VecDeque, hash_map::Entry},
+    future::poll_fn,
+    io,
+    pin::{Pin, pin},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker, ready},
+    time::Instant,
+};
+
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::{
+    error::ProtocolError,
+    rsp::{
+        codepage,
+        command::{CommandRequest, CommandResponse, StdinMode},
+        receive::{CommandProgress, ExitStatus, ReceiveRequest, ReceiveResponse, StreamSequences},
+        send::{SendRequest, SendResponse, StdinWriter},
+        shell::{Shell, ShellChannel, expired},
+        signal::{SignalCode, SignalRequest, SignalResponse},
+    },
+    ws_management::{ResourceUri, WsmanFaultKind},
+};
+
+impl<'a, F, Fut> Shell<'a, F>
+where
+    F: FnMut(String) -> Fut + 'a,
+    Fut: Future<Output = Result<String, ProtocolError>> + 'a,
+{
+    /// The commands of the shell, to start several of them running at once.
+    ///
+    /// The shell is borrowed until the returned handle and the commands it started are
+    /// dropped.
+    pub fn commands<'s>(&'s mut self) -> ShellCommands<'s, F, Fut> {
+        ShellCommands {
+            shared: Arc::new(Mutex::new(Commands {
+                channel: self.channel(),
+                commands: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Start `command` in the shell with its stdin as a pipe, the returned handles read its
+    /// output and write its input as `tokio` IO.
+    ///
+    /// The Receives and Sends are exchanged as the handles are polled, the shell is borrowed
+    /// until they are dropped. See [`Shell::commands`] to start more than one command.
+    pub async fn command<'s>(
+        &'s mut self,
+        command: &str,
+        arguments: &[&str],
+    ) -> Result<RemoteCommand<'s, F, Fut>, ProtocolError> {
+        self.commands().start(command, arguments).await
+    }
+
+    /// Run `command` to its end with no input, buffering its output
+    pub async fn run(
+        &mut self,
+        command: &str,
+        arguments: &[&str],
+    ) -> Result<CommandOutput, ProtocolError> {
+        let mut command = self.command(command, arguments).await?;
+        poll_fn(|cx| Pin::new(&mut command.stdin).poll_shutdown(cx))
+            .await
+            .map_err(into_protocol_error)?;
+
+        let exit = command.wait().await?;
+        let mut commands = command.io.lock();
+        let io = commands.command(command.command_id);
+        Ok(CommandOutput {
+            stdout: io.stdout.take(),
+            stderr: io.stderr.take(),
+            exit,
+        })
+    }
+}
+
+/// The output of a command run with [`Shell::run`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit: ExitStatus,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.exit.success()
+    }
+
+    /// The stdout decoded from the `codepage` of the shell, see [`Shell::codepage`]
+    pub fn stdout_text(&self, codepage: u32) -> Result<String, ProtocolError> {
+        codepage::decode(codepage, &self.stdout)
+    }
+
+    /// The stderr decoded from the `codepage` of the shell, see [`Shell::codepage`]
+    pub fn stderr_text(&self, codepage: u32) -> Result<String, ProtocolError> {
+        codepage::decode(codepage, &self.stderr)
+    }
+}
+
+/// How a command waited with [`RemoteCommand::wait_cancellable`] ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandCompletion {
+    Exited(ExitStatus),
+    /// The token was cancelled first, the exit status is the one the interrupted command
+    /// reported, if any
+    Cancelled(Option<ExitStatus>),
+}
+
+/// The commands running at once in a [`Shell`], see [`Shell::commands`].
+///
+/// Each command has its own Receive in flight, the chunks of a ReceiveResponse are given to
+/// the command of their `CommandId` whichever Receive they answer.
+pub struct ShellCommands<'s, F, Fut> {
+    shared: Shared<'s, F, Fut>,
+}
+
+impl<F, Fut> Clone for ShellCommands<'_, F, Fut> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<'s, F, Fut> ShellCommands<'s, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    /// Start `command` with its stdin as a pipe, whether the other commands are still
+    /// running or not, see [`Shell::command`]
+    pub async fn start(
+        &self,
+        command: &str,
+        arguments: &[&str],
+    ) -> Result<RemoteCommand<'s, F, Fut>, ProtocolError> {
+        let (starting, language, shell_id) = {
+            let mut commands = lock(&self.shared);
+            let channel = &mut commands.channel;
+            let request = CommandRequest::builder()
+                .shell_id(channel.shell_id)
+                .resource_uri(channel.resource_uri)
+                .command(command)
+                .arguments(arguments.to_vec())
+                .stdin(StdinMode::Pipeline)
+                .skip_cmd_shell(channel.skip_cmd_shell)
+                .reference_parameters(channel.reference_parameters)
+                .build();
+            let request = channel.ws_man.command(request).into_element().to_string();
+            *channel.last_activity = Instant::now();
+            (
+                (channel.exchange)(request),
+                channel.language,
+                channel.shell_id,
+            )
+        };
+
+        let response = starting.await?;
+        let document = xml::parser::parse(&response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        let command_id = CommandResponse::from_node(document.root_element(), language)
+            .map_err(|error| expired(shell_id, error))?
+            .command_id();
+
+        {
+            let mut commands = lock(&self.shared);
+            let channel = &commands.channel;
+            let chunk_size = StdinWriter::new(channel.ws_man, channel.shell_id, command_id)
+                .with_resource_uri(channel.resource_uri)
+                .with_reference_parameters(channel.reference_parameters)
+                .with_compression(channel.compression)
+                .chunk_size();
+            commands
+                .commands
+                .insert(command_id, CommandIo::new(chunk_size));
+        }
+
+        Ok(RemoteCommand {
+            command_id,
+            stdin: CommandStdin {
+                io: Handle::new(&self.shared, command_id),
+            },
+            stdout: CommandStdout {
+                io: Handle::new(&self.shared, command_id),
+            },
+            stderr: CommandStderr {
+                io: Handle::new(&self.shared, command_id),
+            },
+            io: Handle::new(&self.shared, command_id),
+        })
+    }
+
+    /// The IDs of the commands started with handles left
+    pub fn command_ids(&self) -> Vec<uuid::Uuid> {
+        lock(&self.shared).commands.keys().copied().collect()
+    }
+}
+
+/// A command running in a [`Shell`], see [`Shell::command`].
+///
+/// The output not read yet is buffered, reading only one of stdout and stderr doesn't stall
+/// the other. Dropping the handles doesn't stop the command, signal it to do so.
+pub struct RemoteCommand<'s, F, Fut> {
+    command_id: uuid::Uuid,
+    pub stdin: CommandStdin<'s, F, Fut>,
+    pub stdout: CommandStdout<'s, F, Fut>,
+    pub stderr: CommandStderr<'s, F, Fut>,
+    io: Handle<'s, F, Fut>,
+}
+
+impl<F, Fut> RemoteCommand<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    pub fn command_id(&self) -> uuid::Uuid {
+        self.command_id
+    }
+
+    /// Send all of `reader` to the stdin of the command and then its end, see
+    /// [`CommandStdin::feed`]
+    pub async fn feed_stdin<R>(&mut self, reader: R) -> Result<u64, ProtocolError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.stdin.feed(reader).await
+    }
+
+    /// Receive until the command is done and its exit code known, buffering the output the
+    /// handles didn't read
+    pub async fn wait(&self) -> Result<ExitStatus, ProtocolError> {
+        poll_fn(|cx| {
+            let mut commands = self.io.lock();
+            loop {
+                if let Some(status) = commands.command(self.command_id).progress.exit_status() {
+                    return Poll::Ready(Ok(status));
+                }
+                ready!(commands.poll_receive(self.command_id, cx))?;
+            }
+        })
+        .await
+    }
+
+    /// Receive like [`RemoteCommand::wait`] until the command is done or `token` is cancelled.
+    ///
+    /// On cancellation the command is interrupted with a `ctrl_c`, the one of PowerShell in a
+    /// PowerShell shell, its final output is received for the handles and it is then
+    /// terminated. A command ignoring the `ctrl_c` keeps this waiting.
+    pub async fn wait_cancellable(
+        &self,
+        token: &CancellationToken,
+    ) -> Result<CommandCompletion, ProtocolError> {
+        let mut cancelled = pin!(token.cancelled());
+        let exited = poll_fn(|cx| {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            let mut commands = self.io.lock();
+            loop {
+                if let Some(status) = commands.command(self.command_id).progress.exit_status() {
+                    return Poll::Ready(Some(Ok(status)));
+                }
+                if let Err(error) = ready!(commands.poll_receive(self.command_id, cx)) {
+                    return Poll::Ready(Some(Err(error)));
+                }
+            }
+        })
+        .await;
+        if let Some(exited) = exited {
+            return exited.map(CommandCompletion::Exited);
+        }
+
+        let interrupt = if ResourceUri::is_powershell(self.io.lock().channel.resource_uri) {
+            SignalCode::PsCrtlC
+        } else {
+            SignalCode::CtrlC
+        };
+        self.signal(interrupt).await?;
+
+        // The Receive in flight, if any, answers with the output up to the interruption
+        let exit = poll_fn(|cx| {
+            let mut commands = self.io.lock();
+            loop {
+                if let Some(status) = commands.command(self.command_id).progress.exit_status() {
+                    return Poll::Ready(Some(status));
+                }
+                if let Err(error) = ready!(commands.poll_receive(self.command_id, cx)) {
+                    debug!(command_id = %self.command_id, %error, "Receive of the cancelled command failed");
+                    return Poll::Ready(None);
+                }
+            }
+        })
+        .await;
+
+        // The command may be gone already
+        if let Err(error) = self.signal(SignalCode::Terminate).await {
+            debug!(command_id = %self.command_id, %error, "Terminating the cancelled command failed");
+        }
+        Ok(CommandCompletion::Cancelled(exit))
+    }
+
+    /// Send `code` to the command, e.g. [`SignalCode::CtrlC`] to interrupt it
+    pub async fn signal(&self, code: SignalCode) -> Result<(), ProtocolError> {
+        let (signalling, language, shell_id) = {
+            let mut commands = self.io.lock();
+            let channel = &mut commands.channel;
+            let request = SignalRequest::builder()
+                .shell_id(channel.shell_id)
+                .resource_uri(channel.resource_uri)
+                .command_id(self.command_id)
+                .code(code)
+                .reference_parameters(channel.reference_parameters)
+                .build();
+            let request = channel.ws_man.signal(request).into_element().to_string();
+            *channel.last_activity = Instant::now();
+            (
+                (channel.exchange)(request),
+                channel.language,
+                channel.shell_id,
+            )
+        };
+
+        let response = signalling.await?;
+        let document = xml::parser::parse(&response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        SignalResponse::from_node(document.root_element(), language)
+            .map_err(|error| expired(shell_id, error))?;
+        Ok(())
+    }
+}
+
+/// The stdin of a [`RemoteCommand`], each write is one `rsp:Send` of at most a chunk of the
+/// `MaxEnvelopeSize` and shutting it down sends the end of the input
+pub struct CommandStdin<'s, F, Fut> {
+    io: Handle<'s, F, Fut>,
+}
+
+impl<F, Fut> CommandStdin<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    /// Send all of `reader`, e.g. a local file, and then the end of the input, returning the
+    /// number of bytes sent.
+    ///
+    /// Each Send carries as much of the input as the `MaxEnvelopeSize` allows, `reader` is
+    /// read again until a chunk is full or it is at its end.
+    pub async fn feed<R>(&mut self, mut reader: R) -> Result<u64, ProtocolError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let chunk_size = self.io.lock().command(self.io.command_id).chunk_size;
+        let mut chunk = vec![0; chunk_size];
+        let mut sent = 0;
+
+        loop {
+            let mut buf = ReadBuf::new(&mut chunk);
+            while buf.remaining() > 0 {
+                let filled = buf.filled().len();
+                poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut buf)).await?;
+                if buf.filled().len() == filled {
+                    break;
+                }
+            }
+            let read = buf.filled().len();
+            if read == 0 {
+                break;
+            }
+
+            let mut written = 0;
+            while written < read {
+                written += poll_fn(|cx| Pin::new(&mut *self).poll_write(cx, &chunk[written..read]))
+                    .await
+                    .map_err(into_protocol_error)?;
+            }
+            sent += read as u64;
+        }
+
+        poll_fn(|cx| Pin::new(&mut *self).poll_shutdown(cx))
+            .await
+            .map_err(into_protocol_error)?;
+        Ok(sent)
+    }
+}
+
+/// The stdout of a [`RemoteCommand`], read as bytes or [chunks](CommandStdout::chunk)
+pub struct CommandStdout<'s, F, Fut> {
+    io: Handle<'s, F, Fut>,
+}
+
+impl<F, Fut> CommandStdout<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    /// The next chunk of stdout untouched, as the server sent it once decompressed, `None`
+    /// at its end. No codepage is applied, e.g. for the binary output of a tool.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, ProtocolError> {
+        poll_fn(|cx| {
+            self.io
+                .lock()
+                .poll_chunk(self.io.command_id, cx, OutputStream::Stdout, usize::MAX)
+        })
+        .await
+    }
+}
+
+/// The stderr of a [`RemoteCommand`], read as bytes or [chunks](CommandStderr::chunk)
+pub struct CommandStderr<'s, F, Fut> {
+    io: Handle<'s, F, Fut>,
+}
+
+impl<F, Fut> CommandStderr<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    /// The next chunk of stderr untouched, see [`CommandStdout::chunk`]
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, ProtocolError> {
+        poll_fn(|cx| {
+            self.io
+                .lock()
+                .poll_chunk(self.io.command_id, cx, OutputStream::Stderr, usize::MAX)
+        })
+        .await
+    }
+}
+
+/// The [`ProtocolError`] an IO error of the handles carries, if any
+fn into_protocol_error(error: io::Error) -> ProtocolError {
+    match error.downcast::<ProtocolError>() {
+        Ok(error) => error,
+        Err(error) => ProtocolError::IoError(error),
+    }
+}
+
+type Shared<'s, F, Fut> = Arc<Mutex<Commands<'s, F, Fut>>>;
+
+fn lock<'m, 's, F, Fut>(shared: &'m Shared<'s, F, Fut>) -> MutexGuard<'m, Commands<'s, F, Fut>> {
+    shared.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A handle of one command, the state of the command is dropped with its last handle
+struct Handle<'s, F, Fut> {
+    shared: Shared<'s, F, Fut>,
+    command_id: uuid::Uuid,
+}
+
+impl<'s, F, Fut> Handle<'s, F, Fut> {
+    fn new(shared: &Shared<'s, F, Fut>, command_id: uuid::Uuid) -> Self {
+        if let Some(io) = lock(shared).commands.get_mut(&command_id) {
+            io.handles += 1;
+        }
+
+        Self {
+            shared: shared.clone(),
+            command_id,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Commands<'s, F, Fut>> {
+        lock(&self.shared)
+    }
+}
+
+impl<F, Fut> Drop for Handle<'_, F, Fut> {
+    fn drop(&mut self) {
+        if let Entry::Occupied(mut io) = self.lock().commands.entry(self.command_id) {
+            io.get_mut().handles -= 1;
+            if io.get().handles == 0 {
+                io.remove();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Output {
+    /// The chunks not read yet, as received
+    chunks: VecDeque<Bytes>,
+    end: bool,
+}
+
+impl Output {
+    fn take(&mut self) -> Vec<u8> {
+        self.chunks.drain(..).flatten().collect()
+    }
+}
+
+/// The state the commands of a shell share, the channel and each command by its ID
+struct Commands<'s, F, Fut> {
+    channel: ShellChannel<'s, F>,
+    commands: HashMap<uuid::Uuid, CommandIo<Fut>>,
+}
+
+/// The state of one command, one Receive and one Send at most are in flight
+struct CommandIo<Fut> {
+    chunk_size: usize,
+    receiving: Option<Pin<Box<Fut>>>,
+    sending: Option<Pin<Box<Fut>>>,
+    stdin_closed: bool,
+    stdout: Output,
+    stderr: Output,
+    /// The chunks taken so far, a retried Receive may be answered with them again
+    sequences: StreamSequences,
+    progress: CommandProgress,
+    /// The error that ended the Receives, returned to every reader after the first
+    failed: Option<String>,
+    /// The readers waiting for the Receive in flight, its future only wakes the last one
+    waiting: Vec<Waker>,
+    handles: usize,
+}
+
+impl<Fut> CommandIo<Fut> {
+    fn new(chunk_size: usize) -> Self {
+        Self {
+            chunk_size,
+            receiving: None,
+            sending: None,
+            stdin_closed: false,
+            stdout: Output::default(),
+            stderr: Output::default(),
+            sequences: StreamSequences::new(),
+            progress: CommandProgress::new(),
+            failed: None,
+            waiting: Vec::new(),
+            handles: 0,
+        }
+    }
+
+    fn output(&mut self, stream: OutputStream) -> &mut Output {
+        match stream {
+            OutputStream::Stdout => &mut self.stdout,
+            OutputStream::Stderr => &mut self.stderr,
+        }
+    }
+
+    fn wake(&mut self) {
+        self.waiting.drain(..).for_each(Waker::wake);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl<F, Fut> Commands<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    /// The state of a command with handles left
+    fn command(&mut self, command_id: uuid::Uuid) -> &mut CommandIo<Fut> {
+        self.commands
+            .get_mut(&command_id)
+            .expect("A command is tracked while it has handles")
+    }
+
+    /// Ready once a ReceiveResponse of `command_id` was taken into account, or at once when
+    /// nothing is left to receive
+    fn poll_receive(
+        &mut self,
+        command_id: uuid::Uuid,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), ProtocolError>> {
+        loop {
+            let response = {
+                let Commands { channel, commands } = &mut *self;
+                let io = commands
+                    .get_mut(&command_id)
+                    .expect("A command is tracked while it has handles");
+                if let Some(error) = &io.failed {
+                    return Poll::Ready(Err(ProtocolError::Unexpected(format!(
+                        "An earlier Receive of the command failed: {error}"
+                    ))));
+                }
+                if !io.progress.needs_receive() {
+                    return Poll::Ready(Ok(()));
+                }
+
+                let receiving = match &mut io.receiving {
+                    Some(receiving) => receiving,
+                    None => {
+                        let request = ReceiveRequest::builder()
+                            .shell_id(channel.shell_id)
+                            .resource_uri(channel.resource_uri)
+                            .command_id(command_id)
+                            .reference_parameters(channel.reference_parameters)
+                            .build();
+                        let request = channel.ws_man.receive(request).into_element().to_string();
+                        *channel.last_activity = Instant::now();
+                        io.receiving.insert(Box::pin((channel.exchange)(request)))
+                    }
+                };
+
+                let Poll::Ready(response) = receiving.as_mut().poll(cx) else {
+                    if !io.waiting.iter().any(|waker| waker.will_wake(cx.waker())) {
+                        io.waiting.push(cx.waker().clone());
+                    }
+                    return Poll::Pending;
+                };
+                io.receiving = None;
+                io.wake();
+                response
+            };
+
+            match response.and_then(|response| self.take_response(command_id, &response)) {
+                // The command had no output within the OperationTimeout, it is still running
+                Err(ProtocolError::WsmanFault {
+                    kind: WsmanFaultKind::OperationTimeout,
+                    ..
+                }) => {
+                    debug!(%command_id, "Receive timed out, receiving again");
+                }
+                Err(error) => {
+                    let error = expired(self.channel.shell_id, error);
+                    self.command(command_id).failed = Some(error.to_string());
+                    return Poll::Ready(Err(error));
+                }
+                Ok(()) => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+
+    /// Take the chunks of a ReceiveResponse of `receiver` into the commands they belong to
+    fn take_response(&mut self, receiver: uuid::Uuid, response: &str) -> Result<(), ProtocolError> {
+        let document = xml::parser::parse(response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        let mut response =
+            ReceiveResponse::from_node(document.root_element(), self.channel.language)?;
+        if let Some(compression) = self.channel.compression {
+            response = response.decompress(compression)?;
+        }
+
+        for chunk in response.chunks() {
+            let command_id = chunk.command_id.unwrap_or(receiver);
+            let Some(io) = self.commands.get_mut(&command_id) else {
+                debug!(%command_id, stream = %chunk.name, "Dropping a chunk of a command without handles");
+                continue;
+            };
+            if !io.sequences.accept(chunk) {
+                debug!(
+                    %command_id,
+                    stream = %chunk.name,
+                    sequence_id = chunk.sequence_id,
+                    "Dropping a chunk received again"
+                );
+                continue;
+            }
+            let output = match chunk.name.as_str() {
+                "stdout" => &mut io.stdout,
+                "stderr" => &mut io.stderr,
+                _ => continue,
+            };
+            if !chunk.data.is_empty() {
+                output.chunks.push_back(chunk.data.clone());
+            }
+            output.end |= chunk.end;
+            // Its readers wait on its own Receive, the output came with another one
+            if command_id != receiver {
+                io.wake();
+            }
+        }
+        self.command(receiver).progress.update(&response);
+
+        Ok(())
+    }
+
+    /// Ready with the next chunk of `stream`, split after `max` bytes, or `None` at its end
+    fn poll_chunk(
+        &mut self,
+        command_id: uuid::Uuid,
+        cx: &mut Context<'_>,
+        stream: OutputStream,
+        max: usize,
+    ) -> Poll<Result<Option<Bytes>, ProtocolError>> {
+        loop {
+            let io = self.command(command_id);
+            let done = !io.progress.needs_receive();
+            let output = io.output(stream);
+            if let Some(chunk) = output.chunks.front_mut() {
+                if chunk.len() > max {
+                    return Poll::Ready(Ok(Some(chunk.split_to(max))));
+                }
+                return Poll::Ready(Ok(output.chunks.pop_front()));
+            }
+            if output.end || done {
+                return Poll::Ready(Ok(None));
+            }
+
+            ready!(self.poll_receive(command_id, cx))?;
+        }
+    }
+
+    fn poll_read(
+        &mut self,
+        command_id: uuid::Uuid,
+        cx: &mut Context<'_>,
+        stream: OutputStream,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let chunk = ready!(self.poll_chunk(command_id, cx, stream, buf.remaining()))
+            .map_err(io::Error::other)?;
+        if let Some(chunk) = chunk {
+            buf.put_slice(&chunk);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Ready once the Send in flight of `command_id`, if any, is answered
+    fn poll_send(&mut self, command_id: uuid::Uuid, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let (language, shell_id) = (self.channel.language, self.channel.shell_id);
+        let io = self.command(command_id);
+        let Some(sending) = &mut io.sending else {
+            return Poll::Ready(Ok(()));
+        };
+        let response = ready!(sending.as_mut().poll(cx));
+        io.sending = None;
+
+        let result = response.and_then(|response| {
+            let document = xml::parser::parse(&response)
+                .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+            SendResponse::from_node(document.root_element(), language)
+                .map_err(|error| expired(shell_id, error))?;
+            Ok(())
+        });
+        Poll::Ready(result.map_err(io::Error::other))
+    }
+
+    fn start_send(&mut self, command_id: uuid::Uuid, data: &[u8], end: bool) {
+        let channel = &mut self.channel;
+        let request = SendRequest::builder()
+            .shell_id(channel.shell_id)
+            .resource_uri(channel.resource_uri)
+            .command_id(command_id)
+            .data(data)
+            .end(end)
+            .compression_opt(channel.compression)
+            .reference_parameters(channel.reference_parameters)
+            .build();
+        let request = channel.ws_man.send(request).into_element().to_string();
+        *channel.last_activity = Instant::now();
+        let sending = Box::pin((channel.exchange)(request));
+        self.command(command_id).sending = Some(sending);
+    }
+}
+
+impl<F, Fut> AsyncRead for CommandStdout<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.io
+            .lock()
+            .poll_read(self.io.command_id, cx, OutputStream::Stdout, buf)
+    }
+}
+
+impl<F, Fut> AsyncRead for CommandStderr<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.io
+            .lock()
+            .poll_read(self.io.command_id, cx, OutputStream::Stderr, buf)
+    }
+}
+
+impl<F, Fut> AsyncWrite for CommandStdin<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let command_id = self.io.command_id;
+        let mut commands = self.io.lock();
+        ready!(commands.poll_send(command_id, cx))?;
+        let io = commands.command(command_id);
+        if io.stdin_closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+
+        let written = buf.len().min(io.chunk_size);
+        commands.start_send(command_id, &buf[..written], false);
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.io.lock().poll_send(self.io.command_id, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let command_id = self.io.command_id;
+        let mut commands = self.io.lock();
+        ready!(commands.poll_send(command_id, cx))?;
+        let io = commands.command(command_id);
+        if !io.stdin_closed {
+            io.stdin_closed = true;
+            commands.start_send(command_id, &[], true);
+        }
+        commands.poll_send(command_id, cx)
+    }
+}
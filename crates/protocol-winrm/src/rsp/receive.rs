@@ -1,11 +1,27 @@
+use std::collections::HashMap;
+
+use base64::Engine;
+use bytes::Bytes;
 use protocol_macros::{SimpleTagValue, SimpleXmlDeserialize};
-use tracing::{info, warn};
 
-use crate::cores::{DesiredStream, ReceiveResponse, Stream, Tag, TagName, TagValue, Text};
+use crate::{
+    cores::{
+        Attribute, CommandState, DesiredStream, Envelope, ExitCode, Namespace, Receive,
+        ReceiveResponse as ReceiveResponseTag, Stream, Tag, TagName, TagValue, Text,
+        UnknownTagPolicy, namespaces_match,
+    },
+    error::ProtocolError,
+    rsp::{command::bool_option, compression::StreamCompression},
+    soap::{SoapEnvelope, body::SoapBody},
+    ws_management::{
+        OptionSetValue, ResourceUri, SelectorSetValue, WsAction, WsMan, body::UnknownParameters,
+        transfer::response_body,
+    },
+};
 use xml::{
     XmlError,
     builder::Element,
-    parser::{XmlDeserialize, XmlVisitor},
+    parser::{Node, XmlDeserialize, XmlVisitor},
 };
 
 #[derive(Debug, Clone, typed_builder::TypedBuilder, SimpleTagValue, SimpleXmlDeserialize)]
@@ -13,10 +29,20 @@ pub struct ReceiveValue<'a> {
     pub desired_stream: Tag<'a, Text<'a>, DesiredStream>,
 }
 
+/// The `rsp:CommandState` of a ReceiveResponse, the `State` attribute tells whether the
+/// command is done and the exit code comes with the last one
+#[derive(Debug, Clone, typed_builder::TypedBuilder, SimpleTagValue, SimpleXmlDeserialize)]
+pub struct CommandStateValue<'a> {
+    #[builder(default, setter(strip_option, into))]
+    pub exit_code: Option<Tag<'a, Text<'a>, ExitCode>>,
+}
+
 // ReceiveResponse main structure
 #[derive(Debug, Clone, typed_builder::TypedBuilder)]
 pub struct ReceiveResponseValue<'a> {
     pub streams: Vec<Tag<'a, Text<'a>, Stream>>,
+    #[builder(default, setter(strip_option))]
+    pub command_state: Option<Tag<'a, CommandStateValue<'a>, CommandState>>,
 }
 
 impl<'a> TagValue<'a> for ReceiveResponseValue<'a> {
@@ -25,12 +51,17 @@ impl<'a> TagValue<'a> for ReceiveResponseValue<'a> {
             element = element.add_child(stream.into_element());
         }
 
+        if let Some(command_state) = self.command_state {
+            element = element.add_child(command_state.into_element());
+        }
+
         element
     }
 }
 
 pub struct ReceiveResponseVisitor<'a> {
     pub streams: Vec<Tag<'a, Text<'a>, Stream>>,
+    pub command_state: Option<Tag<'a, CommandStateValue<'a>, CommandState>>,
 }
 
 impl<'a> XmlVisitor<'a> for ReceiveResponseVisitor<'a> {
@@ -40,18 +71,18 @@ impl<'a> XmlVisitor<'a> for ReceiveResponseVisitor<'a> {
         &mut self,
         nodes: impl Iterator<Item = xml::parser::Node<'a, 'a>>,
     ) -> Result<(), xml::XmlError> {
-        for node in nodes {
+        for node in nodes.filter(|node| node.is_element()) {
             match (node.tag_name().name(), node.tag_name().namespace()) {
-                (Stream::TAG_NAME, Stream::NAMESPACE) => {
+                (Stream::TAG_NAME, namespace) if namespaces_match(namespace, Stream::NAMESPACE) => {
                     let stream = Tag::from_node(node)?;
                     self.streams.push(stream);
                 }
-                _ => {
-                    warn!(
-                        "Unexpected tag in ReceiveResponse: {}",
-                        node.tag_name().name()
-                    );
+                (CommandState::TAG_NAME, namespace)
+                    if namespaces_match(namespace, CommandState::NAMESPACE) =>
+                {
+                    self.command_state = Some(Tag::from_node(node)?);
                 }
+                _ => UnknownTagPolicy::handle("ReceiveResponse", node)?,
             }
         }
         Ok(())
@@ -60,6 +91,7 @@ impl<'a> XmlVisitor<'a> for ReceiveResponseVisitor<'a> {
     fn finish(self) -> Result<Self::Value, XmlError> {
         Ok(ReceiveResponseValue {
             streams: self.streams,
+            command_state: self.command_state,
         })
     }
 }
@@ -70,6 +102,347 @@ impl<'a> XmlDeserialize<'a> for ReceiveResponseValue<'a> {
     fn visitor() -> Self::Visitor {
         ReceiveResponseVisitor {
             streams: Vec::new(),
+            command_state: None,
+        }
+    }
+}
+
+/// A `rsp:Receive` reading the output of a command, or of the shell itself when no
+/// command is given
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct ReceiveRequest<'a> {
+    shell_id: &'a str,
+    #[builder(default = ResourceUri::WINDOWS_SHELL_CMD)]
+    resource_uri: &'a str,
+    #[builder(default, setter(strip_option))]
+    command_id: Option<uuid::Uuid>,
+    /// The streams to read, separated by spaces
+    #[builder(default = "stdout stderr")]
+    streams: &'a str,
+    /// Only keep the shell from reaching its idle timeout, sent as
+    /// `WSMAN_CMDSHELL_OPTION_KEEPALIVE`
+    #[builder(default)]
+    keep_alive: bool,
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: UnknownParameters<'a>,
+}
+
+impl WsMan {
+    /// Build the `rsp:Receive` request
+    pub fn receive<'a>(
+        &'a self,
+        request: ReceiveRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let mut desired_stream = Tag::<Text, DesiredStream>::new(request.streams);
+        if let Some(command_id) = request.command_id {
+            desired_stream = desired_stream.with_attribute(Attribute::CommandId(
+                command_id.to_string().to_uppercase().into(),
+            ));
         }
+
+        let receive = Tag::<ReceiveValue, Receive>::new(
+            ReceiveValue::builder()
+                .desired_stream(desired_stream)
+                .build(),
+        )
+        .with_declaration(Namespace::WsmanShell);
+
+        let option_set = request.keep_alive.then(|| {
+            OptionSetValue::new().add_option("WSMAN_CMDSHELL_OPTION_KEEPALIVE", bool_option(true))
+        });
+
+        self.invoke(
+            WsAction::ShellReceive,
+            Some(request.resource_uri),
+            SoapBody::builder().receive(receive).build(),
+            option_set,
+            Some(SelectorSetValue::new().add_selector("ShellId", request.shell_id)),
+        )
+        .echo_parameters(request.reference_parameters)
+    }
+}
+
+/// The `State` of a `rsp:CommandState`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandStatus {
+    /// Started but its output is not flowing yet
+    Pending,
+    Running,
+    /// Exited, no more output will come
+    Done,
+    Other(String),
+}
+
+impl CommandStatus {
+    const PREFIX: &str = "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandState/";
+
+    pub fn from_uri(uri: &str) -> Self {
+        match uri.trim().strip_prefix(Self::PREFIX) {
+            Some("Pending") => CommandStatus::Pending,
+            Some("Running") => CommandStatus::Running,
+            Some("Done") => CommandStatus::Done,
+            _ => CommandStatus::Other(uri.trim().to_owned()),
+        }
+    }
+}
+
+/// How a command completed, the exit code with the state that came with it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitStatus {
+    /// The exit code of the process, a `DWORD` of Windows read as signed, e.g. `-1` for
+    /// `0xFFFFFFFF`
+    pub code: i32,
+    pub state: CommandStatus,
+}
+
+impl ExitStatus {
+    pub fn success(&self) -> bool {
+        self.code == 0
+    }
+}
+
+/// The completion of a command across ReceiveResponses.
+///
+/// A server may report [`CommandStatus::Done`] before the `ExitCode`, which then comes with a
+/// later ReceiveResponse: the client keeps receiving until [`CommandProgress::exit_status`]
+/// is known.
+#[derive(Debug, Clone, Default)]
+pub struct CommandProgress {
+    state: Option<CommandStatus>,
+    exit_code: Option<i32>,
+}
+
+impl CommandProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the state and exit code of `response` into account, returning the exit status
+    /// once the command is done and its exit code known
+    pub fn update(&mut self, response: &ReceiveResponse) -> Option<ExitStatus> {
+        if let Some(state) = response.state() {
+            // A late CommandState doesn't take a command back from Done
+            if self.state != Some(CommandStatus::Done) {
+                self.state = Some(state.clone());
+            }
+        }
+        if let Some(exit_code) = response.exit_code() {
+            self.exit_code = Some(exit_code);
+        }
+
+        self.exit_status()
+    }
+
+    pub fn state(&self) -> Option<&CommandStatus> {
+        self.state.as_ref()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == Some(CommandStatus::Done)
+    }
+
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        if !self.is_done() {
+            return None;
+        }
+
+        Some(ExitStatus {
+            code: self.exit_code?,
+            state: CommandStatus::Done,
+        })
+    }
+
+    /// Whether the command still has output or an exit code to receive
+    pub fn needs_receive(&self) -> bool {
+        self.exit_status().is_none()
     }
 }
+
+/// A decoded `rsp:Stream` of a ReceiveResponse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamChunk {
+    /// The stream name, e.g. `stdout`
+    pub name: String,
+    pub command_id: Option<uuid::Uuid>,
+    /// The bytes of the chunk, cheap to clone and to hand over untouched
+    pub data: Bytes,
+    /// The last chunk of the stream
+    pub end: bool,
+    /// The `SequenceID` of the chunk in its stream, when the server numbers them
+    pub sequence_id: Option<u64>,
+}
+
+/// The last `SequenceID` of each stream of each command, to drop the chunks a retransmitted
+/// ReceiveResponse carries again.
+///
+/// The chunks the server doesn't number are always accepted.
+#[derive(Debug, Clone, Default)]
+pub struct StreamSequences {
+    last: HashMap<(Option<uuid::Uuid>, String), u64>,
+}
+
+impl StreamSequences {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `chunk` is new, i.e. numbered after the last accepted chunk of its stream,
+    /// recording it as the last one
+    pub fn accept(&mut self, chunk: &StreamChunk) -> bool {
+        let Some(sequence_id) = chunk.sequence_id else {
+            return true;
+        };
+
+        match self.last.get_mut(&(chunk.command_id, chunk.name.clone())) {
+            Some(last) if *last >= sequence_id => false,
+            Some(last) => {
+                *last = sequence_id;
+                true
+            }
+            None => {
+                self.last
+                    .insert((chunk.command_id, chunk.name.clone()), sequence_id);
+                true
+            }
+        }
+    }
+}
+
+/// The response to a [`ReceiveRequest`], the output received so far and the state of the
+/// command. A client receives again until the state is [`CommandStatus::Done`].
+#[derive(Debug, Clone)]
+pub struct ReceiveResponse {
+    chunks: Vec<StreamChunk>,
+    state: Option<CommandStatus>,
+    exit_code: Option<i32>,
+}
+
+impl ReceiveResponse {
+    /// Parse a `rsp:ReceiveResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node<'a>(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::ShellReceiveResponse, language)?;
+
+        let response = body
+            .children()
+            .find(ReceiveResponseTag::matches_node)
+            .ok_or_else(|| {
+                ProtocolError::XmlParsingError("No ReceiveResponse found in body".to_owned())
+            })?;
+        // The streams are read from the nodes, the last chunk of a stream is empty
+        let chunks = response
+            .children()
+            .filter(Stream::matches_node)
+            .map(decode_stream)
+            .collect::<Result<_, _>>()?;
+
+        let command_state = response.children().find(CommandState::matches_node);
+        let state = command_state
+            .and_then(|command_state| command_state.attribute("State"))
+            .map(CommandStatus::from_uri);
+        let exit_code = command_state
+            .and_then(|command_state| command_state.children().find(ExitCode::matches_node))
+            .and_then(|exit_code| exit_code.text())
+            .map(parse_exit_code)
+            .transpose()?;
+
+        Ok(Self {
+            chunks,
+            state,
+            exit_code,
+        })
+    }
+
+    /// Undo the `compression` of the shell on the data of the chunks
+    pub fn decompress(mut self, compression: StreamCompression) -> Result<Self, ProtocolError> {
+        for chunk in &mut self.chunks {
+            chunk.data = compression.decompress(&chunk.data)?.into();
+        }
+
+        Ok(self)
+    }
+
+    pub fn chunks(&self) -> &[StreamChunk] {
+        &self.chunks
+    }
+
+    /// The received bytes of `name`, the chunks concatenated in order
+    pub fn stream(&self, name: &str) -> Vec<u8> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.name == name)
+            .flat_map(|chunk| chunk.data.iter().copied())
+            .collect()
+    }
+
+    pub fn stdout(&self) -> Vec<u8> {
+        self.stream("stdout")
+    }
+
+    pub fn stderr(&self) -> Vec<u8> {
+        self.stream("stderr")
+    }
+
+    /// The state of the command, absent when receiving the output of the shell
+    pub fn state(&self) -> Option<&CommandStatus> {
+        self.state.as_ref()
+    }
+
+    /// The exit code of the command, only sent once it is done, see [`CommandProgress`]
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// The exit status when this response carries both the Done state and the exit code
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        CommandProgress::new().update(self)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == Some(CommandStatus::Done)
+    }
+}
+
+/// Servers send the `DWORD` exit code unsigned, others send it signed
+fn parse_exit_code(exit_code: &str) -> Result<i32, ProtocolError> {
+    let exit_code = exit_code.trim();
+    exit_code
+        .parse::<i32>()
+        .or_else(|_| exit_code.parse::<u32>().map(|exit_code| exit_code as i32))
+        .map_err(|e| ProtocolError::XmlParsingError(format!("Invalid ExitCode {exit_code}: {e}")))
+}
+
+fn decode_stream(stream: Node<'_, '_>) -> Result<StreamChunk, ProtocolError> {
+    let name = stream.attribute("Name").ok_or_else(|| {
+        ProtocolError::XmlParsingError("Stream element missing Name attribute".to_owned())
+    })?;
+    let command_id = stream
+        .attribute("CommandId")
+        .map(|command_id| {
+            uuid::Uuid::parse_str(command_id.trim())
+                .map_err(|e| ProtocolError::XmlParsingError(format!("Invalid CommandId: {e}")))
+        })
+        .transpose()?;
+    let end = stream.attribute("End") == Some("true");
+    let sequence_id = stream
+        .attribute("SequenceID")
+        .map(|sequence_id| {
+            sequence_id.trim().parse::<u64>().map_err(|e| {
+                ProtocolError::XmlParsingError(format!("Invalid SequenceID {sequence_id}: {e}"))
+            })
+        })
+        .transpose()?;
+
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(stream.text().unwrap_or_default().trim())
+        .map_err(|e| ProtocolError::XmlParsingError(format!("Invalid {name} stream: {e}")))?;
+
+    Ok(StreamChunk {
+        name: name.to_owned(),
+        command_id,
+        data: data.into(),
+        end,
+        sequence_id,
+    })
+}
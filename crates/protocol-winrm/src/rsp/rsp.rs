@@ -1,11 +1,16 @@
 use crate::{cores::{
     tag_name::{
-        BufferMode, ClientIP, CompressionMode, CreationXml, DataLocale, Encoding, IdleTimeOut,
-        InputStreams, Locale, MaxIdleTimeOut, Name, OutputStreams, Owner, ProcessId, ProfileLoaded,
-        ResourceUri, ShellId, ShellInactivity, ShellRunTime, State, TagName,
-    }, CommandLine, Tag, Text, Time
+        BufferMode, ClientIP, CompressionMode, CreationXml, DataLocale, Encoding, Environment,
+        IdleTimeOut, InputStreams, Lifetime, Locale, MaxIdleTimeOut, Name, OutputStreams, Owner,
+        ProcessId, ProfileLoaded, ResourceUri, ShellId, ShellInactivity, ShellRunTime, State,
+        TagName, Variable, WorkingDirectory,
+    }, Attribute, CommandLine, Tag, TagValue, Text, Time, UnknownTagPolicy, namespaces_match,
 }, rsp::commandline::CommandLineValue};
 use protocol_macros::{SimpleTagValue, SimpleXmlDeserialize};
+use xml::{
+    builder::Element,
+    parser::{XmlDeserialize, XmlVisitor},
+};
 
 // The XmlTagContainer derive macro generates:
 // - TagValue implementation
@@ -26,6 +31,12 @@ pub struct ShellValue<'a> {
     pub client_ip: Option<Tag<'a, Text<'a>, ClientIP>>,
     #[builder(default, setter(strip_option, into))]
     pub process_id: Option<Tag<'a, Text<'a>, ProcessId>>,
+    #[builder(default, setter(strip_option, into))]
+    pub environment: Option<Tag<'a, EnvironmentValue, Environment>>,
+    #[builder(default, setter(strip_option, into))]
+    pub working_directory: Option<Tag<'a, Text<'a>, WorkingDirectory>>,
+    #[builder(default, setter(strip_option, into))]
+    pub lifetime: Option<Tag<'a, Time, Lifetime>>,
     #[builder(default, setter(strip_option(fallback_suffix = "_opt"), into))]
     pub idle_time_out: Option<Tag<'a, Time, IdleTimeOut>>,
     #[builder(default, setter(strip_option, into))]
@@ -55,3 +66,92 @@ pub struct ShellValue<'a> {
     #[builder(default, setter(strip_option, into))]
     pub creation_xml: Option<Tag<'a, Text<'a>, CreationXml>>,
 }
+
+
+/// The `rsp:Environment` of a shell, the variables set for every command it runs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvironmentValue {
+    pub variables: Vec<(String, String)>,
+}
+
+impl EnvironmentValue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a variable, kept in insertion order
+    /// Example:
+    /// environment.add_variable("PATH", "C:\\Windows")
+    /// Generates: <rsp:Variable Name="PATH">C:\Windows</rsp:Variable>
+    pub fn add_variable(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables
+            .iter()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl<'a> TagValue<'a> for EnvironmentValue {
+    fn append_to_element(self, mut element: Element<'a>) -> Element<'a> {
+        for (name, value) in self.variables {
+            let variable = Tag::from_name(Variable)
+                .with_value(Text::from(value))
+                .with_attribute(Attribute::Name(name.into()));
+
+            element = element.add_child(variable.into_element());
+        }
+
+        element
+    }
+}
+
+pub struct EnvironmentVisitor {
+    variables: Vec<(String, String)>,
+}
+
+impl<'a> XmlVisitor<'a> for EnvironmentVisitor {
+    type Value = EnvironmentValue;
+
+    fn visit_children(
+        &mut self,
+        children: impl Iterator<Item = xml::parser::Node<'a, 'a>>,
+    ) -> Result<(), xml::XmlError> {
+        for child in children.filter(|child| child.is_element()) {
+            match (child.tag_name().name(), child.tag_name().namespace()) {
+                (Variable::TAG_NAME, namespace)
+                    if namespaces_match(namespace, Variable::NAMESPACE) =>
+                {
+                    let name = child.attribute("Name").ok_or_else(|| {
+                        xml::XmlError::InvalidXml("Variable element missing Name attribute".into())
+                    })?;
+                    let value = child.text().unwrap_or_default();
+                    self.variables.push((name.to_string(), value.to_string()));
+                }
+                _ => UnknownTagPolicy::handle("EnvironmentValue", child)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Value, xml::XmlError> {
+        Ok(EnvironmentValue {
+            variables: self.variables,
+        })
+    }
+}
+
+impl<'a> XmlDeserialize<'a> for EnvironmentValue {
+    type Visitor = EnvironmentVisitor;
+
+    fn visitor() -> Self::Visitor {
+        EnvironmentVisitor {
+            variables: Vec::new(),
+        }
+    }
+}
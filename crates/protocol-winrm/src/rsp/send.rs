@@ -0,0 +1,270 @@
+use std::borrow::Cow;
+
+use base64::Engine;
+use xml::{
+    XmlError,
+    builder::Element,
+    parser::{Node, XmlDeserialize, XmlVisitor},
+};
+
+use crate::{
+    cores::{
+        Attribute, Envelope, Namespace, Send, Stream, Tag, TagName, TagValue, Text,
+        UnknownTagPolicy, namespaces_match,
+    },
+    error::ProtocolError,
+    rsp::compression::StreamCompression,
+    soap::{SoapEnvelope, body::SoapBody},
+    ws_management::{
+        ResourceUri, SelectorSetValue, WsAction, WsMan, body::UnknownParameters,
+        transfer::response_body,
+    },
+};
+
+/// The content of a `rsp:Send`, base64 chunks of the input streams
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct SendValue<'a> {
+    pub streams: Vec<Tag<'a, Text<'a>, Stream>>,
+}
+
+impl<'a> TagValue<'a> for SendValue<'a> {
+    fn append_to_element(self, mut element: Element<'a>) -> Element<'a> {
+        for stream in self.streams {
+            element = element.add_child(stream.into_element());
+        }
+
+        element
+    }
+}
+
+pub struct SendVisitor<'a> {
+    pub streams: Vec<Tag<'a, Text<'a>, Stream>>,
+}
+
+impl<'a> XmlVisitor<'a> for SendVisitor<'a> {
+    type Value = SendValue<'a>;
+
+    fn visit_children(
+        &mut self,
+        nodes: impl Iterator<Item = xml::parser::Node<'a, 'a>>,
+    ) -> Result<(), xml::XmlError> {
+        for node in nodes.filter(|node| node.is_element()) {
+            match (node.tag_name().name(), node.tag_name().namespace()) {
+                (Stream::TAG_NAME, namespace) if namespaces_match(namespace, Stream::NAMESPACE) => {
+                    self.streams.push(Tag::from_node(node)?);
+                }
+                _ => UnknownTagPolicy::handle("Send", node)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Value, XmlError> {
+        Ok(SendValue {
+            streams: self.streams,
+        })
+    }
+}
+
+impl<'a> XmlDeserialize<'a> for SendValue<'a> {
+    type Visitor = SendVisitor<'a>;
+
+    fn visitor() -> Self::Visitor {
+        SendVisitor {
+            streams: Vec::new(),
+        }
+    }
+}
+
+/// A `rsp:Send` feeding input to a running command.
+///
+/// The whole `data` goes into one envelope, use a [`StdinWriter`] to split a larger input
+/// into envelopes the server accepts.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct SendRequest<'a> {
+    shell_id: &'a str,
+    #[builder(default = ResourceUri::WINDOWS_SHELL_CMD)]
+    resource_uri: &'a str,
+    /// The command whose stream is written, the shell's own stream without one
+    #[builder(default, setter(strip_option(fallback_suffix = "_opt")))]
+    command_id: Option<uuid::Uuid>,
+    #[builder(default = "stdin")]
+    stream: &'a str,
+    #[builder(default, setter(into))]
+    data: Cow<'a, [u8]>,
+    /// The end of the input, the command sees EOF once it has read `data`
+    #[builder(default)]
+    end: bool,
+    /// The compression of the shell, `data` is compressed with it
+    #[builder(default, setter(strip_option(fallback_suffix = "_opt")))]
+    compression: Option<StreamCompression>,
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: UnknownParameters<'a>,
+}
+
+impl WsMan {
+    /// Build the `rsp:Send` request
+    pub fn send<'a>(&'a self, request: SendRequest<'a>) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let data = match request.compression {
+            Some(compression) => Cow::Owned(compression.compress(&request.data)),
+            None => request.data,
+        };
+        let data = base64::engine::general_purpose::STANDARD.encode(&data);
+        let mut stream =
+            Tag::<Text, Stream>::new(data).with_attribute(Attribute::Name(request.stream.into()));
+        if let Some(command_id) = request.command_id {
+            stream = stream.with_attribute(Attribute::CommandId(
+                command_id.to_string().to_uppercase().into(),
+            ));
+        }
+        if request.end {
+            stream = stream.with_attribute(Attribute::End(true));
+        }
+
+        let send = Tag::<SendValue, Send>::new(SendValue::builder().streams(vec![stream]).build())
+            .with_declaration(Namespace::WsmanShell);
+
+        self.invoke(
+            WsAction::ShellSend,
+            Some(request.resource_uri),
+            SoapBody::builder().send(send).build(),
+            None,
+            Some(SelectorSetValue::new().add_selector("ShellId", request.shell_id)),
+        )
+        .echo_parameters(request.reference_parameters)
+    }
+}
+
+/// The response to a [`SendRequest`], the body carries nothing of use
+#[derive(Debug, Clone, Copy)]
+pub struct SendResponse;
+
+impl SendResponse {
+    pub fn from_node(envelope: Node<'_, '_>, language: &str) -> Result<Self, ProtocolError> {
+        response_body(envelope, WsAction::ShellSendResponse, language)?;
+        Ok(Self)
+    }
+}
+
+/// Splits the input of a command into `rsp:Send` envelopes within the `MaxEnvelopeSize` of
+/// the client.
+///
+/// Bytes are buffered until a chunk is full, [`StdinWriter::close`] sends the rest with
+/// `End="true"`. Each returned envelope must be sent, in order, before the next one.
+#[derive(Debug, Clone)]
+pub struct StdinWriter<'a> {
+    ws_man: &'a WsMan,
+    shell_id: &'a str,
+    resource_uri: &'a str,
+    command_id: uuid::Uuid,
+    reference_parameters: UnknownParameters<'a>,
+    compression: Option<StreamCompression>,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+}
+
+impl<'a> StdinWriter<'a> {
+    pub fn new(ws_man: &'a WsMan, shell_id: &'a str, command_id: uuid::Uuid) -> Self {
+        let mut writer = Self {
+            ws_man,
+            shell_id,
+            resource_uri: ResourceUri::WINDOWS_SHELL_CMD,
+            command_id,
+            reference_parameters: &[],
+            compression: None,
+            chunk_size: 0,
+            buffer: Vec::new(),
+        };
+        writer.chunk_size = writer.measure_chunk_size();
+        writer
+    }
+
+    pub fn with_resource_uri(mut self, resource_uri: &'a str) -> Self {
+        self.resource_uri = resource_uri;
+        self.chunk_size = self.measure_chunk_size();
+        self
+    }
+
+    pub fn with_reference_parameters(
+        mut self,
+        reference_parameters: UnknownParameters<'a>,
+    ) -> Self {
+        self.reference_parameters = reference_parameters;
+        self.chunk_size = self.measure_chunk_size();
+        self
+    }
+
+    /// Compress the input with the compression of the shell
+    pub fn with_compression(mut self, compression: Option<StreamCompression>) -> Self {
+        self.compression = compression;
+        self.chunk_size = self.measure_chunk_size();
+        self
+    }
+
+    /// The most input bytes one envelope carries
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Buffer `data`, returning the envelopes of the chunks it completes
+    pub fn write(&mut self, data: &[u8]) -> Vec<Tag<'a, SoapEnvelope<'a>, Envelope>> {
+        self.buffer.extend_from_slice(data);
+
+        let full = self.buffer.len() / self.chunk_size * self.chunk_size;
+        let chunks: Vec<Vec<u8>> = self
+            .buffer
+            .drain(..full)
+            .collect::<Vec<_>>()
+            .chunks(self.chunk_size)
+            .map(<[u8]>::to_vec)
+            .collect();
+
+        chunks
+            .into_iter()
+            .map(|chunk| self.envelope(chunk, false))
+            .collect()
+    }
+
+    /// Send the buffered bytes without waiting for a full chunk, if any
+    pub fn flush(&mut self) -> Option<Tag<'a, SoapEnvelope<'a>, Envelope>> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        let chunk = std::mem::take(&mut self.buffer);
+        Some(self.envelope(chunk, false))
+    }
+
+    /// Send the buffered bytes as the end of the input
+    pub fn close(mut self) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let chunk = std::mem::take(&mut self.buffer);
+        self.envelope(chunk, true)
+    }
+
+    fn envelope(&self, data: Vec<u8>, end: bool) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        self.ws_man.send(
+            SendRequest::builder()
+                .shell_id(self.shell_id)
+                .resource_uri(self.resource_uri)
+                .command_id(self.command_id)
+                .data(data)
+                .end(end)
+                .compression_opt(self.compression)
+                .reference_parameters(self.reference_parameters)
+                .build(),
+        )
+    }
+
+    fn measure_chunk_size(&self) -> usize {
+        let empty = self.envelope(Vec::new(), true).into_element();
+        let room =
+            (self.ws_man.max_envelope_size() as usize).saturating_sub(empty.estimated_size());
+        let capacity = xml::builder::base64_capacity(room);
+        match self.compression {
+            Some(compression) => compression.input_capacity(capacity),
+            None => capacity,
+        }
+        .max(1)
+    }
+}
@@ -0,0 +1,804 @@
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+use xml::parser::{Node, XmlDeserialize};
+
+use crate::{
+    cores::{Attribute, Envelope, Namespace, OwnedElement, Shell as ShellTag, Tag, TagName, Time},
+    error::ProtocolError,
+    rsp::{
+        codepage::CODEPAGE_UTF8,
+        command::{CommandRequest, bool_option},
+        compression::{self, StreamCompression, compression_type},
+        disconnect::{
+            ConnectRequest, ConnectResponse, DisconnectRequest, DisconnectResponse,
+            ReconnectRequest, ReconnectResponse,
+        },
+        receive::{ReceiveRequest, ReceiveResponse},
+        rsp::{EnvironmentValue, ShellValue},
+    },
+    soap::{SoapEnvelope, body::SoapBody},
+    ws_management::{
+        CreateRequest, CreateResponse, DeleteRequest, DeleteResponse, OptionSetValue, ResourceUri,
+        SelectorSetValue, WsMan, WsmanFaultKind,
+        body::{ReferenceParametersValue, UnknownParameters},
+        eventing::parse_duration,
+    },
+};
+
+/// The creation of a WinRS shell, a `wxf:Create` of a `rsp:Shell`.
+///
+/// Defaults to a `cmd.exe` shell with the streams of winrs, `stdin` in and `stdout stderr`
+/// out. Any other resource URI creates the shell of the plugin registered for it, e.g.
+/// [`ResourceUri::powershell_configuration`], with the payload it expects as `creation_xml`.
+/// PowerShell Remoting shells also need a `ShellId`, see `pwsh-core`.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct CreateShellRequest<'a> {
+    #[builder(default = ResourceUri::WINDOWS_SHELL_CMD)]
+    resource_uri: &'a str,
+    #[builder(default = "stdin")]
+    input_streams: &'a str,
+    #[builder(default = "stdout stderr")]
+    output_streams: &'a str,
+    /// Sent as the `ShellId` attribute, the server picks one when absent
+    #[builder(default, setter(strip_option))]
+    shell_id: Option<&'a str>,
+    #[builder(default, setter(strip_option))]
+    name: Option<&'a str>,
+    #[builder(default, setter(strip_option))]
+    environment: Option<EnvironmentValue>,
+    /// The directory commands start in, the profile directory of the user by default
+    #[builder(default, setter(strip_option))]
+    working_directory: Option<&'a str>,
+    /// How long the shell may live at most, idle or not
+    #[builder(default, setter(strip_option))]
+    lifetime: Option<Duration>,
+    /// How long the shell may stay idle, capped by the `MaxIdleTimeOut` of the server
+    #[builder(default, setter(strip_option))]
+    idle_time_out: Option<Duration>,
+    /// Sent as `WINRS_CODEPAGE`, the encoding of the output, see
+    /// [`OutputDecoder`](crate::rsp::codepage::OutputDecoder)
+    #[builder(default = CODEPAGE_UTF8)]
+    codepage: u32,
+    /// Sent as `WINRS_NOPROFILE`, skip loading the profile of the user, which makes the shell
+    /// start faster but leaves out its environment variables and mapped drives
+    #[builder(default)]
+    no_profile: bool,
+    /// Start the commands of the [`Shell`] handle directly instead of through `cmd /c`, the
+    /// `WINRS_SKIP_CMD_SHELL` of [`Shell::command`]. Arguments are then not escaped for
+    /// `cmd.exe`.
+    #[builder(default)]
+    skip_cmd_shell: bool,
+    /// Ask the server to compress the streams, kept only if it is one of the
+    /// [`SUPPORTED_COMPRESSIONS`](crate::rsp::compression::SUPPORTED_COMPRESSIONS)
+    #[builder(default, setter(strip_option))]
+    compression: Option<StreamCompression>,
+    /// The payload of the plugin of the resource URI, sent as `creationXml`, the base64 PSRP
+    /// messages of a PowerShell Remoting shell
+    #[builder(default, setter(strip_option))]
+    creation_xml: Option<&'a str>,
+    /// Other options, the ones set here take precedence over `codepage` and `no_profile`
+    #[builder(default, setter(strip_option))]
+    option_set: Option<OptionSetValue>,
+}
+
+impl WsMan {
+    /// Build the `wxf:Create` request of a shell
+    pub fn create_shell<'a>(
+        &'a self,
+        request: CreateShellRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let mut value = ShellValue::builder()
+            .input_streams(request.input_streams)
+            .output_streams(request.output_streams)
+            .idle_time_out_opt(request.idle_time_out.map(time).map(Tag::new))
+            .build();
+        value.environment = request.environment.map(Tag::new);
+        value.working_directory = request.working_directory.map(Tag::from);
+        value.lifetime = request.lifetime.map(time).map(Tag::new);
+        value.creation_xml = request.creation_xml.map(|creation_xml| {
+            Tag::new(creation_xml).with_declaration(Namespace::PowerShellRemoting)
+        });
+
+        let mut shell = Tag::new(value).with_declaration(Namespace::WsmanShell);
+        if let Some(shell_id) = request.shell_id {
+            shell = shell.with_attribute(Attribute::ShellId(shell_id.into()));
+        }
+        if let Some(name) = request.name {
+            shell = shell.with_attribute(Attribute::Name(name.into()));
+        }
+
+        let mut option_set = request.option_set.unwrap_or_default();
+        option_set
+            .options
+            .entry("WINRS_CODEPAGE".to_owned())
+            .or_insert_with(|| request.codepage.to_string());
+        option_set
+            .options
+            .entry("WINRS_NOPROFILE".to_owned())
+            .or_insert_with(|| bool_option(request.no_profile).to_owned());
+
+        let mut envelope = self.create(
+            CreateRequest::builder()
+                .resource_uri(request.resource_uri)
+                .body(SoapBody::builder().shell(shell).build())
+                .option_set(option_set)
+                .build(),
+        );
+        if let Some(compression) = compression::negotiate(request.compression)
+            && let Some(header) = envelope.value.header.as_mut()
+        {
+            header.value.compression_type =
+                Some(compression_type(compression).with_declaration(Namespace::WsmanShell));
+        }
+
+        envelope
+    }
+}
+
+/// The response to a [`CreateShellRequest`], the endpoint reference of the shell and the
+/// `rsp:Shell` describing it
+#[derive(Debug, Clone)]
+pub struct CreateShellResponse<'a> {
+    created: CreateResponse<'a>,
+    shell: Option<ShellValue<'a>>,
+}
+
+impl<'a> CreateShellResponse<'a> {
+    /// Parse a `wxf:CreateResponse` envelope of a shell, a fault is returned as an error with
+    /// its reason in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let created = CreateResponse::from_node(envelope, language)?;
+
+        let shell = created
+            .body()
+            .children()
+            .find(ShellTag::matches_node)
+            .map(Tag::<'a, ShellValue<'a>, ShellTag>::from_node)
+            .transpose()
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?
+            .map(|shell| shell.value);
+
+        Ok(Self { created, shell })
+    }
+
+    /// The `ShellId` selector of the shell EPR, every later request on the shell carries it
+    pub fn shell_id(&self) -> Option<&str> {
+        self.endpoint()
+            .selector_set
+            .value
+            .get("ShellId")
+            .map(|shell_id| shell_id.trim())
+            .or_else(|| text(self.shell.as_ref()?.shell_id.as_ref()?.value.as_ref()))
+    }
+
+    /// The reference parameters addressing the shell
+    pub fn endpoint(&self) -> &ReferenceParametersValue<'a> {
+        self.created.endpoint()
+    }
+
+    /// The `rsp:Shell` returned by the server, older servers only return the EPR
+    pub fn shell(&self) -> Option<&ShellValue<'a>> {
+        self.shell.as_ref()
+    }
+
+    /// The user the shell runs as
+    pub fn owner(&self) -> Option<&str> {
+        text(self.shell.as_ref()?.owner.as_ref()?.value.as_ref())
+    }
+
+    pub fn resource_uri(&self) -> Option<&str> {
+        self.shell
+            .as_ref()
+            .and_then(|shell| text(shell.resource_uri.as_ref()?.value.as_ref()))
+            .or_else(|| text(self.endpoint().resource_uri.value.as_ref()))
+    }
+
+    /// How long the shell may stay idle before the server deletes it
+    pub fn idle_time_out(&self) -> Option<Duration> {
+        let idle_time_out = self.shell.as_ref()?.idle_time_out.as_ref()?.value.0;
+        Duration::try_from_secs_f64(idle_time_out).ok()
+    }
+
+    /// The environment the server applied, only echoed by some servers
+    pub fn environment(&self) -> Option<&EnvironmentValue> {
+        Some(&self.shell.as_ref()?.environment.as_ref()?.value)
+    }
+
+    pub fn working_directory(&self) -> Option<&str> {
+        text(
+            self.shell
+                .as_ref()?
+                .working_directory
+                .as_ref()?
+                .value
+                .as_ref(),
+        )
+    }
+
+    pub fn lifetime(&self) -> Option<Duration> {
+        let lifetime = self.shell.as_ref()?.lifetime.as_ref()?.value.0;
+        Duration::try_from_secs_f64(lifetime).ok()
+    }
+
+    /// The `rsp:CompressionMode` of the server, `Some(None)` for `NoCompression`. The server
+    /// reports it to every client but only compresses the streams of the ones that asked for
+    /// it with a `rsp:CompressionType`.
+    pub fn compression_mode(&self) -> Option<Option<StreamCompression>> {
+        let compression_mode = self.shell.as_ref()?.compression_mode.as_ref()?;
+        Some(StreamCompression::from_compression_mode(
+            compression_mode.value.as_ref(),
+        ))
+    }
+
+    /// The longest idle timeout the server grants, a shell asking for more gets this one
+    pub fn max_idle_time_out(&self) -> Option<Duration> {
+        let max_idle_time_out = self.shell.as_ref()?.max_idle_time_out.as_ref()?;
+        parse_duration(text(max_idle_time_out.value.as_ref())?)
+    }
+}
+
+/// The deletion of a shell, a `wxf:Delete` of its EPR. The server ends the commands still
+/// running in it.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct DeleteShellRequest<'a> {
+    shell_id: &'a str,
+    #[builder(default = ResourceUri::WINDOWS_SHELL_CMD)]
+    resource_uri: &'a str,
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: UnknownParameters<'a>,
+}
+
+impl WsMan {
+    /// Build the `wxf:Delete` request of a shell, answered by a
+    /// [`DeleteResponse`](crate::ws_management::DeleteResponse)
+    pub fn delete_shell<'a>(
+        &'a self,
+        request: DeleteShellRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        self.delete(
+            DeleteRequest::builder()
+                .resource_uri(request.resource_uri)
+                .selector_set(SelectorSetValue::new().add_selector("ShellId", request.shell_id))
+                .reference_parameters(request.reference_parameters)
+                .build(),
+        )
+    }
+
+    /// Create the shell `request` describes and return a handle deleting it.
+    ///
+    /// `exchange` sends a request envelope to the server and resolves to the response
+    /// envelope, faults are returned as errors with their reason in `language`.
+    pub async fn open_shell<'a, F, Fut>(
+        &'a self,
+        request: CreateShellRequest<'_>,
+        language: &'a str,
+        mut exchange: F,
+    ) -> Result<Shell<'a, F>, ProtocolError>
+    where
+        F: FnMut(String) -> Fut + 'a,
+        Fut: Future<Output = Result<String, ProtocolError>> + 'a,
+    {
+        let created = self
+            .create_shell_with(request, language, &mut exchange)
+            .await?;
+
+        let mut shell = Shell::attach(self, language, exchange, DisconnectedShell::default());
+        shell.settle(created);
+        Ok(shell)
+    }
+
+    async fn create_shell_with<F, Fut>(
+        &self,
+        request: CreateShellRequest<'_>,
+        language: &str,
+        exchange: &mut F,
+    ) -> Result<CreatedShell, ProtocolError>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<String, ProtocolError>>,
+    {
+        let requested_resource_uri = request.resource_uri.to_owned();
+        let skip_cmd_shell = request.skip_cmd_shell;
+        let requested_lifetime = request.lifetime;
+        let compression = compression::negotiate(request.compression);
+        let codepage = request
+            .option_set
+            .as_ref()
+            .and_then(|option_set| option_set.options.get("WINRS_CODEPAGE"))
+            .and_then(|codepage| codepage.trim().parse().ok())
+            .unwrap_or(request.codepage);
+        let create = self.create_shell(request).into_element().to_string();
+
+        let response = exchange(create).await?;
+        let document = xml::parser::parse(&response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        let created = CreateShellResponse::from_node(document.root_element(), language)?;
+
+        let shell_id = created
+            .shell_id()
+            .ok_or_else(|| ProtocolError::Unexpected("CreateResponse has no ShellId".to_owned()))?
+            .to_owned();
+        let resource_uri = created
+            .resource_uri()
+            .map_or(requested_resource_uri, str::to_owned);
+
+        Ok(CreatedShell {
+            shell: DisconnectedShell {
+                shell_id,
+                resource_uri,
+                command_ids: Vec::new(),
+                reference_parameters: created.endpoint().unknown.clone(),
+            },
+            skip_cmd_shell,
+            idle_time_out: created.idle_time_out(),
+            lifetime: created.lifetime().or(requested_lifetime),
+            codepage,
+            compression: compression.filter(|compression| {
+                created
+                    .compression_mode()
+                    .is_none_or(|mode| mode == Some(*compression))
+            }),
+        })
+    }
+
+    /// Resume a shell this client disconnected with [`Shell::disconnect`], see
+    /// [`ReconnectRequest`]
+    pub async fn reconnect_shell<'a, F, Fut>(
+        &'a self,
+        shell: DisconnectedShell,
+        language: &'a str,
+        mut exchange: F,
+    ) -> Result<Shell<'a, F>, ProtocolError>
+    where
+        F: FnMut(String) -> Fut + 'a,
+        Fut: Future<Output = Result<String, ProtocolError>> + 'a,
+    {
+        let request = ReconnectRequest::builder()
+            .shell_id(&shell.shell_id)
+            .resource_uri(&shell.resource_uri)
+            .reference_parameters(&shell.reference_parameters)
+            .build();
+        let request = self.reconnect(request).into_element().to_string();
+
+        let response = exchange(request).await?;
+        let document = xml::parser::parse(&response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        ReconnectResponse::from_node(document.root_element(), language)?;
+
+        Ok(Shell::attach(self, language, exchange, shell))
+    }
+
+    /// Attach to a shell another client disconnected, e.g. from the [`DisconnectedShell`]
+    /// another process persisted, see [`ConnectRequest`]
+    pub async fn connect_shell<'a, F, Fut>(
+        &'a self,
+        shell: DisconnectedShell,
+        language: &'a str,
+        mut exchange: F,
+    ) -> Result<Shell<'a, F>, ProtocolError>
+    where
+        F: FnMut(String) -> Fut + 'a,
+        Fut: Future<Output = Result<String, ProtocolError>> + 'a,
+    {
+        let request = ConnectRequest::builder()
+            .shell_id(&shell.shell_id)
+            .resource_uri(&shell.resource_uri)
+            .reference_parameters(&shell.reference_parameters)
+            .build();
+        let request = self.connect(request).into_element().to_string();
+
+        let response = exchange(request).await?;
+        let document = xml::parser::parse(&response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        ConnectResponse::from_node(document.root_element(), language)?;
+
+        Ok(Shell::attach(self, language, exchange, shell))
+    }
+}
+
+/// A shell the server created and what this client knows of it from the exchange
+struct CreatedShell {
+    shell: DisconnectedShell,
+    skip_cmd_shell: bool,
+    idle_time_out: Option<Duration>,
+    lifetime: Option<Duration>,
+    codepage: u32,
+    compression: Option<StreamCompression>,
+}
+
+/// What [`Shell::renew`] did to keep the shell usable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellRenewal {
+    /// The idle timeout was reset with a keepalive, the lifetime ends after the margin
+    KeptAlive,
+    /// The shell was replaced by a new one, the previous one was deleted or already gone
+    Recreated { previous_shell_id: String },
+}
+
+/// What identifies a disconnected shell, to persist until it is reconnected, as the server
+/// keeps it and buffers the output of its commands until its idle timeout.
+#[derive(Debug, Clone, Default)]
+pub struct DisconnectedShell {
+    pub shell_id: String,
+    pub resource_uri: String,
+    /// The commands to receive the output of again once reconnected, kept by the caller
+    pub command_ids: Vec<uuid::Uuid>,
+    /// Reference parameters of the shell EPR that WS-Management doesn't define
+    pub reference_parameters: Vec<OwnedElement>,
+}
+
+/// A shell created with [`WsMan::open_shell`], deleted with [`Shell::delete`] or detached
+/// with [`Shell::disconnect`].
+///
+/// The server keeps a shell until it is deleted or its idle timeout elapses, see
+/// [`Shell::keep_alive`] to keep an idle shell. A shell dropped without being deleted hands
+/// the `wxf:Delete` envelope to the hook registered with [`Shell::delete_on_drop`], as a drop
+/// can't wait for the exchange.
+pub struct Shell<'a, F> {
+    ws_man: &'a WsMan,
+    language: &'a str,
+    exchange: F,
+    shell_id: String,
+    resource_uri: String,
+    reference_parameters: Vec<OwnedElement>,
+    skip_cmd_shell: bool,
+    idle_time_out: Option<Duration>,
+    /// How long the shell lives from its creation, idle or not
+    lifetime: Option<Duration>,
+    created_at: Instant,
+    codepage: u32,
+    compression: Option<StreamCompression>,
+    /// The last request on the shell, its idle timeout runs from there
+    last_activity: Instant,
+    /// Deleted or disconnected, there is nothing to delete on drop
+    released: bool,
+    delete_on_drop: Option<Box<dyn FnOnce(String) + 'a>>,
+}
+
+impl<'a, F, Fut> Shell<'a, F>
+where
+    F: FnMut(String) -> Fut + 'a,
+    Fut: Future<Output = Result<String, ProtocolError>> + 'a,
+{
+    /// Call `hook` with the `wxf:Delete` envelope when the shell is dropped without being
+    /// deleted, e.g. to send it from a spawned task
+    pub fn delete_on_drop(mut self, hook: impl FnOnce(String) + 'a) -> Self {
+        self.delete_on_drop = Some(Box::new(hook));
+        self
+    }
+
+    /// Detach from the shell, it keeps running on the server for `idle_time_out` or its
+    /// default idle timeout. The returned state reattaches to it with
+    /// [`WsMan::reconnect_shell`] or, from another client, [`WsMan::connect_shell`].
+    pub async fn disconnect(
+        mut self,
+        idle_time_out: Option<Duration>,
+    ) -> Result<DisconnectedShell, ProtocolError> {
+        let request = DisconnectRequest::builder()
+            .shell_id(&self.shell_id)
+            .resource_uri(&self.resource_uri)
+            .reference_parameters(&self.reference_parameters);
+        let request = match idle_time_out {
+            Some(idle_time_out) => request.idle_time_out(idle_time_out).build(),
+            None => request.build(),
+        };
+        let request = self.ws_man.disconnect(request).into_element().to_string();
+
+        let response = (self.exchange)(request).await?;
+        let document = xml::parser::parse(&response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        DisconnectResponse::from_node(document.root_element(), self.language)?;
+
+        self.released = true;
+        Ok(DisconnectedShell {
+            shell_id: std::mem::take(&mut self.shell_id),
+            resource_uri: std::mem::take(&mut self.resource_uri),
+            command_ids: Vec::new(),
+            reference_parameters: std::mem::take(&mut self.reference_parameters),
+        })
+    }
+
+    /// Send a keepalive Receive each time one is due, until one fails or the idle timeout of
+    /// the shell is unknown.
+    ///
+    /// `sleep` waits for a duration with the timer of the caller's runtime. The future is
+    /// meant to run while the shell waits for its next command and to be dropped to run it.
+    pub async fn keep_alive<S, SFut>(&mut self, mut sleep: S) -> Result<(), ProtocolError>
+    where
+        S: FnMut(Duration) -> SFut,
+        SFut: Future<Output = ()>,
+    {
+        while let Some(keep_alive_at) = self.keep_alive_at() {
+            sleep(keep_alive_at.saturating_duration_since(Instant::now())).await;
+            self.send_keep_alive().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Send one `rsp:Receive` with `WSMAN_CMDSHELL_OPTION_KEEPALIVE`, resetting the idle
+    /// timeout of the shell
+    pub async fn send_keep_alive(&mut self) -> Result<(), ProtocolError> {
+        let request = ReceiveRequest::builder()
+            .shell_id(&self.shell_id)
+            .resource_uri(&self.resource_uri)
+            .keep_alive(true)
+            .reference_parameters(&self.reference_parameters)
+            .build();
+        let request = self.ws_man.receive(request).into_element().to_string();
+
+        self.last_activity = Instant::now();
+        let response = (self.exchange)(request).await?;
+        let document = xml::parser::parse(&response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        match ReceiveResponse::from_node(document.root_element(), self.language) {
+            // The shell is alive, it had nothing to send within the OperationTimeout
+            Ok(_)
+            | Err(ProtocolError::WsmanFault {
+                kind: WsmanFaultKind::OperationTimeout,
+                ..
+            }) => {
+                debug!(shell_id = %self.shell_id, "Shell kept alive");
+                Ok(())
+            }
+            Err(error) => Err(expired(&self.shell_id, error)),
+        }
+    }
+
+    /// Keep the shell usable for at least `margin`, recreating it from `request` when its
+    /// lifetime ends within `margin` or when it is gone, resetting its idle timeout otherwise.
+    ///
+    /// A `Lifetime` can't be extended, the new shell has another `ShellId` and none of the
+    /// commands of the previous one.
+    pub async fn renew(
+        &mut self,
+        request: CreateShellRequest<'_>,
+        margin: Duration,
+    ) -> Result<ShellRenewal, ProtocolError> {
+        let expiring = self
+            .expires_at()
+            .is_some_and(|expires_at| expires_at <= Instant::now() + margin);
+        if !expiring {
+            match self.send_keep_alive().await {
+                Ok(()) => return Ok(ShellRenewal::KeptAlive),
+                Err(ProtocolError::ShellExpired { .. }) => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        let created = self
+            .ws_man
+            .create_shell_with(request, self.language, &mut self.exchange)
+            .await?;
+
+        // The previous shell may be gone already, it only holds resources if it is not
+        let delete = self.delete_request();
+        let deleted = (self.exchange)(delete).await.and_then(|response| {
+            let document = xml::parser::parse(&response)
+                .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+            DeleteResponse::from_node(document.root_element(), self.language)
+        });
+        if let Err(error) = deleted {
+            debug!(shell_id = %self.shell_id, %error, "Deleting the renewed shell failed");
+        }
+
+        let previous_shell_id = std::mem::take(&mut self.shell_id);
+        self.settle(created);
+        debug!(%previous_shell_id, shell_id = %self.shell_id, "Shell recreated");
+        Ok(ShellRenewal::Recreated { previous_shell_id })
+    }
+
+    /// Delete the shell on the server
+    pub async fn delete(mut self) -> Result<(), ProtocolError> {
+        self.released = true;
+
+        let request = self.delete_request();
+        let response = (self.exchange)(request).await?;
+        let document = xml::parser::parse(&response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        DeleteResponse::from_node(document.root_element(), self.language)?;
+
+        Ok(())
+    }
+}
+
+impl<'a, F> Shell<'a, F> {
+    fn attach(ws_man: &'a WsMan, language: &'a str, exchange: F, shell: DisconnectedShell) -> Self {
+        Self {
+            ws_man,
+            language,
+            exchange,
+            shell_id: shell.shell_id,
+            resource_uri: shell.resource_uri,
+            reference_parameters: shell.reference_parameters,
+            skip_cmd_shell: false,
+            idle_time_out: None,
+            lifetime: None,
+            created_at: Instant::now(),
+            codepage: CODEPAGE_UTF8,
+            compression: None,
+            last_activity: Instant::now(),
+            released: false,
+            delete_on_drop: None,
+        }
+    }
+
+    /// Set the idle timeout of a reconnected shell, the one of a shell created by this client
+    /// is known from its creation
+    pub fn with_idle_time_out(mut self, idle_time_out: Duration) -> Self {
+        self.idle_time_out = Some(idle_time_out);
+        self
+    }
+
+    /// How long the shell may stay idle before the server deletes it
+    pub fn idle_time_out(&self) -> Option<Duration> {
+        self.idle_time_out
+    }
+
+    /// Take over the shell the server just created
+    fn settle(&mut self, created: CreatedShell) {
+        self.shell_id = created.shell.shell_id;
+        self.resource_uri = created.shell.resource_uri;
+        self.reference_parameters = created.shell.reference_parameters;
+        self.skip_cmd_shell = created.skip_cmd_shell;
+        self.idle_time_out = created.idle_time_out;
+        self.lifetime = created.lifetime;
+        self.created_at = Instant::now();
+        self.last_activity = self.created_at;
+        self.codepage = created.codepage;
+        self.compression = created.compression;
+    }
+
+    /// How long the shell lives from its creation, idle or not, the one the server reports or
+    /// else the one asked for. Unknown for a reconnected shell.
+    pub fn lifetime(&self) -> Option<Duration> {
+        self.lifetime
+    }
+
+    /// When the server deletes the shell whatever its activity, see [`Shell::renew`]
+    pub fn expires_at(&self) -> Option<Instant> {
+        Some(self.created_at + self.lifetime?)
+    }
+
+    /// When the next keepalive is due, half of the idle timeout after the last request on the
+    /// shell, `None` if the idle timeout is unknown
+    pub fn keep_alive_at(&self) -> Option<Instant> {
+        Some(self.last_activity + self.idle_time_out? / 2)
+    }
+
+    /// Every request on the shell carries it as the `ShellId` selector
+    pub fn shell_id(&self) -> &str {
+        &self.shell_id
+    }
+
+    pub fn resource_uri(&self) -> &str {
+        &self.resource_uri
+    }
+
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, to echo as
+    /// headers
+    pub fn reference_parameters(&self) -> &[OwnedElement] {
+        &self.reference_parameters
+    }
+
+    /// Set the codepage of a reconnected shell, the one of a shell created by this client is
+    /// known from its creation
+    pub fn with_codepage(mut self, codepage: u32) -> Self {
+        self.codepage = codepage;
+        self
+    }
+
+    /// The `WINRS_CODEPAGE` of the output of the commands, see
+    /// [`CommandOutput::stdout_text`](crate::rsp::process::CommandOutput::stdout_text)
+    pub fn codepage(&self) -> u32 {
+        self.codepage
+    }
+
+    /// Set the compression of the streams of a reconnected shell, the one of a shell created by
+    /// this client is known from its creation
+    pub fn with_compression(mut self, compression: StreamCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// The compression of the streams of the commands, the one asked for at creation unless the
+    /// server reports another [`CreateShellResponse::compression_mode`]
+    pub fn compression(&self) -> Option<StreamCompression> {
+        self.compression
+    }
+
+    /// A `rsp:Command` running `command` in the shell, with its `skip_cmd_shell`, see
+    /// [`Shell::command`] to also run it
+    pub fn command_request<'b>(
+        &'b self,
+        command: &'b str,
+        arguments: Vec<&'b str>,
+    ) -> CommandRequest<'b> {
+        CommandRequest::builder()
+            .shell_id(&self.shell_id)
+            .resource_uri(&self.resource_uri)
+            .command(command)
+            .arguments(arguments)
+            .skip_cmd_shell(self.skip_cmd_shell)
+            .reference_parameters(&self.reference_parameters)
+            .build()
+    }
+
+    /// What the requests on the shell are built and exchanged with, borrowed for the time of
+    /// a command
+    pub(crate) fn channel(&mut self) -> ShellChannel<'_, F> {
+        self.last_activity = Instant::now();
+        ShellChannel {
+            ws_man: self.ws_man,
+            language: self.language,
+            exchange: &mut self.exchange,
+            shell_id: &self.shell_id,
+            resource_uri: &self.resource_uri,
+            reference_parameters: &self.reference_parameters,
+            skip_cmd_shell: self.skip_cmd_shell,
+            compression: self.compression,
+            last_activity: &mut self.last_activity,
+        }
+    }
+
+    fn delete_request(&self) -> String {
+        let request = DeleteShellRequest {
+            shell_id: &self.shell_id,
+            resource_uri: &self.resource_uri,
+            reference_parameters: &self.reference_parameters,
+        };
+
+        self.ws_man.delete_shell(request).into_element().to_string()
+    }
+}
+
+/// A fault of a shell the server no longer has is [`ProtocolError::ShellExpired`]
+pub(crate) fn expired(shell_id: &str, error: ProtocolError) -> ProtocolError {
+    match error {
+        ProtocolError::WsmanFault {
+            kind: WsmanFaultKind::ShellNotFound,
+            ..
+        } => ProtocolError::ShellExpired {
+            shell_id: shell_id.to_owned(),
+        },
+        error => error,
+    }
+}
+
+pub(crate) struct ShellChannel<'s, F> {
+    pub(crate) ws_man: &'s WsMan,
+    pub(crate) language: &'s str,
+    pub(crate) exchange: &'s mut F,
+    pub(crate) shell_id: &'s str,
+    pub(crate) resource_uri: &'s str,
+    pub(crate) reference_parameters: &'s [OwnedElement],
+    pub(crate) skip_cmd_shell: bool,
+    pub(crate) compression: Option<StreamCompression>,
+    pub(crate) last_activity: &'s mut Instant,
+}
+
+impl<F> Drop for Shell<'_, F> {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+
+        match self.delete_on_drop.take() {
+            Some(hook) => hook(self.delete_request()),
+            None => warn!(
+                shell_id = self.shell_id,
+                "Shell dropped without being deleted, it lives until its idle timeout"
+            ),
+        }
+    }
+}
+
+fn time(duration: Duration) -> Time {
+    Time(duration.as_secs_f64())
+}
+
+fn text(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|value| !value.is_empty())
+}
@@ -0,0 +1,158 @@
+use xml::{
+    XmlError,
+    builder::Element,
+    parser::{Node, XmlDeserialize, XmlVisitor},
+};
+
+use crate::{
+    cores::{
+        Attribute, Envelope, Namespace, Signal, SignalCodeTag, Tag, TagName, TagValue, Text,
+        UnknownTagPolicy, namespaces_match,
+    },
+    error::ProtocolError,
+    soap::{SoapEnvelope, body::SoapBody},
+    ws_management::{
+        ResourceUri, SelectorSetValue, WsAction, WsMan, body::UnknownParameters,
+        transfer::response_body,
+    },
+};
+
+/// The signals a command accepts, sent as the `rsp:Code` of a `rsp:Signal`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignalCode {
+    /// Ends the command, the server sends it after the command is done to release it
+    Terminate,
+    CtrlC,
+    CtrlBreak,
+    /// Stops a PowerShell pipeline, the typo is in the URI of the protocol
+    PsCrtlC,
+}
+
+impl SignalCode {
+    pub fn as_uri(&self) -> &'static str {
+        match self {
+            SignalCode::Terminate => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/signal/terminate"
+            }
+            SignalCode::CtrlC => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/signal/ctrl_c"
+            }
+            SignalCode::CtrlBreak => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/signal/ctrl_break"
+            }
+            SignalCode::PsCrtlC => "http://schemas.microsoft.com/powershell/signal/crtl_c",
+        }
+    }
+
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        [
+            SignalCode::Terminate,
+            SignalCode::CtrlC,
+            SignalCode::CtrlBreak,
+            SignalCode::PsCrtlC,
+        ]
+        .into_iter()
+        .find(|code| code.as_uri() == uri.trim())
+    }
+}
+
+/// The content of a `rsp:Signal`
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct SignalValue<'a> {
+    pub code: Tag<'a, Text<'a>, SignalCodeTag>,
+}
+
+impl<'a> TagValue<'a> for SignalValue<'a> {
+    fn append_to_element(self, element: Element<'a>) -> Element<'a> {
+        element.add_child(self.code.into_element())
+    }
+}
+
+pub struct SignalVisitor<'a> {
+    pub code: Option<Tag<'a, Text<'a>, SignalCodeTag>>,
+}
+
+impl<'a> XmlVisitor<'a> for SignalVisitor<'a> {
+    type Value = SignalValue<'a>;
+
+    fn visit_children(
+        &mut self,
+        nodes: impl Iterator<Item = xml::parser::Node<'a, 'a>>,
+    ) -> Result<(), xml::XmlError> {
+        for node in nodes.filter(|node| node.is_element()) {
+            match (node.tag_name().name(), node.tag_name().namespace()) {
+                (SignalCodeTag::TAG_NAME, namespace)
+                    if namespaces_match(namespace, SignalCodeTag::NAMESPACE) =>
+                {
+                    self.code = Some(Tag::from_node(node)?);
+                }
+                _ => UnknownTagPolicy::handle("Signal", node)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Value, XmlError> {
+        let code = self
+            .code
+            .ok_or_else(|| XmlError::GenericError("Signal is missing its Code".to_owned()))?;
+        Ok(SignalValue { code })
+    }
+}
+
+impl<'a> XmlDeserialize<'a> for SignalValue<'a> {
+    type Visitor = SignalVisitor<'a>;
+
+    fn visitor() -> Self::Visitor {
+        SignalVisitor { code: None }
+    }
+}
+
+/// A `rsp:Signal` sent to a running command, e.g. to interrupt it or to release it once done
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct SignalRequest<'a> {
+    shell_id: &'a str,
+    #[builder(default = ResourceUri::WINDOWS_SHELL_CMD)]
+    resource_uri: &'a str,
+    command_id: uuid::Uuid,
+    code: SignalCode,
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: UnknownParameters<'a>,
+}
+
+impl WsMan {
+    /// Build the `rsp:Signal` request
+    pub fn signal<'a>(&'a self, request: SignalRequest<'a>) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let value = SignalValue::builder()
+            .code(Tag::new(request.code.as_uri()))
+            .build();
+        let signal = Tag::<SignalValue, Signal>::new(value)
+            .with_declaration(Namespace::WsmanShell)
+            .with_attribute(Attribute::CommandId(
+                request.command_id.to_string().to_uppercase().into(),
+            ));
+
+        self.invoke(
+            WsAction::ShellSignal,
+            Some(request.resource_uri),
+            SoapBody::builder().signal(signal).build(),
+            None,
+            Some(SelectorSetValue::new().add_selector("ShellId", request.shell_id)),
+        )
+        .echo_parameters(request.reference_parameters)
+    }
+}
+
+/// The response to a [`SignalRequest`], the body carries nothing of use
+#[derive(Debug, Clone, Copy)]
+pub struct SignalResponse;
+
+impl SignalResponse {
+    /// Parse a `rsp:SignalResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(envelope: Node<'_, '_>, language: &str) -> Result<Self, ProtocolError> {
+        response_body(envelope, WsAction::ShellSignalResponse, language)?;
+        Ok(Self)
+    }
+}
@@ -0,0 +1,310 @@
+//! The Plain LZ77 variant of Xpress, MS-XCA 2.3 and 2.4, compressing the shell streams.
+//!
+//! The compressed data interleaves 32-bit flag words with the tokens they describe, most
+//! significant bit first: a clear bit is a literal byte, a set bit a match of a length and an
+//! offset of at most 8192 bytes back into the output.
+
+use crate::error::ProtocolError;
+
+const MIN_MATCH: usize = 3;
+const MAX_OFFSET: usize = 8192;
+const HASH_BITS: u32 = 13;
+
+/// Decompress `input`, the output is at most `max_len` bytes long
+pub fn decompress(input: &[u8], max_len: usize) -> Result<Vec<u8>, ProtocolError> {
+    let mut reader = Reader { input, position: 0 };
+    let mut output = Vec::with_capacity(max_len);
+    let mut flags = 0u32;
+    let mut flag_count = 0;
+    let mut last_length_half_byte = None;
+
+    loop {
+        if flag_count == 0 {
+            if reader.is_empty() {
+                break;
+            }
+            flags = reader.u32()?;
+            flag_count = 32;
+        }
+        flag_count -= 1;
+
+        // The unused flags of the last word are set, the end of the input ends a match
+        if reader.is_empty() {
+            break;
+        }
+
+        if flags & (1 << flag_count) == 0 {
+            output.push(reader.u8()?);
+        } else {
+            let match_bytes = usize::from(reader.u16()?);
+            let offset = match_bytes / 8 + 1;
+            let mut length = match_bytes % 8;
+
+            if length == 7 {
+                length = match last_length_half_byte.take() {
+                    Some(position) => usize::from(input[position] >> 4),
+                    None => {
+                        last_length_half_byte = Some(reader.position);
+                        usize::from(reader.u8()? & 0x0F)
+                    }
+                };
+
+                if length == 15 {
+                    length = usize::from(reader.u8()?);
+                    if length == 255 {
+                        length = usize::from(reader.u16()?);
+                        if length == 0 {
+                            length = reader.u32()? as usize;
+                        }
+                        length = length.checked_sub(15 + 7).ok_or_else(|| {
+                            invalid(format!("match length {length} is too short"))
+                        })?;
+                    }
+                    length += 15;
+                }
+                length += 7;
+            }
+            length += MIN_MATCH;
+
+            if offset > output.len() {
+                return Err(invalid(format!(
+                    "match offset {offset} is before the start of the output"
+                )));
+            }
+            if output.len() + length > max_len {
+                return Err(invalid(format!(
+                    "the output is longer than {max_len} bytes"
+                )));
+            }
+            // The match may overlap the bytes it produces
+            let start = output.len() - offset;
+            for index in start..start + length {
+                output.push(output[index]);
+            }
+        }
+
+        if output.len() > max_len {
+            return Err(invalid(format!(
+                "the output is longer than {max_len} bytes"
+            )));
+        }
+    }
+
+    Ok(output)
+}
+
+/// Compress `input` with the greedy matches of a hash table of the last positions of 3 bytes
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() + input.len() / 8 + 4);
+    let mut flags = 0u32;
+    let mut flag_count = 0;
+    let mut flag_position = 0;
+    output.extend_from_slice(&[0; 4]);
+    let mut last_length_half_byte: Option<usize> = None;
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+
+    let mut position = 0;
+    while position < input.len() {
+        let (offset, match_length) = find_match(input, position, &mut table);
+
+        if match_length >= MIN_MATCH {
+            let mut length = match_length - MIN_MATCH;
+            let offset_bits = ((offset - 1) << 3) as u16;
+            if length < 7 {
+                output.extend_from_slice(&(offset_bits | length as u16).to_le_bytes());
+            } else {
+                output.extend_from_slice(&(offset_bits | 7).to_le_bytes());
+                length -= 7;
+
+                let half_byte = length.min(15) as u8;
+                match last_length_half_byte.take() {
+                    Some(position) => output[position] |= half_byte << 4,
+                    None => {
+                        last_length_half_byte = Some(output.len());
+                        output.push(half_byte);
+                    }
+                }
+
+                if length >= 15 {
+                    length -= 15;
+                    if length < 255 {
+                        output.push(length as u8);
+                    } else {
+                        output.push(255);
+                        // The full length less the minimum, past the nibble and the byte
+                        let length = length + 15 + 7;
+                        match u16::try_from(length) {
+                            Ok(length) => output.extend_from_slice(&length.to_le_bytes()),
+                            Err(_) => {
+                                output.extend_from_slice(&0u16.to_le_bytes());
+                                output.extend_from_slice(&(length as u32).to_le_bytes());
+                            }
+                        }
+                    }
+                }
+            }
+
+            let end = position + match_length;
+            for skipped in position + 1..end {
+                insert(input, skipped, &mut table);
+            }
+            position = end;
+            flags = (flags << 1) | 1;
+        } else {
+            output.push(input[position]);
+            position += 1;
+            flags <<= 1;
+        }
+
+        flag_count += 1;
+        if flag_count == 32 {
+            output[flag_position..flag_position + 4].copy_from_slice(&flags.to_le_bytes());
+            flag_count = 0;
+            flag_position = output.len();
+            output.extend_from_slice(&[0; 4]);
+        }
+    }
+
+    // The unused flags are set, a decompressor finds the end of the input on a match
+    let unused = 32 - flag_count;
+    let flags = ((u64::from(flags) << unused) | ((1u64 << unused) - 1)) as u32;
+    output[flag_position..flag_position + 4].copy_from_slice(&flags.to_le_bytes());
+
+    output
+}
+
+/// The offset and length of the longest match at `position` of the last position with the same
+/// 3 bytes, recording `position` as the last one
+fn find_match(input: &[u8], position: usize, table: &mut [usize]) -> (usize, usize) {
+    let Some(hash) = hash(input, position) else {
+        return (0, 0);
+    };
+    let candidate = std::mem::replace(&mut table[hash], position);
+    if candidate == usize::MAX || position - candidate > MAX_OFFSET {
+        return (0, 0);
+    }
+
+    let offset = position - candidate;
+    (offset, length_of(offset, input, position))
+}
+
+fn length_of(offset: usize, input: &[u8], position: usize) -> usize {
+    input[position..]
+        .iter()
+        .zip(&input[position - offset..])
+        .take_while(|(byte, earlier)| byte == earlier)
+        .count()
+}
+
+fn insert(input: &[u8], position: usize, table: &mut [usize]) {
+    if let Some(hash) = hash(input, position) {
+        table[hash] = position;
+    }
+}
+
+fn hash(input: &[u8], position: usize) -> Option<usize> {
+    let bytes = input.get(position..position + MIN_MATCH)?;
+    let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+    Some((value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize)
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn is_empty(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ProtocolError> {
+        let bytes = self
+            .input
+            .get(self.position..self.position + N)
+            .ok_or_else(|| invalid("the input is truncated".to_owned()))?;
+        self.position += N;
+        Ok(bytes.try_into().expect("slice of N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, ProtocolError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ProtocolError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, ProtocolError> {
+        self.take().map(u32::from_le_bytes)
+    }
+}
+
+fn invalid(reason: String) -> ProtocolError {
+    ProtocolError::InvalidCompressedData(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(input: &[u8]) -> Vec<u8> {
+        let compressed = compress(input);
+        let decompressed = decompress(&compressed, input.len()).unwrap();
+        assert_eq!(decompressed, input);
+        compressed
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(b"");
+        round_trip(b"a");
+        round_trip(b"abcabcabcabcabc");
+
+        let repeated = b"Directory of C:\\Windows\r\n".repeat(200);
+        assert!(round_trip(&repeated).len() < repeated.len() / 10);
+
+        // Every length encoding, the nibble, the byte, the u16
+        for length in [10, 25, 100, 300, 5000, 65536] {
+            round_trip(&vec![b'x'; length]);
+        }
+
+        let noise: Vec<u8> = (0u32..20000)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        round_trip(&noise);
+    }
+
+    #[test]
+    fn test_decompress_reference() {
+        // MS-XCA 3.1, "abcdefghijklmnopqrstuvwxyz"
+        let compressed = [
+            0x3f, 0x00, 0x00, 0x00, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a,
+            0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x71, 0x72, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78,
+            0x79, 0x7a,
+        ];
+        assert_eq!(
+            decompress(&compressed, 26).unwrap(),
+            b"abcdefghijklmnopqrstuvwxyz"
+        );
+
+        // MS-XCA 3.2, "abc" repeated 100 times
+        let compressed = [
+            0xff, 0xff, 0xff, 0x1f, 0x61, 0x62, 0x63, 0x17, 0x00, 0x0f, 0xff, 0x26, 0x01,
+        ];
+        assert_eq!(decompress(&compressed, 300).unwrap(), b"abc".repeat(100));
+    }
+
+    #[test]
+    fn test_decompress_errors() {
+        // A match before the start of the output
+        assert!(matches!(
+            decompress(&[0xff, 0xff, 0xff, 0xff, 0x00, 0x00], 16),
+            Err(ProtocolError::InvalidCompressedData(_))
+        ));
+        // A truncated flag word
+        assert!(decompress(&[0x00, 0x00], 16).is_err());
+        // Longer than announced
+        assert!(decompress(&compress(&[b'x'; 100]), 50).is_err());
+    }
+}
@@ -4,10 +4,18 @@ use crate::{
     cores::*,
     rsp::{
         commandline::CommandLineValue,
+        disconnect::{ConnectValue, DisconnectValue},
         receive::{ReceiveResponseValue, ReceiveValue},
         rsp::ShellValue,
+        send::SendValue,
+        signal::SignalValue,
+    },
+    ws_management::{
+        body::{EnumerateValue, PullValue, ReleaseValue, RenewValue, ResourceCreatedValue},
+        eventing::SubscribeValue,
+        fragment::XmlFragmentValue,
+        invoke::MethodInput,
     },
-    ws_management::body::ResourceCreatedValue,
 };
 
 #[derive(Debug, Clone, typed_builder::TypedBuilder, SimpleTagValue, SimpleXmlDeserialize)]
@@ -24,13 +32,28 @@ pub struct SoapBody<'a> {
     #[builder(default, setter(into, strip_option))]
     pub delete: Option<Tag<'a, Text<'a>, Delete>>,
     #[builder(default, setter(into, strip_option))]
-    pub enumerate: Option<Tag<'a, TagList<'a>, Enumerate>>,
+    pub enumerate: Option<Tag<'a, EnumerateValue<'a>, Enumerate>>,
     #[builder(default, setter(into, strip_option))]
-    pub pull: Option<Tag<'a, TagList<'a>, Pull>>,
+    pub pull: Option<Tag<'a, PullValue<'a>, Pull>>,
     #[builder(default, setter(into, strip_option))]
-    pub release: Option<Tag<'a, TagList<'a>, Release>>,
+    pub release: Option<Tag<'a, ReleaseValue<'a>, Release>>,
     #[builder(default, setter(into, strip_option))]
     pub get_status: Option<Tag<'a, TagList<'a>, GetStatus>>,
+    /// Fragment-level Get/Put, see [`XmlFragmentValue`]
+    #[builder(default, setter(into, strip_option))]
+    pub xml_fragment: Option<Tag<'a, XmlFragmentValue<'a>, XmlFragment>>,
+
+    /// WS-Eventing operations, see [`SubscribeRequest`](crate::ws_management::SubscribeRequest)
+    #[builder(default, setter(into, strip_option))]
+    pub subscribe: Option<SubscribeValue<'a>>,
+    #[builder(default, setter(into, strip_option))]
+    pub renew: Option<Tag<'a, RenewValue<'a>, Renew>>,
+    #[builder(default, setter(into, strip_option))]
+    pub unsubscribe: Option<Tag<'a, Empty, Unsubscribe>>,
+
+    /// Custom action input, see [`InvokeRequest`](crate::ws_management::InvokeRequest)
+    #[builder(default, setter(into, strip_option))]
+    pub method_input: Option<MethodInput<'a>>,
 
     /// WS-Transfer operations
     #[builder(default, setter(into, strip_option))]
@@ -50,7 +73,13 @@ pub struct SoapBody<'a> {
     #[builder(default, setter(into, strip_option))]
     pub command_response: Option<Tag<'a, Tag<'a, WsUuid, CommandId>, CommandResponse>>,
     #[builder(default, setter(into, strip_option))]
-    pub send: Option<Tag<'a, TagList<'a>, Send>>,
+    pub send: Option<Tag<'a, SendValue<'a>, Send>>,
+    #[builder(default, setter(into, strip_option))]
+    pub signal: Option<Tag<'a, SignalValue<'a>, Signal>>,
+    #[builder(default, setter(into, strip_option))]
+    pub disconnect: Option<Tag<'a, DisconnectValue<'a>, Disconnect>>,
+    #[builder(default, setter(into, strip_option))]
+    pub reconnect: Option<Tag<'a, Empty, Reconnect>>,
     #[builder(default, setter(into, strip_option))]
-    pub signal: Option<Tag<'a, TagList<'a>, Signal>>,
+    pub connect: Option<Tag<'a, ConnectValue<'a>, Connect>>,
 }
@@ -0,0 +1,145 @@
+use xml::{XmlError, builder::Element, parser::Node};
+
+use crate::cores::namespaces_match;
+
+/// A SOAP header that is not part of [`SoapHeaders`](crate::soap::header::SoapHeaders).
+///
+/// Gateways and vendors add their own headers to the envelope. Implement this trait
+/// for such a header and register it on [`CustomHeaders`] to send it, or look it up
+/// on a parsed envelope to read it back.
+///
+/// A header is recognized by its qualified name, i.e. the namespace URI and the local name.
+/// The prefix used on the wire is irrelevant.
+pub trait SoapHeader<'a>: Sized {
+    /// Namespace URI of the header element
+    const NAMESPACE: &'static str;
+    /// Local name of the header element
+    const NAME: &'static str;
+
+    /// Build the header element.
+    /// The element needs to declare its own namespace, the envelope does not know about it.
+    fn to_header_element(&self) -> Element<'a>;
+
+    /// Parse the header from a received node matching [`SoapHeader::matches`]
+    fn from_header_node(node: Node<'a, 'a>) -> Result<Self, XmlError>;
+
+    fn matches(node: &Node<'a, 'a>) -> bool {
+        node.is_element()
+            && node.tag_name().name() == Self::NAME
+            && namespaces_match(node.tag_name().namespace(), Some(Self::NAMESPACE))
+    }
+}
+
+/// Collection of the custom headers of an envelope.
+///
+/// Outgoing headers are serialized after the well-known headers. Incoming header
+/// elements that are not known to [`SoapHeaders`](crate::soap::header::SoapHeaders)
+/// are kept as raw nodes so they can be decoded on demand.
+#[derive(Debug, Clone, Default)]
+pub struct CustomHeaders<'a> {
+    outgoing: Vec<Element<'a>>,
+    received: Vec<Node<'a, 'a>>,
+}
+
+impl<'a> CustomHeaders<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a header to be sent with the envelope
+    pub fn with_header<H: SoapHeader<'a>>(mut self, header: &H) -> Self {
+        self.push(header);
+        self
+    }
+
+    pub fn push<H: SoapHeader<'a>>(&mut self, header: &H) {
+        self.outgoing.push(header.to_header_element());
+    }
+
+    /// Send an already built header element, e.g. an echoed reference parameter
+    pub fn push_element(&mut self, element: Element<'a>) {
+        self.outgoing.push(element);
+    }
+
+    /// Find and decode the first received header with the qualified name of `H`
+    pub fn get<H: SoapHeader<'a>>(&self) -> Option<Result<H, XmlError>> {
+        self.received
+            .iter()
+            .find(|node| H::matches(node))
+            .map(|node| H::from_header_node(*node))
+    }
+
+    /// Raw received header nodes that are not part of the well-known headers
+    pub fn received(&self) -> impl Iterator<Item = &Node<'a, 'a>> {
+        self.received.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outgoing.is_empty() && self.received.is_empty()
+    }
+
+    pub(crate) fn push_received(&mut self, node: Node<'a, 'a>) {
+        self.received.push(node);
+    }
+
+    pub(crate) fn into_elements(self) -> Vec<Element<'a>> {
+        self.outgoing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct GatewayRoute<'a> {
+        route: &'a str,
+    }
+
+    impl<'a> SoapHeader<'a> for GatewayRoute<'a> {
+        const NAMESPACE: &'static str = "urn:example:gateway";
+        const NAME: &'static str = "Route";
+
+        fn to_header_element(&self) -> Element<'a> {
+            Element::new(Self::NAME)
+                .set_namespace(Self::NAMESPACE)
+                .add_namespace_declaration(Self::NAMESPACE, Some("gw"))
+                .set_text(self.route)
+        }
+
+        fn from_header_node(node: Node<'a, 'a>) -> Result<Self, XmlError> {
+            Ok(Self {
+                route: node.text().unwrap_or_default(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_custom_header_serializes() {
+        let headers = CustomHeaders::new().with_header(&GatewayRoute { route: "east" });
+        let elements = headers.into_elements();
+
+        assert_eq!(elements.len(), 1);
+        assert_eq!(
+            elements[0].to_string(),
+            r#"<gw:Route xmlns:gw="urn:example:gateway">east</gw:Route>"#
+        );
+    }
+
+    #[test]
+    fn test_custom_header_matched_by_qname() {
+        let xml = r#"<h xmlns:other="urn:example:gateway" xmlns:bad="urn:example:unrelated">
+            <bad:Route>west</bad:Route>
+            <other:Route>east</other:Route>
+        </h>"#;
+        let doc = xml::parser::parse(xml).unwrap();
+
+        let mut headers = CustomHeaders::new();
+        for child in doc.root_element().children().filter(|c| c.is_element()) {
+            headers.push_received(child);
+        }
+
+        let route = headers.get::<GatewayRoute>().unwrap().unwrap();
+        assert_eq!(route, GatewayRoute { route: "east" });
+    }
+}
@@ -0,0 +1,161 @@
+use xml::parser::Node;
+
+use crate::{
+    cores::{Namespace, QName, Text, namespaces_match},
+    error::ProtocolError,
+    soap::SoapVersion,
+    ws_management::WsmanFaultKind,
+};
+
+/// A `s:Fault` of either SOAP version.
+///
+/// SOAP 1.2 carries `s:Code/s:Value`, `s:Code/s:Subcode/s:Value`, `s:Reason/s:Text` and `s:Detail`,
+/// SOAP 1.1 carries `faultcode`, `faultstring` and `detail`. For SOAP 1.1 the WS-Management
+/// subcode is the `faultcode` itself when it is not one of the SOAP codes.
+#[derive(Debug, Clone)]
+pub struct SoapFault<'a> {
+    pub version: SoapVersion,
+    pub code: Option<QName<'a>>,
+    pub subcode: Option<QName<'a>>,
+    /// Localized reasons, see [`Text::select_localized`]
+    pub reasons: Vec<Text<'a>>,
+    /// `Code` attribute of the `WSManFault` in the detail
+    pub wsman_code: Option<u32>,
+    pub detail: Option<Node<'a, 'a>>,
+}
+
+/// First child element with the namespace URI `namespace` and local name `name`
+fn child_named<'a>(
+    node: Node<'a, 'a>,
+    namespace: Option<&str>,
+    name: &str,
+) -> Option<Node<'a, 'a>> {
+    node.children().find(|child| {
+        child.is_element()
+            && child.tag_name().name() == name
+            && namespaces_match(child.tag_name().namespace(), namespace)
+    })
+}
+
+fn qname_of<'a>(node: Option<Node<'a, 'a>>) -> Result<Option<QName<'a>>, xml::XmlError> {
+    node.and_then(|node| node.text().map(|text| QName::resolve(text, node)))
+        .transpose()
+}
+
+fn text_of<'a>(node: Node<'a, 'a>) -> Option<Text<'a>> {
+    let text = Text::from(node.text()?.trim());
+    Some(
+        match node.attribute(("http://www.w3.org/XML/1998/namespace", "lang")) {
+            Some(lang) => text.with_lang(lang),
+            None => text,
+        },
+    )
+}
+
+impl<'a> SoapFault<'a> {
+    /// The fault in the body of `envelope`, if any
+    pub fn find(envelope: Node<'a, 'a>) -> Option<Result<Self, xml::XmlError>> {
+        let namespace = envelope.tag_name().namespace();
+        let body = child_named(envelope, namespace, "Body")?;
+        let fault = child_named(body, namespace, "Fault")?;
+        Some(Self::from_node(fault))
+    }
+
+    pub fn from_node(fault: Node<'a, 'a>) -> Result<Self, xml::XmlError> {
+        let version = fault
+            .tag_name()
+            .namespace()
+            .and_then(SoapVersion::from_namespace)
+            .unwrap_or_default();
+
+        let (code, subcode, reasons, detail) = match version {
+            SoapVersion::Soap12 => {
+                let soap = Some(version.namespace().uri());
+                let code_node = child_named(fault, soap, "Code");
+                let code = qname_of(code_node.and_then(|code| child_named(code, soap, "Value")))?;
+                let subcode = qname_of(
+                    code_node
+                        .and_then(|code| child_named(code, soap, "Subcode"))
+                        .and_then(|subcode| child_named(subcode, soap, "Value")),
+                )?;
+                let reasons = child_named(fault, soap, "Reason")
+                    .map(|reason| {
+                        reason
+                            .children()
+                            .filter(|child| {
+                                child.is_element()
+                                    && child.tag_name().name() == "Text"
+                                    && namespaces_match(child.tag_name().namespace(), soap)
+                            })
+                            .filter_map(text_of)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                (code, subcode, reasons, child_named(fault, soap, "Detail"))
+            }
+            // The SOAP 1.1 fault children are unqualified, unless the envelope declares its
+            // namespace as the default one
+            SoapVersion::Soap11 => {
+                let soap11_child = |name| {
+                    child_named(fault, None, name)
+                        .or_else(|| child_named(fault, fault.tag_name().namespace(), name))
+                };
+                let code = qname_of(soap11_child("faultcode"))?;
+                let subcode = code
+                    .clone()
+                    .filter(|code| code.namespace() != Some(SoapVersion::Soap11.namespace().uri()));
+                let reasons = soap11_child("faultstring")
+                    .and_then(text_of)
+                    .into_iter()
+                    .collect();
+
+                (code, subcode, reasons, soap11_child("detail"))
+            }
+        };
+
+        let wsman_code = detail
+            .and_then(|detail| {
+                detail.descendants().find(|node| {
+                    node.is_element()
+                        && node.tag_name().name() == "WSManFault"
+                        && namespaces_match(
+                            node.tag_name().namespace(),
+                            Some(Namespace::WsmanFault.uri()),
+                        )
+                })
+            })
+            .and_then(|wsman_fault| wsman_fault.attribute("Code"))
+            .and_then(|code| code.trim().parse().ok());
+
+        Ok(SoapFault {
+            version,
+            code,
+            subcode,
+            reasons,
+            wsman_code,
+            detail,
+        })
+    }
+
+    /// The reason in `language`, falling back as described in [`Text::select_localized`]
+    pub fn reason(&self, language: &str) -> Option<&str> {
+        Text::select_localized(&self.reasons, language).map(AsRef::as_ref)
+    }
+
+    pub fn kind(&self) -> WsmanFaultKind {
+        WsmanFaultKind::classify(
+            self.wsman_code,
+            self.subcode.as_ref().map(QName::local_name),
+        )
+    }
+
+    pub fn into_error(self, language: &str) -> ProtocolError {
+        ProtocolError::WsmanFault {
+            kind: self.kind(),
+            code: self.wsman_code,
+            subcode: self.subcode.as_ref().map(ToString::to_string),
+            reason: self.reason(language).unwrap_or_default().to_string(),
+        }
+    }
+}
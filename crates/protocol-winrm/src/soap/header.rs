@@ -20,6 +20,10 @@ pub struct SoapHeaders<'a> {
     #[builder(default, setter(into, strip_option))]
     pub reply_to: Option<Tag<'a, AddressValue<'a>, ReplyTo>>,
     #[builder(default, setter(into, strip_option))]
+    pub fault_to: Option<Tag<'a, AddressValue<'a>, FaultTo>>,
+    #[builder(default, setter(into, strip_option))]
+    pub from: Option<Tag<'a, AddressValue<'a>, From>>,
+    #[builder(default, setter(into, strip_option))]
     pub message_id: Option<Tag<'a, WsUuid, MessageID>>,
     #[builder(default, setter(into, strip_option))]
     pub relates_to: Option<Tag<'a, Text<'a>, RelatesTo>>,
@@ -46,5 +50,7 @@ pub struct SoapHeaders<'a> {
     #[builder(default, setter(into, strip_option))]
     pub operation_timeout: Option<Tag<'a, Time, OperationTimeout>>,
     #[builder(default, setter(into, strip_option))]
+    pub fragment_transfer: Option<Tag<'a, Text<'a>, FragmentTransfer>>,
+    #[builder(default, setter(into, strip_option))]
     pub compression_type: Option<Tag<'a, Text<'a>, CompressionType>>,
 }
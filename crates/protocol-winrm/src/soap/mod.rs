@@ -1,11 +1,20 @@
 pub mod body;
+pub mod custom_header;
+pub mod fault;
 pub mod header;
+pub mod must_understand;
 pub mod parsing;
+pub mod version;
+
+pub use custom_header::{CustomHeaders, SoapHeader};
+pub use fault::SoapFault;
+pub use must_understand::HeaderRegistry;
+pub use version::SoapVersion;
 
 use xml::parser::{XmlDeserialize, XmlVisitor};
 
 use crate::{
-    cores::{Tag, TagValue, tag_name::*},
+    cores::{Tag, TagName, TagValue, envelope, tag_name::*},
     soap::{body::SoapBody, header::SoapHeaders},
 };
 
@@ -15,24 +24,74 @@ pub struct SoapEnvelope<'a> {
     pub header: Option<Tag<'a, SoapHeaders<'a>, Header>>,
     #[builder(setter(into))]
     pub body: Tag<'a, SoapBody<'a>, Body>,
+    /// Headers that are not part of [`SoapHeaders`], see [`SoapHeader`]
+    #[builder(default)]
+    pub custom_headers: CustomHeaders<'a>,
+    #[builder(default)]
+    pub soap_version: SoapVersion,
+}
+
+impl<'a> std::convert::From<envelope::Envelope<'a, SoapHeaders<'a>, SoapBody<'a>>>
+    for SoapEnvelope<'a>
+{
+    fn from(envelope: envelope::Envelope<'a, SoapHeaders<'a>, SoapBody<'a>>) -> Self {
+        SoapEnvelope {
+            header: Some(envelope.header),
+            body: envelope.body,
+            custom_headers: envelope.custom_headers,
+            soap_version: envelope.soap_version,
+        }
+    }
+}
+
+impl SoapEnvelope<'_> {
+    /// Fail if a received header marked `s:mustUnderstand` is not understood, see [`HeaderRegistry`]
+    pub fn check_must_understand(
+        &self,
+        registry: &HeaderRegistry,
+    ) -> Result<(), crate::error::ProtocolError> {
+        registry.validate(&self.custom_headers)
+    }
+
+    /// The `p:SessionId` echoed by the server, if any
+    pub fn session_id(&self) -> Option<uuid::Uuid> {
+        self.header
+            .as_ref()
+            .and_then(|header| header.value.session_id.as_ref())
+            .map(|session_id| session_id.value.0)
+    }
 }
 
 impl<'a> TagValue<'a> for SoapEnvelope<'a> {
     fn append_to_element(self, element: xml::builder::Element<'a>) -> xml::builder::Element<'a> {
         let envelope = element;
-
-        if let Some(header) = self.header {
-            envelope.add_child(header.into_element())
+        let custom_headers = self.custom_headers.into_elements();
+
+        let header = match self.header {
+            Some(header) => Some(header.into_element()),
+            None if !custom_headers.is_empty() => Some(
+                xml::builder::Element::new(Header::TAG_NAME)
+                    .set_namespace_optional(Header::NAMESPACE),
+            ),
+            None => None,
+        };
+
+        let envelope = if let Some(header) = header {
+            envelope.add_child(header.add_children(custom_headers))
         } else {
             envelope
         }
-        .add_child(self.body.into_element())
+        .add_child(self.body.into_element());
+
+        self.soap_version.apply(envelope)
     }
 }
 
 pub struct SoapEnvelopeVisitor<'a> {
     pub header: Option<Tag<'a, SoapHeaders<'a>, Header>>,
     pub body: Option<Tag<'a, SoapBody<'a>, Body>>,
+    pub custom_headers: CustomHeaders<'a>,
+    pub soap_version: SoapVersion,
 }
 
 impl<'a> XmlVisitor<'a> for SoapEnvelopeVisitor<'a> {
@@ -51,12 +110,14 @@ impl<'a> XmlVisitor<'a> for SoapEnvelopeVisitor<'a> {
     fn visit_node(&mut self, node: xml::parser::Node<'a, 'a>) -> Result<(), xml::XmlError> {
         // Remove the is_root() check as it prevents parsing document root elements
         // The node should be an Envelope element regardless of its root status
+        self.soap_version = SoapVersion::from_node(node);
 
         let header: Option<Tag<'_, SoapHeaders<'a>, Header>> = node
             .children()
-            .find(|child| child.tag_name().name() == Header::TAG_NAME)
+            .find(|child| Header::matches_node(child))
             .map(|child| {
-                Tag::from_node(child).map_err(|e| xml::XmlError::InvalidXml(e.to_string()))
+                envelope::parse_header(child, &mut self.custom_headers)
+                    .map_err(|e| xml::XmlError::InvalidXml(e.to_string()))
             })
             .transpose()?;
 
@@ -65,7 +126,7 @@ impl<'a> XmlVisitor<'a> for SoapEnvelopeVisitor<'a> {
 
         let body: Option<Tag<'_, SoapBody<'a>, Body>> = node
             .children()
-            .find(|child| child.tag_name().name() == Body::TAG_NAME)
+            .find(|child| Body::matches_node(child))
             .map(|child| {
                 Tag::from_node(child).map_err(|e| xml::XmlError::InvalidXml(e.to_string()))
             })
@@ -88,6 +149,8 @@ impl<'a> XmlVisitor<'a> for SoapEnvelopeVisitor<'a> {
             body: self
                 .body
                 .ok_or_else(|| xml::XmlError::InvalidXml("Missing Soap Body".to_string()))?,
+            custom_headers: self.custom_headers,
+            soap_version: self.soap_version,
         })
     }
 }
@@ -99,6 +162,8 @@ impl<'a> XmlDeserialize<'a> for SoapEnvelope<'a> {
         SoapEnvelopeVisitor {
             header: None,
             body: None,
+            custom_headers: CustomHeaders::new(),
+            soap_version: SoapVersion::default(),
         }
     }
 }
@@ -0,0 +1,104 @@
+use xml::parser::Node;
+
+use crate::{
+    cores::{Namespace, namespaces_match},
+    error::ProtocolError,
+    soap::{CustomHeaders, SoapHeader},
+};
+
+/// Whether a received header is marked `s:mustUnderstand`, for either SOAP version
+pub fn is_must_understand(node: &Node<'_, '_>) -> bool {
+    [Namespace::SoapEnvelope2003, Namespace::SoapEnvelope2000]
+        .iter()
+        .filter_map(|namespace| node.attribute((namespace.uri(), "mustUnderstand")))
+        .any(|value| matches!(value.trim(), "true" | "1"))
+}
+
+/// The headers the client understands besides [`SoapHeaders`](crate::soap::header::SoapHeaders).
+///
+/// The well-known headers are always understood. A received header that isn't one of them,
+/// nor registered here, and is marked `s:mustUnderstand` makes the response fail with
+/// [`ProtocolError::NotUnderstood`] instead of being silently ignored.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderRegistry {
+    understood: Vec<(String, String)>,
+}
+
+impl HeaderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Understand the custom header `H`
+    pub fn register<'a, H: SoapHeader<'a>>(self) -> Self {
+        self.register_name(H::NAMESPACE, H::NAME)
+    }
+
+    pub fn register_name(mut self, namespace: &str, name: &str) -> Self {
+        self.understood
+            .push((namespace.to_owned(), name.to_owned()));
+        self
+    }
+
+    pub fn understands(&self, node: &Node<'_, '_>) -> bool {
+        let tag_name = node.tag_name();
+        self.understood.iter().any(|(namespace, name)| {
+            namespaces_match(tag_name.namespace(), Some(namespace)) && tag_name.name() == name
+        })
+    }
+
+    /// The received custom headers marked `s:mustUnderstand` that are not understood
+    pub fn not_understood<'h, 'a>(
+        &'h self,
+        headers: &'h CustomHeaders<'a>,
+    ) -> impl Iterator<Item = Node<'a, 'a>> + 'h {
+        headers
+            .received()
+            .filter(|node| is_must_understand(node) && !self.understands(node))
+            .copied()
+    }
+
+    /// Fail with the qualified names, in `{namespace}name` form, of the headers not understood
+    pub fn validate(&self, headers: &CustomHeaders<'_>) -> Result<(), ProtocolError> {
+        let headers: Vec<String> = self
+            .not_understood(headers)
+            .map(|node| {
+                let tag_name = node.tag_name();
+                match tag_name.namespace() {
+                    Some(namespace) => format!("{{{namespace}}}{}", tag_name.name()),
+                    None => tag_name.name().to_owned(),
+                }
+            })
+            .collect();
+
+        if headers.is_empty() {
+            Ok(())
+        } else {
+            Err(ProtocolError::NotUnderstood { headers })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_must_understand_versions() {
+        let xml = r#"<h xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:e="http://schemas.xmlsoap.org/soap/envelope/">
+            <a s:mustUnderstand="true"/>
+            <b e:mustUnderstand="1"/>
+            <c s:mustUnderstand="false"/>
+            <d mustUnderstand="true"/>
+        </h>"#;
+        let doc = xml::parser::parse(xml).unwrap();
+        let flags: Vec<_> = doc
+            .root_element()
+            .children()
+            .filter(|child| child.is_element())
+            .map(|child| is_must_understand(&child))
+            .collect();
+
+        assert_eq!(flags, [true, true, false, false]);
+    }
+}
@@ -0,0 +1,104 @@
+use xml::builder::Element;
+
+use crate::cores::Namespace;
+
+/// The SOAP version of an envelope.
+///
+/// Protocol types are always built against SOAP 1.2, a SOAP 1.1 envelope is the same tree moved
+/// to the SOAP 1.1 namespace, see [`SoapVersion::apply`]. Faults of both versions are read by
+/// [`super::SoapFault`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SoapVersion {
+    Soap11,
+    #[default]
+    Soap12,
+}
+
+impl SoapVersion {
+    pub fn namespace(&self) -> Namespace {
+        match self {
+            SoapVersion::Soap11 => Namespace::SoapEnvelope2000,
+            SoapVersion::Soap12 => Namespace::SoapEnvelope2003,
+        }
+    }
+
+    pub fn from_namespace(uri: &str) -> Option<Self> {
+        match Namespace::try_from(uri) {
+            Ok(Namespace::SoapEnvelope2000) => Some(SoapVersion::Soap11),
+            Ok(Namespace::SoapEnvelope2003) => Some(SoapVersion::Soap12),
+            _ => None,
+        }
+    }
+
+    /// Version of a received `Envelope` element, SOAP 1.2 when the namespace is not a SOAP one
+    pub fn from_node(node: xml::parser::Node<'_, '_>) -> Self {
+        node.tag_name()
+            .namespace()
+            .and_then(Self::from_namespace)
+            .unwrap_or_default()
+    }
+
+    /// `Content-Type` of a request carrying an envelope of this version
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SoapVersion::Soap11 => "text/xml; charset=utf-8",
+            SoapVersion::Soap12 => "application/soap+xml; charset=utf-8",
+        }
+    }
+
+    /// Guess the version from a response `Content-Type`
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type = content_type.split(';').next()?.trim();
+
+        if media_type.eq_ignore_ascii_case("application/soap+xml") {
+            Some(SoapVersion::Soap12)
+        } else if media_type.eq_ignore_ascii_case("text/xml") {
+            Some(SoapVersion::Soap11)
+        } else {
+            None
+        }
+    }
+
+    /// Move an envelope built with the SOAP 1.2 namespace to this version
+    pub fn apply<'a>(&self, envelope: Element<'a>) -> Element<'a> {
+        match self {
+            SoapVersion::Soap11 => envelope.rename_namespace(
+                Namespace::SoapEnvelope2003.uri(),
+                Namespace::SoapEnvelope2000.uri(),
+            ),
+            SoapVersion::Soap12 => envelope,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(
+            SoapVersion::from_content_type("application/soap+xml;charset=UTF-8"),
+            Some(SoapVersion::Soap12)
+        );
+        assert_eq!(
+            SoapVersion::from_content_type("text/xml; charset=utf-8"),
+            Some(SoapVersion::Soap11)
+        );
+        assert_eq!(SoapVersion::from_content_type("text/html"), None);
+    }
+
+    #[test]
+    fn test_apply_soap11() {
+        let (uri, alias) = Namespace::SoapEnvelope2003.as_tuple();
+        let envelope = Element::new("Envelope")
+            .set_namespace(uri)
+            .add_namespace_declaration(uri, alias)
+            .add_child(Element::new("Body").set_namespace(uri));
+
+        let xml = SoapVersion::Soap11.apply(envelope).to_string();
+        assert!(xml.contains(Namespace::SoapEnvelope2000.uri()));
+        assert!(!xml.contains(Namespace::SoapEnvelope2003.uri()));
+        assert!(xml.contains("<s:Body"));
+    }
+}
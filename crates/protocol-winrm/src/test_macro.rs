@@ -33,8 +33,8 @@ mod tests {
 
         // The TagValue implementation worked if we got here without panicking
 
-        // Test deserialization with a simple XML string
-        let test_xml = r#"<test>
+        // Test deserialization with a simple XML string, the tags are in the default namespace
+        let test_xml = r#"<test xmlns="http://schemas.xmlsoap.org/ws/2004/08/addressing">
             <Action>test-action</Action>
             <MessageID>msg-123</MessageID>
             <To>destination</To>
@@ -0,0 +1,74 @@
+use crate::{error::ProtocolError, soap::SoapVersion};
+
+/// An envelope to post to the WS-Management endpoint of the server, e.g. `/wsman`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoapRequest {
+    pub envelope: String,
+    /// What the `Content-Type` of the request follows
+    pub soap_version: SoapVersion,
+}
+
+impl SoapRequest {
+    /// A SOAP 1.2 envelope, the version the protocol types are built against
+    pub fn new(envelope: impl Into<String>) -> Self {
+        Self {
+            envelope: envelope.into(),
+            soap_version: SoapVersion::default(),
+        }
+    }
+
+    pub fn with_soap_version(mut self, soap_version: SoapVersion) -> Self {
+        self.soap_version = soap_version;
+        self
+    }
+}
+
+/// The envelope the server answered a [`SoapRequest`] with, a fault included
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoapResponse {
+    pub envelope: String,
+}
+
+impl SoapResponse {
+    pub fn new(envelope: impl Into<String>) -> Self {
+        Self {
+            envelope: envelope.into(),
+        }
+    }
+}
+
+/// What carries the envelopes to the server and back: an HTTP client with its
+/// authentication, a tunnel, or a test double answering canned envelopes.
+///
+/// The protocol types only build and read envelopes, a transport is what their `exchange`
+/// closures call, see [`exchange`](Self::exchange). A fault is a response, not an error of
+/// the transport, it is read as one by whoever reads the envelope.
+pub trait Transport {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Send `request` and resolve to the response of the server
+    fn send(&self, request: SoapRequest)
+    -> impl Future<Output = Result<SoapResponse, Self::Error>>;
+
+    /// [`send`](Self::send) `envelope` as SOAP 1.2, as the `exchange` of the protocol types
+    /// takes it: `|envelope| transport.exchange(envelope)`
+    fn exchange(&self, envelope: String) -> impl Future<Output = Result<String, ProtocolError>> {
+        async move {
+            self.send(SoapRequest::new(envelope))
+                .await
+                .map(|response| response.envelope)
+                .map_err(|error| ProtocolError::Transport(Box::new(error)))
+        }
+    }
+}
+
+impl<T: Transport> Transport for &T {
+    type Error = T::Error;
+
+    fn send(
+        &self,
+        request: SoapRequest,
+    ) -> impl Future<Output = Result<SoapResponse, Self::Error>> {
+        (**self).send(request)
+    }
+}
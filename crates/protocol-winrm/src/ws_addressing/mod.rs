@@ -1,12 +1,57 @@
 use protocol_macros::{SimpleTagValue, SimpleXmlDeserialize};
 
-use crate::cores::{Tag, tag_name::*, tag_value::Text};
+use crate::cores::{Attribute, Tag, tag_name::*, tag_value::Text};
 
+/// `wsa:Address` meaning "reply on the back channel", the only one WinRM supports for `wsa:ReplyTo`
+pub const ANONYMOUS_ADDRESS: &str =
+    "http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous";
+
+/// A WS-Addressing endpoint reference
 #[derive(Debug, Clone, SimpleTagValue, SimpleXmlDeserialize)]
 pub struct AddressValue<'a> {
     pub url: Tag<'a, Text<'a>, Address>,
 }
 
+impl<'a> AddressValue<'a> {
+    pub fn new(url: impl Into<Text<'a>>) -> Self {
+        Self {
+            url: Tag::new(url.into()),
+        }
+    }
+
+    /// The [`ANONYMOUS_ADDRESS`] endpoint
+    pub fn anonymous() -> Self {
+        Self::new(ANONYMOUS_ADDRESS)
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.url.value.as_ref() == ANONYMOUS_ADDRESS
+    }
+
+    pub fn address(&self) -> &str {
+        self.url.value.as_ref()
+    }
+}
+
+/// `wsa:ReplyTo`, every request needs one
+pub type ReplyToHeader<'a> = Tag<'a, AddressValue<'a>, ReplyTo>;
+/// `wsa:FaultTo`, where faults go when they should not be returned to [`ReplyToHeader`]
+pub type FaultToHeader<'a> = Tag<'a, AddressValue<'a>, FaultTo>;
+/// `wsa:From`, the sender of the message
+pub type FromHeader<'a> = Tag<'a, AddressValue<'a>, From>;
+
+pub fn reply_to(address: AddressValue<'_>) -> ReplyToHeader<'_> {
+    Tag::new(address).with_attribute(Attribute::MustUnderstand(true))
+}
+
+pub fn fault_to(address: AddressValue<'_>) -> FaultToHeader<'_> {
+    Tag::new(address).with_attribute(Attribute::MustUnderstand(true))
+}
+
+pub fn from(address: AddressValue<'_>) -> FromHeader<'_> {
+    Tag::new(address)
+}
+
 // impl<'a> TagValue<'a> for AddressValue<'a> {
 //     fn append_to_element(self, element: xml::builder::Element<'a>) -> xml::builder::Element<'a> {
 //         let inner_element = self.url.into_element();
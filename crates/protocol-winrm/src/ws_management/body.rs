@@ -5,114 +5,102 @@ use xml::{
 };
 
 use crate::{
-    cores::{ResourceURI, SelectorSet, Tag, TagValue, tag_name::*, tag_value::Text},
+    cores::{
+        Empty, OwnedElement, ResourceURI, SelectorSet, Tag, TagName, TagValue, Time, U32,
+        namespaces_match, tag_name::*, tag_value::Text,
+    },
+    soap::{CustomHeaders, SoapEnvelope},
     ws_management::SelectorSetValue,
 };
 
 // Enumeration operations
-#[derive(Debug, Clone)]
+
+/// Content of `n:Enumerate`, in schema order
+#[derive(Debug, Clone, Default, SimpleTagValue, SimpleXmlDeserialize)]
 pub struct EnumerateValue<'a> {
-    pub optimize_enumeration: Option<bool>,
-    pub max_elements: Option<u32>,
-    pub filter: Option<Text<'a>>,
+    pub filter: Option<crate::ws_management::Filter<'a>>,
+    /// Return endpoint references instead of, or with, the objects
+    pub enumeration_mode: Option<Tag<'a, Text<'a>, EnumerationMode>>,
+    /// Return the first items in the EnumerateResponse instead of waiting for a Pull
+    pub optimize_enumeration: Option<Tag<'a, Empty, OptimizeEnumeration>>,
+    /// Items to return in the EnumerateResponse, only meaningful with `optimize_enumeration`
+    pub max_elements: Option<Tag<'a, U32, WsmanMaxElements>>,
 }
 
 impl<'a> EnumerateValue<'a> {
     pub fn new() -> Self {
-        Self {
-            optimize_enumeration: None,
-            max_elements: None,
-            filter: None,
-        }
+        Self::default()
     }
 
     pub fn with_optimization(mut self, optimize: bool) -> Self {
-        self.optimize_enumeration = Some(optimize);
+        self.optimize_enumeration = optimize.then(|| Tag::new(Empty));
         self
     }
 
     pub fn with_max_elements(mut self, max: u32) -> Self {
-        self.max_elements = Some(max);
+        self.max_elements = Some(Tag::new(max));
         self
     }
 
-    pub fn with_filter(mut self, filter: Text<'a>) -> Self {
-        self.filter = Some(filter);
+    pub fn with_mode(mut self, mode: crate::ws_management::EnumerationMode) -> Self {
+        self.enumeration_mode = Some(Tag::new(mode.as_str()));
         self
     }
-}
-
-impl<'a> Default for EnumerateValue<'a> {
-    fn default() -> Self {
-        Self::new()
-    }
-}
 
-impl<'a> TagValue<'a> for EnumerateValue<'a> {
-    fn append_to_element(self, _element: Element<'a>) -> Element<'a> {
-        todo!("[EnumerateValue] Implement into_element");
+    pub fn with_filter(mut self, filter: crate::ws_management::Filter<'a>) -> Self {
+        self.filter = Some(filter);
+        self
     }
 }
 
-#[derive(Debug, Clone)]
+/// Content of `n:Pull`, in schema order
+#[derive(Debug, Clone, SimpleTagValue, SimpleXmlDeserialize)]
 pub struct PullValue<'a> {
-    pub enumeration_context: Text<'a>,
-    pub max_elements: Option<u32>,
+    pub enumeration_context: Tag<'a, Text<'a>, EnumerationContext>,
+    pub max_elements: Option<Tag<'a, U32, MaxElements>>,
+    pub max_characters: Option<Tag<'a, U32, MaxCharacters>>,
 }
 
 impl<'a> PullValue<'a> {
     pub fn new(enumeration_context: Text<'a>) -> Self {
         Self {
-            enumeration_context,
+            enumeration_context: Tag::new(enumeration_context),
             max_elements: None,
+            max_characters: None,
         }
     }
 
     pub fn with_max_elements(mut self, max: u32) -> Self {
-        self.max_elements = Some(max);
+        self.max_elements = Some(Tag::new(max));
         self
     }
-}
-
-impl<'a> TagValue<'a> for PullValue<'a> {
-    fn append_to_element(self, mut element: Element<'a>) -> Element<'a> {
-        let context_elem = Element::new("EnumerationContext")
-            .set_namespace("http://schemas.xmlsoap.org/ws/2004/09/enumeration")
-            .set_text(self.enumeration_context);
-
-        element = element.add_child(context_elem);
-
-        if let Some(max) = self.max_elements {
-            let max_elem = Element::new("MaxElements").set_text_owned(max.to_string());
 
-            element = element.add_child(max_elem);
-        }
-
-        element
+    pub fn with_max_characters(mut self, max: u32) -> Self {
+        self.max_characters = Some(Tag::new(max));
+        self
     }
 }
 
-#[derive(Debug, Clone)]
+/// Content of `n:Release`
+#[derive(Debug, Clone, SimpleTagValue, SimpleXmlDeserialize)]
 pub struct ReleaseValue<'a> {
-    pub enumeration_context: Text<'a>,
+    pub enumeration_context: Tag<'a, Text<'a>, EnumerationContext>,
 }
 
 impl<'a> ReleaseValue<'a> {
     pub fn new(enumeration_context: Text<'a>) -> Self {
         Self {
-            enumeration_context,
+            enumeration_context: Tag::new(enumeration_context),
         }
     }
 }
 
-impl<'a> TagValue<'a> for ReleaseValue<'a> {
-    fn append_to_element(self, element: Element<'a>) -> Element<'a> {
-        let context_elem = Element::new("EnumerationContext")
-            .set_namespace("http://schemas.xmlsoap.org/ws/2004/09/enumeration")
-            .set_text(self.enumeration_context);
+// Eventing operations
 
-        element.add_child(context_elem)
-    }
+/// Content of `e:Renew`, the expiry asked for, the server picks one without it
+#[derive(Debug, Clone, Default, SimpleTagValue, SimpleXmlDeserialize)]
+pub struct RenewValue<'a> {
+    pub expires: Option<Tag<'a, Time, Expires>>,
 }
 
 #[derive(Debug, Clone)]
@@ -138,10 +126,115 @@ impl<'a> TagValue<'a> for GetStatusValue<'a> {
     }
 }
 
-#[derive(Debug, Clone, SimpleTagValue, SimpleXmlDeserialize)]
+/// `wsa:ReferenceParameters` of an endpoint reference.
+///
+/// The protocol requires every reference parameter to be echoed as a header of the requests
+/// sent to the endpoint, the ones WS-Management doesn't define are kept opaque in `unknown`.
+#[derive(Debug, Clone)]
 pub struct ReferenceParametersValue<'a> {
     pub resource_uri: Tag<'a, Text<'a>, ResourceURI>,
     pub selector_set: Tag<'a, SelectorSetValue, SelectorSet>,
+    pub unknown: Vec<OwnedElement>,
+}
+
+impl ReferenceParametersValue<'_> {
+    /// Add the unknown reference parameters to the headers of an outgoing envelope,
+    /// the ResourceURI and SelectorSet go through
+    /// [`EnvelopeBuilder::reference_parameters`](crate::cores::envelope::EnvelopeBuilder::reference_parameters)
+    pub fn echo<'b>(&'b self, headers: &mut CustomHeaders<'b>) {
+        echo(&self.unknown, headers);
+    }
+}
+
+/// The reference parameters of an EPR that WS-Management doesn't define, as a request
+/// addressing the EPR holds them, e.g. the [`unknown`](ReferenceParametersValue::unknown) ones
+/// of a shell kept to echo them on its commands
+pub type UnknownParameters<'a> = &'a [OwnedElement];
+
+impl<'a> Tag<'a, SoapEnvelope<'a>, Envelope> {
+    /// Echo `parameters` as headers of the request, as
+    /// [`ReferenceParametersValue::echo`] does for a parsed EPR
+    pub fn echo_parameters(mut self, parameters: UnknownParameters<'a>) -> Self {
+        echo(parameters, &mut self.value.custom_headers);
+        self
+    }
+}
+
+fn echo<'a>(parameters: UnknownParameters<'a>, headers: &mut CustomHeaders<'a>) {
+    for parameter in parameters {
+        headers.push_element(parameter.to_element());
+    }
+}
+
+// The unknown parameters are owned by the value and can't outlive it, they are only sent with `echo`
+impl<'a> TagValue<'a> for ReferenceParametersValue<'a> {
+    fn append_to_element(self, element: Element<'a>) -> Element<'a> {
+        element
+            .add_child(self.resource_uri.into_element())
+            .add_child(self.selector_set.into_element())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReferenceParametersVisitor<'a> {
+    resource_uri: Option<Tag<'a, Text<'a>, ResourceURI>>,
+    selector_set: Option<Tag<'a, SelectorSetValue, SelectorSet>>,
+    unknown: Vec<OwnedElement>,
+}
+
+impl<'a> XmlVisitor<'a> for ReferenceParametersVisitor<'a> {
+    type Value = ReferenceParametersValue<'a>;
+
+    fn visit_node(&mut self, node: xml::parser::Node<'a, 'a>) -> Result<(), xml::XmlError> {
+        self.visit_children(node.children())
+    }
+
+    fn visit_children(
+        &mut self,
+        children: impl Iterator<Item = xml::parser::Node<'a, 'a>>,
+    ) -> Result<(), xml::XmlError> {
+        for child in children.filter(|child| child.is_element()) {
+            match (child.tag_name().name(), child.tag_name().namespace()) {
+                (ResourceURI::TAG_NAME, namespace)
+                    if namespaces_match(namespace, ResourceURI::NAMESPACE) =>
+                {
+                    self.resource_uri = Some(Tag::from_node(child)?);
+                }
+                (SelectorSet::TAG_NAME, namespace)
+                    if namespaces_match(namespace, SelectorSet::NAMESPACE) =>
+                {
+                    self.selector_set = Some(Tag::from_node(child)?);
+                }
+                _ => self.unknown.push(OwnedElement::from_node(child)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Value, xml::XmlError> {
+        Ok(ReferenceParametersValue {
+            resource_uri: self.resource_uri.ok_or_else(|| {
+                xml::XmlError::InvalidXml(
+                    "Missing resource_uri in ReferenceParametersValue".to_string(),
+                )
+            })?,
+            selector_set: self.selector_set.ok_or_else(|| {
+                xml::XmlError::InvalidXml(
+                    "Missing selector_set in ReferenceParametersValue".to_string(),
+                )
+            })?,
+            unknown: self.unknown,
+        })
+    }
+}
+
+impl<'a> XmlDeserialize<'a> for ReferenceParametersValue<'a> {
+    type Visitor = ReferenceParametersVisitor<'a>;
+
+    fn visitor() -> Self::Visitor {
+        ReferenceParametersVisitor::default()
+    }
 }
 
 #[derive(Debug, Clone, SimpleTagValue, SimpleXmlDeserialize)]
@@ -0,0 +1,439 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::{Duration, Instant},
+};
+
+use futures_core::Stream;
+use tracing::debug;
+use xml::parser::Node;
+
+use crate::{
+    cores::{
+        AckRequested, Body, Envelope, Event, Events, Header, MessageID, OwnedElement, Tag, TagName,
+        Text,
+    },
+    error::ProtocolError,
+    soap::{SoapEnvelope, SoapFault, body::SoapBody},
+    ws_management::{
+        DEFAULT_MAX_ELEMENTS, EnumerationEnd, EventBookmark, PullRequest, PullResponse, WsAction,
+        WsMan, WsmanFaultKind, transfer::response_action,
+    },
+};
+
+/// What a subscription delivered, or failed to deliver
+#[derive(Debug, Clone)]
+pub enum SubscriptionEvent {
+    /// An event, e.g. the `Event` of the Windows Event Log
+    Event(OwnedElement),
+    /// The position reached by the events delivered before it, to persist for resuming the
+    /// subscription after a restart
+    Bookmark(EventBookmark),
+    /// The server is alive and had no event to deliver
+    Heartbeat,
+    /// Nothing was received for `silence`, longer than the heartbeats allow: the server or the
+    /// connection may be gone and events may have been lost
+    HeartbeatMissed { silence: Duration },
+}
+
+/// Tracks the heartbeats of a subscription, a heartbeat is missed after twice the interval
+/// without anything received.
+///
+/// Any event counts as a heartbeat. Each missed heartbeat is reported once, the next one is
+/// due another two intervals later.
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatMonitor {
+    tolerance: Duration,
+    last_seen: Instant,
+    deadline: Instant,
+}
+
+impl HeartbeatMonitor {
+    pub fn new(interval: Duration) -> Self {
+        let now = Instant::now();
+        let tolerance = interval * 2;
+        Self {
+            tolerance,
+            last_seen: now,
+            deadline: now + tolerance,
+        }
+    }
+
+    /// A heartbeat or an event was received
+    pub fn received(&mut self) {
+        self.last_seen = Instant::now();
+        self.deadline = self.last_seen + self.tolerance;
+    }
+
+    /// When the next heartbeat is missed if nothing is received
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// The silence so far if a heartbeat was missed since the last check
+    pub fn check(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        if now < self.deadline {
+            return None;
+        }
+
+        self.deadline = now + self.tolerance;
+        Some(now.duration_since(self.last_seen))
+    }
+}
+
+impl WsMan {
+    /// Stream the events of a [`DeliveryMode::Pull`](crate::ws_management::DeliveryMode)
+    /// subscription, issuing Pulls with the `context` of the
+    /// [`SubscribeResponse`](crate::ws_management::SubscribeResponse) until the server ends it.
+    ///
+    /// `heartbeats` is the interval the subscription asked for, a Pull answered with a
+    /// heartbeat, a `w:TimedOut` fault or no event yields a [`SubscriptionEvent::Heartbeat`] and
+    /// a Pull that took longer than the heartbeats allow is reported as missed. Other faults are
+    /// yielded as errors and end the stream.
+    pub fn pull_events<'a, F, Fut>(
+        &'a self,
+        resource_uri: &'a str,
+        context: &str,
+        heartbeats: Option<Duration>,
+        language: &'a str,
+        exchange: F,
+    ) -> EventStream<'a, F, Fut>
+    where
+        F: FnMut(String) -> Fut + 'a,
+        Fut: Future<Output = Result<String, ProtocolError>> + 'a,
+    {
+        EventStream {
+            ws_man: self,
+            resource_uri,
+            language,
+            exchange,
+            context: Some(context.to_owned()),
+            monitor: heartbeats.map(HeartbeatMonitor::new),
+            in_flight: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Build the `w:Ack` answering a pushed message that requested one
+    pub fn ack<'a>(&'a self, message: &PushedMessage<'a>) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let mut envelope =
+            self.invoke(WsAction::Ack, None, SoapBody::builder().build(), None, None);
+
+        if let (Some(header), Some(message_id)) = (&mut envelope.value.header, message.message_id) {
+            header.value.relates_to = Some(Tag::new(Text::from(message_id)));
+        }
+
+        envelope
+    }
+}
+
+/// The events of a pull subscription, see [`WsMan::pull_events`]
+pub struct EventStream<'a, F, Fut> {
+    ws_man: &'a WsMan,
+    resource_uri: &'a str,
+    language: &'a str,
+    exchange: F,
+    /// The context the next Pull is sent with, `None` once the subscription ended
+    context: Option<String>,
+    monitor: Option<HeartbeatMonitor>,
+    in_flight: Option<Pin<Box<Fut>>>,
+    pending: VecDeque<SubscriptionEvent>,
+}
+
+// No field is structurally pinned, the request futures are boxed
+impl<F, Fut> Unpin for EventStream<'_, F, Fut> {}
+
+impl<F, Fut> Stream for EventStream<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    type Item = Result<SubscriptionEvent, ProtocolError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            if let Some(future) = &mut this.in_flight {
+                let response = ready!(future.as_mut().poll(cx));
+                this.in_flight = None;
+
+                if let Some(monitor) = &mut this.monitor {
+                    if let Some(silence) = monitor.check() {
+                        this.pending
+                            .push_back(SubscriptionEvent::HeartbeatMissed { silence });
+                    }
+                    monitor.received();
+                }
+
+                match response.and_then(|response| parse_events(&response, this.language)) {
+                    Ok(page) => {
+                        if page.events.is_empty() && !page.ended {
+                            this.pending.push_back(SubscriptionEvent::Heartbeat);
+                        }
+                        this.pending
+                            .extend(page.events.into_iter().map(SubscriptionEvent::Event));
+                        if let Some(bookmark) = page.bookmark {
+                            this.pending
+                                .push_back(SubscriptionEvent::Bookmark(bookmark));
+                        }
+                        if page.ended {
+                            this.context = None;
+                        } else if let Some(context) = page.context {
+                            this.context = Some(context);
+                        }
+                    }
+                    Err(ProtocolError::WsmanFault {
+                        kind: WsmanFaultKind::OperationTimeout,
+                        ..
+                    }) => this.pending.push_back(SubscriptionEvent::Heartbeat),
+                    Err(error) => {
+                        this.context = None;
+                        return Poll::Ready(Some(Err(error)));
+                    }
+                }
+
+                continue;
+            }
+
+            let Some(context) = &this.context else {
+                return Poll::Ready(None);
+            };
+
+            let request = PullRequest::builder()
+                .resource_uri(this.resource_uri)
+                .context(context)
+                .max_elements(DEFAULT_MAX_ELEMENTS)
+                .build();
+            let request = this.ws_man.pull(request).into_element().to_string();
+            this.in_flight = Some(Box::pin((this.exchange)(request)));
+        }
+    }
+}
+
+/// The owned events of a Pull, the response document doesn't outlive the parsing
+struct EventPage {
+    context: Option<String>,
+    events: Vec<OwnedElement>,
+    bookmark: Option<EventBookmark>,
+    ended: bool,
+}
+
+/// Parse the response to a Pull of events, a heartbeat has no event and an
+/// `n:EnumerationEnd` ends the subscription
+fn parse_events(response: &str, language: &str) -> Result<EventPage, ProtocolError> {
+    let document =
+        xml::parser::parse(response).map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+    let envelope = document.root_element();
+
+    if let Some(end) = EnumerationEnd::find(envelope) {
+        debug!(code = ?end.code(), reason = ?end.reason(), "Subscription ended by the source");
+        return Ok(EventPage {
+            context: None,
+            events: Vec::new(),
+            bookmark: None,
+            ended: true,
+        });
+    }
+
+    if response_action(envelope) == Some(WsAction::Heartbeat.as_str()) {
+        return Ok(EventPage {
+            context: None,
+            events: Vec::new(),
+            bookmark: None,
+            ended: false,
+        });
+    }
+
+    let response = PullResponse::from_node(envelope, language)?;
+    Ok(EventPage {
+        context: response.context().map(str::to_owned),
+        events: response.owned_items(),
+        bookmark: EventBookmark::find(envelope),
+        ended: response.is_end_of_sequence(),
+    })
+}
+
+/// A message the server pushed to the `NotifyTo` address of a
+/// [`DeliveryMode::Push`](crate::ws_management::DeliveryMode) or `PushWithAck` subscription,
+/// received by the caller's listener.
+///
+/// A message with [`PushedMessage::ack_requested`] is answered with [`WsMan::ack`].
+#[derive(Debug, Clone)]
+pub struct PushedMessage<'a> {
+    action: Option<&'a str>,
+    message_id: Option<&'a str>,
+    ack_requested: bool,
+    events: Vec<Node<'a, 'a>>,
+    bookmark: Option<EventBookmark>,
+}
+
+impl<'a> PushedMessage<'a> {
+    /// Parse a pushed envelope, a fault is returned as an error with its reason in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        if let Some(fault) = SoapFault::find(envelope) {
+            let fault = fault.map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+            return Err(fault.into_error(language));
+        }
+
+        let header = envelope.children().find(Header::matches_node);
+        let header_child = |matches: fn(&Node<'_, '_>) -> bool| {
+            header.and_then(|header| header.children().find(|child| matches(child)))
+        };
+
+        let action = response_action(envelope);
+        let events = if action == Some(WsAction::Heartbeat.as_str()) {
+            Vec::new()
+        } else {
+            let body = envelope
+                .children()
+                .find(Body::matches_node)
+                .ok_or(ProtocolError::MissingSoapBody)?;
+            batched_events(body)
+        };
+
+        Ok(Self {
+            action,
+            message_id: header_child(MessageID::matches_node)
+                .and_then(|id| id.text())
+                .map(str::trim),
+            ack_requested: header_child(AckRequested::matches_node).is_some(),
+            events,
+            bookmark: EventBookmark::find(envelope),
+        })
+    }
+
+    pub fn action(&self) -> Option<&'a str> {
+        self.action
+    }
+
+    pub fn message_id(&self) -> Option<&'a str> {
+        self.message_id
+    }
+
+    /// Whether the server waits for a [`WsMan::ack`] before pushing the next message
+    pub fn ack_requested(&self) -> bool {
+        self.ack_requested
+    }
+
+    pub fn is_heartbeat(&self) -> bool {
+        self.action == Some(WsAction::Heartbeat.as_str())
+    }
+
+    pub fn events(&self) -> &[Node<'a, 'a>] {
+        &self.events
+    }
+
+    /// The `w:Bookmark` sent with the events, when the subscription asked for bookmarks
+    pub fn bookmark(&self) -> Option<&EventBookmark> {
+        self.bookmark.as_ref()
+    }
+
+    /// The message as subscription events, to feed a [`HeartbeatWatch`]
+    pub fn subscription_events(&self) -> Vec<SubscriptionEvent> {
+        if self.is_heartbeat() {
+            return vec![SubscriptionEvent::Heartbeat];
+        }
+
+        self.events
+            .iter()
+            .copied()
+            .map(|event| SubscriptionEvent::Event(OwnedElement::from_node(event)))
+            .chain(self.bookmark.clone().map(SubscriptionEvent::Bookmark))
+            .collect()
+    }
+}
+
+/// The events of a pushed body, a batch of `w:Events` is unwrapped into the payload of each of
+/// its `w:Event`
+fn batched_events<'a>(body: Node<'a, 'a>) -> Vec<Node<'a, 'a>> {
+    let elements = |node: Node<'a, 'a>| node.children().filter(|child| child.is_element());
+
+    elements(body)
+        .flat_map(|child| {
+            if Events::matches_node(&child) {
+                elements(child)
+                    .filter(Event::matches_node)
+                    .filter_map(|event| elements(event).next())
+                    .collect()
+            } else {
+                vec![child]
+            }
+        })
+        .collect()
+}
+
+impl<S, T, TFut> HeartbeatWatch<S, T, TFut>
+where
+    T: FnMut(Duration) -> TFut,
+    TFut: Future<Output = ()>,
+{
+    /// Watch the events of a push subscription, yielding a
+    /// [`SubscriptionEvent::HeartbeatMissed`] when `events` stays silent for longer than the
+    /// heartbeat `interval` allows.
+    ///
+    /// `events` is typically the receiving side of a channel the listener forwards the
+    /// [`PushedMessage::subscription_events`] to, `sleep` waits for a duration with the timer of
+    /// the caller's runtime.
+    pub fn new(events: S, interval: Duration, sleep: T) -> Self {
+        Self {
+            events,
+            sleep,
+            timer: None,
+            monitor: HeartbeatMonitor::new(interval),
+        }
+    }
+}
+
+/// The events of a push subscription with the heartbeats it missed, see [`HeartbeatWatch::new`]
+pub struct HeartbeatWatch<S, T, TFut> {
+    events: S,
+    sleep: T,
+    timer: Option<Pin<Box<TFut>>>,
+    monitor: HeartbeatMonitor,
+}
+
+// The timer is boxed, the events stream is required to be Unpin
+impl<S: Unpin, T, TFut> Unpin for HeartbeatWatch<S, T, TFut> {}
+
+impl<S, T, TFut> Stream for HeartbeatWatch<S, T, TFut>
+where
+    S: Stream<Item = Result<SubscriptionEvent, ProtocolError>> + Unpin,
+    T: FnMut(Duration) -> TFut,
+    TFut: Future<Output = ()>,
+{
+    type Item = Result<SubscriptionEvent, ProtocolError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Poll::Ready(event) = Pin::new(&mut this.events).poll_next(cx) {
+                this.timer = None;
+                this.monitor.received();
+                return Poll::Ready(event);
+            }
+
+            if let Some(silence) = this.monitor.check() {
+                this.timer = None;
+                return Poll::Ready(Some(Ok(SubscriptionEvent::HeartbeatMissed { silence })));
+            }
+
+            let monitor = &this.monitor;
+            let sleep = &mut this.sleep;
+            let timer = this.timer.get_or_insert_with(|| {
+                Box::pin(sleep(
+                    monitor.deadline().saturating_duration_since(Instant::now()),
+                ))
+            });
+            ready!(timer.as_mut().poll(cx));
+            this.timer = None;
+        }
+    }
+}
@@ -0,0 +1,763 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use futures_core::Stream;
+use tracing::{debug, warn};
+use xml::parser::Node;
+
+use crate::{
+    cores::{
+        Address, Body, EndOfSequence, EndpointReference as EndpointReferenceTag,
+        EnumerateResponse as EnumerateResponseTag, EnumerationCode, EnumerationContext,
+        EnumerationEnd as EnumerationEndTag, EnumerationReason, Envelope, Item, Items, Namespace,
+        OwnedElement, PullResponse as PullResponseTag, ReferenceParameters, ResourceURI, Selector,
+        SelectorSet, Tag, TagName, Text, WsmanEndOfSequence, WsmanItems,
+    },
+    error::ProtocolError,
+    soap::{SoapEnvelope, body::SoapBody},
+    ws_management::{
+        DeleteRequest, Filter, GetRequest, InvokeRequest, OptionSetValue, SelectorSetValue,
+        WsAction, WsMan,
+        body::{EnumerateValue, PullValue, ReleaseValue},
+        transfer::response_body,
+    },
+};
+
+/// What the items of an enumeration are, the objects by default.
+///
+/// Endpoint references address the enumerated instances for the Get, Put, Delete or Invoke
+/// that follow, see [`EnumeratedItem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumerationMode {
+    /// Only the endpoint reference of each instance
+    EnumerateEPR,
+    /// Each object with its endpoint reference, in a `w:Item`
+    EnumerateObjectAndEPR,
+}
+
+impl EnumerationMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnumerationMode::EnumerateEPR => "EnumerateEPR",
+            EnumerationMode::EnumerateObjectAndEPR => "EnumerateObjectAndEPR",
+        }
+    }
+}
+
+/// Items asked for in each response of an enumeration, unless set on the [`EnumerateRequest`]
+pub const DEFAULT_MAX_ELEMENTS: u32 = 100;
+
+/// A WS-Enumeration Enumerate of the instances of a resource, e.g. all the WMI objects of a class.
+///
+/// With `optimize_enumeration`, on by default, the server returns up to `max_elements` items
+/// right away in the [`EnumerateResponse`]. A server may not grant the optimization, the items
+/// are then only returned by the Pulls that follow.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct EnumerateRequest<'a> {
+    resource_uri: &'a str,
+    #[builder(default, setter(strip_option))]
+    filter: Option<Filter<'a>>,
+    #[builder(default, setter(strip_option))]
+    mode: Option<EnumerationMode>,
+    #[builder(default = true)]
+    optimize_enumeration: bool,
+    /// Items of the optimized EnumerateResponse, also used by the Pulls of
+    /// [`WsMan::enumerate_items`]
+    #[builder(default = DEFAULT_MAX_ELEMENTS)]
+    max_elements: u32,
+    /// Characters of each Pull response, the server returns fewer items to stay below it
+    #[builder(default, setter(strip_option))]
+    max_characters: Option<u32>,
+    #[builder(default, setter(strip_option))]
+    selector_set: Option<SelectorSetValue>,
+    #[builder(default, setter(strip_option))]
+    option_set: Option<OptionSetValue>,
+}
+
+impl<'a> EnumerateRequest<'a> {
+    pub fn resource_uri(&self) -> &'a str {
+        self.resource_uri
+    }
+
+    fn value(&self) -> EnumerateValue<'a> {
+        let mut value = EnumerateValue::new().with_optimization(self.optimize_enumeration);
+
+        if let Some(filter) = &self.filter {
+            value = value.with_filter(filter.clone());
+        }
+
+        if let Some(mode) = self.mode {
+            value = value.with_mode(mode);
+        }
+
+        if self.optimize_enumeration {
+            value = value.with_max_elements(self.max_elements);
+        }
+
+        value
+    }
+}
+
+/// A WS-Enumeration Pull of the next items of an enumeration.
+///
+/// The context is the one of the previous [`EnumerateResponse`] or [`PullResponse`], the
+/// server invalidates it once it returned a newer one.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct PullRequest<'a> {
+    resource_uri: &'a str,
+    context: &'a str,
+    #[builder(default, setter(strip_option))]
+    max_elements: Option<u32>,
+    #[builder(default, setter(strip_option))]
+    max_characters: Option<u32>,
+}
+
+impl<'a> PullRequest<'a> {
+    pub fn resource_uri(&self) -> &'a str {
+        self.resource_uri
+    }
+
+    pub fn context(&self) -> &'a str {
+        self.context
+    }
+}
+
+/// A WS-Enumeration Release of an enumeration context, ending the enumeration before its
+/// end of sequence
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct ReleaseRequest<'a> {
+    resource_uri: &'a str,
+    context: &'a str,
+}
+
+impl WsMan {
+    /// Build the `n:Enumerate` request
+    pub fn enumerate<'a>(
+        &'a self,
+        request: EnumerateRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let enumerate = Tag::new(request.value()).with_declaration(Namespace::WsEnumeration2004);
+
+        self.invoke(
+            WsAction::Enumerate,
+            Some(request.resource_uri),
+            SoapBody::builder().enumerate(enumerate).build(),
+            request.option_set,
+            request.selector_set,
+        )
+    }
+
+    /// Build the `n:Pull` request
+    pub fn pull<'a>(&'a self, request: PullRequest<'a>) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let mut value = PullValue::new(Text::from(request.context));
+        if let Some(max_elements) = request.max_elements {
+            value = value.with_max_elements(max_elements);
+        }
+        if let Some(max_characters) = request.max_characters {
+            value = value.with_max_characters(max_characters);
+        }
+
+        let pull = Tag::new(value).with_declaration(Namespace::WsEnumeration2004);
+
+        self.invoke(
+            WsAction::Pull,
+            Some(request.resource_uri),
+            SoapBody::builder().pull(pull).build(),
+            None,
+            None,
+        )
+    }
+
+    /// Build the `n:Release` request, the body is empty
+    pub fn release<'a>(
+        &'a self,
+        request: ReleaseRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let release = Tag::new(ReleaseValue::new(Text::from(request.context)))
+            .with_declaration(Namespace::WsEnumeration2004);
+
+        self.invoke(
+            WsAction::Release,
+            Some(request.resource_uri),
+            SoapBody::builder().release(release).build(),
+            None,
+            None,
+        )
+    }
+
+    /// Stream every item of the enumeration `request` starts, issuing Pulls until the
+    /// sequence ends.
+    ///
+    /// `exchange` sends a request envelope to the server and resolves to the response
+    /// envelope. Faults, including the ones of the Pulls, are yielded as errors and end the
+    /// stream. Pulls ask for the `max_elements` and `max_characters` of the request. See [`EnumerationStream`] to
+    /// release the enumeration before its end.
+    pub fn enumerate_items<'a, F, Fut>(
+        &'a self,
+        request: EnumerateRequest<'a>,
+        language: &'a str,
+        exchange: F,
+    ) -> EnumerationStream<'a, F, Fut>
+    where
+        F: FnMut(String) -> Fut + 'a,
+        Fut: Future<Output = Result<String, ProtocolError>> + 'a,
+    {
+        EnumerationStream {
+            ws_man: self,
+            resource_uri: request.resource_uri,
+            max_elements: request.max_elements,
+            max_characters: request.max_characters,
+            language,
+            exchange,
+            enumerate: Some(request),
+            context: None,
+            finished: false,
+            in_flight: None,
+            pending: VecDeque::new(),
+            release_on_drop: None,
+        }
+    }
+}
+
+/// The items of an enumeration, see [`WsMan::enumerate_items`].
+///
+/// The server keeps the enumeration context until the sequence ends or it is released. A
+/// stream dropped before the end hands the `n:Release` envelope to the hook registered with
+/// [`EnumerationStream::release_on_drop`], as a drop can't wait for the exchange. Use
+/// [`EnumerationStream::cancel`] to release it through the exchange instead.
+pub struct EnumerationStream<'a, F, Fut> {
+    ws_man: &'a WsMan,
+    resource_uri: &'a str,
+    max_elements: u32,
+    max_characters: Option<u32>,
+    language: &'a str,
+    exchange: F,
+    /// The Enumerate, until it is sent
+    enumerate: Option<EnumerateRequest<'a>>,
+    /// The context the next Pull is sent with, also the one to release
+    context: Option<String>,
+    finished: bool,
+    in_flight: Option<(Pin<Box<Fut>>, WsAction)>,
+    pending: VecDeque<OwnedElement>,
+    release_on_drop: Option<Box<dyn FnOnce(String) + 'a>>,
+}
+
+impl<'a, F, Fut> EnumerationStream<'a, F, Fut>
+where
+    F: FnMut(String) -> Fut + 'a,
+    Fut: Future<Output = Result<String, ProtocolError>> + 'a,
+{
+    /// Call `hook` with the `n:Release` envelope when the stream is dropped before the
+    /// sequence ends, e.g. to send it from a spawned task
+    pub fn release_on_drop(mut self, hook: impl FnOnce(String) + 'a) -> Self {
+        self.release_on_drop = Some(Box::new(hook));
+        self
+    }
+
+    /// Stop the enumeration, releasing its context on the server if it didn't end yet
+    pub async fn cancel(mut self) -> Result<(), ProtocolError> {
+        self.in_flight = None;
+        self.finished = true;
+
+        let Some(release) = self.take_release() else {
+            return Ok(());
+        };
+
+        let response = (self.exchange)(release).await?;
+        let document = xml::parser::parse(&response)
+            .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        ReleaseResponse::from_node(document.root_element(), self.language)?;
+
+        Ok(())
+    }
+}
+
+impl<F, Fut> EnumerationStream<'_, F, Fut> {
+    /// The `n:Release` envelope of the context the server still holds, if any
+    fn take_release(&mut self) -> Option<String> {
+        let context = self.context.take()?;
+        let request = ReleaseRequest {
+            resource_uri: self.resource_uri,
+            context: &context,
+        };
+
+        Some(self.ws_man.release(request).into_element().to_string())
+    }
+}
+
+// No field is structurally pinned, the request futures are boxed
+impl<F, Fut> Unpin for EnumerationStream<'_, F, Fut> {}
+
+impl<F, Fut> Stream for EnumerationStream<'_, F, Fut>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, ProtocolError>>,
+{
+    type Item = Result<OwnedElement, ProtocolError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(item) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(item)));
+            }
+
+            if let Some((future, action)) = &mut this.in_flight {
+                let response = ready!(future.as_mut().poll(cx));
+                let action = action.clone();
+                this.in_flight = None;
+
+                match response.and_then(|response| parse_page(&response, action, this.language)) {
+                    Ok(page) => {
+                        this.pending.extend(page.items);
+                        this.context = page.context.filter(|_| !page.end_of_sequence);
+                        this.finished = this.context.is_none();
+                    }
+                    Err(error) => {
+                        // The context stays valid after a fault such as w:TimedOut, it is
+                        // still released on drop
+                        this.finished = true;
+                        return Poll::Ready(Some(Err(error)));
+                    }
+                }
+
+                continue;
+            }
+
+            let (request, action) = if let Some(request) = this.enumerate.take() {
+                let request = this.ws_man.enumerate(request).into_element().to_string();
+                (request, WsAction::EnumerateResponse)
+            } else if let Some(context) = this.context.as_deref().filter(|_| !this.finished) {
+                let request = PullRequest {
+                    resource_uri: this.resource_uri,
+                    context,
+                    max_elements: Some(this.max_elements),
+                    max_characters: this.max_characters,
+                };
+                let request = this.ws_man.pull(request).into_element().to_string();
+                (request, WsAction::PullResponse)
+            } else {
+                return Poll::Ready(None);
+            };
+
+            this.in_flight = Some((Box::pin((this.exchange)(request)), action));
+        }
+    }
+}
+
+impl<F, Fut> Drop for EnumerationStream<'_, F, Fut> {
+    fn drop(&mut self) {
+        if let Some(release) = self.take_release() {
+            match self.release_on_drop.take() {
+                Some(hook) => hook(release),
+                None => warn!(
+                    resource_uri = self.resource_uri,
+                    "Enumeration stream dropped before its end, the context is not released"
+                ),
+            }
+        }
+    }
+}
+
+/// The owned content of an `n:EnumerateResponse` or `n:PullResponse`, the response
+/// document doesn't outlive the parsing
+struct Page {
+    context: Option<String>,
+    items: Vec<OwnedElement>,
+    end_of_sequence: bool,
+}
+
+/// Parse the response to an Enumerate or a Pull, an unsolicited `n:EnumerationEnd` ends
+/// the sequence
+fn parse_page(response: &str, action: WsAction, language: &str) -> Result<Page, ProtocolError> {
+    let document =
+        xml::parser::parse(response).map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+    let envelope = document.root_element();
+
+    if let Some(end) = EnumerationEnd::find(envelope) {
+        debug!(code = ?end.code(), reason = ?end.reason(), "Enumeration ended by the source");
+        return Ok(Page {
+            context: None,
+            items: Vec::new(),
+            end_of_sequence: true,
+        });
+    }
+
+    let (context, items, end_of_sequence) = match action {
+        WsAction::EnumerateResponse => {
+            let response = EnumerateResponse::from_node(envelope, language)?;
+            (response.context, response.items, response.end_of_sequence)
+        }
+        _ => {
+            let response = PullResponse::from_node(envelope, language)?;
+            (response.context, response.items, response.end_of_sequence)
+        }
+    };
+
+    Ok(Page {
+        context: context.map(str::to_owned),
+        items: items.into_iter().map(OwnedElement::from_node).collect(),
+        end_of_sequence,
+    })
+}
+
+/// Items, end of sequence and context of an `n:EnumerateResponse` or `n:PullResponse`.
+///
+/// An optimized enumeration returns them in the `w` namespace, a Pull in the `n` namespace,
+/// both are accepted.
+pub(crate) fn enumeration_items<'a>(
+    response: Node<'a, 'a>,
+) -> (Option<&'a str>, Vec<Node<'a, 'a>>, bool) {
+    let children = || response.children().filter(|child| child.is_element());
+
+    let context = children()
+        .find(EnumerationContext::matches_node)
+        .and_then(|context| context.text())
+        .map(str::trim)
+        .filter(|context| !context.is_empty());
+
+    let items = children()
+        .filter(|child| Items::matches_node(child) || WsmanItems::matches_node(child))
+        .flat_map(|items| items.children().filter(|item| item.is_element()))
+        .collect();
+
+    let end_of_sequence = children().any(|child| {
+        EndOfSequence::matches_node(&child) || WsmanEndOfSequence::matches_node(&child)
+    });
+
+    (context, items, end_of_sequence)
+}
+
+/// The response to an [`EnumerateRequest`].
+///
+/// The context is used to Pull the remaining items, the server may omit it when the
+/// sequence already ended.
+#[derive(Debug, Clone)]
+pub struct EnumerateResponse<'a> {
+    context: Option<&'a str>,
+    items: Vec<Node<'a, 'a>>,
+    end_of_sequence: bool,
+}
+
+impl<'a> EnumerateResponse<'a> {
+    /// Parse a `n:EnumerateResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::EnumerateResponse, language)?;
+
+        let response = body
+            .children()
+            .find(EnumerateResponseTag::matches_node)
+            .ok_or_else(|| {
+                ProtocolError::XmlParsingError("No EnumerateResponse found in body".to_owned())
+            })?;
+
+        let (context, items, end_of_sequence) = enumeration_items(response);
+
+        Ok(Self {
+            context,
+            items,
+            end_of_sequence,
+        })
+    }
+
+    /// The `n:EnumerationContext` to Pull with
+    pub fn context(&self) -> Option<&'a str> {
+        self.context
+    }
+
+    /// Items inlined by an optimized enumeration
+    pub fn items(&self) -> &[Node<'a, 'a>] {
+        &self.items
+    }
+
+    pub fn owned_items(&self) -> Vec<OwnedElement> {
+        self.items
+            .iter()
+            .copied()
+            .map(OwnedElement::from_node)
+            .collect()
+    }
+
+    /// The items paired with their endpoint reference, see [`EnumerationMode`]
+    pub fn enumerated_items(&self) -> Vec<EnumeratedItem> {
+        self.owned_items()
+            .into_iter()
+            .map(EnumeratedItem::from_owned)
+            .collect()
+    }
+
+    pub fn is_end_of_sequence(&self) -> bool {
+        self.end_of_sequence
+    }
+}
+
+/// The response to a [`PullRequest`].
+///
+/// A Pull that times out before any item is available is answered with a fault, an empty
+/// `Items` is also valid and only means that the enumeration continues.
+#[derive(Debug, Clone)]
+pub struct PullResponse<'a> {
+    context: Option<&'a str>,
+    items: Vec<Node<'a, 'a>>,
+    end_of_sequence: bool,
+}
+
+impl<'a> PullResponse<'a> {
+    /// Parse a `n:PullResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::PullResponse, language)?;
+
+        let response = body
+            .children()
+            .find(PullResponseTag::matches_node)
+            .ok_or_else(|| {
+                ProtocolError::XmlParsingError("No PullResponse found in body".to_owned())
+            })?;
+
+        let (context, items, end_of_sequence) = enumeration_items(response);
+
+        Ok(Self {
+            context,
+            items,
+            end_of_sequence,
+        })
+    }
+
+    /// The `n:EnumerationContext` to Pull the next items with
+    pub fn context(&self) -> Option<&'a str> {
+        self.context
+    }
+
+    pub fn items(&self) -> &[Node<'a, 'a>] {
+        &self.items
+    }
+
+    pub fn owned_items(&self) -> Vec<OwnedElement> {
+        self.items
+            .iter()
+            .copied()
+            .map(OwnedElement::from_node)
+            .collect()
+    }
+
+    /// The items paired with their endpoint reference, see [`EnumerationMode`]
+    pub fn enumerated_items(&self) -> Vec<EnumeratedItem> {
+        self.owned_items()
+            .into_iter()
+            .map(EnumeratedItem::from_owned)
+            .collect()
+    }
+
+    pub fn is_end_of_sequence(&self) -> bool {
+        self.end_of_sequence
+    }
+}
+
+/// The response to a [`ReleaseRequest`], the body is empty
+#[derive(Debug, Clone, Copy)]
+pub struct ReleaseResponse;
+
+impl ReleaseResponse {
+    pub fn from_node(envelope: Node<'_, '_>, language: &str) -> Result<Self, ProtocolError> {
+        response_body(envelope, WsAction::ReleaseResponse, language)?;
+        Ok(Self)
+    }
+}
+
+/// Why the source ended an enumeration, the `n:Code` of an [`EnumerationEnd`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnumerationEndCode {
+    SourceShuttingDown,
+    SourceCancelling,
+    Other(String),
+}
+
+impl EnumerationEndCode {
+    fn from_uri(uri: &str) -> Self {
+        match uri.strip_prefix(Namespace::WsEnumeration2004.uri()) {
+            Some("/SourceShuttingDown") => Self::SourceShuttingDown,
+            Some("/SourceCancelling") => Self::SourceCancelling,
+            _ => Self::Other(uri.to_owned()),
+        }
+    }
+}
+
+/// An `n:EnumerationEnd`, sent by the source when it ends an enumeration on its own.
+///
+/// The context is no longer valid and must not be released.
+#[derive(Debug, Clone)]
+pub struct EnumerationEnd<'a> {
+    context: Option<&'a str>,
+    code: EnumerationEndCode,
+    reason: Option<&'a str>,
+}
+
+impl<'a> EnumerationEnd<'a> {
+    /// The `n:EnumerationEnd` in the body of `envelope`, if any
+    pub fn find(envelope: Node<'a, 'a>) -> Option<Self> {
+        envelope
+            .children()
+            .find(Body::matches_node)?
+            .children()
+            .find(EnumerationEndTag::matches_node)
+            .map(Self::from_node)
+    }
+
+    pub fn from_node(end: Node<'a, 'a>) -> Self {
+        let child_text = |matches: fn(&Node<'_, '_>) -> bool| {
+            end.children()
+                .find(matches)
+                .and_then(|child| child.text())
+                .map(str::trim)
+        };
+
+        Self {
+            context: child_text(EnumerationContext::matches_node),
+            code: EnumerationEndCode::from_uri(
+                child_text(EnumerationCode::matches_node).unwrap_or_default(),
+            ),
+            reason: child_text(EnumerationReason::matches_node),
+        }
+    }
+
+    /// The context of the ended enumeration
+    pub fn context(&self) -> Option<&'a str> {
+        self.context
+    }
+
+    pub fn code(&self) -> &EnumerationEndCode {
+        &self.code
+    }
+
+    pub fn reason(&self) -> Option<&'a str> {
+        self.reason
+    }
+}
+
+/// An endpoint reference of an enumerated instance, owned to outlive the response.
+///
+/// The reference parameters WS-Management doesn't define are kept in `unknown` and echoed by
+/// [`EndpointReference::delete_request`].
+#[derive(Debug, Clone)]
+pub struct EndpointReference {
+    pub address: String,
+    pub resource_uri: String,
+    pub selector_set: SelectorSetValue,
+    pub unknown: Vec<OwnedElement>,
+}
+
+impl EndpointReference {
+    /// Parse an `a:EndpointReference`, `None` if it isn't one or has no resource URI
+    pub fn from_owned(element: &OwnedElement) -> Option<Self> {
+        if !element.is::<EndpointReferenceTag>() {
+            return None;
+        }
+
+        Self::from_reference(element)
+    }
+
+    /// Parse the address and reference parameters of an element of the endpoint reference
+    /// type, whatever its name, e.g. an `e:SubscriptionManager`
+    pub fn from_reference(element: &OwnedElement) -> Option<Self> {
+        let parameters = element.child::<ReferenceParameters>()?;
+        let resource_uri = parameters.child::<ResourceURI>()?.text.as_deref()?.trim();
+
+        let mut selector_set = SelectorSetValue::new();
+        for selector in parameters
+            .child::<SelectorSet>()
+            .into_iter()
+            .flat_map(|set| &set.children)
+            .filter(|selector| selector.is::<Selector>())
+        {
+            let name = selector
+                .attributes
+                .iter()
+                .find(|attribute| attribute.name == "Name");
+            if let (Some(name), Some(value)) = (name, &selector.text) {
+                selector_set = selector_set.add_selector(&name.value, value.trim());
+            }
+        }
+
+        Some(Self {
+            address: element
+                .child::<Address>()
+                .and_then(|address| address.text.as_deref())
+                .map(str::trim)
+                .unwrap_or_default()
+                .to_owned(),
+            resource_uri: resource_uri.to_owned(),
+            selector_set,
+            unknown: parameters
+                .children
+                .iter()
+                .filter(|child| !child.is::<ResourceURI>() && !child.is::<SelectorSet>())
+                .cloned()
+                .collect(),
+        })
+    }
+
+    pub fn get_request(&self) -> GetRequest<'_> {
+        GetRequest::builder()
+            .resource_uri(&self.resource_uri)
+            .selector_set(self.selector_set.clone())
+            .build()
+    }
+
+    pub fn delete_request(&self) -> DeleteRequest<'_> {
+        DeleteRequest::builder()
+            .resource_uri(&self.resource_uri)
+            .selector_set(self.selector_set.clone())
+            .reference_parameters(&self.unknown)
+            .build()
+    }
+
+    /// Invoke `method` on the instance
+    pub fn invoke_request<'a>(&'a self, method: &'a str) -> InvokeRequest<'a> {
+        InvokeRequest::new(&self.resource_uri, method).with_selector_set(self.selector_set.clone())
+    }
+}
+
+/// An item of an enumeration, the object and the endpoint reference the
+/// [`EnumerationMode`] asked for.
+#[derive(Debug, Clone)]
+pub struct EnumeratedItem {
+    pub object: Option<OwnedElement>,
+    pub endpoint: Option<EndpointReference>,
+}
+
+impl EnumeratedItem {
+    /// Split an item of any mode: an `a:EndpointReference`, a `w:Item` with the object and its
+    /// endpoint reference, or the object itself
+    pub fn from_owned(item: OwnedElement) -> Self {
+        if let Some(endpoint) = EndpointReference::from_owned(&item) {
+            return Self {
+                object: None,
+                endpoint: Some(endpoint),
+            };
+        }
+
+        if !item.is::<Item>() {
+            return Self {
+                object: Some(item),
+                endpoint: None,
+            };
+        }
+
+        let mut object = None;
+        let mut endpoint = None;
+        for child in item.children {
+            match EndpointReference::from_owned(&child) {
+                Some(reference) => endpoint = Some(reference),
+                None => object = object.or(Some(child)),
+            }
+        }
+
+        Self { object, endpoint }
+    }
+}
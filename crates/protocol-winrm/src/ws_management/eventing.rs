@@ -0,0 +1,640 @@
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+use xml::{
+    builder::{Attribute, Element},
+    parser::Node,
+};
+
+use crate::{
+    cores::{
+        Address, Bookmark as BookmarkTag, Delivery, Empty, EnumerationContext, Envelope, Expires,
+        Header, Heartbeats, Identifier, MaxEnvelopeSize, MaxTime, Namespace, NotifyTo,
+        OwnedElement, RenewResponse as RenewResponseTag, SendBookmarks, Subscribe,
+        SubscribeResponse as SubscribeResponseTag, SubscriptionManager, Tag, TagName, Text, Time,
+        U32, WsmanMaxElements,
+    },
+    error::ProtocolError,
+    soap::{SoapEnvelope, body::SoapBody},
+    ws_management::{
+        EndpointReference, Filter, OptionSetValue, ResourceUri, SelectorSetValue, WsAction, WsMan,
+        body::RenewValue, transfer::response_body,
+    },
+};
+
+/// Bookmark asking for the events from the oldest one the log still holds
+const EARLIEST_BOOKMARK: &str = "http://schemas.dmtf.org/wbem/wsman/1/wsman/bookmark/earliest";
+
+/// How the events of a subscription are delivered to the subscriber
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DeliveryMode<'a> {
+    /// The subscriber Pulls the events with the enumeration context of the
+    /// [`SubscribeResponse`], the only mode that needs no listener on the subscriber side
+    #[default]
+    Pull,
+    /// The server sends each event to the `notify_to` address
+    Push { notify_to: &'a str },
+    /// Like [`DeliveryMode::Push`], the server waits for an acknowledgement of each event
+    PushWithAck { notify_to: &'a str },
+    /// The server sends the events to the `notify_to` address in batches of `w:Events`,
+    /// acknowledged like [`DeliveryMode::PushWithAck`], see [`EventBatching`]
+    Events { notify_to: &'a str },
+}
+
+impl<'a> DeliveryMode<'a> {
+    /// The URI of the `Mode` attribute
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryMode::Pull => "http://schemas.dmtf.org/wbem/wsman/1/wsman/Pull",
+            DeliveryMode::Push { .. } => {
+                "http://schemas.xmlsoap.org/ws/2004/08/eventing/DeliveryModes/Push"
+            }
+            DeliveryMode::PushWithAck { .. } => {
+                "http://schemas.dmtf.org/wbem/wsman/1/wsman/PushWithAck"
+            }
+            DeliveryMode::Events { .. } => "http://schemas.dmtf.org/wbem/wsman/1/wsman/Events",
+        }
+    }
+
+    fn notify_to(&self) -> Option<&'a str> {
+        match *self {
+            DeliveryMode::Pull => None,
+            DeliveryMode::Push { notify_to }
+            | DeliveryMode::PushWithAck { notify_to }
+            | DeliveryMode::Events { notify_to } => Some(notify_to),
+        }
+    }
+}
+
+/// How many events the server gathers before delivering them, the batch is sent as soon as one
+/// of the limits is reached
+#[derive(Debug, Clone, Copy, Default, typed_builder::TypedBuilder)]
+pub struct EventBatching {
+    #[builder(default, setter(strip_option))]
+    max_elements: Option<u32>,
+    /// How long the first event of a batch waits for the others
+    #[builder(default, setter(strip_option))]
+    max_time: Option<Duration>,
+    /// Size of a batch envelope in bytes
+    #[builder(default, setter(strip_option))]
+    max_envelope_size: Option<u32>,
+}
+
+impl EventBatching {
+    /// The `w:MaxElements`, `w:MaxTime` and `w:MaxEnvelopeSize` of the `e:Delivery`
+    fn elements<'a>(self) -> impl Iterator<Item = Element<'a>> {
+        [
+            self.max_elements
+                .map(|max| Tag::<U32, WsmanMaxElements>::new(max).into_element()),
+            self.max_time
+                .map(|max| Tag::<Time, MaxTime>::new(Time(max.as_secs_f64())).into_element()),
+            self.max_envelope_size
+                .map(|max| Tag::<U32, MaxEnvelopeSize>::new(max).into_element()),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Where the events of a subscription start
+#[derive(Debug, Clone, Copy)]
+pub enum Bookmark<'a> {
+    /// The oldest events the log still holds, then the new ones
+    Earliest,
+    /// Right after the events already received, `w:Bookmark` element sent with the last of
+    /// them when the subscription asked to [send bookmarks](SubscribeRequest)
+    Resume(&'a OwnedElement),
+}
+
+impl<'a> Bookmark<'a> {
+    pub fn into_element(self) -> Element<'a> {
+        match self {
+            Bookmark::Earliest => {
+                Tag::<Text<'a>, BookmarkTag>::new(EARLIEST_BOOKMARK).into_element()
+            }
+            Bookmark::Resume(bookmark) => bookmark.to_element(),
+        }
+    }
+}
+
+impl<'a> From<&'a EventBookmark> for Bookmark<'a> {
+    fn from(bookmark: &'a EventBookmark) -> Self {
+        Bookmark::Resume(&bookmark.0)
+    }
+}
+
+/// A `w:Bookmark` delivered with events, the position to [resume](Bookmark::Resume) a
+/// subscription at.
+///
+/// The content is opaque, e.g. a `BookmarkList` of the record ID reached in each channel for
+/// the Event Log, it is kept as received and persisted as XML with [`EventBookmark::to_xml`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventBookmark(OwnedElement);
+
+impl EventBookmark {
+    /// The `w:Bookmark` header of a message delivering events
+    pub fn find(envelope: Node<'_, '_>) -> Option<Self> {
+        let bookmark = envelope
+            .children()
+            .find(Header::matches_node)?
+            .children()
+            .find(BookmarkTag::matches_node)?;
+
+        Some(Self(OwnedElement::from_node(bookmark)))
+    }
+
+    /// The bookmark as a standalone XML document, to store until the next subscription
+    pub fn to_xml(&self) -> String {
+        self.0.to_element().to_string()
+    }
+
+    /// Load a bookmark stored with [`EventBookmark::to_xml`]
+    pub fn from_xml(xml: &str) -> Result<Self, ProtocolError> {
+        let document =
+            xml::parser::parse(xml).map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        let bookmark = document.root_element();
+        if !BookmarkTag::matches_node(&bookmark) {
+            return Err(ProtocolError::XmlParsingError(format!(
+                "Expected a Bookmark, found {}",
+                bookmark.tag_name().name()
+            )));
+        }
+
+        Ok(Self(OwnedElement::from_node(bookmark)))
+    }
+
+    pub fn element(&self) -> &OwnedElement {
+        &self.0
+    }
+}
+
+/// A WS-Eventing Subscribe, e.g. a Windows Event Collector style subscription to the channels
+/// of the Event Log.
+///
+/// The subscription lasts until it `expires`, or until it is unsubscribed when no expiry is
+/// asked for. The server can grant a shorter one, see [`SubscribeResponse::expires_in`].
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct SubscribeRequest<'a> {
+    #[builder(default = ResourceUri::EVENT_LOG)]
+    resource_uri: &'a str,
+    #[builder(default)]
+    delivery: DeliveryMode<'a>,
+    /// Interval of the heartbeats the server sends when it has no event to deliver, see
+    /// [`HeartbeatMonitor`](crate::ws_management::HeartbeatMonitor)
+    #[builder(default, setter(strip_option))]
+    heartbeats: Option<Duration>,
+    #[builder(default, setter(strip_option))]
+    batching: Option<EventBatching>,
+    #[builder(default, setter(strip_option))]
+    expires: Option<Duration>,
+    /// The events to deliver, e.g. a [`Filter::EventQuery`]
+    #[builder(default, setter(strip_option))]
+    filter: Option<Filter<'a>>,
+    #[builder(default, setter(strip_option))]
+    bookmark: Option<Bookmark<'a>>,
+    /// Send a `w:Bookmark` with the events, to resume the subscription after them
+    #[builder(default)]
+    send_bookmarks: bool,
+    #[builder(default, setter(strip_option))]
+    selector_set: Option<SelectorSetValue>,
+    #[builder(default, setter(strip_option))]
+    option_set: Option<OptionSetValue>,
+}
+
+impl<'a> SubscribeRequest<'a> {
+    pub fn resource_uri(&self) -> &'a str {
+        self.resource_uri
+    }
+
+    pub fn heartbeats(&self) -> Option<Duration> {
+        self.heartbeats
+    }
+
+    fn value(&self) -> SubscribeValue<'a> {
+        SubscribeValue {
+            delivery: self.delivery.clone(),
+            heartbeats: self.heartbeats,
+            batching: self.batching,
+            expires: self.expires,
+            filter: self.filter.clone(),
+            bookmark: self.bookmark,
+            send_bookmarks: self.send_bookmarks,
+        }
+    }
+}
+
+/// Content of `e:Subscribe`, in schema order
+#[derive(Debug, Clone)]
+pub struct SubscribeValue<'a> {
+    delivery: DeliveryMode<'a>,
+    heartbeats: Option<Duration>,
+    batching: Option<EventBatching>,
+    expires: Option<Duration>,
+    filter: Option<Filter<'a>>,
+    bookmark: Option<Bookmark<'a>>,
+    send_bookmarks: bool,
+}
+
+impl<'a> SubscribeValue<'a> {
+    pub fn into_element(self) -> Element<'a> {
+        let mut delivery = Element::new(Delivery::TAG_NAME)
+            .set_namespace(Namespace::WsEventing2004.uri())
+            .add_attribute(Attribute::new("Mode", self.delivery.as_str()));
+        if let Some(notify_to) = self.delivery.notify_to() {
+            delivery = delivery.add_child(
+                Element::new(NotifyTo::TAG_NAME)
+                    .set_namespace(Namespace::WsEventing2004.uri())
+                    .add_child(Tag::<Text<'a>, Address>::new(notify_to).into_element()),
+            );
+        }
+        if let Some(heartbeats) = self.heartbeats {
+            delivery = delivery.add_child(
+                Tag::<Time, Heartbeats>::new(Time(heartbeats.as_secs_f64())).into_element(),
+            );
+        }
+        if let Some(batching) = self.batching {
+            delivery = delivery.add_children(batching.elements().collect());
+        }
+
+        let children = [
+            Some(delivery),
+            self.expires.map(|expires| {
+                Tag::<Time, Expires>::new(Time(expires.as_secs_f64())).into_element()
+            }),
+            self.filter.map(Filter::into_element),
+            self.bookmark.map(Bookmark::into_element),
+            self.send_bookmarks
+                .then(|| Tag::<Empty, SendBookmarks>::new(Empty).into_element()),
+        ];
+
+        Element::new(Subscribe::TAG_NAME)
+            .set_namespace(Namespace::WsEventing2004.uri())
+            .add_namespace_declaration(
+                Namespace::WsEventing2004.uri(),
+                Namespace::WsEventing2004.alias(),
+            )
+            .add_children(children.into_iter().flatten().collect())
+    }
+}
+
+impl WsMan {
+    /// Build the `e:Subscribe` request
+    pub fn subscribe<'a>(
+        &'a self,
+        request: SubscribeRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let subscribe = request.value();
+
+        self.invoke(
+            WsAction::Subscribe,
+            Some(request.resource_uri),
+            SoapBody::builder().subscribe(subscribe).build(),
+            request.option_set,
+            request.selector_set,
+        )
+    }
+}
+
+/// The response to a [`SubscribeRequest`], the subscription manager to renew or unsubscribe
+/// the subscription with, and the expiry the server granted.
+#[derive(Debug, Clone)]
+pub struct SubscribeResponse<'a> {
+    manager: EndpointReference,
+    expires: Option<&'a str>,
+    context: Option<&'a str>,
+}
+
+impl<'a> SubscribeResponse<'a> {
+    /// Parse a `e:SubscribeResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::SubscribeResponse, language)?;
+
+        let response = body
+            .children()
+            .find(SubscribeResponseTag::matches_node)
+            .ok_or_else(|| {
+                ProtocolError::XmlParsingError("No SubscribeResponse found in body".to_owned())
+            })?;
+
+        let manager = response
+            .children()
+            .find(SubscriptionManager::matches_node)
+            .map(OwnedElement::from_node)
+            .and_then(|manager| EndpointReference::from_reference(&manager))
+            .ok_or_else(|| {
+                ProtocolError::XmlParsingError(
+                    "No SubscriptionManager found in SubscribeResponse".to_owned(),
+                )
+            })?;
+
+        let text = |node: Node<'a, 'a>| node.text().map(str::trim);
+
+        Ok(Self {
+            manager,
+            expires: response
+                .children()
+                .find(Expires::matches_node)
+                .and_then(text),
+            context: response
+                .children()
+                .find(EnumerationContext::matches_node)
+                .and_then(text),
+        })
+    }
+
+    /// The endpoint reference of the subscription manager
+    pub fn manager(&self) -> &EndpointReference {
+        &self.manager
+    }
+
+    /// The `e:Identifier` of the subscription, among the reference parameters of the manager
+    pub fn identifier(&self) -> Option<&str> {
+        identifier(&self.manager)
+    }
+
+    /// The granted `e:Expires`, a duration or a date and time, `None` if the subscription
+    /// doesn't expire
+    pub fn expires(&self) -> Option<&'a str> {
+        self.expires
+    }
+
+    /// The granted expiry when it is a duration
+    pub fn expires_in(&self) -> Option<Duration> {
+        parse_duration(self.expires?)
+    }
+
+    /// The `n:EnumerationContext` to Pull the events with, for [`DeliveryMode::Pull`]
+    pub fn context(&self) -> Option<&'a str> {
+        self.context
+    }
+}
+
+/// A WS-Eventing Renew of the subscription `manager` refers to, extending its expiry
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct RenewRequest<'a> {
+    manager: &'a EndpointReference,
+    /// The expiry asked for, the server picks one without it
+    #[builder(default, setter(strip_option))]
+    expires: Option<Duration>,
+}
+
+/// A WS-Eventing Unsubscribe, ending the subscription `manager` refers to
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct UnsubscribeRequest<'a> {
+    manager: &'a EndpointReference,
+}
+
+impl WsMan {
+    /// Build the `e:Renew` request
+    pub fn renew<'a>(&'a self, request: RenewRequest<'a>) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let mut value = RenewValue::default();
+        if let Some(expires) = request.expires {
+            value.expires = Some(Tag::new(Time(expires.as_secs_f64())));
+        }
+        let renew = Tag::new(value).with_declaration(Namespace::WsEventing2004);
+
+        self.to_manager(
+            WsAction::Renew,
+            request.manager,
+            SoapBody::builder().renew(renew).build(),
+        )
+    }
+
+    /// Build the `e:Unsubscribe` request
+    pub fn unsubscribe<'a>(
+        &'a self,
+        request: UnsubscribeRequest<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let unsubscribe = Tag::new(Empty).with_declaration(Namespace::WsEventing2004);
+
+        self.to_manager(
+            WsAction::Unsubscribe,
+            request.manager,
+            SoapBody::builder().unsubscribe(unsubscribe).build(),
+        )
+    }
+
+    /// A request to the subscription manager, its reference parameters are echoed as headers
+    fn to_manager<'a>(
+        &'a self,
+        action: WsAction,
+        manager: &'a EndpointReference,
+        body: SoapBody<'a>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        self.invoke(
+            action,
+            Some(&manager.resource_uri),
+            body,
+            None,
+            (!manager.selector_set.selectors.is_empty()).then(|| manager.selector_set.clone()),
+        )
+        .echo_parameters(&manager.unknown)
+    }
+}
+
+/// The response to a [`RenewRequest`], with the expiry the server granted
+#[derive(Debug, Clone, Copy)]
+pub struct RenewResponse<'a> {
+    expires: Option<&'a str>,
+}
+
+impl<'a> RenewResponse<'a> {
+    /// Parse a `e:RenewResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::RenewResponse, language)?;
+
+        let expires = body
+            .children()
+            .find(RenewResponseTag::matches_node)
+            .and_then(|response| response.children().find(Expires::matches_node))
+            .and_then(|expires| expires.text())
+            .map(str::trim);
+
+        Ok(Self { expires })
+    }
+
+    /// The granted `e:Expires`, a duration or a date and time
+    pub fn expires(&self) -> Option<&'a str> {
+        self.expires
+    }
+
+    /// The granted expiry when it is a duration
+    pub fn expires_in(&self) -> Option<Duration> {
+        parse_duration(self.expires?)
+    }
+}
+
+/// The response to an [`UnsubscribeRequest`], the body is empty
+#[derive(Debug, Clone, Copy)]
+pub struct UnsubscribeResponse;
+
+impl UnsubscribeResponse {
+    pub fn from_node(envelope: Node<'_, '_>, language: &str) -> Result<Self, ProtocolError> {
+        response_body(envelope, WsAction::UnsubscribeResponse, language)?;
+        Ok(Self)
+    }
+}
+
+/// A subscription kept alive by the client, renewed before the expiry the server granted.
+///
+/// Renewals are due when half of the granted expiry has elapsed, leaving the other half to
+/// retry a failed one. A subscription without expiry, or one granted a date and time rather
+/// than a duration, is never renewed.
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    manager: EndpointReference,
+    requested: Option<Duration>,
+    granted: Option<Duration>,
+    granted_at: Instant,
+}
+
+impl Subscription {
+    /// Track the subscription of `response`, renewals ask for `requested` again
+    pub fn new(response: &SubscribeResponse<'_>, requested: Option<Duration>) -> Self {
+        Self {
+            manager: response.manager.clone(),
+            requested,
+            granted: response.expires_in(),
+            granted_at: Instant::now(),
+        }
+    }
+
+    pub fn manager(&self) -> &EndpointReference {
+        &self.manager
+    }
+
+    pub fn identifier(&self) -> Option<&str> {
+        identifier(&self.manager)
+    }
+
+    /// The expiry granted by the last Subscribe or Renew
+    pub fn granted(&self) -> Option<Duration> {
+        self.granted
+    }
+
+    /// When the next Renew is due, `None` if the subscription is never renewed
+    pub fn renew_at(&self) -> Option<Instant> {
+        Some(self.granted_at + self.granted? / 2)
+    }
+
+    pub fn renew_request(&self) -> RenewRequest<'_> {
+        RenewRequest {
+            manager: &self.manager,
+            expires: self.requested,
+        }
+    }
+
+    /// Reschedule the next Renew after the expiry granted by `response`
+    pub fn renewed(&mut self, response: &RenewResponse<'_>) {
+        self.granted = response.expires_in();
+        self.granted_at = Instant::now();
+    }
+
+    pub fn unsubscribe_request(&self) -> UnsubscribeRequest<'_> {
+        UnsubscribeRequest {
+            manager: &self.manager,
+        }
+    }
+
+    /// Renew the subscription each time it is due, until a Renew fails or the subscription no
+    /// longer needs renewing.
+    ///
+    /// `sleep` waits for a duration with the timer of the caller's runtime, `exchange` sends a
+    /// request envelope to the server and resolves to the response envelope. The future is
+    /// meant to run next to the delivery of the events and to be dropped before Unsubscribing.
+    pub async fn keep_alive<S, SFut, F, Fut>(
+        &mut self,
+        ws_man: &WsMan,
+        language: &str,
+        mut sleep: S,
+        mut exchange: F,
+    ) -> Result<(), ProtocolError>
+    where
+        S: FnMut(Duration) -> SFut,
+        SFut: Future<Output = ()>,
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<String, ProtocolError>>,
+    {
+        while let Some(renew_at) = self.renew_at() {
+            sleep(renew_at.saturating_duration_since(Instant::now())).await;
+
+            let request = ws_man
+                .renew(self.renew_request())
+                .into_element()
+                .to_string();
+            let response = exchange(request).await?;
+            let document = xml::parser::parse(&response)
+                .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+            let response = RenewResponse::from_node(document.root_element(), language)?;
+
+            debug!(expires = ?response.expires(), "Subscription renewed");
+            self.renewed(&response);
+        }
+
+        Ok(())
+    }
+}
+
+/// The `e:Identifier` of a subscription, among the reference parameters of its manager
+fn identifier(manager: &EndpointReference) -> Option<&str> {
+    manager
+        .unknown
+        .iter()
+        .find(|parameter| parameter.is::<Identifier>())?
+        .text
+        .as_deref()
+        .map(str::trim)
+}
+
+/// Parse a `xs:duration` of days, hours, minutes and seconds, e.g. `P1DT2H30.5S`.
+/// Durations in years or months have no fixed length and are not supported.
+pub(crate) fn parse_duration(duration: &str) -> Option<Duration> {
+    let rest = duration.trim().strip_prefix('P')?;
+    let (days, time) = match rest.split_once('T') {
+        Some((days, time)) => (days, Some(time)),
+        None => (rest, None),
+    };
+
+    let mut seconds = 0.0;
+    if !days.is_empty() {
+        seconds += days.strip_suffix('D')?.parse::<u64>().ok()? as f64 * 86_400.0;
+    }
+
+    if let Some(mut time) = time {
+        if time.is_empty() {
+            return None;
+        }
+        for (unit, scale) in [('H', 3_600.0), ('M', 60.0), ('S', 1.0)] {
+            if let Some((value, tail)) = time.split_once(unit) {
+                seconds += value.parse::<f64>().ok()? * scale;
+                time = tail;
+            }
+        }
+        if !time.is_empty() {
+            return None;
+        }
+    }
+
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT60.000S"), Some(Duration::from_secs(60)));
+        assert_eq!(
+            parse_duration("P1DT2H30M"),
+            Some(Duration::from_secs(86_400 + 2 * 3_600 + 30 * 60))
+        );
+        assert_eq!(parse_duration("PT0.5S"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("P1Y"), None);
+        assert_eq!(parse_duration("PT"), None);
+        assert_eq!(parse_duration("2026-10-14T00:00:00Z"), None);
+    }
+}
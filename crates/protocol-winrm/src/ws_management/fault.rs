@@ -0,0 +1,162 @@
+/// Structured classification of a `WSManFault`.
+///
+/// Servers report faults with a numeric `Code` on the `w:WSManFault` detail element
+/// and/or a subcode QName such as `w:TimedOut` in `s:Subcode/s:Value`.
+/// Both can be mapped onto the same kind so callers don't have to match raw numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WsmanFaultKind {
+    /// The operation did not complete within `OperationTimeout`, usually means "poll again"
+    OperationTimeout,
+    /// The caller is not allowed to perform the operation
+    AccessDenied,
+    /// A per-user or per-shell quota (shells, commands, memory) was exceeded
+    QuotaLimit,
+    /// The referenced shell no longer exists on the server
+    ShellNotFound,
+    /// The server is busy processing a concurrent request for the same resource
+    Concurrency,
+    /// The resource URI or endpoint could not be reached
+    DestinationUnreachable,
+    /// The `wsa:Action` is not supported by the resource
+    ActionNotSupported,
+    /// The envelope or a value exceeded a server-side encoding limit
+    EncodingLimit,
+    /// The selectors did not identify a valid resource instance
+    InvalidSelectors,
+    /// One or more options in the `w:OptionSet` were rejected
+    InvalidOptions,
+    /// A parameter of the operation was invalid
+    InvalidParameter,
+    /// A header marked with `mustUnderstand` was not understood by the server
+    NotUnderstood,
+    /// The enumeration context is unknown or has expired
+    InvalidEnumerationContext,
+    /// The message failed schema validation on the server
+    SchemaValidation,
+    /// The requested feature is not supported by the server
+    UnsupportedFeature,
+    /// The resource already exists
+    AlreadyExists,
+    /// An unclassified error happened on the server side
+    InternalError,
+    /// Any fault that is not part of the catalog
+    Unknown,
+}
+
+impl WsmanFaultKind {
+    /// Well-known numeric `WSManFault` codes
+    pub const CODE_ACCESS_DENIED: u32 = 5;
+    pub const CODE_OPERATION_TIMEOUT: u32 = 2150858793;
+    pub const CODE_SHELL_NOT_FOUND: u32 = 2150858843;
+    pub const CODE_QUOTA_LIMIT: u32 = 2150859174;
+
+    /// Map a numeric `WSManFault` code onto a kind
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            Self::CODE_ACCESS_DENIED => Some(Self::AccessDenied),
+            Self::CODE_OPERATION_TIMEOUT => Some(Self::OperationTimeout),
+            Self::CODE_SHELL_NOT_FOUND => Some(Self::ShellNotFound),
+            Self::CODE_QUOTA_LIMIT => Some(Self::QuotaLimit),
+            _ => None,
+        }
+    }
+
+    /// Map a subcode QName (e.g. `w:TimedOut`) onto a kind.
+    /// Only the local part is considered since servers are free to choose the prefix.
+    pub fn from_subcode(subcode: &str) -> Option<Self> {
+        let local = subcode
+            .trim()
+            .rsplit_once(':')
+            .map_or(subcode.trim(), |(_, local)| local);
+
+        let kind = match local {
+            "TimedOut" => Self::OperationTimeout,
+            "AccessDenied" => Self::AccessDenied,
+            "QuotaLimit" => Self::QuotaLimit,
+            "Concurrency" => Self::Concurrency,
+            "DestinationUnreachable" | "EndpointUnavailable" => Self::DestinationUnreachable,
+            "ActionNotSupported" => Self::ActionNotSupported,
+            "EncodingLimit" => Self::EncodingLimit,
+            "InvalidSelectors" => Self::InvalidSelectors,
+            "InvalidOptions" => Self::InvalidOptions,
+            "InvalidParameter" | "InvalidValue" => Self::InvalidParameter,
+            "MustUnderstand" => Self::NotUnderstood,
+            "InvalidEnumerationContext" => Self::InvalidEnumerationContext,
+            "SchemaValidationError" => Self::SchemaValidation,
+            "UnsupportedFeature" => Self::UnsupportedFeature,
+            "AlreadyExists" => Self::AlreadyExists,
+            "InternalError" => Self::InternalError,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    /// Resolve a kind from whatever the fault carried.
+    /// The numeric code is more specific than the subcode, so it wins when both are known.
+    pub fn classify(code: Option<u32>, subcode: Option<&str>) -> Self {
+        code.and_then(Self::from_code)
+            .or_else(|| subcode.and_then(Self::from_subcode))
+            .unwrap_or(Self::Unknown)
+    }
+
+    /// Whether re-issuing the same request is expected to succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::OperationTimeout | Self::Concurrency)
+    }
+
+    /// Whether the fault is caused by the caller's credentials or permissions
+    pub fn is_auth_failure(&self) -> bool {
+        matches!(self, Self::AccessDenied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_codes() {
+        assert_eq!(
+            WsmanFaultKind::from_code(2150858793),
+            Some(WsmanFaultKind::OperationTimeout)
+        );
+        assert_eq!(
+            WsmanFaultKind::from_code(2150859174),
+            Some(WsmanFaultKind::QuotaLimit)
+        );
+        assert_eq!(WsmanFaultKind::from_code(1), None);
+    }
+
+    #[test]
+    fn test_subcode_ignores_prefix() {
+        assert_eq!(
+            WsmanFaultKind::from_subcode("w:TimedOut"),
+            Some(WsmanFaultKind::OperationTimeout)
+        );
+        assert_eq!(
+            WsmanFaultKind::from_subcode("wsman:AccessDenied"),
+            Some(WsmanFaultKind::AccessDenied)
+        );
+        assert_eq!(
+            WsmanFaultKind::from_subcode("InvalidSelectors"),
+            Some(WsmanFaultKind::InvalidSelectors)
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        let kind = WsmanFaultKind::classify(Some(2150858793), Some("w:InternalError"));
+        assert!(kind.is_retryable());
+        assert!(!kind.is_auth_failure());
+
+        let kind = WsmanFaultKind::classify(Some(42), Some("w:AccessDenied"));
+        assert!(kind.is_auth_failure());
+        assert!(!kind.is_retryable());
+
+        assert_eq!(
+            WsmanFaultKind::classify(None, None),
+            WsmanFaultKind::Unknown
+        );
+    }
+}
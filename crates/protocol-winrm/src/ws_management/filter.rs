@@ -0,0 +1,214 @@
+use xml::builder::{Attribute, Element};
+
+use crate::{
+    cores::{
+        Address, AssociatedInstances, AssociationClassName, AssociationInstances,
+        Filter as FilterTag, IncludeResultProperty, Namespace, Object, ReferenceParameters,
+        ResultClassName, ResultRole, Role, Tag, TagName, Text,
+    },
+    ws_management::{SelectorSetValue, body::ReferenceParametersValue},
+};
+
+/// Dialect of [`Filter::Wql`]
+pub const WQL_DIALECT: &str = "http://schemas.microsoft.com/wbem/wsman/1/WQL";
+
+/// Dialect of [`Filter::XPath`], XPath 1.0 as defined by WS-Management
+pub const XPATH_DIALECT: &str = "http://www.w3.org/TR/1999/REC-xpath-19991116";
+
+/// Dialect of [`Filter::AssociatedInstances`] and [`Filter::AssociationInstances`]
+pub const ASSOCIATION_DIALECT: &str =
+    "http://schemas.dmtf.org/wbem/wsman/1/cimbinding/associationFilter";
+
+/// Dialect of [`Filter::EventQuery`], the structured queries of the Windows Event Log
+pub const EVENT_QUERY_DIALECT: &str = "http://schemas.microsoft.com/win/2004/08/events/eventquery";
+
+/// Address of an endpoint reference the server resolves itself
+const ANONYMOUS_ADDRESS: &str = "http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous";
+
+/// The `w:Filter` of an Enumerate or a Subscribe, selecting the instances or events the
+/// server returns.
+///
+/// A query is sent as the text of the filter, the XML special characters it contains are
+/// escaped when the request is serialized.
+#[derive(Debug, Clone)]
+pub enum Filter<'a> {
+    /// A WQL query of WMI, e.g. `SELECT * FROM Win32_Service WHERE State='Running'`
+    Wql(&'a str),
+    /// An XPath 1.0 expression evaluated against each instance, e.g.
+    /// `/p:Win32_Service[p:State='Running']`.
+    ///
+    /// The prefixes of the expression are resolved with `namespaces`, pairs of prefix and URI
+    /// declared on the filter.
+    XPath {
+        expression: &'a str,
+        namespaces: Vec<(&'a str, &'a str)>,
+    },
+    /// The instances associated to an object, e.g. the partitions of a `Win32_LogicalDisk`.
+    /// The resource URI of the Enumerate is the one of all the classes, `.../cimv2/*` for WMI.
+    AssociatedInstances(AssociationFilter<'a>),
+    /// The association instances that refer to an object, e.g. the
+    /// `Win32_LogicalDiskToPartition` of a `Win32_LogicalDisk`.
+    AssociationInstances(AssociationFilter<'a>),
+    /// An event log query of a subscription, pairs of channel and XPath selection, e.g.
+    /// `("System", "*[System[(Level=1 or Level=2)]]")`.
+    ///
+    /// Sent as a `QueryList` with a single `Query`, each pair being one of its `Select`.
+    EventQuery(Vec<(&'a str, &'a str)>),
+}
+
+impl<'a> Filter<'a> {
+    /// An XPath filter binding each `(prefix, uri)` of `namespaces` for the expression
+    pub fn xpath(
+        expression: &'a str,
+        namespaces: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        Filter::XPath {
+            expression,
+            namespaces: namespaces.into_iter().collect(),
+        }
+    }
+
+    /// An event query selecting with each `(path, select)` of `selects`
+    pub fn event_query(selects: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        Filter::EventQuery(selects.into_iter().collect())
+    }
+
+    /// The URI of the `Dialect` attribute
+    pub fn dialect(&self) -> &'static str {
+        match self {
+            Filter::Wql(_) => WQL_DIALECT,
+            Filter::XPath { .. } => XPATH_DIALECT,
+            Filter::AssociatedInstances(_) | Filter::AssociationInstances(_) => ASSOCIATION_DIALECT,
+            Filter::EventQuery(_) => EVENT_QUERY_DIALECT,
+        }
+    }
+
+    pub fn into_element(self) -> Element<'a> {
+        let mut element = Element::new(FilterTag::TAG_NAME)
+            .add_attribute(Attribute::new("Dialect", self.dialect()));
+        if let Some(namespace) = FilterTag::NAMESPACE {
+            element = element.set_namespace(namespace);
+        }
+
+        match self {
+            Filter::Wql(query) => element.set_text(query),
+            Filter::XPath {
+                expression,
+                namespaces,
+            } => {
+                for (prefix, uri) in namespaces {
+                    element = element.add_namespace_declaration(uri, Some(prefix));
+                }
+                element.set_text(expression)
+            }
+            Filter::AssociatedInstances(filter) => {
+                element.add_child(filter.into_element::<AssociatedInstances>(true))
+            }
+            Filter::AssociationInstances(filter) => {
+                element.add_child(filter.into_element::<AssociationInstances>(false))
+            }
+            Filter::EventQuery(selects) => {
+                let selects = selects
+                    .into_iter()
+                    .map(|(path, select)| {
+                        Element::new("Select")
+                            .add_attribute(Attribute::new("Path", path))
+                            .set_text(select)
+                    })
+                    .collect();
+                let query = Element::new("Query")
+                    .add_attribute(Attribute::new("Id", "0"))
+                    .add_children(selects);
+
+                element.add_child(Element::new("QueryList").add_child(query))
+            }
+        }
+    }
+}
+
+/// The object and the optional constraints of an association filter, in the terms of CIM:
+/// associations link the roles of an association class, e.g. `Antecedent` and `Dependent`.
+///
+/// For [`Filter::AssociationInstances`] `result_class_name` is the association class, the
+/// `association_class_name` and `result_role` only apply to [`Filter::AssociatedInstances`].
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct AssociationFilter<'a> {
+    /// Resource URI of the class of the object
+    resource_uri: &'a str,
+    /// Selectors of the keys of the object
+    selector_set: SelectorSetValue,
+    #[builder(default = ANONYMOUS_ADDRESS)]
+    address: &'a str,
+    #[builder(default, setter(strip_option))]
+    association_class_name: Option<&'a str>,
+    /// Role of the object in the association
+    #[builder(default, setter(strip_option))]
+    role: Option<&'a str>,
+    #[builder(default, setter(strip_option))]
+    result_class_name: Option<&'a str>,
+    /// Role of the returned instances in the association
+    #[builder(default, setter(strip_option))]
+    result_role: Option<&'a str>,
+    /// Properties to return, all of them when empty
+    #[builder(default)]
+    include_result_property: Vec<&'a str>,
+}
+
+impl<'a> AssociationFilter<'a> {
+    /// `b:AssociatedInstances` or `b:AssociationInstances`, in schema order
+    fn into_element<N: TagName>(self, associated: bool) -> Element<'a> {
+        let object = Element::new(Object::TAG_NAME)
+            .set_namespace(Namespace::WsmanCimBinding.uri())
+            .add_child(Tag::<Text<'a>, Address>::new(self.address).into_element())
+            .add_child(
+                Tag::<ReferenceParametersValue<'a>, ReferenceParameters>::new(
+                    ReferenceParametersValue {
+                        resource_uri: Tag::new(self.resource_uri),
+                        selector_set: Tag::new(self.selector_set),
+                        unknown: Vec::new(),
+                    },
+                )
+                .into_element(),
+            );
+
+        let constraints = if associated {
+            vec![
+                self.association_class_name
+                    .map(|name| Tag::<Text<'a>, AssociationClassName>::new(name).into_element()),
+                self.role
+                    .map(|role| Tag::<Text<'a>, Role>::new(role).into_element()),
+                self.result_class_name
+                    .map(|name| Tag::<Text<'a>, ResultClassName>::new(name).into_element()),
+                self.result_role
+                    .map(|role| Tag::<Text<'a>, ResultRole>::new(role).into_element()),
+            ]
+        } else {
+            vec![
+                self.result_class_name
+                    .map(|name| Tag::<Text<'a>, ResultClassName>::new(name).into_element()),
+                self.role
+                    .map(|role| Tag::<Text<'a>, Role>::new(role).into_element()),
+            ]
+        };
+
+        let properties = self
+            .include_result_property
+            .into_iter()
+            .map(|property| Tag::<Text<'a>, IncludeResultProperty>::new(property).into_element());
+
+        Element::new(N::TAG_NAME)
+            .set_namespace(Namespace::WsmanCimBinding.uri())
+            .add_namespace_declaration(
+                Namespace::WsmanCimBinding.uri(),
+                Namespace::WsmanCimBinding.alias(),
+            )
+            .add_child(object)
+            .add_children(
+                constraints
+                    .into_iter()
+                    .flatten()
+                    .chain(properties)
+                    .collect(),
+            )
+    }
+}
@@ -0,0 +1,244 @@
+use xml::{
+    builder::{Child, Content, Element, XML_NAMESPACE},
+    parser::{Node, XmlDeserialize, XmlVisitor},
+};
+
+use crate::cores::{Attribute, FragmentTransfer, Tag, TagValue, Text};
+
+/// Default dialect of `w:FragmentTransfer`, XPath 1.0 level 1
+pub const XPATH_LEVEL_1_DIALECT: &str = "http://www.w3.org/TR/1999/REC-xpath-19991116";
+
+/// Build a `w:FragmentTransfer` header selecting a fragment of the resource with an XPath expression.
+/// Example:
+/// fragment_transfer("Config/MaxEnvelopeSizekb/text()")
+/// Generates: <w:FragmentTransfer s:mustUnderstand="true" Dialect="http://www.w3.org/TR/1999/REC-xpath-19991116">Config/MaxEnvelopeSizekb/text()</w:FragmentTransfer>
+pub fn fragment_transfer<'a>(
+    expression: impl Into<Text<'a>>,
+) -> Tag<'a, Text<'a>, FragmentTransfer> {
+    Tag::new(expression.into())
+        .with_attribute(Attribute::MustUnderstand(true))
+        .with_attribute(Attribute::Dialect(XPATH_LEVEL_1_DIALECT.into()))
+}
+
+/// Content of a `w:XmlFragment` wrapper returned by (or sent with) fragment-level Get/Put.
+///
+/// A fragment selected with `text()` is plain text, otherwise it is a list of elements.
+#[derive(Debug, Clone)]
+pub enum XmlFragmentValue<'a> {
+    Text(Text<'a>),
+    /// Elements built by the caller, e.g. for a fragment Put
+    Elements(Vec<Element<'a>>),
+    /// Elements received from the server
+    Nodes(Vec<Node<'a, 'a>>),
+    Empty,
+}
+
+impl<'a> XmlFragmentValue<'a> {
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            XmlFragmentValue::Text(text) => Some(text.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Received elements of the fragment, empty for any other content
+    pub fn nodes(&self) -> &[Node<'a, 'a>] {
+        match self {
+            XmlFragmentValue::Nodes(nodes) => nodes,
+            _ => &[],
+        }
+    }
+}
+
+/// Rebuild an element from a received node so a fragment can be sent back as-is,
+/// with the namespaces of its attributes and the text around its children
+fn node_to_element<'a>(node: Node<'a, 'a>) -> Element<'a> {
+    let mut element = Element::new(node.tag_name().name());
+
+    if let Some(uri) = node.tag_name().namespace() {
+        match node.lookup_prefix(uri) {
+            Some(prefix) => {
+                element = element
+                    .set_namespace(uri)
+                    .add_namespace_declaration(uri, Some(prefix));
+            }
+            // Default namespace, declaring it on the element is enough
+            None => element = element.add_namespace_declaration(uri, None),
+        }
+    }
+
+    for attribute in node.attributes() {
+        let mut xml_attribute = xml::builder::Attribute::new(attribute.name(), attribute.value());
+        if let Some(uri) = attribute.namespace() {
+            xml_attribute = xml_attribute.set_namespace(uri);
+            // The xml prefix is bound by definition and must not be declared again
+            if uri != XML_NAMESPACE && node.tag_name().namespace() != Some(uri) {
+                element = element
+                    .add_namespace_declaration(uri, Some(node.lookup_prefix(uri).unwrap_or("ns1")));
+            }
+        }
+        element = element.add_attribute(xml_attribute);
+    }
+
+    if node.children().any(|c| c.is_element()) {
+        let children = node
+            .children()
+            .filter_map(|child| {
+                if child.is_element() {
+                    Some(Child::Element(node_to_element(child)))
+                } else {
+                    child.text().map(|text| Child::Text(text.into()))
+                }
+            })
+            .collect();
+        element = element.set_content(Content::Mixed(children));
+    } else if let Some(text) = node.text() {
+        element = element.set_text(text);
+    }
+
+    element
+}
+
+impl<'a> TagValue<'a> for XmlFragmentValue<'a> {
+    fn append_to_element(self, element: Element<'a>) -> Element<'a> {
+        match self {
+            XmlFragmentValue::Text(text) => text.append_to_element(element),
+            XmlFragmentValue::Elements(elements) => element.add_children(elements),
+            XmlFragmentValue::Nodes(nodes) => {
+                element.add_children(nodes.into_iter().map(node_to_element).collect())
+            }
+            XmlFragmentValue::Empty => element,
+        }
+    }
+}
+
+pub struct XmlFragmentVisitor<'a> {
+    value: XmlFragmentValue<'a>,
+}
+
+impl<'a> XmlVisitor<'a> for XmlFragmentVisitor<'a> {
+    type Value = XmlFragmentValue<'a>;
+
+    fn visit_children(
+        &mut self,
+        children: impl Iterator<Item = Node<'a, 'a>>,
+    ) -> Result<(), xml::XmlError> {
+        let children: Vec<_> = children.collect();
+        let elements: Vec<_> = children
+            .iter()
+            .copied()
+            .filter(|c| c.is_element())
+            .collect();
+
+        self.value = if !elements.is_empty() {
+            XmlFragmentValue::Nodes(elements)
+        } else {
+            let text: String = children.iter().filter_map(|c| c.text()).collect();
+            if text.trim().is_empty() {
+                XmlFragmentValue::Empty
+            } else {
+                XmlFragmentValue::Text(Text::from(text.trim().to_string()))
+            }
+        };
+
+        Ok(())
+    }
+
+    fn visit_node(&mut self, node: Node<'a, 'a>) -> Result<(), xml::XmlError> {
+        self.visit_children(node.children())
+    }
+
+    fn finish(self) -> Result<Self::Value, xml::XmlError> {
+        Ok(self.value)
+    }
+}
+
+impl<'a> XmlDeserialize<'a> for XmlFragmentValue<'a> {
+    type Visitor = XmlFragmentVisitor<'a>;
+
+    fn visitor() -> Self::Visitor {
+        XmlFragmentVisitor {
+            value: XmlFragmentValue::Empty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cores::XmlFragment;
+
+    #[test]
+    fn test_xml_fragment_text() {
+        let xml = r#"<w:XmlFragment xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+            500
+        </w:XmlFragment>"#;
+        let doc = xml::parser::parse(xml).unwrap();
+        let tag: Tag<'_, XmlFragmentValue, XmlFragment> =
+            Tag::from_node(doc.root_element()).unwrap();
+
+        assert_eq!(tag.value.as_text(), Some("500"));
+    }
+
+    #[test]
+    fn test_xml_fragment_elements_round_trip() {
+        let xml = r#"<w:XmlFragment xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:cfg="http://schemas.microsoft.com/wbem/wsman/1/config"><cfg:MaxEnvelopeSizekb Source="GPO">500</cfg:MaxEnvelopeSizekb></w:XmlFragment>"#;
+        let doc = xml::parser::parse(xml).unwrap();
+        let tag: Tag<'_, XmlFragmentValue, XmlFragment> =
+            Tag::from_node(doc.root_element()).unwrap();
+
+        assert_eq!(tag.value.nodes().len(), 1);
+        assert_eq!(tag.value.nodes()[0].tag_name().name(), "MaxEnvelopeSizekb");
+
+        let element = Element::new("Root").add_children(
+            tag.value
+                .nodes()
+                .iter()
+                .copied()
+                .map(node_to_element)
+                .collect(),
+        );
+        assert_eq!(
+            element.to_string(),
+            r#"<Root><cfg:MaxEnvelopeSizekb xmlns:cfg="http://schemas.microsoft.com/wbem/wsman/1/config" Source="GPO">500</cfg:MaxEnvelopeSizekb></Root>"#
+        );
+    }
+
+    #[test]
+    fn test_xml_fragment_namespaced_attributes_round_trip() {
+        let xml = r#"<w:XmlFragment xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:cfg="http://schemas.microsoft.com/wbem/wsman/1/config" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"><cfg:Listener xml:lang="en-US">Port <cfg:Port>5985</cfg:Port> and <cfg:CertificateThumbprint xsi:nil="true"/> set</cfg:Listener></w:XmlFragment>"#;
+        let doc = xml::parser::parse(xml).unwrap();
+        let tag: Tag<'_, XmlFragmentValue, XmlFragment> =
+            Tag::from_node(doc.root_element()).unwrap();
+
+        let rebuilt = node_to_element(tag.value.nodes()[0]).to_string();
+        let rebuilt = xml::parser::parse(&rebuilt).unwrap();
+        let listener = rebuilt.root_element();
+        assert_eq!(listener.attribute((XML_NAMESPACE, "lang")), Some("en-US"));
+        let texts: Vec<_> = listener
+            .children()
+            .filter(|c| c.is_text())
+            .filter_map(|c| c.text())
+            .collect();
+        assert_eq!(texts, ["Port ", " and ", " set"]);
+        let thumbprint = listener
+            .children()
+            .find(|c| c.tag_name().name() == "CertificateThumbprint")
+            .unwrap();
+        assert_eq!(
+            thumbprint.attribute(("http://www.w3.org/2001/XMLSchema-instance", "nil")),
+            Some("true")
+        );
+    }
+
+    #[test]
+    fn test_fragment_transfer_dialect() {
+        let element = fragment_transfer("Config/MaxEnvelopeSizekb/text()").into_element();
+        let xml = Element::new("Header")
+            .add_namespace_declaration("http://www.w3.org/2003/05/soap-envelope", Some("s"))
+            .add_namespace_declaration("http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd", Some("w"))
+            .add_child(element)
+            .to_string();
+        assert!(xml.contains(r#"Dialect="http://www.w3.org/TR/1999/REC-xpath-19991116""#));
+    }
+}
@@ -6,7 +6,14 @@ use xml::{
     parser::{XmlDeserialize, XmlVisitor},
 };
 
-use crate::cores::{self, OptionTagName, Selector, Tag, TagName, TagValue, Text};
+use crate::{
+    cores::{
+        self, OptionTagName, Selector, Tag, TagName, TagValue, Text, UnknownTagPolicy,
+        namespaces_match,
+    },
+    error::ProtocolError,
+    ws_management::WsmanFaultKind,
+};
 
 #[derive(Debug, Clone)]
 pub struct SelectorSetValue {
@@ -78,7 +85,9 @@ impl<'a> XmlVisitor<'a> for SelectorSetVisitor {
             }
 
             match (child.tag_name().name(), child.tag_name().namespace()) {
-                (Selector::TAG_NAME, Selector::NAMESPACE) => {
+                (Selector::TAG_NAME, namespace)
+                    if namespaces_match(namespace, Selector::NAMESPACE) =>
+                {
                     // Extract Name attribute and text content
                     let mut name = None;
                     for attr in child.attributes() {
@@ -95,13 +104,7 @@ impl<'a> XmlVisitor<'a> for SelectorSetVisitor {
                         warn!("Selector element missing Name attribute");
                     }
                 }
-                _ => {
-                    warn!(
-                        "Unexpected child element in SelectorSetValue: {} (namespace: {:?})",
-                        child.tag_name().name(),
-                        child.tag_name().namespace()
-                    );
-                }
+                _ => UnknownTagPolicy::handle("SelectorSetValue", child)?,
             }
         }
 
@@ -130,9 +133,29 @@ impl<'a> XmlDeserialize<'a> for SelectorSetValue {
     }
 }
 
+/// Attributes of a `w:Option` besides its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionAttributes {
+    /// The server must fault instead of ignoring an option it cannot honor
+    pub must_comply: bool,
+    /// Declared type of the value, e.g. `xs:int`
+    pub value_type: Option<String>,
+}
+
+impl Default for OptionAttributes {
+    fn default() -> Self {
+        Self {
+            must_comply: true,
+            value_type: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OptionSetValue {
     pub options: HashMap<String, String>,
+    /// Attributes per option name, options missing here use [`OptionAttributes::default`]
+    pub attributes: HashMap<String, OptionAttributes>,
 }
 
 impl Default for OptionSetValue {
@@ -145,34 +168,122 @@ impl OptionSetValue {
     pub fn new() -> Self {
         Self {
             options: HashMap::new(),
+            attributes: HashMap::new(),
         }
     }
 
     /// Add an option as a key-value pair
     /// Example:
     /// option_set.add_option("WINRS_CONSOLEMODE_STDIN", "TRUE")
-    /// Generates: <w:Option Name="WINRS_CONSOLEMODE_STDIN">TRUE</w:Option>
+    /// Generates: <w:Option Name="WINRS_CONSOLEMODE_STDIN" MustComply="true">TRUE</w:Option>
     pub fn add_option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
         self.options.insert(name.into(), value.into());
         self
     }
 
+    /// Add an option with explicit attributes
+    /// Example:
+    /// option_set.add_option_with("WSMAN_CMDSHELL_OPTION_KEEPALIVE", "1", OptionAttributes { must_comply: false, value_type: Some("xs:int".into()) })
+    /// Generates: <w:Option Name="WSMAN_CMDSHELL_OPTION_KEEPALIVE" MustComply="false" Type="xs:int">1</w:Option>
+    pub fn add_option_with(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+        attributes: OptionAttributes,
+    ) -> Self {
+        self.insert_option_with(name, value, attributes);
+        self
+    }
+
     /// Add an option using a mutable reference for chaining
     pub fn insert_option(&mut self, name: impl Into<String>, value: impl Into<String>) {
         self.options.insert(name.into(), value.into());
     }
+
+    pub fn insert_option_with(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+        attributes: OptionAttributes,
+    ) {
+        let name = name.into();
+        self.options.insert(name.clone(), value.into());
+        self.attributes.insert(name, attributes);
+    }
+
+    pub fn option_attributes(&self, name: &str) -> OptionAttributes {
+        self.attributes.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Names of the options the server is not allowed to ignore
+    pub fn must_comply_options(&self) -> impl Iterator<Item = &str> {
+        self.options
+            .keys()
+            .filter(|name| self.option_attributes(name).must_comply)
+            .map(String::as_str)
+    }
+
+    /// Turn an `InvalidOptions` fault answering a request with this option set into
+    /// [`ProtocolError::MustComplyOptionRejected`], any other error is returned as-is.
+    ///
+    /// The rejected options are the MustComply ones named in the fault reason,
+    /// or all of them when the reason names none.
+    pub fn check_rejection(&self, error: ProtocolError) -> ProtocolError {
+        let ProtocolError::WsmanFault {
+            kind: WsmanFaultKind::InvalidOptions,
+            reason,
+            ..
+        } = &error
+        else {
+            return error;
+        };
+
+        let must_comply: Vec<_> = self.must_comply_options().collect();
+        if must_comply.is_empty() {
+            return error;
+        }
+
+        let named: Vec<_> = must_comply
+            .iter()
+            .filter(|name| reason.contains(**name))
+            .collect();
+
+        let mut options: Vec<String> = if named.is_empty() {
+            must_comply.iter().map(|name| name.to_string()).collect()
+        } else {
+            named.into_iter().map(|name| name.to_string()).collect()
+        };
+        options.sort();
+
+        ProtocolError::MustComplyOptionRejected {
+            options,
+            reason: reason.clone(),
+        }
+    }
 }
 
 impl<'a> TagValue<'a> for OptionSetValue {
     fn append_to_element(self, mut element: Element<'a>) -> Element<'a> {
+        let mut attributes = self.attributes;
         for (name, value) in self.options {
-            let option_element = Element::new("Option")
+            let OptionAttributes {
+                must_comply,
+                value_type,
+            } = attributes.remove(&name).unwrap_or_default();
+
+            let mut option_element = Element::new("Option")
                 .set_namespace(xml::builder::Namespace::from(
                     OptionTagName::NAMESPACE.expect("OptionTagName definately has a namespace"),
                 ))
                 .set_text(value)
                 .add_attribute(cores::Attribute::Name(name.into()).into())
-                .add_attribute(cores::Attribute::MustComply(true).into());
+                .add_attribute(cores::Attribute::MustComply(must_comply).into());
+
+            if let Some(value_type) = value_type {
+                option_element =
+                    option_element.add_attribute(cores::Attribute::Type(value_type.into()).into());
+            }
+
             element = element.add_child(option_element);
         }
 
@@ -182,6 +293,7 @@ impl<'a> TagValue<'a> for OptionSetValue {
 
 pub struct OptionSetVisitor {
     options: HashMap<String, String>,
+    attributes: HashMap<String, OptionAttributes>,
 }
 
 impl<'a> XmlVisitor<'a> for OptionSetVisitor {
@@ -197,30 +309,37 @@ impl<'a> XmlVisitor<'a> for OptionSetVisitor {
             }
 
             match (child.tag_name().name(), child.tag_name().namespace()) {
-                (OptionTagName::TAG_NAME, OptionTagName::NAMESPACE) => {
-                    // Extract Name attribute and text content
+                (OptionTagName::TAG_NAME, namespace)
+                    if namespaces_match(namespace, OptionTagName::NAMESPACE) =>
+                {
+                    // Extract Name, MustComply and Type attributes and text content
                     let mut name = None;
+                    let mut attributes = OptionAttributes::default();
                     for attr in child.attributes() {
-                        if attr.name() == "Name" {
-                            name = Some(attr.value().to_string());
-                            break;
+                        match attr.name() {
+                            "Name" => name = Some(attr.value().to_string()),
+                            "MustComply" => {
+                                attributes.must_comply = attr.value().parse().map_err(|_| {
+                                    xml::XmlError::InvalidXml(format!(
+                                        "Invalid value for MustComply: {}",
+                                        attr.value()
+                                    ))
+                                })?;
+                            }
+                            "Type" => attributes.value_type = Some(attr.value().to_string()),
+                            _ => {}
                         }
                     }
 
                     if let Some(name) = name {
                         let value = child.text().unwrap_or_default().to_string();
-                        self.options.insert(name, value);
+                        self.options.insert(name.clone(), value);
+                        self.attributes.insert(name, attributes);
                     } else {
                         warn!("Option element missing Name attribute");
                     }
                 }
-                _ => {
-                    warn!(
-                        "Unexpected child element in OptionSetValue: {} (namespace: {:?})",
-                        child.tag_name().name(),
-                        child.tag_name().namespace()
-                    );
-                }
+                _ => UnknownTagPolicy::handle("OptionSetValue", child)?,
             }
         }
 
@@ -235,6 +354,7 @@ impl<'a> XmlVisitor<'a> for OptionSetVisitor {
     fn finish(self) -> Result<Self::Value, xml::XmlError> {
         Ok(OptionSetValue {
             options: self.options,
+            attributes: self.attributes,
         })
     }
 }
@@ -245,6 +365,7 @@ impl<'a> XmlDeserialize<'a> for OptionSetValue {
     fn visitor() -> Self::Visitor {
         OptionSetVisitor {
             options: HashMap::new(),
+            attributes: HashMap::new(),
         }
     }
 }
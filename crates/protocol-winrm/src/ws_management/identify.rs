@@ -0,0 +1,130 @@
+use xml::parser::Node;
+
+use crate::{
+    cores::{
+        Body, Empty, Envelope, IdentifyResponse as IdentifyResponseTag, Namespace, ProductVendor,
+        ProductVersion, ProtocolVersion, SecurityProfileName, SecurityProfiles, Tag, TagName,
+    },
+    error::ProtocolError,
+    soap::{SoapEnvelope, SoapFault, body::SoapBody, header::SoapHeaders},
+    ws_management::WsMan,
+};
+
+/// HTTP header asking Windows to answer an Identify without authenticating the client, sent
+/// as `WSMANIDENTIFY: unauthenticated`
+pub const IDENTIFY_UNAUTHENTICATED_HEADER: (&str, &str) = ("WSMANIDENTIFY", "unauthenticated");
+
+/// The implementation behind an endpoint, as told by its [`IdentifyResponse`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerKind {
+    /// The WinRM service of Windows
+    Windows,
+    /// The Open Management Infrastructure of Linux and Unix hosts, e.g. PowerShell remoting
+    /// over OMI. It only speaks the DMTF namespaces and has no WinRS shells of its own.
+    Omi,
+    Other,
+}
+
+impl WsMan {
+    /// Build the `wsmid:Identify` request.
+    ///
+    /// Unlike the other operations it carries no WS-Addressing or WS-Management header, so it
+    /// can be sent before anything is known about the endpoint, see
+    /// [`IDENTIFY_UNAUTHENTICATED_HEADER`] to skip authentication.
+    pub fn identify(&self) -> Tag<'_, SoapEnvelope<'_>, Envelope> {
+        let identify = Tag::new(Empty).with_declaration(Namespace::WsmanIdentity);
+
+        let envelope = SoapEnvelope::builder()
+            .header(Tag::new(SoapHeaders::builder().build()))
+            .body(Tag::new(SoapBody::builder().identify(identify).build()))
+            .soap_version(self.soap_version())
+            .build();
+
+        Tag::new(envelope).with_declaration(Namespace::SoapEnvelope2003)
+    }
+}
+
+/// The response to an Identify, the protocol and product of the endpoint
+#[derive(Debug, Clone)]
+pub struct IdentifyResponse<'a> {
+    protocol_version: Option<&'a str>,
+    product_vendor: Option<&'a str>,
+    product_version: Option<&'a str>,
+    security_profiles: Vec<&'a str>,
+}
+
+impl<'a> IdentifyResponse<'a> {
+    /// Parse a `wsmid:IdentifyResponse` envelope, a fault, e.g. of an endpoint refusing
+    /// unauthenticated Identify, is returned as an error with its reason in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        if let Some(fault) = SoapFault::find(envelope) {
+            let fault = fault.map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+            return Err(fault.into_error(language));
+        }
+
+        let response = envelope
+            .children()
+            .find(Body::matches_node)
+            .ok_or(ProtocolError::MissingSoapBody)?
+            .children()
+            .find(IdentifyResponseTag::matches_node)
+            .ok_or_else(|| {
+                ProtocolError::XmlParsingError("No IdentifyResponse found in body".to_owned())
+            })?;
+
+        let text = |matches: fn(&Node<'_, '_>) -> bool| {
+            response
+                .children()
+                .find(|child| matches(child))
+                .and_then(|child| child.text())
+                .map(str::trim)
+        };
+
+        Ok(Self {
+            protocol_version: text(ProtocolVersion::matches_node),
+            product_vendor: text(ProductVendor::matches_node),
+            product_version: text(ProductVersion::matches_node),
+            security_profiles: response
+                .children()
+                .filter(SecurityProfiles::matches_node)
+                .flat_map(|profiles| profiles.children())
+                .filter(SecurityProfileName::matches_node)
+                .filter_map(|profile| profile.text())
+                .map(str::trim)
+                .collect(),
+        })
+    }
+
+    /// The namespace URI of the WS-Management version, e.g. the DMTF `wsman.xsd` one
+    pub fn protocol_version(&self) -> Option<&'a str> {
+        self.protocol_version
+    }
+
+    pub fn product_vendor(&self) -> Option<&'a str> {
+        self.product_vendor
+    }
+
+    /// e.g. `OS: 10.0.17763 SP: 0.0 Stack: 3.0` for Windows, `1.6.8-1` for OMI
+    pub fn product_version(&self) -> Option<&'a str> {
+        self.product_version
+    }
+
+    /// The authentication profiles the endpoint accepts, only listed by some servers
+    pub fn security_profiles(&self) -> &[&'a str] {
+        &self.security_profiles
+    }
+
+    pub fn server_kind(&self) -> ServerKind {
+        match self.product_vendor {
+            Some(vendor) if vendor.contains("Open Management Infrastructure") => ServerKind::Omi,
+            Some(vendor) if vendor.starts_with("Microsoft") => ServerKind::Windows,
+            _ => ServerKind::Other,
+        }
+    }
+
+    /// The version of the WinRM stack, e.g. `3.0`, from the product version of Windows
+    pub fn stack_version(&self) -> Option<&'a str> {
+        let (_, stack) = self.product_version?.split_once("Stack:")?;
+        stack.split_whitespace().next()
+    }
+}
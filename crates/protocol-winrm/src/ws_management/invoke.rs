@@ -0,0 +1,188 @@
+use std::borrow::Cow;
+
+use xml::{builder::Element, parser::Node};
+
+use crate::{
+    cores::{Envelope, OwnedElement, Tag, namespaces_match},
+    error::ProtocolError,
+    soap::{SoapEnvelope, body::SoapBody},
+    ws_management::{OptionSetValue, SelectorSetValue, WsAction, WsMan, transfer::response_body},
+};
+
+/// Prefix the method input is sent with, bound to the resource URI
+const INPUT_PREFIX: &str = "p";
+
+/// A custom action invoking a method of a resource, e.g. `Win32_Process.Create`.
+///
+/// The action URI is `<resourceURI>/<MethodName>` and the body is a `<MethodName>_INPUT`
+/// element in the namespace of the resource URI, with one child per input parameter.
+/// Example:
+/// InvokeRequest::new(uri, "Create").add_parameter("CommandLine", "notepad.exe")
+/// Generates: <p:Create_INPUT xmlns:p="{uri}"><p:CommandLine>notepad.exe</p:CommandLine></p:Create_INPUT>
+#[derive(Debug, Clone)]
+pub struct InvokeRequest<'a> {
+    resource_uri: &'a str,
+    method: &'a str,
+    input_name: String,
+    parameters: Vec<(&'a str, Cow<'a, str>)>,
+    selector_set: Option<SelectorSetValue>,
+    option_set: Option<OptionSetValue>,
+}
+
+impl<'a> InvokeRequest<'a> {
+    pub fn new(resource_uri: &'a str, method: &'a str) -> Self {
+        Self {
+            resource_uri,
+            method,
+            input_name: format!("{method}_INPUT"),
+            parameters: Vec::new(),
+            selector_set: None,
+            option_set: None,
+        }
+    }
+
+    /// Parameters are sent in the order they are added
+    pub fn add_parameter(mut self, name: &'a str, value: impl Into<Cow<'a, str>>) -> Self {
+        self.parameters.push((name, value.into()));
+        self
+    }
+
+    /// Select the instance for an instance method, static methods are invoked on the class
+    pub fn with_selector_set(mut self, selector_set: SelectorSetValue) -> Self {
+        self.selector_set = Some(selector_set);
+        self
+    }
+
+    pub fn with_option_set(mut self, option_set: OptionSetValue) -> Self {
+        self.option_set = Some(option_set);
+        self
+    }
+
+    pub fn resource_uri(&self) -> &'a str {
+        self.resource_uri
+    }
+
+    pub fn method(&self) -> &'a str {
+        self.method
+    }
+
+    pub fn action(&self) -> String {
+        format!("{}/{}", self.resource_uri, self.method)
+    }
+
+    /// The action of a successful response, `<resourceURI>/<MethodName>Response`
+    pub fn response_action(&self) -> String {
+        format!("{}/{}Response", self.resource_uri, self.method)
+    }
+}
+
+/// The `<MethodName>_INPUT` body of an [`InvokeRequest`]
+#[derive(Debug, Clone)]
+pub struct MethodInput<'a> {
+    name: &'a str,
+    namespace: &'a str,
+    parameters: Vec<(&'a str, Cow<'a, str>)>,
+}
+
+impl<'a> MethodInput<'a> {
+    pub fn into_element(self) -> Element<'a> {
+        let parameters = self
+            .parameters
+            .into_iter()
+            .map(|(name, value)| {
+                Element::new(name)
+                    .set_namespace(self.namespace)
+                    .set_text(value)
+            })
+            .collect();
+
+        Element::new(self.name)
+            .set_namespace(self.namespace)
+            .add_namespace_declaration(self.namespace, Some(INPUT_PREFIX))
+            .add_children(parameters)
+    }
+}
+
+impl WsMan {
+    /// Build the custom action request of `request`
+    pub fn invoke_method<'a>(
+        &'a self,
+        request: &'a InvokeRequest<'_>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let input = MethodInput {
+            name: &request.input_name,
+            namespace: request.resource_uri,
+            parameters: request.parameters.clone(),
+        };
+
+        self.invoke(
+            WsAction::Custom(request.action()),
+            Some(request.resource_uri),
+            SoapBody::builder().method_input(input).build(),
+            request.option_set.clone(),
+            request.selector_set.clone(),
+        )
+    }
+}
+
+/// The `<MethodName>_OUTPUT` of an [`InvokeRequest`], with the output parameters and the
+/// `ReturnValue` of the method
+#[derive(Debug, Clone, Copy)]
+pub struct InvokeResponse<'a> {
+    output: Node<'a, 'a>,
+}
+
+impl<'a> InvokeResponse<'a> {
+    /// Parse the response to `request`, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(
+        envelope: Node<'a, 'a>,
+        request: &InvokeRequest<'_>,
+        language: &str,
+    ) -> Result<Self, ProtocolError> {
+        let body = response_body(
+            envelope,
+            WsAction::Custom(request.response_action()),
+            language,
+        )?;
+
+        let output_name = format!("{}_OUTPUT", request.method);
+        let output = body
+            .children()
+            .find(|child| {
+                child.is_element()
+                    && child.tag_name().name() == output_name
+                    && namespaces_match(child.tag_name().namespace(), Some(request.resource_uri))
+            })
+            .ok_or_else(|| ProtocolError::XmlParsingError(format!("No {output_name} found")))?;
+
+        Ok(Self { output })
+    }
+
+    /// The value of the output parameter `name`
+    pub fn parameter(&self, name: &str) -> Option<&'a str> {
+        let namespace = self.output.tag_name().namespace();
+        self.output
+            .children()
+            .find(|child| {
+                child.is_element()
+                    && child.tag_name().name() == name
+                    && child.tag_name().namespace() == namespace
+            })?
+            .text()
+            .map(str::trim)
+    }
+
+    /// The `ReturnValue` of the method, 0 is success for WMI methods
+    pub fn return_value(&self) -> Option<u32> {
+        self.parameter("ReturnValue")?.parse().ok()
+    }
+
+    pub fn output(&self) -> Node<'a, 'a> {
+        self.output
+    }
+
+    pub fn to_owned_element(&self) -> OwnedElement {
+        OwnedElement::from_node(self.output)
+    }
+}
@@ -1,13 +1,45 @@
 pub mod body;
+pub mod delivery;
+pub mod enumeration;
+pub mod eventing;
+pub mod fault;
+pub mod filter;
+pub mod fragment;
 pub mod header;
+pub mod identify;
+pub mod invoke;
+pub mod operation;
+pub mod resource_uri;
+pub mod transfer;
+pub use delivery::{
+    EventStream, HeartbeatMonitor, HeartbeatWatch, PushedMessage, SubscriptionEvent,
+};
+pub use enumeration::{
+    DEFAULT_MAX_ELEMENTS, EndpointReference, EnumerateRequest, EnumerateResponse, EnumeratedItem,
+    EnumerationEnd, EnumerationEndCode, EnumerationMode, EnumerationStream, PullRequest,
+    PullResponse, ReleaseRequest, ReleaseResponse,
+};
+pub use eventing::{
+    Bookmark, DeliveryMode, EventBatching, EventBookmark, RenewRequest, RenewResponse,
+    SubscribeRequest, SubscribeResponse, Subscription, UnsubscribeRequest, UnsubscribeResponse,
+};
+pub use fault::WsmanFaultKind;
+pub use filter::{
+    ASSOCIATION_DIALECT, AssociationFilter, EVENT_QUERY_DIALECT, Filter, WQL_DIALECT, XPATH_DIALECT,
+};
 pub use header::*;
+pub use identify::{IDENTIFY_UNAUTHENTICATED_HEADER, IdentifyResponse, ServerKind};
+pub use invoke::{InvokeRequest, InvokeResponse};
+pub use operation::Operation;
+pub use resource_uri::ResourceUri;
+pub use transfer::{
+    CreateRequest, CreateResponse, DeleteRequest, DeleteResponse, GetRequest, GetResponse,
+    PutRequest, PutResponse,
+};
 
 use crate::{
-    cores::{
-        Attribute, Tag, Time, WsUuid, anytag::AnyTag, namespace::Namespace, tag_name::*,
-        tag_value::Text,
-    },
-    soap::{SoapEnvelope, body::SoapBody, header::SoapHeaders},
+    cores::{Attribute, Tag, Time, envelope, namespace::Namespace, tag_name::*},
+    soap::{SoapEnvelope, SoapVersion, body::SoapBody},
     ws_addressing::AddressValue,
 };
 
@@ -25,12 +57,15 @@ pub struct WsMan {
     #[builder(default = "en-US".to_string())]
     locale: String,
 
-    #[builder(default = "http://schemas.microsoft.com/powershell/Microsoft.PowerShell".to_string())]
+    #[builder(default = ResourceUri::POWERSHELL.to_string())]
     resource_uri: String,
 
     #[builder(default = uuid::Uuid::new_v4())]
     session_id: uuid::Uuid,
 
+    #[builder(default)]
+    soap_version: SoapVersion,
+
     to: String,
 }
 
@@ -38,27 +73,110 @@ impl WsMan {
     pub fn max_envelope_size(&self) -> u32 {
         self.max_envelope_size
     }
+
+    /// The `p:SessionId` sent with every envelope of this client
+    pub fn session_id(&self) -> uuid::Uuid {
+        self.session_id
+    }
+
+    pub fn soap_version(&self) -> SoapVersion {
+        self.soap_version
+    }
+
+    /// The `w:Locale` of the requests, the language fault reasons are read in
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum WsAction {
+    Ack,
     Create,
+    CreateResponse,
     Delete,
+    DeleteResponse,
+    Enumerate,
+    EnumerateResponse,
+    EnumerationEnd,
     Get,
+    GetResponse,
+    Heartbeat,
+    Pull,
+    PullResponse,
     Put,
+    PutResponse,
+    Release,
+    ReleaseResponse,
+    Renew,
+    RenewResponse,
+    Subscribe,
+    SubscribeResponse,
+    Unsubscribe,
+    UnsubscribeResponse,
     Command,
     CommandResponse,
     ShellReceive,
+    ShellReceiveResponse,
+    ShellSend,
+    ShellSendResponse,
+    ShellSignal,
+    ShellSignalResponse,
+    ShellDisconnect,
+    ShellDisconnectResponse,
+    ShellReconnect,
+    ShellReconnectResponse,
+    ShellConnect,
+    ShellConnectResponse,
     ShellCreate,
+    /// A custom action, e.g. the method of a resource, see [`InvokeRequest`]
+    Custom(String),
 }
 
 impl WsAction {
     pub fn as_str(&self) -> &str {
         match self {
+            WsAction::Ack => "http://schemas.dmtf.org/wbem/wsman/1/wsman/Ack",
             WsAction::Create => "http://schemas.xmlsoap.org/ws/2004/09/transfer/Create",
+            WsAction::CreateResponse => {
+                "http://schemas.xmlsoap.org/ws/2004/09/transfer/CreateResponse"
+            }
             WsAction::Delete => "http://schemas.xmlsoap.org/ws/2004/09/transfer/Delete",
+            WsAction::DeleteResponse => {
+                "http://schemas.xmlsoap.org/ws/2004/09/transfer/DeleteResponse"
+            }
+            WsAction::Enumerate => "http://schemas.xmlsoap.org/ws/2004/09/enumeration/Enumerate",
+            WsAction::EnumerateResponse => {
+                "http://schemas.xmlsoap.org/ws/2004/09/enumeration/EnumerateResponse"
+            }
+            WsAction::EnumerationEnd => {
+                "http://schemas.xmlsoap.org/ws/2004/09/enumeration/EnumerationEnd"
+            }
             WsAction::Get => "http://schemas.xmlsoap.org/ws/2004/09/transfer/Get",
+            WsAction::GetResponse => "http://schemas.xmlsoap.org/ws/2004/09/transfer/GetResponse",
+            WsAction::Heartbeat => "http://schemas.dmtf.org/wbem/wsman/1/wsman/Heartbeat",
+            WsAction::Pull => "http://schemas.xmlsoap.org/ws/2004/09/enumeration/Pull",
+            WsAction::PullResponse => {
+                "http://schemas.xmlsoap.org/ws/2004/09/enumeration/PullResponse"
+            }
             WsAction::Put => "http://schemas.xmlsoap.org/ws/2004/09/transfer/Put",
+            WsAction::PutResponse => "http://schemas.xmlsoap.org/ws/2004/09/transfer/PutResponse",
+            WsAction::Release => "http://schemas.xmlsoap.org/ws/2004/09/enumeration/Release",
+            WsAction::ReleaseResponse => {
+                "http://schemas.xmlsoap.org/ws/2004/09/enumeration/ReleaseResponse"
+            }
+            WsAction::Renew => "http://schemas.xmlsoap.org/ws/2004/08/eventing/Renew",
+            WsAction::RenewResponse => {
+                "http://schemas.xmlsoap.org/ws/2004/08/eventing/RenewResponse"
+            }
+            WsAction::Subscribe => "http://schemas.xmlsoap.org/ws/2004/08/eventing/Subscribe",
+            WsAction::SubscribeResponse => {
+                "http://schemas.xmlsoap.org/ws/2004/08/eventing/SubscribeResponse"
+            }
+            WsAction::Unsubscribe => "http://schemas.xmlsoap.org/ws/2004/08/eventing/Unsubscribe",
+            WsAction::UnsubscribeResponse => {
+                "http://schemas.xmlsoap.org/ws/2004/08/eventing/UnsubscribeResponse"
+            }
             WsAction::Command => "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Command",
             WsAction::CommandResponse => {
                 "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandResponse"
@@ -66,14 +184,47 @@ impl WsAction {
             WsAction::ShellReceive => {
                 "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Receive"
             } // See note below
+            WsAction::ShellReceiveResponse => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/ReceiveResponse"
+            }
+            WsAction::ShellSend => "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Send",
+            WsAction::ShellSendResponse => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/SendResponse"
+            }
+            WsAction::ShellSignal => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Signal"
+            }
+            WsAction::ShellSignalResponse => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/SignalResponse"
+            }
+            WsAction::ShellDisconnect => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Disconnect"
+            }
+            WsAction::ShellDisconnectResponse => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/DisconnectResponse"
+            }
+            WsAction::ShellReconnect => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Reconnect"
+            }
+            WsAction::ShellReconnectResponse => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/ReconnectResponse"
+            }
+            WsAction::ShellConnect => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Connect"
+            }
+            WsAction::ShellConnectResponse => {
+                "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/ConnectResponse"
+            }
             WsAction::ShellCreate => {
                 "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/create"
             }
+            WsAction::Custom(action) => action,
         }
     }
 }
 
 impl WsMan {
+    /// Build a request that is a single-shot operation, see [`WsMan::invoke_operation`]
     pub fn invoke<'a>(
         &'a self,
         action: WsAction,
@@ -82,75 +233,84 @@ impl WsMan {
         option_set: Option<header::OptionSetValue>,
         selector_set: Option<header::SelectorSetValue>,
     ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
-        // Generate a unique message ID and operation ID for this request
+        self.invoke_operation(
+            &mut Operation::new(),
+            action,
+            resource_uri,
+            resource_body,
+            option_set,
+            selector_set,
+        )
+    }
+
+    /// Build a request belonging to `operation`, advancing its SequenceId.
+    /// Retransmissions of the same request must reuse the same operation.
+    pub fn invoke_operation<'a>(
+        &'a self,
+        operation: &mut Operation,
+        action: WsAction,
+        resource_uri: Option<&'a str>,
+        resource_body: SoapBody<'a>,
+        option_set: Option<header::OptionSetValue>,
+        selector_set: Option<header::SelectorSetValue>,
+    ) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        // Generate a unique message ID for this request
         let message_id = uuid::Uuid::new_v4();
-        let operation_id = uuid::Uuid::new_v4();
 
         let resource_uri = resource_uri.unwrap_or(self.resource_uri.as_str());
 
-        // Create reply-to address value
-        let reply_to_addr = AddressValue {
-            url: Tag::new("http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous"),
-        };
-
-        // Create the SOAP header with all required fields
-        let header = SoapHeaders::builder()
-            .action(
-                Tag::new(action.as_str().to_owned())
-                    .with_name(Action)
-                    .with_attribute(Attribute::MustUnderstand(true)),
-            )
-            .data_locale(
-                Tag::new(())
-                    .with_attribute(Attribute::MustUnderstand(false))
-                    .with_attribute(Attribute::XmlLang(self.data_locale.clone().into())),
-            )
-            .locale(
-                Tag::new(())
-                    .with_attribute(Attribute::XmlLang(self.locale.clone().into()))
-                    .with_attribute(Attribute::MustUnderstand(false)),
-            )
-            .max_envelope_size(
-                Tag::new(self.max_envelope_size).with_attribute(Attribute::MustUnderstand(true)),
-            )
-            .resource_uri(Tag::new(resource_uri).with_attribute(Attribute::MustUnderstand(true)))
-            .operation_timeout(Time::from(self.operation_timeout))
+        let mut builder = envelope::Envelope::builder()
+            .to(self.to.as_str())
+            .action(action.as_str().to_owned())
+            .resource_uri(resource_uri)
             .message_id(message_id)
-            .to(self.to.as_ref())
-            .reply_to(Tag::new(reply_to_addr).with_attribute(Attribute::MustUnderstand(true)))
-            .session_id(
-                Tag::new(WsUuid(self.session_id)).with_attribute(Attribute::MustUnderstand(false)),
-            )
-            .operation_id(
-                Tag::new(WsUuid(operation_id)).with_attribute(Attribute::MustUnderstand(false)),
-            )
-            .sequence_id(Tag::new(Text::from("1")).with_attribute(Attribute::MustUnderstand(false)))
-            .option_set_opt(option_set.map(Tag::from).map(|t| {
-                t.with_declaration(Namespace::XmlSchemaInstance)
-                    .with_attribute(Attribute::MustUnderstand(true))
-            }))
-            .selector_set_opt(selector_set.map(Tag::from))
-            .build();
+            .session_id(self.session_id)
+            .operation_id(operation.id())
+            .sequence_id(operation.next_sequence_id())
+            .soap_version(self.soap_version)
+            .reply_to(AddressValue::anonymous())
+            .headers(|header| {
+                header.data_locale = Some(
+                    Tag::new(())
+                        .with_attribute(Attribute::MustUnderstand(false))
+                        .with_attribute(Attribute::XmlLang(self.data_locale.clone().into())),
+                );
+                header.locale = Some(
+                    Tag::new(())
+                        .with_attribute(Attribute::XmlLang(self.locale.clone().into()))
+                        .with_attribute(Attribute::MustUnderstand(false)),
+                );
+                header.max_envelope_size = Some(
+                    Tag::new(self.max_envelope_size)
+                        .with_attribute(Attribute::MustUnderstand(true)),
+                );
+                header.operation_timeout = Some(Time::from(self.operation_timeout).into());
+            });
 
-        // TODO: I don't like this design; it's a bit problematic, but I guess I will live with it right now.
-        let add_rsp_declaration = resource_body.command_line.is_some();
+        if let Some(option_set) = option_set {
+            builder = builder.options(option_set);
+        }
+
+        if let Some(selector_set) = selector_set {
+            builder = builder.selectors(selector_set);
+        }
 
-        // Create the complete SOAP envelope
-        let envelope = SoapEnvelope::builder()
-            .header(header)
-            .body(resource_body)
-            .build();
+        // TODO: I don't like this design; it's a bit problematic, but I guess I will live with it right now.
+        if resource_body.command_line.is_some() {
+            builder = builder.with_declaration(Namespace::WsmanShell);
+        }
 
-        // Convert to XML using Tag wrapper with proper namespaces
+        if resource_body.identify.is_some() {
+            builder = builder.with_declaration(Namespace::WsmanIdentity);
+        }
 
-        let mut soap = Tag::<SoapEnvelope, Envelope>::new(envelope)
-            .with_declaration(Namespace::SoapEnvelope2003)
-            .with_declaration(Namespace::WsAddressing2004)
-            .with_declaration(Namespace::DmtfWsmanSchema)
-            .with_declaration(Namespace::MsWsmanSchema);
+        let envelope = builder.body(resource_body).build();
 
-        if add_rsp_declaration {
-            soap = soap.with_declaration(Namespace::WsmanShell)
+        // Convert to XML using Tag wrapper with proper namespaces
+        let declarations = envelope.namespaces_declaration.clone();
+        let mut soap = Tag::<SoapEnvelope, Envelope>::new(SoapEnvelope::from(envelope));
+        for declaration in declarations {
+            soap = soap.with_declaration(declaration);
         }
 
         soap
@@ -0,0 +1,135 @@
+use crate::{error::ProtocolError, soap::SoapEnvelope};
+
+/// Tracks the `p:OperationID` / `p:SequenceId` pair of one logical operation (MS-WSMV robust sessions).
+///
+/// Every envelope sent for the operation carries the same OperationID and the next SequenceId,
+/// so a retransmitted request can be told apart from a new one. Responses echo both headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    id: uuid::Uuid,
+    /// The last SequenceId that was sent, 0 before the first request
+    sequence_id: u64,
+}
+
+impl Default for Operation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Operation {
+    pub fn new() -> Self {
+        Self::with_id(uuid::Uuid::new_v4())
+    }
+
+    pub fn with_id(id: uuid::Uuid) -> Self {
+        Self { id, sequence_id: 0 }
+    }
+
+    pub fn id(&self) -> uuid::Uuid {
+        self.id
+    }
+
+    /// The SequenceId of the last request sent for this operation
+    pub fn sequence_id(&self) -> u64 {
+        self.sequence_id
+    }
+
+    /// Advance to the SequenceId of the next request, sequences start at 1
+    pub fn next_sequence_id(&mut self) -> u64 {
+        self.sequence_id += 1;
+        self.sequence_id
+    }
+
+    /// Check that a response belongs to this operation.
+    ///
+    /// Both headers are optional on the wire, a missing header is not an error.
+    /// A SequenceId that was never sent means the response answers a request we don't know about.
+    pub fn validate_response(&self, envelope: &SoapEnvelope<'_>) -> Result<(), ProtocolError> {
+        let Some(header) = envelope.header.as_ref().map(|header| &header.value) else {
+            return Ok(());
+        };
+
+        if let Some(operation_id) = &header.operation_id
+            && operation_id.value.0 != self.id
+        {
+            return Err(ProtocolError::OperationIdMismatch {
+                expected: self.id,
+                found: operation_id.value.0,
+            });
+        }
+
+        if let Some(sequence_id) = &header.sequence_id {
+            let received =
+                sequence_id.value.as_ref().parse::<u64>().map_err(|e| {
+                    ProtocolError::XmlParsingError(format!("Invalid SequenceId: {e}"))
+                })?;
+
+            if received == 0 || received > self.sequence_id {
+                return Err(ProtocolError::SequenceIdMismatch {
+                    sent: self.sequence_id,
+                    received,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xml::parser::XmlDeserialize;
+
+    fn response(operation_id: uuid::Uuid, sequence_id: u64) -> String {
+        format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wsman.xsd">
+                <s:Header>
+                    <p:OperationID s:mustUnderstand="false">uuid:{operation_id}</p:OperationID>
+                    <p:SequenceId>{sequence_id}</p:SequenceId>
+                </s:Header>
+                <s:Body/>
+            </s:Envelope>"#
+        )
+    }
+
+    #[test]
+    fn test_sequence_increments() {
+        let mut operation = Operation::new();
+        assert_eq!(operation.sequence_id(), 0);
+        assert_eq!(operation.next_sequence_id(), 1);
+        assert_eq!(operation.next_sequence_id(), 2);
+    }
+
+    #[test]
+    fn test_validate_response() {
+        let mut operation = Operation::new();
+        operation.next_sequence_id();
+        operation.next_sequence_id();
+
+        let xml = response(operation.id(), 2);
+        let doc = xml::parser::parse(&xml).unwrap();
+        let envelope = SoapEnvelope::from_node(doc.root_element()).unwrap();
+        assert!(operation.validate_response(&envelope).is_ok());
+
+        let xml = response(operation.id(), 3);
+        let doc = xml::parser::parse(&xml).unwrap();
+        let envelope = SoapEnvelope::from_node(doc.root_element()).unwrap();
+        assert!(matches!(
+            operation.validate_response(&envelope),
+            Err(ProtocolError::SequenceIdMismatch {
+                sent: 2,
+                received: 3
+            })
+        ));
+
+        let xml = response(uuid::Uuid::new_v4(), 1);
+        let doc = xml::parser::parse(&xml).unwrap();
+        let envelope = SoapEnvelope::from_node(doc.root_element()).unwrap();
+        assert!(matches!(
+            operation.validate_response(&envelope),
+            Err(ProtocolError::OperationIdMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{cores::Text, error::ProtocolError};
+
+/// The `w:ResourceURI` addressing a class of resources on the server.
+///
+/// Validated on construction, use the constants for the well known shells
+/// and [`ResourceUri::wmi`] / [`ResourceUri::wmi_builder`] for WMI classes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceUri(String);
+
+impl ResourceUri {
+    /// The `cmd.exe` shell of WinRS
+    pub const WINDOWS_SHELL_CMD: &'static str =
+        "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/cmd";
+    /// Every shell of the server, whatever its resource URI, to enumerate them
+    pub const WINDOWS_SHELL: &'static str =
+        "http://schemas.microsoft.com/wbem/wsman/1/windows/shell";
+    /// The default PowerShell Remoting endpoint
+    pub const POWERSHELL: &'static str =
+        "http://schemas.microsoft.com/powershell/Microsoft.PowerShell";
+    /// Prefix of the PowerShell Remoting endpoints, followed by the name of the session
+    /// configuration
+    pub const POWERSHELL_PREFIX: &'static str = "http://schemas.microsoft.com/powershell/";
+    /// The Windows Event Log, the resource URI of event subscriptions
+    pub const EVENT_LOG: &'static str =
+        "http://schemas.microsoft.com/wbem/wsman/1/windows/EventLog";
+    /// WinRM configuration
+    pub const CONFIG: &'static str = "http://schemas.microsoft.com/wbem/wsman/1/config";
+    /// WinRS settings of the WinRM configuration, e.g. `MaxMemoryPerShellMB`
+    pub const CONFIG_WINRS: &'static str = "http://schemas.microsoft.com/wbem/wsman/1/config/winrs";
+    /// Prefix of the WMI classes, followed by the namespace and class name
+    pub const WMI_PREFIX: &'static str = "http://schemas.microsoft.com/wbem/wsman/1/wmi";
+    /// Prefix of the DMTF CIM classes, followed by the class name
+    pub const CIM_PREFIX: &'static str = "http://schemas.dmtf.org/wbem/wscim/1/cim-schema/2";
+
+    /// The default WMI namespace
+    pub const DEFAULT_WMI_NAMESPACE: &'static str = "root/cimv2";
+
+    /// Validate an arbitrary resource URI, it must be absolute and must not contain whitespace
+    pub fn parse(uri: impl Into<String>) -> Result<Self, ProtocolError> {
+        let uri = uri.into();
+
+        let Some((scheme, rest)) = uri.split_once(':') else {
+            return Err(ProtocolError::InvalidResourceUri(format!(
+                "{uri} is not an absolute URI"
+            )));
+        };
+
+        if scheme.is_empty()
+            || !scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        {
+            return Err(ProtocolError::InvalidResourceUri(format!(
+                "{uri} has an invalid scheme"
+            )));
+        }
+
+        if rest.is_empty() || uri.chars().any(char::is_whitespace) {
+            return Err(ProtocolError::InvalidResourceUri(format!(
+                "{uri} is not a valid URI"
+            )));
+        }
+
+        Ok(Self(uri))
+    }
+
+    pub fn windows_shell_cmd() -> Self {
+        Self(Self::WINDOWS_SHELL_CMD.to_string())
+    }
+
+    pub fn powershell() -> Self {
+        Self(Self::POWERSHELL.to_string())
+    }
+
+    /// The endpoint of a PowerShell session configuration, e.g. `Microsoft.PowerShell32` or
+    /// one registered with `Register-PSSessionConfiguration`
+    pub fn powershell_configuration(name: &str) -> Result<Self, ProtocolError> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.chars().any(char::is_whitespace) {
+            return Err(ProtocolError::InvalidResourceUri(format!(
+                "{name} is not a valid session configuration name"
+            )));
+        }
+
+        Ok(Self(format!("{}{name}", Self::POWERSHELL_PREFIX)))
+    }
+
+    /// Whether `uri` is the one of a PowerShell Remoting endpoint, whose commands are pipelines
+    pub fn is_powershell(uri: &str) -> bool {
+        uri.starts_with(Self::POWERSHELL_PREFIX)
+    }
+
+    /// Derive the URI of a WMI class from its namespace and class name.
+    /// Example:
+    /// ResourceUri::wmi("root\\cimv2", "Win32_OperatingSystem")
+    /// Gives: http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_OperatingSystem
+    pub fn wmi(namespace: &str, class_name: &str) -> Result<Self, ProtocolError> {
+        let namespace = namespace.trim_matches(['/', '\\']).replace('\\', "/");
+
+        if namespace.is_empty() || !namespace.split('/').all(is_wmi_identifier) {
+            return Err(ProtocolError::InvalidResourceUri(format!(
+                "{namespace} is not a valid WMI namespace"
+            )));
+        }
+
+        if !is_wmi_identifier(class_name) {
+            return Err(ProtocolError::InvalidResourceUri(format!(
+                "{class_name} is not a valid WMI class name"
+            )));
+        }
+
+        Ok(Self(format!(
+            "{}/{namespace}/{class_name}",
+            Self::WMI_PREFIX
+        )))
+    }
+
+    pub fn wmi_builder() -> WmiResourceUriBuilder {
+        WmiResourceUriBuilder::default()
+    }
+
+    /// Namespace and class name of a WMI resource URI
+    pub fn wmi_parts(&self) -> Option<(&str, &str)> {
+        let path = self.0.strip_prefix(Self::WMI_PREFIX)?.strip_prefix('/')?;
+        path.rsplit_once('/')
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Namespace and class names are word characters and must not start with a digit
+fn is_wmi_identifier(identifier: &str) -> bool {
+    !identifier.is_empty()
+        && !identifier.starts_with(|c: char| c.is_ascii_digit())
+        && identifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl AsRef<str> for ResourceUri {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ResourceUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<ResourceUri> for String {
+    fn from(uri: ResourceUri) -> Self {
+        uri.0
+    }
+}
+
+impl<'a> From<ResourceUri> for Text<'a> {
+    fn from(uri: ResourceUri) -> Self {
+        Text::from(uri.0)
+    }
+}
+
+impl std::str::FromStr for ResourceUri {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Builder of WMI resource URIs, the namespace defaults to `root/cimv2`
+#[derive(Debug, Clone, Default)]
+pub struct WmiResourceUriBuilder {
+    namespace: Option<String>,
+    class_name: Option<String>,
+}
+
+impl WmiResourceUriBuilder {
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn class_name(mut self, class_name: impl Into<String>) -> Self {
+        self.class_name = Some(class_name.into());
+        self
+    }
+
+    pub fn build(self) -> Result<ResourceUri, ProtocolError> {
+        let class_name = self.class_name.ok_or_else(|| {
+            ProtocolError::InvalidResourceUri("A WMI class name is required".to_string())
+        })?;
+
+        ResourceUri::wmi(
+            self.namespace
+                .as_deref()
+                .unwrap_or(ResourceUri::DEFAULT_WMI_NAMESPACE),
+            &class_name,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wmi_resource_uri() {
+        let uri = ResourceUri::wmi_builder()
+            .class_name("Win32_OperatingSystem")
+            .build()
+            .unwrap();
+        assert_eq!(
+            uri.as_str(),
+            "http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_OperatingSystem"
+        );
+        assert_eq!(
+            uri.wmi_parts(),
+            Some(("root/cimv2", "Win32_OperatingSystem"))
+        );
+
+        let uri = ResourceUri::wmi("root\\StandardCimv2", "MSFT_NetAdapter").unwrap();
+        assert_eq!(
+            uri.as_str(),
+            "http://schemas.microsoft.com/wbem/wsman/1/wmi/root/StandardCimv2/MSFT_NetAdapter"
+        );
+    }
+
+    #[test]
+    fn test_invalid_resource_uri() {
+        assert!(ResourceUri::wmi("root/cimv2", "Win32 OperatingSystem").is_err());
+        assert!(ResourceUri::wmi("", "Win32_Service").is_err());
+        assert!(ResourceUri::wmi_builder().build().is_err());
+        assert!(ResourceUri::parse("not a uri").is_err());
+        assert!(ResourceUri::parse("Win32_Service").is_err());
+        assert!(ResourceUri::parse(ResourceUri::WINDOWS_SHELL_CMD).is_ok());
+        assert_eq!(ResourceUri::powershell().wmi_parts(), None);
+        assert!(ResourceUri::powershell_configuration("").is_err());
+        assert!(ResourceUri::powershell_configuration("a/b").is_err());
+        assert!(ResourceUri::powershell_configuration("My Endpoint").is_err());
+    }
+
+    #[test]
+    fn test_powershell_configuration() {
+        let uri = ResourceUri::powershell_configuration("Microsoft.PowerShell").unwrap();
+        assert_eq!(uri, ResourceUri::powershell());
+
+        let uri = ResourceUri::powershell_configuration("JEA.Maintenance").unwrap();
+        assert_eq!(
+            uri.as_str(),
+            "http://schemas.microsoft.com/powershell/JEA.Maintenance"
+        );
+        assert!(ResourceUri::is_powershell(uri.as_str()));
+        assert!(!ResourceUri::is_powershell(ResourceUri::WINDOWS_SHELL_CMD));
+    }
+}
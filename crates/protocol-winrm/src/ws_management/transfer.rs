@@ -0,0 +1,342 @@
+use xml::parser::{Node, XmlDeserialize};
+
+use crate::{
+    cores::{
+        Action, Body, Envelope, Header, OwnedElement, ResourceCreated, Tag, TagName, XmlFragment,
+    },
+    error::ProtocolError,
+    soap::{SoapEnvelope, SoapFault, body::SoapBody},
+    ws_management::{
+        OptionSetValue, SelectorSetValue, WsAction, WsMan,
+        body::{ReferenceParametersValue, ResourceCreatedValue, UnknownParameters},
+        fragment::{XmlFragmentValue, fragment_transfer},
+    },
+};
+
+/// A WS-Transfer Get of a single resource instance, e.g. one WMI object.
+///
+/// The instance is addressed by its resource URI and, for classes with several instances,
+/// the selectors of its keys.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct GetRequest<'a> {
+    resource_uri: &'a str,
+    #[builder(default, setter(strip_option))]
+    selector_set: Option<SelectorSetValue>,
+    #[builder(default, setter(strip_option))]
+    option_set: Option<OptionSetValue>,
+    /// XPath expression selecting a single property instead of the whole instance, e.g.
+    /// `MaxMemoryPerShellMB` of winrm/config/winrs, see [`GetResponse::fragment`]
+    #[builder(default, setter(strip_option))]
+    fragment: Option<&'a str>,
+}
+
+impl<'a> GetRequest<'a> {
+    pub fn resource_uri(&self) -> &'a str {
+        self.resource_uri
+    }
+}
+
+/// A WS-Transfer Put replacing the representation of a resource instance.
+///
+/// With a fragment only the selected property is updated, the body then carries the new
+/// value in a `w:XmlFragment`:
+/// ```ignore
+/// let request = PutRequest::builder()
+///     .resource_uri(ResourceUri::CONFIG_WINRS)
+///     .fragment("MaxMemoryPerShellMB/text()")
+///     .body(
+///         SoapBody::builder()
+///             .xml_fragment(Tag::new(XmlFragmentValue::Text("2048".into())))
+///             .build(),
+///     )
+///     .build();
+/// ```
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct PutRequest<'a> {
+    resource_uri: &'a str,
+    body: SoapBody<'a>,
+    #[builder(default, setter(strip_option))]
+    selector_set: Option<SelectorSetValue>,
+    #[builder(default, setter(strip_option))]
+    option_set: Option<OptionSetValue>,
+    #[builder(default, setter(strip_option))]
+    fragment: Option<&'a str>,
+}
+
+/// A WS-Transfer Create of a new resource, e.g. a shell or a subscription.
+///
+/// The body is the initial representation of the resource. The resource URI defaults to the
+/// one of the [`WsMan`] client.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct CreateRequest<'a> {
+    #[builder(default, setter(strip_option))]
+    resource_uri: Option<&'a str>,
+    body: SoapBody<'a>,
+    #[builder(default, setter(strip_option))]
+    selector_set: Option<SelectorSetValue>,
+    #[builder(default, setter(strip_option))]
+    option_set: Option<OptionSetValue>,
+}
+
+/// A WS-Transfer Delete of the resource an endpoint reference points to
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct DeleteRequest<'a> {
+    resource_uri: &'a str,
+    #[builder(default, setter(strip_option))]
+    selector_set: Option<SelectorSetValue>,
+    /// Reference parameters of the EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: UnknownParameters<'a>,
+}
+
+impl<'a> DeleteRequest<'a> {
+    /// Delete the resource `endpoint` refers to, e.g. the one returned by a [`CreateResponse`]
+    pub fn to_endpoint(endpoint: &'a ReferenceParametersValue<'_>) -> Self {
+        Self {
+            resource_uri: endpoint.resource_uri.value.as_ref(),
+            selector_set: Some(endpoint.selector_set.value.clone()),
+            reference_parameters: &endpoint.unknown,
+        }
+    }
+}
+
+impl WsMan {
+    /// Build the `wxf:Get` request, the body is empty
+    pub fn get<'a>(&'a self, request: GetRequest<'a>) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let envelope = self.invoke(
+            WsAction::Get,
+            Some(request.resource_uri),
+            SoapBody::builder().build(),
+            request.option_set,
+            request.selector_set,
+        );
+
+        with_fragment(envelope, request.fragment)
+    }
+
+    /// Build the `wxf:Put` request
+    pub fn put<'a>(&'a self, request: PutRequest<'a>) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        let envelope = self.invoke(
+            WsAction::Put,
+            Some(request.resource_uri),
+            request.body,
+            request.option_set,
+            request.selector_set,
+        );
+
+        with_fragment(envelope, request.fragment)
+    }
+
+    /// Build the `wxf:Create` request
+    pub fn create<'a>(&'a self, request: CreateRequest<'a>) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        self.invoke(
+            WsAction::Create,
+            request.resource_uri,
+            request.body,
+            request.option_set,
+            request.selector_set,
+        )
+    }
+
+    /// Build the `wxf:Delete` request, the body is empty
+    pub fn delete<'a>(&'a self, request: DeleteRequest<'a>) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+        self.invoke(
+            WsAction::Delete,
+            Some(request.resource_uri),
+            SoapBody::builder().build(),
+            None,
+            request.selector_set,
+        )
+        .echo_parameters(request.reference_parameters)
+    }
+}
+
+/// Add the `w:FragmentTransfer` header selecting `fragment`, if any
+fn with_fragment<'a>(
+    mut envelope: Tag<'a, SoapEnvelope<'a>, Envelope>,
+    fragment: Option<&'a str>,
+) -> Tag<'a, SoapEnvelope<'a>, Envelope> {
+    if let (Some(header), Some(fragment)) = (&mut envelope.value.header, fragment) {
+        header.value.fragment_transfer = Some(fragment_transfer(fragment));
+    }
+
+    envelope
+}
+
+/// The content of a `w:XmlFragment` returned in place of a whole instance
+fn parse_fragment<'a>(instance: Node<'a, 'a>) -> Result<XmlFragmentValue<'a>, ProtocolError> {
+    if !XmlFragment::matches_node(&instance) {
+        return Err(ProtocolError::XmlParsingError(format!(
+            "Expected a XmlFragment, found {}",
+            instance.tag_name().name()
+        )));
+    }
+
+    let fragment: Tag<'a, XmlFragmentValue<'a>, XmlFragment> =
+        Tag::from_node(instance).map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+
+    Ok(fragment.value)
+}
+
+/// The `wa:Action` header of a received envelope, if any
+pub(crate) fn response_action<'a>(envelope: Node<'a, 'a>) -> Option<&'a str> {
+    envelope
+        .children()
+        .find(Header::matches_node)?
+        .children()
+        .find(Action::matches_node)?
+        .text()
+        .map(str::trim)
+}
+
+/// The `s:Body` of a received envelope, or the fault it carries as an error
+pub(crate) fn response_body<'a>(
+    envelope: Node<'a, 'a>,
+    expected_action: WsAction,
+    language: &str,
+) -> Result<Node<'a, 'a>, ProtocolError> {
+    if let Some(fault) = SoapFault::find(envelope) {
+        let fault = fault.map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+        return Err(fault.into_error(language));
+    }
+
+    if let Some(action) = response_action(envelope)
+        && action != expected_action.as_str()
+    {
+        return Err(ProtocolError::UnexpectedAction {
+            expected: expected_action.as_str().to_owned(),
+            found: action.to_owned(),
+        });
+    }
+
+    envelope
+        .children()
+        .find(Body::matches_node)
+        .ok_or(ProtocolError::MissingSoapBody)
+}
+
+/// The response to a [`GetRequest`], the body holds the representation of the instance.
+///
+/// The instance stays a node of the response document, use [`GetResponse::to_owned_element`]
+/// to keep it after the response is dropped or [`GetResponse::deserialize`] for a typed struct.
+#[derive(Debug, Clone, Copy)]
+pub struct GetResponse<'a> {
+    instance: Node<'a, 'a>,
+}
+
+impl<'a> GetResponse<'a> {
+    /// Parse a `wxf:GetResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::GetResponse, language)?;
+
+        let instance = body
+            .children()
+            .find(|child| child.is_element())
+            .ok_or_else(|| {
+                ProtocolError::XmlParsingError("GetResponse body is empty".to_owned())
+            })?;
+
+        Ok(Self { instance })
+    }
+
+    pub fn instance(&self) -> Node<'a, 'a> {
+        self.instance
+    }
+
+    pub fn to_owned_element(&self) -> OwnedElement {
+        OwnedElement::from_node(self.instance)
+    }
+
+    /// Deserialize the instance, e.g. into a `Tag` of a typed value
+    pub fn deserialize<T: XmlDeserialize<'a>>(&self) -> Result<T, ProtocolError> {
+        T::from_node(self.instance).map_err(|e| ProtocolError::XmlParsingError(e.to_string()))
+    }
+
+    /// The property selected by the fragment of the request, as text for a `text()` expression
+    pub fn fragment(&self) -> Result<XmlFragmentValue<'a>, ProtocolError> {
+        parse_fragment(self.instance)
+    }
+}
+
+/// The response to a [`PutRequest`], servers may return the updated representation
+#[derive(Debug, Clone, Copy)]
+pub struct PutResponse<'a> {
+    instance: Option<Node<'a, 'a>>,
+}
+
+impl<'a> PutResponse<'a> {
+    /// Parse a `wxf:PutResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::PutResponse, language)?;
+
+        Ok(Self {
+            instance: body.children().find(|child| child.is_element()),
+        })
+    }
+
+    pub fn instance(&self) -> Option<Node<'a, 'a>> {
+        self.instance
+    }
+
+    /// The updated fragment, if the server returned it
+    pub fn fragment(&self) -> Option<Result<XmlFragmentValue<'a>, ProtocolError>> {
+        self.instance.map(parse_fragment)
+    }
+}
+
+/// The response to a [`CreateRequest`], the endpoint reference of the new resource.
+///
+/// Some resources return more than the `wxf:ResourceCreated` in the body, e.g. a WinRS shell
+/// also returns its `rsp:Shell`, the whole body is kept for them.
+#[derive(Debug, Clone)]
+pub struct CreateResponse<'a> {
+    pub resource_created: ResourceCreatedValue<'a>,
+    body: Node<'a, 'a>,
+}
+
+impl<'a> CreateResponse<'a> {
+    /// Parse a `wxf:CreateResponse` envelope, a fault is returned as an error with its reason
+    /// in `language`
+    pub fn from_node(envelope: Node<'a, 'a>, language: &str) -> Result<Self, ProtocolError> {
+        let body = response_body(envelope, WsAction::CreateResponse, language)?;
+
+        let resource_created = body
+            .children()
+            .find(ResourceCreated::matches_node)
+            .ok_or_else(|| {
+                ProtocolError::XmlParsingError(
+                    "No ResourceCreated found in CreateResponse".to_owned(),
+                )
+            })?;
+        let resource_created: Tag<'a, ResourceCreatedValue<'a>, ResourceCreated> =
+            Tag::from_node(resource_created)
+                .map_err(|e| ProtocolError::XmlParsingError(e.to_string()))?;
+
+        Ok(Self {
+            resource_created: resource_created.value,
+            body,
+        })
+    }
+
+    /// The reference parameters addressing the new resource, see [`DeleteRequest::to_endpoint`]
+    pub fn endpoint(&self) -> &ReferenceParametersValue<'a> {
+        &self.resource_created.reference_parameters.value
+    }
+
+    pub fn body(&self) -> Node<'a, 'a> {
+        self.body
+    }
+}
+
+/// The response to a [`DeleteRequest`], the body is empty
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteResponse;
+
+impl DeleteResponse {
+    pub fn from_node(envelope: Node<'_, '_>, language: &str) -> Result<Self, ProtocolError> {
+        response_body(envelope, WsAction::DeleteResponse, language)?;
+        Ok(Self)
+    }
+}
@@ -0,0 +1,35 @@
+//! Fixtures shared by the integration tests, each test crate uses its own part of them
+#![allow(dead_code)]
+
+use std::{
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use protocol_winrm::ws_management::WsMan;
+
+/// Poll `future` to completion, the futures of the tests never wait on anything
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// A [`WsMan`] addressing the listener of the local host
+pub fn ws_man() -> WsMan {
+    WsMan::builder()
+        .to("http://localhost:5985/wsman".to_string())
+        .build()
+}
+
+/// [`ws_man`] with a `MaxEnvelopeSize` of `max_envelope_size`
+pub fn ws_man_with_max_envelope_size(max_envelope_size: u32) -> WsMan {
+    WsMan::builder()
+        .to("http://localhost:5985/wsman".to_string())
+        .max_envelope_size(max_envelope_size)
+        .build()
+}
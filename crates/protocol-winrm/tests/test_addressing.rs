@@ -0,0 +1,41 @@
+use protocol_winrm::{
+    cores::{Empty, Tag, envelope::Envelope},
+    soap::{body::SoapBody, header::SoapHeaders},
+    ws_addressing::{ANONYMOUS_ADDRESS, AddressValue},
+};
+use xml::parser::XmlDeserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addressing_headers_round_trip() {
+        let envelope = Envelope::builder()
+            .to("http://localhost:5985/wsman")
+            .reply_to(AddressValue::anonymous())
+            .fault_to(AddressValue::new("http://collector.example.com/faults"))
+            .from(AddressValue::new("http://client.example.com/"))
+            .body(SoapBody::builder().identify(Tag::new(Empty)).build())
+            .build();
+
+        let xml = envelope.into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let parsed: Envelope<'_, SoapHeaders<'_>, SoapBody<'_>> =
+            Envelope::from_node(document.root_element()).expect("Failed to parse envelope");
+
+        let header = &parsed.header.value;
+        let reply_to = &header.reply_to.as_ref().unwrap().value;
+        assert!(reply_to.is_anonymous());
+        assert_eq!(reply_to.address(), ANONYMOUS_ADDRESS);
+
+        let fault_to = &header.fault_to.as_ref().unwrap().value;
+        assert!(!fault_to.is_anonymous());
+        assert_eq!(fault_to.address(), "http://collector.example.com/faults");
+
+        assert_eq!(
+            header.from.as_ref().unwrap().value.address(),
+            "http://client.example.com/"
+        );
+    }
+}
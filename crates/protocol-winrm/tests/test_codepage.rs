@@ -0,0 +1,53 @@
+use protocol_winrm::{
+    error::ProtocolError,
+    rsp::codepage::{self, CODEPAGE_UTF8, OutputDecoder},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_utf8_across_chunks() {
+        let output = "Répertoire de C:\\Users\\Zoë — 日本語\r\n".as_bytes();
+        let mut decoder = OutputDecoder::new(CODEPAGE_UTF8).unwrap();
+
+        // Split every character
+        let mut text = String::new();
+        for byte in output {
+            text.push_str(&decoder.decode(std::slice::from_ref(byte)));
+        }
+        text.push_str(&decoder.finish());
+        assert_eq!(text.as_bytes(), output);
+    }
+
+    #[test]
+    fn test_decode_incomplete_character() {
+        let mut decoder = OutputDecoder::new(CODEPAGE_UTF8).unwrap();
+        assert_eq!(decoder.decode(b"ok \xE6\x97"), "ok ");
+        assert_eq!(decoder.finish(), "\u{FFFD}");
+    }
+
+    #[test]
+    fn test_decode_codepages() {
+        assert_eq!(codepage::decode(1252, b"caf\xE9 \x80").unwrap(), "café €");
+        assert_eq!(
+            codepage::decode(866, b"\x8F\xE0\xA8\xA2\xA5\xE2").unwrap(),
+            "Привет"
+        );
+        assert_eq!(codepage::decode(932, b"\x93\xFA\x96\x7B").unwrap(), "日本");
+        assert_eq!(
+            codepage::decode(1200, &[0x48, 0x00, 0xE9, 0x00]).unwrap(),
+            "Hé"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_codepage() {
+        assert!(codepage::encoding(437).is_none());
+        assert!(matches!(
+            OutputDecoder::new(437),
+            Err(ProtocolError::UnsupportedCodepage(437))
+        ));
+    }
+}
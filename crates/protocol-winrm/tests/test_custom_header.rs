@@ -0,0 +1,89 @@
+use protocol_winrm::{
+    cores::{Tag, tag_name::*},
+    soap::{CustomHeaders, SoapEnvelope, SoapHeader, body::SoapBody, header::SoapHeaders},
+};
+use xml::{
+    XmlError,
+    builder::Element,
+    parser::{Node, XmlDeserialize},
+};
+
+#[derive(Debug, PartialEq)]
+struct TraceContext<'a> {
+    trace_id: &'a str,
+}
+
+impl<'a> SoapHeader<'a> for TraceContext<'a> {
+    const NAMESPACE: &'static str = "urn:example:trace";
+    const NAME: &'static str = "TraceContext";
+
+    fn to_header_element(&self) -> Element<'a> {
+        Element::new(Self::NAME)
+            .set_namespace(Self::NAMESPACE)
+            .add_namespace_declaration(Self::NAMESPACE, Some("t"))
+            .set_text(self.trace_id)
+    }
+
+    fn from_header_node(node: Node<'a, 'a>) -> Result<Self, XmlError> {
+        Ok(Self {
+            trace_id: node.text().unwrap_or_default().trim(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_with_custom_header_parses() {
+        let xml_content = r#"<s:Envelope
+            xmlns:s="http://www.w3.org/2003/05/soap-envelope"
+            xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing"
+            xmlns:trc="urn:example:trace">
+            <s:Header>
+                <a:To>http://localhost:5985/wsman</a:To>
+                <trc:TraceContext>abc-123</trc:TraceContext>
+            </s:Header>
+            <s:Body/>
+        </s:Envelope>"#;
+
+        let document = xml::parser::parse(xml_content).expect("Failed to parse XML content");
+        let envelope = SoapEnvelope::from_node(document.root_element())
+            .expect("Custom headers must not break envelope parsing");
+
+        let header = envelope.header.as_ref().expect("header should be parsed");
+        assert!(header.value.to.is_some());
+
+        let trace = envelope
+            .custom_headers
+            .get::<TraceContext>()
+            .expect("custom header should be kept")
+            .unwrap();
+        assert_eq!(
+            trace,
+            TraceContext {
+                trace_id: "abc-123"
+            }
+        );
+    }
+
+    #[test]
+    fn test_envelope_with_custom_header_builds() {
+        let envelope = SoapEnvelope::builder()
+            .header(SoapHeaders::builder().build())
+            .body(SoapBody::builder().build())
+            .custom_headers(CustomHeaders::new().with_header(&TraceContext { trace_id: "xyz" }))
+            .build();
+
+        let xml = Tag::<SoapEnvelope, Envelope>::new(envelope)
+            .with_declaration(protocol_winrm::cores::namespace::Namespace::SoapEnvelope2003)
+            .into_element()
+            .to_string();
+
+        assert!(
+            xml.contains(r#"<t:TraceContext xmlns:t="urn:example:trace">xyz</t:TraceContext>"#)
+        );
+        assert!(xml.find("<s:Header>").unwrap() < xml.find("<t:TraceContext").unwrap());
+    }
+}
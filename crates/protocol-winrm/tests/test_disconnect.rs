@@ -0,0 +1,204 @@
+mod common;
+
+use std::{cell::RefCell, fs, time::Duration};
+
+use common::{block_on, ws_man};
+use protocol_winrm::{
+    rsp::{
+        disconnect::{
+            ConnectRequest, ConnectResponse, DisconnectRequest, DisconnectResponse,
+            ReconnectRequest, ReconnectResponse,
+        },
+        shell::CreateShellRequest,
+    },
+    ws_management::ResourceUri,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHELL_ID: &str = "07936B27-7752-4325-8B0D-E7A1E9448320";
+    const COMMAND_ID: &str = "9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C";
+
+    fn texts(xml: &str) -> Vec<(String, String)> {
+        let document = xml::parser::parse(xml).expect("Built envelope must be valid XML");
+        document
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name().to_owned(), node.text()?.to_owned())))
+            .collect()
+    }
+
+    fn has(texts: &[(String, String)], name: &str, text: &str) -> bool {
+        texts.contains(&(name.to_owned(), text.to_owned()))
+    }
+
+    fn response(action: &str, body: &str) -> String {
+        format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+            <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/{action}</a:Action></s:Header>
+            <s:Body>{body}</s:Body>
+        </s:Envelope>"#
+        )
+    }
+
+    #[test]
+    fn test_disconnect_request() {
+        let ws_man = ws_man();
+        let request = DisconnectRequest::builder()
+            .shell_id(SHELL_ID)
+            .resource_uri(ResourceUri::POWERSHELL)
+            .idle_time_out(Duration::from_secs(240))
+            .build();
+        let texts = texts(&ws_man.disconnect(request).into_element().to_string());
+
+        assert!(has(
+            &texts,
+            "Action",
+            "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Disconnect"
+        ));
+        assert!(has(&texts, "Selector", SHELL_ID));
+        assert!(has(&texts, "IdleTimeOut", "PT240.000S"));
+    }
+
+    #[test]
+    fn test_reconnect_request() {
+        let ws_man = ws_man();
+        let request = ReconnectRequest::builder().shell_id(SHELL_ID).build();
+        let xml = ws_man.reconnect(request).into_element().to_string();
+        let texts = texts(&xml);
+
+        assert!(has(
+            &texts,
+            "Action",
+            "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Reconnect"
+        ));
+        assert!(has(&texts, "Selector", SHELL_ID));
+
+        let document = xml::parser::parse(&xml).unwrap();
+        let reconnect = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Reconnect")
+            .unwrap();
+        assert_eq!(
+            reconnect
+                .children()
+                .filter(|node| node.is_element())
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_connect_request() {
+        let ws_man = ws_man();
+        let request = ConnectRequest::builder()
+            .shell_id(SHELL_ID)
+            .resource_uri(ResourceUri::POWERSHELL)
+            .command_id(uuid::Uuid::parse_str(COMMAND_ID).unwrap())
+            .connect_xml("AAAAAAAAAAEAAAAAAAAAAAMAAADeAAAAAAQQAA==")
+            .build();
+        let xml = ws_man.connect(request).into_element().to_string();
+        let texts = texts(&xml);
+
+        assert!(has(
+            &texts,
+            "Action",
+            "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Connect"
+        ));
+        assert!(has(
+            &texts,
+            "connectXml",
+            "AAAAAAAAAAEAAAAAAAAAAAMAAADeAAAAAAQQAA=="
+        ));
+
+        let document = xml::parser::parse(&xml).unwrap();
+        let connect = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Connect")
+            .unwrap();
+        assert_eq!(connect.attribute("CommandId"), Some(COMMAND_ID));
+        let connect_xml = connect.first_element_child().unwrap();
+        assert_eq!(
+            connect_xml.tag_name().namespace(),
+            Some("http://schemas.microsoft.com/powershell")
+        );
+    }
+
+    #[test]
+    fn test_responses() {
+        let xml = response("DisconnectResponse", "<rsp:DisconnectResponse/>");
+        let document = xml::parser::parse(&xml).unwrap();
+        assert!(DisconnectResponse::from_node(document.root_element(), "en-US").is_ok());
+        assert!(ReconnectResponse::from_node(document.root_element(), "en-US").is_err());
+
+        let xml = response("ReconnectResponse", "<rsp:ReconnectResponse/>");
+        let document = xml::parser::parse(&xml).unwrap();
+        assert!(ReconnectResponse::from_node(document.root_element(), "en-US").is_ok());
+
+        let xml = response(
+            "ConnectResponse",
+            r#"<rsp:ConnectResponse><pwsh:connectResponseXml xmlns:pwsh="http://schemas.microsoft.com/powershell">AAAAAAAAAAIAAAAAAAAAAAMAAAA=</pwsh:connectResponseXml></rsp:ConnectResponse>"#,
+        );
+        let document = xml::parser::parse(&xml).unwrap();
+        let connected = ConnectResponse::from_node(document.root_element(), "en-US").unwrap();
+        assert_eq!(
+            connected.connect_response_xml(),
+            Some("AAAAAAAAAAIAAAAAAAAAAAMAAAA=")
+        );
+    }
+
+    #[test]
+    fn test_disconnect_and_reconnect_shell() {
+        let ws_man = ws_man();
+        let created = fs::read_to_string("tests/resources/resource_created.xml").unwrap();
+        let sent = RefCell::new(Vec::new());
+        let exchange = |request: String| {
+            sent.borrow_mut().push(request);
+            let response = match sent.borrow().len() {
+                1 => created.clone(),
+                2 => response("DisconnectResponse", "<rsp:DisconnectResponse/>"),
+                3 => response("ReconnectResponse", "<rsp:ReconnectResponse/>"),
+                _ => unreachable!("No request after the Reconnect"),
+            };
+            async move { Ok(response) }
+        };
+
+        let dropped = RefCell::new(false);
+        let shell = block_on(
+            ws_man.open_shell(
+                CreateShellRequest::builder()
+                    .resource_uri(ResourceUri::POWERSHELL)
+                    .build(),
+                "en-US",
+                &exchange,
+            ),
+        )
+        .unwrap()
+        .delete_on_drop(|_| *dropped.borrow_mut() = true);
+
+        let mut detached = block_on(shell.disconnect(Some(Duration::from_secs(240)))).unwrap();
+        assert!(!*dropped.borrow(), "A disconnected shell is not deleted");
+        assert_eq!(detached.shell_id, SHELL_ID);
+        assert_eq!(detached.resource_uri, ResourceUri::POWERSHELL);
+
+        // Persisted by another process with the commands it runs
+        detached
+            .command_ids
+            .push(uuid::Uuid::parse_str(COMMAND_ID).unwrap());
+        let persisted = detached.clone();
+
+        let shell = block_on(ws_man.reconnect_shell(persisted, "en-US", &exchange)).unwrap();
+        assert_eq!(shell.shell_id(), SHELL_ID);
+        drop(shell);
+
+        let sent = sent.into_inner();
+        assert_eq!(sent.len(), 3);
+        assert!(has(
+            &texts(&sent[2]),
+            "Action",
+            "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Reconnect"
+        ));
+        assert!(has(&texts(&sent[2]), "Selector", SHELL_ID));
+    }
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::{cell::RefCell, time::Duration};
+
+use common::block_on;
+use protocol_winrm::{
+    cores::OwnedElement,
+    rsp::enumerate::ShellInfo,
+    ws_management::{ResourceUri, WsMan},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CMD_SHELL: &str = r#"<rsp:Shell xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell"><rsp:ShellId>0A1B2C3D-0000-4000-8000-000000000001</rsp:ShellId><rsp:ResourceUri>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/cmd</rsp:ResourceUri><rsp:Owner>CONTOSO\Administrator</rsp:Owner><rsp:ClientIP>10.0.0.5</rsp:ClientIP><rsp:ProcessId>4312</rsp:ProcessId><rsp:IdleTimeOut>PT7200.000S</rsp:IdleTimeOut><rsp:InputStreams>stdin</rsp:InputStreams><rsp:OutputStreams>stdout stderr</rsp:OutputStreams><rsp:MaxIdleTimeOut>PT2147483.647S</rsp:MaxIdleTimeOut><rsp:Locale>en-US</rsp:Locale><rsp:DataLocale>en-US</rsp:DataLocale><rsp:CompressionMode>NoCompression</rsp:CompressionMode><rsp:ProfileLoaded>Yes</rsp:ProfileLoaded><rsp:Encoding>UTF8</rsp:Encoding><rsp:BufferMode>Block</rsp:BufferMode><rsp:State>Connected</rsp:State><rsp:ShellRunTime>P0DT0H12M5S</rsp:ShellRunTime><rsp:ShellInactivity>P0DT0H1M30S</rsp:ShellInactivity></rsp:Shell>"#;
+
+    const POWERSHELL_SHELL: &str = r#"<rsp:Shell xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell"><rsp:ShellId>07936B27-7752-4325-8B0D-E7A1E9448320</rsp:ShellId><rsp:Name>Runspace1</rsp:Name><rsp:ResourceUri>http://schemas.microsoft.com/powershell/Microsoft.PowerShell</rsp:ResourceUri><rsp:Owner>CONTOSO\svc-backup</rsp:Owner><rsp:ClientIP>10.0.0.9</rsp:ClientIP><rsp:State>Disconnected</rsp:State></rsp:Shell>"#;
+
+    fn enumerate_response(shell: &str) -> String {
+        format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+            <s:Header><a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/EnumerateResponse</a:Action></s:Header>
+            <s:Body><n:EnumerateResponse><n:EnumerationContext>uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4</n:EnumerationContext><w:Items>{shell}</w:Items></n:EnumerateResponse></s:Body>
+        </s:Envelope>"#
+        )
+    }
+
+    fn pull_response(shell: &str) -> String {
+        format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration">
+            <s:Header><a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/PullResponse</a:Action></s:Header>
+            <s:Body><n:PullResponse><n:Items>{shell}</n:Items><n:EndOfSequence/></n:PullResponse></s:Body>
+        </s:Envelope>"#
+        )
+    }
+
+    fn element(xml: &str) -> OwnedElement {
+        let document = xml::parser::parse(xml).unwrap();
+        OwnedElement::from_node(document.root_element())
+    }
+
+    #[test]
+    fn test_shell_info() {
+        let shell = ShellInfo::from_element(&element(CMD_SHELL)).unwrap();
+
+        assert_eq!(shell.shell_id, "0A1B2C3D-0000-4000-8000-000000000001");
+        assert_eq!(
+            shell.resource_uri.as_deref(),
+            Some(ResourceUri::WINDOWS_SHELL_CMD)
+        );
+        assert_eq!(shell.owner.as_deref(), Some(r"CONTOSO\Administrator"));
+        assert_eq!(shell.client_ip.as_deref(), Some("10.0.0.5"));
+        assert_eq!(shell.process_id, Some(4312));
+        assert_eq!(shell.state.as_deref(), Some("Connected"));
+        assert_eq!(shell.idle_time_out, Some(Duration::from_secs(7200)));
+        assert_eq!(shell.shell_run_time, Some(Duration::from_secs(725)));
+        assert_eq!(shell.shell_inactivity, Some(Duration::from_secs(90)));
+
+        let without_id = CMD_SHELL.replace(
+            "<rsp:ShellId>0A1B2C3D-0000-4000-8000-000000000001</rsp:ShellId>",
+            "",
+        );
+        assert!(ShellInfo::from_element(&element(&without_id)).is_none());
+    }
+
+    #[test]
+    fn test_enumerate_shells() {
+        let ws_man = WsMan::builder()
+            .to("http://localhost:5985/wsman".to_string())
+            .build();
+        let sent = RefCell::new(Vec::new());
+        let exchange = |request: String| {
+            sent.borrow_mut().push(request);
+            let response = match sent.borrow().len() {
+                1 => enumerate_response(CMD_SHELL),
+                _ => pull_response(POWERSHELL_SHELL),
+            };
+            async move { Ok(response) }
+        };
+
+        let shells = block_on(ws_man.enumerate_shells("en-US", exchange)).unwrap();
+        assert_eq!(shells.len(), 2);
+        assert_eq!(shells[1].name.as_deref(), Some("Runspace1"));
+        assert_eq!(shells[1].state.as_deref(), Some("Disconnected"));
+
+        let sent = sent.into_inner();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].contains(&format!(">{}<", ResourceUri::WINDOWS_SHELL)));
+
+        // Attach to the disconnected PowerShell shell, delete the cmd one
+        let disconnected = shells[1].disconnected();
+        assert_eq!(
+            disconnected.shell_id,
+            "07936B27-7752-4325-8B0D-E7A1E9448320"
+        );
+        assert_eq!(disconnected.resource_uri, ResourceUri::POWERSHELL);
+
+        let delete = ws_man
+            .delete_shell(shells[0].delete_request())
+            .into_element()
+            .to_string();
+        assert!(delete.contains("http://schemas.xmlsoap.org/ws/2004/09/transfer/Delete"));
+        assert!(delete.contains("0A1B2C3D-0000-4000-8000-000000000001"));
+        assert!(delete.contains(&format!(">{}<", ResourceUri::WINDOWS_SHELL_CMD)));
+    }
+}
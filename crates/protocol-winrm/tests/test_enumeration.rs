@@ -0,0 +1,683 @@
+mod common;
+
+use std::cell::RefCell;
+
+use common::{block_on, ws_man};
+use futures_util::StreamExt;
+use protocol_winrm::{
+    error::ProtocolError,
+    ws_management::{
+        ASSOCIATION_DIALECT, AssociationFilter, DEFAULT_MAX_ELEMENTS, EnumerateRequest,
+        EnumerateResponse, EnumerationEnd, EnumerationEndCode, EnumerationMode, Filter,
+        PullRequest, PullResponse, SelectorSetValue, WQL_DIALECT, XPATH_DIALECT,
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICE_URI: &str =
+        "http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Service";
+
+    const ENUMERATE_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Service">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/EnumerateResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <n:EnumerateResponse>
+                <n:EnumerationContext>uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4</n:EnumerationContext>
+                <w:Items>
+                    <p:Win32_Service><p:Name>WinRM</p:Name></p:Win32_Service>
+                    <p:Win32_Service><p:Name>Spooler</p:Name></p:Win32_Service>
+                </w:Items>
+            </n:EnumerateResponse>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_enumerate_request() {
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .optimize_enumeration(true)
+            .max_elements(32000)
+            .build();
+        let xml = ws_man.enumerate(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let enumerate = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Enumerate")
+            .expect("The body carries the Enumerate");
+        assert_eq!(
+            enumerate.tag_name().namespace(),
+            Some("http://schemas.xmlsoap.org/ws/2004/09/enumeration")
+        );
+
+        let children: Vec<_> = enumerate
+            .children()
+            .filter(|child| child.is_element())
+            .map(|child| (child.tag_name().name(), child.text()))
+            .collect();
+        assert_eq!(
+            children,
+            [
+                ("OptimizeEnumeration", None),
+                ("MaxElements", Some("32000"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enumerate_request_defaults() {
+        let enumerate_children = |request| {
+            let xml = ws_man().enumerate(request).into_element().to_string();
+            let document = xml::parser::parse(&xml).unwrap();
+            let enumerate = document
+                .descendants()
+                .find(|node| node.tag_name().name() == "Enumerate")
+                .unwrap();
+            enumerate
+                .children()
+                .filter(|child| child.is_element())
+                .map(|child| {
+                    (
+                        child.tag_name().name().to_owned(),
+                        child.text().map(str::to_owned),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .build();
+        assert_eq!(
+            enumerate_children(request),
+            [
+                ("OptimizeEnumeration".to_owned(), None),
+                (
+                    "MaxElements".to_owned(),
+                    Some(DEFAULT_MAX_ELEMENTS.to_string())
+                )
+            ]
+        );
+
+        // MaxElements only applies to an optimized enumeration
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .optimize_enumeration(false)
+            .max_elements(10)
+            .build();
+        assert!(enumerate_children(request).is_empty());
+    }
+
+    #[test]
+    fn test_enumerate_request_with_wql_filter() {
+        let query = "SELECT * FROM Win32_Service WHERE State='Running' AND ProcessId < 1000";
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri("http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/*")
+            .filter(Filter::Wql(query))
+            .build();
+        let xml = ws_man.enumerate(request).into_element().to_string();
+
+        assert!(xml.contains("State=&apos;Running&apos; AND ProcessId &lt; 1000"));
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let filter = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Filter")
+            .expect("The Enumerate carries the Filter");
+        assert_eq!(
+            filter.tag_name().namespace(),
+            Some("http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd")
+        );
+        assert_eq!(filter.attribute("Dialect"), Some(WQL_DIALECT));
+        assert_eq!(filter.text(), Some(query));
+    }
+
+    #[test]
+    fn test_enumerate_request_with_xpath_filter() {
+        let expression = "/p:Win32_Service[p:State='Running']";
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .filter(Filter::xpath(expression, [("p", SERVICE_URI)]))
+            .build();
+        let xml = ws_man.enumerate(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let filter = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Filter")
+            .expect("The Enumerate carries the Filter");
+        assert_eq!(filter.attribute("Dialect"), Some(XPATH_DIALECT));
+        assert_eq!(filter.text(), Some(expression));
+        assert_eq!(filter.lookup_namespace_uri(Some("p")), Some(SERVICE_URI));
+    }
+
+    #[test]
+    fn test_enumerate_request_with_associated_instances_filter() {
+        let filter = AssociationFilter::builder()
+            .resource_uri(
+                "http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_LogicalDisk",
+            )
+            .selector_set(SelectorSetValue::new().add_selector("DeviceID", "C:"))
+            .association_class_name("Win32_LogicalDiskToPartition")
+            .result_class_name("Win32_DiskPartition")
+            .build();
+
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri("http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/*")
+            .filter(Filter::AssociatedInstances(filter))
+            .build();
+        let xml = ws_man.enumerate(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let filter = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Filter")
+            .expect("The Enumerate carries the Filter");
+        assert_eq!(filter.attribute("Dialect"), Some(ASSOCIATION_DIALECT));
+
+        let associated = filter.first_element_child().unwrap();
+        assert_eq!(associated.tag_name().name(), "AssociatedInstances");
+        assert_eq!(
+            associated.tag_name().namespace(),
+            Some("http://schemas.dmtf.org/wbem/wsman/1/cimbinding.xsd")
+        );
+
+        let children: Vec<_> = associated
+            .children()
+            .filter(|child| child.is_element())
+            .map(|child| child.tag_name().name())
+            .collect();
+        assert_eq!(
+            children,
+            ["Object", "AssociationClassName", "ResultClassName"]
+        );
+
+        let texts: Vec<_> = associated
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&("Selector", "C:")));
+        assert!(texts.contains(&("ResultClassName", "Win32_DiskPartition")));
+    }
+
+    #[test]
+    fn test_enumerate_response_with_items() {
+        let document = xml::parser::parse(ENUMERATE_RESPONSE).unwrap();
+        let response = EnumerateResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(
+            response.context(),
+            Some("uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4")
+        );
+        assert!(!response.is_end_of_sequence());
+
+        let items = response.owned_items();
+        assert_eq!(items.len(), 2);
+        assert!(items[0].matches(Some(SERVICE_URI), "Win32_Service"));
+        assert_eq!(items[1].children[0].text.as_deref(), Some("Spooler"));
+    }
+
+    #[test]
+    fn test_enumerate_response_end_of_sequence() {
+        let xml = ENUMERATE_RESPONSE.replace(
+            "<n:EnumerationContext>uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4</n:EnumerationContext>",
+            "<n:EnumerationContext/>",
+        );
+        let xml = xml.replace("</w:Items>", "</w:Items><w:EndOfSequence/>");
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = EnumerateResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(response.context(), None);
+        assert!(response.is_end_of_sequence());
+        assert_eq!(response.items().len(), 2);
+    }
+
+    const PULL_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration" xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Service">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/PullResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <n:PullResponse>
+                <n:EnumerationContext>uuid:0B8F2C44-1D7A-4E55-9C61-5A3E2D7B9F10</n:EnumerationContext>
+                <n:Items>
+                    <p:Win32_Service><p:Name>W32Time</p:Name></p:Win32_Service>
+                </n:Items>
+            </n:PullResponse>
+        </s:Body>
+    </s:Envelope>"#;
+
+    // The last page of the enumeration, the server drops the context with the EndOfSequence
+    const LAST_PULL_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration" xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Service">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/PullResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <n:PullResponse>
+                <n:Items>
+                    <p:Win32_Service><p:Name>Winmgmt</p:Name></p:Win32_Service>
+                </n:Items>
+                <n:EndOfSequence/>
+            </n:PullResponse>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_pull_request() {
+        let ws_man = ws_man();
+        let request = PullRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .context("uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4")
+            .max_elements(20)
+            .max_characters(65536)
+            .build();
+        let xml = ws_man.pull(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        assert!(
+            document.descendants().any(|node| node.text()
+                == Some("http://schemas.xmlsoap.org/ws/2004/09/enumeration/Pull"))
+        );
+
+        let pull = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Pull")
+            .expect("The body carries the Pull");
+        let children: Vec<_> = pull
+            .children()
+            .filter(|child| child.is_element())
+            .map(|child| {
+                (
+                    child.tag_name().namespace(),
+                    child.tag_name().name(),
+                    child.text(),
+                )
+            })
+            .collect();
+        let enumeration = Some("http://schemas.xmlsoap.org/ws/2004/09/enumeration");
+        assert_eq!(
+            children,
+            [
+                (
+                    enumeration,
+                    "EnumerationContext",
+                    Some("uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4")
+                ),
+                (enumeration, "MaxElements", Some("20")),
+                (enumeration, "MaxCharacters", Some("65536"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pull_response() {
+        let document = xml::parser::parse(PULL_RESPONSE).unwrap();
+        let response = PullResponse::from_node(document.root_element(), "en-US").unwrap();
+        assert_eq!(
+            response.context(),
+            Some("uuid:0B8F2C44-1D7A-4E55-9C61-5A3E2D7B9F10")
+        );
+        assert!(!response.is_end_of_sequence());
+        assert_eq!(
+            response.owned_items()[0].children[0].text.as_deref(),
+            Some("W32Time")
+        );
+
+        let document = xml::parser::parse(LAST_PULL_RESPONSE).unwrap();
+        let response = PullResponse::from_node(document.root_element(), "en-US").unwrap();
+        assert_eq!(response.context(), None);
+        assert!(response.is_end_of_sequence());
+        assert_eq!(response.items().len(), 1);
+    }
+
+    #[test]
+    fn test_enumerate_items_pulls_until_end_of_sequence() {
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .optimize_enumeration(true)
+            .build();
+
+        let sent = RefCell::new(Vec::new());
+        let responses = RefCell::new(vec![LAST_PULL_RESPONSE, PULL_RESPONSE, ENUMERATE_RESPONSE]);
+        let items = ws_man.enumerate_items(request, "en-US", |request| {
+            sent.borrow_mut().push(request);
+            let response = responses.borrow_mut().pop();
+            async move {
+                response
+                    .map(str::to_owned)
+                    .ok_or(ProtocolError::MissingSoapEnvelope)
+            }
+        });
+
+        let names: Vec<_> = block_on(items.collect::<Vec<_>>())
+            .into_iter()
+            .map(|item| item.unwrap().children[0].text.clone().unwrap())
+            .collect();
+        assert_eq!(names, ["WinRM", "Spooler", "W32Time", "Winmgmt"]);
+
+        // Each Pull carries the context of the previous response
+        let sent = sent.into_inner();
+        assert_eq!(sent.len(), 3);
+        assert!(sent[1].contains("uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4"));
+        assert!(sent[2].contains("uuid:0B8F2C44-1D7A-4E55-9C61-5A3E2D7B9F10"));
+    }
+
+    #[test]
+    fn test_enumerate_items_yields_fault() {
+        let fault = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Receiver</s:Value><s:Subcode><s:Value>w:TimedOut</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The operation timed out.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .build();
+        let responses = RefCell::new(vec![fault, ENUMERATE_RESPONSE]);
+        let items = ws_man.enumerate_items(request, "en-US", |_| {
+            let response = responses.borrow_mut().pop();
+            async move {
+                response
+                    .map(str::to_owned)
+                    .ok_or(ProtocolError::MissingSoapEnvelope)
+            }
+        });
+
+        let results = block_on(items.collect::<Vec<_>>());
+        assert_eq!(results.len(), 3);
+        assert!(results[..2].iter().all(Result::is_ok));
+        assert!(matches!(results[2], Err(ProtocolError::WsmanFault { .. })));
+    }
+
+    const RELEASE_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/ReleaseResponse</a:Action>
+        </s:Header>
+        <s:Body/>
+    </s:Envelope>"#;
+
+    const ENUMERATION_END: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/EnumerationEnd</a:Action>
+        </s:Header>
+        <s:Body>
+            <n:EnumerationEnd>
+                <n:EnumerationContext>uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4</n:EnumerationContext>
+                <n:Code>http://schemas.xmlsoap.org/ws/2004/09/enumeration/SourceShuttingDown</n:Code>
+                <n:Reason xml:lang="en-US">The service is stopping.</n:Reason>
+            </n:EnumerationEnd>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_dropped_stream_releases_context() {
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .build();
+        let released = RefCell::new(None);
+
+        {
+            let mut items = ws_man
+                .enumerate_items(request, "en-US", |_| async {
+                    Ok(ENUMERATE_RESPONSE.to_owned())
+                })
+                .release_on_drop(|release| *released.borrow_mut() = Some(release));
+            assert!(block_on(items.next()).unwrap().is_ok());
+        }
+
+        let release = released
+            .into_inner()
+            .expect("The stream is dropped before its end");
+        let document = xml::parser::parse(&release).unwrap();
+        assert!(
+            document.descendants().any(|node| node.text()
+                == Some("http://schemas.xmlsoap.org/ws/2004/09/enumeration/Release"))
+        );
+        let context = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "EnumerationContext")
+            .unwrap();
+        assert_eq!(
+            context.text(),
+            Some("uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4")
+        );
+    }
+
+    #[test]
+    fn test_finished_stream_is_not_released() {
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .build();
+        let released = RefCell::new(false);
+        let responses = RefCell::new(vec![LAST_PULL_RESPONSE, ENUMERATE_RESPONSE]);
+
+        let items = ws_man
+            .enumerate_items(request, "en-US", |_| {
+                let response = responses.borrow_mut().pop();
+                async move {
+                    response
+                        .map(str::to_owned)
+                        .ok_or(ProtocolError::MissingSoapEnvelope)
+                }
+            })
+            .release_on_drop(|_| *released.borrow_mut() = true);
+        assert_eq!(block_on(items.collect::<Vec<_>>()).len(), 3);
+
+        assert!(!released.into_inner());
+    }
+
+    #[test]
+    fn test_cancel_sends_release() {
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .build();
+        let sent = RefCell::new(Vec::new());
+        let responses = RefCell::new(vec![RELEASE_RESPONSE, ENUMERATE_RESPONSE]);
+
+        let mut items = ws_man.enumerate_items(request, "en-US", |request| {
+            sent.borrow_mut().push(request);
+            let response = responses.borrow_mut().pop();
+            async move {
+                response
+                    .map(str::to_owned)
+                    .ok_or(ProtocolError::MissingSoapEnvelope)
+            }
+        });
+        assert!(block_on(items.next()).unwrap().is_ok());
+        block_on(items.cancel()).unwrap();
+
+        let sent = sent.into_inner();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[1].contains("http://schemas.xmlsoap.org/ws/2004/09/enumeration/Release"));
+    }
+
+    #[test]
+    fn test_enumeration_end() {
+        let document = xml::parser::parse(ENUMERATION_END).unwrap();
+        let end = EnumerationEnd::find(document.root_element()).unwrap();
+        assert_eq!(
+            end.context(),
+            Some("uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4")
+        );
+        assert_eq!(end.code(), &EnumerationEndCode::SourceShuttingDown);
+        assert_eq!(end.reason(), Some("The service is stopping."));
+
+        // The items received before the end are kept and the context is not released
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .build();
+        let released = RefCell::new(false);
+        let responses = RefCell::new(vec![ENUMERATION_END, ENUMERATE_RESPONSE]);
+
+        let items = ws_man
+            .enumerate_items(request, "en-US", |_| {
+                let response = responses.borrow_mut().pop();
+                async move {
+                    response
+                        .map(str::to_owned)
+                        .ok_or(ProtocolError::MissingSoapEnvelope)
+                }
+            })
+            .release_on_drop(|_| *released.borrow_mut() = true);
+        let results = block_on(items.collect::<Vec<_>>());
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(Result::is_ok));
+
+        assert!(!released.into_inner());
+    }
+
+    #[test]
+    fn test_enumerate_items_without_granted_optimization() {
+        // The server ignored OptimizeEnumeration, every item comes from the Pulls
+        let start = r#"<w:Items>
+                    <p:Win32_Service><p:Name>WinRM</p:Name></p:Win32_Service>
+                    <p:Win32_Service><p:Name>Spooler</p:Name></p:Win32_Service>
+                </w:Items>"#;
+        assert!(ENUMERATE_RESPONSE.contains(start));
+        let not_optimized = ENUMERATE_RESPONSE.replace(start, "");
+
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .max_elements(50)
+            .max_characters(65536)
+            .build();
+        let sent = RefCell::new(Vec::new());
+        let responses = RefCell::new(vec![LAST_PULL_RESPONSE, not_optimized.as_str()]);
+        let items = ws_man.enumerate_items(request, "en-US", |request| {
+            sent.borrow_mut().push(request);
+            let response = responses.borrow_mut().pop();
+            async move {
+                response
+                    .map(str::to_owned)
+                    .ok_or(ProtocolError::MissingSoapEnvelope)
+            }
+        });
+
+        let results = block_on(items.collect::<Vec<_>>());
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].as_ref().unwrap().children[0].text.as_deref(),
+            Some("Winmgmt")
+        );
+
+        let sent = sent.into_inner();
+        let document = xml::parser::parse(&sent[1]).unwrap();
+        let texts: Vec<_> = document
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&("MaxElements", "50")));
+        assert!(texts.contains(&("MaxCharacters", "65536")));
+    }
+    const OBJECT_AND_EPR_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Service">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/EnumerateResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <n:EnumerateResponse>
+                <w:Items>
+                    <w:Item>
+                        <p:Win32_Service><p:Name>WinRM</p:Name></p:Win32_Service>
+                        <a:EndpointReference>
+                            <a:Address>http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous</a:Address>
+                            <a:ReferenceParameters>
+                                <w:ResourceURI>http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Service</w:ResourceURI>
+                                <w:SelectorSet><w:Selector Name="Name">WinRM</w:Selector></w:SelectorSet>
+                            </a:ReferenceParameters>
+                        </a:EndpointReference>
+                    </w:Item>
+                </w:Items>
+                <w:EndOfSequence/>
+            </n:EnumerateResponse>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_enumerate_object_and_epr() {
+        let ws_man = ws_man();
+        let request = EnumerateRequest::builder()
+            .resource_uri(SERVICE_URI)
+            .mode(EnumerationMode::EnumerateObjectAndEPR)
+            .build();
+        let xml = ws_man.enumerate(request).into_element().to_string();
+        let document = xml::parser::parse(&xml).unwrap();
+        let mode = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "EnumerationMode")
+            .expect("The Enumerate carries the mode");
+        assert_eq!(mode.text(), Some("EnumerateObjectAndEPR"));
+        assert_eq!(
+            mode.next_sibling_element()
+                .map(|node| node.tag_name().name()),
+            Some("OptimizeEnumeration")
+        );
+
+        let document = xml::parser::parse(OBJECT_AND_EPR_RESPONSE).unwrap();
+        let response = EnumerateResponse::from_node(document.root_element(), "en-US").unwrap();
+        let items = response.enumerated_items();
+        assert_eq!(items.len(), 1);
+
+        let object = items[0].object.as_ref().unwrap();
+        assert!(object.matches(Some(SERVICE_URI), "Win32_Service"));
+
+        let endpoint = items[0].endpoint.as_ref().unwrap();
+        assert_eq!(endpoint.resource_uri, SERVICE_URI);
+        assert_eq!(
+            endpoint.selector_set.get("Name").map(String::as_str),
+            Some("WinRM")
+        );
+
+        // The endpoint addresses the instance for the follow-up requests
+        let xml = ws_man
+            .get(endpoint.get_request())
+            .into_element()
+            .to_string();
+        let document = xml::parser::parse(&xml).unwrap();
+        assert!(
+            document
+                .descendants()
+                .any(|node| node.tag_name().name() == "Selector"
+                    && node.attribute("Name") == Some("Name")
+                    && node.text() == Some("WinRM"))
+        );
+    }
+
+    #[test]
+    fn test_enumerate_epr_only() {
+        let item_start = OBJECT_AND_EPR_RESPONSE.find("<w:Item>").unwrap();
+        let item_end = OBJECT_AND_EPR_RESPONSE.find("</w:Item>").unwrap() + "</w:Item>".len();
+        let epr_start = OBJECT_AND_EPR_RESPONSE
+            .find("<a:EndpointReference>")
+            .unwrap();
+        let epr_end = OBJECT_AND_EPR_RESPONSE
+            .find("</a:EndpointReference>")
+            .unwrap()
+            + "</a:EndpointReference>".len();
+        let xml = format!(
+            "{}{}{}",
+            &OBJECT_AND_EPR_RESPONSE[..item_start],
+            &OBJECT_AND_EPR_RESPONSE[epr_start..epr_end],
+            &OBJECT_AND_EPR_RESPONSE[item_end..]
+        );
+
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = EnumerateResponse::from_node(document.root_element(), "en-US").unwrap();
+        let items = response.enumerated_items();
+        assert_eq!(items.len(), 1);
+        assert!(items[0].object.is_none());
+        assert_eq!(
+            items[0].endpoint.as_ref().unwrap().address,
+            "http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous"
+        );
+    }
+}
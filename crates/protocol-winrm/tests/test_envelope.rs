@@ -0,0 +1,121 @@
+use protocol_winrm::{
+    cores::{Empty, Tag, envelope::Envelope},
+    soap::SoapEnvelope,
+    soap::{body::SoapBody, header::SoapHeaders},
+    ws_management::{OptionSetValue, SelectorSetValue, WsAction, WsMan},
+};
+use xml::parser::XmlDeserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_builder_round_trip() {
+        let message_id = uuid::Uuid::new_v4();
+        let body = SoapBody::builder().identify(Tag::new(Empty)).build();
+
+        let envelope = Envelope::builder()
+            .to("http://localhost:5985/wsman")
+            .action("http://schemas.xmlsoap.org/ws/2004/09/transfer/Get")
+            .resource_uri("http://schemas.microsoft.com/wbem/wsman/1/windows/shell/cmd")
+            .message_id(message_id)
+            .options(OptionSetValue::new().add_option("WINRS_NOPROFILE", "TRUE"))
+            .selectors(SelectorSetValue::new().add_selector("ShellId", "1234"))
+            .body(body)
+            .build();
+
+        let xml = envelope.into_element().to_string();
+        assert!(xml.starts_with("<s:Envelope"));
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let parsed: Envelope<'_, SoapHeaders<'_>, SoapBody<'_>> =
+            Envelope::from_node(document.root_element()).expect("Failed to parse envelope");
+
+        let header = &parsed.header.value;
+        assert_eq!(
+            header.to.as_ref().unwrap().value.as_ref(),
+            "http://localhost:5985/wsman"
+        );
+        assert_eq!(
+            header.action.as_ref().unwrap().value.as_ref(),
+            "http://schemas.xmlsoap.org/ws/2004/09/transfer/Get"
+        );
+        assert_eq!(
+            header.resource_uri.as_ref().unwrap().value.as_ref(),
+            "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/cmd"
+        );
+        assert_eq!(header.message_id.as_ref().unwrap().value.0, message_id);
+        assert_eq!(
+            header
+                .option_set
+                .as_ref()
+                .unwrap()
+                .value
+                .options
+                .get("WINRS_NOPROFILE")
+                .map(String::as_str),
+            Some("TRUE")
+        );
+        assert_eq!(
+            header
+                .selector_set
+                .as_ref()
+                .unwrap()
+                .value
+                .get("ShellId")
+                .map(String::as_str),
+            Some("1234")
+        );
+        assert!(parsed.body.value.identify.is_some());
+    }
+
+    #[test]
+    fn test_envelope_requires_body() {
+        let xml = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope">
+            <s:Header/>
+        </s:Envelope>"#;
+
+        let document = xml::parser::parse(xml).unwrap();
+        let parsed: Result<Envelope<'_, SoapHeaders<'_>, SoapBody<'_>>, _> =
+            Envelope::from_node(document.root_element());
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn test_session_id_round_trip() {
+        let session_id = uuid::Uuid::new_v4();
+        let wsman = WsMan::builder()
+            .to("http://localhost:5985/wsman".to_string())
+            .session_id(session_id)
+            .build();
+        assert_eq!(wsman.session_id(), session_id);
+
+        let xml = wsman
+            .invoke(WsAction::Get, None, SoapBody::builder().build(), None, None)
+            .into_element()
+            .to_string();
+        assert!(xml.contains(&format!("uuid:{session_id}")));
+
+        let document = xml::parser::parse(&xml).unwrap();
+        let parsed = SoapEnvelope::from_node(document.root_element()).unwrap();
+        assert_eq!(parsed.session_id(), Some(session_id));
+    }
+
+    #[test]
+    fn test_estimated_size() {
+        let envelope = Envelope::builder()
+            .to("http://localhost:5985/wsman")
+            .action("http://schemas.xmlsoap.org/ws/2004/09/transfer/Get")
+            .message_id(uuid::Uuid::new_v4())
+            .options(OptionSetValue::new().add_option("WINRS_NOPROFILE", "TRUE"))
+            .body(SoapBody::builder().identify(Tag::new(Empty)).build())
+            .build();
+
+        let estimated = envelope.estimated_size();
+        assert_eq!(estimated, envelope.clone().into_element().to_string().len());
+
+        assert_eq!(envelope.base64_capacity(estimated), 0);
+        assert_eq!(envelope.base64_capacity(estimated + 400), 300);
+    }
+}
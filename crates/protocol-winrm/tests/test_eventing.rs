@@ -0,0 +1,714 @@
+mod common;
+
+use std::{cell::RefCell, time::Duration};
+
+use common::{block_on, ws_man};
+use futures_util::{StreamExt, stream};
+use protocol_winrm::{
+    cores::OwnedElement,
+    error::ProtocolError,
+    ws_management::{
+        Bookmark, DeliveryMode, EVENT_QUERY_DIALECT, EventBatching, EventBookmark, Filter,
+        HeartbeatWatch, PushedMessage, RenewRequest, RenewResponse, ResourceUri, SubscribeRequest,
+        SubscribeResponse, Subscription, SubscriptionEvent, UnsubscribeRequest,
+        UnsubscribeResponse,
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUBSCRIBE_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:e="http://schemas.xmlsoap.org/ws/2004/08/eventing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/08/eventing/SubscribeResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <e:SubscribeResponse>
+                <e:SubscriptionManager>
+                    <a:Address>http://localhost:5985/wsman</a:Address>
+                    <a:ReferenceParameters>
+                        <w:ResourceURI>http://schemas.microsoft.com/wbem/wsman/1/windows/EventLog</w:ResourceURI>
+                        <e:Identifier>uuid:5C2E7F6A-3B1D-4C8E-9F10-2A3B4C5D6E7F</e:Identifier>
+                    </a:ReferenceParameters>
+                </e:SubscriptionManager>
+                <e:Expires>PT3600.000S</e:Expires>
+                <n:EnumerationContext>uuid:0E5B3A1C-7D2F-4E6A-8B9C-1D2E3F4A5B6C</n:EnumerationContext>
+            </e:SubscribeResponse>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_subscribe_request() {
+        let ws_man = ws_man();
+        let request = SubscribeRequest::builder()
+            .delivery(DeliveryMode::Push {
+                notify_to: "http://collector:5985/wsman/subscriptions/1",
+            })
+            .expires(Duration::from_secs(3600))
+            .filter(Filter::event_query([(
+                "System",
+                "*[System[(Level=1 or Level=2)]]",
+            )]))
+            .bookmark(Bookmark::Earliest)
+            .send_bookmarks(true)
+            .build();
+        assert_eq!(request.resource_uri(), ResourceUri::EVENT_LOG);
+
+        let xml = ws_man.subscribe(request).into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+
+        let subscribe = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Subscribe")
+            .unwrap();
+        assert_eq!(
+            subscribe.tag_name().namespace(),
+            Some("http://schemas.xmlsoap.org/ws/2004/08/eventing")
+        );
+        let children: Vec<_> = subscribe
+            .children()
+            .filter(|child| child.is_element())
+            .map(|child| child.tag_name().name())
+            .collect();
+        assert_eq!(
+            children,
+            ["Delivery", "Expires", "Filter", "Bookmark", "SendBookmarks"]
+        );
+
+        let delivery = subscribe.first_element_child().unwrap();
+        assert_eq!(
+            delivery.attribute("Mode"),
+            Some("http://schemas.xmlsoap.org/ws/2004/08/eventing/DeliveryModes/Push")
+        );
+        assert!(
+            delivery
+                .descendants()
+                .any(|node| node.text() == Some("http://collector:5985/wsman/subscriptions/1"))
+        );
+
+        let texts: Vec<_> = document
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&(
+            "Action",
+            "http://schemas.xmlsoap.org/ws/2004/08/eventing/Subscribe"
+        )));
+        assert!(texts.contains(&("Expires", "PT3600.000S")));
+        assert!(texts.contains(&(
+            "Bookmark",
+            "http://schemas.dmtf.org/wbem/wsman/1/wsman/bookmark/earliest"
+        )));
+
+        let filter = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Filter")
+            .unwrap();
+        assert_eq!(filter.attribute("Dialect"), Some(EVENT_QUERY_DIALECT));
+        let select = filter
+            .descendants()
+            .find(|node| node.tag_name().name() == "Select")
+            .unwrap();
+        assert_eq!(select.attribute("Path"), Some("System"));
+        assert_eq!(select.text(), Some("*[System[(Level=1 or Level=2)]]"));
+    }
+
+    #[test]
+    fn test_subscribe_pull_with_bookmark() {
+        let bookmark = r#"<w:Bookmark xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><BookmarkList><Bookmark Channel="System" RecordId="4242" IsCurrent="true"/></BookmarkList></w:Bookmark>"#;
+        let bookmark =
+            OwnedElement::from_node(xml::parser::parse(bookmark).unwrap().root_element());
+
+        let ws_man = ws_man();
+        let request = SubscribeRequest::builder()
+            .filter(Filter::Wql("SELECT * FROM Win32_NTLogEvent"))
+            .bookmark(Bookmark::Resume(&bookmark))
+            .build();
+        let xml = ws_man.subscribe(request).into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+
+        let delivery = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Delivery")
+            .unwrap();
+        assert_eq!(
+            delivery.attribute("Mode"),
+            Some("http://schemas.dmtf.org/wbem/wsman/1/wsman/Pull")
+        );
+        assert!(delivery.first_element_child().is_none());
+
+        let record = document
+            .descendants()
+            .find(|node| node.attribute("RecordId").is_some())
+            .unwrap();
+        assert_eq!(record.attribute("RecordId"), Some("4242"));
+        assert!(
+            !document
+                .descendants()
+                .any(|node| node.tag_name().name() == "Expires"
+                    || node.tag_name().name() == "SendBookmarks")
+        );
+    }
+
+    #[test]
+    fn test_subscribe_response() {
+        let document = xml::parser::parse(SUBSCRIBE_RESPONSE).unwrap();
+        let response = SubscribeResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(response.manager().address, "http://localhost:5985/wsman");
+        assert_eq!(response.manager().resource_uri, ResourceUri::EVENT_LOG);
+        assert_eq!(
+            response.identifier(),
+            Some("uuid:5C2E7F6A-3B1D-4C8E-9F10-2A3B4C5D6E7F")
+        );
+        assert_eq!(response.expires(), Some("PT3600.000S"));
+        assert_eq!(response.expires_in(), Some(Duration::from_secs(3600)));
+        assert_eq!(
+            response.context(),
+            Some("uuid:0E5B3A1C-7D2F-4E6A-8B9C-1D2E3F4A5B6C")
+        );
+
+        let push = SUBSCRIBE_RESPONSE
+            .replace("<e:Expires>PT3600.000S</e:Expires>", "")
+            .replace(
+                "<n:EnumerationContext>uuid:0E5B3A1C-7D2F-4E6A-8B9C-1D2E3F4A5B6C</n:EnumerationContext>",
+                "",
+            );
+        let document = xml::parser::parse(&push).unwrap();
+        let response = SubscribeResponse::from_node(document.root_element(), "en-US").unwrap();
+        assert_eq!(response.expires_in(), None);
+        assert_eq!(response.context(), None);
+
+        let wrong_action =
+            SUBSCRIBE_RESPONSE.replace("SubscribeResponse</a:Action>", "Subscribe</a:Action>");
+        let document = xml::parser::parse(&wrong_action).unwrap();
+        assert!(matches!(
+            SubscribeResponse::from_node(document.root_element(), "en-US"),
+            Err(ProtocolError::UnexpectedAction { .. })
+        ));
+    }
+
+    fn renew_response(expires: Option<&str>) -> String {
+        let expires = expires
+            .map(|expires| format!("<e:Expires>{expires}</e:Expires>"))
+            .unwrap_or_default();
+        format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:e="http://schemas.xmlsoap.org/ws/2004/08/eventing"><s:Header><a:Action>http://schemas.xmlsoap.org/ws/2004/08/eventing/RenewResponse</a:Action></s:Header><s:Body><e:RenewResponse>{expires}</e:RenewResponse></s:Body></s:Envelope>"#
+        )
+    }
+
+    #[test]
+    fn test_renew_and_unsubscribe_requests() {
+        let document = xml::parser::parse(SUBSCRIBE_RESPONSE).unwrap();
+        let response = SubscribeResponse::from_node(document.root_element(), "en-US").unwrap();
+        let ws_man = ws_man();
+
+        let request = RenewRequest::builder()
+            .manager(response.manager())
+            .expires(Duration::from_secs(600))
+            .build();
+        let xml = ws_man.renew(request).into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let texts: Vec<_> = document
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&(
+            "Action",
+            "http://schemas.xmlsoap.org/ws/2004/08/eventing/Renew"
+        )));
+        assert!(texts.contains(&("ResourceURI", ResourceUri::EVENT_LOG)));
+        assert!(texts.contains(&("Expires", "PT600.000S")));
+
+        let identifier = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Identifier")
+            .unwrap();
+        assert_eq!(
+            identifier.parent_element().unwrap().tag_name().name(),
+            "Header"
+        );
+        assert_eq!(
+            identifier.text(),
+            Some("uuid:5C2E7F6A-3B1D-4C8E-9F10-2A3B4C5D6E7F")
+        );
+
+        let request = UnsubscribeRequest::builder()
+            .manager(response.manager())
+            .build();
+        let xml = ws_man.unsubscribe(request).into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let body = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Body")
+            .unwrap();
+        assert_eq!(
+            body.first_element_child()
+                .map(|child| child.tag_name().name()),
+            Some("Unsubscribe")
+        );
+        assert!(
+            document
+                .descendants()
+                .any(|node| node.tag_name().name() == "Identifier")
+        );
+
+        let unsubscribe_response =
+            renew_response(None).replace("RenewResponse", "UnsubscribeResponse");
+        let document = xml::parser::parse(&unsubscribe_response).unwrap();
+        assert!(UnsubscribeResponse::from_node(document.root_element(), "en-US").is_ok());
+    }
+
+    #[test]
+    fn test_renew_response() {
+        let xml = renew_response(Some("P1DT30M"));
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = RenewResponse::from_node(document.root_element(), "en-US").unwrap();
+        assert_eq!(response.expires(), Some("P1DT30M"));
+        assert_eq!(
+            response.expires_in(),
+            Some(Duration::from_secs(86_400 + 1_800))
+        );
+
+        let xml = renew_response(Some("2026-10-14T12:00:00Z"));
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = RenewResponse::from_node(document.root_element(), "en-US").unwrap();
+        assert_eq!(response.expires_in(), None);
+    }
+
+    #[test]
+    fn test_subscription_keep_alive() {
+        let document = xml::parser::parse(SUBSCRIBE_RESPONSE).unwrap();
+        let response = SubscribeResponse::from_node(document.root_element(), "en-US").unwrap();
+        let mut subscription = Subscription::new(&response, Some(Duration::from_secs(3600)));
+        assert_eq!(subscription.granted(), Some(Duration::from_secs(3600)));
+        assert!(subscription.renew_at().is_some());
+
+        let sleeps = RefCell::new(Vec::new());
+        let requests = RefCell::new(Vec::new());
+        let mut responses = vec![renew_response(None), renew_response(Some("PT10.000S"))];
+
+        let ws_man = ws_man();
+        let result = block_on(subscription.keep_alive(
+            &ws_man,
+            "en-US",
+            |duration| {
+                sleeps.borrow_mut().push(duration);
+                async {}
+            },
+            |request| {
+                requests.borrow_mut().push(request);
+                let response = responses.pop().unwrap();
+                async move { Ok(response) }
+            },
+        ));
+        assert!(result.is_ok());
+
+        let sleeps = sleeps.into_inner();
+        assert_eq!(sleeps.len(), 2);
+        assert!(sleeps[0] <= Duration::from_secs(1800) && sleeps[0] > Duration::from_secs(1790));
+        assert!(sleeps[1] <= Duration::from_secs(5) && sleeps[1] > Duration::from_secs(4));
+
+        let requests = requests.into_inner();
+        assert_eq!(requests.len(), 2);
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.contains("PT3600.000S"))
+        );
+        assert_eq!(subscription.granted(), None);
+        assert!(subscription.renew_at().is_none());
+    }
+
+    #[test]
+    fn test_subscription_keep_alive_fault() {
+        let document = xml::parser::parse(SUBSCRIBE_RESPONSE).unwrap();
+        let response = SubscribeResponse::from_node(document.root_element(), "en-US").unwrap();
+        let mut subscription = Subscription::new(&response, None);
+
+        let fault = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:e="http://schemas.xmlsoap.org/ws/2004/08/eventing"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Receiver</s:Value><s:Subcode><s:Value>e:UnableToRenew</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The subscription cannot be renewed.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let ws_man = ws_man();
+        let result = block_on(subscription.keep_alive(
+            &ws_man,
+            "en-US",
+            |_| async {},
+            |_| async { Ok(fault.to_owned()) },
+        ));
+        assert!(result.is_err());
+        assert_eq!(subscription.granted(), Some(Duration::from_secs(3600)));
+    }
+
+    const EVENTS_PULL_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/PullResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <n:PullResponse>
+                <n:EnumerationContext>uuid:context-2</n:EnumerationContext>
+                <n:Items>
+                    <Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event"><System><EventID>7036</EventID></System></Event>
+                    <Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event"><System><EventID>7040</EventID></System></Event>
+                </n:Items>
+            </n:PullResponse>
+        </s:Body>
+    </s:Envelope>"#;
+
+    const HEARTBEAT: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header><a:Action>http://schemas.dmtf.org/wbem/wsman/1/wsman/Heartbeat</a:Action><a:MessageID>uuid:heartbeat-1</a:MessageID><w:AckRequested/></s:Header><s:Body/></s:Envelope>"#;
+
+    const TIMED_OUT: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Receiver</s:Value><s:Subcode><s:Value>w:TimedOut</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The operation timed out.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+
+    const ENUMERATION_END: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration"><s:Header><a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/EnumerationEnd</a:Action></s:Header><s:Body><n:EnumerationEnd><n:EnumerationContext>uuid:context-2</n:EnumerationContext><n:Code>http://schemas.xmlsoap.org/ws/2004/09/enumeration/SourceShuttingDown</n:Code></n:EnumerationEnd></s:Body></s:Envelope>"#;
+
+    #[test]
+    fn test_subscribe_heartbeats() {
+        let ws_man = ws_man();
+        let request = SubscribeRequest::builder()
+            .delivery(DeliveryMode::PushWithAck {
+                notify_to: "http://collector:5985/wsman/subscriptions/1",
+            })
+            .heartbeats(Duration::from_secs(30))
+            .build();
+        assert_eq!(request.heartbeats(), Some(Duration::from_secs(30)));
+
+        let xml = ws_man.subscribe(request).into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let delivery = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Delivery")
+            .unwrap();
+        assert_eq!(
+            delivery.attribute("Mode"),
+            Some("http://schemas.dmtf.org/wbem/wsman/1/wsman/PushWithAck")
+        );
+
+        let children: Vec<_> = delivery
+            .children()
+            .filter(|child| child.is_element())
+            .map(|child| (child.tag_name().name(), child.tag_name().namespace()))
+            .collect();
+        assert_eq!(
+            children,
+            [
+                (
+                    "NotifyTo",
+                    Some("http://schemas.xmlsoap.org/ws/2004/08/eventing")
+                ),
+                (
+                    "Heartbeats",
+                    Some("http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd")
+                ),
+            ]
+        );
+        assert_eq!(
+            delivery.last_element_child().unwrap().text(),
+            Some("PT30.000S")
+        );
+    }
+
+    #[test]
+    fn test_pull_events() {
+        let ws_man = ws_man();
+        let requests = RefCell::new(Vec::new());
+        let mut responses = vec![ENUMERATION_END, TIMED_OUT, HEARTBEAT, EVENTS_PULL_RESPONSE];
+
+        let events: Vec<_> = block_on(
+            ws_man
+                .pull_events(
+                    ResourceUri::EVENT_LOG,
+                    "uuid:context-1",
+                    None,
+                    "en-US",
+                    |request| {
+                        requests.borrow_mut().push(request);
+                        let response = responses.pop().unwrap().to_owned();
+                        async move { Ok(response) }
+                    },
+                )
+                .collect(),
+        );
+
+        let events: Vec<_> = events.into_iter().map(Result::unwrap).collect();
+        assert_eq!(events.len(), 4);
+        let SubscriptionEvent::Event(event) = &events[0] else {
+            panic!("Expected an event");
+        };
+        assert_eq!(event.name, "Event");
+        assert!(matches!(events[1], SubscriptionEvent::Event(_)));
+        assert!(matches!(events[2], SubscriptionEvent::Heartbeat));
+        assert!(matches!(events[3], SubscriptionEvent::Heartbeat));
+
+        let requests = requests.into_inner();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[0].contains("uuid:context-1"));
+        assert!(requests[1..].iter().all(|request| {
+            request.contains("uuid:context-2") && request.contains(ResourceUri::EVENT_LOG)
+        }));
+    }
+
+    #[test]
+    fn test_pull_events_missed_heartbeat() {
+        let ws_man = ws_man();
+        let mut stream = ws_man.pull_events(
+            ResourceUri::EVENT_LOG,
+            "uuid:context-1",
+            Some(Duration::ZERO),
+            "en-US",
+            |_| async { Ok(HEARTBEAT.to_owned()) },
+        );
+
+        assert!(matches!(
+            block_on(stream.next()),
+            Some(Ok(SubscriptionEvent::HeartbeatMissed { .. }))
+        ));
+        assert!(matches!(
+            block_on(stream.next()),
+            Some(Ok(SubscriptionEvent::Heartbeat))
+        ));
+    }
+
+    #[test]
+    fn test_pushed_message_ack() {
+        let document = xml::parser::parse(HEARTBEAT).unwrap();
+        let message = PushedMessage::from_node(document.root_element(), "en-US").unwrap();
+        assert!(message.is_heartbeat());
+        assert!(message.ack_requested());
+        assert_eq!(message.message_id(), Some("uuid:heartbeat-1"));
+        assert!(matches!(
+            message.subscription_events().as_slice(),
+            [SubscriptionEvent::Heartbeat]
+        ));
+
+        let ws_man = ws_man();
+        let xml = ws_man.ack(&message).into_element().to_string();
+        let ack = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let texts: Vec<_> = ack
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&("Action", "http://schemas.dmtf.org/wbem/wsman/1/wsman/Ack")));
+        assert!(texts.contains(&("RelatesTo", "uuid:heartbeat-1")));
+
+        let pushed = EVENTS_PULL_RESPONSE
+            .replace("<n:PullResponse>", "")
+            .replace("</n:PullResponse>", "")
+            .replace("<n:Items>", "")
+            .replace("</n:Items>", "")
+            .replace(
+                "<n:EnumerationContext>uuid:context-2</n:EnumerationContext>",
+                "",
+            )
+            .replace(
+                "http://schemas.xmlsoap.org/ws/2004/09/enumeration/PullResponse",
+                "http://schemas.dmtf.org/wbem/wsman/1/wsman/Event",
+            );
+        let document = xml::parser::parse(&pushed).unwrap();
+        let message = PushedMessage::from_node(document.root_element(), "en-US").unwrap();
+        assert!(!message.is_heartbeat());
+        assert!(!message.ack_requested());
+        assert_eq!(message.events().len(), 2);
+        assert_eq!(message.subscription_events().len(), 2);
+    }
+
+    #[test]
+    fn test_heartbeat_watch() {
+        let first = SubscriptionEvent::Heartbeat;
+        let events = stream::iter([Ok(first)]).chain(stream::pending());
+        let sleeps = RefCell::new(0);
+        let mut watch = HeartbeatWatch::new(events, Duration::ZERO, |_| {
+            *sleeps.borrow_mut() += 1;
+            async {}
+        });
+
+        assert!(matches!(
+            block_on(watch.next()),
+            Some(Ok(SubscriptionEvent::Heartbeat))
+        ));
+        assert!(matches!(
+            block_on(watch.next()),
+            Some(Ok(SubscriptionEvent::HeartbeatMissed { .. }))
+        ));
+
+        let mut watch = HeartbeatWatch::new(
+            stream::pending::<Result<SubscriptionEvent, ProtocolError>>(),
+            Duration::from_millis(5),
+            |duration| {
+                *sleeps.borrow_mut() += 1;
+                let until = std::time::Instant::now() + duration;
+                async move { while std::time::Instant::now() < until {} }
+            },
+        );
+        let Some(Ok(SubscriptionEvent::HeartbeatMissed { silence })) = block_on(watch.next())
+        else {
+            panic!("Expected a missed heartbeat");
+        };
+        assert!(silence >= Duration::from_millis(10));
+        assert!(*sleeps.borrow() >= 1);
+    }
+
+    const BOOKMARKED_EVENT: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+        <s:Header>
+            <a:Action>http://schemas.dmtf.org/wbem/wsman/1/wsman/Event</a:Action>
+            <a:MessageID>uuid:event-1</a:MessageID>
+            <w:Bookmark><BookmarkList><Bookmark Channel="System" RecordId="4242" IsCurrent="true"/></BookmarkList></w:Bookmark>
+        </s:Header>
+        <s:Body>
+            <Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event"><System><EventID>7036</EventID></System></Event>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_bookmark_persisted_and_resumed() {
+        let document = xml::parser::parse(BOOKMARKED_EVENT).unwrap();
+        let message = PushedMessage::from_node(document.root_element(), "en-US").unwrap();
+        let events = message.subscription_events();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], SubscriptionEvent::Event(_)));
+        let SubscriptionEvent::Bookmark(bookmark) = &events[1] else {
+            panic!("Expected the bookmark after the events");
+        };
+        assert_eq!(message.bookmark(), Some(bookmark));
+
+        let stored = bookmark.to_xml();
+        let restored = EventBookmark::from_xml(&stored).unwrap();
+        assert_eq!(&restored, bookmark);
+        assert!(EventBookmark::from_xml("<BookmarkList/>").is_err());
+
+        let ws_man = ws_man();
+        let request = SubscribeRequest::builder()
+            .bookmark(Bookmark::from(&restored))
+            .send_bookmarks(true)
+            .build();
+        let xml = ws_man.subscribe(request).into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let bookmark = document
+            .descendants()
+            .find(|node| {
+                node.tag_name().name() == "Bookmark"
+                    && node.tag_name().namespace()
+                        == Some("http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd")
+            })
+            .unwrap();
+        assert_eq!(
+            bookmark.parent_element().unwrap().tag_name().name(),
+            "Subscribe"
+        );
+        let record = bookmark
+            .descendants()
+            .find(|node| node.attribute("RecordId").is_some())
+            .unwrap();
+        assert_eq!(record.attribute("RecordId"), Some("4242"));
+        assert_eq!(record.attribute("Channel"), Some("System"));
+    }
+
+    #[test]
+    fn test_pull_events_bookmark() {
+        let response = EVENTS_PULL_RESPONSE.replace(
+            "</s:Header>",
+            r#"<w:Bookmark xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><BookmarkList><Bookmark Channel="System" RecordId="7"/></BookmarkList></w:Bookmark></s:Header>"#,
+        );
+        let mut responses = vec![ENUMERATION_END.to_owned(), response];
+
+        let ws_man = ws_man();
+        let events: Vec<_> = block_on(
+            ws_man
+                .pull_events(
+                    ResourceUri::EVENT_LOG,
+                    "uuid:context-1",
+                    None,
+                    "en-US",
+                    |_| {
+                        let response = responses.pop().unwrap();
+                        async move { Ok(response) }
+                    },
+                )
+                .collect(),
+        );
+
+        let events: Vec<_> = events.into_iter().map(Result::unwrap).collect();
+        assert_eq!(events.len(), 3);
+        let SubscriptionEvent::Bookmark(bookmark) = &events[2] else {
+            panic!("Expected the bookmark after the events");
+        };
+        assert!(bookmark.to_xml().contains(r#"RecordId="7""#));
+    }
+
+    #[test]
+    fn test_batched_delivery() {
+        let ws_man = ws_man();
+        let request = SubscribeRequest::builder()
+            .delivery(DeliveryMode::Events {
+                notify_to: "http://collector:5985/wsman/subscriptions/1",
+            })
+            .batching(
+                EventBatching::builder()
+                    .max_elements(20)
+                    .max_time(Duration::from_secs(30))
+                    .max_envelope_size(512_000)
+                    .build(),
+            )
+            .build();
+        let xml = ws_man.subscribe(request).into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+
+        let delivery = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Delivery")
+            .unwrap();
+        assert_eq!(
+            delivery.attribute("Mode"),
+            Some("http://schemas.dmtf.org/wbem/wsman/1/wsman/Events")
+        );
+        let children: Vec<_> = delivery
+            .children()
+            .filter(|child| child.is_element())
+            .map(|child| (child.tag_name().name(), child.text()))
+            .collect();
+        assert_eq!(
+            children,
+            [
+                ("NotifyTo", None),
+                ("MaxElements", Some("20")),
+                ("MaxTime", Some("PT30.000S")),
+                ("MaxEnvelopeSize", Some("512000")),
+            ]
+        );
+
+        let batch = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+            <s:Header>
+                <a:Action>http://schemas.dmtf.org/wbem/wsman/1/wsman/Events</a:Action>
+                <a:MessageID>uuid:batch-1</a:MessageID>
+                <w:AckRequested/>
+            </s:Header>
+            <s:Body>
+                <w:Events>
+                    <w:Event Action="http://schemas.dmtf.org/wbem/wsman/1/wsman/Event"><Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event"><System><EventID>1</EventID></System></Event></w:Event>
+                    <w:Event Action="http://schemas.dmtf.org/wbem/wsman/1/wsman/Event"><Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event"><System><EventID>2</EventID></System></Event></w:Event>
+                    <w:Event Action="http://schemas.dmtf.org/wbem/wsman/1/wsman/Event"><Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event"><System><EventID>3</EventID></System></Event></w:Event>
+                </w:Events>
+            </s:Body>
+        </s:Envelope>"#;
+        let document = xml::parser::parse(batch).unwrap();
+        let message = PushedMessage::from_node(document.root_element(), "en-US").unwrap();
+        assert!(message.ack_requested());
+
+        let ids: Vec<_> = message
+            .events()
+            .iter()
+            .map(|event| {
+                assert_eq!(event.tag_name().name(), "Event");
+                assert_eq!(
+                    event.tag_name().namespace(),
+                    Some("http://schemas.microsoft.com/win/2004/08/events/event")
+                );
+                event
+                    .descendants()
+                    .find(|node| node.tag_name().name() == "EventID")
+                    .and_then(|id| id.text())
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+        assert_eq!(message.subscription_events().len(), 3);
+    }
+}
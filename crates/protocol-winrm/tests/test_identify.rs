@@ -0,0 +1,119 @@
+mod common;
+
+use common::ws_man;
+use protocol_winrm::{
+    error::ProtocolError,
+    soap::SoapVersion,
+    ws_management::{IDENTIFY_UNAUTHENTICATED_HEADER, IdentifyResponse, ServerKind, WsMan},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOWS_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xml:lang="en-US">
+        <s:Header/>
+        <s:Body>
+            <wsmid:IdentifyResponse xmlns:wsmid="http://schemas.dmtf.org/wbem/wsman/identity/1/wsmanidentity.xsd">
+                <wsmid:ProtocolVersion>http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd</wsmid:ProtocolVersion>
+                <wsmid:ProductVendor>Microsoft Corporation</wsmid:ProductVendor>
+                <wsmid:ProductVersion>OS: 10.0.17763 SP: 0.0 Stack: 3.0</wsmid:ProductVersion>
+                <wsmid:SecurityProfiles>
+                    <wsmid:SecurityProfileName>http://schemas.dmtf.org/wbem/wsman/1/wsman/secprofile/http/spnego-kerberos</wsmid:SecurityProfileName>
+                </wsmid:SecurityProfiles>
+            </wsmid:IdentifyResponse>
+        </s:Body>
+    </s:Envelope>"#;
+
+    const OMI_RESPONSE: &str = r#"<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope" xmlns:wsmid="http://schemas.dmtf.org/wbem/wsman/identity/1/wsmanidentity.xsd">
+        <SOAP-ENV:Header/>
+        <SOAP-ENV:Body>
+            <wsmid:IdentifyResponse>
+                <wsmid:ProtocolVersion>http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd</wsmid:ProtocolVersion>
+                <wsmid:ProductVendor>Open Management Infrastructure</wsmid:ProductVendor>
+                <wsmid:ProductVersion>1.6.8-1</wsmid:ProductVersion>
+            </wsmid:IdentifyResponse>
+        </SOAP-ENV:Body>
+    </SOAP-ENV:Envelope>"#;
+
+    #[test]
+    fn test_identify_request() {
+        let ws_man = ws_man();
+        let xml = ws_man.identify().into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+
+        let header = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Header")
+            .unwrap();
+        assert!(header.first_element_child().is_none());
+
+        let body = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Body")
+            .unwrap();
+        let identify = body.first_element_child().unwrap();
+        assert_eq!(identify.tag_name().name(), "Identify");
+        assert_eq!(
+            identify.tag_name().namespace(),
+            Some("http://schemas.dmtf.org/wbem/wsman/identity/1/wsmanidentity.xsd")
+        );
+        assert_eq!(IDENTIFY_UNAUTHENTICATED_HEADER.1, "unauthenticated");
+    }
+
+    #[test]
+    fn test_identify_request_soap11() {
+        let ws_man = WsMan::builder()
+            .to("http://localhost:5985/wsman".to_string())
+            .soap_version(SoapVersion::Soap11)
+            .build();
+        let xml = ws_man.identify().into_element().to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        assert_eq!(
+            document.root_element().tag_name().namespace(),
+            Some("http://schemas.xmlsoap.org/soap/envelope/")
+        );
+    }
+
+    #[test]
+    fn test_identify_windows() {
+        let document = xml::parser::parse(WINDOWS_RESPONSE).unwrap();
+        let response = IdentifyResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(
+            response.protocol_version(),
+            Some("http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd")
+        );
+        assert_eq!(response.product_vendor(), Some("Microsoft Corporation"));
+        assert_eq!(response.server_kind(), ServerKind::Windows);
+        assert_eq!(response.stack_version(), Some("3.0"));
+        assert_eq!(response.security_profiles().len(), 1);
+    }
+
+    #[test]
+    fn test_identify_omi() {
+        let document = xml::parser::parse(OMI_RESPONSE).unwrap();
+        let response = IdentifyResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(response.server_kind(), ServerKind::Omi);
+        assert_eq!(response.product_version(), Some("1.6.8-1"));
+        assert_eq!(response.stack_version(), None);
+        assert!(response.security_profiles().is_empty());
+    }
+
+    #[test]
+    fn test_identify_fault() {
+        let fault = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>w:AccessDenied</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">Access is denied.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let document = xml::parser::parse(fault).unwrap();
+        assert!(matches!(
+            IdentifyResponse::from_node(document.root_element(), "en-US"),
+            Err(ProtocolError::WsmanFault { .. })
+        ));
+
+        let document = xml::parser::parse(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body/></s:Envelope>"#,
+        )
+        .unwrap();
+        assert!(IdentifyResponse::from_node(document.root_element(), "en-US").is_err());
+    }
+}
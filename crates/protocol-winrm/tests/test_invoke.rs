@@ -0,0 +1,72 @@
+use protocol_winrm::{
+    error::ProtocolError,
+    ws_management::{InvokeRequest, InvokeResponse, ResourceUri, WsMan},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROCESS_URI: &str =
+        "http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Process";
+
+    const CREATE_OUTPUT: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Process">
+        <s:Header>
+            <a:Action>http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Process/CreateResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <p:Create_OUTPUT>
+                <p:ProcessId>4242</p:ProcessId>
+                <p:ReturnValue>0</p:ReturnValue>
+            </p:Create_OUTPUT>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_invoke_request() {
+        let resource_uri = ResourceUri::wmi("root/cimv2", "Win32_Process").unwrap();
+        assert_eq!(resource_uri.as_str(), PROCESS_URI);
+
+        let ws_man = WsMan::builder()
+            .to("http://localhost:5985/wsman".to_string())
+            .build();
+        let request = InvokeRequest::new(resource_uri.as_str(), "Create")
+            .add_parameter("CommandLine", "notepad.exe");
+        let xml = ws_man.invoke_method(&request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        assert!(document.descendants().any(|node| node.text()
+            == Some(
+                "http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Process/Create"
+            )));
+
+        let input = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Create_INPUT")
+            .expect("The body carries the method input");
+        assert_eq!(input.tag_name().namespace(), Some(PROCESS_URI));
+
+        let command_line = input.first_element_child().unwrap();
+        assert_eq!(command_line.tag_name().name(), "CommandLine");
+        assert_eq!(command_line.tag_name().namespace(), Some(PROCESS_URI));
+        assert_eq!(command_line.text(), Some("notepad.exe"));
+    }
+
+    #[test]
+    fn test_invoke_response() {
+        let request = InvokeRequest::new(PROCESS_URI, "Create");
+        let document = xml::parser::parse(CREATE_OUTPUT).unwrap();
+
+        let response =
+            InvokeResponse::from_node(document.root_element(), &request, "en-US").unwrap();
+        assert_eq!(response.return_value(), Some(0));
+        assert_eq!(response.parameter("ProcessId"), Some("4242"));
+        assert_eq!(response.parameter("Handle"), None);
+
+        let terminate = InvokeRequest::new(PROCESS_URI, "Terminate");
+        assert!(matches!(
+            InvokeResponse::from_node(document.root_element(), &terminate, "en-US"),
+            Err(ProtocolError::UnexpectedAction { .. })
+        ));
+    }
+}
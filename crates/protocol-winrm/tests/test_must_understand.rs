@@ -0,0 +1,42 @@
+use protocol_winrm::{
+    error::ProtocolError,
+    soap::{HeaderRegistry, SoapEnvelope},
+};
+use xml::parser::XmlDeserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:v="urn:example:vendor">
+        <s:Header>
+            <a:Action s:mustUnderstand="true">http://schemas.xmlsoap.org/ws/2004/09/transfer/CreateResponse</a:Action>
+            <v:Audit s:mustUnderstand="false">ignored</v:Audit>
+            <v:Route s:mustUnderstand="true">east</v:Route>
+        </s:Header>
+        <s:Body/>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_unknown_must_understand_header_is_rejected() {
+        let document = xml::parser::parse(RESPONSE).unwrap();
+        let envelope = SoapEnvelope::from_node(document.root_element()).unwrap();
+
+        let error = envelope
+            .check_must_understand(&HeaderRegistry::new())
+            .unwrap_err();
+        let ProtocolError::NotUnderstood { headers } = error else {
+            panic!("Expected NotUnderstood, got {error:?}");
+        };
+        assert_eq!(headers, ["{urn:example:vendor}Route"]);
+    }
+
+    #[test]
+    fn test_registered_header_is_understood() {
+        let document = xml::parser::parse(RESPONSE).unwrap();
+        let envelope = SoapEnvelope::from_node(document.root_element()).unwrap();
+
+        let registry = HeaderRegistry::new().register_name("urn:example:vendor", "Route");
+        assert!(envelope.check_must_understand(&registry).is_ok());
+    }
+}
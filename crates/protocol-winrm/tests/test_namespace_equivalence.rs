@@ -0,0 +1,39 @@
+use protocol_winrm::{
+    cores::{Namespace, SelectorSet, SessionId, Tag, TagName, namespaces_match},
+    ws_management::SelectorSetValue,
+};
+use xml::parser::XmlDeserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wsman_namespaces_are_equivalent() {
+        let dmtf = Some(Namespace::DmtfWsmanSchema.uri());
+        let microsoft = Some(Namespace::MsWsmanSchema.uri());
+
+        assert!(namespaces_match(microsoft, dmtf));
+        assert!(namespaces_match(dmtf, microsoft));
+        assert!(!namespaces_match(Some(Namespace::WsmanShell.uri()), dmtf));
+        assert!(!namespaces_match(None, dmtf));
+    }
+
+    #[test]
+    fn test_selector_set_in_microsoft_namespace() {
+        let xml = r#"<p:SelectorSet xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wsman.xsd"><p:Selector Name="ShellId">1234</p:Selector></p:SelectorSet>"#;
+        let document = xml::parser::parse(xml).unwrap();
+
+        let tag: Tag<'_, SelectorSetValue, SelectorSet> =
+            Tag::from_node(document.root_element()).unwrap();
+        assert_eq!(tag.value.get("ShellId").map(String::as_str), Some("1234"));
+    }
+
+    #[test]
+    fn test_microsoft_tag_in_dmtf_namespace() {
+        let xml = r#"<w:SessionId xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">uuid:00000000-0000-0000-0000-000000000000</w:SessionId>"#;
+        let document = xml::parser::parse(xml).unwrap();
+
+        assert!(SessionId::matches_node(&document.root_element()));
+    }
+}
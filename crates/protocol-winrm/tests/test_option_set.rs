@@ -0,0 +1,88 @@
+use protocol_winrm::{
+    cores::{Tag, tag_name::*},
+    error::ProtocolError,
+    ws_management::{OptionAttributes, OptionSetValue, WsmanFaultKind},
+};
+use xml::parser::XmlDeserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_attributes_round_trip() {
+        let option_set = OptionSetValue::new()
+            .add_option("WINRS_NOPROFILE", "TRUE")
+            .add_option_with(
+                "WSMAN_CMDSHELL_OPTION_KEEPALIVE",
+                "1",
+                OptionAttributes {
+                    must_comply: false,
+                    value_type: Some("xs:int".to_string()),
+                },
+            );
+
+        let xml = Tag::<OptionSetValue, OptionSet>::new(option_set)
+            .with_declaration(protocol_winrm::cores::namespace::Namespace::DmtfWsmanSchema)
+            .into_element()
+            .to_string();
+        assert!(xml.contains(r#"MustComply="false""#));
+        assert!(xml.contains(r#"Type="xs:int""#));
+
+        let document = xml::parser::parse(&xml).unwrap();
+        let parsed: Tag<'_, OptionSetValue, OptionSet> =
+            Tag::from_node(document.root_element()).unwrap();
+
+        assert_eq!(
+            parsed
+                .value
+                .option_attributes("WSMAN_CMDSHELL_OPTION_KEEPALIVE"),
+            OptionAttributes {
+                must_comply: false,
+                value_type: Some("xs:int".to_string()),
+            }
+        );
+        assert!(
+            parsed
+                .value
+                .option_attributes("WINRS_NOPROFILE")
+                .must_comply
+        );
+        assert_eq!(
+            parsed.value.must_comply_options().collect::<Vec<_>>(),
+            vec!["WINRS_NOPROFILE"]
+        );
+    }
+
+    #[test]
+    fn test_must_comply_option_rejected() {
+        let option_set = OptionSetValue::new()
+            .add_option("WINRS_CODEPAGE", "65001")
+            .add_option("WINRS_NOPROFILE", "TRUE");
+
+        let fault = ProtocolError::WsmanFault {
+            kind: WsmanFaultKind::InvalidOptions,
+            code: None,
+            subcode: Some("w:InvalidOptions".to_string()),
+            reason: "The option WINRS_CODEPAGE is not supported".to_string(),
+        };
+
+        match option_set.check_rejection(fault) {
+            ProtocolError::MustComplyOptionRejected { options, .. } => {
+                assert_eq!(options, vec!["WINRS_CODEPAGE".to_string()]);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        let timeout = ProtocolError::WsmanFault {
+            kind: WsmanFaultKind::OperationTimeout,
+            code: Some(WsmanFaultKind::CODE_OPERATION_TIMEOUT),
+            subcode: None,
+            reason: String::new(),
+        };
+        assert!(matches!(
+            option_set.check_rejection(timeout),
+            ProtocolError::WsmanFault { .. }
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+use protocol_winrm::{
+    cores::{Attribute, SelectorSet, Tag},
+    soap::{SoapEnvelope, fault::SoapFault},
+    ws_management::SelectorSetValue,
+};
+use xml::parser::XmlDeserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every namespace is bound to an unusual prefix, the default namespace is WS-Addressing,
+    // and a vendor header reuses the local name `Action` in its own namespace
+    const RESPONSE: &str = r#"<env:Envelope xmlns:env="http://www.w3.org/2003/05/soap-envelope" xmlns="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:s="urn:example:not-soap" xmlns:wsman_1="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+        <env:Header>
+            <s:Action env:mustUnderstand="false">urn:example:vendor-action</s:Action>
+            <Action env:mustUnderstand="true">http://schemas.xmlsoap.org/ws/2004/09/transfer/GetResponse</Action>
+            <RelatesTo>uuid:00000000-0000-0000-0000-000000000001</RelatesTo>
+            <wsman_1:SelectorSet><wsman_1:Selector Name="ShellId">ABC</wsman_1:Selector></wsman_1:SelectorSet>
+        </env:Header>
+        <env:Body/>
+    </env:Envelope>"#;
+
+    #[test]
+    fn test_headers_are_matched_by_namespace_uri() {
+        let document = xml::parser::parse(RESPONSE).unwrap();
+        let envelope = SoapEnvelope::from_node(document.root_element()).unwrap();
+        let header = envelope.header.as_ref().unwrap();
+
+        let action = header.value.action.as_ref().unwrap();
+        assert_eq!(
+            action.value.as_ref(),
+            "http://schemas.xmlsoap.org/ws/2004/09/transfer/GetResponse"
+        );
+        assert!(
+            action
+                .attributes
+                .iter()
+                .any(|attribute| matches!(attribute, Attribute::MustUnderstand(true)))
+        );
+        assert!(header.value.relates_to.is_some());
+
+        let selectors = &header.value.selector_set.as_ref().unwrap().value;
+        assert_eq!(selectors.get("ShellId").map(String::as_str), Some("ABC"));
+
+        let vendor: Vec<_> = envelope.custom_headers.received().collect();
+        assert_eq!(vendor.len(), 1);
+        assert_eq!(
+            vendor[0].tag_name().namespace(),
+            Some("urn:example:not-soap")
+        );
+    }
+
+    #[test]
+    fn test_wrong_namespace_is_not_matched() {
+        let xml = r#"<wsman:SelectorSet xmlns:wsman="http://schemas.microsoft.com/wbem/wsman/1/windows/shell"><wsman:Selector Name="ShellId">ABC</wsman:Selector></wsman:SelectorSet>"#;
+        let document = xml::parser::parse(xml).unwrap();
+
+        let result: Result<Tag<'_, SelectorSetValue, SelectorSet>, _> =
+            Tag::from_node(document.root_element());
+        assert!(result.is_err());
+
+        let xml = r#"<a:Envelope xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing"><a:Body/></a:Envelope>"#;
+        let document = xml::parser::parse(xml).unwrap();
+        assert!(SoapEnvelope::from_node(document.root_element()).is_err());
+    }
+
+    #[test]
+    fn test_fault_with_unusual_prefixes() {
+        let xml = r#"<S12:Envelope xmlns:S12="http://www.w3.org/2003/05/soap-envelope" xmlns:mgmt="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:s="urn:example:not-soap">
+            <S12:Body>
+                <S12:Fault>
+                    <S12:Code>
+                        <S12:Value>S12:Sender</S12:Value>
+                        <S12:Subcode><S12:Value>mgmt:InvalidSelectors</S12:Value></S12:Subcode>
+                    </S12:Code>
+                    <S12:Reason>
+                        <s:Text xml:lang="en-US">Not a SOAP reason</s:Text>
+                        <S12:Text xml:lang="en-US">The selectors are invalid.</S12:Text>
+                    </S12:Reason>
+                    <S12:Detail><F:WSManFault xmlns:F="http://schemas.microsoft.com/wbem/wsman/1/wsmanfault" Code="2150858843"/></S12:Detail>
+                </S12:Fault>
+            </S12:Body>
+        </S12:Envelope>"#;
+        let document = xml::parser::parse(xml).unwrap();
+
+        let fault = SoapFault::find(document.root_element()).unwrap().unwrap();
+        assert_eq!(
+            fault.subcode.as_ref().map(|subcode| subcode.local_name()),
+            Some("InvalidSelectors")
+        );
+        assert_eq!(fault.reasons.len(), 1);
+        assert_eq!(fault.reason("en-US"), Some("The selectors are invalid."));
+        assert_eq!(fault.wsman_code, Some(2150858843));
+    }
+}
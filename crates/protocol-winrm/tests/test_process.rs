@@ -0,0 +1,740 @@
+mod common;
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use base64::Engine;
+use common::{block_on, ws_man_with_max_envelope_size};
+use protocol_winrm::{
+    error::ProtocolError,
+    rsp::{
+        compression::StreamCompression, process::CommandCompletion, send::StdinWriter,
+        shell::CreateShellRequest,
+    },
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
+use tokio_util::sync::CancellationToken;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMAND_ID: &str = "9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C";
+
+    const COMMAND_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+        <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandResponse</a:Action></s:Header>
+        <s:Body><rsp:CommandResponse><rsp:CommandId>9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C</rsp:CommandId></rsp:CommandResponse></s:Body>
+    </s:Envelope>"#;
+
+    const SEND_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+        <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/SendResponse</a:Action></s:Header>
+        <s:Body><rsp:SendResponse/></s:Body>
+    </s:Envelope>"#;
+
+    const SIGNAL_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+        <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/SignalResponse</a:Action></s:Header>
+        <s:Body><rsp:SignalResponse/></s:Body>
+    </s:Envelope>"#;
+
+    fn receive_response(body: &str) -> String {
+        format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+            <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/ReceiveResponse</a:Action></s:Header>
+            <s:Body><rsp:ReceiveResponse>{body}</rsp:ReceiveResponse></s:Body>
+        </s:Envelope>"#
+        )
+    }
+
+    fn stream(name: &str, data: &[u8], end: bool) -> String {
+        let data = base64::engine::general_purpose::STANDARD.encode(data);
+        format!(
+            r#"<rsp:Stream Name="{name}" CommandId="{COMMAND_ID}" End="{end}">{data}</rsp:Stream>"#
+        )
+    }
+
+    fn command_state(state: &str, exit_code: Option<i32>) -> String {
+        let exit_code = exit_code
+            .map(|exit_code| format!("<rsp:ExitCode>{exit_code}</rsp:ExitCode>"))
+            .unwrap_or_default();
+        format!(
+            r#"<rsp:CommandState CommandId="{COMMAND_ID}" State="http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandState/{state}">{exit_code}</rsp:CommandState>"#
+        )
+    }
+
+    /// Answers each request by its action, the Receives in order from `receives`
+    struct Server {
+        created: String,
+        receives: RefCell<VecDeque<String>>,
+        sent: RefCell<Vec<String>>,
+    }
+
+    impl Server {
+        fn new(receives: Vec<String>) -> Self {
+            Self {
+                created: fs::read_to_string("tests/resources/resource_created.xml").unwrap(),
+                receives: RefCell::new(receives.into()),
+                sent: RefCell::new(Vec::new()),
+            }
+        }
+
+        fn answer(&self, request: String) -> String {
+            let response = if request.contains("shell/Command<") {
+                COMMAND_RESPONSE.to_owned()
+            } else if request.contains("shell/Receive<") {
+                self.receives
+                    .borrow_mut()
+                    .pop_front()
+                    .expect("No Receive is expected")
+            } else if request.contains("shell/Send<") {
+                SEND_RESPONSE.to_owned()
+            } else if request.contains("shell/Signal<") {
+                SIGNAL_RESPONSE.to_owned()
+            } else {
+                self.created.clone()
+            };
+            self.sent.borrow_mut().push(request);
+            response
+        }
+
+        /// The decoded data and `End` of the Sends so far
+        fn stdin(&self) -> Vec<(Vec<u8>, bool)> {
+            self.sent
+                .borrow()
+                .iter()
+                .filter(|request| request.contains("shell/Send<"))
+                .map(|request| {
+                    let document = xml::parser::parse(request).unwrap();
+                    let stream = document
+                        .descendants()
+                        .find(|node| node.tag_name().name() == "Stream")
+                        .unwrap();
+                    assert_eq!(stream.attribute("CommandId"), Some(COMMAND_ID));
+                    let data = base64::engine::general_purpose::STANDARD
+                        .decode(stream.text().unwrap_or_default())
+                        .unwrap();
+                    (data, stream.attribute("End") == Some("true"))
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_command_output() {
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let server = Server::new(vec![
+            receive_response(&format!(
+                "{}{}{}",
+                stream("stdout", b"hello ", false),
+                stream("stderr", b"oops", false),
+                command_state("Running", None)
+            )),
+            receive_response(&format!(
+                "{}{}{}",
+                stream("stdout", b"world", true),
+                stream("stderr", b"", true),
+                command_state("Done", Some(3))
+            )),
+        ]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+
+        let mut command = block_on(shell.command("echo", &["hello"])).unwrap();
+        assert_eq!(command.command_id().to_string().to_uppercase(), COMMAND_ID);
+
+        let mut stdout = Vec::new();
+        block_on(command.stdout.read_to_end(&mut stdout)).unwrap();
+        assert_eq!(stdout, b"hello world");
+
+        // Buffered while stdout was read
+        let mut stderr = String::new();
+        block_on(command.stderr.read_to_string(&mut stderr)).unwrap();
+        assert_eq!(stderr, "oops");
+
+        let status = block_on(command.wait()).unwrap();
+        assert_eq!(status.code, 3);
+        assert!(!status.success());
+        drop(command);
+
+        let sent = server.sent.borrow();
+        let command_request = sent
+            .iter()
+            .find(|request| request.contains("shell/Command<"))
+            .unwrap();
+        assert!(command_request.contains("WINRS_CONSOLEMODE_STDIN"));
+        assert_eq!(
+            sent.iter()
+                .filter(|request| request.contains("shell/Receive<"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_command_stdin() {
+        let max_envelope_size = 4096;
+        let ws_man = ws_man_with_max_envelope_size(max_envelope_size);
+        let server = Server::new(vec![receive_response(&format!(
+            "{}{}{}",
+            stream("stdout", b"", true),
+            stream("stderr", b"", true),
+            command_state("Done", Some(0))
+        ))]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        let mut command = block_on(shell.command("findstr", &["x"])).unwrap();
+
+        let input: Vec<u8> = (0..max_envelope_size as usize * 2)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        block_on(async {
+            command.stdin.write_all(&input).await?;
+            command.stdin.shutdown().await
+        })
+        .unwrap();
+        assert!(
+            block_on(command.stdin.write_all(b"late"))
+                .is_err_and(|error| error.kind() == std::io::ErrorKind::BrokenPipe)
+        );
+
+        assert!(block_on(command.wait()).unwrap().success());
+        drop(command);
+
+        let stdin = server.stdin();
+        assert!(stdin.len() > 2, "The input is split into chunks");
+        assert!(stdin.iter().rev().skip(1).all(|(_, end)| !end));
+        assert_eq!(stdin.last(), Some(&(Vec::new(), true)));
+        assert_eq!(
+            stdin
+                .into_iter()
+                .flat_map(|(data, _)| data)
+                .collect::<Vec<_>>(),
+            input
+        );
+        assert!(
+            server
+                .sent
+                .borrow()
+                .iter()
+                .all(|request| request.len() <= max_envelope_size as usize)
+        );
+    }
+
+    #[test]
+    fn test_command_receive_fault() {
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let fault = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>w:InvalidSelectors</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The shell was not found on the server.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let server = Server::new(vec![fault.to_owned()]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        let mut command = block_on(shell.command("dir", &[])).unwrap();
+
+        let error = block_on(command.stdout.read_to_end(&mut Vec::new())).unwrap_err();
+        let fault = error
+            .get_ref()
+            .and_then(|error| error.downcast_ref::<ProtocolError>());
+        assert!(matches!(fault, Some(ProtocolError::WsmanFault { .. })));
+
+        // Not received again
+        assert!(block_on(command.stderr.read_to_end(&mut Vec::new())).is_err());
+        assert!(block_on(command.wait()).is_err());
+    }
+
+    #[test]
+    fn test_command_receive_timed_out() {
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let timed_out = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Receiver</s:Value><s:Subcode><s:Value>w:TimedOut</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The WS-Management service cannot complete the operation within the time specified in OperationTimeout.</s:Text></s:Reason><s:Detail><f:WSManFault xmlns:f="http://schemas.microsoft.com/wbem/wsman/1/wsmanfault" Code="2150858793"/></s:Detail></s:Fault></s:Body></s:Envelope>"#;
+        let server = Server::new(vec![
+            timed_out.to_owned(),
+            timed_out.to_owned(),
+            receive_response(&format!(
+                "{}{}{}",
+                stream("stdout", b"late", true),
+                stream("stderr", b"", true),
+                command_state("Done", Some(0))
+            )),
+        ]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        let mut command = block_on(shell.command("timeout", &["600"])).unwrap();
+
+        let mut stdout = Vec::new();
+        block_on(command.stdout.read_to_end(&mut stdout)).unwrap();
+        assert_eq!(stdout, b"late");
+        assert!(block_on(command.wait()).unwrap().success());
+        drop(command);
+
+        assert!(server.receives.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_command_compressed_streams() {
+        let compression = StreamCompression::Xpress;
+        let output = b"0123456789".repeat(1000);
+        let input = b"the same line of input\n".repeat(500);
+
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let server = Server::new(vec![receive_response(&format!(
+            "{}{}{}",
+            stream("stdout", &compression.compress(&output), true),
+            stream("stderr", &compression.compress(b""), true),
+            command_state("Done", Some(0))
+        ))]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell = block_on(
+            ws_man.open_shell(
+                CreateShellRequest::builder()
+                    .compression(compression)
+                    .build(),
+                "en-US",
+                exchange,
+            ),
+        )
+        .unwrap()
+        .delete_on_drop(|_| {});
+        assert_eq!(shell.compression(), Some(compression));
+        let mut command = block_on(shell.command("more", &[])).unwrap();
+
+        block_on(async {
+            command.stdin.write_all(&input).await?;
+            command.stdin.shutdown().await
+        })
+        .unwrap();
+        let mut stdout = Vec::new();
+        block_on(command.stdout.read_to_end(&mut stdout)).unwrap();
+        assert_eq!(stdout, output);
+        drop(command);
+
+        let sent: Vec<u8> = server
+            .stdin()
+            .into_iter()
+            .flat_map(|(data, _)| compression.decompress(&data).unwrap())
+            .collect();
+        assert_eq!(sent, input);
+    }
+
+    #[test]
+    fn test_run_command() {
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let server = Server::new(vec![
+            receive_response(&format!(
+                "{}{}",
+                stream("stdout", b"caf\xe9 ", false),
+                command_state("Running", None)
+            )),
+            receive_response(&format!(
+                "{}{}{}",
+                stream("stdout", b"cr\xe8me", true),
+                stream("stderr", b"Acc\xe8s refus\xe9", true),
+                command_state("Done", Some(5))
+            )),
+        ]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell = block_on(ws_man.open_shell(
+            CreateShellRequest::builder().codepage(1252).build(),
+            "en-US",
+            exchange,
+        ))
+        .unwrap()
+        .delete_on_drop(|_| {});
+        assert_eq!(shell.codepage(), 1252);
+
+        let output = block_on(shell.run("type", &["menu.txt"])).unwrap();
+        assert_eq!(output.stdout, b"caf\xe9 cr\xe8me");
+        assert_eq!(output.stdout_text(shell.codepage()).unwrap(), "café crème");
+        assert_eq!(
+            output.stderr_text(shell.codepage()).unwrap(),
+            "Accès refusé"
+        );
+        assert_eq!(output.exit.code, 5);
+        assert!(!output.success());
+
+        // The input is closed for the command not to wait on it
+        assert_eq!(server.stdin(), [(Vec::new(), true)]);
+    }
+
+    /// Reads `data` a few bytes at a time
+    struct Trickle<'a> {
+        data: &'a [u8],
+    }
+
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            let read = self.data.len().min(buf.remaining()).min(100);
+            buf.put_slice(&self.data[..read]);
+            self.data = &self.data[read..];
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_command_feed_stdin() {
+        let ws_man = ws_man_with_max_envelope_size(4096);
+        let server = Server::new(vec![receive_response(&format!(
+            "{}{}{}",
+            stream("stdout", b"", true),
+            stream("stderr", b"", true),
+            command_state("Done", Some(0))
+        ))]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        let chunk_size = StdinWriter::new(&ws_man, shell.shell_id(), uuid::Uuid::nil())
+            .with_resource_uri(shell.resource_uri())
+            .chunk_size();
+        let mut command = block_on(shell.command("findstr", &["x"])).unwrap();
+
+        let input: Vec<u8> = (0..chunk_size * 3 + 10).map(|i| (i % 251) as u8).collect();
+        let sent = block_on(command.feed_stdin(Trickle { data: &input })).unwrap();
+        assert_eq!(sent, input.len() as u64);
+        assert!(block_on(command.wait()).unwrap().success());
+        drop(command);
+
+        // Full chunks despite the short reads, then the end
+        let stdin = server.stdin();
+        let sizes: Vec<_> = stdin.iter().map(|(data, end)| (data.len(), *end)).collect();
+        assert_eq!(
+            sizes,
+            [
+                (chunk_size, false),
+                (chunk_size, false),
+                (chunk_size, false),
+                (10, false),
+                (0, true)
+            ]
+        );
+        assert_eq!(
+            stdin
+                .into_iter()
+                .flat_map(|(data, _)| data)
+                .collect::<Vec<_>>(),
+            input
+        );
+    }
+
+    #[test]
+    fn test_command_cancelled() {
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let server = Server::new(vec![receive_response(&format!(
+            "{}{}{}",
+            stream("stdout", b"^C", true),
+            stream("stderr", b"", true),
+            command_state("Done", Some(0xC000013Au32 as i32))
+        ))]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        let mut command = block_on(shell.command("ping", &["-t", "localhost"])).unwrap();
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let completion = block_on(command.wait_cancellable(&token)).unwrap();
+        let CommandCompletion::Cancelled(Some(status)) = completion else {
+            panic!("The command must be cancelled, got {completion:?}");
+        };
+        assert_eq!(status.code, 0xC000013Au32 as i32);
+
+        // The final output is still read
+        let mut stdout = Vec::new();
+        block_on(command.stdout.read_to_end(&mut stdout)).unwrap();
+        assert_eq!(stdout, b"^C");
+        drop(command);
+
+        // Interrupted as a pipeline of the PowerShell shell, drained and then released
+        let sent = server.sent.borrow();
+        let exchanged: Vec<_> = sent
+            .iter()
+            .skip_while(|request| !request.contains("shell/Command<"))
+            .skip(1)
+            .map(|request| {
+                if request.contains("powershell/signal/crtl_c") {
+                    "crtl_c"
+                } else if request.contains("signal/terminate") {
+                    "terminate"
+                } else if request.contains("shell/Receive<") {
+                    "receive"
+                } else {
+                    "other"
+                }
+            })
+            .collect();
+        assert_eq!(exchanged, ["crtl_c", "receive", "terminate"]);
+    }
+
+    #[test]
+    fn test_command_not_cancelled() {
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let server = Server::new(vec![receive_response(&format!(
+            "{}{}{}",
+            stream("stdout", b"done", true),
+            stream("stderr", b"", true),
+            command_state("Done", Some(0))
+        ))]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        let command = block_on(shell.command("echo", &["done"])).unwrap();
+
+        let token = CancellationToken::new();
+        let completion = block_on(command.wait_cancellable(&token)).unwrap();
+        assert!(matches!(completion, CommandCompletion::Exited(status) if status.success()));
+        assert!(!token.is_cancelled());
+        drop(command);
+
+        assert!(
+            !server
+                .sent
+                .borrow()
+                .iter()
+                .any(|request| request.contains("shell/Signal<"))
+        );
+    }
+
+    #[test]
+    fn test_command_duplicate_chunks() {
+        let numbered = |sequence_id: u64, data: &[u8], end: bool| {
+            let data = base64::engine::general_purpose::STANDARD.encode(data);
+            format!(
+                r#"<rsp:Stream Name="stdout" CommandId="{COMMAND_ID}" SequenceID="{sequence_id}" End="{end}">{data}</rsp:Stream>"#
+            )
+        };
+        let first = receive_response(&format!(
+            "{}{}{}",
+            numbered(0, b"hello ", false),
+            numbered(1, b"world", false),
+            command_state("Running", None)
+        ));
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        // The first response again after a retry, then one overlapping it
+        let server = Server::new(vec![
+            first.clone(),
+            first,
+            receive_response(&format!(
+                "{}{}{}{}",
+                numbered(1, b"world", false),
+                numbered(2, b"!", true),
+                stream("stderr", b"", true),
+                command_state("Done", Some(0))
+            )),
+        ]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        let mut command = block_on(shell.command("echo", &["hello"])).unwrap();
+
+        let mut stdout = String::new();
+        block_on(command.stdout.read_to_string(&mut stdout)).unwrap();
+        assert_eq!(stdout, "hello world!");
+        assert!(block_on(command.wait()).unwrap().success());
+    }
+
+    #[test]
+    fn test_concurrent_commands() {
+        const FIRST: &str = "11111111-0000-4000-8000-000000000001";
+        const SECOND: &str = "22222222-0000-4000-8000-000000000002";
+        let chunk = |command_id: &str, name: &str, data: &[u8], end: bool| {
+            let data = base64::engine::general_purpose::STANDARD.encode(data);
+            format!(
+                r#"<rsp:Stream Name="{name}" CommandId="{command_id}" End="{end}">{data}</rsp:Stream>"#
+            )
+        };
+        let done = |command_id: &str, exit_code: i32| {
+            format!(
+                r#"{}<rsp:CommandState CommandId="{command_id}" State="http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandState/Done"><rsp:ExitCode>{exit_code}</rsp:ExitCode></rsp:CommandState>"#,
+                chunk(command_id, "stderr", b"", true)
+            )
+        };
+
+        let created = fs::read_to_string("tests/resources/resource_created.xml").unwrap();
+        let started = RefCell::new(VecDeque::from([FIRST, SECOND]));
+        // The Receives of each command in order, the last one of the first command also
+        // carries output of the second command
+        let receives = RefCell::new(vec![
+            (
+                FIRST,
+                VecDeque::from([
+                    receive_response(&chunk(FIRST, "stdout", b"first ", false)),
+                    receive_response(&format!(
+                        "{}{}{}",
+                        chunk(FIRST, "stdout", b"done", true),
+                        chunk(SECOND, "stdout", b"second ", false),
+                        done(FIRST, 1)
+                    )),
+                ]),
+            ),
+            (
+                SECOND,
+                VecDeque::from([receive_response(&format!(
+                    "{}{}",
+                    chunk(SECOND, "stdout", b"done", true),
+                    done(SECOND, 0)
+                ))]),
+            ),
+        ]);
+        let received = RefCell::new(Vec::new());
+        let exchange = |request: String| {
+            let response = if request.contains("shell/Command<") {
+                COMMAND_RESPONSE.replace(COMMAND_ID, started.borrow_mut().pop_front().unwrap())
+            } else if request.contains("shell/Receive<") {
+                let mut receives = receives.borrow_mut();
+                let (command_id, responses) = receives
+                    .iter_mut()
+                    .find(|(command_id, _)| request.contains(*command_id))
+                    .expect("The Receive must be of a started command");
+                received.borrow_mut().push(*command_id);
+                responses.pop_front().expect("No Receive is expected")
+            } else {
+                created.clone()
+            };
+            async move { Ok(response) }
+        };
+
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        let commands = shell.commands();
+
+        let mut first = block_on(commands.start("ping", &["localhost"])).unwrap();
+        let mut output = [0; 64];
+        let read = block_on(first.stdout.read(&mut output)).unwrap();
+        assert_eq!(&output[..read], b"first ");
+
+        // Started while the first one is still streaming
+        let mut second = block_on(commands.start("dir", &[])).unwrap();
+        assert_eq!(second.command_id().to_string().to_uppercase(), SECOND);
+        assert_eq!(commands.command_ids().len(), 2);
+
+        let mut stdout = String::new();
+        block_on(first.stdout.read_to_string(&mut stdout)).unwrap();
+        assert_eq!(stdout, "done");
+        assert_eq!(block_on(first.wait()).unwrap().code, 1);
+        drop(first);
+        assert_eq!(
+            commands.command_ids(),
+            [uuid::Uuid::parse_str(SECOND).unwrap()]
+        );
+
+        // Its output that came with a Receive of the first command is kept
+        let mut stdout = String::new();
+        block_on(second.stdout.read_to_string(&mut stdout)).unwrap();
+        assert_eq!(stdout, "second done");
+        assert_eq!(block_on(second.wait()).unwrap().code, 0);
+        drop(second);
+        drop(commands);
+
+        assert_eq!(*received.borrow(), [FIRST, FIRST, SECOND]);
+    }
+
+    #[test]
+    fn test_command_raw_chunks() {
+        let first: Vec<u8> = vec![0xFF, 0xFE, 0x00, 0x80, b'\r', b'\n'];
+        let second: Vec<u8> = (0..=255).collect();
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let server = Server::new(vec![
+            receive_response(&format!(
+                "{}{}",
+                stream("stdout", &first, false),
+                stream("stdout", &second, false)
+            )),
+            receive_response(&format!(
+                "{}{}{}",
+                stream("stdout", b"", true),
+                stream("stderr", b"", true),
+                command_state("Done", Some(0))
+            )),
+        ]);
+        let exchange = |request: String| {
+            let response = server.answer(request);
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        let mut command = block_on(shell.command("certutil", &["-encodehex"])).unwrap();
+
+        // The chunks as received, the bytes of the first one read are not given again
+        let mut read = [0; 2];
+        block_on(command.stdout.read_exact(&mut read)).unwrap();
+        assert_eq!(read, [0xFF, 0xFE]);
+        assert_eq!(
+            block_on(command.stdout.chunk()).unwrap().unwrap(),
+            first[2..]
+        );
+        assert_eq!(block_on(command.stdout.chunk()).unwrap().unwrap(), second);
+        assert_eq!(block_on(command.stdout.chunk()).unwrap(), None);
+        assert_eq!(block_on(command.stderr.chunk()).unwrap(), None);
+        assert!(block_on(command.wait()).unwrap().success());
+    }
+}
@@ -0,0 +1,225 @@
+use std::fs;
+
+use protocol_winrm::{
+    error::ProtocolError,
+    rsp::receive::{
+        CommandProgress, CommandStatus, ExitStatus, ReceiveRequest, ReceiveResponse,
+        StreamSequences,
+    },
+    ws_management::WsMan,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHELL_ID: &str = "0A1B2C3D-0000-4000-8000-000000000001";
+    const COMMAND_ID: &str = "9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C";
+
+    fn receive_response(body: &str) -> String {
+        format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+            <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/ReceiveResponse</a:Action></s:Header>
+            <s:Body><rsp:ReceiveResponse>{body}</rsp:ReceiveResponse></s:Body>
+        </s:Envelope>"#
+        )
+    }
+
+    #[test]
+    fn test_receive_request() {
+        let ws_man = WsMan::builder()
+            .to("http://localhost:5985/wsman".to_string())
+            .build();
+        let request = ReceiveRequest::builder()
+            .shell_id(SHELL_ID)
+            .command_id(uuid::Uuid::parse_str(COMMAND_ID).unwrap())
+            .build();
+        let xml = ws_man.receive(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let texts: Vec<_> = document
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&(
+            "Action",
+            "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Receive"
+        )));
+        assert!(texts.contains(&("Selector", SHELL_ID)));
+        assert!(texts.contains(&("DesiredStream", "stdout stderr")));
+
+        let desired_stream = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "DesiredStream")
+            .unwrap();
+        assert_eq!(desired_stream.attribute("CommandId"), Some(COMMAND_ID));
+    }
+
+    #[test]
+    fn test_receive_running() {
+        let xml = receive_response(&format!(
+            r#"<rsp:Stream Name="stdout" CommandId="{COMMAND_ID}">aGVsbG8g</rsp:Stream>
+            <rsp:Stream Name="stderr" CommandId="{COMMAND_ID}">b29wcw==</rsp:Stream>
+            <rsp:Stream Name="stdout" CommandId="{COMMAND_ID}">d29ybGQ=</rsp:Stream>
+            <rsp:CommandState CommandId="{COMMAND_ID}" State="http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandState/Running"/>"#
+        ));
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = ReceiveResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(response.stdout(), b"hello world");
+        assert_eq!(response.stderr(), b"oops");
+        assert_eq!(response.chunks().len(), 3);
+        assert_eq!(
+            response.chunks()[0].command_id,
+            Some(uuid::Uuid::parse_str(COMMAND_ID).unwrap())
+        );
+        assert_eq!(response.state(), Some(&CommandStatus::Running));
+        assert!(!response.is_done());
+        assert_eq!(response.exit_code(), None);
+    }
+
+    #[test]
+    fn test_receive_done() {
+        let xml = receive_response(&format!(
+            r#"<rsp:Stream Name="stdout" CommandId="{COMMAND_ID}" End="true"></rsp:Stream>
+            <rsp:Stream Name="stderr" CommandId="{COMMAND_ID}" End="true"></rsp:Stream>
+            <rsp:CommandState CommandId="{COMMAND_ID}" State="http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandState/Done"><rsp:ExitCode>3</rsp:ExitCode></rsp:CommandState>"#
+        ));
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = ReceiveResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert!(response.chunks().iter().all(|chunk| chunk.end));
+        assert!(response.stdout().is_empty());
+        assert!(response.is_done());
+        assert_eq!(response.exit_code(), Some(3));
+    }
+
+    #[test]
+    fn test_receive_shell_output() {
+        let xml = fs::read_to_string("tests/resources/receive_response.xml").unwrap();
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = ReceiveResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(response.chunks().len(), 1);
+        assert!(!response.stdout().is_empty());
+        assert_eq!(response.state(), None);
+    }
+
+    #[test]
+    fn test_receive_errors() {
+        let xml = receive_response(r#"<rsp:Stream Name="stdout">not base64!</rsp:Stream>"#);
+        let document = xml::parser::parse(&xml).unwrap();
+        assert!(matches!(
+            ReceiveResponse::from_node(document.root_element(), "en-US"),
+            Err(ProtocolError::XmlParsingError(_))
+        ));
+
+        let fault = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Receiver</s:Value><s:Subcode><s:Value>w:TimedOut</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The WS-Management service cannot complete the operation within the time specified in OperationTimeout.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let document = xml::parser::parse(fault).unwrap();
+        assert!(matches!(
+            ReceiveResponse::from_node(document.root_element(), "en-US"),
+            Err(ProtocolError::WsmanFault { .. })
+        ));
+    }
+
+    fn command_state(state: &str, exit_code: Option<&str>) -> ReceiveResponse {
+        let exit_code = exit_code
+            .map(|exit_code| format!("<rsp:ExitCode>{exit_code}</rsp:ExitCode>"))
+            .unwrap_or_default();
+        let xml = receive_response(&format!(
+            r#"<rsp:CommandState CommandId="{COMMAND_ID}" State="http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandState/{state}">{exit_code}</rsp:CommandState>"#
+        ));
+        let document = xml::parser::parse(&xml).unwrap();
+        ReceiveResponse::from_node(document.root_element(), "en-US").unwrap()
+    }
+
+    #[test]
+    fn test_exit_status() {
+        let done = command_state("Done", Some("0"));
+        assert_eq!(
+            done.exit_status(),
+            Some(ExitStatus {
+                code: 0,
+                state: CommandStatus::Done
+            })
+        );
+        assert!(done.exit_status().unwrap().success());
+
+        // The DWORD of Windows, unsigned or signed
+        assert_eq!(
+            command_state("Done", Some("4294967295")).exit_code(),
+            Some(-1)
+        );
+        assert_eq!(command_state("Done", Some("-1")).exit_code(), Some(-1));
+        assert_eq!(
+            command_state("Done", Some("3221225786")).exit_code(),
+            Some(0xC000013Au32 as i32)
+        );
+
+        assert!(command_state("Running", None).exit_status().is_none());
+    }
+
+    #[test]
+    fn test_exit_code_after_done() {
+        let mut progress = CommandProgress::new();
+        assert!(progress.needs_receive());
+
+        assert_eq!(progress.update(&command_state("Running", None)), None);
+        assert_eq!(progress.state(), Some(&CommandStatus::Running));
+
+        // Done without the exit code, it comes with the next response
+        let done = command_state("Done", None);
+        assert_eq!(done.exit_status(), None);
+        assert_eq!(progress.update(&done), None);
+        assert!(progress.is_done());
+        assert!(progress.needs_receive());
+
+        let status = progress.update(&command_state("Done", Some("2"))).unwrap();
+        assert_eq!(status.code, 2);
+        assert_eq!(status.state, CommandStatus::Done);
+        assert!(!status.success());
+        assert!(!progress.needs_receive());
+    }
+
+    #[test]
+    fn test_stream_sequences() {
+        let xml = receive_response(&format!(
+            r#"<rsp:Stream Name="stdout" CommandId="{COMMAND_ID}" SequenceID="0">b25l</rsp:Stream>
+            <rsp:Stream Name="stderr" CommandId="{COMMAND_ID}" SequenceID="0">b29wcw==</rsp:Stream>
+            <rsp:Stream Name="stdout" CommandId="{COMMAND_ID}" SequenceID="1">dHdv</rsp:Stream>
+            <rsp:Stream Name="stdout" CommandId="{COMMAND_ID}">dW5udW1iZXJlZA==</rsp:Stream>"#
+        ));
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = ReceiveResponse::from_node(document.root_element(), "en-US").unwrap();
+        let sequence_ids: Vec<_> = response
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.sequence_id)
+            .collect();
+        assert_eq!(sequence_ids, [Some(0), Some(0), Some(1), None]);
+
+        let mut sequences = StreamSequences::new();
+        assert!(
+            response
+                .chunks()
+                .iter()
+                .all(|chunk| sequences.accept(chunk))
+        );
+
+        // A retransmission, only the chunks the server doesn't number are taken again
+        let accepted: Vec<_> = response
+            .chunks()
+            .iter()
+            .filter(|chunk| sequences.accept(chunk))
+            .map(|chunk| chunk.data.clone())
+            .collect();
+        assert_eq!(accepted, [b"unnumbered".to_vec()]);
+
+        let invalid = receive_response(r#"<rsp:Stream Name="stdout" SequenceID="x"></rsp:Stream>"#);
+        let document = xml::parser::parse(&invalid).unwrap();
+        assert!(matches!(
+            ReceiveResponse::from_node(document.root_element(), "en-US"),
+            Err(ProtocolError::XmlParsingError(_))
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+use protocol_winrm::{
+    cores::{Empty, ResourceCreated, Tag, envelope::Envelope},
+    soap::body::SoapBody,
+    ws_management::body::ResourceCreatedValue,
+};
+use xml::parser::XmlDeserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOURCE_CREATED: &str = r#"<x:ResourceCreated xmlns:x="http://schemas.xmlsoap.org/ws/2004/09/transfer" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:v="urn:example:vendor">
+        <a:Address>http://10.10.0.3:5985/wsman</a:Address>
+        <a:ReferenceParameters>
+            <w:ResourceURI>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/cmd</w:ResourceURI>
+            <w:SelectorSet><w:Selector Name="ShellId">2D6534D0-6B12-40E3-B773-CBA26459CFA8</w:Selector></w:SelectorSet>
+            <v:Route v:Hop="2">east</v:Route>
+        </a:ReferenceParameters>
+    </x:ResourceCreated>"#;
+
+    #[test]
+    fn test_unknown_reference_parameters_are_echoed() {
+        let document = xml::parser::parse(RESOURCE_CREATED).unwrap();
+        let created: Tag<'_, ResourceCreatedValue, ResourceCreated> =
+            Tag::from_node(document.root_element()).unwrap();
+        let parameters = &created.value.reference_parameters.value;
+
+        assert_eq!(parameters.unknown.len(), 1);
+        assert!(parameters.unknown[0].matches(Some("urn:example:vendor"), "Route"));
+
+        let xml = Envelope::builder()
+            .reference_parameters(parameters)
+            .body(SoapBody::builder().identify(Tag::new(Empty)).build())
+            .build()
+            .into_element()
+            .to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let header = document
+            .root_element()
+            .children()
+            .find(|child| child.tag_name().name() == "Header")
+            .unwrap();
+
+        let route = header
+            .children()
+            .find(|child| child.tag_name().name() == "Route")
+            .expect("The vendor parameter is echoed as a header");
+        assert_eq!(route.tag_name().namespace(), Some("urn:example:vendor"));
+        assert_eq!(route.attribute(("urn:example:vendor", "Hop")), Some("2"));
+        assert_eq!(route.text(), Some("east"));
+
+        assert!(xml.contains("2D6534D0-6B12-40E3-B773-CBA26459CFA8"));
+        assert!(xml.contains("http://schemas.microsoft.com/wbem/wsman/1/windows/shell/cmd"));
+    }
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use base64::Engine;
+use common::ws_man_with_max_envelope_size;
+use protocol_winrm::rsp::send::{SendRequest, SendResponse, StdinWriter};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHELL_ID: &str = "0A1B2C3D-0000-4000-8000-000000000001";
+    const COMMAND_ID: &str = "9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C";
+
+    fn command_id() -> uuid::Uuid {
+        uuid::Uuid::parse_str(COMMAND_ID).unwrap()
+    }
+
+    /// The decoded data and the `End` attribute of the stream of a Send envelope
+    fn sent(xml: &str) -> (Vec<u8>, bool) {
+        let document = xml::parser::parse(xml).expect("Built envelope must be valid XML");
+        let stream = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Stream")
+            .unwrap();
+        assert_eq!(stream.attribute("Name"), Some("stdin"));
+        assert_eq!(stream.attribute("CommandId"), Some(COMMAND_ID));
+
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(stream.text().unwrap_or_default())
+            .unwrap();
+        (data, stream.attribute("End") == Some("true"))
+    }
+
+    #[test]
+    fn test_send_request() {
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let request = SendRequest::builder()
+            .shell_id(SHELL_ID)
+            .command_id(command_id())
+            .data(&b"dir\r\n"[..])
+            .end(true)
+            .build();
+        let xml = ws_man.send(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).unwrap();
+        assert!(document.descendants().any(|node| node.text()
+            == Some("http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Send")));
+        assert!(
+            document
+                .descendants()
+                .any(|node| node.text() == Some(SHELL_ID))
+        );
+        assert_eq!(sent(&xml), (b"dir\r\n".to_vec(), true));
+    }
+
+    #[test]
+    fn test_send_request_to_the_shell() {
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let request = SendRequest::builder()
+            .shell_id(SHELL_ID)
+            .data(&b"host response"[..])
+            .build();
+        let xml = ws_man.send(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).unwrap();
+        let stream = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Stream")
+            .unwrap();
+        assert_eq!(stream.attribute("Name"), Some("stdin"));
+        assert_eq!(stream.attribute("CommandId"), None);
+    }
+
+    #[test]
+    fn test_stdin_writer_splits_input() {
+        let max_envelope_size = 4096;
+        let ws_man = ws_man_with_max_envelope_size(max_envelope_size);
+        let mut writer = StdinWriter::new(&ws_man, SHELL_ID, command_id());
+        let chunk_size = writer.chunk_size();
+        assert!(chunk_size > 0 && chunk_size < max_envelope_size as usize);
+
+        let input: Vec<u8> = (0..chunk_size * 2 + chunk_size / 2)
+            .map(|i| (i % 251) as u8)
+            .collect();
+
+        let mut envelopes = writer.write(&input[..10]);
+        assert!(envelopes.is_empty());
+        envelopes.extend(writer.write(&input[10..]));
+        assert_eq!(envelopes.len(), 2);
+        envelopes.push(writer.close());
+
+        let mut received = Vec::new();
+        for (index, envelope) in envelopes.into_iter().enumerate() {
+            let element = envelope.into_element();
+            assert!(element.estimated_size() <= max_envelope_size as usize);
+
+            let (data, end) = sent(&element.to_string());
+            assert_eq!(end, index == 2);
+            received.extend(data);
+        }
+        assert_eq!(received, input);
+    }
+
+    #[test]
+    fn test_stdin_writer_flush() {
+        let ws_man = ws_man_with_max_envelope_size(512000);
+        let mut writer = StdinWriter::new(&ws_man, SHELL_ID, command_id());
+        assert!(writer.flush().is_none());
+
+        assert!(writer.write(b"y\r\n").is_empty());
+        let flushed = writer.flush().unwrap();
+        assert_eq!(
+            sent(&flushed.into_element().to_string()),
+            (b"y\r\n".to_vec(), false)
+        );
+
+        let closed = writer.close();
+        assert_eq!(sent(&closed.into_element().to_string()), (Vec::new(), true));
+    }
+
+    #[test]
+    fn test_send_response() {
+        let xml = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+            <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/SendResponse</a:Action></s:Header>
+            <s:Body><rsp:SendResponse/></s:Body>
+        </s:Envelope>"#;
+        let document = xml::parser::parse(xml).unwrap();
+        assert!(SendResponse::from_node(document.root_element(), "en-US").is_ok());
+
+        let wrong = xml.replace("shell/SendResponse", "shell/ReceiveResponse");
+        let document = xml::parser::parse(&wrong).unwrap();
+        assert!(SendResponse::from_node(document.root_element(), "en-US").is_err());
+    }
+}
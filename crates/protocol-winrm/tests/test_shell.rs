@@ -0,0 +1,735 @@
+mod common;
+
+use std::{cell::RefCell, fs, time::Duration};
+
+use common::{block_on, ws_man};
+use protocol_winrm::{
+    cores::{Shell, Tag, TagName},
+    error::ProtocolError,
+    rsp::{
+        command::{CommandRequest, CommandResponse, StdinMode},
+        compression::StreamCompression,
+        rsp::{EnvironmentValue, ShellValue},
+        shell::{CreateShellRequest, CreateShellResponse, DeleteShellRequest, ShellRenewal},
+    },
+    ws_management::{OptionSetValue, ResourceUri},
+};
+use xml::parser::{Node, XmlDeserialize};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_shell_request() {
+        let ws_man = ws_man();
+        let request = CreateShellRequest::builder()
+            .option_set(OptionSetValue::new().add_option("WINRS_NOPROFILE", "TRUE"))
+            .build();
+        let xml = ws_man.create_shell(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let texts: Vec<_> = document
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&(
+            "Action",
+            "http://schemas.xmlsoap.org/ws/2004/09/transfer/Create"
+        )));
+        assert!(texts.contains(&("ResourceURI", ResourceUri::WINDOWS_SHELL_CMD)));
+        assert!(texts.contains(&("Option", "TRUE")));
+        assert!(texts.contains(&("Option", "65001")));
+        assert!(texts.contains(&("InputStreams", "stdin")));
+        assert!(texts.contains(&("OutputStreams", "stdout stderr")));
+
+        let shell = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Shell")
+            .unwrap();
+        assert_eq!(
+            shell.tag_name().namespace(),
+            Some("http://schemas.microsoft.com/wbem/wsman/1/windows/shell")
+        );
+        assert!(shell.attribute("ShellId").is_none());
+    }
+
+    #[test]
+    fn test_create_shell_request_codepage() {
+        let ws_man = ws_man();
+        let codepage = |request: CreateShellRequest<'_>| {
+            let xml = ws_man.create_shell(request).into_element().to_string();
+            let document = xml::parser::parse(&xml).unwrap();
+            document
+                .descendants()
+                .find(|node| node.attribute("Name") == Some("WINRS_CODEPAGE"))
+                .and_then(|node| node.text())
+                .map(str::to_owned)
+        };
+
+        assert_eq!(
+            codepage(CreateShellRequest::builder().build()).as_deref(),
+            Some("65001")
+        );
+        assert_eq!(
+            codepage(CreateShellRequest::builder().codepage(1252).build()).as_deref(),
+            Some("1252")
+        );
+        assert_eq!(
+            codepage(
+                CreateShellRequest::builder()
+                    .option_set(OptionSetValue::new().add_option("WINRS_CODEPAGE", "437"))
+                    .build()
+            )
+            .as_deref(),
+            Some("437")
+        );
+    }
+
+    #[test]
+    fn test_create_shell_request_compression() {
+        let ws_man = ws_man();
+        let request = CreateShellRequest::builder()
+            .compression(StreamCompression::Xpress)
+            .build();
+        let xml = ws_man.create_shell(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).unwrap();
+        let compression_type = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "CompressionType")
+            .expect("Supported compression must be requested");
+        assert_eq!(compression_type.text(), Some("xpress"));
+        assert_eq!(
+            compression_type
+                .attribute(("http://www.w3.org/2003/05/soap-envelope", "mustUnderstand")),
+            Some("true")
+        );
+
+        let xml = ws_man
+            .create_shell(CreateShellRequest::builder().build())
+            .into_element()
+            .to_string();
+        assert!(!xml.contains("CompressionType"));
+    }
+
+    #[test]
+    fn test_create_shell_request_with_id() {
+        let ws_man = ws_man();
+        let request = CreateShellRequest::builder()
+            .resource_uri(ResourceUri::POWERSHELL)
+            .input_streams("stdin pr")
+            .output_streams("stdout")
+            .shell_id("07936B27-7752-4325-8B0D-E7A1E9448320")
+            .name("Runspace1")
+            .build();
+        let xml = ws_man.create_shell(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let shell = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Shell")
+            .unwrap();
+        assert_eq!(
+            shell.attribute("ShellId"),
+            Some("07936B27-7752-4325-8B0D-E7A1E9448320")
+        );
+        assert_eq!(shell.attribute("Name"), Some("Runspace1"));
+        assert!(
+            document
+                .descendants()
+                .any(|node| node.text() == Some(ResourceUri::POWERSHELL))
+        );
+    }
+
+    #[test]
+    fn test_create_shell_request_plugin() {
+        let ws_man = ws_man();
+        let resource_uri = ResourceUri::powershell_configuration("JEA.Maintenance").unwrap();
+        let request = CreateShellRequest::builder()
+            .resource_uri(resource_uri.as_str())
+            .input_streams("stdin pr")
+            .output_streams("stdout")
+            .creation_xml("AAAAAAAAAAEAAAAAAAAAAAMAAADKAgAAAAIAAQA=")
+            .build();
+        let xml = ws_man.create_shell(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let header = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "ResourceURI")
+            .unwrap();
+        assert_eq!(
+            header.text(),
+            Some("http://schemas.microsoft.com/powershell/JEA.Maintenance")
+        );
+        let creation_xml = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "creationXml")
+            .unwrap();
+        assert_eq!(
+            creation_xml.tag_name().namespace(),
+            Some("http://schemas.microsoft.com/powershell")
+        );
+        assert_eq!(
+            creation_xml.text(),
+            Some("AAAAAAAAAAEAAAAAAAAAAAMAAADKAgAAAAIAAQA=")
+        );
+    }
+
+    #[test]
+    fn test_create_shell_response() {
+        let xml = fs::read_to_string("tests/resources/resource_created.xml").unwrap();
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = CreateShellResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(
+            response.shell_id(),
+            Some("07936B27-7752-4325-8B0D-E7A1E9448320")
+        );
+        assert_eq!(response.owner(), Some("Administrator"));
+        assert_eq!(response.resource_uri(), Some(ResourceUri::POWERSHELL));
+        assert_eq!(response.idle_time_out(), Some(Duration::from_secs(7200)));
+        assert_eq!(
+            response.max_idle_time_out(),
+            Some(Duration::from_millis(2147483647))
+        );
+        assert_eq!(
+            response.compression_mode(),
+            Some(Some(StreamCompression::Xpress))
+        );
+        assert!(response.shell().is_some());
+    }
+
+    #[test]
+    fn test_create_shell_response_without_shell() {
+        let xml = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:x="http://schemas.xmlsoap.org/ws/2004/09/transfer" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+            <s:Header><a:Action>http://schemas.xmlsoap.org/ws/2004/09/transfer/CreateResponse</a:Action></s:Header>
+            <s:Body>
+                <x:ResourceCreated>
+                    <a:Address>http://localhost:5985/wsman</a:Address>
+                    <a:ReferenceParameters>
+                        <w:ResourceURI>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/cmd</w:ResourceURI>
+                        <w:SelectorSet><w:Selector Name="ShellId">0A1B2C3D</w:Selector></w:SelectorSet>
+                    </a:ReferenceParameters>
+                </x:ResourceCreated>
+            </s:Body>
+        </s:Envelope>"#;
+        let document = xml::parser::parse(xml).unwrap();
+        let response = CreateShellResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(response.shell_id(), Some("0A1B2C3D"));
+        assert_eq!(
+            response.resource_uri(),
+            Some(ResourceUri::WINDOWS_SHELL_CMD)
+        );
+        assert!(response.shell().is_none());
+        assert!(response.owner().is_none());
+        assert!(response.idle_time_out().is_none());
+
+        let fault = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>w:QuotaLimit</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The maximum number of concurrent shells for this user has been exceeded.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let document = xml::parser::parse(fault).unwrap();
+        assert!(matches!(
+            CreateShellResponse::from_node(document.root_element(), "en-US"),
+            Err(ProtocolError::WsmanFault { .. })
+        ));
+    }
+
+    /// The shell of `winrs -r:host -d:C:\Users\Public -env:PATH=C:\Tools -env:LANG=en_US.UTF-8`
+    const WINRS_SHELL: &str = r#"<rsp:Shell xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell"><rsp:Environment><rsp:Variable Name="PATH">C:\Tools</rsp:Variable><rsp:Variable Name="LANG">en_US.UTF-8</rsp:Variable></rsp:Environment><rsp:WorkingDirectory>C:\Users\Public</rsp:WorkingDirectory><rsp:Lifetime>PT3600.000S</rsp:Lifetime><rsp:IdleTimeOut>PT180.000S</rsp:IdleTimeOut><rsp:InputStreams>stdin</rsp:InputStreams><rsp:OutputStreams>stdout stderr</rsp:OutputStreams></rsp:Shell>"#;
+
+    /// The local names and texts of the children of `shell`, in document order
+    fn shell_children<'a>(shell: Node<'a, 'a>) -> Vec<(&'a str, Option<&'a str>)> {
+        shell
+            .descendants()
+            .skip(1)
+            .filter(|node| node.is_element())
+            .map(|node| (node.tag_name().name(), node.text()))
+            .collect()
+    }
+
+    fn shell_options() -> CreateShellRequest<'static> {
+        CreateShellRequest::builder()
+            .environment(
+                EnvironmentValue::new()
+                    .add_variable("PATH", r"C:\Tools")
+                    .add_variable("LANG", "en_US.UTF-8"),
+            )
+            .working_directory(r"C:\Users\Public")
+            .lifetime(Duration::from_secs(3600))
+            .idle_time_out(Duration::from_secs(180))
+            .build()
+    }
+
+    #[test]
+    fn test_shell_options_match_winrs() {
+        let ws_man = ws_man();
+        let xml = ws_man
+            .create_shell(shell_options())
+            .into_element()
+            .to_string();
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let built = document
+            .descendants()
+            .find(|node| Shell::matches_node(node))
+            .unwrap();
+
+        let capture = xml::parser::parse(WINRS_SHELL).unwrap();
+        let captured = capture.root_element();
+
+        assert_eq!(shell_children(built), shell_children(captured));
+        let names: Vec<_> = built
+            .descendants()
+            .filter_map(|node| node.attribute("Name"))
+            .collect();
+        assert_eq!(names, ["PATH", "LANG"]);
+    }
+
+    #[test]
+    fn test_shell_options_round_trip() {
+        let capture = xml::parser::parse(WINRS_SHELL).unwrap();
+        let captured = capture.root_element();
+        let shell: Tag<'_, ShellValue<'_>, Shell> = Tag::from_node(captured).unwrap();
+
+        let environment = &shell.value.environment.as_ref().unwrap().value;
+        assert_eq!(environment.get("PATH"), Some(r"C:\Tools"));
+        assert_eq!(environment.get("LANG"), Some("en_US.UTF-8"));
+        assert_eq!(
+            shell
+                .value
+                .working_directory
+                .as_ref()
+                .unwrap()
+                .value
+                .as_ref(),
+            r"C:\Users\Public"
+        );
+        assert_eq!(shell.value.lifetime.as_ref().unwrap().value.0, 3600.0);
+        assert_eq!(shell.value.idle_time_out.as_ref().unwrap().value.0, 180.0);
+
+        let xml = shell
+            .with_declaration(protocol_winrm::cores::Namespace::WsmanShell)
+            .into_element()
+            .to_string();
+        let rebuilt = xml::parser::parse(&xml).unwrap();
+        assert_eq!(
+            shell_children(rebuilt.root_element()),
+            shell_children(captured)
+        );
+    }
+
+    #[test]
+    fn test_create_shell_response_options() {
+        let xml = format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:x="http://schemas.xmlsoap.org/ws/2004/09/transfer" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+            <s:Header><a:Action>http://schemas.xmlsoap.org/ws/2004/09/transfer/CreateResponse</a:Action></s:Header>
+            <s:Body>
+                <x:ResourceCreated>
+                    <a:Address>http://localhost:5985/wsman</a:Address>
+                    <a:ReferenceParameters>
+                        <w:ResourceURI>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/cmd</w:ResourceURI>
+                        <w:SelectorSet><w:Selector Name="ShellId">0A1B2C3D</w:Selector></w:SelectorSet>
+                    </a:ReferenceParameters>
+                </x:ResourceCreated>
+                {}
+            </s:Body>
+        </s:Envelope>"#,
+            WINRS_SHELL
+        );
+        let document = xml::parser::parse(&xml).unwrap();
+        let response = CreateShellResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        assert_eq!(
+            response.environment().and_then(|env| env.get("PATH")),
+            Some(r"C:\Tools")
+        );
+        assert_eq!(response.working_directory(), Some(r"C:\Users\Public"));
+        assert_eq!(response.lifetime(), Some(Duration::from_secs(3600)));
+        assert_eq!(response.idle_time_out(), Some(Duration::from_secs(180)));
+    }
+
+    const SHELL_ID: &str = "0A1B2C3D-0000-4000-8000-000000000001";
+
+    fn command_texts(xml: &str) -> Vec<(String, String)> {
+        let document = xml::parser::parse(xml).expect("Built envelope must be valid XML");
+        document
+            .descendants()
+            .filter_map(|node| {
+                let text = node.text()?;
+                let name = match node.attribute("Name") {
+                    Some(name) => name,
+                    None => node.tag_name().name(),
+                };
+                Some((name.to_owned(), text.to_owned()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_command_request() {
+        let ws_man = ws_man();
+        let command_id = uuid::Uuid::parse_str("9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C").unwrap();
+        let request = CommandRequest::builder()
+            .shell_id(SHELL_ID)
+            .command("findstr")
+            .arguments(vec!["two words", "a&b", r#"say "hi""#])
+            .command_id(command_id)
+            .build();
+        let xml = ws_man.command(request).into_element().to_string();
+        let texts = command_texts(&xml);
+        let has = |name: &str, text: &str| texts.contains(&(name.to_owned(), text.to_owned()));
+
+        assert!(has(
+            "Action",
+            "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Command"
+        ));
+        assert!(has("ShellId", SHELL_ID));
+        assert!(has("WINRS_CONSOLEMODE_STDIN", "TRUE"));
+        assert!(has("WINRS_SKIP_CMD_SHELL", "FALSE"));
+        assert!(has("Command", "findstr"));
+        assert!(has("Arguments", r#""two words""#));
+        assert!(has("Arguments", "a^&b"));
+        assert!(has("Arguments", r#""say \"hi\"""#));
+
+        let document = xml::parser::parse(&xml).unwrap();
+        let command_line = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "CommandLine")
+            .unwrap();
+        assert_eq!(
+            command_line.attribute("CommandId"),
+            Some("9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C")
+        );
+    }
+
+    #[test]
+    fn test_command_request_without_cmd_shell() {
+        let ws_man = ws_man();
+        let request = CommandRequest::builder()
+            .shell_id(SHELL_ID)
+            .command(r"C:\Program Files\tool.exe")
+            .arguments(vec!["a&b"])
+            .stdin(StdinMode::Pipeline)
+            .skip_cmd_shell(true)
+            .build();
+        let xml = ws_man.command(request).into_element().to_string();
+        let texts = command_texts(&xml);
+        let has = |name: &str, text: &str| texts.contains(&(name.to_owned(), text.to_owned()));
+
+        assert!(has("WINRS_CONSOLEMODE_STDIN", "FALSE"));
+        assert!(has("WINRS_SKIP_CMD_SHELL", "TRUE"));
+        assert!(has("Command", r#""C:\Program Files\tool.exe""#));
+        assert!(has("Arguments", "a&b"));
+    }
+
+    #[test]
+    fn test_command_response() {
+        let xml = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+            <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandResponse</a:Action></s:Header>
+            <s:Body><rsp:CommandResponse><rsp:CommandId>9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C</rsp:CommandId></rsp:CommandResponse></s:Body>
+        </s:Envelope>"#;
+        let document = xml::parser::parse(xml).unwrap();
+        let response = CommandResponse::from_node(document.root_element(), "en-US").unwrap();
+        assert_eq!(
+            response.command_id(),
+            uuid::Uuid::parse_str("9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C").unwrap()
+        );
+
+        let empty = xml.replace(
+            "<rsp:CommandResponse><rsp:CommandId>9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C</rsp:CommandId></rsp:CommandResponse>",
+            "",
+        );
+        let document = xml::parser::parse(&empty).unwrap();
+        assert!(CommandResponse::from_node(document.root_element(), "en-US").is_err());
+    }
+
+    const DELETE_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing">
+        <s:Header><a:Action>http://schemas.xmlsoap.org/ws/2004/09/transfer/DeleteResponse</a:Action></s:Header>
+        <s:Body/>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_delete_shell_request() {
+        let ws_man = ws_man();
+        let request = DeleteShellRequest::builder()
+            .shell_id(SHELL_ID)
+            .resource_uri(ResourceUri::POWERSHELL)
+            .build();
+        let texts = command_texts(&ws_man.delete_shell(request).into_element().to_string());
+        let has = |name: &str, text: &str| texts.contains(&(name.to_owned(), text.to_owned()));
+
+        assert!(has(
+            "Action",
+            "http://schemas.xmlsoap.org/ws/2004/09/transfer/Delete"
+        ));
+        assert!(has("ResourceURI", ResourceUri::POWERSHELL));
+        assert!(has("ShellId", SHELL_ID));
+    }
+
+    #[test]
+    fn test_open_and_delete_shell() {
+        let ws_man = ws_man();
+        let created = fs::read_to_string("tests/resources/resource_created.xml").unwrap();
+        let sent = RefCell::new(Vec::new());
+        let exchange = |request: String| {
+            sent.borrow_mut().push(request);
+            let response = match sent.borrow().len() {
+                1 => created.clone(),
+                _ => DELETE_RESPONSE.to_owned(),
+            };
+            async move { Ok(response) }
+        };
+
+        let shell = block_on(
+            ws_man.open_shell(
+                CreateShellRequest::builder()
+                    .resource_uri(ResourceUri::POWERSHELL)
+                    .build(),
+                "en-US",
+                exchange,
+            ),
+        )
+        .unwrap();
+        assert_eq!(shell.shell_id(), "07936B27-7752-4325-8B0D-E7A1E9448320");
+        assert_eq!(shell.resource_uri(), ResourceUri::POWERSHELL);
+
+        block_on(shell.delete()).unwrap();
+
+        let sent = sent.into_inner();
+        assert_eq!(sent.len(), 2);
+        let texts = command_texts(&sent[1]);
+        assert!(texts.contains(&(
+            "ShellId".to_owned(),
+            "07936B27-7752-4325-8B0D-E7A1E9448320".to_owned()
+        )));
+        assert!(sent[1].contains("http://schemas.xmlsoap.org/ws/2004/09/transfer/Delete"));
+    }
+
+    #[test]
+    fn test_shell_deleted_on_drop() {
+        let ws_man = ws_man();
+        let created = fs::read_to_string("tests/resources/resource_created.xml").unwrap();
+        let exchange = |_: String| {
+            let response = created.clone();
+            async move { Ok(response) }
+        };
+
+        let dropped = RefCell::new(None);
+        let shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|request| *dropped.borrow_mut() = Some(request));
+        drop(shell);
+
+        let request = dropped
+            .into_inner()
+            .expect("Dropped shell must hand its Delete");
+        assert!(request.contains("http://schemas.xmlsoap.org/ws/2004/09/transfer/Delete"));
+        assert!(request.contains("07936B27-7752-4325-8B0D-E7A1E9448320"));
+    }
+
+    #[test]
+    fn test_delete_shell_fault() {
+        let ws_man = ws_man();
+        let created = fs::read_to_string("tests/resources/resource_created.xml").unwrap();
+        let fault = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>w:InvalidSelectors</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The shell was not found on the server.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let calls = RefCell::new(0);
+        let exchange = |_: String| {
+            *calls.borrow_mut() += 1;
+            let response = if *calls.borrow() == 1 {
+                created.clone()
+            } else {
+                fault.to_owned()
+            };
+            async move { Ok(response) }
+        };
+
+        let dropped = RefCell::new(false);
+        let shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| *dropped.borrow_mut() = true);
+
+        assert!(matches!(
+            block_on(shell.delete()),
+            Err(ProtocolError::WsmanFault { .. })
+        ));
+        assert!(
+            !dropped.into_inner(),
+            "A deleted shell is not deleted again"
+        );
+    }
+
+    #[test]
+    fn test_shell_startup_options() {
+        let ws_man = ws_man();
+        let created = fs::read_to_string("tests/resources/resource_created.xml").unwrap();
+        let sent = RefCell::new(Vec::new());
+        let exchange = |request: String| {
+            sent.borrow_mut().push(request);
+            let response = created.clone();
+            async move { Ok(response) }
+        };
+
+        let shell = block_on(
+            ws_man.open_shell(
+                CreateShellRequest::builder()
+                    .no_profile(true)
+                    .skip_cmd_shell(true)
+                    .build(),
+                "en-US",
+                exchange,
+            ),
+        )
+        .unwrap();
+
+        let create = command_texts(&sent.borrow()[0]);
+        assert!(create.contains(&("WINRS_NOPROFILE".to_owned(), "TRUE".to_owned())));
+
+        let command = ws_man
+            .command(shell.command_request("tool.exe", vec!["a&b"]))
+            .into_element()
+            .to_string();
+        let texts = command_texts(&command);
+        let has = |name: &str, text: &str| texts.contains(&(name.to_owned(), text.to_owned()));
+        assert!(has("WINRS_SKIP_CMD_SHELL", "TRUE"));
+        assert!(has("ShellId", "07936B27-7752-4325-8B0D-E7A1E9448320"));
+        assert!(has("Arguments", "a&b"));
+
+        let defaults = ws_man
+            .create_shell(CreateShellRequest::builder().build())
+            .into_element()
+            .to_string();
+        assert!(
+            command_texts(&defaults).contains(&("WINRS_NOPROFILE".to_owned(), "FALSE".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_shell_keep_alive() {
+        let ws_man = ws_man();
+        let created = fs::read_to_string("tests/resources/resource_created.xml").unwrap();
+        let received = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+            <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/ReceiveResponse</a:Action></s:Header>
+            <s:Body><rsp:ReceiveResponse/></s:Body>
+        </s:Envelope>"#;
+        let timed_out = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Receiver</s:Value><s:Subcode><s:Value>w:TimedOut</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The WS-Management service cannot complete the operation within the time specified in OperationTimeout.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let not_found = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>w:InvalidSelectors</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The shell was not found on the server.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let sent = RefCell::new(Vec::new());
+        let exchange = |request: String| {
+            sent.borrow_mut().push(request);
+            let response = match sent.borrow().len() {
+                1 => created.clone(),
+                2 => received.to_owned(),
+                3 => timed_out.to_owned(),
+                _ => not_found.to_owned(),
+            };
+            async move { Ok(response) }
+        };
+
+        let mut shell =
+            block_on(ws_man.open_shell(CreateShellRequest::builder().build(), "en-US", exchange))
+                .unwrap()
+                .delete_on_drop(|_| {});
+        assert_eq!(shell.idle_time_out(), Some(Duration::from_secs(7200)));
+        let due = shell
+            .keep_alive_at()
+            .unwrap()
+            .saturating_duration_since(std::time::Instant::now());
+        assert!(due > Duration::from_secs(3590) && due <= Duration::from_secs(3600));
+
+        // Kept alive by a response and by a timed out Receive, until the shell is gone
+        let slept = RefCell::new(Vec::new());
+        let result = block_on(shell.keep_alive(|duration| {
+            slept.borrow_mut().push(duration);
+            std::future::ready(())
+        }));
+        assert!(matches!(result, Err(ProtocolError::WsmanFault { .. })));
+        assert_eq!(slept.borrow().len(), 3);
+        assert!(slept.borrow()[1] > Duration::from_secs(3590));
+
+        let sent = sent.borrow();
+        assert_eq!(sent.len(), 4);
+        for request in &sent[1..] {
+            let texts = command_texts(request);
+            assert!(texts.contains(&(
+                "WSMAN_CMDSHELL_OPTION_KEEPALIVE".to_owned(),
+                "TRUE".to_owned()
+            )));
+            assert!(
+                request.contains("http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Receive")
+            );
+        }
+    }
+
+    #[test]
+    fn test_shell_renew() {
+        const SHELL_ID: &str = "07936B27-7752-4325-8B0D-E7A1E9448320";
+        const RENEWED: &str = "5D3B6A12-0000-4000-8000-00000000000A";
+        const RECREATED: &str = "5D3B6A12-0000-4000-8000-00000000000B";
+        let ws_man = ws_man();
+        let created = fs::read_to_string("tests/resources/resource_created.xml").unwrap();
+        let not_found = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>w:InvalidSelectors</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The request for the Windows Remote Shell with ShellId failed because the shell was not found on the server.</s:Text></s:Reason><s:Detail><f:WSManFault xmlns:f="http://schemas.microsoft.com/wbem/wsman/1/wsmanfault" Code="2150858843"/></s:Detail></s:Fault></s:Body></s:Envelope>"#;
+        let sent = RefCell::new(Vec::new());
+        let exchange = |request: String| {
+            sent.borrow_mut().push(request);
+            let response = match sent.borrow().len() {
+                1 => created.clone(),
+                // Recreated as its lifetime ends within the margin
+                2 => created.replace(SHELL_ID, RENEWED),
+                3 => DELETE_RESPONSE.to_owned(),
+                // Recreated as it is gone, deleting it fails
+                4 => not_found.to_owned(),
+                5 => created.replace(SHELL_ID, RECREATED),
+                _ => not_found.to_owned(),
+            };
+            async move { Ok(response) }
+        };
+        let request = || {
+            CreateShellRequest::builder()
+                .lifetime(Duration::from_secs(60))
+                .build()
+        };
+
+        let mut shell = block_on(ws_man.open_shell(request(), "en-US", exchange))
+            .unwrap()
+            .delete_on_drop(|_| {});
+        assert_eq!(shell.lifetime(), Some(Duration::from_secs(60)));
+        let expires_in = shell
+            .expires_at()
+            .unwrap()
+            .saturating_duration_since(std::time::Instant::now());
+        assert!(expires_in > Duration::from_secs(50) && expires_in <= Duration::from_secs(60));
+
+        let renewal = block_on(shell.renew(request(), Duration::from_secs(120))).unwrap();
+        assert_eq!(
+            renewal,
+            ShellRenewal::Recreated {
+                previous_shell_id: SHELL_ID.to_owned()
+            }
+        );
+        assert_eq!(shell.shell_id(), RENEWED);
+        {
+            let sent = sent.borrow();
+            assert!(sent[2].contains("http://schemas.xmlsoap.org/ws/2004/09/transfer/Delete"));
+            assert!(sent[2].contains(SHELL_ID));
+        }
+
+        let renewal = block_on(shell.renew(request(), Duration::ZERO)).unwrap();
+        assert_eq!(
+            renewal,
+            ShellRenewal::Recreated {
+                previous_shell_id: RENEWED.to_owned()
+            }
+        );
+        assert_eq!(shell.shell_id(), RECREATED);
+        assert_eq!(sent.borrow().len(), 6);
+
+        // The shell gone on the server is surfaced, not as a generic fault
+        let kept_alive = block_on(shell.send_keep_alive());
+        assert!(matches!(
+            kept_alive,
+            Err(ProtocolError::ShellExpired { shell_id }) if shell_id == RECREATED
+        ));
+    }
+}
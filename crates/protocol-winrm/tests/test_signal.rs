@@ -0,0 +1,77 @@
+use protocol_winrm::{
+    rsp::signal::{SignalCode, SignalRequest, SignalResponse},
+    ws_management::WsMan,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHELL_ID: &str = "0A1B2C3D-0000-4000-8000-000000000001";
+    const COMMAND_ID: &str = "9EC885D6-F5A4-4771-9D47-4BDF7DAAEA8C";
+
+    #[test]
+    fn test_signal_request() {
+        let ws_man = WsMan::builder()
+            .to("http://localhost:5985/wsman".to_string())
+            .build();
+        let request = SignalRequest::builder()
+            .shell_id(SHELL_ID)
+            .command_id(uuid::Uuid::parse_str(COMMAND_ID).unwrap())
+            .code(SignalCode::CtrlC)
+            .build();
+        let xml = ws_man.signal(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let texts: Vec<_> = document
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&(
+            "Action",
+            "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Signal"
+        )));
+        assert!(texts.contains(&("Selector", SHELL_ID)));
+        assert!(texts.contains(&(
+            "Code",
+            "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/signal/ctrl_c"
+        )));
+
+        let signal = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Signal")
+            .unwrap();
+        assert_eq!(signal.attribute("CommandId"), Some(COMMAND_ID));
+    }
+
+    #[test]
+    fn test_signal_code_uri() {
+        for code in [
+            SignalCode::Terminate,
+            SignalCode::CtrlC,
+            SignalCode::CtrlBreak,
+            SignalCode::PsCrtlC,
+        ] {
+            assert_eq!(SignalCode::from_uri(code.as_uri()), Some(code));
+        }
+        assert_eq!(
+            SignalCode::PsCrtlC.as_uri(),
+            "http://schemas.microsoft.com/powershell/signal/crtl_c"
+        );
+        assert_eq!(SignalCode::from_uri("http://example.com/signal/hup"), None);
+    }
+
+    #[test]
+    fn test_signal_response() {
+        let xml = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+            <s:Header><a:Action>http://schemas.microsoft.com/wbem/wsman/1/windows/shell/SignalResponse</a:Action></s:Header>
+            <s:Body><rsp:SignalResponse/></s:Body>
+        </s:Envelope>"#;
+        let document = xml::parser::parse(xml).unwrap();
+        assert!(SignalResponse::from_node(document.root_element(), "en-US").is_ok());
+
+        let fault = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value></s:Code><s:Reason><s:Text xml:lang="en-US">The request for the Windows Remote Shell with ShellId 0A1B2C3D failed because the shell was not found on the server.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let document = xml::parser::parse(fault).unwrap();
+        assert!(SignalResponse::from_node(document.root_element(), "en-US").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use protocol_winrm::{
+    cores::{Empty, Namespace, Tag, envelope::Envelope},
+    soap::{SoapFault, SoapVersion, body::SoapBody, header::SoapHeaders},
+    ws_management::WsmanFaultKind,
+};
+use xml::parser::XmlDeserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOAP12_FAULT: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:f="http://schemas.microsoft.com/wbem/wsman/1/wsmanfault"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Receiver</s:Value><s:Subcode><s:Value>w:TimedOut</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The WS-Management service cannot complete the operation within the time specified in OperationTimeout.</s:Text><s:Text xml:lang="fr-FR">Délai dépassé.</s:Text></s:Reason><s:Detail><f:WSManFault Code="2150858793"/></s:Detail></s:Fault></s:Body></s:Envelope>"#;
+
+    const SOAP11_FAULT: &str = r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Body><s:Fault><faultcode>w:AccessDenied</faultcode><faultstring xml:lang="en-US">Access is denied.</faultstring><detail/></s:Fault></s:Body></s:Envelope>"#;
+
+    #[test]
+    fn test_soap11_envelope_round_trip() {
+        let envelope = Envelope::builder()
+            .to("http://localhost:5985/wsman")
+            .action("http://schemas.xmlsoap.org/ws/2004/09/transfer/Get")
+            .soap_version(SoapVersion::Soap11)
+            .body(SoapBody::builder().identify(Tag::new(Empty)).build())
+            .build();
+
+        let xml = envelope.into_element().to_string();
+        assert!(xml.contains(Namespace::SoapEnvelope2000.uri()));
+        assert!(!xml.contains(Namespace::SoapEnvelope2003.uri()));
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let parsed: Envelope<'_, SoapHeaders<'_>, SoapBody<'_>> =
+            Envelope::from_node(document.root_element()).expect("Failed to parse envelope");
+
+        assert_eq!(parsed.soap_version, SoapVersion::Soap11);
+        assert_eq!(
+            parsed.header.value.to.as_ref().unwrap().value.as_ref(),
+            "http://localhost:5985/wsman"
+        );
+    }
+
+    #[test]
+    fn test_soap12_fault() {
+        let document = xml::parser::parse(SOAP12_FAULT).unwrap();
+        let fault = SoapFault::find(document.root_element())
+            .expect("The body carries a fault")
+            .unwrap();
+
+        assert_eq!(fault.version, SoapVersion::Soap12);
+        assert!(
+            fault
+                .code
+                .as_ref()
+                .unwrap()
+                .matches(Some(Namespace::SoapEnvelope2003.uri()), "Receiver")
+        );
+        assert_eq!(fault.wsman_code, Some(2150858793));
+        assert_eq!(fault.reason("fr-CA"), Some("Délai dépassé."));
+        assert_eq!(fault.kind(), WsmanFaultKind::OperationTimeout);
+    }
+
+    #[test]
+    fn test_soap11_fault_in_default_namespace() {
+        let xml = r#"<Envelope xmlns="http://schemas.xmlsoap.org/soap/envelope/" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><Body><Fault><faultcode>w:AccessDenied</faultcode><faultstring xml:lang="en-US">Access is denied.</faultstring><detail><f:WSManFault xmlns:f="http://schemas.microsoft.com/wbem/wsman/1/wsmanfault" Code="5"/></detail></Fault></Body></Envelope>"#;
+        let document = xml::parser::parse(xml).unwrap();
+        let fault = SoapFault::find(document.root_element())
+            .expect("The body carries a fault")
+            .unwrap();
+
+        assert_eq!(fault.version, SoapVersion::Soap11);
+        assert!(fault.code.as_ref().unwrap().matches(
+            Some(Namespace::DmtfWsmanSchema.uri()),
+            "AccessDenied"
+        ));
+        assert_eq!(fault.reason("en-US"), Some("Access is denied."));
+        assert_eq!(fault.wsman_code, Some(5));
+        assert_eq!(fault.kind(), WsmanFaultKind::AccessDenied);
+    }
+
+    #[test]
+    fn test_soap11_fault() {
+        let document = xml::parser::parse(SOAP11_FAULT).unwrap();
+        let fault = SoapFault::find(document.root_element())
+            .expect("The body carries a fault")
+            .unwrap();
+
+        assert_eq!(fault.version, SoapVersion::Soap11);
+        assert_eq!(fault.reason("en-US"), Some("Access is denied."));
+        assert_eq!(fault.kind(), WsmanFaultKind::AccessDenied);
+        assert!(matches!(
+            fault.into_error("en-US"),
+            protocol_winrm::error::ProtocolError::WsmanFault {
+                kind: WsmanFaultKind::AccessDenied,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,48 @@
+use protocol_winrm::cores::{Tag, TagSearch, Text, tag_name::*};
+use xml::parser::XmlDeserialize;
+
+const ENVELOPE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+    <s:Header/>
+    <s:Body>
+        <rsp:Shell>
+            <rsp:ShellId>2C2B4DA8-4F2C-4C3B-9D8A-2E9E9A1E0F13</rsp:ShellId>
+        </rsp:Shell>
+    </s:Body>
+</s:Envelope>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_descendant_tag() {
+        let document = xml::parser::parse(ENVELOPE).unwrap();
+        let shell_id: Tag<'_, Text<'_>, ShellId> =
+            Tag::from_descendant(document.root_element(), TagSearch::DEFAULT_MAX_DEPTH).unwrap();
+
+        assert_eq!(
+            shell_id.value.as_ref(),
+            "2C2B4DA8-4F2C-4C3B-9D8A-2E9E9A1E0F13"
+        );
+    }
+
+    #[test]
+    fn test_descendant_search_is_bounded() {
+        let document = xml::parser::parse(ENVELOPE).unwrap();
+        let shell_id: Result<Tag<'_, Text<'_>, ShellId>, _> =
+            Tag::from_descendant(document.root_element(), 2);
+        assert!(shell_id.is_err());
+
+        let shell_id: Result<Tag<'_, Text<'_>, ShellId>, _> =
+            Tag::from_descendant(document.root_element(), 3);
+        assert!(shell_id.is_ok());
+    }
+
+    #[test]
+    fn test_root_search_is_the_default() {
+        let document = xml::parser::parse(ENVELOPE).unwrap();
+        let shell_id: Result<Tag<'_, Text<'_>, ShellId>, _> =
+            Tag::from_node(document.root_element());
+        assert!(shell_id.is_err());
+    }
+}
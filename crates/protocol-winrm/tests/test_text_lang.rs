@@ -0,0 +1,50 @@
+use protocol_winrm::cores::{Tag, Text, tag_name::*};
+use xml::parser::XmlDeserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_lang_round_trip() {
+        let xml = r#"<w:Locale xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xml:lang="fr-CA">Accès refusé</w:Locale>"#;
+        let document = xml::parser::parse(xml).unwrap();
+        let tag: Tag<'_, Text<'_>, Locale> = Tag::from_node(document.root_element()).unwrap();
+
+        assert_eq!(tag.value.as_ref(), "Accès refusé");
+        assert_eq!(tag.value.lang(), Some("fr-CA"));
+
+        let element = xml::builder::Element::new("Text").set_text("ignored");
+        let rebuilt = protocol_winrm::cores::TagValue::append_to_element(tag.value, element);
+        assert_eq!(
+            rebuilt.to_string(),
+            r#"<Text xml:lang="fr-CA">Accès refusé</Text>"#
+        );
+    }
+
+    #[test]
+    fn test_text_without_lang() {
+        let xml = r#"<w:Locale xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">en-US</w:Locale>"#;
+        let document = xml::parser::parse(xml).unwrap();
+        let tag: Tag<'_, Text<'_>, Locale> = Tag::from_node(document.root_element()).unwrap();
+
+        assert_eq!(tag.value.lang(), None);
+    }
+
+    #[test]
+    fn test_select_localized_text() {
+        let texts = [
+            Text::from("Access is denied.").with_lang("en-US"),
+            Text::from("Accès refusé.").with_lang("fr-FR"),
+            Text::from("Zugriff verweigert."),
+        ];
+
+        let selected = |language| Text::select_localized(&texts, language).map(AsRef::as_ref);
+
+        assert_eq!(selected("fr-FR"), Some("Accès refusé."));
+        assert_eq!(selected("FR-ca"), Some("Accès refusé."));
+        assert_eq!(selected("en"), Some("Access is denied."));
+        assert_eq!(selected("ja-JP"), Some("Zugriff verweigert."));
+        assert_eq!(Text::select_localized(&[], "en-US"), None);
+    }
+}
@@ -0,0 +1,258 @@
+mod common;
+
+use common::ws_man;
+use protocol_winrm::{
+    cores::{Empty, Tag, TagName},
+    define_custom_tagname,
+    error::ProtocolError,
+    soap::body::SoapBody,
+    ws_management::{
+        CreateRequest, CreateResponse, DeleteRequest, DeleteResponse, GetRequest, GetResponse,
+        PutRequest, PutResponse, ResourceUri, SelectorSetValue, WsmanFaultKind,
+        fragment::XmlFragmentValue,
+    },
+};
+
+define_custom_tagname!(
+    Win32Service,
+    "Win32_Service",
+    Some("http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Service")
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GET_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Service">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/transfer/GetResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <p:Win32_Service>
+                <p:Name>WinRM</p:Name>
+                <p:State>Running</p:State>
+            </p:Win32_Service>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_get_request() {
+        let ws_man = ws_man();
+        let resource_uri = ResourceUri::wmi("root/cimv2", "Win32_Service").unwrap();
+
+        let request = GetRequest::builder()
+            .resource_uri(resource_uri.as_str())
+            .selector_set(SelectorSetValue::new().add_selector("Name", "WinRM"))
+            .build();
+        let xml = ws_man.get(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let texts: Vec<_> = document
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+
+        assert!(texts.contains(&(
+            "Action",
+            "http://schemas.xmlsoap.org/ws/2004/09/transfer/Get"
+        )));
+        assert!(texts.contains(&("ResourceURI", resource_uri.as_str())));
+        assert!(texts.contains(&("Selector", "WinRM")));
+
+        let body = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "Body")
+            .unwrap();
+        assert!(body.first_element_child().is_none());
+    }
+
+    #[test]
+    fn test_get_response_instance() {
+        let document = xml::parser::parse(GET_RESPONSE).unwrap();
+        let response = GetResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        let owned = response.to_owned_element();
+        assert!(owned.matches(Win32Service::NAMESPACE, "Win32_Service"));
+        assert_eq!(owned.children.len(), 2);
+        assert_eq!(owned.children[0].text.as_deref(), Some("WinRM"));
+
+        let typed: Tag<'_, XmlFragmentValue<'_>, Win32Service> = response.deserialize().unwrap();
+        assert_eq!(typed.value.nodes().len(), 2);
+    }
+
+    #[test]
+    fn test_get_response_errors() {
+        let wrong_action = GET_RESPONSE.replace("transfer/GetResponse", "transfer/PutResponse");
+        let document = xml::parser::parse(&wrong_action).unwrap();
+        assert!(matches!(
+            GetResponse::from_node(document.root_element(), "en-US"),
+            Err(ProtocolError::UnexpectedAction { .. })
+        ));
+
+        let fault = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Header/><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>w:InvalidSelectors</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">The selectors are invalid.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+        let document = xml::parser::parse(fault).unwrap();
+        let Err(ProtocolError::WsmanFault { kind, reason, .. }) =
+            GetResponse::from_node(document.root_element(), "en-US")
+        else {
+            panic!("Expected a WS-Management fault");
+        };
+        assert_eq!(kind, WsmanFaultKind::InvalidSelectors);
+        assert_eq!(reason, "The selectors are invalid.");
+    }
+
+    const CREATE_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:x="http://schemas.xmlsoap.org/ws/2004/09/transfer" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:v="urn:example:vendor">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/transfer/CreateResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <x:ResourceCreated>
+                <a:Address>http://localhost:5985/wsman</a:Address>
+                <a:ReferenceParameters>
+                    <w:ResourceURI>http://schemas.example.com/wbem/wsman/1/resource</w:ResourceURI>
+                    <w:SelectorSet><w:Selector Name="Id">42</w:Selector></w:SelectorSet>
+                    <v:Route>east</v:Route>
+                </a:ReferenceParameters>
+            </x:ResourceCreated>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_create_request() {
+        let ws_man = ws_man();
+        let request = CreateRequest::builder()
+            .resource_uri("http://schemas.example.com/wbem/wsman/1/resource")
+            .body(SoapBody::builder().identify(Tag::new(Empty)).build())
+            .build();
+        let xml = ws_man.create(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        assert!(document.descendants().any(
+            |node| node.text() == Some("http://schemas.xmlsoap.org/ws/2004/09/transfer/Create")
+        ));
+        assert!(
+            document
+                .descendants()
+                .any(|node| node.tag_name().name() == "Identify")
+        );
+    }
+
+    #[test]
+    fn test_delete_created_resource() {
+        let document = xml::parser::parse(CREATE_RESPONSE).unwrap();
+        let response = CreateResponse::from_node(document.root_element(), "en-US").unwrap();
+
+        let endpoint = response.endpoint();
+        assert_eq!(
+            endpoint.selector_set.value.get("Id").map(String::as_str),
+            Some("42")
+        );
+
+        let ws_man = ws_man();
+        let xml = ws_man
+            .delete(DeleteRequest::to_endpoint(endpoint))
+            .into_element()
+            .to_string();
+
+        let request = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let texts: Vec<_> = request
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&(
+            "Action",
+            "http://schemas.xmlsoap.org/ws/2004/09/transfer/Delete"
+        )));
+        assert!(texts.contains(&(
+            "ResourceURI",
+            "http://schemas.example.com/wbem/wsman/1/resource"
+        )));
+        assert!(texts.contains(&("Selector", "42")));
+        assert!(texts.contains(&("Route", "east")));
+
+        let delete_response = CREATE_RESPONSE.replace("CreateResponse", "DeleteResponse");
+        let document = xml::parser::parse(&delete_response).unwrap();
+        assert!(DeleteResponse::from_node(document.root_element(), "en-US").is_ok());
+    }
+
+    const FRAGMENT_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd" xmlns:cfg="http://schemas.microsoft.com/wbem/wsman/1/config/winrs">
+        <s:Header>
+            <a:Action>http://schemas.xmlsoap.org/ws/2004/09/transfer/GetResponse</a:Action>
+        </s:Header>
+        <s:Body>
+            <w:XmlFragment><cfg:MaxMemoryPerShellMB>1024</cfg:MaxMemoryPerShellMB></w:XmlFragment>
+        </s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_fragment_get() {
+        let ws_man = ws_man();
+        let request = GetRequest::builder()
+            .resource_uri(ResourceUri::CONFIG_WINRS)
+            .fragment("MaxMemoryPerShellMB")
+            .build();
+        let xml = ws_man.get(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let fragment_transfer = document
+            .descendants()
+            .find(|node| node.tag_name().name() == "FragmentTransfer")
+            .expect("Fragment Get must carry a FragmentTransfer header");
+        assert_eq!(fragment_transfer.text(), Some("MaxMemoryPerShellMB"));
+        assert_eq!(
+            fragment_transfer
+                .attribute(("http://www.w3.org/2003/05/soap-envelope", "mustUnderstand")),
+            Some("true")
+        );
+
+        let document = xml::parser::parse(FRAGMENT_RESPONSE).unwrap();
+        let response = GetResponse::from_node(document.root_element(), "en-US").unwrap();
+        let fragment = response.fragment().unwrap();
+        assert_eq!(fragment.nodes().len(), 1);
+        assert_eq!(fragment.nodes()[0].tag_name().name(), "MaxMemoryPerShellMB");
+        assert_eq!(fragment.nodes()[0].text(), Some("1024"));
+
+        let document = xml::parser::parse(GET_RESPONSE).unwrap();
+        let response = GetResponse::from_node(document.root_element(), "en-US").unwrap();
+        assert!(response.fragment().is_err());
+    }
+
+    #[test]
+    fn test_fragment_put() {
+        let ws_man = ws_man();
+        let request = PutRequest::builder()
+            .resource_uri(ResourceUri::CONFIG_WINRS)
+            .fragment("MaxMemoryPerShellMB/text()")
+            .body(
+                SoapBody::builder()
+                    .xml_fragment(Tag::new(XmlFragmentValue::Text("2048".into())))
+                    .build(),
+            )
+            .build();
+        let xml = ws_man.put(request).into_element().to_string();
+
+        let document = xml::parser::parse(&xml).expect("Built envelope must be valid XML");
+        let texts: Vec<_> = document
+            .descendants()
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?)))
+            .collect();
+        assert!(texts.contains(&(
+            "Action",
+            "http://schemas.xmlsoap.org/ws/2004/09/transfer/Put"
+        )));
+        assert!(texts.contains(&("FragmentTransfer", "MaxMemoryPerShellMB/text()")));
+        assert!(texts.contains(&("XmlFragment", "2048")));
+
+        let put_response = FRAGMENT_RESPONSE
+            .replace("transfer/GetResponse", "transfer/PutResponse")
+            .replace(">1024<", ">2048<");
+        let document = xml::parser::parse(&put_response).unwrap();
+        let response = PutResponse::from_node(document.root_element(), "en-US").unwrap();
+        let fragment = response.fragment().unwrap().unwrap();
+        assert_eq!(fragment.nodes()[0].text(), Some("2048"));
+
+        let empty = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing"><s:Header><a:Action>http://schemas.xmlsoap.org/ws/2004/09/transfer/PutResponse</a:Action></s:Header><s:Body/></s:Envelope>"#;
+        let document = xml::parser::parse(empty).unwrap();
+        let response = PutResponse::from_node(document.root_element(), "en-US").unwrap();
+        assert!(response.fragment().is_none());
+    }
+}
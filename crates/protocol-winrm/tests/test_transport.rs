@@ -0,0 +1,98 @@
+mod common;
+
+use std::cell::RefCell;
+
+use common::block_on;
+use protocol_winrm::{
+    error::ProtocolError,
+    soap::SoapVersion,
+    transport::{SoapRequest, SoapResponse, Transport},
+    ws_management::WsMan,
+};
+
+#[derive(Debug, thiserror::Error)]
+#[error("the connection was reset")]
+struct ConnectionReset;
+
+/// Answers the requests with the envelopes it was given, in order, and keeps the requests
+#[derive(Default)]
+struct CannedTransport {
+    responses: RefCell<Vec<String>>,
+    requests: RefCell<Vec<SoapRequest>>,
+}
+
+impl Transport for CannedTransport {
+    type Error = ConnectionReset;
+
+    async fn send(&self, request: SoapRequest) -> Result<SoapResponse, Self::Error> {
+        self.requests.borrow_mut().push(request);
+        let mut responses = self.responses.borrow_mut();
+        if responses.is_empty() {
+            return Err(ConnectionReset);
+        }
+        Ok(SoapResponse::new(responses.remove(0)))
+    }
+}
+
+/// A transport passed by value, as a borrowed one is one too
+async fn exchange_empty(transport: impl Transport) -> Result<String, ProtocolError> {
+    transport.exchange("<s:Envelope/>".to_string()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENUMERATE_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:n="http://schemas.xmlsoap.org/ws/2004/09/enumeration" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+        <s:Header><a:Action>http://schemas.xmlsoap.org/ws/2004/09/enumeration/EnumerateResponse</a:Action></s:Header>
+        <s:Body><n:EnumerateResponse><n:EnumerationContext>uuid:6F4CA1C3-6B25-4A36-8E3B-11D4A9F0C6B4</n:EnumerationContext><w:Items><rsp:Shell xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell"><rsp:ShellId>07936B27-7752-4325-8B0D-E7A1E9448320</rsp:ShellId><rsp:State>Disconnected</rsp:State></rsp:Shell></w:Items><w:EndOfSequence/></n:EnumerateResponse></s:Body>
+    </s:Envelope>"#;
+
+    #[test]
+    fn test_transport_exchange() {
+        let ws_man = WsMan::builder()
+            .to("http://localhost:5985/wsman".to_string())
+            .build();
+        let transport = CannedTransport::default();
+        transport
+            .responses
+            .borrow_mut()
+            .push(ENUMERATE_RESPONSE.to_string());
+
+        let shells =
+            block_on(ws_man.enumerate_shells("en-US", |envelope| transport.exchange(envelope)))
+                .unwrap();
+        assert_eq!(shells.len(), 1);
+        assert_eq!(shells[0].shell_id, "07936B27-7752-4325-8B0D-E7A1E9448320");
+
+        let requests = transport.requests.into_inner();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].soap_version, SoapVersion::Soap12);
+        assert!(
+            requests[0]
+                .envelope
+                .contains("http://schemas.xmlsoap.org/ws/2004/09/enumeration/Enumerate")
+        );
+    }
+
+    #[test]
+    fn test_transport_error() {
+        let transport = CannedTransport::default();
+
+        let error = block_on(exchange_empty(&transport)).unwrap_err();
+        let ProtocolError::Transport(source) = &error else {
+            panic!("expected a transport error, got {error:?}");
+        };
+        assert!(source.is::<ConnectionReset>());
+        assert_eq!(
+            error.to_string(),
+            "Transport error: the connection was reset"
+        );
+
+        let request = SoapRequest::new("<s:Envelope/>").with_soap_version(SoapVersion::Soap11);
+        assert_eq!(
+            request.soap_version.content_type(),
+            "text/xml; charset=utf-8"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use protocol_winrm::{
+    cores::{Tag, UnknownTagPolicy, tag_name::*},
+    soap::SoapEnvelope,
+    ws_management::SelectorSetValue,
+};
+use xml::parser::XmlDeserialize;
+
+const SELECTOR_SET: &str = r#"<w:SelectorSet xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+    <w:Selector Name="ShellId">1234</w:Selector>
+    <w:Unexpected>value</w:Unexpected>
+</w:SelectorSet>"#;
+
+const ENVELOPE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell">
+    <s:Header/>
+    <s:Body>
+        <rsp:ReceiveResponse>
+            <rsp:Stream Name="stdout">AAAA</rsp:Stream>
+            <rsp:CommandState State="http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandState/Running"/>
+            <rsp:Unexpected>value</rsp:Unexpected>
+        </rsp:ReceiveResponse>
+    </s:Body>
+</s:Envelope>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_selector_set() -> Result<(), xml::XmlError> {
+        let document = xml::parser::parse(SELECTOR_SET).unwrap();
+        let selector_set: Tag<'_, SelectorSetValue, SelectorSet> =
+            Tag::from_node(document.root_element())?;
+        assert_eq!(
+            selector_set.value.get("ShellId").map(String::as_str),
+            Some("1234")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lenient_policy_skips_unknown_tags() {
+        assert!(UnknownTagPolicy::Warn.scope(parse_selector_set).is_ok());
+        assert!(UnknownTagPolicy::Ignore.scope(parse_selector_set).is_ok());
+    }
+
+    #[test]
+    fn test_strict_policy_rejects_unknown_tags() {
+        assert!(UnknownTagPolicy::Error.scope(parse_selector_set).is_err());
+
+        let document = xml::parser::parse(ENVELOPE).unwrap();
+        let strict =
+            UnknownTagPolicy::Error.scope(|| SoapEnvelope::from_node(document.root_element()));
+        assert!(strict.is_err());
+
+        let lenient =
+            UnknownTagPolicy::Warn.scope(|| SoapEnvelope::from_node(document.root_element()));
+        assert!(lenient.is_ok());
+    }
+}
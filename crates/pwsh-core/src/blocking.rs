@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use protocol_powershell_remoting::HostInfo;
+use protocol_winrm::{
+    rsp::{process::CommandOutput, shell::CreateShellRequest},
+    transport::Transport,
+    ws_management::WsMan,
+};
+use tracing::instrument;
+
+use crate::{
+    connector::{ConnectorConfig, transport::HttpTransport},
+    runspace_pool::{AsyncRunspacePool, PipelineOutput, RunspacePoolCreator},
+};
+
+/// A client running commands and scripts on a server to their end, for the programs that do
+/// not run an async runtime: the async client runs on a single threaded runtime of its own,
+/// the calls block until it is done.
+///
+/// Each call opens a shell or a runspace pool of its own and closes it once done, for a script
+/// to keep state between calls use an [`AsyncRunspacePool`].
+pub struct Client {
+    runtime: tokio::runtime::Runtime,
+    transport: HttpTransport,
+    connection: Arc<WsMan>,
+}
+
+impl Client {
+    pub fn new(config: &ConnectorConfig) -> Result<Self, crate::PwshCoreError> {
+        Self::with_transport(HttpTransport::new(config))
+    }
+
+    /// A client sending with `transport`, e.g. one of a client trusting the certificate of the
+    /// server
+    pub fn with_transport(transport: HttpTransport) -> Result<Self, crate::PwshCoreError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(crate::PwshCoreError::IOError)?;
+        let connection = Arc::new(WsMan::builder().to(transport.url().to_string()).build());
+
+        Ok(Self {
+            runtime,
+            transport,
+            connection,
+        })
+    }
+
+    /// Run `command` with `arguments` in a `cmd` shell, e.g. `run_cmd("ipconfig", &["/all"])`.
+    ///
+    /// The shell writes its output as UTF-8, see
+    /// [`CommandOutput::stdout_text`] with [`CODEPAGE_UTF8`](protocol_winrm::rsp::codepage::CODEPAGE_UTF8).
+    #[instrument(skip(self, arguments), name = "Client::run_cmd")]
+    pub fn run_cmd(
+        &self,
+        command: &str,
+        arguments: &[&str],
+    ) -> Result<CommandOutput, crate::PwshCoreError> {
+        self.runtime.block_on(async {
+            let exchange = |envelope| self.transport.exchange(envelope);
+            let mut shell = self
+                .connection
+                .open_shell(
+                    CreateShellRequest::builder().build(),
+                    self.connection.locale(),
+                    exchange,
+                )
+                .await?;
+
+            // The shell is deleted whether the command ran or not
+            let output = shell.run(command, arguments).await;
+            let deleted = shell.delete().await;
+            let output = output?;
+            deleted?;
+            Ok(output)
+        })
+    }
+
+    /// Run `script` in a runspace pool, e.g. `run_ps("Get-Service WinRM")`.
+    ///
+    /// A script that fails is not an error, its [`PipelineOutput::failure`] tells why.
+    #[instrument(skip_all, name = "Client::run_ps")]
+    pub fn run_ps(&self, script: &str) -> Result<PipelineOutput, crate::PwshCoreError> {
+        self.runtime.block_on(async {
+            let pool = RunspacePoolCreator::builder()
+                .host_info(HostInfo::builder().build())
+                .build()
+                .into_runspace_pool(self.connection.clone());
+            let transport = &self.transport;
+            let mut pool = AsyncRunspacePool::open(pool, |envelope| async move {
+                Ok(transport.exchange(envelope).await?)
+            })
+            .await?;
+
+            // The pool is closed whether the script ran or not
+            let output = pool.invoke_script(script).await;
+            let closed = pool.close().await;
+            let output = output?;
+            closed?;
+            Ok(output)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::{
+        test_server::{Response, TestServer, basic},
+        transport::HttpTransportError,
+    };
+
+    const FAULT: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd"><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>w:AccessDenied</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en-US">Access is denied.</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#;
+
+    #[test]
+    fn test_run_cmd_fault() {
+        let server = TestServer::new();
+        server.respond(Response::envelope(500, FAULT));
+        let client = Client::new(&server.config(basic("user", "password"))).unwrap();
+
+        let error = client.run_cmd("ipconfig", &[]).unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                crate::PwshCoreError::WsManError(
+                    protocol_winrm::error::ProtocolError::WsmanFault { .. }
+                )
+            ),
+            "{error:?}"
+        );
+    }
+
+    #[test]
+    fn test_run_ps_unauthorized() {
+        let server = TestServer::new();
+        server.respond(Response::new(401).header("WWW-Authenticate", "Basic"));
+        let client = Client::new(&server.config(basic("user", "password"))).unwrap();
+
+        let error = client.run_ps("Get-Service WinRM").unwrap_err();
+        let crate::PwshCoreError::WsManError(protocol_winrm::error::ProtocolError::Transport(
+            error,
+        )) = error
+        else {
+            panic!("not an error of the transport: {error:?}");
+        };
+        assert!(
+            matches!(
+                error.downcast_ref::<HttpTransportError>(),
+                Some(HttpTransportError::Unauthorized)
+            ),
+            "{error}"
+        );
+    }
+}
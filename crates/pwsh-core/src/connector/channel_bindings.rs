@@ -0,0 +1,67 @@
+//! The channel bindings of the TLS connection [RFC 5929], binding the authentication to it:
+//! `tls-server-end-point`, the hash of the certificate of the server, what the listeners of
+//! WinRM with a `CbtHardeningLevel` of `Strict` require over HTTPS.
+
+use openssl::{hash::MessageDigest, nid::Nid, x509::X509};
+
+use crate::PwshCoreError;
+
+/// The bindings of a TLS connection, given to [`Ntlm`](super::ntlm::Ntlm),
+/// [`Kerberos`](super::kerberos::Kerberos) or [`Negotiate`](super::negotiate::Negotiate)
+/// before their first token.
+#[derive(Debug, Clone)]
+pub struct ChannelBindings {
+    application_data: Vec<u8>,
+    /// The MD5 of the `gss_channel_bindings_struct`, what NTLM sends
+    digest: [u8; 16],
+}
+
+impl ChannelBindings {
+    /// The `tls-server-end-point` bindings of the DER `certificate` of the server: its hash
+    /// with the digest of its signature, SHA-256 when that is MD5 or SHA-1
+    pub fn tls_server_end_point(certificate: &[u8]) -> Result<Self, PwshCoreError> {
+        let certificate = X509::from_der(certificate).map_err(invalid_certificate)?;
+        let digest = certificate
+            .signature_algorithm()
+            .object()
+            .nid()
+            .signature_algorithms()
+            .filter(|algorithms| !matches!(algorithms.digest, Nid::MD5 | Nid::SHA1))
+            .and_then(|algorithms| MessageDigest::from_nid(algorithms.digest))
+            .unwrap_or_else(MessageDigest::sha256);
+        let hash = certificate.digest(digest).map_err(invalid_certificate)?;
+
+        let mut application_data = b"tls-server-end-point:".to_vec();
+        application_data.extend_from_slice(&hash);
+        Self::new(application_data)
+    }
+
+    /// Bindings of `application_data`, without the addresses of the ends
+    pub fn new(application_data: Vec<u8>) -> Result<Self, PwshCoreError> {
+        // The addresses are unspecified: their types and lengths are zero
+        let mut bindings = vec![0; 16];
+        bindings.extend_from_slice(&(application_data.len() as u32).to_le_bytes());
+        bindings.extend_from_slice(&application_data);
+        let digest = openssl::hash::hash(MessageDigest::md5(), &bindings)
+            .map_err(|error| PwshCoreError::AuthenticationError(format!("MD5 failed: {error}")))?;
+
+        Ok(Self {
+            application_data,
+            digest: (*digest).try_into().expect("MD5 is 16 bytes"),
+        })
+    }
+
+    /// The application data of the bindings, `tls-server-end-point:` then the hash
+    pub fn application_data(&self) -> &[u8] {
+        &self.application_data
+    }
+
+    /// The `MsvAvChannelBindings` of NTLM
+    pub(crate) fn digest(&self) -> &[u8; 16] {
+        &self.digest
+    }
+}
+
+fn invalid_certificate(error: openssl::error::ErrorStack) -> PwshCoreError {
+    PwshCoreError::AuthenticationError(format!("invalid certificate of the server: {error}"))
+}
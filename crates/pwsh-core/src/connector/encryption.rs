@@ -0,0 +1,258 @@
+//! The encrypted bodies of WinRM over HTTP [MS-WSMV 2.2.9.1]: the envelope sealed by the
+//! security context of the connection, in a `multipart/encrypted` body telling what it was.
+//!
+//! ```text
+//! --Encrypted Boundary
+//!     Content-Type: application/HTTP-SPNEGO-session-encrypted
+//!     OriginalContent: type=application/soap+xml;charset=UTF-8;Length=<length of the envelope>
+//! --Encrypted Boundary
+//!     Content-Type: application/octet-stream
+//! <length of the header, u32 LE><header of the wrap token><sealed envelope>--Encrypted Boundary--
+//! ```
+//!
+//! The `protocol` follows the mechanism sealing the envelopes, see [`protocol`]: the wrap
+//! tokens of Kerberos go as `application/HTTP-Kerberos-session-encrypted`, whether SPNEGO
+//! settled on it or not. A body is unsealed whichever of them it tells.
+
+use crate::{PwshCoreError, connector::negotiate::Mechanism};
+
+const BOUNDARY: &str = "Encrypted Boundary";
+
+/// The `protocol` of the encrypted bodies sealed by NTLM
+pub const SPNEGO_SESSION_ENCRYPTED: &str = "application/HTTP-SPNEGO-session-encrypted";
+
+/// The `protocol` of the encrypted bodies sealed by the wrap tokens of Kerberos
+pub const KERBEROS_SESSION_ENCRYPTED: &str = "application/HTTP-Kerberos-session-encrypted";
+
+/// The `protocol` of the bodies sealed by `mechanism`
+pub fn protocol(mechanism: Mechanism) -> &'static str {
+    match mechanism {
+        Mechanism::Kerberos => KERBEROS_SESSION_ENCRYPTED,
+        Mechanism::Ntlm => SPNEGO_SESSION_ENCRYPTED,
+    }
+}
+
+/// The `Content-Type` of a body encrypted for `protocol`
+pub fn content_type(protocol: &str) -> String {
+    format!("multipart/encrypted;protocol=\"{protocol}\";boundary=\"{BOUNDARY}\"")
+}
+
+/// Whether `content_type` is the one of an encrypted body
+pub fn is_encrypted(content_type: &str) -> bool {
+    content_type
+        .trim_start()
+        .to_ascii_lowercase()
+        .starts_with("multipart/encrypted")
+}
+
+/// The body carrying the `header` and the `sealed` data of the wrap token of a message of
+/// `original_type` and `original_length`
+pub fn encrypted_body(
+    protocol: &str,
+    original_type: &str,
+    original_length: usize,
+    header: &[u8],
+    sealed: &[u8],
+) -> Vec<u8> {
+    let mut body = format!(
+        "--{BOUNDARY}\r\n\
+         \tContent-Type: {protocol}\r\n\
+         \tOriginalContent: type={original_type};Length={original_length}\r\n\
+         --{BOUNDARY}\r\n\
+         \tContent-Type: application/octet-stream\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    body.extend_from_slice(header);
+    body.extend_from_slice(sealed);
+    body.extend_from_slice(format!("--{BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+/// The header and the sealed data of the wrap token an encrypted `body` carries
+pub fn parse_encrypted_body(body: &[u8]) -> Result<(&[u8], &[u8]), PwshCoreError> {
+    const OCTET_STREAM: &[u8] = b"Content-Type: application/octet-stream\r\n";
+
+    let start = find(body, OCTET_STREAM)
+        .map(|at| at + OCTET_STREAM.len())
+        .ok_or_else(|| invalid_body("no application/octet-stream part"))?;
+    let trailer = format!("--{BOUNDARY}--");
+    let end = rfind(body, trailer.as_bytes()).ok_or_else(|| invalid_body("no end boundary"))?;
+    let payload = body
+        .get(start..end)
+        .ok_or_else(|| invalid_body("the end boundary is misplaced"))?;
+
+    let (length, rest) = payload
+        .split_first_chunk::<4>()
+        .ok_or_else(|| invalid_body("truncated"))?;
+    let length = u32::from_le_bytes(*length) as usize;
+    if rest.len() < length {
+        return Err(invalid_body("the header is truncated"));
+    }
+    Ok(rest.split_at(length))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+fn invalid_body(reason: &str) -> PwshCoreError {
+    PwshCoreError::InvalidResponse(format!("invalid encrypted body: {reason}").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::ntlm::{
+        Ntlm,
+        tests::{FLAGS, challenge_message, target_info},
+    };
+
+    const ORIGINAL_TYPE: &str = "application/soap+xml;charset=UTF-8";
+
+    #[test]
+    fn test_encrypted_body() {
+        let body = encrypted_body(
+            SPNEGO_SESSION_ENCRYPTED,
+            ORIGINAL_TYPE,
+            42,
+            b"header",
+            b"sealed",
+        );
+
+        let mut expected = b"--Encrypted Boundary\r\n\
+            \tContent-Type: application/HTTP-SPNEGO-session-encrypted\r\n\
+            \tOriginalContent: type=application/soap+xml;charset=UTF-8;Length=42\r\n\
+            --Encrypted Boundary\r\n\
+            \tContent-Type: application/octet-stream\r\n"
+            .to_vec();
+        expected.extend_from_slice(&[6, 0, 0, 0]);
+        expected.extend_from_slice(b"headersealed--Encrypted Boundary--\r\n");
+        assert_eq!(body, expected);
+
+        assert_eq!(
+            parse_encrypted_body(&body).unwrap(),
+            (&b"header"[..], &b"sealed"[..])
+        );
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(
+            content_type(SPNEGO_SESSION_ENCRYPTED),
+            "multipart/encrypted;protocol=\"application/HTTP-SPNEGO-session-encrypted\";boundary=\"Encrypted Boundary\""
+        );
+        assert!(is_encrypted(&content_type(SPNEGO_SESSION_ENCRYPTED)));
+        assert!(is_encrypted(" Multipart/Encrypted;boundary=x"));
+        assert!(!is_encrypted(ORIGINAL_TYPE));
+    }
+
+    #[test]
+    fn test_kerberos_encrypted_body() {
+        assert_eq!(protocol(Mechanism::Kerberos), KERBEROS_SESSION_ENCRYPTED);
+        assert_eq!(protocol(Mechanism::Ntlm), SPNEGO_SESSION_ENCRYPTED);
+
+        let body = encrypted_body(
+            protocol(Mechanism::Kerberos),
+            ORIGINAL_TYPE,
+            42,
+            b"header",
+            b"sealed",
+        );
+        assert!(
+            String::from_utf8_lossy(&body)
+                .contains("\tContent-Type: application/HTTP-Kerberos-session-encrypted\r\n"),
+            "{body:?}"
+        );
+        assert!(is_encrypted(&content_type(KERBEROS_SESSION_ENCRYPTED)));
+        assert_eq!(
+            parse_encrypted_body(&body).unwrap(),
+            (&b"header"[..], &b"sealed"[..])
+        );
+    }
+
+    #[test]
+    fn test_parse_encrypted_body_invalid() {
+        let body = encrypted_body(SPNEGO_SESSION_ENCRYPTED, ORIGINAL_TYPE, 0, b"header", b"");
+
+        let no_part = String::from_utf8_lossy(&body).replace("octet-stream", "other");
+        let no_end = &body[..body.len() - "--Encrypted Boundary--\r\n".len()];
+        let mut truncated = body.clone();
+        let at = truncated.len() - "header--Encrypted Boundary--\r\n".len() - 4;
+        truncated[at] = 7;
+        for body in [no_part.as_bytes(), no_end, &truncated] {
+            let error = parse_encrypted_body(body).unwrap_err();
+            assert!(
+                matches!(error, PwshCoreError::InvalidResponse(_)),
+                "{error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ntlm_sealed_bodies() {
+        let mut client = Ntlm::new("User", "Password");
+        client.negotiate();
+        client
+            .authenticate(&challenge_message(FLAGS, &target_info()))
+            .unwrap();
+        let mut server = client.acceptor().unwrap();
+
+        // Each message goes on with the RC4 stream and the sequence numbers of the ones before
+        for envelope in ["<s:Envelope>1</s:Envelope>", "<s:Envelope>2</s:Envelope>"] {
+            let (signature, sealed) = client.seal(envelope.as_bytes()).unwrap();
+            let body = encrypted_body(
+                SPNEGO_SESSION_ENCRYPTED,
+                ORIGINAL_TYPE,
+                envelope.len(),
+                &signature,
+                &sealed,
+            );
+            let length = format!("Length={}\r\n", envelope.len());
+            assert!(String::from_utf8_lossy(&body).contains(&length), "{body:?}");
+
+            let (header, sealed) = parse_encrypted_body(&body).unwrap();
+            assert_eq!(header, signature);
+            assert_eq!(server.unseal(header, sealed).unwrap(), envelope.as_bytes());
+
+            let (signature, sealed) = server.seal(b"<s:Envelope>response</s:Envelope>").unwrap();
+            let body = encrypted_body(
+                SPNEGO_SESSION_ENCRYPTED,
+                ORIGINAL_TYPE,
+                sealed.len(),
+                &signature,
+                &sealed,
+            );
+            let (header, sealed) = parse_encrypted_body(&body).unwrap();
+            assert_eq!(
+                client.unseal(header, sealed).unwrap(),
+                b"<s:Envelope>response</s:Envelope>"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ntlm_sealed_out_of_order() {
+        let mut client = Ntlm::new("User", "Password");
+        client.negotiate();
+        client
+            .authenticate(&challenge_message(FLAGS, &target_info()))
+            .unwrap();
+        let mut server = client.acceptor().unwrap();
+
+        // Two envelopes sealed at once and unsealed in the other order break the RC4 stream, for
+        // the first one too
+        let (first_signature, first) = client.seal(b"first").unwrap();
+        let (second_signature, second) = client.seal(b"second").unwrap();
+        assert!(server.unseal(&second_signature, &second).is_err());
+        assert!(server.unseal(&first_signature, &first).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use base64::Engine;
+use protocol_winrm::soap::SoapVersion;
 use std::{fmt::Display, net::IpAddr};
 
 #[derive(Debug, Clone)]
@@ -52,24 +53,30 @@ pub struct HttpBuilder {
     pub(crate) server: ServerAddress,
     pub(crate) port: u16,
     pub(crate) scheme: crate::connector::Scheme,
-    pub(crate) authentication: crate::connector::Authentication,
+    /// The `Authorization` of every request
+    pub(crate) authorization: String,
     pub(crate) cookie: Option<String>,
+    pub(crate) soap_version: SoapVersion,
 }
 
 impl HttpBuilder {
+    /// The requests of a server authenticated with `Basic`, the handshakes of NTLM, Kerberos
+    /// and Negotiate take several requests on a same connection, only the
+    /// [`HttpTransport`](crate::connector::transport::HttpTransport) carries them
     pub fn new(
         server: ServerAddress,
         port: u16,
         scheme: crate::connector::Scheme,
         authentication: crate::connector::Authentication,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, crate::PwshCoreError> {
+        Ok(Self {
             server,
             port,
             scheme,
-            authentication,
+            authorization: Self::build_auth_header(&authentication)?,
             cookie: None,
-        }
+            soap_version: SoapVersion::default(),
+        })
     }
 
     pub fn with_cookie(mut self, cookie: String) -> Self {
@@ -77,6 +84,12 @@ impl HttpBuilder {
         self
     }
 
+    /// The `Content-Type` of the requests follows the SOAP version of the envelopes
+    pub fn with_soap_version(mut self, soap_version: SoapVersion) -> Self {
+        self.soap_version = soap_version;
+        self
+    }
+
     fn build_url(&self, path: &str) -> String {
         let scheme_str = match self.scheme {
             crate::connector::Scheme::Http => "http",
@@ -91,14 +104,22 @@ impl HttpBuilder {
         format!("{}://{}:{}{}", scheme_str, server_str, self.port, path)
     }
 
-    fn build_auth_header(&self) -> String {
-        match &self.authentication {
+    fn build_auth_header(
+        authentication: &crate::connector::Authentication,
+    ) -> Result<String, crate::PwshCoreError> {
+        let scheme = match authentication {
             crate::connector::Authentication::Basic { username, password } => {
                 let credentials = format!("{username}:{password}");
                 let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
-                format!("Basic {encoded}")
+                return Ok(format!("Basic {encoded}"));
             }
-        }
+            crate::connector::Authentication::Ntlm { .. } => "NTLM",
+            crate::connector::Authentication::Kerberos { .. } => "Kerberos",
+            crate::connector::Authentication::Negotiate { .. } => "Negotiate",
+        };
+        Err(crate::PwshCoreError::AuthenticationError(format!(
+            "{scheme} needs a handshake on the connection, only the HttpTransport carries it"
+        )))
     }
 
     fn build_host_header(&self) -> String {
@@ -113,11 +134,12 @@ impl HttpBuilder {
             ("Host".to_string(), self.build_host_header()),
             (
                 "Content-Type".to_string(),
-                "application/soap+xml; charset=utf-8".to_string(),
+                self.soap_version.content_type().to_string(),
             ),
-            ("Authorization".to_string(), self.build_auth_header()),
         ];
 
+        headers.push(("Authorization".to_string(), self.authorization.clone()));
+
         if let Some(body_content) = body {
             headers.push(("Content-Length".to_string(), body_content.len().to_string()));
         } else {
@@ -171,3 +193,62 @@ impl HttpBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::{Authentication, Scheme};
+
+    fn builder(authentication: Authentication) -> Result<HttpBuilder, crate::PwshCoreError> {
+        HttpBuilder::new(
+            ServerAddress::Domain("server".into()),
+            5985,
+            Scheme::Http,
+            authentication,
+        )
+    }
+
+    #[test]
+    fn test_basic_authorization() {
+        let request = builder(Authentication::Basic {
+            username: "user".into(),
+            password: "password".into(),
+        })
+        .unwrap()
+        .post("/wsman", "<s:Envelope/>".into());
+
+        assert_eq!(request.url, "http://server:5985/wsman");
+        assert!(
+            request
+                .headers
+                .contains(&("Authorization".into(), "Basic dXNlcjpwYXNzd29yZA==".into()))
+        );
+    }
+
+    #[test]
+    fn test_handshakes_are_rejected() {
+        let authentications = [
+            Authentication::Ntlm {
+                username: "user".into(),
+                password: "password".into(),
+            },
+            Authentication::Kerberos {
+                username: None,
+                password: None,
+                spn: None,
+            },
+            Authentication::Negotiate {
+                username: None,
+                password: None,
+                spn: None,
+            },
+        ];
+        for authentication in authentications {
+            let error = builder(authentication).unwrap_err();
+            assert!(
+                matches!(error, crate::PwshCoreError::AuthenticationError(_)),
+                "{error}"
+            );
+        }
+    }
+}
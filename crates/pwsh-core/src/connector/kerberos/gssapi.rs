@@ -0,0 +1,534 @@
+//! The functions of GSSAPI [RFC 2744] used by the client, with the MIT extension acquiring
+//! credentials with a password. The library is opened at run time, a system without it only
+//! fails when Kerberos is used.
+
+use std::{
+    ffi::{CStr, c_void},
+    ptr,
+    sync::OnceLock,
+};
+
+use crate::PwshCoreError;
+
+const LIBRARIES: &[&CStr] = &[c"libgssapi_krb5.so.2", c"libgssapi_krb5.so"];
+
+/// 1.2.840.113554.1.2.2, the Kerberos V5 mechanism
+static KRB5_MECHANISM: [u8; 9] = *b"\x2a\x86\x48\x86\xf7\x12\x01\x02\x02";
+/// 1.2.840.113554.1.2.1.4, `service@host`
+static NT_HOSTBASED_SERVICE: [u8; 10] = *b"\x2a\x86\x48\x86\xf7\x12\x01\x02\x01\x04";
+/// 1.2.840.113554.1.2.2.1, a Kerberos principal name, `service/host@REALM` or `user@REALM`
+static NT_KRB5_PRINCIPAL_NAME: [u8; 10] = *b"\x2a\x86\x48\x86\xf7\x12\x01\x02\x02\x01";
+
+const GSS_S_CONTINUE_NEEDED: u32 = 1;
+/// The calling and routine errors of a major status, its supplementary bits are not errors
+const GSS_ERROR_MASK: u32 = 0xFFFF_0000;
+
+const GSS_C_MUTUAL_FLAG: u32 = 2;
+const GSS_C_REPLAY_FLAG: u32 = 4;
+const GSS_C_SEQUENCE_FLAG: u32 = 8;
+const GSS_C_CONF_FLAG: u32 = 16;
+const GSS_C_INTEG_FLAG: u32 = 32;
+const REQUEST_FLAGS: u32 = GSS_C_MUTUAL_FLAG
+    | GSS_C_REPLAY_FLAG
+    | GSS_C_SEQUENCE_FLAG
+    | GSS_C_CONF_FLAG
+    | GSS_C_INTEG_FLAG;
+
+const GSS_C_INITIATE: i32 = 1;
+const GSS_C_INDEFINITE: u32 = 0xFFFF_FFFF;
+const GSS_C_GSS_CODE: i32 = 1;
+const GSS_C_MECH_CODE: i32 = 2;
+const GSS_C_QOP_DEFAULT: u32 = 0;
+
+const GSS_IOV_BUFFER_TYPE_DATA: u32 = 1;
+const GSS_IOV_BUFFER_TYPE_HEADER: u32 = 2;
+const GSS_IOV_BUFFER_TYPE_PADDING: u32 = 9;
+const GSS_IOV_BUFFER_FLAG_ALLOCATE: u32 = 0x0001_0000;
+
+#[repr(C)]
+struct Buffer {
+    length: usize,
+    value: *mut c_void,
+}
+
+impl Buffer {
+    fn empty() -> Self {
+        Self {
+            length: 0,
+            value: ptr::null_mut(),
+        }
+    }
+
+    /// A buffer the library reads `bytes` through, for the duration of a call
+    fn borrowing(bytes: &[u8]) -> Self {
+        Self {
+            length: bytes.len(),
+            value: bytes.as_ptr() as *mut c_void,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `value` points to `length` bytes, or is null.
+    unsafe fn to_vec(&self) -> Vec<u8> {
+        if self.value.is_null() {
+            return Vec::new();
+        }
+        // SAFETY: the buffer was filled by the library
+        unsafe { std::slice::from_raw_parts(self.value as *const u8, self.length).to_vec() }
+    }
+}
+
+/// A buffer of [`wrap_iov`](Library::wrap_iov), an MIT extension
+#[repr(C)]
+struct IovBuffer {
+    buffer_type: u32,
+    buffer: Buffer,
+}
+
+#[repr(C)]
+struct Oid {
+    length: u32,
+    elements: *mut c_void,
+}
+
+impl Oid {
+    fn new(der: &'static [u8]) -> Self {
+        Self {
+            length: der.len() as u32,
+            elements: der.as_ptr() as *mut c_void,
+        }
+    }
+}
+
+/// A `gss_channel_bindings_struct` without the addresses of the ends
+#[repr(C)]
+struct ChannelBindings {
+    initiator_address_type: u32,
+    initiator_address: Buffer,
+    acceptor_address_type: u32,
+    acceptor_address: Buffer,
+    application_data: Buffer,
+}
+
+#[repr(C)]
+struct OidSet {
+    count: usize,
+    elements: *mut Oid,
+}
+
+type Handle = *mut c_void;
+
+#[rustfmt::skip]
+struct Library {
+    import_name: unsafe extern "C" fn(*mut u32, *mut Buffer, *mut Oid, *mut Handle) -> u32,
+    release_name: unsafe extern "C" fn(*mut u32, *mut Handle) -> u32,
+    acquire_cred_with_password: unsafe extern "C" fn(
+        *mut u32, Handle, *mut Buffer, u32, *mut OidSet, i32, *mut Handle, *mut *mut OidSet, *mut u32,
+    ) -> u32,
+    release_cred: unsafe extern "C" fn(*mut u32, *mut Handle) -> u32,
+    init_sec_context: unsafe extern "C" fn(
+        *mut u32, Handle, *mut Handle, Handle, *mut Oid, u32, u32, *mut ChannelBindings, *mut Buffer,
+        *mut *mut Oid, *mut Buffer, *mut u32, *mut u32,
+    ) -> u32,
+    delete_sec_context: unsafe extern "C" fn(*mut u32, *mut Handle, *mut Buffer) -> u32,
+    release_buffer: unsafe extern "C" fn(*mut u32, *mut Buffer) -> u32,
+    display_status: unsafe extern "C" fn(*mut u32, u32, i32, *mut Oid, *mut u32, *mut Buffer) -> u32,
+    wrap_iov: unsafe extern "C" fn(*mut u32, Handle, i32, u32, *mut i32, *mut IovBuffer, i32) -> u32,
+    release_iov_buffer: unsafe extern "C" fn(*mut u32, *mut IovBuffer, i32) -> u32,
+    unwrap: unsafe extern "C" fn(*mut u32, Handle, *mut Buffer, *mut Buffer, *mut i32, *mut u32) -> u32,
+}
+
+impl Library {
+    fn get() -> Result<&'static Self, PwshCoreError> {
+        static LIBRARY: OnceLock<Result<Library, String>> = OnceLock::new();
+        LIBRARY
+            .get_or_init(Self::load)
+            .as_ref()
+            .map_err(|error| PwshCoreError::AuthenticationError(error.clone()))
+    }
+
+    fn load() -> Result<Self, String> {
+        let handle = LIBRARIES
+            .iter()
+            // SAFETY: the names are NUL terminated, the library is never closed
+            .map(|name| unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) })
+            .find(|handle| !handle.is_null())
+            .ok_or("GSSAPI is not installed, libgssapi_krb5 was not found")?;
+
+        // SAFETY: the symbols are the functions of GSSAPI with these signatures
+        unsafe {
+            Ok(Self {
+                import_name: symbol(handle, c"gss_import_name")?,
+                release_name: symbol(handle, c"gss_release_name")?,
+                acquire_cred_with_password: symbol(handle, c"gss_acquire_cred_with_password")?,
+                release_cred: symbol(handle, c"gss_release_cred")?,
+                init_sec_context: symbol(handle, c"gss_init_sec_context")?,
+                delete_sec_context: symbol(handle, c"gss_delete_sec_context")?,
+                release_buffer: symbol(handle, c"gss_release_buffer")?,
+                display_status: symbol(handle, c"gss_display_status")?,
+                wrap_iov: symbol(handle, c"gss_wrap_iov")?,
+                release_iov_buffer: symbol(handle, c"gss_release_iov_buffer")?,
+                unwrap: symbol(handle, c"gss_unwrap")?,
+            })
+        }
+    }
+
+    /// The name of `name_type` the library reads `name` as
+    fn import_name(&self, name: &str, name_type: &'static [u8]) -> Result<Handle, PwshCoreError> {
+        let mut minor = 0;
+        let mut imported = ptr::null_mut();
+        // SAFETY: the buffers live through the call
+        let major = unsafe {
+            (self.import_name)(
+                &mut minor,
+                &mut Buffer::borrowing(name.as_bytes()),
+                &mut Oid::new(name_type),
+                &mut imported,
+            )
+        };
+        self.check(major, minor)?;
+        Ok(imported)
+    }
+
+    fn check(&self, major: u32, minor: u32) -> Result<u32, PwshCoreError> {
+        check(major, || {
+            let mut messages = self.status_messages(major, GSS_C_GSS_CODE);
+            if minor != 0 {
+                messages.extend(self.status_messages(minor, GSS_C_MECH_CODE));
+            }
+            messages
+        })
+    }
+
+    fn status_messages(&self, status: u32, status_type: i32) -> Vec<String> {
+        let mut messages = Vec::new();
+        let mut message_context = 0;
+        loop {
+            let mut minor = 0;
+            let mut message = Buffer::empty();
+            // SAFETY: the message is released once copied
+            let major = unsafe {
+                let major = (self.display_status)(
+                    &mut minor,
+                    status,
+                    status_type,
+                    &mut Oid::new(&KRB5_MECHANISM),
+                    &mut message_context,
+                    &mut message,
+                );
+                messages.push(String::from_utf8_lossy(&message.to_vec()).into_owned());
+                (self.release_buffer)(&mut minor, &mut message);
+                major
+            };
+            if major & GSS_ERROR_MASK != 0 || message_context == 0 {
+                return messages;
+            }
+        }
+    }
+}
+
+/// `major` when it is not an error, else the error of the `messages` telling it, from the
+/// routine error to the one of the mechanism
+fn check(major: u32, messages: impl FnOnce() -> Vec<String>) -> Result<u32, PwshCoreError> {
+    if major & GSS_ERROR_MASK == 0 {
+        return Ok(major);
+    }
+    Err(PwshCoreError::AuthenticationError(messages().join(": ")))
+}
+
+/// The name of the service `spn` and its type: `HTTP/host` is the host based service
+/// `HTTP@host`, whose realm the library finds, a name with a realm is a principal
+fn target_name(spn: &str) -> (String, &'static [u8]) {
+    match spn.split_once('/') {
+        Some((service, host)) if !host.contains('@') => {
+            (format!("{service}@{host}"), &NT_HOSTBASED_SERVICE)
+        }
+        _ => (spn.to_string(), &NT_KRB5_PRINCIPAL_NAME),
+    }
+}
+
+/// The principal of `username`: `DOMAIN\user` is `user@DOMAIN`, the realm being the domain in
+/// upper case, `user@REALM` is already one
+fn principal(username: &str) -> String {
+    match username.split_once('\\') {
+        Some((domain, user)) => format!("{user}@{}", domain.to_uppercase()),
+        None => username.to_string(),
+    }
+}
+
+/// # Safety
+///
+/// `T` is the function pointer type of the symbol `name`.
+unsafe fn symbol<T>(handle: *mut c_void, name: &CStr) -> Result<T, String> {
+    // SAFETY: upheld by the caller
+    unsafe {
+        let symbol = libc::dlsym(handle, name.as_ptr());
+        if symbol.is_null() {
+            return Err(format!("GSSAPI has no {}", name.to_string_lossy()));
+        }
+        Ok(std::mem::transmute_copy(&symbol))
+    }
+}
+
+/// A security context of the Kerberos mechanism, the handles released when dropped
+pub(super) struct Context {
+    library: &'static Library,
+    credential: Handle,
+    target: Handle,
+    context: Handle,
+    flags: u32,
+}
+
+// SAFETY: the handles are owned by the context, the library is thread safe
+unsafe impl Send for Context {}
+
+impl Context {
+    pub(super) fn new(spn: &str, credentials: Option<(&str, &str)>) -> Result<Self, PwshCoreError> {
+        let library = Library::get()?;
+        let (name, name_type) = target_name(spn);
+        let target = library.import_name(&name, name_type)?;
+        let mut context = Self {
+            library,
+            credential: ptr::null_mut(),
+            target,
+            context: ptr::null_mut(),
+            flags: 0,
+        };
+
+        if let Some((username, password)) = credentials {
+            let mut user = library.import_name(&principal(username), &NT_KRB5_PRINCIPAL_NAME)?;
+
+            let mut minor = 0;
+            let mut mechanism = Oid::new(&KRB5_MECHANISM);
+            // SAFETY: the buffers live through the call, the name is released after it
+            let major = unsafe {
+                let major = (library.acquire_cred_with_password)(
+                    &mut minor,
+                    user,
+                    &mut Buffer::borrowing(password.as_bytes()),
+                    GSS_C_INDEFINITE,
+                    &mut OidSet {
+                        count: 1,
+                        elements: &mut mechanism,
+                    },
+                    GSS_C_INITIATE,
+                    &mut context.credential,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                );
+                (library.release_name)(&mut 0, &mut user);
+                major
+            };
+            library.check(major, minor)?;
+        }
+
+        Ok(context)
+    }
+
+    /// The token to send and whether the context is established, bound to the channel of the
+    /// `channel_bindings` application data
+    pub(super) fn step(
+        &mut self,
+        token: Option<&[u8]>,
+        channel_bindings: Option<&[u8]>,
+    ) -> Result<(Option<Vec<u8>>, bool), PwshCoreError> {
+        let mut input = token.map(Buffer::borrowing).unwrap_or_else(Buffer::empty);
+        let mut bindings = channel_bindings.map(|application_data| ChannelBindings {
+            initiator_address_type: 0,
+            initiator_address: Buffer::empty(),
+            acceptor_address_type: 0,
+            acceptor_address: Buffer::empty(),
+            application_data: Buffer::borrowing(application_data),
+        });
+        let mut output = Buffer::empty();
+        let mut minor = 0;
+        // SAFETY: the buffers live through the call, the output is released once copied
+        let (major, output) = unsafe {
+            let major = (self.library.init_sec_context)(
+                &mut minor,
+                self.credential,
+                &mut self.context,
+                self.target,
+                &mut Oid::new(&KRB5_MECHANISM),
+                REQUEST_FLAGS,
+                0,
+                bindings
+                    .as_mut()
+                    .map_or(ptr::null_mut(), |bindings| bindings as *mut ChannelBindings),
+                &mut input,
+                ptr::null_mut(),
+                &mut output,
+                &mut self.flags,
+                ptr::null_mut(),
+            );
+            let token = output.to_vec();
+            (self.library.release_buffer)(&mut 0, &mut output);
+            (major, token)
+        };
+
+        let major = self.library.check(major, minor)?;
+        Ok((Some(output), major & GSS_S_CONTINUE_NEEDED == 0))
+    }
+
+    pub(super) fn is_mutual(&self) -> bool {
+        self.flags & GSS_C_MUTUAL_FLAG != 0
+    }
+
+    /// The header of the wrap token of `message` and the message encrypted with its padding.
+    ///
+    /// Without a trailer buffer the library rotates the checksum into the header, as SSPI
+    /// does, so the server finds the token it expects.
+    pub(super) fn wrap(&mut self, message: &[u8]) -> Result<(Vec<u8>, Vec<u8>), PwshCoreError> {
+        let mut data = message.to_vec();
+        let mut iov = [
+            IovBuffer {
+                buffer_type: GSS_IOV_BUFFER_TYPE_HEADER | GSS_IOV_BUFFER_FLAG_ALLOCATE,
+                buffer: Buffer::empty(),
+            },
+            IovBuffer {
+                buffer_type: GSS_IOV_BUFFER_TYPE_DATA,
+                buffer: Buffer {
+                    length: data.len(),
+                    value: data.as_mut_ptr() as *mut c_void,
+                },
+            },
+            IovBuffer {
+                buffer_type: GSS_IOV_BUFFER_TYPE_PADDING | GSS_IOV_BUFFER_FLAG_ALLOCATE,
+                buffer: Buffer::empty(),
+            },
+        ];
+        let mut minor = 0;
+        let mut confidential = 0;
+        // SAFETY: the data is encrypted in place, the buffers the library allocated are
+        // released once copied
+        let (major, header, padding) = unsafe {
+            let major = (self.library.wrap_iov)(
+                &mut minor,
+                self.context,
+                1,
+                GSS_C_QOP_DEFAULT,
+                &mut confidential,
+                iov.as_mut_ptr(),
+                iov.len() as i32,
+            );
+            let header = iov[0].buffer.to_vec();
+            let padding = iov[2].buffer.to_vec();
+            (self.library.release_iov_buffer)(&mut 0, iov.as_mut_ptr(), iov.len() as i32);
+            (major, header, padding)
+        };
+
+        self.library.check(major, minor)?;
+        if confidential == 0 {
+            return Err(PwshCoreError::AuthenticationError(
+                "the Kerberos context does not encrypt".to_string(),
+            ));
+        }
+        data.extend_from_slice(&padding);
+        Ok((header, data))
+    }
+
+    /// The message of the wrap token of `header` and the `encrypted` data
+    pub(super) fn unwrap(
+        &mut self,
+        header: &[u8],
+        encrypted: &[u8],
+    ) -> Result<Vec<u8>, PwshCoreError> {
+        let token = [header, encrypted].concat();
+        let mut output = Buffer::empty();
+        let mut minor = 0;
+        // SAFETY: the buffers live through the call, the output is released once copied
+        let (major, message) = unsafe {
+            let major = (self.library.unwrap)(
+                &mut minor,
+                self.context,
+                &mut Buffer::borrowing(&token),
+                &mut output,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            let message = output.to_vec();
+            (self.library.release_buffer)(&mut 0, &mut output);
+            (major, message)
+        };
+
+        self.library.check(major, minor)?;
+        Ok(message)
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        let mut minor = 0;
+        // SAFETY: the handles are owned by the context, a null one is not released
+        unsafe {
+            if !self.context.is_null() {
+                (self.library.delete_sec_context)(&mut minor, &mut self.context, ptr::null_mut());
+            }
+            if !self.credential.is_null() {
+                (self.library.release_cred)(&mut minor, &mut self.credential);
+            }
+            (self.library.release_name)(&mut minor, &mut self.target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_name() {
+        assert_eq!(
+            target_name("HTTP/server.contoso.com"),
+            (
+                "HTTP@server.contoso.com".to_string(),
+                &NT_HOSTBASED_SERVICE[..]
+            )
+        );
+        assert_eq!(
+            target_name("HTTP/fe80::1"),
+            ("HTTP@fe80::1".to_string(), &NT_HOSTBASED_SERVICE[..])
+        );
+        assert_eq!(
+            target_name("HTTP/server.contoso.com@CONTOSO.COM"),
+            (
+                "HTTP/server.contoso.com@CONTOSO.COM".to_string(),
+                &NT_KRB5_PRINCIPAL_NAME[..]
+            )
+        );
+    }
+
+    #[test]
+    fn test_principal() {
+        assert_eq!(principal(r"contoso\user"), "user@CONTOSO");
+        assert_eq!(principal("user@CONTOSO.COM"), "user@CONTOSO.COM");
+        assert_eq!(principal("user"), "user");
+    }
+
+    #[test]
+    fn test_check() {
+        let unreachable = || -> Vec<String> { panic!("not an error") };
+        assert_eq!(check(0, unreachable).unwrap(), 0);
+        assert_eq!(
+            check(GSS_S_CONTINUE_NEEDED, unreachable).unwrap(),
+            GSS_S_CONTINUE_NEEDED
+        );
+        // GSS_S_DUPLICATE_TOKEN, a supplementary bit
+        assert_eq!(check(0x0000_0002, unreachable).unwrap(), 0x0000_0002);
+
+        // GSS_S_FAILURE, with the message of the mechanism
+        let error = check(0x000D_0000, || {
+            vec![
+                "Unspecified GSS failure".to_string(),
+                "Server not found in Kerberos database".to_string(),
+            ]
+        })
+        .unwrap_err();
+        assert!(
+            matches!(&error, PwshCoreError::AuthenticationError(message)
+                if message == "Unspecified GSS failure: Server not found in Kerberos database"),
+            "{error}"
+        );
+    }
+}
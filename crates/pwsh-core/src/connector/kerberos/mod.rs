@@ -0,0 +1,149 @@
+//! Kerberos through the security library of the system: GSSAPI on Unix, loaded from
+//! `libgssapi_krb5` when first used, SSPI on Windows.
+//!
+//! The tickets are those of the current user, its credential cache or logon session, unless a
+//! user and its password are given for the library to acquire them from the KDC.
+
+#[cfg(unix)]
+mod gssapi;
+#[cfg(windows)]
+mod sspi;
+
+#[cfg(unix)]
+use gssapi::Context;
+#[cfg(windows)]
+use sspi::Context;
+
+use crate::{PwshCoreError, connector::channel_bindings::ChannelBindings};
+
+/// A Kerberos handshake with a service, authenticating the server too: the context is only
+/// [complete](Self::is_complete) once the server proved who it is.
+pub struct Kerberos {
+    context: Context,
+    complete: bool,
+    /// The application data of the bindings of the TLS connection
+    channel_bindings: Option<Vec<u8>>,
+}
+
+impl Kerberos {
+    /// A handshake with the service named `spn`, see [`spn`](Self::spn), as the current user
+    /// or as the `(username, password)` of `credentials`.
+    ///
+    /// `username` is either `user@REALM` or `DOMAIN\user`.
+    pub fn new(spn: &str, credentials: Option<(&str, &str)>) -> Result<Self, PwshCoreError> {
+        Ok(Self {
+            context: Context::new(spn, credentials)?,
+            complete: false,
+            channel_bindings: None,
+        })
+    }
+
+    /// The handshake bound to the TLS connection of `bindings`, in the authenticator of the
+    /// ticket
+    pub fn with_channel_bindings(mut self, bindings: &ChannelBindings) -> Self {
+        self.channel_bindings = Some(bindings.application_data().to_vec());
+        self
+    }
+
+    /// The service principal name of WinRM on `host`, `HTTP/host`, whether the endpoint is
+    /// HTTP or HTTPS. The port of `host` is not part of it, nor are the brackets of an IPv6
+    /// address.
+    pub fn spn(host: &str) -> String {
+        let host = match host.strip_prefix('[') {
+            Some(bracketed) => bracketed
+                .split_once(']')
+                .map_or(bracketed, |(address, _)| address),
+            // An IPv6 address has several colons, a port follows the only one
+            None => match host.split_once(':') {
+                Some((name, port)) if !port.contains(':') => name,
+                _ => host,
+            },
+        };
+        format!("HTTP/{host}")
+    }
+
+    /// The `spn` the configuration names, else the one of WinRM on `host`
+    pub(crate) fn spn_or_default(spn: Option<&str>, host: &str) -> String {
+        spn.map_or_else(|| Self::spn(host), str::to_string)
+    }
+
+    /// Process the `token` the server answered, none to start the handshake, into the token
+    /// to send it, if there is more to send
+    pub fn step(&mut self, token: Option<&[u8]>) -> Result<Option<Vec<u8>>, PwshCoreError> {
+        if self.complete {
+            return Err(PwshCoreError::InvalidState(
+                "the Kerberos handshake is complete",
+            ));
+        }
+
+        let (token, complete) = self.context.step(token, self.channel_bindings.as_deref())?;
+        if complete && !self.context.is_mutual() {
+            return Err(PwshCoreError::AuthenticationError(
+                "the server did not authenticate itself".to_string(),
+            ));
+        }
+
+        self.complete = complete;
+        Ok(token.filter(|token| !token.is_empty()))
+    }
+
+    /// Whether the context is established, both sides authenticated
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// The header of the wrap token sealing `message` and the encrypted message, padded as
+    /// the cipher needs, the two parts of an encrypted body of WinRM.
+    ///
+    /// With AES the header is the 16 bytes of the token header and the rotated 44 bytes of
+    /// the confounder, the encrypted copy of the header and the checksum, there is no padding.
+    pub fn wrap(&mut self, message: &[u8]) -> Result<(Vec<u8>, Vec<u8>), PwshCoreError> {
+        self.established()?;
+        self.context.wrap(message)
+    }
+
+    /// The message of the `header` and the `encrypted` data of a wrap token of the server
+    pub fn unwrap(&mut self, header: &[u8], encrypted: &[u8]) -> Result<Vec<u8>, PwshCoreError> {
+        self.established()?;
+        self.context.unwrap(header, encrypted)
+    }
+
+    fn established(&self) -> Result<(), PwshCoreError> {
+        if !self.complete {
+            return Err(PwshCoreError::InvalidState(
+                "the Kerberos handshake is not complete",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spn() {
+        assert_eq!(
+            Kerberos::spn("server.contoso.com"),
+            "HTTP/server.contoso.com"
+        );
+        assert_eq!(
+            Kerberos::spn("server.contoso.com:5986"),
+            "HTTP/server.contoso.com"
+        );
+        assert_eq!(Kerberos::spn("192.168.1.10:5985"), "HTTP/192.168.1.10");
+        assert_eq!(Kerberos::spn("fe80::1"), "HTTP/fe80::1");
+        assert_eq!(Kerberos::spn("[fe80::1]"), "HTTP/fe80::1");
+        assert_eq!(Kerberos::spn("[fe80::1]:5985"), "HTTP/fe80::1");
+    }
+
+    #[test]
+    fn test_custom_spn() {
+        assert_eq!(
+            Kerberos::spn_or_default(Some("WSMAN/server.contoso.com"), "server"),
+            "WSMAN/server.contoso.com"
+        );
+        assert_eq!(Kerberos::spn_or_default(None, "server"), "HTTP/server");
+    }
+}
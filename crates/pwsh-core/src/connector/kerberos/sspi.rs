@@ -0,0 +1,445 @@
+//! The functions of SSPI used by the client, with the `Kerberos` package of `secur32`.
+
+use std::{ffi::c_void, ptr};
+
+use crate::PwshCoreError;
+
+const SECPKG_CRED_OUTBOUND: u32 = 2;
+const SECURITY_NATIVE_DREP: u32 = 0x10;
+const SEC_WINNT_AUTH_IDENTITY_UNICODE: u32 = 2;
+const SECBUFFER_VERSION: u32 = 0;
+const SECBUFFER_DATA: u32 = 1;
+const SECBUFFER_TOKEN: u32 = 2;
+const SECBUFFER_PADDING: u32 = 9;
+const SECBUFFER_CHANNEL_BINDINGS: u32 = 14;
+const SECPKG_ATTR_SIZES: u32 = 0;
+
+const SEC_E_OK: i32 = 0;
+const SEC_I_CONTINUE_NEEDED: i32 = 0x0009_0312;
+
+const ISC_REQ_MUTUAL_AUTH: u32 = 0x0000_0002;
+const ISC_REQ_REPLAY_DETECT: u32 = 0x0000_0004;
+const ISC_REQ_SEQUENCE_DETECT: u32 = 0x0000_0008;
+const ISC_REQ_CONFIDENTIALITY: u32 = 0x0000_0010;
+const ISC_REQ_ALLOCATE_MEMORY: u32 = 0x0000_0100;
+const ISC_REQ_INTEGRITY: u32 = 0x0001_0000;
+const REQUEST_FLAGS: u32 = ISC_REQ_MUTUAL_AUTH
+    | ISC_REQ_REPLAY_DETECT
+    | ISC_REQ_SEQUENCE_DETECT
+    | ISC_REQ_CONFIDENTIALITY
+    | ISC_REQ_ALLOCATE_MEMORY
+    | ISC_REQ_INTEGRITY;
+/// `ISC_RET_MUTUAL_AUTH`, the flag the context is returned with once the server authenticated
+const ISC_RET_MUTUAL_AUTH: u32 = 0x0000_0002;
+
+#[repr(C)]
+#[derive(Default)]
+struct SecHandle {
+    lower: usize,
+    upper: usize,
+}
+
+#[repr(C)]
+struct SecBuffer {
+    length: u32,
+    buffer_type: u32,
+    buffer: *mut c_void,
+}
+
+#[repr(C)]
+struct SecBufferDesc {
+    version: u32,
+    count: u32,
+    buffers: *mut SecBuffer,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct SecPkgContextSizes {
+    max_token: u32,
+    max_signature: u32,
+    block_size: u32,
+    security_trailer: u32,
+}
+
+#[repr(C)]
+struct SecWinntAuthIdentityW {
+    user: *mut u16,
+    user_length: u32,
+    domain: *mut u16,
+    domain_length: u32,
+    password: *mut u16,
+    password_length: u32,
+    flags: u32,
+}
+
+#[link(name = "secur32")]
+unsafe extern "system" {
+    fn AcquireCredentialsHandleW(
+        principal: *const u16,
+        package: *const u16,
+        credential_use: u32,
+        logon_id: *mut c_void,
+        auth_data: *mut c_void,
+        get_key_fn: *mut c_void,
+        get_key_argument: *mut c_void,
+        credential: *mut SecHandle,
+        expiry: *mut i64,
+    ) -> i32;
+
+    fn InitializeSecurityContextW(
+        credential: *mut SecHandle,
+        context: *mut SecHandle,
+        target_name: *const u16,
+        context_requirements: u32,
+        reserved1: u32,
+        target_data_representation: u32,
+        input: *mut SecBufferDesc,
+        reserved2: u32,
+        new_context: *mut SecHandle,
+        output: *mut SecBufferDesc,
+        context_attributes: *mut u32,
+        expiry: *mut i64,
+    ) -> i32;
+
+    fn QueryContextAttributesW(context: *mut SecHandle, attribute: u32, buffer: *mut c_void)
+    -> i32;
+
+    fn EncryptMessage(
+        context: *mut SecHandle,
+        quality_of_protection: u32,
+        message: *mut SecBufferDesc,
+        sequence: u32,
+    ) -> i32;
+
+    fn DecryptMessage(
+        context: *mut SecHandle,
+        message: *mut SecBufferDesc,
+        sequence: u32,
+        quality_of_protection: *mut u32,
+    ) -> i32;
+
+    fn FreeContextBuffer(buffer: *mut c_void) -> i32;
+
+    fn DeleteSecurityContext(context: *mut SecHandle) -> i32;
+
+    fn FreeCredentialsHandle(credential: *mut SecHandle) -> i32;
+}
+
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().collect()
+}
+
+fn wide_nul(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(Some(0)).collect()
+}
+
+/// The domain and the user of `username`: `DOMAIN\user` is split, a user principal name is
+/// given whole for SSPI to find its domain
+fn identity(username: &str) -> (&str, &str) {
+    username.split_once('\\').unwrap_or(("", username))
+}
+
+/// Whether the context is established once `InitializeSecurityContextW` returned `status`
+fn established(status: i32) -> Result<bool, PwshCoreError> {
+    match status {
+        SEC_E_OK => Ok(true),
+        SEC_I_CONTINUE_NEEDED => Ok(false),
+        status => Err(sspi_error("InitializeSecurityContextW", status)),
+    }
+}
+
+fn sspi_error(function: &str, status: i32) -> PwshCoreError {
+    PwshCoreError::AuthenticationError(format!("{function} failed with {status:#010X}"))
+}
+
+/// A security context of the Kerberos package, the handles released when dropped
+pub(super) struct Context {
+    credential: SecHandle,
+    context: Option<SecHandle>,
+    target: Vec<u16>,
+    flags: u32,
+}
+
+impl Context {
+    pub(super) fn new(spn: &str, credentials: Option<(&str, &str)>) -> Result<Self, PwshCoreError> {
+        let package = wide_nul("Kerberos");
+        let mut credential = SecHandle::default();
+
+        let identity = credentials.map(|(username, password)| {
+            let (domain, user) = identity(username);
+            (wide(user), wide(domain), wide(password))
+        });
+        let mut auth_data =
+            identity
+                .as_ref()
+                .map(|(user, domain, password)| SecWinntAuthIdentityW {
+                    user: user.as_ptr() as *mut u16,
+                    user_length: user.len() as u32,
+                    domain: domain.as_ptr() as *mut u16,
+                    domain_length: domain.len() as u32,
+                    password: password.as_ptr() as *mut u16,
+                    password_length: password.len() as u32,
+                    flags: SEC_WINNT_AUTH_IDENTITY_UNICODE,
+                });
+        let auth_data = auth_data.as_mut().map_or(ptr::null_mut(), |identity| {
+            identity as *mut _ as *mut c_void
+        });
+
+        // SAFETY: the strings and the identity live through the call
+        let status = unsafe {
+            AcquireCredentialsHandleW(
+                ptr::null(),
+                package.as_ptr(),
+                SECPKG_CRED_OUTBOUND,
+                ptr::null_mut(),
+                auth_data,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut credential,
+                ptr::null_mut(),
+            )
+        };
+        if status != SEC_E_OK {
+            return Err(sspi_error("AcquireCredentialsHandleW", status));
+        }
+
+        Ok(Self {
+            credential,
+            context: None,
+            target: wide_nul(spn),
+            flags: 0,
+        })
+    }
+
+    /// The token to send and whether the context is established, bound to the channel of the
+    /// `channel_bindings` application data
+    pub(super) fn step(
+        &mut self,
+        token: Option<&[u8]>,
+        channel_bindings: Option<&[u8]>,
+    ) -> Result<(Option<Vec<u8>>, bool), PwshCoreError> {
+        // A SEC_CHANNEL_BINDINGS without the addresses, the application data after it
+        let bindings = channel_bindings.map(|application_data| {
+            let mut bindings = vec![0; 24];
+            bindings.extend_from_slice(&(application_data.len() as u32).to_le_bytes());
+            bindings.extend_from_slice(&32u32.to_le_bytes());
+            bindings.extend_from_slice(application_data);
+            bindings
+        });
+        let mut input_buffers = token
+            .map(|token| SecBuffer {
+                length: token.len() as u32,
+                buffer_type: SECBUFFER_TOKEN,
+                buffer: token.as_ptr() as *mut c_void,
+            })
+            .into_iter()
+            .chain(bindings.as_ref().map(|bindings| SecBuffer {
+                length: bindings.len() as u32,
+                buffer_type: SECBUFFER_CHANNEL_BINDINGS,
+                buffer: bindings.as_ptr() as *mut c_void,
+            }))
+            .collect::<Vec<_>>();
+        let mut input = (!input_buffers.is_empty()).then_some(SecBufferDesc {
+            version: SECBUFFER_VERSION,
+            count: input_buffers.len() as u32,
+            buffers: input_buffers.as_mut_ptr(),
+        });
+        let mut output_buffer = SecBuffer {
+            length: 0,
+            buffer_type: SECBUFFER_TOKEN,
+            buffer: ptr::null_mut(),
+        };
+        let mut output = SecBufferDesc {
+            version: SECBUFFER_VERSION,
+            count: 1,
+            buffers: &mut output_buffer,
+        };
+
+        let mut new_context = SecHandle::default();
+        let context = self
+            .context
+            .as_mut()
+            .map_or(ptr::null_mut(), |context| context as *mut SecHandle);
+        // SAFETY: the buffers live through the call, the output one allocated by SSPI is freed
+        // once copied
+        let (status, token) = unsafe {
+            let status = InitializeSecurityContextW(
+                &mut self.credential,
+                context,
+                self.target.as_ptr(),
+                REQUEST_FLAGS,
+                0,
+                SECURITY_NATIVE_DREP,
+                input
+                    .as_mut()
+                    .map_or(ptr::null_mut(), |input| input as *mut SecBufferDesc),
+                0,
+                if context.is_null() {
+                    &mut new_context as *mut SecHandle
+                } else {
+                    context
+                },
+                &mut output,
+                &mut self.flags,
+                ptr::null_mut(),
+            );
+            let token = if output_buffer.buffer.is_null() {
+                Vec::new()
+            } else {
+                let token = std::slice::from_raw_parts(
+                    output_buffer.buffer as *const u8,
+                    output_buffer.length as usize,
+                )
+                .to_vec();
+                FreeContextBuffer(output_buffer.buffer);
+                token
+            };
+            (status, token)
+        };
+
+        if self.context.is_none() && (status == SEC_E_OK || status == SEC_I_CONTINUE_NEEDED) {
+            self.context = Some(new_context);
+        }
+        Ok((Some(token), established(status)?))
+    }
+
+    pub(super) fn is_mutual(&self) -> bool {
+        self.flags & ISC_RET_MUTUAL_AUTH != 0
+    }
+
+    fn established(&mut self) -> Result<&mut SecHandle, PwshCoreError> {
+        self.context.as_mut().ok_or(PwshCoreError::InvalidState(
+            "the Kerberos context is not established",
+        ))
+    }
+
+    /// The header of the wrap token of `message` and the message encrypted with its padding
+    pub(super) fn wrap(&mut self, message: &[u8]) -> Result<(Vec<u8>, Vec<u8>), PwshCoreError> {
+        let context = self.established()?;
+        let mut sizes = SecPkgContextSizes::default();
+        // SAFETY: the sizes are the structure of the attribute
+        let status = unsafe {
+            QueryContextAttributesW(
+                context,
+                SECPKG_ATTR_SIZES,
+                &mut sizes as *mut _ as *mut c_void,
+            )
+        };
+        if status != SEC_E_OK {
+            return Err(sspi_error("QueryContextAttributesW", status));
+        }
+
+        let mut header = vec![0; sizes.security_trailer as usize];
+        let mut data = message.to_vec();
+        let mut padding = vec![0; sizes.block_size as usize];
+        let mut buffers = [
+            SecBuffer {
+                length: header.len() as u32,
+                buffer_type: SECBUFFER_TOKEN,
+                buffer: header.as_mut_ptr() as *mut c_void,
+            },
+            SecBuffer {
+                length: data.len() as u32,
+                buffer_type: SECBUFFER_DATA,
+                buffer: data.as_mut_ptr() as *mut c_void,
+            },
+            SecBuffer {
+                length: padding.len() as u32,
+                buffer_type: SECBUFFER_PADDING,
+                buffer: padding.as_mut_ptr() as *mut c_void,
+            },
+        ];
+        let mut description = SecBufferDesc {
+            version: SECBUFFER_VERSION,
+            count: buffers.len() as u32,
+            buffers: buffers.as_mut_ptr(),
+        };
+        // SAFETY: the buffers live through the call, the data is encrypted in place
+        let status = unsafe { EncryptMessage(context, 0, &mut description, 0) };
+        if status != SEC_E_OK {
+            return Err(sspi_error("EncryptMessage", status));
+        }
+
+        // The header and the padding are as long as the cipher needs them
+        header.truncate(buffers[0].length as usize);
+        padding.truncate(buffers[2].length as usize);
+        data.extend_from_slice(&padding);
+        Ok((header, data))
+    }
+
+    /// The message of the wrap token of `header` and the `encrypted` data
+    pub(super) fn unwrap(
+        &mut self,
+        header: &[u8],
+        encrypted: &[u8],
+    ) -> Result<Vec<u8>, PwshCoreError> {
+        let context = self.established()?;
+        let mut header = header.to_vec();
+        let mut data = encrypted.to_vec();
+        let mut buffers = [
+            SecBuffer {
+                length: header.len() as u32,
+                buffer_type: SECBUFFER_TOKEN,
+                buffer: header.as_mut_ptr() as *mut c_void,
+            },
+            SecBuffer {
+                length: data.len() as u32,
+                buffer_type: SECBUFFER_DATA,
+                buffer: data.as_mut_ptr() as *mut c_void,
+            },
+        ];
+        let mut description = SecBufferDesc {
+            version: SECBUFFER_VERSION,
+            count: buffers.len() as u32,
+            buffers: buffers.as_mut_ptr(),
+        };
+        let mut quality_of_protection = 0;
+        // SAFETY: the buffers live through the call, the data is decrypted in place
+        let status =
+            unsafe { DecryptMessage(context, &mut description, 0, &mut quality_of_protection) };
+        if status != SEC_E_OK {
+            return Err(sspi_error("DecryptMessage", status));
+        }
+
+        data.truncate(buffers[1].length as usize);
+        Ok(data)
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        // SAFETY: the handles are owned by the context
+        unsafe {
+            if let Some(context) = &mut self.context {
+                DeleteSecurityContext(context);
+            }
+            FreeCredentialsHandle(&mut self.credential);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity() {
+        assert_eq!(identity(r"CONTOSO\user"), ("CONTOSO", "user"));
+        assert_eq!(identity("user@contoso.com"), ("", "user@contoso.com"));
+    }
+
+    #[test]
+    fn test_established() {
+        assert!(established(SEC_E_OK).unwrap());
+        assert!(!established(SEC_I_CONTINUE_NEEDED).unwrap());
+
+        // SEC_E_TARGET_UNKNOWN
+        let error = established(0x8009_0303_u32 as i32).unwrap_err();
+        assert!(
+            matches!(&error, PwshCoreError::AuthenticationError(message)
+                if message == "InitializeSecurityContextW failed with 0x80090303"),
+            "{error}"
+        );
+    }
+}
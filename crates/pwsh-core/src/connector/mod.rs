@@ -15,11 +15,43 @@ use crate::{
 pub use active_session::{ActiveSession, SessionStepResult, UserOperation};
 pub mod http;
 pub mod active_session;
+pub mod channel_bindings;
+pub mod encryption;
+pub mod kerberos;
+pub mod negotiate;
+pub mod ntlm;
+#[cfg(feature = "reqwest")]
+pub mod pool;
+#[cfg(feature = "reqwest")]
+pub mod transport;
+#[cfg(all(test, feature = "reqwest"))]
+pub(crate) mod test_server;
 
 #[derive(Debug, Clone)]
 pub enum Authentication {
     Basic { username: String, password: String },
-    // TODO: Add SSPI
+    /// NTLMv2 through the `Negotiate` scheme, `username` is either `DOMAIN\user` or
+    /// `user@domain.com`. The handshake takes several requests, only the
+    /// [`HttpTransport`](transport::HttpTransport) carries it.
+    Ntlm { username: String, password: String },
+    /// Kerberos through the `Negotiate` scheme, GSSAPI on Unix and SSPI on Windows, with the
+    /// tickets of the current user or, given both, of `username` and `password`. The service
+    /// is `HTTP/<server>` unless `spn` names another, the server has to prove it is that
+    /// service. Only the [`HttpTransport`](transport::HttpTransport) carries it.
+    Kerberos {
+        username: Option<String>,
+        password: Option<String>,
+        spn: Option<String>,
+    },
+    /// SPNEGO through the `Negotiate` scheme: Kerberos as with [`Authentication::Kerberos`],
+    /// NTLM when no ticket of the service can be had and `username` and `password` are
+    /// given. Only the [`HttpTransport`](transport::HttpTransport) carries it, which tells the
+    /// mechanism used.
+    Negotiate {
+        username: Option<String>,
+        password: Option<String>,
+        spn: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -176,7 +208,7 @@ impl Connector {
                     self.config.server.1,
                     self.config.scheme.clone(),
                     self.config.authentication.clone(),
-                );
+                )?;
 
                 let (xml_body, expect_shell_created) = runspace_pool.open()?;
 
@@ -0,0 +1,612 @@
+//! SPNEGO [RFC 4178], the mechanism behind the `Negotiate` scheme: Kerberos when the client
+//! gets a ticket of the service, NTLM otherwise.
+//!
+//! The tokens of the mechanism go in a `NegTokenInit` first, then in `NegTokenResp`s. With
+//! NTLM the `mechListMIC` protecting the list of the mechanisms is exchanged too, as
+//! [MS-SPNG] has it.
+
+use tracing::debug;
+
+use crate::{
+    PwshCoreError,
+    connector::{channel_bindings::ChannelBindings, kerberos::Kerberos, ntlm::Ntlm},
+};
+
+/// 1.3.6.1.5.5.2
+const SPNEGO_OID: &[u8] = b"\x2b\x06\x01\x05\x05\x02";
+/// 1.2.840.113554.1.2.2
+const KERBEROS_OID: &[u8] = b"\x2a\x86\x48\x86\xf7\x12\x01\x02\x02";
+/// 1.2.840.48018.1.2.2, the Kerberos of older Windows
+const MS_KERBEROS_OID: &[u8] = b"\x2a\x86\x48\x82\xf7\x12\x01\x02\x02";
+/// 1.3.6.1.4.1.311.2.2.10
+const NTLM_OID: &[u8] = b"\x2b\x06\x01\x04\x01\x82\x37\x02\x02\x0a";
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_ENUMERATED: u8 = 0x0A;
+const TAG_APPLICATION: u8 = 0x60;
+const TAG_NEG_TOKEN_INIT: u8 = 0xA0;
+const TAG_NEG_TOKEN_RESP: u8 = 0xA1;
+
+const ACCEPT_COMPLETED: u8 = 0;
+const REJECT: u8 = 2;
+
+/// The mechanism a [`Negotiate`] handshake settled on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mechanism {
+    Kerberos,
+    Ntlm,
+}
+
+impl std::fmt::Display for Mechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mechanism::Kerberos => write!(f, "Kerberos"),
+            Mechanism::Ntlm => write!(f, "NTLM"),
+        }
+    }
+}
+
+enum Context {
+    Kerberos(Kerberos),
+    Ntlm(Box<Ntlm>),
+}
+
+/// A SPNEGO handshake: [`step`](Self::step) without a token to start it, then with each token
+/// the server answers until it is [complete](Self::is_complete).
+pub struct Negotiate {
+    credentials: Option<(String, String)>,
+    context: Context,
+    /// The DER of the `MechTypeList` sent, what the `mechListMIC`s are computed over
+    mechanisms: Vec<u8>,
+    /// The bindings of the TLS connection, for NTLM too when it is fallen back to
+    channel_bindings: Option<ChannelBindings>,
+    started: bool,
+    complete: bool,
+}
+
+impl Negotiate {
+    /// A handshake with the service named `spn`, as the current user or as the
+    /// `(username, password)` of `credentials`.
+    ///
+    /// Kerberos is tried first, NTLM is fallen back to when no ticket of the service can be
+    /// had, as long as there is a password for it.
+    pub fn new(spn: &str, credentials: Option<(&str, &str)>) -> Result<Self, PwshCoreError> {
+        let kerberos = Kerberos::new(spn, credentials);
+        let (context, mechanisms) = match (kerberos, credentials) {
+            (Ok(kerberos), Some(_)) => (Context::Kerberos(kerberos), &[KERBEROS_OID, NTLM_OID][..]),
+            (Ok(kerberos), None) => (Context::Kerberos(kerberos), &[KERBEROS_OID][..]),
+            (Err(error), Some((username, password))) => {
+                debug!(%error, "Kerberos is not available, falling back to NTLM");
+                (
+                    Context::Ntlm(Box::new(Ntlm::new(username, password))),
+                    &[NTLM_OID][..],
+                )
+            }
+            (Err(error), None) => return Err(error),
+        };
+
+        Ok(Self {
+            credentials: credentials
+                .map(|(username, password)| (username.to_string(), password.to_string())),
+            context,
+            mechanisms: der(
+                TAG_SEQUENCE,
+                &mechanisms
+                    .iter()
+                    .map(|oid| der(TAG_OID, oid))
+                    .collect::<Vec<_>>()
+                    .concat(),
+            ),
+            channel_bindings: None,
+            started: false,
+            complete: false,
+        })
+    }
+
+    /// The handshake bound to the TLS connection of `bindings`, whichever the mechanism
+    pub fn with_channel_bindings(mut self, bindings: &ChannelBindings) -> Self {
+        self.context = match self.context {
+            Context::Kerberos(kerberos) => {
+                Context::Kerberos(kerberos.with_channel_bindings(bindings))
+            }
+            Context::Ntlm(ntlm) => Context::Ntlm(Box::new(ntlm.with_channel_bindings(bindings))),
+        };
+        self.channel_bindings = Some(bindings.clone());
+        self
+    }
+
+    /// The mechanism in use, the one the handshake settled on once complete
+    pub fn mechanism(&self) -> Mechanism {
+        match self.context {
+            Context::Kerberos(_) => Mechanism::Kerberos,
+            Context::Ntlm(_) => Mechanism::Ntlm,
+        }
+    }
+
+    /// Whether the context is established: Kerberos authenticated both sides, or NTLM sent
+    /// its `AUTHENTICATE_MESSAGE`
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Whether the next token the client sends is answered with a challenge, not with the
+    /// response to a request, the `NEGOTIATE_MESSAGE` of NTLM
+    pub(crate) fn expects_challenge(&self) -> bool {
+        matches!(&self.context, Context::Ntlm(ntlm) if ntlm.session_key().is_none())
+    }
+
+    /// `message` sealed for the server: the header of the wrap token, then the sealed data,
+    /// see [`Kerberos::wrap`]
+    pub fn wrap(&mut self, message: &[u8]) -> Result<(Vec<u8>, Vec<u8>), PwshCoreError> {
+        match &mut self.context {
+            Context::Ntlm(ntlm) => {
+                let (signature, sealed) = ntlm.seal(message)?;
+                Ok((signature.to_vec(), sealed))
+            }
+            Context::Kerberos(kerberos) => kerberos.wrap(message),
+        }
+    }
+
+    /// The message the server sealed, from the `header` of its wrap token and the `sealed`
+    /// data
+    pub fn unwrap(&mut self, header: &[u8], sealed: &[u8]) -> Result<Vec<u8>, PwshCoreError> {
+        match &mut self.context {
+            Context::Ntlm(ntlm) => ntlm.unseal(header, sealed),
+            Context::Kerberos(kerberos) => kerberos.unwrap(header, sealed),
+        }
+    }
+
+    /// Process the `token` the server answered, none to start the handshake, into the token
+    /// to send it, if there is more to send
+    pub fn step(&mut self, token: Option<&[u8]>) -> Result<Option<Vec<u8>>, PwshCoreError> {
+        let Some(token) = token else {
+            return self.start();
+        };
+        if !self.started {
+            return Err(PwshCoreError::InvalidState(
+                "the Negotiate handshake is not started",
+            ));
+        }
+
+        let response = NegTokenResp::parse(token)?;
+        if response.state == Some(REJECT) {
+            return Err(PwshCoreError::AuthenticationError(format!(
+                "the server rejected {}",
+                self.mechanism()
+            )));
+        }
+
+        // The server can choose NTLM over the optimistic Kerberos token
+        if let Some(supported) = response.mechanism {
+            let chosen = match supported {
+                KERBEROS_OID | MS_KERBEROS_OID => Mechanism::Kerberos,
+                NTLM_OID => Mechanism::Ntlm,
+                _ => {
+                    return Err(PwshCoreError::AuthenticationError(
+                        "the server chose an unknown mechanism".to_string(),
+                    ));
+                }
+            };
+            if chosen != self.mechanism() {
+                return self.fall_back_to_ntlm(chosen);
+            }
+        }
+
+        let mut sent = None;
+        if let Some(token) = response.token {
+            sent = match &mut self.context {
+                Context::Kerberos(kerberos) => {
+                    let sent = kerberos.step(Some(token))?;
+                    self.complete = kerberos.is_complete();
+                    sent
+                }
+                Context::Ntlm(ntlm) if ntlm.session_key().is_none() => {
+                    let authenticate = ntlm.authenticate(token)?;
+                    let mic = ntlm.sign(&self.mechanisms)?;
+                    ntlm.reset_security(true)?;
+                    self.complete = true;
+                    return Ok(Some(neg_token_resp(&authenticate, Some(&mic))));
+                }
+                Context::Ntlm(_) => {
+                    return Err(PwshCoreError::AuthenticationError(
+                        "the server sent NTLM a token once authenticated".to_string(),
+                    ));
+                }
+            };
+        }
+
+        if let (Context::Ntlm(ntlm), Some(mic)) = (&mut self.context, response.mic) {
+            ntlm.verify(&self.mechanisms, mic)?;
+            ntlm.reset_security(false)?;
+        }
+        if response.state == Some(ACCEPT_COMPLETED) && !self.complete {
+            return Err(PwshCoreError::AuthenticationError(format!(
+                "the server completed {} before the client",
+                self.mechanism()
+            )));
+        }
+
+        Ok(sent.map(|sent| neg_token_resp(&sent, None)))
+    }
+
+    /// The `NegTokenInit` with the first token of the mechanism
+    fn start(&mut self) -> Result<Option<Vec<u8>>, PwshCoreError> {
+        if self.started {
+            return Err(PwshCoreError::InvalidState(
+                "the Negotiate handshake is started",
+            ));
+        }
+
+        let token = match &mut self.context {
+            Context::Kerberos(kerberos) => kerberos.step(None),
+            Context::Ntlm(ntlm) => Ok(Some(ntlm.negotiate())),
+        };
+        let token = match (token, &self.credentials) {
+            (Ok(token), _) => token.unwrap_or_default(),
+            // Without a ticket there is no Kerberos token, NTLM is the one sent then
+            (Err(error), Some((username, password))) => {
+                debug!(%error, "Kerberos is not available, falling back to NTLM");
+                let mut ntlm = self.ntlm(username, password);
+                let token = ntlm.negotiate();
+                self.context = Context::Ntlm(Box::new(ntlm));
+                self.mechanisms = der(TAG_SEQUENCE, &der(TAG_OID, NTLM_OID));
+                token
+            }
+            (Err(error), None) => return Err(error),
+        };
+        self.started = true;
+
+        let init = der(
+            TAG_SEQUENCE,
+            &[
+                der(TAG_NEG_TOKEN_INIT, &self.mechanisms),
+                der(0xA2, &der(TAG_OCTET_STRING, &token)),
+            ]
+            .concat(),
+        );
+        let init = der(TAG_NEG_TOKEN_INIT, &init);
+        Ok(Some(der(
+            TAG_APPLICATION,
+            &[der(TAG_OID, SPNEGO_OID), init].concat(),
+        )))
+    }
+
+    /// The server chose NTLM, the Kerberos token is dropped for its `NEGOTIATE_MESSAGE`
+    fn fall_back_to_ntlm(&mut self, chosen: Mechanism) -> Result<Option<Vec<u8>>, PwshCoreError> {
+        let (Mechanism::Ntlm, Some((username, password))) = (chosen, &self.credentials) else {
+            return Err(PwshCoreError::AuthenticationError(format!(
+                "the server chose {chosen}, the client can not use it"
+            )));
+        };
+        debug!("The server chose NTLM over Kerberos");
+
+        let mut ntlm = self.ntlm(username, password);
+        let negotiate = ntlm.negotiate();
+        self.context = Context::Ntlm(Box::new(ntlm));
+        Ok(Some(neg_token_resp(&negotiate, None)))
+    }
+
+    fn ntlm(&self, username: &str, password: &str) -> Ntlm {
+        let ntlm = Ntlm::new(username, password);
+        match &self.channel_bindings {
+            Some(bindings) => ntlm.with_channel_bindings(bindings),
+            None => ntlm,
+        }
+    }
+}
+
+/// What the client reads of a `NegTokenResp`
+struct NegTokenResp<'a> {
+    state: Option<u8>,
+    mechanism: Option<&'a [u8]>,
+    token: Option<&'a [u8]>,
+    mic: Option<&'a [u8]>,
+}
+
+impl<'a> NegTokenResp<'a> {
+    fn parse(token: &'a [u8]) -> Result<Self, PwshCoreError> {
+        let (tag, resp, _) = tlv(token)?;
+        if tag != TAG_NEG_TOKEN_RESP {
+            return Err(invalid_token("not a NegTokenResp"));
+        }
+        let (tag, mut fields, _) = tlv(resp)?;
+        if tag != TAG_SEQUENCE {
+            return Err(invalid_token("a NegTokenResp is a sequence"));
+        }
+
+        let mut response = Self {
+            state: None,
+            mechanism: None,
+            token: None,
+            mic: None,
+        };
+        while !fields.is_empty() {
+            let (tag, field, rest) = tlv(fields)?;
+            let (inner_tag, value, _) = tlv(field)?;
+            match (tag, inner_tag) {
+                (0xA0, TAG_ENUMERATED) => response.state = value.first().copied(),
+                (0xA1, TAG_OID) => response.mechanism = Some(value),
+                (0xA2, TAG_OCTET_STRING) => response.token = Some(value),
+                (0xA3, TAG_OCTET_STRING) => response.mic = Some(value),
+                _ => return Err(invalid_token("unknown NegTokenResp field")),
+            }
+            fields = rest;
+        }
+        Ok(response)
+    }
+}
+
+/// The `NegTokenResp` of the client, carrying `token` and a `mechListMIC`
+fn neg_token_resp(token: &[u8], mic: Option<&[u8]>) -> Vec<u8> {
+    let mut fields = der(0xA2, &der(TAG_OCTET_STRING, token));
+    if let Some(mic) = mic {
+        fields.extend(der(0xA3, &der(TAG_OCTET_STRING, mic)));
+    }
+    der(TAG_NEG_TOKEN_RESP, &der(TAG_SEQUENCE, &fields))
+}
+
+/// The DER of `value` tagged `tag`
+fn der(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    match value.len() {
+        length @ 0..0x80 => encoded.push(length as u8),
+        length => {
+            let bytes = (length as u32).to_be_bytes();
+            let skipped = bytes.iter().take_while(|byte| **byte == 0).count();
+            encoded.push(0x80 | (4 - skipped) as u8);
+            encoded.extend_from_slice(&bytes[skipped..]);
+        }
+    }
+    encoded.extend_from_slice(value);
+    encoded
+}
+
+/// The tag, the value and what follows of the DER `input` starts with
+fn tlv(input: &[u8]) -> Result<(u8, &[u8], &[u8]), PwshCoreError> {
+    let [tag, first, rest @ ..] = input else {
+        return Err(invalid_token("truncated"));
+    };
+    let (length, rest) = match *first {
+        length @ 0..0x80 => (length as usize, rest),
+        long => {
+            let count = (long & 0x7F) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return Err(invalid_token("invalid length"));
+            }
+            let length = rest[..count]
+                .iter()
+                .fold(0usize, |length, byte| length << 8 | *byte as usize);
+            (length, &rest[count..])
+        }
+    };
+    if rest.len() < length {
+        return Err(invalid_token("truncated"));
+    }
+    Ok((*tag, &rest[..length], &rest[length..]))
+}
+
+fn invalid_token(reason: &str) -> PwshCoreError {
+    PwshCoreError::AuthenticationError(format!("invalid SPNEGO token: {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::ntlm::tests::{FLAGS, challenge_message, target_info};
+
+    fn hex(hex: &str) -> Vec<u8> {
+        let hex: String = hex.split_whitespace().collect();
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// A handshake of NTLM alone, as one without a ticket of the service
+    fn ntlm_negotiate() -> Negotiate {
+        Negotiate {
+            credentials: Some((r"Domain\User".to_string(), "Password".to_string())),
+            context: Context::Ntlm(Box::new(Ntlm::new(r"Domain\User", "Password"))),
+            mechanisms: der(TAG_SEQUENCE, &der(TAG_OID, NTLM_OID)),
+            channel_bindings: None,
+            started: false,
+            complete: false,
+        }
+    }
+
+    /// The `NegTokenResp` of a server
+    fn server_resp(
+        state: Option<u8>,
+        mechanism: Option<&[u8]>,
+        token: Option<&[u8]>,
+        mic: Option<&[u8]>,
+    ) -> Vec<u8> {
+        let mut fields = Vec::new();
+        if let Some(state) = state {
+            fields.extend(der(0xA0, &der(TAG_ENUMERATED, &[state])));
+        }
+        if let Some(mechanism) = mechanism {
+            fields.extend(der(0xA1, &der(TAG_OID, mechanism)));
+        }
+        if let Some(token) = token {
+            fields.extend(der(0xA2, &der(TAG_OCTET_STRING, token)));
+        }
+        if let Some(mic) = mic {
+            fields.extend(der(0xA3, &der(TAG_OCTET_STRING, mic)));
+        }
+        der(TAG_NEG_TOKEN_RESP, &der(TAG_SEQUENCE, &fields))
+    }
+
+    #[test]
+    fn test_neg_token_init() {
+        let mut negotiate = ntlm_negotiate();
+        let init = negotiate.step(None).unwrap().unwrap();
+
+        let mut expected = hex("6048 0606 2b0601050502
+             a03e 303c
+             a00e 300c 060a 2b06010401823702020a
+             a22a 0428");
+        expected.extend(Ntlm::new(r"Domain\User", "Password").negotiate());
+        assert_eq!(init, expected);
+        assert!(negotiate.expects_challenge());
+        assert!(negotiate.step(None).is_err());
+    }
+
+    #[test]
+    fn test_neg_token_resp() {
+        assert_eq!(
+            neg_token_resp(&[1, 2, 3], Some(&[9; 16])),
+            [hex("a11d 301b a205 0403 010203 a312 0410"), vec![9; 16]].concat()
+        );
+        assert_eq!(
+            neg_token_resp(&[1, 2, 3], None),
+            hex("a109 3007 a205 0403 010203")
+        );
+    }
+
+    #[test]
+    fn test_parse_accept_incomplete() {
+        let token = hex("a11c 301a
+             a003 0a01 01
+             a10c 060a 2b06010401823702020a
+             a205 0403 010203");
+        let response = NegTokenResp::parse(&token).unwrap();
+        assert_eq!(response.state, Some(1));
+        assert_eq!(response.mechanism, Some(NTLM_OID));
+        assert_eq!(response.token, Some(&[1, 2, 3][..]));
+        assert_eq!(response.mic, None);
+    }
+
+    #[test]
+    fn test_parse_accept_completed() {
+        let mut token = hex("a11b 3019 a003 0a01 00 a312 0410");
+        token.extend([7; 16]);
+        let response = NegTokenResp::parse(&token).unwrap();
+        assert_eq!(response.state, Some(ACCEPT_COMPLETED));
+        assert_eq!(response.mechanism, None);
+        assert_eq!(response.token, None);
+        assert_eq!(response.mic, Some(&[7; 16][..]));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        // A NegTokenInit
+        assert!(NegTokenResp::parse(&hex("a003 3001 00")).is_err());
+        // Not a sequence
+        assert!(NegTokenResp::parse(&hex("a103 0401 00")).is_err());
+        // An unknown field
+        assert!(NegTokenResp::parse(&hex("a107 3005 a403 0a01 00")).is_err());
+        // A field longer than the sequence
+        assert!(NegTokenResp::parse(&hex("a107 3005 a205 0403 0102")).is_err());
+    }
+
+    #[test]
+    fn test_der_lengths() {
+        assert_eq!(der(TAG_OCTET_STRING, &[]), [TAG_OCTET_STRING, 0]);
+        assert_eq!(
+            der(TAG_OCTET_STRING, &[0; 0x7F])[..2],
+            [TAG_OCTET_STRING, 0x7F]
+        );
+        assert_eq!(
+            der(TAG_OCTET_STRING, &[0; 200])[..3],
+            [TAG_OCTET_STRING, 0x81, 200]
+        );
+        assert_eq!(
+            der(TAG_OCTET_STRING, &[0; 300])[..4],
+            [TAG_OCTET_STRING, 0x82, 0x01, 0x2C]
+        );
+
+        for length in [0, 0x7F, 0x80, 300, 70_000] {
+            let mut encoded = der(TAG_OCTET_STRING, &vec![0xAB; length]);
+            encoded.extend([0xCD, 0xEF]);
+            let (tag, value, rest) = tlv(&encoded).unwrap();
+            assert_eq!(tag, TAG_OCTET_STRING);
+            assert_eq!(value.len(), length);
+            assert_eq!(rest, [0xCD, 0xEF]);
+        }
+    }
+
+    #[test]
+    fn test_tlv_invalid_lengths() {
+        assert!(tlv(&[]).is_err());
+        assert!(tlv(&[TAG_OCTET_STRING]).is_err());
+        // The value is shorter than its length
+        assert!(tlv(&[TAG_OCTET_STRING, 3, 1, 2]).is_err());
+        assert!(tlv(&[TAG_OCTET_STRING, 0x82, 0x01, 0x2C, 0, 0]).is_err());
+        // The indefinite length of BER
+        assert!(tlv(&[TAG_OCTET_STRING, 0x80, 0, 0]).is_err());
+        // More length bytes than there are, or than a length of 32 bits has
+        assert!(tlv(&[TAG_OCTET_STRING, 0x82, 0x01]).is_err());
+        assert!(tlv(&[TAG_OCTET_STRING, 0x85, 0, 0, 0, 0, 1, 0]).is_err());
+    }
+
+    #[test]
+    fn test_mech_list_mic() {
+        let mut negotiate = ntlm_negotiate();
+        negotiate.step(None).unwrap();
+
+        let challenge = challenge_message(FLAGS, &target_info());
+        let reply = negotiate
+            .step(Some(&server_resp(
+                Some(1),
+                Some(NTLM_OID),
+                Some(&challenge),
+                None,
+            )))
+            .unwrap()
+            .unwrap();
+        assert!(negotiate.is_complete());
+        assert!(!negotiate.expects_challenge());
+
+        let response = NegTokenResp::parse(&reply).unwrap();
+        assert_eq!(&response.token.unwrap()[..8], b"NTLMSSP\0");
+        let Context::Ntlm(ntlm) = &negotiate.context else {
+            panic!("the handshake is NTLM");
+        };
+        // The server checks the MIC of the client over the mechanisms it sent, then signs them
+        let mut acceptor = ntlm.acceptor().unwrap();
+        acceptor
+            .verify(&negotiate.mechanisms, response.mic.unwrap())
+            .unwrap();
+        let mic = acceptor.sign(&negotiate.mechanisms).unwrap();
+
+        let completed = server_resp(Some(ACCEPT_COMPLETED), None, None, Some(&mic));
+        assert_eq!(negotiate.step(Some(&completed)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_mech_list_mic_mismatch() {
+        let mut negotiate = ntlm_negotiate();
+        negotiate.step(None).unwrap();
+        let challenge = challenge_message(FLAGS, &target_info());
+        negotiate
+            .step(Some(&server_resp(
+                Some(1),
+                Some(NTLM_OID),
+                Some(&challenge),
+                None,
+            )))
+            .unwrap();
+
+        let mut mic = vec![0; 16];
+        mic[0] = 1;
+        let completed = server_resp(Some(ACCEPT_COMPLETED), None, None, Some(&mic));
+        assert!(negotiate.step(Some(&completed)).is_err());
+    }
+
+    #[test]
+    fn test_rejected() {
+        let mut negotiate = ntlm_negotiate();
+        negotiate.step(None).unwrap();
+
+        let error = negotiate
+            .step(Some(&server_resp(Some(REJECT), None, None, None)))
+            .unwrap_err();
+        assert!(
+            matches!(&error, PwshCoreError::AuthenticationError(message) if message == "the server rejected NTLM"),
+            "{error}"
+        );
+    }
+}
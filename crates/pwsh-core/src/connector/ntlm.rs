@@ -0,0 +1,900 @@
+//! The client side of NTLMv2 [MS-NLMP]: the `NEGOTIATE_MESSAGE` opening the handshake and the
+//! `AUTHENTICATE_MESSAGE` answering the `CHALLENGE_MESSAGE` of the server.
+//!
+//! The messages are carried by the transport, over HTTP in the `Authorization` and
+//! `WWW-Authenticate` headers of the `Negotiate` scheme, see
+//! [`HttpTransport`](crate::connector::transport::HttpTransport).
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use openssl::{
+    hash::{MessageDigest, hash},
+    pkey::PKey,
+    sign::Signer,
+};
+
+use crate::{PwshCoreError, connector::channel_bindings::ChannelBindings};
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+const REQUEST_TARGET: u32 = 0x0000_0004;
+const NEGOTIATE_SIGN: u32 = 0x0000_0010;
+const NEGOTIATE_SEAL: u32 = 0x0000_0020;
+const NEGOTIATE_NTLM: u32 = 0x0000_0200;
+const NEGOTIATE_ALWAYS_SIGN: u32 = 0x0000_8000;
+const NEGOTIATE_EXTENDED_SESSIONSECURITY: u32 = 0x0008_0000;
+const NEGOTIATE_TARGET_INFO: u32 = 0x0080_0000;
+const NEGOTIATE_VERSION: u32 = 0x0200_0000;
+const NEGOTIATE_128: u32 = 0x2000_0000;
+const NEGOTIATE_KEY_EXCH: u32 = 0x4000_0000;
+const NEGOTIATE_56: u32 = 0x8000_0000;
+
+/// What the client asks for, the server answers with the flags it agrees to
+const NEGOTIATE_FLAGS: u32 = NEGOTIATE_UNICODE
+    | REQUEST_TARGET
+    | NEGOTIATE_SIGN
+    | NEGOTIATE_SEAL
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSIONSECURITY
+    | NEGOTIATE_TARGET_INFO
+    | NEGOTIATE_VERSION
+    | NEGOTIATE_128
+    | NEGOTIATE_KEY_EXCH
+    | NEGOTIATE_56;
+
+const MSV_AV_EOL: u16 = 0x0000;
+const MSV_AV_FLAGS: u16 = 0x0006;
+const MSV_AV_TIMESTAMP: u16 = 0x0007;
+const MSV_AV_CHANNEL_BINDINGS: u16 = 0x000A;
+
+/// The `MsvAvFlags` bit telling the `AUTHENTICATE_MESSAGE` carries a MIC
+const AV_FLAG_MIC: u32 = 0x0000_0002;
+
+/// Windows 10 build 19041, speaking the revision 15 of NTLMSSP
+const VERSION: [u8; 8] = [10, 0, 0x61, 0x4A, 0, 0, 0, 0x0F];
+
+/// The length of the fixed part of the `AUTHENTICATE_MESSAGE`, the version and the MIC included
+const AUTHENTICATE_HEADER: usize = 88;
+const MIC_OFFSET: usize = 72;
+
+/// The seconds between the FILETIME epoch, 1601, and the UNIX one
+const FILETIME_UNIX_EPOCH: u64 = 11_644_473_600;
+
+const CLIENT_SIGNING: &[u8] = b"session key to client-to-server signing key magic constant\0";
+const SERVER_SIGNING: &[u8] = b"session key to server-to-client signing key magic constant\0";
+const CLIENT_SEALING: &[u8] = b"session key to client-to-server sealing key magic constant\0";
+const SERVER_SEALING: &[u8] = b"session key to server-to-client sealing key magic constant\0";
+
+/// An NTLM handshake: [`negotiate`](Self::negotiate), then
+/// [`authenticate`](Self::authenticate) with the challenge of the server.
+pub struct Ntlm {
+    user: String,
+    domain: String,
+    password: String,
+    negotiate: Option<Vec<u8>>,
+    session_key: Option<[u8; 16]>,
+    security: Option<SessionSecurity>,
+    /// The hash of the bindings of the TLS connection, sent as `MsvAvChannelBindings`
+    channel_bindings: Option<[u8; 16]>,
+}
+
+impl Ntlm {
+    /// A handshake authenticating `username`, either `DOMAIN\user` or `user@domain.com`
+    pub fn new(username: &str, password: &str) -> Self {
+        let (domain, user) = match username.split_once('\\') {
+            Some((domain, user)) => (domain.to_string(), user.to_string()),
+            // A user principal name is sent whole, its domain is found by the server
+            None => (String::new(), username.to_string()),
+        };
+
+        Self {
+            user,
+            domain,
+            password: password.to_string(),
+            negotiate: None,
+            session_key: None,
+            security: None,
+            channel_bindings: None,
+        }
+    }
+
+    /// The handshake bound to the TLS connection of `bindings`
+    pub fn with_channel_bindings(mut self, bindings: &ChannelBindings) -> Self {
+        self.channel_bindings = Some(*bindings.digest());
+        self
+    }
+
+    /// The `NEGOTIATE_MESSAGE` opening the handshake
+    pub fn negotiate(&mut self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(40);
+        message.extend_from_slice(SIGNATURE);
+        message.extend_from_slice(&1u32.to_le_bytes());
+        message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+        // No domain nor workstation is supplied
+        message.extend_from_slice(&[0; 16]);
+        message.extend_from_slice(&VERSION);
+
+        self.negotiate = Some(message.clone());
+        message
+    }
+
+    /// The `AUTHENTICATE_MESSAGE` answering the `CHALLENGE_MESSAGE` of the server
+    pub fn authenticate(&mut self, challenge: &[u8]) -> Result<Vec<u8>, PwshCoreError> {
+        let mut client_challenge = [0; 8];
+        let mut exported_session_key = [0; 16];
+        openssl::rand::rand_bytes(&mut client_challenge).map_err(crypto_error)?;
+        openssl::rand::rand_bytes(&mut exported_session_key).map_err(crypto_error)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| (since.as_secs() + FILETIME_UNIX_EPOCH) * 10_000_000)
+            .unwrap_or_default();
+
+        self.authenticate_with(challenge, client_challenge, exported_session_key, now)
+    }
+
+    /// The key the session security of the messages is derived from, once authenticated
+    pub fn session_key(&self) -> Option<&[u8; 16]> {
+        self.session_key.as_ref()
+    }
+
+    /// The signature of the next `message` the client sends, once authenticated
+    pub fn sign(&mut self, message: &[u8]) -> Result<[u8; 16], PwshCoreError> {
+        let security = self.security()?;
+        security
+            .client
+            .signature(message, security.flags & NEGOTIATE_KEY_EXCH != 0)
+    }
+
+    /// Check `signature` is the one of the next `message` the server sends, once authenticated
+    pub fn verify(&mut self, message: &[u8], signature: &[u8]) -> Result<(), PwshCoreError> {
+        let security = self.security()?;
+        let expected = security
+            .server
+            .signature(message, security.flags & NEGOTIATE_KEY_EXCH != 0)?;
+        if signature != expected {
+            return Err(PwshCoreError::AuthenticationError(
+                "the signature of the server does not match".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// `message` sealed for the server, with its signature, once authenticated
+    pub fn seal(&mut self, message: &[u8]) -> Result<([u8; 16], Vec<u8>), PwshCoreError> {
+        let security = self.sealing()?;
+        let mut sealed = message.to_vec();
+        security.client.sealing.apply(&mut sealed);
+        let signature = security
+            .client
+            .signature(message, security.flags & NEGOTIATE_KEY_EXCH != 0)?;
+        Ok((signature, sealed))
+    }
+
+    /// The message the server `sealed`, checked against its `signature`, once authenticated
+    pub fn unseal(&mut self, signature: &[u8], sealed: &[u8]) -> Result<Vec<u8>, PwshCoreError> {
+        let security = self.sealing()?;
+        let mut message = sealed.to_vec();
+        security.server.sealing.apply(&mut message);
+        let expected = security
+            .server
+            .signature(&message, security.flags & NEGOTIATE_KEY_EXCH != 0)?;
+        if signature != expected {
+            return Err(PwshCoreError::AuthenticationError(
+                "the signature of the sealed message does not match".to_string(),
+            ));
+        }
+        Ok(message)
+    }
+
+    /// Start the sequence and the key stream of the messages the client sends, or of those
+    /// the server sends, over, as SPNEGO has it once the `mechListMIC` is signed or checked
+    pub(crate) fn reset_security(&mut self, client: bool) -> Result<(), PwshCoreError> {
+        let security = self.security()?;
+        if client {
+            security.client = Direction::new(
+                &security.session_key,
+                security.flags,
+                CLIENT_SIGNING,
+                CLIENT_SEALING,
+            )?;
+        } else {
+            security.server = Direction::new(
+                &security.session_key,
+                security.flags,
+                SERVER_SIGNING,
+                SERVER_SEALING,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn sealing(&mut self) -> Result<&mut SessionSecurity, PwshCoreError> {
+        let security = self.security()?;
+        if security.flags & NEGOTIATE_SEAL == 0 {
+            return Err(PwshCoreError::AuthenticationError(
+                "the server does not seal the messages".to_string(),
+            ));
+        }
+        Ok(security)
+    }
+
+    fn security(&mut self) -> Result<&mut SessionSecurity, PwshCoreError> {
+        let security = self
+            .security
+            .as_mut()
+            .ok_or(PwshCoreError::InvalidState("NTLM is not authenticated"))?;
+        if security.flags & NEGOTIATE_EXTENDED_SESSIONSECURITY == 0 {
+            return Err(PwshCoreError::AuthenticationError(
+                "the server does not support extended session security".to_string(),
+            ));
+        }
+        Ok(security)
+    }
+
+    fn authenticate_with(
+        &mut self,
+        challenge_message: &[u8],
+        client_challenge: [u8; 8],
+        exported_session_key: [u8; 16],
+        now: u64,
+    ) -> Result<Vec<u8>, PwshCoreError> {
+        let negotiate = self.negotiate.as_ref().ok_or(PwshCoreError::InvalidState(
+            "NTLM challenge before the negotiation",
+        ))?;
+        let challenge = Challenge::parse(challenge_message)?;
+        if challenge.flags & NEGOTIATE_UNICODE == 0 {
+            return Err(PwshCoreError::AuthenticationError(
+                "the server does not speak Unicode".to_string(),
+            ));
+        }
+
+        let response_key = self.ntowf_v2()?;
+        // With a timestamp from the server the MIC is expected in place of a LMv2 response
+        let (timestamp, lm_response, target_info) = match challenge.timestamp {
+            Some(timestamp) => (
+                timestamp,
+                vec![0; 24],
+                challenge.client_target_info(true, self.channel_bindings.as_ref()),
+            ),
+            None => {
+                let mut lm_response = hmac_md5(
+                    &response_key,
+                    &[&challenge.server_challenge, &client_challenge],
+                )?
+                .to_vec();
+                lm_response.extend_from_slice(&client_challenge);
+                let target_info = match &self.channel_bindings {
+                    Some(bindings) => challenge.client_target_info(false, Some(bindings)),
+                    None => challenge.target_info.clone(),
+                };
+                (now, lm_response, target_info)
+            }
+        };
+
+        let mut blob = vec![0x01, 0x01, 0, 0, 0, 0, 0, 0];
+        blob.extend_from_slice(&timestamp.to_le_bytes());
+        blob.extend_from_slice(&client_challenge);
+        blob.extend_from_slice(&[0; 4]);
+        blob.extend_from_slice(&target_info);
+        blob.extend_from_slice(&[0; 4]);
+
+        let nt_proof = hmac_md5(&response_key, &[&challenge.server_challenge, &blob])?;
+        let mut nt_response = nt_proof.to_vec();
+        nt_response.extend_from_slice(&blob);
+
+        let key_exchange_key = hmac_md5(&response_key, &[&nt_proof])?;
+        let (session_key, encrypted_session_key) = if challenge.flags & NEGOTIATE_KEY_EXCH != 0 {
+            let mut encrypted = exported_session_key;
+            Rc4::new(&key_exchange_key).apply(&mut encrypted);
+            (exported_session_key, encrypted.to_vec())
+        } else {
+            (key_exchange_key, Vec::new())
+        };
+
+        let mut message = authenticate_message(
+            challenge.flags,
+            &[
+                &lm_response,
+                &nt_response,
+                &utf16(&self.domain),
+                &utf16(&self.user),
+                &[],
+                &encrypted_session_key,
+            ],
+        );
+        if challenge.timestamp.is_some() {
+            let mic = hmac_md5(&session_key, &[negotiate, challenge_message, &message])?;
+            message[MIC_OFFSET..MIC_OFFSET + 16].copy_from_slice(&mic);
+        }
+
+        self.security = Some(SessionSecurity {
+            session_key,
+            flags: challenge.flags,
+            client: Direction::new(
+                &session_key,
+                challenge.flags,
+                CLIENT_SIGNING,
+                CLIENT_SEALING,
+            )?,
+            server: Direction::new(
+                &session_key,
+                challenge.flags,
+                SERVER_SIGNING,
+                SERVER_SEALING,
+            )?,
+        });
+        self.session_key = Some(session_key);
+        Ok(message)
+    }
+
+    /// The server side of the session of an authenticated handshake, signing and sealing what
+    /// the client verifies and unseals
+    #[cfg(test)]
+    pub(crate) fn acceptor(&self) -> Result<Self, PwshCoreError> {
+        let security = self
+            .security
+            .as_ref()
+            .ok_or(PwshCoreError::InvalidState("NTLM is not authenticated"))?;
+        let (session_key, flags) = (security.session_key, security.flags);
+        Ok(Self {
+            user: self.user.clone(),
+            domain: self.domain.clone(),
+            password: self.password.clone(),
+            negotiate: None,
+            session_key: Some(session_key),
+            security: Some(SessionSecurity {
+                session_key,
+                flags,
+                client: Direction::new(&session_key, flags, SERVER_SIGNING, SERVER_SEALING)?,
+                server: Direction::new(&session_key, flags, CLIENT_SIGNING, CLIENT_SEALING)?,
+            }),
+            channel_bindings: None,
+        })
+    }
+
+    /// The NT hash of the password keyed by the user and the domain
+    fn ntowf_v2(&self) -> Result<[u8; 16], PwshCoreError> {
+        let nt_hash = md4(&utf16(&self.password));
+        let identity = utf16(&format!("{}{}", self.user.to_uppercase(), self.domain));
+        hmac_md5(&nt_hash, &[&identity])
+    }
+}
+
+/// What the client reads of a `CHALLENGE_MESSAGE`
+struct Challenge {
+    flags: u32,
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+    timestamp: Option<u64>,
+}
+
+impl Challenge {
+    fn parse(message: &[u8]) -> Result<Self, PwshCoreError> {
+        if message.len() < 48 || &message[..8] != SIGNATURE || u32_at(message, 8) != 2 {
+            return Err(invalid_challenge("not a CHALLENGE_MESSAGE"));
+        }
+
+        let target_info = field(message, 40)?.to_vec();
+        let timestamp = av_pairs(&target_info)?
+            .into_iter()
+            .find(|(id, _)| *id == MSV_AV_TIMESTAMP)
+            .and_then(|(_, value)| value.try_into().ok())
+            .map(u64::from_le_bytes);
+
+        Ok(Self {
+            flags: u32_at(message, 20),
+            server_challenge: message[24..32].try_into().expect("8 bytes of the message"),
+            target_info,
+            timestamp,
+        })
+    }
+
+    /// The target info of the server with the pairs of the client: its `MsvAvFlags` telling
+    /// a MIC is sent, the `MsvAvChannelBindings`
+    fn client_target_info(&self, mic: bool, channel_bindings: Option<&[u8; 16]>) -> Vec<u8> {
+        let pairs = av_pairs(&self.target_info).unwrap_or_default();
+        let mut flags = if mic { AV_FLAG_MIC } else { 0 };
+        let mut target_info = Vec::with_capacity(self.target_info.len() + 28);
+        for (id, value) in pairs {
+            match id {
+                MSV_AV_FLAGS => {
+                    flags |= value.try_into().map(u32::from_le_bytes).unwrap_or_default();
+                }
+                id => push_av_pair(&mut target_info, id, value),
+            }
+        }
+        if flags != 0 {
+            push_av_pair(&mut target_info, MSV_AV_FLAGS, &flags.to_le_bytes());
+        }
+        if let Some(bindings) = channel_bindings {
+            push_av_pair(&mut target_info, MSV_AV_CHANNEL_BINDINGS, bindings);
+        }
+        push_av_pair(&mut target_info, MSV_AV_EOL, &[]);
+        target_info
+    }
+}
+
+/// The signing and sealing of the messages of an authenticated session
+struct SessionSecurity {
+    session_key: [u8; 16],
+    /// The flags the server agreed to
+    flags: u32,
+    client: Direction,
+    server: Direction,
+}
+
+/// The keys and the sequence of the messages one side sends
+struct Direction {
+    signing_key: [u8; 16],
+    sealing: Rc4,
+    sequence: u32,
+}
+
+impl Direction {
+    fn new(
+        session_key: &[u8; 16],
+        flags: u32,
+        signing_magic: &[u8],
+        sealing_magic: &[u8],
+    ) -> Result<Self, PwshCoreError> {
+        // The sealing key is weakened to what the server agreed to
+        let sealing_key = if flags & NEGOTIATE_128 != 0 {
+            &session_key[..]
+        } else if flags & NEGOTIATE_56 != 0 {
+            &session_key[..7]
+        } else {
+            &session_key[..5]
+        };
+
+        Ok(Self {
+            signing_key: md5(&[session_key, signing_magic])?,
+            sealing: Rc4::new(&md5(&[sealing_key, sealing_magic])?),
+            sequence: 0,
+        })
+    }
+
+    /// The `NTLMSSP_MESSAGE_SIGNATURE` of the next message, with extended session security
+    fn signature(&mut self, message: &[u8], key_exchange: bool) -> Result<[u8; 16], PwshCoreError> {
+        let sequence = self.sequence.to_le_bytes();
+        let mut checksum = [0; 8];
+        checksum.copy_from_slice(&hmac_md5(&self.signing_key, &[&sequence, message])?[..8]);
+        if key_exchange {
+            self.sealing.apply(&mut checksum);
+        }
+        self.sequence = self.sequence.wrapping_add(1);
+
+        let mut signature = [0; 16];
+        signature[..4].copy_from_slice(&1u32.to_le_bytes());
+        signature[4..12].copy_from_slice(&checksum);
+        signature[12..].copy_from_slice(&sequence);
+        Ok(signature)
+    }
+}
+
+/// The pairs of `target_info` before its `MsvAvEOL`
+fn av_pairs(target_info: &[u8]) -> Result<Vec<(u16, &[u8])>, PwshCoreError> {
+    let mut pairs = Vec::new();
+    let mut rest = target_info;
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let length = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        if id == MSV_AV_EOL {
+            return Ok(pairs);
+        }
+        let value = rest
+            .get(4..4 + length)
+            .ok_or_else(|| invalid_challenge("truncated target info"))?;
+        pairs.push((id, value));
+        rest = &rest[4 + length..];
+    }
+    Err(invalid_challenge("the target info does not end"))
+}
+
+fn push_av_pair(target_info: &mut Vec<u8>, id: u16, value: &[u8]) {
+    target_info.extend_from_slice(&id.to_le_bytes());
+    target_info.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    target_info.extend_from_slice(value);
+}
+
+/// The `AUTHENTICATE_MESSAGE` carrying `payloads` in the order of their fields: the LM and
+/// NT responses, the domain, the user, the workstation and the encrypted session key. The MIC
+/// is left zeroed.
+fn authenticate_message(flags: u32, payloads: &[&[u8]; 6]) -> Vec<u8> {
+    let length = payloads.iter().map(|payload| payload.len()).sum::<usize>();
+    let mut message = Vec::with_capacity(AUTHENTICATE_HEADER + length);
+    message.extend_from_slice(SIGNATURE);
+    message.extend_from_slice(&3u32.to_le_bytes());
+
+    let mut offset = AUTHENTICATE_HEADER;
+    for payload in payloads {
+        message.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        message.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        message.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += payload.len();
+    }
+    message.extend_from_slice(&flags.to_le_bytes());
+    message.extend_from_slice(&VERSION);
+    message.extend_from_slice(&[0; 16]);
+
+    for payload in payloads {
+        message.extend_from_slice(payload);
+    }
+    message
+}
+
+fn u32_at(message: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(
+        message[at..at + 4]
+            .try_into()
+            .expect("4 bytes of the message"),
+    )
+}
+
+/// The payload a `len, max len, offset` field at `at` points to
+fn field(message: &[u8], at: usize) -> Result<&[u8], PwshCoreError> {
+    let length = u16::from_le_bytes([message[at], message[at + 1]]) as usize;
+    let offset = u32_at(message, at + 4) as usize;
+    message
+        .get(offset..offset + length)
+        .ok_or_else(|| invalid_challenge("a field points past the end of the message"))
+}
+
+fn utf16(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn md5(parts: &[&[u8]]) -> Result<[u8; 16], PwshCoreError> {
+    let digest = hash(MessageDigest::md5(), &parts.concat()).map_err(crypto_error)?;
+    let mut md5 = [0; 16];
+    md5.copy_from_slice(&digest);
+    Ok(md5)
+}
+
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> Result<[u8; 16], PwshCoreError> {
+    let key = PKey::hmac(key).map_err(crypto_error)?;
+    let mut signer = Signer::new(MessageDigest::md5(), &key).map_err(crypto_error)?;
+    for part in parts {
+        signer.update(part).map_err(crypto_error)?;
+    }
+
+    let mut mac = [0; 16];
+    signer.sign(&mut mac).map_err(crypto_error)?;
+    Ok(mac)
+}
+
+/// MD4 [RFC 1320], which OpenSSL 3 only has in its legacy provider
+fn md4(data: &[u8]) -> [u8; 16] {
+    let mut state: [u32; 4] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    for block in message.chunks_exact(64) {
+        let words: [u32; 16] = std::array::from_fn(|i| u32_at(block, i * 4));
+        // Each step updates a, then the registers rotate so the next one updates d, c, then b
+        let mut registers = state;
+        let step = |v: &mut [u32; 4], value: u32, k: usize, rotation: u32, constant: u32| {
+            let updated = v[0]
+                .wrapping_add(value)
+                .wrapping_add(words[k])
+                .wrapping_add(constant)
+                .rotate_left(rotation);
+            *v = [v[3], updated, v[1], v[2]];
+        };
+
+        for i in 0..16 {
+            let [_, b, c, d] = registers;
+            step(
+                &mut registers,
+                (b & c) | (!b & d),
+                i,
+                [3, 7, 11, 19][i % 4],
+                0,
+            );
+        }
+        for i in 0..16 {
+            let [_, b, c, d] = registers;
+            let value = (b & c) | (b & d) | (c & d);
+            step(
+                &mut registers,
+                value,
+                (i % 4) * 4 + i / 4,
+                [3, 5, 9, 13][i % 4],
+                0x5A82_7999,
+            );
+        }
+        for (i, k) in [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15]
+            .into_iter()
+            .enumerate()
+        {
+            let [_, b, c, d] = registers;
+            step(
+                &mut registers,
+                b ^ c ^ d,
+                k,
+                [3, 9, 11, 15][i % 4],
+                0x6ED9_EBA1,
+            );
+        }
+
+        for (word, added) in state.iter_mut().zip(registers) {
+            *word = word.wrapping_add(added);
+        }
+    }
+
+    let mut digest = [0; 16];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+/// The RC4 key stream, which OpenSSL 3 only has in its legacy provider
+struct Rc4 {
+    state: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4 {
+    fn new(key: &[u8]) -> Self {
+        let mut state: [u8; 256] = std::array::from_fn(|i| i as u8);
+        let mut j = 0u8;
+        for i in 0..256 {
+            j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+            state.swap(i, j as usize);
+        }
+        Self { state, i: 0, j: 0 }
+    }
+
+    fn apply(&mut self, data: &mut [u8]) {
+        for byte in data {
+            self.i = self.i.wrapping_add(1);
+            self.j = self.j.wrapping_add(self.state[self.i as usize]);
+            self.state.swap(self.i as usize, self.j as usize);
+            let k = self.state[self.i as usize].wrapping_add(self.state[self.j as usize]);
+            *byte ^= self.state[k as usize];
+        }
+    }
+}
+
+fn invalid_challenge(reason: &str) -> PwshCoreError {
+    PwshCoreError::AuthenticationError(format!("invalid NTLM challenge: {reason}"))
+}
+
+fn crypto_error(error: openssl::error::ErrorStack) -> PwshCoreError {
+    PwshCoreError::AuthenticationError(error.to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // The values of the NTLMv2 example of [MS-NLMP] 4.2.4
+    const SERVER_CHALLENGE: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+    const CLIENT_CHALLENGE: [u8; 8] = [0xaa; 8];
+    const RANDOM_SESSION_KEY: [u8; 16] = [0x55; 16];
+    pub(crate) const FLAGS: u32 = 0xe28a_8233;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    /// The `MsvAvNbDomainName` and `MsvAvNbComputerName` of the example
+    pub(crate) fn target_info() -> Vec<u8> {
+        let mut target_info = Vec::new();
+        push_av_pair(&mut target_info, 0x0002, &utf16("Domain"));
+        push_av_pair(&mut target_info, 0x0001, &utf16("Server"));
+        push_av_pair(&mut target_info, MSV_AV_EOL, &[]);
+        target_info
+    }
+
+    /// A `CHALLENGE_MESSAGE` with the target name `Server` and `target_info`
+    pub(crate) fn challenge_message(flags: u32, target_info: &[u8]) -> Vec<u8> {
+        let target_name = utf16("Server");
+        let mut message = SIGNATURE.to_vec();
+        message.extend_from_slice(&2u32.to_le_bytes());
+        message.extend_from_slice(&(target_name.len() as u16).to_le_bytes());
+        message.extend_from_slice(&(target_name.len() as u16).to_le_bytes());
+        message.extend_from_slice(&56u32.to_le_bytes());
+        message.extend_from_slice(&flags.to_le_bytes());
+        message.extend_from_slice(&SERVER_CHALLENGE);
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&(target_info.len() as u16).to_le_bytes());
+        message.extend_from_slice(&(target_info.len() as u16).to_le_bytes());
+        message.extend_from_slice(&((56 + target_name.len()) as u32).to_le_bytes());
+        message.extend_from_slice(&[0x06, 0x00, 0x70, 0x17, 0x00, 0x00, 0x00, 0x0f]);
+        message.extend_from_slice(&target_name);
+        message.extend_from_slice(target_info);
+        message
+    }
+
+    /// The example authenticated, with its `AUTHENTICATE_MESSAGE`
+    fn authenticated() -> (Ntlm, Vec<u8>) {
+        let mut ntlm = Ntlm::new(r"Domain\User", "Password");
+        ntlm.negotiate();
+        let authenticate = ntlm
+            .authenticate_with(
+                &challenge_message(FLAGS, &target_info()),
+                CLIENT_CHALLENGE,
+                RANDOM_SESSION_KEY,
+                0,
+            )
+            .unwrap();
+        (ntlm, authenticate)
+    }
+
+    #[test]
+    fn test_md4() {
+        // [RFC 1320] A.5
+        assert_eq!(hex(&md4(b"")), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(hex(&md4(b"abc")), "a448017aaf21d8525fc10ae87aa6729d");
+        assert_eq!(
+            hex(&md4(b"message digest")),
+            "d9130a8164549fe818874806e1c7014b"
+        );
+    }
+
+    #[test]
+    fn test_ntowf_v2() {
+        let ntlm = Ntlm::new(r"Domain\User", "Password");
+        assert_eq!(
+            hex(&md4(&utf16("Password"))),
+            "a4f49c406510bdcab6824ee7c30fd852"
+        );
+        assert_eq!(
+            hex(&ntlm.ntowf_v2().unwrap()),
+            "0c868a403bfd7a93a3001ef22ef02e3f"
+        );
+    }
+
+    #[test]
+    fn test_authenticate_message() {
+        let (ntlm, authenticate) = authenticated();
+
+        assert_eq!(&authenticate[..8], SIGNATURE);
+        assert_eq!(u32_at(&authenticate, 8), 3);
+        assert_eq!(u32_at(&authenticate, 60), FLAGS);
+
+        let lm_response = field(&authenticate, 12).unwrap();
+        assert_eq!(
+            hex(lm_response),
+            "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa"
+        );
+
+        let nt_response = field(&authenticate, 20).unwrap();
+        assert_eq!(hex(&nt_response[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+        let mut blob = vec![0x01, 0x01, 0, 0, 0, 0, 0, 0];
+        blob.extend_from_slice(&[0; 8]);
+        blob.extend_from_slice(&CLIENT_CHALLENGE);
+        blob.extend_from_slice(&[0; 4]);
+        blob.extend_from_slice(&target_info());
+        blob.extend_from_slice(&[0; 4]);
+        assert_eq!(&nt_response[16..], blob);
+
+        assert_eq!(field(&authenticate, 28).unwrap(), utf16("Domain"));
+        assert_eq!(field(&authenticate, 36).unwrap(), utf16("User"));
+        assert_eq!(
+            hex(field(&authenticate, 52).unwrap()),
+            "c5dad2544fc9799094ce1ce90bc9d03e"
+        );
+        assert_eq!(ntlm.session_key(), Some(&RANDOM_SESSION_KEY));
+        // Without a timestamp from the server no MIC is sent
+        assert_eq!(&authenticate[MIC_OFFSET..MIC_OFFSET + 16], [0; 16]);
+    }
+
+    #[test]
+    fn test_seal() {
+        let (mut ntlm, _) = authenticated();
+
+        let (signature, sealed) = ntlm.seal(&utf16("Plaintext")).unwrap();
+        assert_eq!(hex(&sealed), "54e50165bf1936dc996020c1811b0f06fb5f");
+        assert_eq!(hex(&signature), "010000007fb38ec5c55d497600000000");
+    }
+
+    #[test]
+    fn test_unseal() {
+        let (mut client, _) = authenticated();
+        let mut server = client.acceptor().unwrap();
+
+        let (signature, sealed) = server.seal(b"the response").unwrap();
+        assert_eq!(client.unseal(&signature, &sealed).unwrap(), b"the response");
+
+        let (mut signature, sealed) = server.seal(b"the next response").unwrap();
+        signature[4] ^= 1;
+        assert!(client.unseal(&signature, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_mic_with_timestamp() {
+        let mut target_info = Vec::new();
+        push_av_pair(&mut target_info, 0x0002, &utf16("Domain"));
+        push_av_pair(&mut target_info, MSV_AV_TIMESTAMP, &42u64.to_le_bytes());
+        push_av_pair(&mut target_info, MSV_AV_EOL, &[]);
+        let challenge = challenge_message(FLAGS, &target_info);
+
+        let mut ntlm = Ntlm::new(r"Domain\User", "Password");
+        let negotiate = ntlm.negotiate();
+        let mut authenticate = ntlm
+            .authenticate_with(&challenge, CLIENT_CHALLENGE, RANDOM_SESSION_KEY, 0)
+            .unwrap();
+
+        // The LMv2 response is zeroed, the blob has the timestamp of the server and the flags
+        assert_eq!(field(&authenticate, 12).unwrap(), [0; 24]);
+        let nt_response = field(&authenticate, 20).unwrap();
+        assert_eq!(nt_response[24..32], 42u64.to_le_bytes());
+        let pairs = av_pairs(&nt_response[44..]).unwrap();
+        assert!(pairs.contains(&(MSV_AV_FLAGS, &AV_FLAG_MIC.to_le_bytes()[..])));
+
+        let mic: [u8; 16] = authenticate[MIC_OFFSET..MIC_OFFSET + 16]
+            .try_into()
+            .unwrap();
+        authenticate[MIC_OFFSET..MIC_OFFSET + 16].fill(0);
+        let expected = hmac_md5(
+            &RANDOM_SESSION_KEY,
+            &[&negotiate, &challenge, &authenticate],
+        )
+        .unwrap();
+        assert_eq!(mic, expected);
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        let challenge = Challenge::parse(&challenge_message(FLAGS, &target_info())).unwrap();
+        assert_eq!(challenge.flags, FLAGS);
+        assert_eq!(challenge.server_challenge, SERVER_CHALLENGE);
+        assert_eq!(challenge.target_info, target_info());
+        assert_eq!(challenge.timestamp, None);
+
+        let mut negotiate = Ntlm::new("user", "password").negotiate();
+        negotiate.resize(48, 0);
+        assert!(Challenge::parse(&negotiate).is_err());
+        assert!(Challenge::parse(&challenge_message(FLAGS, &target_info())[..40]).is_err());
+
+        // The target info points past the end of the message
+        let mut message = challenge_message(FLAGS, &target_info());
+        message.truncate(message.len() - 1);
+        assert!(Challenge::parse(&message).is_err());
+    }
+
+    #[test]
+    fn test_av_pairs() {
+        let target_info = target_info();
+        assert_eq!(
+            av_pairs(&target_info).unwrap(),
+            [
+                (0x0002, &utf16("Domain")[..]),
+                (0x0001, &utf16("Server")[..])
+            ]
+        );
+
+        // Without its MsvAvEOL
+        assert!(av_pairs(&target_info[..target_info.len() - 4]).is_err());
+        // A value longer than what is left
+        assert!(av_pairs(&[0x02, 0x00, 0x10, 0x00, 0x44, 0x00]).is_err());
+        assert!(av_pairs(&[]).is_err());
+    }
+
+    #[test]
+    fn test_client_target_info() {
+        let challenge = Challenge::parse(&challenge_message(FLAGS, &target_info())).unwrap();
+        let bindings = [0x11; 16];
+
+        let target_info = challenge.client_target_info(true, Some(&bindings));
+        assert_eq!(
+            av_pairs(&target_info).unwrap(),
+            [
+                (0x0002, &utf16("Domain")[..]),
+                (0x0001, &utf16("Server")[..]),
+                (MSV_AV_FLAGS, &AV_FLAG_MIC.to_le_bytes()[..]),
+                (MSV_AV_CHANNEL_BINDINGS, &bindings[..]),
+            ]
+        );
+    }
+}
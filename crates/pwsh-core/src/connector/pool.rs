@@ -0,0 +1,687 @@
+//! The connections of the endpoints, kept open and authenticated for the next envelopes: the
+//! bursts of a pool, its polling `Receive`s and parallel pipelines, do not pay for a TCP
+//! connection, a TLS handshake and an authentication each.
+//!
+//! NTLM and Kerberos authenticate a connection, not a request, so each connection of an
+//! endpoint is an [`HttpTransport`] on a client of its own, keeping the context of its
+//! handshake. An envelope goes on an idle connection, on a new one below the `max_connections`
+//! of the [`PoolConfig`], else waits for the first one to be idle: a Signal is not held behind
+//! a Receive waiting for output on another connection.
+//!
+//! A connection an envelope failed or was cancelled on is closed, the state of its handshake
+//! unknown, and so is one idle past the `idle_timeout`, the server may have closed it.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use futures_util::future::{poll_fn, try_join_all};
+use protocol_winrm::{
+    transport::{SoapRequest, SoapResponse, Transport},
+    ws_management::WsMan,
+};
+use tracing::{debug, instrument};
+
+use crate::connector::{
+    Authentication, ConnectorConfig,
+    transport::{HttpTransport, HttpTransportError},
+};
+
+/// What the client of each connection is built from
+pub type ClientBuilder = Arc<dyn Fn() -> reqwest::ClientBuilder + Send + Sync>;
+
+#[derive(Clone, typed_builder::TypedBuilder)]
+pub struct PoolConfig {
+    /// The builder of the client of a connection, e.g. one trusting the certificate of the
+    /// server or going through a proxy, the keep-alive of the pool is set on top of it
+    #[builder(default = Arc::new(reqwest::Client::builder))]
+    client_builder: ClientBuilder,
+
+    /// How long a connection is kept idle before it is closed, its handshake with it
+    #[builder(default = Duration::from_secs(90))]
+    idle_timeout: Duration,
+
+    /// The connections of an endpoint open at once, the envelopes beyond them wait
+    #[builder(default = 4)]
+    max_connections: usize,
+
+    /// The connections [`prewarm`](PooledTransport::prewarm) opens and authenticates
+    #[builder(default = 1)]
+    prewarm: usize,
+
+    /// The interval of the TCP keep-alive probes, keeping the idle connections through the
+    /// firewalls and the proxies, none to not send them
+    #[builder(default = Some(Duration::from_secs(30)))]
+    tcp_keepalive: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl std::fmt::Debug for PoolConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolConfig")
+            .field("idle_timeout", &self.idle_timeout)
+            .field("max_connections", &self.max_connections)
+            .field("prewarm", &self.prewarm)
+            .field("tcp_keepalive", &self.tcp_keepalive)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PoolConfig {
+    /// The client of a connection: its one connection is kept while idle for `idle_timeout`,
+    /// the certificate of the server told for the channel bindings
+    fn client(&self) -> Result<reqwest::Client, HttpTransportError> {
+        Ok((self.client_builder)()
+            .tls_info(true)
+            .pool_max_idle_per_host(1)
+            .pool_idle_timeout(self.idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive)
+            .build()?)
+    }
+}
+
+/// What the connections are shared by: the endpoint and the credentials authenticating them,
+/// the SHA-256 of the password included for a connection to only be reused by who
+/// authenticated it.
+///
+/// The password is kept by the transports of the open connections, to authenticate them again,
+/// not by the endpoint: its connections are opened from the [`PooledTransport`] sending on them
+#[derive(PartialEq, Eq, Hash)]
+struct PoolKey {
+    url: String,
+    scheme: &'static str,
+    username: Option<String>,
+    password: Option<[u8; 32]>,
+    spn: Option<String>,
+}
+
+impl PoolKey {
+    fn new(config: &ConnectorConfig) -> Self {
+        let (scheme, username, password, spn) = match &config.authentication {
+            Authentication::Basic { username, password } => {
+                ("Basic", Some(username), Some(password), None)
+            }
+            Authentication::Ntlm { username, password } => {
+                ("NTLM", Some(username), Some(password), None)
+            }
+            Authentication::Kerberos {
+                username,
+                password,
+                spn,
+            } => (
+                "Kerberos",
+                username.as_ref(),
+                password.as_ref(),
+                spn.as_ref(),
+            ),
+            Authentication::Negotiate {
+                username,
+                password,
+                spn,
+            } => (
+                "Negotiate",
+                username.as_ref(),
+                password.as_ref(),
+                spn.as_ref(),
+            ),
+        };
+
+        Self {
+            url: config.wsman_to(None),
+            scheme,
+            username: username.cloned(),
+            password: password.map(|password| openssl::sha::sha256(password.as_bytes())),
+            spn: spn.cloned(),
+        }
+    }
+}
+
+/// The connections of the endpoints, a clone shares them.
+///
+/// The [`transport`](Self::transport)s of the configurations of a same endpoint and
+/// credentials share their connections.
+#[derive(Clone, Default)]
+pub struct ConnectionPool {
+    config: PoolConfig,
+    endpoints: Arc<Mutex<HashMap<PoolKey, Arc<Endpoint>>>>,
+}
+
+impl std::fmt::Debug for ConnectionPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionPool")
+            .field("config", &self.config)
+            .field("endpoints", &lock(&self.endpoints).len())
+            .finish()
+    }
+}
+
+impl ConnectionPool {
+    pub fn new(config: PoolConfig) -> Self {
+        Self {
+            config,
+            endpoints: Arc::default(),
+        }
+    }
+
+    /// The transport of the endpoint of `config` on the connections of its credentials
+    pub fn transport(
+        &self,
+        config: &ConnectorConfig,
+    ) -> Result<PooledTransport, HttpTransportError> {
+        let transport = HttpTransport::with_client(self.config.client()?, config);
+        let mut endpoints = lock(&self.endpoints);
+        // The endpoints no transport sends on and without a connection left are forgotten
+        endpoints.retain(|_, endpoint| {
+            let mut connections = lock(&endpoint.connections);
+            connections.prune(self.config.idle_timeout);
+            Arc::strong_count(endpoint) > 1 || connections.open > 0
+        });
+
+        let endpoint = endpoints.entry(PoolKey::new(config)).or_insert_with(|| {
+            Arc::new(Endpoint {
+                config: self.config.clone(),
+                connections: Mutex::default(),
+            })
+        });
+        Ok(PooledTransport {
+            endpoint: Arc::clone(endpoint),
+            transport,
+        })
+    }
+}
+
+/// The connections of an endpoint for a set of credentials
+#[derive(Debug)]
+struct Endpoint {
+    config: PoolConfig,
+    connections: Mutex<Connections>,
+}
+
+#[derive(Debug, Default)]
+struct Connections {
+    /// The connections no envelope is sent on, the last used last
+    idle: Vec<Connection>,
+    /// The connections open, idle or not
+    open: usize,
+    /// The envelopes waiting for a connection to be idle, all woken when one is
+    waiters: Vec<Waker>,
+}
+
+impl Connections {
+    /// Close the idle connections unused for `idle_timeout`, the server may have closed them
+    fn prune(&mut self, idle_timeout: Duration) {
+        let idle = self.idle.len();
+        self.idle
+            .retain(|connection| connection.last_used.elapsed() < idle_timeout);
+        if self.idle.len() < idle {
+            self.open -= idle - self.idle.len();
+            debug!(
+                connections = self.open,
+                "Closed the connections idle too long"
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Connection {
+    transport: HttpTransport,
+    last_used: Instant,
+}
+
+impl Endpoint {
+    /// An idle connection, a new one of `transport` when all are busy and there can be more,
+    /// else the first one to be idle
+    async fn checkout(
+        &self,
+        transport: &HttpTransport,
+    ) -> Result<Checkout<'_>, HttpTransportError> {
+        let mut waiting = false;
+        poll_fn(|context| {
+            let mut connections = lock(&self.connections);
+            connections.prune(self.config.idle_timeout);
+            if let Some(connection) = connections.idle.pop() {
+                return Poll::Ready(Ok(self.checked_out(connection)));
+            }
+
+            if connections.open < self.config.max_connections.max(1) {
+                let client = match self.config.client() {
+                    Ok(client) => client,
+                    Err(error) => return Poll::Ready(Err(error)),
+                };
+                connections.open += 1;
+                debug!(connections = connections.open, "New connection");
+                return Poll::Ready(Ok(self.checked_out(Connection {
+                    transport: transport.with_other_client(client),
+                    last_used: Instant::now(),
+                })));
+            }
+
+            if !waiting {
+                debug!("All the connections are busy, waiting for one");
+                waiting = true;
+            }
+            if !connections
+                .waiters
+                .iter()
+                .any(|waker| waker.will_wake(context.waker()))
+            {
+                connections.waiters.push(context.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    fn checked_out(&self, connection: Connection) -> Checkout<'_> {
+        Checkout {
+            endpoint: self,
+            connection: Some(connection),
+            reusable: true,
+        }
+    }
+}
+
+/// A connection an envelope is sent on, idle again once dropped, closed when the envelope
+/// failed or was dropped before its response
+struct Checkout<'a> {
+    endpoint: &'a Endpoint,
+    connection: Option<Connection>,
+    /// Whether no envelope is being sent on the connection and the last one was answered
+    reusable: bool,
+}
+
+impl std::ops::Deref for Checkout<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection
+            .as_ref()
+            .expect("the connection is kept until the drop")
+    }
+}
+
+impl std::ops::DerefMut for Checkout<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.connection
+            .as_mut()
+            .expect("the connection is kept until the drop")
+    }
+}
+
+impl Drop for Checkout<'_> {
+    fn drop(&mut self) {
+        let Some(connection) = self.connection.take() else {
+            return;
+        };
+        let waiters = {
+            let mut connections = lock(&self.endpoint.connections);
+            if self.reusable {
+                connections.idle.push(connection);
+            } else {
+                connections.open -= 1;
+                debug!(
+                    connections = connections.open,
+                    "Closed the connection of a failed envelope"
+                );
+            }
+            std::mem::take(&mut connections.waiters)
+        };
+        // Every waiter checks again, for the idle connection or the one it can open, the one dropped before it is polled does not hold the
+        // others back
+        for waker in waiters {
+            waker.wake();
+        }
+    }
+}
+
+/// The [`Transport`] of an endpoint of a [`ConnectionPool`], sending each envelope on one of
+/// its authenticated connections. A clone shares them.
+#[derive(Debug, Clone)]
+pub struct PooledTransport {
+    endpoint: Arc<Endpoint>,
+    /// What the connections it opens are made after, it sends nothing itself
+    transport: HttpTransport,
+}
+
+impl PooledTransport {
+    pub fn url(&self) -> &str {
+        self.transport.url()
+    }
+
+    /// The connections open or being opened to the endpoint
+    pub fn connections(&self) -> usize {
+        lock(&self.endpoint.connections).open
+    }
+
+    /// Open and authenticate the `prewarm` connections of the [`PoolConfig`], sending an
+    /// Identify on each, for the first envelopes not to wait for their handshakes
+    #[instrument(skip_all, name = "PooledTransport::prewarm", fields(url = %self.url()))]
+    pub async fn prewarm(&self) -> Result<(), HttpTransportError> {
+        let config = &self.endpoint.config;
+        let count = config.prewarm.min(config.max_connections.max(1));
+
+        // All are held at once, for each Identify to go on a connection of its own
+        let mut connections = Vec::with_capacity(count);
+        for _ in 0..count {
+            connections.push(self.endpoint.checkout(&self.transport).await?);
+        }
+
+        let identify = WsMan::builder()
+            .to(self.url().to_string())
+            .build()
+            .identify()
+            .into_element()
+            .to_string();
+        try_join_all(connections.iter_mut().map(|connection| {
+            let identify = SoapRequest::new(identify.clone());
+            async move { send(connection, identify).await }
+        }))
+        .await?;
+        Ok(())
+    }
+}
+
+impl Transport for PooledTransport {
+    type Error = HttpTransportError;
+
+    #[instrument(skip_all, name = "PooledTransport::send", fields(url = %self.url()))]
+    async fn send(&self, request: SoapRequest) -> Result<SoapResponse, Self::Error> {
+        let mut connection = self.endpoint.checkout(&self.transport).await?;
+        send(&mut connection, request).await
+    }
+}
+
+/// Send `request` on `connection`, kept for the next envelopes once answered
+async fn send(
+    connection: &mut Checkout<'_>,
+    request: SoapRequest,
+) -> Result<SoapResponse, HttpTransportError> {
+    connection.reusable = false;
+    let response = connection.transport.send(request).await;
+    connection.last_used = Instant::now();
+    connection.reusable = response.is_ok();
+    response
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::test_server::{Response, TestServer, basic};
+
+    fn pool(config: PoolConfig, server: &TestServer) -> PooledTransport {
+        ConnectionPool::new(config)
+            .transport(&server.config(basic("user", "password")))
+            .unwrap()
+    }
+
+    /// Wait for the server to have received `count` requests
+    async fn received(server: &TestServer, count: usize) {
+        while server.requests().len() < count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_endpoints_of_the_credentials() {
+        let server = TestServer::new();
+        let pool = ConnectionPool::default();
+        let transport = |username, password| {
+            pool.transport(&server.config(basic(username, password)))
+                .unwrap()
+        };
+
+        let user = transport("user", "password");
+        let same = transport("user", "password");
+        let other_password = transport("user", "other");
+        let other_user = transport("other", "password");
+        assert!(Arc::ptr_eq(&user.endpoint, &same.endpoint));
+        assert!(!Arc::ptr_eq(&user.endpoint, &other_password.endpoint));
+        assert!(!Arc::ptr_eq(&user.endpoint, &other_user.endpoint));
+        assert_eq!(lock(&pool.endpoints).len(), 3);
+
+        // The connection of a password is not reused by another
+        user.send(SoapRequest::new("<s:Envelope/>")).await.unwrap();
+        same.send(SoapRequest::new("<s:Envelope/>")).await.unwrap();
+        other_password
+            .send(SoapRequest::new("<s:Envelope/>"))
+            .await
+            .unwrap();
+        assert_eq!(server.connections(), 2);
+        let requests = server.requests();
+        // user:other
+        assert_eq!(
+            requests[2].header("Authorization"),
+            Some("Basic dXNlcjpvdGhlcg==")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_burst_within_max_connections() {
+        let server = TestServer::new();
+        for _ in 0..6 {
+            server
+                .respond(Response::envelope(200, "<s:Envelope/>").delay(Duration::from_millis(50)));
+        }
+        let transport = pool(PoolConfig::builder().max_connections(2).build(), &server);
+
+        let responses = futures_util::future::join_all(
+            (0..6).map(|_| transport.send(SoapRequest::new("<s:Envelope/>"))),
+        )
+        .await;
+        assert!(responses.iter().all(Result::is_ok), "{responses:?}");
+        assert_eq!(server.requests().len(), 6);
+        assert_eq!(transport.connections(), 2);
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_the_first_idle_connection() {
+        let server = TestServer::new();
+        server.respond(Response::envelope(200, "<s:Envelope/>").delay(Duration::from_secs(2)));
+        server.respond(Response::envelope(200, "<s:Envelope/>").delay(Duration::from_millis(100)));
+        let transport = pool(PoolConfig::builder().max_connections(2).build(), &server);
+
+        let mut sent = Vec::new();
+        for count in 1..=2 {
+            let transport = transport.clone();
+            sent.push(tokio::spawn(async move {
+                transport.send(SoapRequest::new("<s:Envelope/>")).await
+            }));
+            received(&server, count).await;
+        }
+
+        // The connection of the second envelope is idle first, the slow one still is busy
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            transport.send(SoapRequest::new("<s:Envelope/>")),
+        )
+        .await
+        .expect("the envelope is not held behind the slow one")
+        .unwrap();
+        assert!(!sent[0].is_finished());
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_signal_during_receive() {
+        let server = TestServer::new();
+        server.respond(
+            Response::envelope(200, "<s:Envelope>Receive</s:Envelope>")
+                .delay(Duration::from_secs(2)),
+        );
+        server.respond(Response::envelope(200, "<s:Envelope>Signal</s:Envelope>"));
+        let transport = pool(PoolConfig::default(), &server);
+
+        let receive = tokio::spawn({
+            let transport = transport.clone();
+            async move { transport.send(SoapRequest::new("<s:Envelope/>")).await }
+        });
+        received(&server, 1).await;
+
+        let signal = tokio::time::timeout(
+            Duration::from_secs(1),
+            transport.send(SoapRequest::new("<s:Envelope/>")),
+        )
+        .await
+        .expect("the Signal is not held behind the Receive")
+        .unwrap();
+        assert_eq!(signal.envelope, "<s:Envelope>Signal</s:Envelope>");
+        assert!(!receive.is_finished());
+        assert_eq!(
+            receive.await.unwrap().unwrap().envelope,
+            "<s:Envelope>Receive</s:Envelope>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prewarm() {
+        for (prewarm, max_connections, expected) in [(2, 4, 2), (5, 3, 3)] {
+            let server = TestServer::new();
+            let transport = pool(
+                PoolConfig::builder()
+                    .prewarm(prewarm)
+                    .max_connections(max_connections)
+                    .build(),
+                &server,
+            );
+
+            transport.prewarm().await.unwrap();
+            assert_eq!(transport.connections(), expected);
+            assert_eq!(server.connections(), expected);
+            let requests = server.requests();
+            assert_eq!(requests.len(), expected);
+            for request in requests {
+                assert!(request.header("Authorization").is_some());
+                assert!(String::from_utf8_lossy(&request.body).contains("Identify"));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idle_connection_is_closed() {
+        let server = TestServer::new();
+        let transport = pool(PoolConfig::default(), &server);
+
+        transport
+            .send(SoapRequest::new("<s:Envelope/>"))
+            .await
+            .unwrap();
+        transport
+            .send(SoapRequest::new("<s:Envelope/>"))
+            .await
+            .unwrap();
+        assert_eq!(server.connections(), 1);
+
+        // Idle past the `idle_timeout`, the client would still reuse its connection
+        lock(&transport.endpoint.connections).idle[0].last_used = Instant::now()
+            .checked_sub(Duration::from_secs(120))
+            .unwrap();
+
+        transport
+            .send(SoapRequest::new("<s:Envelope/>"))
+            .await
+            .unwrap();
+        assert_eq!(server.connections(), 2);
+        assert_eq!(transport.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_builder() {
+        let server = TestServer::new();
+        let transport = pool(
+            PoolConfig::builder()
+                .client_builder(Arc::new(|| reqwest::Client::builder().user_agent("pooled")))
+                .build(),
+            &server,
+        );
+
+        transport
+            .send(SoapRequest::new("<s:Envelope/>"))
+            .await
+            .unwrap();
+        assert_eq!(server.requests()[0].header("User-Agent"), Some("pooled"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_envelope_closes_its_connection() {
+        let server = TestServer::new();
+        server.respond(Response::new(503));
+        let transport = pool(PoolConfig::builder().max_connections(1).build(), &server);
+
+        assert!(matches!(
+            transport.send(SoapRequest::new("<s:Envelope/>")).await,
+            Err(HttpTransportError::Status { status: 503, .. })
+        ));
+        assert_eq!(transport.connections(), 0);
+
+        transport
+            .send(SoapRequest::new("<s:Envelope/>"))
+            .await
+            .unwrap();
+        assert_eq!(transport.connections(), 1);
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_envelope_closes_its_connection() {
+        let server = TestServer::new();
+        server.respond(
+            Response::envelope(200, "<s:Envelope>Late</s:Envelope>").delay(Duration::from_secs(2)),
+        );
+        let transport = pool(PoolConfig::builder().max_connections(1).build(), &server);
+
+        let cancelled = tokio::spawn({
+            let transport = transport.clone();
+            async move { transport.send(SoapRequest::new("<s:Envelope/>")).await }
+        });
+        received(&server, 1).await;
+        cancelled.abort();
+        assert!(cancelled.await.unwrap_err().is_cancelled());
+        assert_eq!(transport.connections(), 0);
+
+        // The late response of the cancelled envelope is not read as the one of the next
+        let response = tokio::time::timeout(
+            Duration::from_secs(1),
+            transport.send(SoapRequest::new("<s:Envelope/>")),
+        )
+        .await
+        .expect("the next envelope does not wait for the cancelled one")
+        .unwrap();
+        assert_eq!(response.envelope, "<s:Envelope/>");
+        assert_eq!(transport.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unused_endpoints_are_forgotten() {
+        let server = TestServer::new();
+        let pool = ConnectionPool::new(PoolConfig::builder().idle_timeout(Duration::ZERO).build());
+
+        let transport = pool
+            .transport(&server.config(basic("user", "password")))
+            .unwrap();
+        transport
+            .send(SoapRequest::new("<s:Envelope/>"))
+            .await
+            .unwrap();
+        drop(transport);
+
+        pool.transport(&server.config(basic("other", "password")))
+            .unwrap();
+        assert_eq!(lock(&pool.endpoints).len(), 1);
+    }
+}
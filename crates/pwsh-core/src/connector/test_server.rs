@@ -0,0 +1,207 @@
+//! An HTTP server answering the requests of a transport in the tests with the responses the
+//! test scripted, on a port of the loopback of its own. The connections are kept alive.
+
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use crate::connector::{Authentication, ConnectorConfig, Scheme, http::ServerAddress};
+
+/// A request the server received
+#[derive(Debug, Clone)]
+pub(crate) struct Request {
+    headers: Vec<(String, String)>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Request {
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// What the server answers a request with
+pub(crate) struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    /// How long the server waits before answering, as for a Receive waiting for output
+    delay: Duration,
+}
+
+impl Response {
+    pub(crate) fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// A SOAP 1.2 `envelope`, a fault when `status` is a `500`
+    pub(crate) fn envelope(status: u16, envelope: &str) -> Self {
+        Self::new(status).body("application/soap+xml;charset=UTF-8", envelope.as_bytes())
+    }
+
+    pub(crate) fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub(crate) fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub(crate) fn body(self, content_type: &str, body: &[u8]) -> Self {
+        let mut response = self.header("Content-Type", content_type);
+        response.body = body.to_vec();
+        response
+    }
+}
+
+/// What answers the requests not scripted
+type Answer = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+
+#[derive(Default)]
+struct State {
+    responses: VecDeque<Response>,
+    answer: Option<Answer>,
+    requests: Vec<Request>,
+    /// The connections the server accepted
+    connections: usize,
+}
+
+#[derive(Clone)]
+pub(crate) struct TestServer {
+    port: u16,
+    state: Arc<Mutex<State>>,
+}
+
+impl TestServer {
+    pub(crate) fn new() -> Self {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("a port of the loopback");
+        let server = Self {
+            port: listener.local_addr().expect("a bound listener").port(),
+            state: Arc::default(),
+        };
+
+        let state = Arc::clone(&server.state);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { return };
+                lock(&state).connections += 1;
+                let state = Arc::clone(&state);
+                std::thread::spawn(move || serve(stream, &state));
+            }
+        });
+        server
+    }
+
+    /// Answer the next request with `response`, the ones not scripted are answered by the
+    /// [`answer`](Self::answer), else an empty envelope
+    pub(crate) fn respond(&self, response: Response) {
+        lock(&self.state).responses.push_back(response);
+    }
+
+    /// Answer the requests not scripted with `answer`, e.g. of a server of the protocol above
+    pub(crate) fn answer(&self, answer: impl Fn(&Request) -> Response + Send + Sync + 'static) {
+        lock(&self.state).answer = Some(Arc::new(answer));
+    }
+
+    pub(crate) fn requests(&self) -> Vec<Request> {
+        lock(&self.state).requests.clone()
+    }
+
+    pub(crate) fn connections(&self) -> usize {
+        lock(&self.state).connections
+    }
+
+    /// The configuration of a client of the server over HTTP
+    pub(crate) fn config(&self, authentication: Authentication) -> ConnectorConfig {
+        ConnectorConfig {
+            server: (ServerAddress::Domain("127.0.0.1".into()), self.port),
+            scheme: Scheme::Http,
+            authentication,
+        }
+    }
+}
+
+/// The `Basic` authentication of `username` and `password`
+pub(crate) fn basic(username: &str, password: &str) -> Authentication {
+    Authentication::Basic {
+        username: username.to_owned(),
+        password: password.to_owned(),
+    }
+}
+
+/// Answer the requests of a connection until the client closes it
+fn serve(stream: TcpStream, state: &Mutex<State>) {
+    let mut reader = BufReader::new(stream.try_clone().expect("a clone of the stream"));
+    let mut stream = stream;
+    loop {
+        let mut line = String::new();
+        // The request line
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+
+        let mut headers = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let Some((name, value)) = line.trim_end().split_once(':') else {
+                break;
+            };
+            headers.push((name.trim().to_owned(), value.trim().to_owned()));
+        }
+        let mut request = Request {
+            headers,
+            body: Vec::new(),
+        };
+        let length = request
+            .header("Content-Length")
+            .map_or(0, |length| length.parse().expect("a length"));
+        request.body = vec![0; length];
+        if reader.read_exact(&mut request.body).is_err() {
+            return;
+        }
+
+        let (response, answer) = {
+            let mut state = lock(state);
+            state.requests.push(request.clone());
+            (state.responses.pop_front(), state.answer.clone())
+        };
+        // Answered out of the lock, the other connections are served meanwhile
+        let response = response.unwrap_or_else(|| match answer {
+            Some(answer) => answer(&request),
+            None => Response::envelope(200, "<s:Envelope/>"),
+        });
+        std::thread::sleep(response.delay);
+
+        let mut head = format!("HTTP/1.1 {} Stub\r\n", response.status);
+        for (name, value) in &response.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str(&format!("Content-Length: {}\r\n\r\n", response.body.len()));
+        if stream.write_all(head.as_bytes()).is_err() || stream.write_all(&response.body).is_err() {
+            return;
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,850 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use base64::Engine;
+use futures_util::lock::Mutex as AsyncMutex;
+use protocol_winrm::{
+    soap::SoapVersion,
+    transport::{SoapRequest, SoapResponse, Transport},
+};
+use reqwest::{StatusCode, header};
+use tracing::{debug, instrument};
+
+use crate::connector::{
+    Authentication, ConnectorConfig, Scheme,
+    channel_bindings::ChannelBindings,
+    encryption::{content_type, encrypted_body, is_encrypted, parse_encrypted_body, protocol},
+    kerberos::Kerberos,
+    negotiate::{Mechanism, Negotiate},
+    ntlm::Ntlm,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpTransportError {
+    #[error("HTTP request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("The server rejected the credentials")]
+    Unauthorized,
+
+    #[error("The server answered HTTP {status}: {body}")]
+    Status { status: u16, body: String },
+
+    #[error(transparent)]
+    Authentication(#[from] crate::PwshCoreError),
+}
+
+/// The [`Transport`] of the `/wsman` endpoint of a server over HTTP or HTTPS.
+///
+/// The connections are kept alive and reused by the requests, a clone shares them and their
+/// handshakes. A SOAP fault, which WinRM sends with a `500`, is a response for the protocol
+/// layer to read, the other statuses that are not a success are errors.
+///
+/// The envelopes of a transport authenticated by NTLM, Kerberos or Negotiate go in turn on the
+/// connection of its handshake: each is sealed, sent and its response unsealed before the
+/// next, the context of the handshake being one sequence. A Receive waiting for output holds
+/// the Send or the Signal of its command until it is answered, up to its `OperationTimeout`.
+///
+/// The handshake authenticates one connection at a time, the envelopes sent at once, e.g. the
+/// signals of the commands being received, are better sent on the several authenticated
+/// connections of a [`ConnectionPool`](crate::connector::pool::ConnectionPool).
+///
+/// With [`Authentication::Ntlm`] the first request shakes hands on its connection: a
+/// `NEGOTIATE_MESSAGE` without a body, then the envelope with the `AUTHENTICATE_MESSAGE`
+/// answering the challenge of the `401`. The connection stays authenticated, the requests
+/// after it go without an `Authorization` until one is answered a `401` again.
+///
+/// Over HTTP, the envelopes are sealed by the security context of the handshake, NTLM or
+/// Kerberos with AES, in `multipart/encrypted` bodies, as the listeners of WinRM require by
+/// default: the handshake goes without a body then.
+///
+/// With [`Authentication::Kerberos`] the envelope goes with the ticket of the service, the
+/// response has to carry the token of the server authenticating itself.
+///
+/// With [`Authentication::Negotiate`] the handshake is the one of the mechanism SPNEGO settles
+/// on, see [`mechanism`](Self::mechanism).
+///
+/// Over HTTPS, the handshakes are bound to the TLS connection with the `tls-server-end-point`
+/// channel bindings of the certificate of the server, which the listeners with a
+/// `CbtHardeningLevel` of `Strict` require. Kerberos has its first token bound, the certificate
+/// is found by a request without credentials first.
+///
+/// The `exchange` of an [`AsyncRunspacePool`](crate::runspace_pool::AsyncRunspacePool) over a
+/// borrowed `transport` is `|envelope| async move { Ok(transport.exchange(envelope).await?) }`.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    /// What the service principal name of Kerberos is built from
+    host: String,
+    authentication: Authentication,
+    /// What the last `Negotiate` handshake settled on
+    mechanism: Arc<Mutex<Option<Mechanism>>>,
+    /// Whether the envelopes are sealed once the connection is authenticated, over HTTP
+    encrypts: bool,
+    /// Whether the handshakes are bound to the TLS connection, over HTTPS
+    binds_channel: bool,
+    /// The handshake of the connection, held by an envelope until its response is read
+    session: Arc<AsyncMutex<Session>>,
+}
+
+/// What the handshake of NTLM, Kerberos or Negotiate left on the connection of a transport
+#[derive(Debug, Default)]
+struct Session {
+    /// Whether the connection went through the handshake
+    authenticated: bool,
+    /// The security context of the connection, when it seals the envelopes
+    sealing: Option<Sealing>,
+}
+
+/// The security context the envelopes of an authenticated connection are sealed with
+enum Sealing {
+    Ntlm(Box<Ntlm>),
+    Kerberos(Kerberos),
+    Negotiate(Negotiate),
+}
+
+impl Sealing {
+    /// The mechanism the envelopes are sealed by, telling the `protocol` of their bodies
+    fn mechanism(&self) -> Mechanism {
+        match self {
+            Sealing::Ntlm(_) => Mechanism::Ntlm,
+            Sealing::Kerberos(_) => Mechanism::Kerberos,
+            Sealing::Negotiate(negotiate) => negotiate.mechanism(),
+        }
+    }
+
+    fn wrap(&mut self, message: &[u8]) -> Result<(Vec<u8>, Vec<u8>), crate::PwshCoreError> {
+        match self {
+            Sealing::Ntlm(ntlm) => {
+                let (signature, sealed) = ntlm.seal(message)?;
+                Ok((signature.to_vec(), sealed))
+            }
+            Sealing::Kerberos(kerberos) => kerberos.wrap(message),
+            Sealing::Negotiate(negotiate) => negotiate.wrap(message),
+        }
+    }
+
+    fn unwrap(&mut self, header: &[u8], sealed: &[u8]) -> Result<Vec<u8>, crate::PwshCoreError> {
+        match self {
+            Sealing::Ntlm(ntlm) => ntlm.unseal(header, sealed),
+            Sealing::Kerberos(kerberos) => kerberos.unwrap(header, sealed),
+            Sealing::Negotiate(negotiate) => negotiate.unwrap(header, sealed),
+        }
+    }
+}
+
+impl std::fmt::Debug for Sealing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sealing::Ntlm(_) => write!(f, "Sealing(NTLM)"),
+            Sealing::Kerberos(_) => write!(f, "Sealing(Kerberos)"),
+            Sealing::Negotiate(negotiate) => write!(f, "Sealing({})", negotiate.mechanism()),
+        }
+    }
+}
+
+impl HttpTransport {
+    pub fn new(config: &ConnectorConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .tls_info(true)
+            .build()
+            .expect("the TLS backend can be initialized");
+        Self::with_client(client, config)
+    }
+
+    /// A transport sending with `client`, e.g. one trusting the certificate of the server or
+    /// going through a proxy.
+    ///
+    /// The channel bindings need the certificate of the server, a `client` built with
+    /// [`tls_info`](reqwest::ClientBuilder::tls_info), without it the handshakes are not bound.
+    pub fn with_client(client: reqwest::Client, config: &ConnectorConfig) -> Self {
+        Self {
+            client,
+            url: config.wsman_to(None),
+            host: config.server.0.to_string(),
+            authentication: config.authentication.clone(),
+            mechanism: Arc::new(Mutex::new(None)),
+            encrypts: matches!(config.scheme, Scheme::Http),
+            binds_channel: matches!(config.scheme, Scheme::Https),
+            session: Arc::default(),
+        }
+    }
+
+    /// A transport of the same endpoint and authentication sending with `client`, on
+    /// connections of its own: none of the handshakes of this one is carried over
+    pub(crate) fn with_other_client(&self, client: reqwest::Client) -> Self {
+        Self {
+            client,
+            url: self.url.clone(),
+            host: self.host.clone(),
+            authentication: self.authentication.clone(),
+            mechanism: Arc::new(Mutex::new(None)),
+            encrypts: self.encrypts,
+            binds_channel: self.binds_channel,
+            session: Arc::default(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The mechanism the last handshake of [`Authentication::Negotiate`] settled on, none
+    /// before the first one completes
+    pub fn mechanism(&self) -> Option<Mechanism> {
+        *lock(&self.mechanism)
+    }
+
+    fn post(&self, request: &SoapRequest, body: String) -> reqwest::RequestBuilder {
+        self.client
+            .post(&self.url)
+            .header(header::CONTENT_TYPE, request.soap_version.content_type())
+            .body(body)
+    }
+
+    /// The bindings of the TLS connection `response` came over, none over HTTP or when the
+    /// client does not tell the certificate of the server
+    fn channel_bindings(
+        &self,
+        response: &reqwest::Response,
+    ) -> Result<Option<ChannelBindings>, HttpTransportError> {
+        if !self.binds_channel {
+            return Ok(None);
+        }
+        let Some(certificate) = response
+            .extensions()
+            .get::<reqwest::tls::TlsInfo>()
+            .and_then(|info| info.peer_certificate())
+        else {
+            debug!("The certificate of the server is unknown, the handshake is not bound");
+            return Ok(None);
+        };
+        Ok(Some(ChannelBindings::tls_server_end_point(certificate)?))
+    }
+
+    /// The bindings of the TLS connection for a handshake whose first token is bound, from
+    /// the certificate a `request` without credentials finds
+    async fn probe_channel_bindings(
+        &self,
+        request: &SoapRequest,
+    ) -> Result<Option<ChannelBindings>, HttpTransportError> {
+        if !self.binds_channel {
+            return Ok(None);
+        }
+        let response = self.post(request, String::new()).send().await?;
+        let bindings = self.channel_bindings(&response)?;
+        // Read to its end, for the connection to be reused by the handshake
+        response.bytes().await?;
+        Ok(bindings)
+    }
+
+    /// `request` with its envelope sealed, when the connection has a context sealing them
+    fn post_sealed(
+        &self,
+        session: &mut Session,
+        request: &SoapRequest,
+    ) -> Result<Option<reqwest::RequestBuilder>, HttpTransportError> {
+        let Some(sealing) = session.sealing.as_mut() else {
+            return Ok(None);
+        };
+
+        let protocol = protocol(sealing.mechanism());
+        let (header, sealed) = sealing.wrap(request.envelope.as_bytes())?;
+        let body = encrypted_body(
+            protocol,
+            &request.soap_version.content_type().replace(' ', ""),
+            request.envelope.len(),
+            &header,
+            &sealed,
+        );
+        Ok(Some(
+            self.client
+                .post(&self.url)
+                .header(header::CONTENT_TYPE, content_type(protocol))
+                .body(body),
+        ))
+    }
+
+    /// Keep `sealing` as the context of the connection and send `request` sealed by it
+    async fn send_sealed(
+        &self,
+        session: &mut Session,
+        request: &SoapRequest,
+        sealing: Sealing,
+    ) -> Result<reqwest::Response, HttpTransportError> {
+        debug!(?sealing, "The envelopes are sealed");
+        session.sealing = Some(sealing);
+        session.authenticated = true;
+
+        let builder =
+            self.post_sealed(session, request)?
+                .ok_or(crate::PwshCoreError::UnlikelyToHappen(
+                    "the sealing context was just kept",
+                ))?;
+        Ok(builder.send().await?)
+    }
+
+    /// The envelope of an encrypted `body`, unsealed by the context of the connection
+    fn unseal(session: Option<&mut Session>, body: &[u8]) -> Result<String, HttpTransportError> {
+        let (header, sealed) = parse_encrypted_body(body)?;
+        let sealing = session.and_then(|session| session.sealing.as_mut()).ok_or(
+            crate::PwshCoreError::InvalidState(
+                "an encrypted response without a context unsealing it",
+            ),
+        )?;
+        let envelope = sealing.unwrap(header, sealed)?;
+        Ok(String::from_utf8_lossy(&envelope).into_owned())
+    }
+
+    /// The response to `request` sent without an `Authorization`, when the connection went
+    /// through a handshake already and still is authenticated
+    async fn send_authenticated(
+        &self,
+        session: &mut Session,
+        request: &SoapRequest,
+    ) -> Result<Option<reqwest::Response>, HttpTransportError> {
+        if !session.authenticated {
+            return Ok(None);
+        }
+
+        let builder = match self.post_sealed(session, request)? {
+            Some(builder) => builder,
+            None => self.post(request, request.envelope.clone()),
+        };
+        let response = builder.send().await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(Some(response));
+        }
+        // A new connection, the previous one was closed
+        response.bytes().await?;
+        debug!("The connection is no longer authenticated");
+        *session = Session::default();
+        Ok(None)
+    }
+
+    /// Send `request` on an authenticated connection, shaking hands first when the connection
+    /// is not
+    async fn send_ntlm(
+        &self,
+        session: &mut Session,
+        request: &SoapRequest,
+        username: &str,
+        password: &str,
+    ) -> Result<reqwest::Response, HttpTransportError> {
+        if let Some(response) = self.send_authenticated(session, request).await? {
+            return Ok(response);
+        }
+
+        let mut ntlm = Ntlm::new(username, password);
+        let response = self
+            .post(request, String::new())
+            .header(header::AUTHORIZATION, negotiate_header(&ntlm.negotiate()))
+            .send()
+            .await?;
+        let status = response.status();
+        let challenge = negotiate_challenge(response.headers());
+        if let Some(bindings) = self.channel_bindings(&response)? {
+            ntlm = ntlm.with_channel_bindings(&bindings);
+        }
+        // Read to its end, for the connection to be reused by the next leg
+        let body = response.text().await?;
+        let challenge = match (status, challenge) {
+            (StatusCode::UNAUTHORIZED, Some(challenge)) => challenge,
+            (StatusCode::UNAUTHORIZED, None) => return Err(HttpTransportError::Unauthorized),
+            (status, _) => {
+                return Err(HttpTransportError::Status {
+                    status: status.as_u16(),
+                    body,
+                });
+            }
+        };
+        debug!(length = challenge.len(), "NTLM challenge received");
+
+        let authenticate = ntlm.authenticate(&challenge)?;
+        if self.encrypts {
+            let response = self
+                .post(request, String::new())
+                .header(header::AUTHORIZATION, negotiate_header(&authenticate))
+                .send()
+                .await?;
+            let status = response.status();
+            let body = response.text().await?;
+            if status == StatusCode::UNAUTHORIZED {
+                return Err(HttpTransportError::Unauthorized);
+            }
+            if !status.is_success() {
+                return Err(HttpTransportError::Status {
+                    status: status.as_u16(),
+                    body,
+                });
+            }
+            return self
+                .send_sealed(session, request, Sealing::Ntlm(Box::new(ntlm)))
+                .await;
+        }
+
+        let response = self
+            .post(request, request.envelope.clone())
+            .header(header::AUTHORIZATION, negotiate_header(&authenticate))
+            .send()
+            .await?;
+        session.authenticated = response.status() != StatusCode::UNAUTHORIZED;
+        Ok(response)
+    }
+
+    /// Send `request` with the ticket of the service, on a connection authenticated already or
+    /// authenticating it
+    async fn send_kerberos(
+        &self,
+        session: &mut Session,
+        request: &SoapRequest,
+        credentials: Option<(&str, &str)>,
+        spn: Option<&str>,
+    ) -> Result<reqwest::Response, HttpTransportError> {
+        if let Some(response) = self.send_authenticated(session, request).await? {
+            return Ok(response);
+        }
+
+        let spn = Kerberos::spn_or_default(spn, &self.host);
+        debug!(%spn, "Kerberos handshake");
+        let mut kerberos = Kerberos::new(&spn, credentials)?;
+        if let Some(bindings) = self.probe_channel_bindings(request).await? {
+            kerberos = kerberos.with_channel_bindings(&bindings);
+        }
+        let mut token = kerberos.step(None)?;
+        while let Some(sent) = token.take() {
+            // The envelope is sent sealed once the handshake is done
+            let body = if self.encrypts {
+                String::new()
+            } else {
+                request.envelope.clone()
+            };
+            let response = self
+                .post(request, body)
+                .header(header::AUTHORIZATION, negotiate_header(&sent))
+                .send()
+                .await?;
+            let Some(reply) = negotiate_challenge(response.headers()) else {
+                if response.status() == StatusCode::UNAUTHORIZED {
+                    return Err(HttpTransportError::Unauthorized);
+                }
+                break;
+            };
+            if response.status() == StatusCode::UNAUTHORIZED {
+                // The server asks for another leg, the envelope goes again with it
+                response.bytes().await?;
+                token = kerberos.step(Some(&reply))?;
+                continue;
+            }
+
+            // The token of the server, authenticating it
+            kerberos.step(Some(&reply))?;
+            if kerberos.is_complete() {
+                if self.encrypts {
+                    response.bytes().await?;
+                    return self
+                        .send_sealed(session, request, Sealing::Kerberos(kerberos))
+                        .await;
+                }
+                session.authenticated = true;
+                return Ok(response);
+            }
+            break;
+        }
+
+        Err(HttpTransportError::Authentication(
+            crate::PwshCoreError::AuthenticationError(
+                "the server did not complete the Kerberos handshake".to_string(),
+            ),
+        ))
+    }
+
+    /// Send `request` on a connection authenticated already or authenticating it with the
+    /// mechanism SPNEGO settles on, the NTLM challenge asked for without a body
+    async fn send_negotiate(
+        &self,
+        session: &mut Session,
+        request: &SoapRequest,
+        credentials: Option<(&str, &str)>,
+        spn: Option<&str>,
+    ) -> Result<reqwest::Response, HttpTransportError> {
+        if let Some(response) = self.send_authenticated(session, request).await? {
+            return Ok(response);
+        }
+
+        let spn = Kerberos::spn_or_default(spn, &self.host);
+        let mut negotiate = Negotiate::new(&spn, credentials)?;
+        if let Some(bindings) = self.probe_channel_bindings(request).await? {
+            negotiate = negotiate.with_channel_bindings(&bindings);
+        }
+        let mut token = negotiate.step(None)?;
+        debug!(%spn, mechanism = %negotiate.mechanism(), "Negotiate handshake");
+        while let Some(sent) = token.take() {
+            let challenged = negotiate.expects_challenge();
+            // The envelope is sent sealed once the handshake is done
+            let body = if challenged || self.encrypts {
+                String::new()
+            } else {
+                request.envelope.clone()
+            };
+            let response = self
+                .post(request, body)
+                .header(header::AUTHORIZATION, negotiate_header(&sent))
+                .send()
+                .await?;
+            let status = response.status();
+            let reply = negotiate_challenge(response.headers());
+            if status == StatusCode::UNAUTHORIZED {
+                let Some(reply) = reply else {
+                    return Err(HttpTransportError::Unauthorized);
+                };
+                // Read to its end, for the connection to be reused by the next leg
+                response.bytes().await?;
+                token = negotiate.step(Some(&reply))?;
+                continue;
+            }
+            if challenged {
+                return Err(HttpTransportError::Status {
+                    status: status.as_u16(),
+                    body: response.text().await?,
+                });
+            }
+
+            // The last token of the server, authenticating it
+            if let Some(reply) = reply {
+                negotiate.step(Some(&reply))?;
+            }
+            if negotiate.is_complete() {
+                debug!(mechanism = %negotiate.mechanism(), "Negotiate handshake complete");
+                *lock(&self.mechanism) = Some(negotiate.mechanism());
+                if self.encrypts {
+                    response.bytes().await?;
+                    return self
+                        .send_sealed(session, request, Sealing::Negotiate(negotiate))
+                        .await;
+                }
+                session.authenticated = true;
+                return Ok(response);
+            }
+            break;
+        }
+
+        Err(HttpTransportError::Authentication(
+            crate::PwshCoreError::AuthenticationError(format!(
+                "the server did not complete the {} handshake",
+                negotiate.mechanism()
+            )),
+        ))
+    }
+}
+
+impl Transport for HttpTransport {
+    type Error = HttpTransportError;
+
+    #[instrument(skip_all, name = "HttpTransport::send", fields(url = %self.url))]
+    async fn send(&self, request: SoapRequest) -> Result<SoapResponse, Self::Error> {
+        // The handshake is held from the envelope to its response: the sealing of the next
+        // envelope follows the unsealing of this one, a `401` shakes hands again for all
+        let mut session = match &self.authentication {
+            Authentication::Basic { .. } => None,
+            _ => Some(self.session.lock().await),
+        };
+        let response = match (&self.authentication, session.as_deref_mut()) {
+            (Authentication::Basic { username, password }, _) => {
+                self.post(&request, request.envelope.clone())
+                    .basic_auth(username, Some(password))
+                    .send()
+                    .await?
+            }
+            (Authentication::Ntlm { username, password }, Some(session)) => {
+                self.send_ntlm(session, &request, username, password)
+                    .await?
+            }
+            (
+                Authentication::Kerberos {
+                    username,
+                    password,
+                    spn,
+                },
+                Some(session),
+            ) => {
+                let credentials = username.as_deref().zip(password.as_deref());
+                self.send_kerberos(session, &request, credentials, spn.as_deref())
+                    .await?
+            }
+            (
+                Authentication::Negotiate {
+                    username,
+                    password,
+                    spn,
+                },
+                Some(session),
+            ) => {
+                let credentials = username.as_deref().zip(password.as_deref());
+                self.send_negotiate(session, &request, credentials, spn.as_deref())
+                    .await?
+            }
+            (_, None) => {
+                return Err(HttpTransportError::Authentication(
+                    crate::PwshCoreError::UnlikelyToHappen("a handshake without its session"),
+                ));
+            }
+        };
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = response.bytes().await?;
+        let encrypted = is_encrypted(&content_type);
+        let (body, is_envelope) = if encrypted {
+            (Self::unseal(session.as_deref_mut(), &body)?, true)
+        } else {
+            (
+                String::from_utf8_lossy(&body).into_owned(),
+                SoapVersion::from_content_type(&content_type).is_some(),
+            )
+        };
+        debug!(%status, encrypted, length = body.len(), "Response received");
+
+        match status {
+            status if status.is_success() => Ok(SoapResponse::new(body)),
+            StatusCode::INTERNAL_SERVER_ERROR if is_envelope => Ok(SoapResponse::new(body)),
+            StatusCode::UNAUTHORIZED => Err(HttpTransportError::Unauthorized),
+            status => Err(HttpTransportError::Status {
+                status: status.as_u16(),
+                body,
+            }),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn negotiate_header(token: &[u8]) -> String {
+    format!(
+        "Negotiate {}",
+        base64::engine::general_purpose::STANDARD.encode(token)
+    )
+}
+
+/// The token of the `Negotiate` (or `NTLM`) challenge among the `WWW-Authenticate` of a `401`
+fn negotiate_challenge(headers: &header::HeaderMap) -> Option<Vec<u8>> {
+    headers
+        .get_all(header::WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split_once(' '))
+        .filter(|(scheme, _)| {
+            scheme.eq_ignore_ascii_case("Negotiate") || scheme.eq_ignore_ascii_case("NTLM")
+        })
+        .find_map(|(_, token)| {
+            base64::engine::general_purpose::STANDARD
+                .decode(token.trim())
+                .ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::connector::{
+        encryption::{KERBEROS_SESSION_ENCRYPTED, SPNEGO_SESSION_ENCRYPTED},
+        ntlm::tests::{FLAGS, challenge_message, target_info},
+        test_server::{Response, TestServer, basic},
+    };
+
+    const FAULT: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value></s:Code></s:Fault></s:Body></s:Envelope>"#;
+
+    async fn send(server: &TestServer) -> Result<SoapResponse, HttpTransportError> {
+        let transport = HttpTransport::new(&server.config(basic("user", "password")));
+        transport.send(SoapRequest::new("<s:Envelope/>")).await
+    }
+
+    #[tokio::test]
+    async fn test_request() {
+        let server = TestServer::new();
+        server.respond(Response::envelope(200, "<s:Envelope>ok</s:Envelope>"));
+
+        let response = send(&server).await.unwrap();
+        assert_eq!(response.envelope, "<s:Envelope>ok</s:Envelope>");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].header("Content-Type"),
+            Some(SoapVersion::Soap12.content_type())
+        );
+        // user:password
+        assert_eq!(
+            requests[0].header("Authorization"),
+            Some("Basic dXNlcjpwYXNzd29yZA==")
+        );
+        assert_eq!(requests[0].body, b"<s:Envelope/>");
+    }
+
+    #[tokio::test]
+    async fn test_fault_is_a_response() {
+        let server = TestServer::new();
+        server.respond(Response::envelope(500, FAULT));
+
+        let response = send(&server).await.unwrap();
+        assert_eq!(response.envelope, FAULT);
+    }
+
+    #[tokio::test]
+    async fn test_status_errors() {
+        let server = TestServer::new();
+        server.respond(Response::new(500).body("text/html", b"<html>Internal error</html>"));
+        server.respond(Response::new(401).header("WWW-Authenticate", "Basic"));
+        server.respond(Response::new(404).body("text/plain", b"Not found"));
+
+        let error = send(&server).await.unwrap_err();
+        assert!(
+            matches!(&error, HttpTransportError::Status { status: 500, body } if body == "<html>Internal error</html>"),
+            "{error}"
+        );
+        let error = send(&server).await.unwrap_err();
+        assert!(matches!(error, HttpTransportError::Unauthorized), "{error}");
+        let error = send(&server).await.unwrap_err();
+        assert!(
+            matches!(&error, HttpTransportError::Status { status: 404, body } if body == "Not found"),
+            "{error}"
+        );
+    }
+
+    fn ntlm(server: &TestServer) -> HttpTransport {
+        HttpTransport::new(&server.config(Authentication::Ntlm {
+            username: "User".to_owned(),
+            password: "Password".to_owned(),
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_handshake_scheme() {
+        let server = TestServer::new();
+        server.respond(Response::new(401));
+
+        let error = ntlm(&server)
+            .send(SoapRequest::new("<s:Envelope/>"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, HttpTransportError::Unauthorized), "{error}");
+        let requests = server.requests();
+        let authorization = requests[0].header("Authorization").unwrap();
+        assert!(authorization.starts_with("Negotiate "), "{authorization}");
+    }
+
+    #[tokio::test]
+    async fn test_signal_during_receive() {
+        let server = TestServer::new();
+        let delay = Duration::from_millis(500);
+        server.respond(Response::envelope(200, "<s:Envelope>Receive</s:Envelope>").delay(delay));
+        server.respond(Response::envelope(200, "<s:Envelope>Signal</s:Envelope>"));
+        let transport = ntlm(&server);
+        // Authenticated already, on a connection not sealing the envelopes
+        *transport.session.lock().await = Session {
+            authenticated: true,
+            sealing: None,
+        };
+
+        let sent = Instant::now();
+        let receive = tokio::spawn({
+            let transport = transport.clone();
+            async move { transport.send(SoapRequest::new("<s:Envelope/>")).await }
+        });
+        while server.requests().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // The Signal goes once the Receive is answered, on the connection of the handshake
+        let signal = transport
+            .send(SoapRequest::new("<s:Envelope/>"))
+            .await
+            .unwrap();
+        assert_eq!(signal.envelope, "<s:Envelope>Signal</s:Envelope>");
+        assert!(sent.elapsed() >= delay);
+        assert_eq!(
+            receive.await.unwrap().unwrap().envelope,
+            "<s:Envelope>Receive</s:Envelope>"
+        );
+        let requests = server.requests();
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.header("Authorization").is_none())
+        );
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[test]
+    fn test_sealed_content_type() {
+        let server = TestServer::new();
+        let mut context = Ntlm::new("User", "Password");
+        context.negotiate();
+        context
+            .authenticate(&challenge_message(FLAGS, &target_info()))
+            .unwrap();
+        let mut session = Session {
+            authenticated: true,
+            sealing: Some(Sealing::Ntlm(Box::new(context))),
+        };
+
+        // The protocol of the mechanism sealing the envelope
+        let request = ntlm(&server)
+            .post_sealed(&mut session, &SoapRequest::new("<s:Envelope/>"))
+            .unwrap()
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.headers()[header::CONTENT_TYPE],
+            content_type(SPNEGO_SESSION_ENCRYPTED)
+        );
+        let body = request.body().and_then(|body| body.as_bytes()).unwrap();
+        assert!(
+            String::from_utf8_lossy(body).contains(
+                "\tContent-Type: application/HTTP-SPNEGO-session-encrypted\r\n\
+                 \tOriginalContent: type=application/soap+xml;charset=utf-8;Length=13\r\n"
+            ),
+            "{body:?}"
+        );
+    }
+
+    #[test]
+    fn test_unseal_either_protocol() {
+        let mut context = Ntlm::new("User", "Password");
+        context.negotiate();
+        context
+            .authenticate(&challenge_message(FLAGS, &target_info()))
+            .unwrap();
+        let mut server = context.acceptor().unwrap();
+        let mut session = Session {
+            authenticated: true,
+            sealing: Some(Sealing::Ntlm(Box::new(context))),
+        };
+
+        for protocol in [SPNEGO_SESSION_ENCRYPTED, KERBEROS_SESSION_ENCRYPTED] {
+            let (signature, sealed) = server.seal(b"<s:Envelope/>").unwrap();
+            let body = encrypted_body(
+                protocol,
+                "application/soap+xml;charset=UTF-8",
+                sealed.len(),
+                &signature,
+                &sealed,
+            );
+            assert_eq!(
+                HttpTransport::unseal(Some(&mut session), &body).unwrap(),
+                "<s:Envelope/>"
+            );
+        }
+    }
+}
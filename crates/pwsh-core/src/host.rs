@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+
+use protocol_powershell_remoting::{
+    BufferCell, BufferCellArray, ChoiceDescription, ConsoleColor, Coordinates, FieldDescription,
+    HostMethodCall, HostMethodId, KeyInfo, ProgressRecord, PsCredential, PsValue, ReadKeyOptions,
+    Rectangle, RunspacePoolHostCall, ScrollBuffer, SessionKey, Size, ToPsObject, host_exception,
+};
+use tracing::warn;
+
+/// The client side of `$Host` of the pipelines of a pool, what `Read-Host`, `Write-Host` and
+/// the prompts of a script call.
+///
+/// Every method has a default: the writes are dropped and the others return `None`, the
+/// host does not implement them and the call throws a `HostException` in the script.
+pub trait PsHost {
+    fn read_line(&mut self) -> Option<String> {
+        None
+    }
+
+    /// The text of a `Read-Host -AsSecureString`, sent encrypted with the session key
+    fn read_line_as_secure_string(&mut self) -> Option<String> {
+        None
+    }
+
+    /// The text of `Write-Host`, which ends with a new line unless `-NoNewline`
+    fn write(
+        &mut self,
+        _text: &str,
+        _foreground: Option<ConsoleColor>,
+        _background: Option<ConsoleColor>,
+    ) {
+    }
+
+    fn write_error_line(&mut self, _line: &str) {}
+
+    fn write_warning_line(&mut self, _line: &str) {}
+
+    fn write_verbose_line(&mut self, _line: &str) {}
+
+    fn write_debug_line(&mut self, _line: &str) {}
+
+    fn write_progress(&mut self, _source_id: i64, _record: &ProgressRecord) {}
+
+    /// The value of each of `fields` by its name
+    fn prompt(
+        &mut self,
+        _caption: &str,
+        _message: &str,
+        _fields: &[FieldDescription],
+    ) -> Option<BTreeMap<String, PsValue>> {
+        None
+    }
+
+    /// The index of the choice made among `choices`
+    fn prompt_for_choice(
+        &mut self,
+        _caption: &str,
+        _message: &str,
+        _choices: &[ChoiceDescription],
+        _default_choice: i32,
+    ) -> Option<i32> {
+        None
+    }
+
+    /// The indexes of the choices made among `choices`
+    fn prompt_for_choice_multiple_selection(
+        &mut self,
+        _caption: &str,
+        _message: &str,
+        _choices: &[ChoiceDescription],
+        _default_choices: &[i32],
+    ) -> Option<Vec<i32>> {
+        None
+    }
+
+    /// The credential typed, sent with its password encrypted with the session key
+    fn prompt_for_credential(
+        &mut self,
+        _caption: &str,
+        _message: &str,
+        _user_name: Option<&str>,
+        _target_name: Option<&str>,
+    ) -> Option<PsCredential> {
+        None
+    }
+
+    /// The script called `exit`
+    fn set_should_exit(&mut self, _exit_code: i32) {}
+
+    /// `$Host.UI.RawUI`, what the scripts that draw on the console call
+    fn raw_ui(&mut self) -> Option<&mut dyn PsHostRawUi> {
+        None
+    }
+}
+
+/// The client side of `$Host.UI.RawUI`: the colors, cursor, window, keyboard and screen
+/// buffer of the console of the host.
+///
+/// As for [`PsHost`], the setters do nothing by default and the others return `None`.
+pub trait PsHostRawUi {
+    fn foreground_color(&mut self) -> Option<ConsoleColor> {
+        None
+    }
+
+    fn set_foreground_color(&mut self, _color: ConsoleColor) {}
+
+    fn background_color(&mut self) -> Option<ConsoleColor> {
+        None
+    }
+
+    fn set_background_color(&mut self, _color: ConsoleColor) {}
+
+    fn cursor_position(&mut self) -> Option<Coordinates> {
+        None
+    }
+
+    fn set_cursor_position(&mut self, _position: Coordinates) {}
+
+    fn window_position(&mut self) -> Option<Coordinates> {
+        None
+    }
+
+    fn set_window_position(&mut self, _position: Coordinates) {}
+
+    /// The height of the cursor, in percent of a cell
+    fn cursor_size(&mut self) -> Option<i32> {
+        None
+    }
+
+    fn set_cursor_size(&mut self, _size: i32) {}
+
+    fn buffer_size(&mut self) -> Option<Size> {
+        None
+    }
+
+    fn set_buffer_size(&mut self, _size: Size) {}
+
+    fn window_size(&mut self) -> Option<Size> {
+        None
+    }
+
+    fn set_window_size(&mut self, _size: Size) {}
+
+    fn window_title(&mut self) -> Option<String> {
+        None
+    }
+
+    fn set_window_title(&mut self, _title: &str) {}
+
+    fn max_window_size(&mut self) -> Option<Size> {
+        None
+    }
+
+    fn max_physical_window_size(&mut self) -> Option<Size> {
+        None
+    }
+
+    /// Whether a key was pressed, a `ReadKey` would not wait for it
+    fn key_available(&mut self) -> Option<bool> {
+        None
+    }
+
+    fn read_key(&mut self, _options: ReadKeyOptions) -> Option<KeyInfo> {
+        None
+    }
+
+    fn flush_input_buffer(&mut self) {}
+
+    /// Fill every cell of `rectangle` with `fill`
+    fn fill_buffer_contents(&mut self, _rectangle: Rectangle, _fill: BufferCell) {}
+
+    /// Write `contents` to the screen buffer from `origin`
+    fn set_buffer_contents(&mut self, _origin: Coordinates, _contents: &BufferCellArray) {}
+
+    fn buffer_contents(&mut self, _rectangle: Rectangle) -> Option<BufferCellArray> {
+        None
+    }
+
+    fn scroll_buffer_contents(&mut self, _scroll: ScrollBuffer) {}
+}
+
+/// A host call received, the response goes to the pipeline that made it or to the pool
+#[derive(Debug, Clone)]
+pub(crate) struct PendingHostCall {
+    pub pipeline_id: Option<uuid::Uuid>,
+    pub call: RunspacePoolHostCall,
+}
+
+/// Whether the answer to `call` has a `SecureString`, the session key must be exchanged first
+pub(crate) fn needs_session_key(call: &RunspacePoolHostCall) -> bool {
+    matches!(
+        call.method(),
+        Ok(HostMethodId::ReadLineAsSecureString
+            | HostMethodId::PromptForCredential1
+            | HostMethodId::PromptForCredential2)
+    )
+}
+
+/// Run `call` on `host`, what to respond if the server waits for it. Without a host every
+/// call that returns a value throws.
+pub(crate) fn call_host(
+    host: Option<&mut dyn PsHost>,
+    session_key: Option<&SessionKey>,
+    call: &RunspacePoolHostCall,
+) -> Option<Result<PsValue, PsValue>> {
+    let method = match call.method() {
+        Ok(method) => method,
+        Err(error) => {
+            warn!(%error, "Ignoring the host call");
+            return None;
+        }
+    };
+
+    let result = match (host, call.decode()) {
+        (Some(host), Ok(decoded)) => invoke(host, session_key, decoded),
+        (None, Ok(_)) => Err("The client has no host".to_string()),
+        (_, Err(error)) => Err(error.to_string()),
+    };
+
+    if !method.returns_value() {
+        if let Err(error) = result {
+            warn!(%error, method = method.name(), "The host call failed");
+        }
+        return None;
+    }
+
+    Some(result.map_err(|message| host_exception(&message)))
+}
+
+fn invoke(
+    host: &mut dyn PsHost,
+    session_key: Option<&SessionKey>,
+    call: HostMethodCall,
+) -> Result<PsValue, String> {
+    let not_implemented = |method: &str| format!("The host does not implement {method}");
+    let nil = PsValue::Primitive(protocol_powershell_remoting::PsPrimitiveValue::Nil);
+
+    match call {
+        HostMethodCall::ReadLine => host
+            .read_line()
+            .map(|line| line.to_ps_value())
+            .ok_or_else(|| not_implemented("ReadLine")),
+        HostMethodCall::ReadLineAsSecureString => {
+            let line = host
+                .read_line_as_secure_string()
+                .ok_or_else(|| not_implemented("ReadLineAsSecureString"))?;
+            let session_key =
+                session_key.ok_or("Reading a SecureString needs the session key".to_string())?;
+            session_key
+                .encrypt(&line)
+                .map_err(|error| error.to_string())
+        }
+        HostMethodCall::Write {
+            foreground,
+            background,
+            mut text,
+            new_line,
+        } => {
+            if new_line {
+                text.push('\n');
+            }
+            host.write(&text, foreground, background);
+            Ok(nil)
+        }
+        HostMethodCall::WriteErrorLine(line) => {
+            host.write_error_line(&line);
+            Ok(nil)
+        }
+        HostMethodCall::WriteWarningLine(line) => {
+            host.write_warning_line(&line);
+            Ok(nil)
+        }
+        HostMethodCall::WriteVerboseLine(line) => {
+            host.write_verbose_line(&line);
+            Ok(nil)
+        }
+        HostMethodCall::WriteDebugLine(line) => {
+            host.write_debug_line(&line);
+            Ok(nil)
+        }
+        HostMethodCall::WriteProgress { source_id, record } => {
+            host.write_progress(source_id, &record);
+            Ok(nil)
+        }
+        HostMethodCall::Prompt {
+            caption,
+            message,
+            descriptions,
+        } => host
+            .prompt(&caption, &message, &descriptions)
+            .map(|answers| answers.to_ps_value())
+            .ok_or_else(|| not_implemented("Prompt")),
+        HostMethodCall::PromptForChoice {
+            caption,
+            message,
+            choices,
+            default_choice,
+        } => host
+            .prompt_for_choice(&caption, &message, &choices, default_choice)
+            .map(|choice| choice.to_ps_value())
+            .ok_or_else(|| not_implemented("PromptForChoice")),
+        HostMethodCall::PromptForCredential {
+            caption,
+            message,
+            user_name,
+            target_name,
+        } => {
+            let credential = host
+                .prompt_for_credential(
+                    &caption,
+                    &message,
+                    user_name.as_deref(),
+                    target_name.as_deref(),
+                )
+                .ok_or_else(|| not_implemented("PromptForCredential"))?;
+            let session_key =
+                session_key.ok_or("Sending a PSCredential needs the session key".to_string())?;
+            credential
+                .to_ps_value(session_key)
+                .map_err(|error| error.to_string())
+        }
+        HostMethodCall::SetShouldExit(exit_code) => {
+            host.set_should_exit(exit_code);
+            Ok(nil)
+        }
+        HostMethodCall::PromptForChoiceMultipleSelection {
+            caption,
+            message,
+            choices,
+            default_choices,
+        } => host
+            .prompt_for_choice_multiple_selection(&caption, &message, &choices, &default_choices)
+            .map(|choices| choices.to_ps_value())
+            .ok_or_else(|| not_implemented("PromptForChoiceMultipleSelection")),
+        HostMethodCall::Other(method) => host
+            .raw_ui()
+            .and_then(|raw_ui| get_raw_ui(raw_ui, method))
+            .ok_or_else(|| not_implemented(method.name())),
+        call => host
+            .raw_ui()
+            .and_then(|raw_ui| invoke_raw_ui(raw_ui, call))
+            .ok_or_else(|| not_implemented("this method of $Host.UI.RawUI")),
+    }
+}
+
+/// The calls of `$Host.UI.RawUI` with parameters
+fn invoke_raw_ui(raw_ui: &mut dyn PsHostRawUi, call: HostMethodCall) -> Option<PsValue> {
+    let nil = PsValue::Primitive(protocol_powershell_remoting::PsPrimitiveValue::Nil);
+
+    match call {
+        HostMethodCall::SetForegroundColor(color) => raw_ui.set_foreground_color(color),
+        HostMethodCall::SetBackgroundColor(color) => raw_ui.set_background_color(color),
+        HostMethodCall::SetCursorPosition(position) => raw_ui.set_cursor_position(position),
+        HostMethodCall::SetWindowPosition(position) => raw_ui.set_window_position(position),
+        HostMethodCall::SetCursorSize(size) => raw_ui.set_cursor_size(size),
+        HostMethodCall::SetBufferSize(size) => raw_ui.set_buffer_size(size),
+        HostMethodCall::SetWindowSize(size) => raw_ui.set_window_size(size),
+        HostMethodCall::SetWindowTitle(title) => raw_ui.set_window_title(&title),
+        HostMethodCall::FillBufferContents { rectangle, fill } => {
+            raw_ui.fill_buffer_contents(rectangle, fill)
+        }
+        HostMethodCall::SetBufferContents { origin, contents } => {
+            raw_ui.set_buffer_contents(origin, &contents)
+        }
+        HostMethodCall::ScrollBufferContents(scroll) => raw_ui.scroll_buffer_contents(scroll),
+        HostMethodCall::ReadKey(options) => {
+            return raw_ui.read_key(options).map(|key| key.to_ps_value());
+        }
+        HostMethodCall::GetBufferContents(rectangle) => {
+            return raw_ui
+                .buffer_contents(rectangle)
+                .map(|contents| contents.to_ps_value());
+        }
+        _ => return None,
+    }
+    Some(nil)
+}
+
+/// The getters of `$Host.UI.RawUI`, which have no parameters
+fn get_raw_ui(raw_ui: &mut dyn PsHostRawUi, method: HostMethodId) -> Option<PsValue> {
+    match method {
+        HostMethodId::GetForegroundColor => raw_ui.foreground_color().map(|c| c.to_ps_value()),
+        HostMethodId::GetBackgroundColor => raw_ui.background_color().map(|c| c.to_ps_value()),
+        HostMethodId::GetCursorPosition => raw_ui.cursor_position().map(|p| p.to_ps_value()),
+        HostMethodId::GetWindowPosition => raw_ui.window_position().map(|p| p.to_ps_value()),
+        HostMethodId::GetCursorSize => raw_ui.cursor_size().map(|size| size.to_ps_value()),
+        HostMethodId::GetBufferSize => raw_ui.buffer_size().map(|size| size.to_ps_value()),
+        HostMethodId::GetWindowSize => raw_ui.window_size().map(|size| size.to_ps_value()),
+        HostMethodId::GetWindowTitle => raw_ui.window_title().map(|title| title.to_ps_value()),
+        HostMethodId::GetMaxWindowSize => raw_ui.max_window_size().map(|size| size.to_ps_value()),
+        HostMethodId::GetMaxPhysicalWindowSize => raw_ui
+            .max_physical_window_size()
+            .map(|size| size.to_ps_value()),
+        HostMethodId::GetKeyAvailable => raw_ui.key_available().map(|key| key.to_ps_value()),
+        HostMethodId::FlushInputBuffer => {
+            raw_ui.flush_input_buffer();
+            Some(PsValue::Primitive(
+                protocol_powershell_remoting::PsPrimitiveValue::Nil,
+            ))
+        }
+        _ => None,
+    }
+}
@@ -1,6 +1,9 @@
 use std::borrow::Cow;
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod connector;
+pub mod host;
 pub mod runspace;
 pub mod runspace_pool;
 pub mod pipeline;
@@ -28,9 +31,31 @@ pub enum PwshCoreError {
     #[error("Protocol error: {0}")]
     PowerShellRemotingError(#[from] protocol_powershell_remoting::PowerShellRemotingError),
 
+    #[error("WS-Management error: {0}")]
+    WsManError(#[from] protocol_winrm::error::ProtocolError),
+
     #[error("XML parsing error: {0}")]
     XmlParsingError(#[from] xml::XmlError),
 
     #[error("Invalid response: {0}")]
     InvalidResponse(Cow<'static, str>),
+
+    #[error("The runspace pool is broken: {0}")]
+    RunspacePoolBroken(String),
+
+    #[error("File copy failed: {0}")]
+    FileCopyError(String),
+
+    #[error("The pipeline did not complete: {0}")]
+    PipelineFailed(String),
+
+    #[error("Authentication failed: {0}")]
+    AuthenticationError(String),
+
+    #[error("{feature} needs the protocol {needed}, the server speaks {negotiated}")]
+    UnsupportedByServer {
+        feature: &'static str,
+        needed: protocol_powershell_remoting::ProtocolVersion,
+        negotiated: protocol_powershell_remoting::ProtocolVersion,
+    },
 }
@@ -1,15 +1,20 @@
 use base64::Engine;
 use protocol_winrm::{
-    cores::{
-        Attribute, CommandLine, DesiredStream, Receive, Shell, Tag, Time, anytag::AnyTag, tag_name,
-    },
+    cores::{Attribute, DesiredStream, OwnedElement, Receive, Shell, Tag, Time},
     rsp::{
         commandline::CommandLineValue,
-        receive::{ReceiveResponseValue, ReceiveValue},
+        compression::{self, StreamCompression, compression_type},
+        disconnect::{ConnectRequest, DisconnectRequest},
+        receive::ReceiveValue,
         rsp::ShellValue,
+        send::SendRequest,
+        shell::{DeleteShellRequest, DisconnectedShell},
+        signal::{SignalCode, SignalRequest},
     },
     soap::{SoapEnvelope, body::SoapBody},
-    ws_management::{self, OptionSetValue, SelectorSetValue, WsMan},
+    ws_management::{
+        self, CreateRequest, Operation, OptionSetValue, ResourceUri, SelectorSetValue, WsMan,
+    },
 };
 use uuid::Uuid;
 use xml::builder::Element;
@@ -27,7 +32,7 @@ pub struct WinRunspace {
     #[builder(default, setter(strip_option))]
     name: Option<String>,
 
-    #[builder(default = "http://schemas.microsoft.com/powershell/Microsoft.PowerShell".to_string())]
+    #[builder(default = ResourceUri::POWERSHELL.to_string())]
     resource_uri: String,
 
     #[builder(default = uuid::Uuid::new_v4())]
@@ -39,6 +44,14 @@ pub struct WinRunspace {
     #[builder(default)]
     codepage: Option<u32>,
 
+    /// Compression to request for the streams, only sent when the decoder supports it
+    #[builder(default, setter(strip_option))]
+    compression: Option<StreamCompression>,
+
+    /// Compression the server accepted for this shell
+    #[builder(default)]
+    negotiated_compression: Option<StreamCompression>,
+
     #[builder(default)]
     shell_id: Option<String>,
     #[builder(default)]
@@ -53,8 +66,16 @@ pub struct WinRunspace {
     #[builder(default)]
     selector_set: SelectorSetValue,
 
+    /// Reference parameters of the shell EPR that WS-Management doesn't define, echoed as headers
+    #[builder(default)]
+    reference_parameters: Vec<OwnedElement>,
+
     #[builder(default)]
     opened: bool,
+
+    /// The Receive that has not been answered yet, polling again retransmits it
+    #[builder(default)]
+    pending_receive: Option<Operation>,
 }
 
 impl WinRunspace {
@@ -95,17 +116,29 @@ impl WinRunspace {
             option_set = option_set.add_option("WINRS_CODEPAGE", codepage.to_string());
         }
 
-        ws_man.invoke(
-            ws_management::WsAction::Create,
-            None,
-            SoapBody::builder().shell(shell).build(),
-            Some(option_set),
-            None,
-        )
+        let mut request = ws_man.create(
+            CreateRequest::builder()
+                .body(SoapBody::builder().shell(shell).build())
+                .option_set(option_set)
+                .build(),
+        );
+
+        if let Some(compression) = compression::negotiate(self.compression)
+            && let Some(header) = request.value.header.as_mut()
+        {
+            header.value.compression_type = Some(compression_type(compression));
+        }
+
+        request
+    }
+
+    /// The stream compression in effect once the shell is created
+    pub fn negotiated_compression(&self) -> Option<StreamCompression> {
+        self.negotiated_compression
     }
 
     pub fn fire_receive<'a>(
-        &'a self,
+        &'a mut self,
         ws_man: &'a WsMan,
         stream: Option<&'a str>,
         command_id: Option<&'a str>,
@@ -138,19 +171,28 @@ impl WinRunspace {
             .as_ref()
             .map(|shell_id| SelectorSetValue::new().add_selector("ShellId", shell_id));
 
-        ws_man.invoke(
-            ws_management::WsAction::ShellReceive,
-            Some(&self.resource_uri),
-            SoapBody::builder().receive(receive_tag).build(),
-            Some(option_set),
-            selector_set,
-        )
+        let operation = self.pending_receive.get_or_insert_with(Operation::new);
+
+        ws_man
+            .invoke_operation(
+                operation,
+                ws_management::WsAction::ShellReceive,
+                Some(&self.resource_uri),
+                SoapBody::builder().receive(receive_tag).build(),
+                Some(option_set),
+                selector_set,
+            )
+            .echo_parameters(&self.reference_parameters)
     }
 
     pub fn accept_receive_response<'a>(
         &mut self,
         soap_envelope: &SoapEnvelope<'a>,
     ) -> Result<Vec<Vec<u8>>, crate::PwshCoreError> {
+        if let Some(operation) = self.pending_receive.take() {
+            operation.validate_response(soap_envelope)?;
+        }
+
         let receive_response = &soap_envelope
             .body
             .as_ref()
@@ -189,6 +231,7 @@ impl WinRunspace {
         let output_stream = &shell.as_ref().output_streams;
         let shell_run_time = &shell.as_ref().shell_run_time;
         let shell_inactivity = &shell.as_ref().shell_inactivity;
+        let compression_mode = &shell.as_ref().compression_mode;
 
         self.shell_id = shell_id.map(|s| s.as_ref().to_string());
         self.owner = owner.as_ref().map(|o| o.value.as_ref().to_string());
@@ -212,6 +255,12 @@ impl WinRunspace {
             .as_ref()
             .map(|t| t.value.as_ref().to_string());
 
+        // Only trust a compression we asked for, the decoder may not support anything else
+        self.negotiated_compression = compression_mode
+            .as_ref()
+            .and_then(|mode| StreamCompression::from_compression_mode(mode.value.as_ref()))
+            .filter(|mode| compression::negotiate(self.compression) == Some(*mode));
+
         let resource_created = soap_envelop.body.as_ref().resource_created.as_ref().ok_or(
             crate::PwshCoreError::InvalidResponse("No ResourceCreated found in response".into()),
         )?;
@@ -221,12 +270,172 @@ impl WinRunspace {
         let selector_set = &reference_parameters.selector_set;
 
         self.selector_set = selector_set.value.clone();
+        self.reference_parameters = reference_parameters.unknown.clone();
 
         self.opened = true;
 
         Ok(())
     }
 
+    /// The `wxf:Delete` of the shell
+    pub(crate) fn close<'a>(
+        &'a self,
+        ws_man: &'a WsMan,
+    ) -> Result<impl Into<Element<'a>>, crate::PwshCoreError> {
+        let shell_id = self
+            .shell_id
+            .as_deref()
+            .ok_or(crate::PwshCoreError::InvalidState(
+                "The shell must be created to be deleted",
+            ))?;
+
+        Ok(ws_man.delete_shell(
+            DeleteShellRequest::builder()
+                .shell_id(shell_id)
+                .resource_uri(&self.resource_uri)
+                .reference_parameters(&self.reference_parameters)
+                .build(),
+        ))
+    }
+
+    /// Attach to the disconnected `shell`, its requests are built as the ones of a shell this
+    /// client created
+    pub(crate) fn attach(&mut self, shell: &DisconnectedShell) {
+        self.shell_id = Some(shell.shell_id.clone());
+        self.resource_uri = shell.resource_uri.clone();
+        self.reference_parameters = shell.reference_parameters.clone();
+        self.selector_set = SelectorSetValue::new().add_selector("ShellId", &shell.shell_id);
+        self.opened = true;
+    }
+
+    /// What identifies the shell to connect to it again, with the commands of `command_ids`
+    pub(crate) fn disconnected(
+        &self,
+        command_ids: Vec<Uuid>,
+    ) -> Result<DisconnectedShell, crate::PwshCoreError> {
+        let shell_id = self
+            .shell_id
+            .clone()
+            .ok_or(crate::PwshCoreError::InvalidState(
+                "The shell must be created to be disconnected",
+            ))?;
+
+        Ok(DisconnectedShell {
+            shell_id,
+            resource_uri: self.resource_uri.clone(),
+            command_ids,
+            reference_parameters: self.reference_parameters.clone(),
+        })
+    }
+
+    /// The `rsp:Disconnect` of the shell, the server keeps it for `idle_time_out`
+    pub(crate) fn disconnect_request<'a>(
+        &'a self,
+        ws_man: &'a WsMan,
+        idle_time_out: Option<std::time::Duration>,
+    ) -> Result<impl Into<Element<'a>>, crate::PwshCoreError> {
+        let shell_id = self
+            .shell_id
+            .as_deref()
+            .ok_or(crate::PwshCoreError::InvalidState(
+                "The shell must be created to be disconnected",
+            ))?;
+
+        let request = DisconnectRequest::builder()
+            .shell_id(shell_id)
+            .resource_uri(&self.resource_uri)
+            .reference_parameters(&self.reference_parameters);
+        let request = match idle_time_out {
+            Some(idle_time_out) => request.idle_time_out(idle_time_out).build(),
+            None => request.build(),
+        };
+        Ok(ws_man.disconnect(request))
+    }
+
+    /// The `rsp:Connect` of the shell with its `connectXml`, or of one of its commands
+    pub(crate) fn connect_request<'a>(
+        &'a self,
+        ws_man: &'a WsMan,
+        command_id: Option<Uuid>,
+        connect_xml: Option<&'a str>,
+    ) -> Result<impl Into<Element<'a>>, crate::PwshCoreError> {
+        let shell_id = self
+            .shell_id
+            .as_deref()
+            .ok_or(crate::PwshCoreError::InvalidState(
+                "The shell must be attached to be connected",
+            ))?;
+
+        let request = ConnectRequest::builder()
+            .shell_id(shell_id)
+            .resource_uri(&self.resource_uri)
+            .reference_parameters(&self.reference_parameters);
+        let request = match (command_id, connect_xml) {
+            (Some(command_id), Some(connect_xml)) => request
+                .command_id(command_id)
+                .connect_xml(connect_xml)
+                .build(),
+            (Some(command_id), None) => request.command_id(command_id).build(),
+            (None, Some(connect_xml)) => request.connect_xml(connect_xml).build(),
+            (None, None) => request.build(),
+        };
+        Ok(ws_man.connect(request))
+    }
+
+    /// The `rsp:Signal` of a command of the shell
+    pub(crate) fn signal_request<'a>(
+        &'a self,
+        ws_man: &'a WsMan,
+        command_id: Uuid,
+        code: SignalCode,
+    ) -> Result<impl Into<Element<'a>>, crate::PwshCoreError> {
+        let shell_id = self
+            .shell_id
+            .as_deref()
+            .ok_or(crate::PwshCoreError::InvalidState(
+                "The shell must be created to signal its commands",
+            ))?;
+
+        Ok(ws_man.signal(
+            SignalRequest::builder()
+                .shell_id(shell_id)
+                .resource_uri(&self.resource_uri)
+                .command_id(command_id)
+                .code(code)
+                .reference_parameters(&self.reference_parameters)
+                .build(),
+        ))
+    }
+
+    /// The `rsp:Send` of `data` on the `stdin` stream of a command, or of the shell without
+    /// one, `end` closes the stream
+    pub(crate) fn send_request<'a>(
+        &'a self,
+        ws_man: &'a WsMan,
+        command_id: Option<Uuid>,
+        data: &'a [u8],
+        end: bool,
+    ) -> Result<impl Into<Element<'a>>, crate::PwshCoreError> {
+        let shell_id = self
+            .shell_id
+            .as_deref()
+            .ok_or(crate::PwshCoreError::InvalidState(
+                "The shell must be created to send input to its commands",
+            ))?;
+
+        Ok(ws_man.send(
+            SendRequest::builder()
+                .shell_id(shell_id)
+                .resource_uri(&self.resource_uri)
+                .command_id_opt(command_id)
+                .data(data)
+                .end(end)
+                .compression_opt(self.negotiated_compression)
+                .reference_parameters(&self.reference_parameters)
+                .build(),
+        ))
+    }
+
     pub(crate) fn create_pipeline_request<'a>(
         &'a self,
         connection: &'a WsMan,
@@ -240,23 +449,23 @@ impl WinRunspace {
             arguments,
         };
 
-        let request = connection.invoke(
-            ws_management::WsAction::Command,
-            Some(self.resource_uri.as_ref()),
-            SoapBody::builder()
-                .command_line(
-                    Tag::new(command_line)
-                        .with_attribute(Attribute::CommandId(command_id.to_string().into())),
-                )
-                .build(),
-            Some(OptionSetValue::default().add_option(
-                "WINRS_SKIP_CMD_SHELL",
-                no_shell.unwrap_or_default().to_string(),
-            )),
-            self.selector_set.clone().into(),
-        );
-
-        Ok(request)
+        Ok(connection
+            .invoke(
+                ws_management::WsAction::Command,
+                Some(self.resource_uri.as_ref()),
+                SoapBody::builder()
+                    .command_line(
+                        Tag::new(command_line)
+                            .with_attribute(Attribute::CommandId(command_id.to_string().into())),
+                    )
+                    .build(),
+                Some(OptionSetValue::default().add_option(
+                    "WINRS_SKIP_CMD_SHELL",
+                    no_shell.unwrap_or_default().to_string(),
+                )),
+                self.selector_set.clone().into(),
+            )
+            .echo_parameters(&self.reference_parameters))
     }
 
     pub fn accept_commannd_response<'a>(
@@ -0,0 +1,1227 @@
+use futures_core::Stream;
+use protocol_powershell_remoting::{
+    Availability, PipelineInput, PowerShellPipeline, PsCredential, PsValue, ToPsObject, UserEvent,
+};
+use protocol_winrm::rsp::shell::DisconnectedShell;
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    pin::{Pin, pin},
+    task::Poll,
+    time::Duration,
+};
+use tracing::{debug, info, instrument};
+
+use super::{
+    enums::RunspacePoolState,
+    pool::{PoolCall, RunspacePool},
+    types::{PipelineOutput, PowerShell, StateChange},
+};
+use crate::host::{PsHost, call_host, needs_session_key};
+
+/// A [`RunspacePool`] driven over `exchange`, which sends a request envelope to the server
+/// and resolves to the response envelope.
+///
+/// [`AsyncRunspacePool::open`] runs the whole opening sequence: the `wxf:Create` of the shell
+/// with the `SESSION_CAPABILITY` and `INIT_RUNSPACEPOOL` of its `creationXml`, then the
+/// receives until the server reports the pool `Opened`. The session key is only exchanged
+/// once a `SecureString` needs it.
+///
+/// The host calls of the pipelines, e.g. of a `Read-Host`, are answered by the host of
+/// [`set_host`](Self::set_host) as they are received.
+///
+/// [`AsyncRunspacePool::disconnect`] leaves the pool and its pipelines running on the server,
+/// [`AsyncRunspacePool::connect`] resumes it with their buffered output.
+///
+/// Several pipelines run at once with [`start`](Self::start) and [`wait_all`](Self::wait_all):
+/// each has its own command and streams, and its own receive in flight over `exchange`, as
+/// long as `exchange` can carry several envelopes at once.
+///
+/// The events the scripts forward, e.g. of `New-Event`, go to the handlers of
+/// [`subscribe`](Self::subscribe) as they are received, and the changes of state of the pool
+/// and its pipelines to the ones of [`watch_state`](Self::watch_state).
+pub struct AsyncRunspacePool<F> {
+    pub(super) pool: RunspacePool,
+    pub(super) exchange: F,
+    host: Option<Box<dyn PsHost + Send>>,
+    /// The handlers of [`subscribe`](Self::subscribe) and their source identifier
+    subscriptions: Vec<(String, EventHandler)>,
+    /// The user events received that no handler subscribed to
+    user_events: Vec<UserEvent>,
+    /// The handlers of [`watch_state`](Self::watch_state)
+    state_watchers: Vec<StateWatcher>,
+}
+
+/// The most items of a streamed input sent in one go, they are fragmented together
+const INPUT_BATCH: usize = 64;
+
+/// What is told of the changes of state of a pool and its pipelines
+type StateWatcher = Box<dyn FnMut(&StateChange) + Send>;
+
+/// What handles the user events of a source identifier
+type EventHandler = Box<dyn FnMut(&UserEvent) + Send>;
+
+impl<F, Fut> AsyncRunspacePool<F>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, crate::PwshCoreError>>,
+{
+    /// Open `pool`, it is returned once the server reports it `Opened`.
+    ///
+    /// A pool the server breaks while opening is returned as
+    /// [`PwshCoreError::RunspacePoolBroken`](crate::PwshCoreError::RunspacePoolBroken).
+    #[instrument(skip_all, name = "AsyncRunspacePool::open", fields(id = %pool.id()))]
+    pub async fn open(pool: RunspacePool, mut exchange: F) -> Result<Self, crate::PwshCoreError> {
+        let (create, expect_shell_created) = pool.open()?;
+        let response = exchange(create).await?;
+        let pool = expect_shell_created.accept(response)?;
+
+        let mut pool = Self {
+            pool,
+            exchange,
+            host: None,
+            subscriptions: Vec::new(),
+            user_events: Vec::new(),
+            state_watchers: Vec::new(),
+        };
+        pool.receive_until_opened().await?;
+        info!("RunspacePool opened");
+        Ok(pool)
+    }
+
+    /// Connect `pool` to the disconnected pool of `shell`, what `Connect-PSSession` does:
+    /// the pool is returned opened, with the pipelines still running on the server. Their
+    /// output buffered while disconnected is received with [`wait`](Self::wait).
+    #[instrument(skip_all, name = "AsyncRunspacePool::connect", fields(shell_id = %shell.shell_id))]
+    pub async fn connect(
+        pool: RunspacePool,
+        shell: DisconnectedShell,
+        mut exchange: F,
+    ) -> Result<(Self, Vec<PowerShell>), crate::PwshCoreError> {
+        let command_ids = shell.command_ids.clone();
+        let (connect, expect_shell_connected) = pool.connect(shell)?;
+        let response = exchange(connect).await?;
+        let pool = expect_shell_connected.accept(response)?;
+
+        let mut pool = Self {
+            pool,
+            exchange,
+            host: None,
+            subscriptions: Vec::new(),
+            user_events: Vec::new(),
+            state_watchers: Vec::new(),
+        };
+        for command_id in &command_ids {
+            let connect = pool.pool.fire_connect_pipeline(*command_id)?;
+            let response = (pool.exchange)(connect).await?;
+            pool.pool.accept_connect_pipeline_response(response)?;
+        }
+        info!(pipelines = command_ids.len(), "RunspacePool connected");
+
+        let pipelines = command_ids.into_iter().map(PowerShell::new).collect();
+        Ok((pool, pipelines))
+    }
+
+    async fn receive_until_opened(&mut self) -> Result<(), crate::PwshCoreError> {
+        loop {
+            match self.pool.state() {
+                RunspacePoolState::Opened => return Ok(()),
+                RunspacePoolState::Broken => return Err(self.broken()),
+                RunspacePoolState::Closing | RunspacePoolState::Closed => {
+                    return Err(crate::PwshCoreError::InvalidState(
+                        "RunspacePool was closed while opening",
+                    ));
+                }
+                state => debug!(?state, "Waiting for the RunspacePool to open"),
+            }
+
+            let receive = self.pool.fire_receive()?;
+            let response = (self.exchange)(receive).await?;
+            self.pool.accept_response(response)?;
+            self.answer_server_requests().await?;
+        }
+    }
+
+    /// Exchange the session key if the server asked for it, then run the host calls received
+    /// on the host and send the responses the server waits for
+    async fn answer_server_requests(&mut self) -> Result<(), crate::PwshCoreError> {
+        self.dispatch_state_changes();
+        self.dispatch_user_events();
+        if self.pool.take_public_key_request() {
+            self.exchange_keys().await?;
+        }
+
+        for pending in self.pool.take_host_calls() {
+            if needs_session_key(&pending.call) {
+                self.exchange_keys().await?;
+            }
+
+            let host = self
+                .host
+                .as_mut()
+                .map(|host| host.as_mut() as &mut dyn PsHost);
+            let Some(result) = call_host(host, self.pool.session_key(), &pending.call) else {
+                continue;
+            };
+
+            let response = pending.call.response(result);
+            for send in self
+                .pool
+                .fire_host_response(pending.pipeline_id, response)?
+            {
+                let response = (self.exchange)(send).await?;
+                self.pool.accept_send_response(response)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The key of the `SecureString`s of the pool: the client sends a `PUBLIC_KEY`, then
+    /// receives until the server answers with the `ENCRYPTED_SESSION_KEY`. The key is only
+    /// exchanged once.
+    #[instrument(skip_all, name = "AsyncRunspacePool::exchange_keys", fields(id = %self.pool.id()))]
+    pub async fn exchange_keys(
+        &mut self,
+    ) -> Result<&protocol_powershell_remoting::SessionKey, crate::PwshCoreError> {
+        if self.pool.session_key().is_none() {
+            for send in self.pool.fire_public_key()? {
+                let response = (self.exchange)(send).await?;
+                self.pool.accept_send_response(response)?;
+            }
+
+            while self.pool.session_key().is_none() {
+                if self.pool.state() == RunspacePoolState::Broken {
+                    return Err(self.broken());
+                }
+
+                let receive = self.pool.fire_receive()?;
+                let response = (self.exchange)(receive).await?;
+                self.pool.accept_response(response)?;
+            }
+            info!("Session key exchanged");
+        }
+
+        self.pool
+            .session_key()
+            .ok_or(crate::PwshCoreError::UnlikelyToHappen(
+                "The session key was just exchanged",
+            ))
+    }
+
+    /// The `PSCredential` of `credential` for a `-Credential` parameter, e.g. of
+    /// [`PowerShell::add_parameter`](protocol_powershell_remoting::PowerShell::add_parameter).
+    /// Its password is encrypted with the session key, exchanged first if needed.
+    pub async fn credential(
+        &mut self,
+        credential: &PsCredential,
+    ) -> Result<PsValue, crate::PwshCoreError> {
+        let session_key = self.exchange_keys().await?;
+        Ok(credential.to_ps_value(session_key)?)
+    }
+
+    /// Change the maximum number of runspaces of the pool, whether the server accepted it
+    #[instrument(skip_all, name = "AsyncRunspacePool::set_max_runspaces", fields(id = %self.pool.id()))]
+    pub async fn set_max_runspaces(
+        &mut self,
+        max_runspaces: usize,
+    ) -> Result<bool, crate::PwshCoreError> {
+        match self.call_pool(PoolCall::SetMax(max_runspaces)).await? {
+            Availability::Changed(changed) => Ok(changed),
+            Availability::Available(_) => Err(crate::PwshCoreError::InvalidResponse(
+                "Expected a boolean RunspaceAvailability for SET_MAX_RUNSPACES".into(),
+            )),
+        }
+    }
+
+    /// Change the minimum number of runspaces of the pool, whether the server accepted it
+    #[instrument(skip_all, name = "AsyncRunspacePool::set_min_runspaces", fields(id = %self.pool.id()))]
+    pub async fn set_min_runspaces(
+        &mut self,
+        min_runspaces: usize,
+    ) -> Result<bool, crate::PwshCoreError> {
+        match self.call_pool(PoolCall::SetMin(min_runspaces)).await? {
+            Availability::Changed(changed) => Ok(changed),
+            Availability::Available(_) => Err(crate::PwshCoreError::InvalidResponse(
+                "Expected a boolean RunspaceAvailability for SET_MIN_RUNSPACES".into(),
+            )),
+        }
+    }
+
+    /// The number of runspaces of the pool free to run a pipeline
+    #[instrument(skip_all, name = "AsyncRunspacePool::get_available_runspaces", fields(id = %self.pool.id()))]
+    pub async fn get_available_runspaces(&mut self) -> Result<usize, crate::PwshCoreError> {
+        match self.call_pool(PoolCall::GetAvailable).await? {
+            Availability::Available(available) => Ok(usize::try_from(available).unwrap_or(0)),
+            Availability::Changed(_) => Err(crate::PwshCoreError::InvalidResponse(
+                "Expected a numeric RunspaceAvailability for GET_AVAILABLE_RUNSPACES".into(),
+            )),
+        }
+    }
+
+    /// Send `call`, then receive until the server answers it with a `RUNSPACE_AVAILABILITY`
+    async fn call_pool(&mut self, call: PoolCall) -> Result<Availability, crate::PwshCoreError> {
+        let (call_id, sends) = self.pool.fire_pool_call(call)?;
+        for send in sends {
+            let response = (self.exchange)(send).await?;
+            self.pool.accept_send_response(response)?;
+        }
+
+        loop {
+            if let Some(availability) = self.pool.take_availability(call_id) {
+                debug!(call_id, ?availability, "The server answered the pool call");
+                return Ok(availability);
+            }
+            if self.pool.state() == RunspacePoolState::Broken {
+                return Err(self.broken());
+            }
+
+            let receive = self.pool.fire_receive()?;
+            let response = (self.exchange)(receive).await?;
+            self.pool.accept_response(response)?;
+            self.answer_server_requests().await?;
+        }
+    }
+
+    /// Run `power_shell` in the pool: its `CREATE_PIPELINE` is the command, then the output is
+    /// received until the pipeline is `Completed`, `Failed` or `Stopped`.
+    ///
+    /// A pipeline that fails is not an error, its [`PipelineOutput::failure`] tells why.
+    #[instrument(skip_all, name = "AsyncRunspacePool::invoke", fields(id = %self.pool.id()))]
+    pub async fn invoke(
+        &mut self,
+        power_shell: PowerShellPipeline,
+    ) -> Result<PipelineOutput, crate::PwshCoreError> {
+        self.run(power_shell, None).await
+    }
+
+    /// Run `power_shell` with `items` as its input, what a script reads from `$input` or a
+    /// command binds from the pipeline. The input ends with the last of `items`.
+    #[instrument(skip_all, name = "AsyncRunspacePool::invoke_with_input", fields(id = %self.pool.id()))]
+    pub async fn invoke_with_input<T: ToPsObject>(
+        &mut self,
+        power_shell: PowerShellPipeline,
+        items: impl IntoIterator<Item = T>,
+    ) -> Result<PipelineOutput, crate::PwshCoreError> {
+        let inputs = items
+            .into_iter()
+            .map(|item| PipelineInput::new(item.to_ps_value()))
+            .collect();
+        self.run(power_shell, Some(inputs)).await
+    }
+
+    /// Run `power_shell` with the items of `items` as its input as they come, e.g. a local
+    /// file read line by line piped into a remote `ForEach-Object`.
+    ///
+    /// The items ready at once are sent together, the next ones are only polled once the
+    /// server acknowledged their `rsp:Send`, so a source faster than the link is held back
+    /// instead of buffered. The input ends with the stream.
+    #[instrument(skip_all, name = "AsyncRunspacePool::invoke_with_input_stream", fields(id = %self.pool.id()))]
+    pub async fn invoke_with_input_stream<T: ToPsObject>(
+        &mut self,
+        power_shell: PowerShellPipeline,
+        items: impl Stream<Item = T>,
+    ) -> Result<PipelineOutput, crate::PwshCoreError> {
+        let (pipeline_id, create) = self.pool.fire_invoke_pipeline(power_shell, false)?;
+        let response = (self.exchange)(create).await?;
+        self.pool.accept_response(response)?;
+
+        let mut items = pin!(items);
+
+        loop {
+            let mut inputs = Vec::new();
+            let end = loop {
+                // Wait for the first item of the batch only, then take what is ready
+                let polled = poll_fn(|cx| match items.as_mut().poll_next(cx) {
+                    Poll::Pending if !inputs.is_empty() => Poll::Ready(None),
+                    polled => polled.map(Some),
+                })
+                .await;
+                match polled {
+                    Some(Some(item)) => inputs.push(PipelineInput::new(item.to_ps_value())),
+                    // The stream ended
+                    Some(None) => break true,
+                    None => break false,
+                }
+                if inputs.len() == INPUT_BATCH {
+                    break false;
+                }
+            };
+
+            self.send_input(pipeline_id, &inputs, end).await?;
+            if end {
+                break;
+            }
+        }
+
+        self.wait(PowerShell::new(pipeline_id)).await
+    }
+
+    async fn run(
+        &mut self,
+        power_shell: PowerShellPipeline,
+        inputs: Option<Vec<PipelineInput>>,
+    ) -> Result<PipelineOutput, crate::PwshCoreError> {
+        let pipeline = self.start_pipeline(power_shell, inputs).await?;
+        self.wait(pipeline).await
+    }
+
+    /// Start `power_shell` in the pool without waiting for it, its output is received by
+    /// [`wait`](Self::wait) or [`wait_all`](Self::wait_all).
+    ///
+    /// Each pipeline is a command of its own on the shell, the server runs as many of them at
+    /// once as the pool has runspaces, see [`set_max_runspaces`](Self::set_max_runspaces).
+    #[instrument(skip_all, name = "AsyncRunspacePool::start", fields(id = %self.pool.id()))]
+    pub async fn start(
+        &mut self,
+        power_shell: PowerShellPipeline,
+    ) -> Result<PowerShell, crate::PwshCoreError> {
+        self.start_pipeline(power_shell, None).await
+    }
+
+    async fn start_pipeline(
+        &mut self,
+        power_shell: PowerShellPipeline,
+        inputs: Option<Vec<PipelineInput>>,
+    ) -> Result<PowerShell, crate::PwshCoreError> {
+        let (pipeline_id, create) = self
+            .pool
+            .fire_invoke_pipeline(power_shell, inputs.is_none())?;
+        let response = (self.exchange)(create).await?;
+        self.pool.accept_response(response)?;
+
+        if let Some(inputs) = inputs {
+            self.send_input(pipeline_id, &inputs, true).await?;
+        }
+
+        Ok(PowerShell::new(pipeline_id))
+    }
+
+    /// The `rsp:Send` of `inputs` to the pipeline, each waiting for the previous to be
+    /// acknowledged, with the end of the input when `end` is set
+    pub(super) async fn send_input(
+        &mut self,
+        pipeline_id: uuid::Uuid,
+        inputs: &[PipelineInput],
+        end: bool,
+    ) -> Result<(), crate::PwshCoreError> {
+        for send in self.pool.fire_send_input(pipeline_id, inputs, end)? {
+            let response = (self.exchange)(send).await?;
+            self.pool.accept_send_response(response)?;
+        }
+        Ok(())
+    }
+
+    /// Receive the output of `pipeline` until it is `Completed`, `Failed` or `Stopped`
+    #[instrument(skip_all, name = "AsyncRunspacePool::wait", fields(id = %self.pool.id(), pipeline = %pipeline.id()))]
+    pub async fn wait(
+        &mut self,
+        pipeline: PowerShell,
+    ) -> Result<PipelineOutput, crate::PwshCoreError> {
+        while !self.is_finished(pipeline.id()) {
+            self.receive_pipeline(pipeline.id()).await?;
+        }
+
+        self.finish(pipeline.id()).await
+    }
+
+    /// Start `power_shell` and stream its output, see [`output_stream`](Self::output_stream)
+    #[instrument(skip_all, name = "AsyncRunspacePool::invoke_stream", fields(id = %self.pool.id()))]
+    pub async fn invoke_stream(
+        &mut self,
+        power_shell: PowerShellPipeline,
+    ) -> Result<impl Stream<Item = Result<PsValue, crate::PwshCoreError>>, crate::PwshCoreError>
+    {
+        let pipeline = self.start(power_shell).await?;
+        Ok(self.output_stream(pipeline))
+    }
+
+    /// The output of `pipeline`, each object as soon as its `PIPELINE_OUTPUT` is received
+    /// instead of all of it once the pipeline finished, e.g. of a `Get-ChildItem -Recurse`
+    /// too large to be kept whole.
+    ///
+    /// The stream ends with the pipeline, with a [`PipelineFailed`] last if it failed or was
+    /// stopped. The records of its other streams are not returned, see [`wait`](Self::wait)
+    /// for them. A stream dropped before its end leaves the pipeline running, see
+    /// [`stop`](Self::stop).
+    ///
+    /// [`PipelineFailed`]: crate::PwshCoreError::PipelineFailed
+    pub fn output_stream(
+        &mut self,
+        pipeline: PowerShell,
+    ) -> impl Stream<Item = Result<PsValue, crate::PwshCoreError>> {
+        let output = OutputStream {
+            pool: self,
+            pipeline_id: pipeline.id(),
+            received: VecDeque::new(),
+            finished: false,
+            error: None,
+        };
+        futures_util::stream::unfold(output, |mut output| async move {
+            let item = output.next().await?;
+            Some((item, output))
+        })
+    }
+
+    /// Stop `pipeline` without closing the pool, then receive what it wrote until the server
+    /// reports it `Stopped`. A pipeline that finished in the meantime keeps its state.
+    #[instrument(skip_all, name = "AsyncRunspacePool::stop", fields(id = %self.pool.id(), pipeline = %pipeline.id()))]
+    pub async fn stop(
+        &mut self,
+        pipeline: PowerShell,
+    ) -> Result<PipelineOutput, crate::PwshCoreError> {
+        if !self.is_finished(pipeline.id()) {
+            let signal = self.pool.fire_stop_pipeline(pipeline.id())?;
+            let response = (self.exchange)(signal).await?;
+            self.pool.accept_signal_response(response)?;
+            info!("Pipeline stop requested");
+        }
+
+        self.wait(pipeline).await
+    }
+
+    /// Receive the output of all of `pipelines` until each is finished, their outputs are in
+    /// the same order.
+    ///
+    /// Each running pipeline has its `rsp:Receive` in flight, a long-polling one of a pipeline
+    /// with nothing to say does not hold back the output of the others when `exchange` sends
+    /// them at once, e.g. on the connections of a
+    /// [`PooledTransport`](crate::connector::pool::PooledTransport) with a `max_connections` of
+    /// at least the pipelines. An [`HttpTransport`](crate::connector::transport::HttpTransport)
+    /// authenticated by NTLM, Kerberos or Negotiate sends one envelope at a time on the
+    /// connection of its handshake: there the receive of an idle pipeline holds the others
+    /// back until its `OperationTimeout`.
+    #[instrument(skip_all, name = "AsyncRunspacePool::wait_all", fields(id = %self.pool.id()))]
+    pub async fn wait_all(
+        &mut self,
+        pipelines: impl IntoIterator<Item = PowerShell>,
+    ) -> Result<Vec<PipelineOutput>, crate::PwshCoreError> {
+        let pipeline_ids: Vec<uuid::Uuid> = pipelines
+            .into_iter()
+            .map(|pipeline| pipeline.id())
+            .collect();
+
+        let mut receiving: Vec<(uuid::Uuid, Pin<Box<Fut>>)> = Vec::new();
+        loop {
+            for pipeline_id in &pipeline_ids {
+                if self.is_finished(*pipeline_id)
+                    || receiving
+                        .iter()
+                        .any(|(receiver, _)| receiver == pipeline_id)
+                {
+                    continue;
+                }
+                if self.pool.state() == RunspacePoolState::Broken {
+                    return Err(self.broken());
+                }
+
+                let receive = self.pool.fire_receive_pipeline(*pipeline_id)?;
+                receiving.push((*pipeline_id, Box::pin((self.exchange)(receive))));
+            }
+            if receiving.is_empty() {
+                break;
+            }
+
+            // The first receive answered, the others stay in flight
+            let (index, response) = poll_fn(|cx| {
+                receiving
+                    .iter_mut()
+                    .enumerate()
+                    .find_map(|(index, (_, receive))| match receive.as_mut().poll(cx) {
+                        Poll::Ready(response) => Some((index, response)),
+                        Poll::Pending => None,
+                    })
+                    .map_or(Poll::Pending, Poll::Ready)
+            })
+            .await;
+            receiving.swap_remove(index);
+
+            self.pool.accept_response(response?)?;
+            self.answer_server_requests().await?;
+        }
+
+        let mut outputs = Vec::with_capacity(pipeline_ids.len());
+        for pipeline_id in pipeline_ids {
+            outputs.push(self.finish(pipeline_id).await?);
+        }
+        Ok(outputs)
+    }
+
+    /// Run all of `power_shells` at once in the pool, see [`start`](Self::start) and
+    /// [`wait_all`](Self::wait_all)
+    #[instrument(skip_all, name = "AsyncRunspacePool::invoke_all", fields(id = %self.pool.id()))]
+    pub async fn invoke_all(
+        &mut self,
+        power_shells: impl IntoIterator<Item = PowerShellPipeline>,
+    ) -> Result<Vec<PipelineOutput>, crate::PwshCoreError> {
+        let mut pipelines = Vec::new();
+        for power_shell in power_shells {
+            pipelines.push(self.start(power_shell).await?);
+        }
+
+        self.wait_all(pipelines).await
+    }
+
+    /// Whether the pipeline is done, a pipeline the pool does not know is
+    pub(super) fn is_finished(&self, pipeline_id: uuid::Uuid) -> bool {
+        self.pool
+            .pipeline(pipeline_id)
+            .is_none_or(|pipeline| pipeline.state.is_finished())
+    }
+
+    /// One `rsp:Receive` of the output of the pipeline
+    pub(super) async fn receive_pipeline(
+        &mut self,
+        pipeline_id: uuid::Uuid,
+    ) -> Result<(), crate::PwshCoreError> {
+        if self.pool.state() == RunspacePoolState::Broken {
+            return Err(self.broken());
+        }
+
+        let receive = self.pool.fire_receive_pipeline(pipeline_id)?;
+        let response = (self.exchange)(receive).await?;
+        self.pool.accept_response(response)?;
+        self.answer_server_requests().await
+    }
+
+    /// Release the command of a finished pipeline, and take its output
+    pub(super) async fn finish(
+        &mut self,
+        pipeline_id: uuid::Uuid,
+    ) -> Result<PipelineOutput, crate::PwshCoreError> {
+        // The server keeps the command until it is terminated, failing to does not lose output
+        let release = self.pool.fire_release_pipeline(pipeline_id)?;
+        match (self.exchange)(release).await {
+            Ok(response) => {
+                if let Err(error) = self.pool.accept_signal_response(response) {
+                    debug!(%error, "Failed to release the pipeline");
+                }
+            }
+            Err(error) => debug!(%error, "Failed to release the pipeline"),
+        }
+
+        self.pool
+            .take_pipeline(pipeline_id)
+            .map(PipelineOutput::from)
+            .ok_or(crate::PwshCoreError::InvalidState(
+                "The pipeline was lost while it ran",
+            ))
+    }
+
+    /// Run `script` as a single script command, see [`invoke`](Self::invoke)
+    pub async fn invoke_script(
+        &mut self,
+        script: &str,
+    ) -> Result<PipelineOutput, crate::PwshCoreError> {
+        let power_shell = protocol_powershell_remoting::PowerShell::new()
+            .add_script(script)
+            .build()?;
+
+        self.invoke(power_shell).await
+    }
+
+    /// Disconnect from the pool, what `Disconnect-PSSession` does: the server keeps it and its
+    /// running pipelines for `idle_time_out`, or its own idle timeout, buffering their output.
+    /// The [`DisconnectedShell`] connects to it again with [`connect`](Self::connect), from
+    /// this process or another one.
+    #[instrument(skip_all, name = "AsyncRunspacePool::disconnect", fields(id = %self.pool.id()))]
+    pub async fn disconnect(
+        mut self,
+        idle_time_out: Option<Duration>,
+    ) -> Result<DisconnectedShell, crate::PwshCoreError> {
+        let disconnect = self.pool.fire_disconnect(idle_time_out)?;
+        let response = (self.exchange)(disconnect).await?;
+        let shell = self.pool.accept_disconnect_response(response)?;
+        info!(
+            pipelines = shell.command_ids.len(),
+            "RunspacePool disconnected"
+        );
+        Ok(shell)
+    }
+
+    /// Receive the events the server forwarded to the pool, outside of the pipelines: the
+    /// ones no handler subscribed to are returned. The receive waits for the server up to the
+    /// `OperationTimeout` of the connection.
+    #[instrument(skip_all, name = "AsyncRunspacePool::receive_user_events", fields(id = %self.pool.id()))]
+    pub async fn receive_user_events(&mut self) -> Result<Vec<UserEvent>, crate::PwshCoreError> {
+        if self.pool.state() == RunspacePoolState::Broken {
+            return Err(self.broken());
+        }
+
+        let receive = self.pool.fire_receive()?;
+        let response = (self.exchange)(receive).await?;
+        self.pool.accept_response(response)?;
+        self.answer_server_requests().await?;
+        Ok(self.take_user_events())
+    }
+
+    /// Close the pool and delete its shell, the server stops the pipelines still running
+    #[instrument(skip_all, name = "AsyncRunspacePool::close", fields(id = %self.pool.id()))]
+    pub async fn close(mut self) -> Result<(), crate::PwshCoreError> {
+        let delete = self.pool.fire_close()?;
+        let response = (self.exchange)(delete).await?;
+        self.pool.accept_close_response(response)
+    }
+}
+
+impl<F> AsyncRunspacePool<F> {
+    pub fn id(&self) -> uuid::Uuid {
+        self.pool.id()
+    }
+
+    /// The host of the pipelines run from now on, without one their prompts throw
+    pub fn set_host(&mut self, host: impl PsHost + Send + 'static) {
+        self.host = Some(Box::new(host));
+    }
+
+    /// Call `handler` with each event of `source_identifier` the server forwards, e.g. of a
+    /// `New-Event -SourceIdentifier` or `Register-EngineEvent -Forward` of a script. It
+    /// replaces the handler the source identifier had, whatever the case.
+    pub fn subscribe(
+        &mut self,
+        source_identifier: impl Into<String>,
+        handler: impl FnMut(&UserEvent) + Send + 'static,
+    ) {
+        let source_identifier = source_identifier.into();
+        self.unsubscribe(&source_identifier);
+        self.subscriptions
+            .push((source_identifier, Box::new(handler)));
+    }
+
+    /// Stop handling the events of `source_identifier`, whether it had a handler
+    pub fn unsubscribe(&mut self, source_identifier: &str) -> bool {
+        let subscribed = self.subscriptions.len();
+        self.subscriptions
+            .retain(|(subscribed, _)| !subscribed.eq_ignore_ascii_case(source_identifier));
+        self.subscriptions.len() != subscribed
+    }
+
+    /// The events received that no handler subscribed to, in order
+    pub fn take_user_events(&mut self) -> Vec<UserEvent> {
+        self.dispatch_user_events();
+        std::mem::take(&mut self.user_events)
+    }
+
+    /// Call `watcher` with each change of state of the pool and of its pipelines the server
+    /// reports from now on, e.g. to connect again to a pool that broke. A pipeline that fails
+    /// or is stopped comes with its error record, a pool that breaks with its reason.
+    pub fn watch_state(&mut self, watcher: impl FnMut(&StateChange) + Send + 'static) {
+        self.state_watchers.push(Box::new(watcher));
+    }
+
+    /// Tell the watchers of the changes of state received, they are dropped without any
+    fn dispatch_state_changes(&mut self) {
+        for change in self.pool.take_state_changes() {
+            debug!(?change, "State changed");
+            for watcher in &mut self.state_watchers {
+                watcher(&change);
+            }
+        }
+    }
+
+    /// Hand the events received to their handler, keep the others
+    fn dispatch_user_events(&mut self) {
+        for user_event in self.pool.take_user_events() {
+            let handler = self
+                .subscriptions
+                .iter_mut()
+                .find(|(source_identifier, _)| {
+                    source_identifier.eq_ignore_ascii_case(&user_event.source_identifier)
+                });
+            match handler {
+                Some((_, handler)) => handler(&user_event),
+                None => self.user_events.push(user_event),
+            }
+        }
+    }
+
+    /// The state of the pool, as the last `RUNSPACEPOOL_STATE` of the server left it
+    pub fn state(&self) -> RunspacePoolState {
+        self.pool.state()
+    }
+
+    pub fn is_opened(&self) -> bool {
+        self.pool.state() == RunspacePoolState::Opened
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.pool.state() == RunspacePoolState::Broken
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.pool.state() == RunspacePoolState::Closed
+    }
+
+    /// Why the server broke the pool, see [`RunspacePool::broken_reason`]
+    pub fn broken_reason(&self) -> Option<&str> {
+        self.pool.broken_reason()
+    }
+
+    /// The key of the `SecureString`s of the pool, see [`exchange_keys`](Self::exchange_keys)
+    pub fn session_key(&self) -> Option<&protocol_powershell_remoting::SessionKey> {
+        self.pool.session_key()
+    }
+
+    /// The protocol version negotiated with the server
+    pub fn protocol_version(&self) -> Option<protocol_powershell_remoting::ProtocolVersion> {
+        self.pool.protocol_version()
+    }
+
+    pub fn min_runspaces(&self) -> usize {
+        self.pool.min_runspaces()
+    }
+
+    pub fn max_runspaces(&self) -> usize {
+        self.pool.max_runspaces()
+    }
+
+    pub fn runspace_pool(&self) -> &RunspacePool {
+        &self.pool
+    }
+
+    fn broken(&self) -> crate::PwshCoreError {
+        crate::PwshCoreError::RunspacePoolBroken(
+            self.pool
+                .broken_reason()
+                .unwrap_or("the server broke the pool")
+                .to_owned(),
+        )
+    }
+}
+
+/// The state of an [`AsyncRunspacePool::output_stream`] between its items
+struct OutputStream<'a, F> {
+    pool: &'a mut AsyncRunspacePool<F>,
+    pipeline_id: uuid::Uuid,
+    /// The output of the last receive not yet yielded
+    received: VecDeque<PsValue>,
+    finished: bool,
+    /// What ends the stream once `received` is yielded
+    error: Option<crate::PwshCoreError>,
+}
+
+impl<F, Fut> OutputStream<'_, F>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, crate::PwshCoreError>>,
+{
+    async fn next(&mut self) -> Option<Result<PsValue, crate::PwshCoreError>> {
+        loop {
+            if let Some(value) = self.received.pop_front() {
+                return Some(Ok(value));
+            }
+            if self.finished {
+                return self.error.take().map(Err);
+            }
+
+            // The output of the last receive is consumed before the pipeline can be released,
+            // and yielded before the next receive waits for more
+            let finished = self.pool.is_finished(self.pipeline_id);
+            self.received
+                .extend(self.pool.pool.take_output(self.pipeline_id));
+            if !self.received.is_empty() && !finished {
+                continue;
+            }
+
+            if !finished {
+                if let Err(error) = self.pool.receive_pipeline(self.pipeline_id).await {
+                    self.finished = true;
+                    self.error = Some(error);
+                }
+                continue;
+            }
+
+            self.finished = true;
+            match self.pool.finish(self.pipeline_id).await {
+                Ok(output) if !output.success() => {
+                    let reason = output.failure.map_or_else(
+                        || format!("the pipeline is {:?}", output.state),
+                        |failure| failure.to_string(),
+                    );
+                    self.error = Some(crate::PwshCoreError::PipelineFailed(reason));
+                }
+                Ok(_) => {}
+                Err(error) => self.error = Some(error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol_powershell_remoting::{MessageType, PipelineStateValue, RunspacePoolStateValue};
+
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::runspace_pool::enums::PsInvocationState;
+    use crate::runspace_pool::test_server::{
+        Gate, Message, Reply, Request, TestServer, error_record,
+    };
+    use futures_util::StreamExt;
+
+    fn script(script: &str) -> PowerShellPipeline {
+        protocol_powershell_remoting::PowerShell::new()
+            .add_script(script)
+            .build()
+            .unwrap()
+    }
+
+    fn output(value: &str) -> Message {
+        Message::Output(value.to_ps_value())
+    }
+
+    fn completed() -> Message {
+        Message::PipelineState(PipelineStateValue::Completed, None)
+    }
+
+    fn outputs(output: &PipelineOutput) -> Vec<&str> {
+        output.output.iter().filter_map(PsValue::as_str).collect()
+    }
+
+    #[tokio::test]
+    async fn test_open_and_close() {
+        let (pool, server) = TestServer::new();
+        server.pool_receive(Reply::new([Message::PoolState(
+            RunspacePoolStateValue::Opening,
+            None,
+        )]));
+        server.pool_receive(Reply::new([Message::PoolState(
+            RunspacePoolStateValue::Opened,
+            None,
+        )]));
+
+        let pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        assert!(pool.is_opened());
+        assert_eq!(
+            server.requests(),
+            [
+                Request::Create,
+                Request::Receive(None),
+                Request::Receive(None)
+            ]
+        );
+
+        pool.close().await.unwrap();
+        assert_eq!(server.requests().last(), Some(&Request::Delete));
+    }
+
+    #[tokio::test]
+    async fn test_open_broken() {
+        let (pool, server) = TestServer::new();
+        server.pool_receive(Reply::new([Message::PoolState(
+            RunspacePoolStateValue::Broken,
+            Some(error_record("the configuration is unknown")),
+        )]));
+
+        let error = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            matches!(&error, crate::PwshCoreError::RunspacePoolBroken(reason) if reason == "the configuration is unknown"),
+            "{error}"
+        );
+        assert_eq!(server.requests(), [Request::Create, Request::Receive(None)]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_all_receives_at_once() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        let slow = pool.start(script("Start-Sleep 60; 'slow'")).await.unwrap();
+        let fast = pool.start(script("'a'; 'b'")).await.unwrap();
+
+        // The slow pipeline is only answered once the fast one completed
+        let gate = Gate::new();
+        server.pipeline_receive(0, Reply::new([output("slow"), completed()]).after(&gate));
+        server.pipeline_receive(1, Reply::new([output("a")]));
+        server.pipeline_receive(1, Reply::new([output("b"), completed()]).opening(&gate));
+
+        let outputs_of = tokio::time::timeout(Duration::from_secs(5), pool.wait_all([slow, fast]))
+            .await
+            .expect("the receive of the slow pipeline held back the fast one")
+            .unwrap();
+        assert_eq!(outputs(&outputs_of[0]), ["slow"]);
+        assert_eq!(outputs(&outputs_of[1]), ["a", "b"]);
+
+        let receives: Vec<Request> = server
+            .requests()
+            .into_iter()
+            .filter(|request| matches!(request, Request::Receive(Some(_))))
+            .collect();
+        assert_eq!(
+            receives,
+            [
+                Request::Receive(Some(0)),
+                Request::Receive(Some(1)),
+                Request::Receive(Some(1)),
+            ]
+        );
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_wait_all_on_pooled_connections() {
+        use crate::connector::{
+            pool::{ConnectionPool, PoolConfig},
+            test_server::{self, basic},
+        };
+        use protocol_winrm::transport::Transport;
+
+        // The envelopes go over HTTP to the server of the pool, on connections of a pool
+        let (pool, server) = TestServer::opening();
+        let http = test_server::TestServer::new();
+        let runtime = tokio::runtime::Handle::current();
+        let answer = Mutex::new(server.exchange());
+        http.answer(move |request| {
+            let envelope = String::from_utf8_lossy(&request.body).into_owned();
+            let response = (answer.lock().unwrap())(envelope);
+            test_server::Response::envelope(200, &runtime.block_on(response).unwrap())
+        });
+        let transport = ConnectionPool::new(PoolConfig::builder().max_connections(2).build())
+            .transport(&http.config(basic("user", "password")))
+            .unwrap();
+        let connections = transport.clone();
+        let exchange = move |envelope: String| {
+            let transport = transport.clone();
+            async move { Ok::<_, crate::PwshCoreError>(transport.exchange(envelope).await?) }
+        };
+
+        let mut pool = AsyncRunspacePool::open(pool, exchange).await.unwrap();
+        let idle = pool.start(script("Wait-Event -Timeout 60")).await.unwrap();
+        let busy = pool.start(script("'a'; 'b'")).await.unwrap();
+
+        // The idle pipeline never writes, its receive is only answered once the other completed
+        let gate = Gate::new();
+        server.pipeline_receive(0, Reply::new([completed()]).after(&gate));
+        server.pipeline_receive(1, Reply::new([output("a")]));
+        server.pipeline_receive(1, Reply::new([output("b"), completed()]).opening(&gate));
+
+        let outputs_of = tokio::time::timeout(Duration::from_secs(5), pool.wait_all([idle, busy]))
+            .await
+            .expect("the receive of the idle pipeline held back the other one")
+            .unwrap();
+        assert!(outputs_of[0].output.is_empty());
+        assert_eq!(outputs(&outputs_of[1]), ["a", "b"]);
+        assert_eq!(connections.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stop() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        let pipeline = pool
+            .start(script("while ($true) { 'busy' }"))
+            .await
+            .unwrap();
+        server.pipeline_receive(
+            0,
+            Reply::new([
+                output("busy"),
+                Message::PipelineState(
+                    PipelineStateValue::Stopped,
+                    Some(error_record("The pipeline has been stopped.")),
+                ),
+            ]),
+        );
+
+        let output = pool.stop(pipeline).await.unwrap();
+        assert_eq!(output.state, PsInvocationState::Stopped);
+        assert_eq!(outputs(&output), ["busy"]);
+        assert_eq!(
+            output.failure.map(|failure| failure.message).as_deref(),
+            Some("The pipeline has been stopped.")
+        );
+        assert!(pool.is_opened());
+        assert_eq!(
+            server.requests()[2..],
+            [
+                Request::Command(0),
+                Request::Signal(0, "crtl_c"),
+                Request::Receive(Some(0)),
+                Request::Signal(0, "terminate"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_state() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        pool.watch_state({
+            let changes = Arc::clone(&changes);
+            move |change| changes.lock().unwrap().push(change.clone())
+        });
+
+        let pipeline = pool.start(script("'a'; throw 'failed'")).await.unwrap();
+        let pipeline_id = pipeline.id();
+        // A pipeline is running once created, the server saying so again is no change
+        server.pipeline_receive(
+            0,
+            Reply::new([
+                Message::PipelineState(PipelineStateValue::Running, None),
+                output("a"),
+            ]),
+        );
+        server.pipeline_receive(
+            0,
+            Reply::new([Message::PipelineState(
+                PipelineStateValue::Failed,
+                Some(error_record("failed")),
+            )]),
+        );
+        pool.wait(pipeline).await.unwrap();
+        server.pool_receive(Reply::new([Message::PoolState(
+            RunspacePoolStateValue::Broken,
+            Some(error_record("the shell was closed")),
+        )]));
+        pool.receive_user_events().await.unwrap();
+
+        let changes = changes.lock().unwrap();
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| match change {
+                StateChange::RunspacePool {
+                    previous,
+                    state,
+                    reason,
+                } => (
+                    None,
+                    format!("{previous:?} -> {state:?}"),
+                    reason.as_ref().map(|reason| reason.message.as_str()),
+                ),
+                StateChange::Pipeline {
+                    id,
+                    previous,
+                    state,
+                    failure,
+                } => (
+                    Some(*id),
+                    format!("{previous:?} -> {state:?}"),
+                    failure.as_ref().map(|failure| failure.message.as_str()),
+                ),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    Some(pipeline_id),
+                    "Running -> Failed".to_owned(),
+                    Some("failed")
+                ),
+                (
+                    None,
+                    "Opened -> Broken".to_owned(),
+                    Some("the shell was closed")
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_output_stream() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        let pipeline = pool
+            .start(script("'a'; 'b'; throw 'failed'"))
+            .await
+            .unwrap();
+        server.pipeline_receive(0, Reply::new([output("a"), output("b")]));
+        server.pipeline_receive(
+            0,
+            Reply::new([Message::PipelineState(
+                PipelineStateValue::Failed,
+                Some(error_record("failed")),
+            )]),
+        );
+        let pipeline_receives = || {
+            server
+                .requests()
+                .iter()
+                .filter(|request| matches!(request, Request::Receive(Some(_))))
+                .count()
+        };
+
+        let mut stream = pin!(pool.output_stream(pipeline));
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first.as_str(), Some("a"));
+        let second = stream.next().await.unwrap().unwrap();
+        assert_eq!(second.as_str(), Some("b"));
+        // Both were yielded before the pipeline finished
+        assert_eq!(pipeline_receives(), 1);
+
+        let error = stream.next().await.unwrap().unwrap_err();
+        assert!(
+            matches!(&error, crate::PwshCoreError::PipelineFailed(reason) if reason.contains("failed")),
+            "{error}"
+        );
+        assert!(stream.next().await.is_none());
+        assert_eq!(pipeline_receives(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_input_stream_waits_for_acknowledgement() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        server.pipeline_receive(0, Reply::new([output("3"), completed()]));
+
+        // Each item is only ready once polled again, with the sends seen then
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let items = futures_util::stream::unfold(0, |item| {
+            let server = server.clone();
+            let sends = Arc::clone(&sends);
+            async move {
+                if item == 3 {
+                    return None;
+                }
+                tokio::task::yield_now().await;
+                let sent = server
+                    .requests()
+                    .iter()
+                    .filter(|request| matches!(request, Request::Send(..)))
+                    .count();
+                sends.lock().unwrap().push(sent);
+                Some((item, item + 1))
+            }
+        });
+
+        let output = pool
+            .invoke_with_input_stream(script("$input | Measure-Object"), items)
+            .await
+            .unwrap();
+        assert_eq!(outputs(&output), ["3"]);
+        assert_eq!(*sends.lock().unwrap(), [0, 1, 2]);
+
+        let sent: Vec<_> = server
+            .requests()
+            .into_iter()
+            .filter_map(|request| match request {
+                Request::Send(0, messages) => Some(messages),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            sent,
+            [
+                vec![MessageType::PipelineInput],
+                vec![MessageType::PipelineInput],
+                // The end of the stream was ready with the last item
+                vec![MessageType::PipelineInput, MessageType::EndOfPipelineInput],
+            ]
+        );
+    }
+}
@@ -1,13 +1,10 @@
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+use std::{collections::HashMap, sync::Arc};
 
 use protocol_powershell_remoting::{
     ApartmentState, ApplicationPrivateData, Defragmenter, Fragment, Fragmenter, HostInfo,
-    PSThreadOptions, PsValue, SessionCapability,
+    PSThreadOptions, ProtocolVersion, PsPrimitiveDictionary, SessionCapability,
 };
-use protocol_winrm::ws_management::WsMan;
+use protocol_winrm::{soap::HeaderRegistry, ws_management::WsMan};
 
 use crate::runspace::win_rs::WinRunspace;
 
@@ -37,8 +34,14 @@ pub struct RunspacePoolCreator {
 
     host_info: HostInfo,
 
-    #[builder(default = std::collections::BTreeMap::new())]
-    application_arguments: std::collections::BTreeMap<PsValue, PsValue>,
+    /// What the session configuration reads from `$PSSenderInfo.ApplicationArguments`
+    #[builder(default)]
+    application_arguments: PsPrimitiveDictionary,
+
+    /// The protocol version the client speaks, the pool speaks the older of it and the one of
+    /// the server
+    #[builder(default)]
+    protocol_version: ProtocolVersion,
 
     #[builder(default)]
     pipeline: HashMap<String, PowerShell>,
@@ -53,7 +56,11 @@ pub struct RunspacePoolCreator {
     session_capability: Option<SessionCapability>,
 
     #[builder(default)]
-    pipelines: HashSet<PipelineRepresentation>,
+    pipelines: HashMap<uuid::Uuid, PipelineRepresentation>,
+
+    /// Custom headers understood in responses, besides the well-known ones
+    #[builder(default)]
+    header_registry: HeaderRegistry,
 }
 
 impl RunspacePoolCreator {
@@ -69,13 +76,26 @@ impl RunspacePoolCreator {
             apartment_state: self.apartment_state,
             host_info: self.host_info,
             application_arguments: self.application_arguments,
-            fragmenter: Fragmenter::new(connection.max_envelope_size() as usize),
+            fragmenter: Fragmenter::for_envelope_size(connection.max_envelope_size() as usize),
             connection,
             shell,
             defragmenter: self.defragmenter,
             application_private_data: self.application_private_data,
             session_capability: self.session_capability,
+            protocol_version: self.protocol_version,
+            negotiated_version: None,
             pipelines: self.pipelines,
+            header_registry: self.header_registry,
+            broken_reason: None,
+            host_calls: Vec::new(),
+            user_events: Vec::new(),
+            state_changes: Vec::new(),
+            key_exchange: None,
+            session_key: None,
+            public_key_requested: false,
+            next_call_id: 0,
+            pool_calls: HashMap::new(),
+            availabilities: HashMap::new(),
         }
     }
 }
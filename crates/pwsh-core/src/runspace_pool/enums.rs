@@ -1,4 +1,4 @@
-use protocol_powershell_remoting::{RunspacePoolStateMessage, RunspacePoolStateValue};
+use protocol_powershell_remoting::{PipelineStateValue, RunspacePoolStateValue};
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum PowerShellState {
@@ -17,6 +17,42 @@ pub enum PsInvocationState {
     Disconnected = 6,
 }
 
+impl From<&PipelineStateValue> for PsInvocationState {
+    fn from(value: &PipelineStateValue) -> Self {
+        match value {
+            PipelineStateValue::NotStarted => PsInvocationState::NotStarted,
+            PipelineStateValue::Running => PsInvocationState::Running,
+            PipelineStateValue::Stopping => PsInvocationState::Stopping,
+            PipelineStateValue::Stopped => PsInvocationState::Stopped,
+            PipelineStateValue::Completed => PsInvocationState::Completed,
+            PipelineStateValue::Failed => PsInvocationState::Failed,
+            PipelineStateValue::Disconnected => PsInvocationState::Disconnected,
+        }
+    }
+}
+
+impl PsInvocationState {
+    /// Whether the pipeline is over, it sends no more output
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            PsInvocationState::Stopped | PsInvocationState::Completed | PsInvocationState::Failed
+        )
+    }
+}
+
+/// The PowerShell streams a pipeline writes to
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum PsStream {
+    Output,
+    Error,
+    Warning,
+    Verbose,
+    Debug,
+    Information,
+    Progress,
+}
+
 /// https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-psrp/b05495bc-a9b2-4794-9f43-4bf1f3633900
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
@@ -48,4 +84,4 @@ impl From<&RunspacePoolStateValue> for RunspacePoolState {
             RunspacePoolStateValue::Disconnected => RunspacePoolState::Disconnected,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,37 @@
+use base64::Engine;
+use protocol_winrm::rsp::disconnect::ConnectResponse;
+
+use super::{enums::RunspacePoolState, pool::RunspacePool};
+
+#[derive(Debug)]
+pub struct ExpectShellConnected {
+    pub(super) runspace_pool: RunspacePool,
+}
+
+impl ExpectShellConnected {
+    /// The pool is opened again once its `connectResponseXml` is read, the
+    /// `SESSION_CAPABILITY`, `RUNSPACEPOOL_INIT_DATA` and `APPLICATION_PRIVATE_DATA` of the
+    /// server. The pipelines output what they buffered while disconnected on their next
+    /// receive.
+    pub fn accept(self, response: String) -> Result<RunspacePool, crate::PwshCoreError> {
+        let ExpectShellConnected { mut runspace_pool } = self;
+
+        let parsed = xml::parser::parse(response.as_str())?;
+        let connect_response =
+            ConnectResponse::from_node(parsed.root_element(), runspace_pool.connection.locale())?;
+
+        if let Some(connect_response_xml) = connect_response.connect_response_xml() {
+            let fragments = base64::engine::general_purpose::STANDARD
+                .decode(connect_response_xml)
+                .map_err(|_| {
+                    crate::PwshCoreError::InvalidResponse(
+                        "The connectResponseXml is not base64".into(),
+                    )
+                })?;
+            runspace_pool.parse_responses(vec![fragments])?;
+        }
+
+        runspace_pool.state = RunspacePoolState::Opened;
+        Ok(runspace_pool)
+    }
+}
@@ -16,6 +16,8 @@ impl ExpectShellCreated {
 
         let soap_response = SoapEnvelope::from_node(parsed.root_element())
             .map_err(crate::PwshCoreError::XmlParsingError)?;
+        runspace_pool.check_session_id(&soap_response);
+        soap_response.check_must_understand(&runspace_pool.header_registry)?;
 
         runspace_pool.shell.accept_create_response(&soap_response)?;
 
@@ -0,0 +1,424 @@
+use base64::Engine;
+use protocol_powershell_remoting::{PipelineInput, PsPrimitiveValue, PsValue, ToPsObject};
+use tracing::{debug, info, instrument};
+
+use super::{
+    async_pool::AsyncRunspacePool,
+    types::{PipelineOutput, PowerShell},
+};
+
+/// The bytes of a file in each `PIPELINE_INPUT` or `PIPELINE_OUTPUT` string, before base64
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Writes the base64 chunks of its input to `$Path`, then outputs their SHA-256
+const UPLOAD_SCRIPT: &str = r#"param([string]$Path)
+$ErrorActionPreference = 'Stop'
+$Path = $ExecutionContext.SessionState.Path.GetUnresolvedProviderPathFromPSPath($Path)
+$sha256 = [System.Security.Cryptography.SHA256]::Create()
+$stream = [System.IO.File]::Create($Path)
+try {
+    foreach ($chunk in $input) {
+        $bytes = [System.Convert]::FromBase64String($chunk)
+        $stream.Write($bytes, 0, $bytes.Length)
+        $null = $sha256.TransformBlock($bytes, 0, $bytes.Length, $bytes, 0)
+    }
+    $null = $sha256.TransformFinalBlock((New-Object byte[] 0), 0, 0)
+    [System.BitConverter]::ToString($sha256.Hash).Replace('-', '').ToLowerInvariant()
+}
+finally {
+    $stream.Dispose()
+    $sha256.Dispose()
+}"#;
+
+/// Outputs the length of `$Path`, the base64 of each of its chunks, then an object with
+/// their SHA-256
+const DOWNLOAD_SCRIPT: &str = r#"param([string]$Path, [int]$ChunkSize)
+$ErrorActionPreference = 'Stop'
+$Path = $ExecutionContext.SessionState.Path.GetUnresolvedProviderPathFromPSPath($Path)
+$sha256 = [System.Security.Cryptography.SHA256]::Create()
+$stream = [System.IO.File]::OpenRead($Path)
+try {
+    [long]$stream.Length
+    $buffer = New-Object byte[] $ChunkSize
+    while (($read = $stream.Read($buffer, 0, $ChunkSize)) -gt 0) {
+        $null = $sha256.TransformBlock($buffer, 0, $read, $buffer, 0)
+        [System.Convert]::ToBase64String($buffer, 0, $read)
+    }
+    $null = $sha256.TransformFinalBlock((New-Object byte[] 0), 0, 0)
+    [PSCustomObject]@{
+        Sha256 = [System.BitConverter]::ToString($sha256.Hash).Replace('-', '').ToLowerInvariant()
+    }
+}
+finally {
+    $stream.Dispose()
+    $sha256.Dispose()
+}"#;
+
+impl<F, Fut> AsyncRunspacePool<F>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String, crate::PwshCoreError>>,
+{
+    /// Write `data` to the file `remote_path` of the server, replacing it. The file is sent
+    /// as the base64 chunks of the input of a helper script, `progress` is called with the
+    /// bytes sent so far and the total after each of them.
+    ///
+    /// The SHA-256 the script computes of what it wrote must be the one of `data`.
+    #[instrument(skip_all, name = "AsyncRunspacePool::upload", fields(id = %self.pool.id(), remote_path))]
+    pub async fn upload(
+        &mut self,
+        data: &[u8],
+        remote_path: &str,
+        mut progress: impl FnMut(u64, u64),
+    ) -> Result<(), crate::PwshCoreError> {
+        let power_shell = protocol_powershell_remoting::PowerShell::new()
+            .add_script(UPLOAD_SCRIPT)
+            .add_parameter("Path", remote_path)
+            .build()?;
+
+        let (pipeline_id, create) = self.pool.fire_invoke_pipeline(power_shell, false)?;
+        let response = (self.exchange)(create).await?;
+        self.pool.accept_response(response)?;
+
+        let total = data.len() as u64;
+        let mut sent = 0;
+        let mut chunks = data.chunks(CHUNK_SIZE).peekable();
+        if chunks.peek().is_none() {
+            self.send_input(pipeline_id, &[], true).await?;
+        }
+        while let Some(chunk) = chunks.next() {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(chunk);
+            let input = PipelineInput::new(encoded.to_ps_value());
+            self.send_input(pipeline_id, &[input], chunks.peek().is_none())
+                .await?;
+
+            sent += chunk.len() as u64;
+            progress(sent, total);
+        }
+
+        let output = self.wait(PowerShell::new(pipeline_id)).await?;
+        check_copied(&output, remote_path)?;
+
+        let remote_hash = output
+            .output
+            .last()
+            .and_then(PsValue::as_str)
+            .ok_or_else(|| {
+                crate::PwshCoreError::FileCopyError(format!(
+                    "The upload of {remote_path} wrote no SHA-256"
+                ))
+            })?;
+        verify_hash(remote_path, data, remote_hash)?;
+
+        info!(bytes = total, "Uploaded");
+        Ok(())
+    }
+
+    /// Read the file `remote_path` of the server. The script sends the base64 of each chunk
+    /// of the file as its output, `progress` is called with the bytes received so far and the
+    /// total after each of them.
+    ///
+    /// The SHA-256 the script computes of what it read must be the one of the bytes received.
+    #[instrument(skip_all, name = "AsyncRunspacePool::download", fields(id = %self.pool.id(), remote_path))]
+    pub async fn download(
+        &mut self,
+        remote_path: &str,
+        mut progress: impl FnMut(u64, u64),
+    ) -> Result<Vec<u8>, crate::PwshCoreError> {
+        let power_shell = protocol_powershell_remoting::PowerShell::new()
+            .add_script(DOWNLOAD_SCRIPT)
+            .add_parameter("Path", remote_path)
+            .add_parameter("ChunkSize", CHUNK_SIZE as i32)
+            .build()?;
+        let pipeline = self.start(power_shell).await?;
+
+        let invalid = |what: &str| {
+            crate::PwshCoreError::FileCopyError(format!(
+                "The download of {remote_path} sent {what}"
+            ))
+        };
+
+        let mut data = Vec::new();
+        let mut total = None;
+        let mut remote_hash = None;
+        loop {
+            // The output of the last receive is consumed before the pipeline can be released
+            let finished = self.is_finished(pipeline.id());
+            for value in self.pool.take_output(pipeline.id()) {
+                match (&value, total) {
+                    (PsValue::Primitive(PsPrimitiveValue::I64(length)), None) => {
+                        total = Some(u64::try_from(*length).map_err(|_| invalid("a length"))?);
+                    }
+                    (PsValue::Primitive(PsPrimitiveValue::Str(chunk)), Some(total)) => {
+                        let chunk = base64::engine::general_purpose::STANDARD
+                            .decode(chunk)
+                            .map_err(|_| invalid("a chunk which is not base64"))?;
+                        data.extend_from_slice(&chunk);
+                        progress(data.len() as u64, total);
+                    }
+                    (PsValue::Object(object), Some(_)) => {
+                        remote_hash = object
+                            .property("Sha256")
+                            .and_then(PsValue::as_str)
+                            .map(str::to_owned);
+                    }
+                    _ => return Err(invalid("an unexpected output")),
+                }
+            }
+
+            if finished {
+                break;
+            }
+            self.receive_pipeline(pipeline.id()).await?;
+        }
+
+        let output = self.finish(pipeline.id()).await?;
+        check_copied(&output, remote_path)?;
+
+        let remote_hash = remote_hash.ok_or_else(|| invalid("no SHA-256"))?;
+        verify_hash(remote_path, &data, &remote_hash)?;
+
+        info!(bytes = data.len(), "Downloaded");
+        Ok(data)
+    }
+}
+
+/// The helper script must have completed without writing an error
+fn check_copied(output: &PipelineOutput, remote_path: &str) -> Result<(), crate::PwshCoreError> {
+    if let Some(error) = output.failure.as_ref().or(output.errors.first()) {
+        return Err(crate::PwshCoreError::FileCopyError(format!(
+            "{remote_path}: {error}"
+        )));
+    }
+
+    if !output.success() {
+        return Err(crate::PwshCoreError::FileCopyError(format!(
+            "{remote_path}: the copy ended {:?}",
+            output.state
+        )));
+    }
+    Ok(())
+}
+
+fn verify_hash(
+    remote_path: &str,
+    data: &[u8],
+    remote_hash: &str,
+) -> Result<(), crate::PwshCoreError> {
+    let local_hash: String = openssl::sha::sha256(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    debug!(%local_hash, %remote_hash, "Comparing the SHA-256 of the copy");
+
+    if !local_hash.eq_ignore_ascii_case(remote_hash.trim()) {
+        return Err(crate::PwshCoreError::FileCopyError(format!(
+            "The SHA-256 of {remote_path} is {remote_hash}, expected {local_hash}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol_powershell_remoting::{MessageType, PipelineStateValue};
+
+    use super::*;
+    use crate::runspace_pool::test_server::{Message, Reply, Request, TestServer, error_record};
+
+    fn sha256(data: &[u8]) -> String {
+        openssl::sha::sha256(data)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    fn completed() -> Message {
+        Message::PipelineState(PipelineStateValue::Completed, None)
+    }
+
+    fn hash_object(hash: &str) -> PsValue {
+        let clixml = format!(r#"<Obj RefId="0"><MS><S N="Sha256">{hash}</S></MS></Obj>"#);
+        PsValue::from_clixml(&clixml)
+            .expect("the object is CLIXML")
+            .remove(0)
+    }
+
+    #[tokio::test]
+    async fn test_upload_chunks() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        let data: Vec<u8> = (0..2 * CHUNK_SIZE + 10).map(|i| i as u8).collect();
+        server.pipeline_receive(
+            0,
+            Reply::new([Message::Output(sha256(&data).to_ps_value()), completed()]),
+        );
+
+        let mut progress = Vec::new();
+        pool.upload(&data, r"C:\temp\file.bin", |sent, total| {
+            progress.push((sent, total))
+        })
+        .await
+        .unwrap();
+
+        let total = data.len() as u64;
+        let chunk = CHUNK_SIZE as u64;
+        assert_eq!(
+            progress,
+            [(chunk, total), (2 * chunk, total), (total, total)]
+        );
+
+        let sends: Vec<_> = server
+            .requests()
+            .into_iter()
+            .filter_map(|request| match request {
+                Request::Send(0, messages) => Some(messages),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            sends,
+            [
+                vec![MessageType::PipelineInput],
+                vec![MessageType::PipelineInput],
+                vec![MessageType::PipelineInput, MessageType::EndOfPipelineInput],
+            ]
+        );
+
+        let uploaded: Vec<u8> = server
+            .inputs(0)
+            .iter()
+            .flat_map(|input| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(input.as_str().expect("a chunk is a string"))
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(uploaded, data);
+    }
+
+    #[tokio::test]
+    async fn test_upload_hash_mismatch() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        server.pipeline_receive(
+            0,
+            Reply::new([Message::Output(sha256(b"other").to_ps_value()), completed()]),
+        );
+
+        let error = pool
+            .upload(b"data", "file.txt", |_, _| {})
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, crate::PwshCoreError::FileCopyError(message) if message.contains("SHA-256")),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_reassembles() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        let data: Vec<u8> = (0..CHUNK_SIZE + 10).map(|i| (i % 251) as u8).collect();
+        let chunk = |range: std::ops::Range<usize>| {
+            Message::Output(
+                base64::engine::general_purpose::STANDARD
+                    .encode(&data[range])
+                    .to_ps_value(),
+            )
+        };
+        server.pipeline_receive(
+            0,
+            Reply::new([
+                Message::Output(PsValue::Primitive(PsPrimitiveValue::I64(data.len() as i64))),
+                chunk(0..CHUNK_SIZE),
+            ]),
+        );
+        server.pipeline_receive(
+            0,
+            Reply::new([
+                chunk(CHUNK_SIZE..data.len()),
+                Message::Output(hash_object(&sha256(&data))),
+                completed(),
+            ]),
+        );
+
+        let mut progress = Vec::new();
+        let downloaded = pool
+            .download("file.bin", |received, total| {
+                progress.push((received, total))
+            })
+            .await
+            .unwrap();
+        assert_eq!(downloaded, data);
+
+        let total = data.len() as u64;
+        assert_eq!(progress, [(CHUNK_SIZE as u64, total), (total, total)]);
+    }
+
+    #[tokio::test]
+    async fn test_download_hash_mismatch() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        server.pipeline_receive(
+            0,
+            Reply::new([
+                Message::Output(PsValue::Primitive(PsPrimitiveValue::I64(4))),
+                Message::Output("ZGF0YQ==".to_ps_value()),
+                Message::Output(hash_object(&sha256(b"other"))),
+                completed(),
+            ]),
+        );
+
+        let error = pool.download("file.txt", |_, _| {}).await.unwrap_err();
+        assert!(
+            matches!(&error, crate::PwshCoreError::FileCopyError(message) if message.contains("SHA-256")),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_failed() {
+        let (pool, server) = TestServer::opening();
+        let mut pool = AsyncRunspacePool::open(pool, server.exchange())
+            .await
+            .unwrap();
+        server.pipeline_receive(
+            0,
+            Reply::new([Message::PipelineState(
+                PipelineStateValue::Failed,
+                Some(error_record("Could not find file 'missing.txt'.")),
+            )]),
+        );
+
+        let error = pool.download("missing.txt", |_, _| {}).await.unwrap_err();
+        assert!(
+            matches!(&error, crate::PwshCoreError::FileCopyError(message) if message.contains("Could not find file")),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_verify_hash() {
+        let hash = sha256(b"data");
+        assert!(verify_hash("file.txt", b"data", &hash).is_ok());
+        assert!(
+            verify_hash(
+                "file.txt",
+                b"data",
+                &format!(" {}\r\n", hash.to_uppercase())
+            )
+            .is_ok()
+        );
+        assert!(verify_hash("file.txt", b"other", &hash).is_err());
+    }
+}
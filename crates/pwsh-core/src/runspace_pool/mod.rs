@@ -1,12 +1,21 @@
+pub mod async_pool;
 pub mod creator;
 pub mod enums;
+pub mod expect_shell_connected;
 pub mod expect_shell_created;
+pub mod file_copy;
 pub mod pool;
+#[cfg(test)]
+mod test_server;
 pub mod types;
 
 // Re-export public types
+pub use async_pool::AsyncRunspacePool;
 pub use creator::RunspacePoolCreator;
-pub use enums::{PowerShellState, PsInvocationState, RunspacePoolState};
+pub use enums::{PowerShellState, PsInvocationState, PsStream, RunspacePoolState};
+pub use expect_shell_connected::ExpectShellConnected;
 pub use expect_shell_created::ExpectShellCreated;
 pub use pool::RunspacePool;
-pub use types::{PipelineRepresentation, PowerShell, Runspace};
\ No newline at end of file
+pub use types::{
+    PipelineOutput, PipelineRepresentation, PowerShell, Runspace, StateChange, StreamRecord,
+};
\ No newline at end of file
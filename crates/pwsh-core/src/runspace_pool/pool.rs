@@ -1,34 +1,41 @@
 use core::error;
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use tracing::error;
 
 use base64::Engine;
 use protocol_powershell_remoting::{
-    ApartmentState, ApplicationPrivateData, Command, Commands, CreatePipeline, Defragmenter,
-    HostInfo, InitRunspacePool, PSThreadOptions, PowerShellPipeline, PsValue,
-    RunspacePoolStateMessage, SessionCapability, fragment,
+    ApartmentState, ApplicationPrivateData, Availability, Commands, ConnectRunspacePool,
+    CreatePipeline, Defragmenter, EncryptedSessionKey, EndOfPipelineInput, FromPsValue,
+    GetAvailableRunspaces, HostInfo, InformationRecord, InformationalRecord, InitRunspacePool,
+    KeyExchange, PSThreadOptions, PipelineHostResponse, PipelineInput, PipelineStateMessage,
+    PowerShellPipeline, ProgressRecord, ProtocolVersion, PsErrorRecord, PsObjectWithType,
+    PsPrimitiveDictionary, PsValue, RunspaceAvailability, RunspacePoolHostCall,
+    RunspacePoolHostResponse, RunspacePoolInitData, RunspacePoolStateMessage, SessionCapability,
+    SessionKey, SetMaxRunspaces, SetMinRunspaces, UserEvent, fragment,
 };
 use protocol_winrm::{
-    soap::SoapEnvelope,
-    ws_management::{OptionSetValue, WsMan},
+    rsp::{
+        disconnect::{ConnectResponse, DisconnectResponse},
+        send::SendResponse,
+        shell::DisconnectedShell,
+        signal::{SignalCode, SignalResponse},
+    },
+    soap::{HeaderRegistry, SoapEnvelope, SoapFault},
+    ws_management::{DeleteResponse, OptionSetValue, WsMan, WsmanFaultKind},
 };
-use tracing::{debug, info, instrument, trace};
+use tracing::{debug, instrument, trace, warn};
 use xml::parser::XmlDeserialize;
 
-use crate::{PwshCoreError, runspace::win_rs::WinRunspace, runspace_pool::PsInvocationState};
+use crate::{
+    PwshCoreError, host::PendingHostCall, runspace::win_rs::WinRunspace,
+    runspace_pool::PsInvocationState,
+};
 
 use super::{
     enums::RunspacePoolState,
-    types::{PipelineRepresentation, PowerShell},
+    types::{OwnedStreamRecord, PipelineRepresentation, PowerShell, StateChange},
 };
 
-const PROTOCOL_VERSION: &str = "2.3";
-const PS_VERSION: &str = "2.0";
-const SERIALIZATION_VERSION: &str = "1.1.0.1";
-
 pub enum AcceptResponsResult {
     ReceiveResponse,
     NewPipeline(PowerShell),
@@ -43,17 +50,106 @@ pub struct RunspacePool {
     pub(super) thread_options: PSThreadOptions,
     pub(super) apartment_state: ApartmentState,
     pub(super) host_info: HostInfo,
-    pub(super) application_arguments: std::collections::BTreeMap<PsValue, PsValue>,
+    pub(super) application_arguments: PsPrimitiveDictionary,
     pub(super) shell: WinRunspace,
     pub(super) connection: Arc<WsMan>,
     pub(super) defragmenter: Defragmenter,
     pub(super) application_private_data: Option<ApplicationPrivateData>,
     pub(super) session_capability: Option<SessionCapability>,
-    pub(super) pipelines: HashSet<PipelineRepresentation>,
+    pub(super) protocol_version: ProtocolVersion,
+    /// The version of the `SESSION_CAPABILITY` of the server, or the one of the client if older
+    pub(super) negotiated_version: Option<ProtocolVersion>,
+    pub(super) pipelines: HashMap<uuid::Uuid, PipelineRepresentation>,
     pub(super) fragmenter: fragment::Fragmenter,
+    pub(super) header_registry: HeaderRegistry,
+    /// The error of the `RUNSPACEPOOL_STATE` that broke the pool
+    pub(super) broken_reason: Option<String>,
+    /// The host calls received and not answered yet
+    pub(super) host_calls: Vec<PendingHostCall>,
+    /// The `USER_EVENT`s received and not taken yet
+    pub(super) user_events: Vec<UserEvent>,
+    /// The states the server reported and not taken yet
+    pub(super) state_changes: Vec<StateChange>,
+    /// The key pair of the `PUBLIC_KEY` sent, until the `ENCRYPTED_SESSION_KEY` arrives
+    pub(super) key_exchange: Option<KeyExchange>,
+    pub(super) session_key: Option<SessionKey>,
+    /// The server sent a `PUBLIC_KEY_REQUEST`, it is about to send a `SecureString`
+    pub(super) public_key_requested: bool,
+    /// The call ID of the next `SET_MAX_RUNSPACES`, `SET_MIN_RUNSPACES` or
+    /// `GET_AVAILABLE_RUNSPACES`
+    pub(super) next_call_id: i64,
+    /// The calls waiting for their `RUNSPACE_AVAILABILITY`, by call ID
+    pub(super) pool_calls: HashMap<i64, PoolCall>,
+    /// The `RUNSPACE_AVAILABILITY`s received and not taken yet, by call ID
+    pub(super) availabilities: HashMap<i64, Availability>,
+}
+
+/// A call on the pool the server answers with a `RUNSPACE_AVAILABILITY`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PoolCall {
+    SetMax(usize),
+    SetMin(usize),
+    GetAvailable,
 }
 
 impl RunspacePool {
+    pub fn id(&self) -> uuid::Uuid {
+        self.id
+    }
+
+    pub fn state(&self) -> RunspacePoolState {
+        self.state
+    }
+
+    /// Why the server broke the pool, from the error record of its `RUNSPACEPOOL_STATE`
+    pub fn broken_reason(&self) -> Option<&str> {
+        self.broken_reason.as_deref()
+    }
+
+    /// The key of the `SecureString`s of the pool, once exchanged
+    pub fn session_key(&self) -> Option<&SessionKey> {
+        self.session_key.as_ref()
+    }
+
+    /// The `ApplicationArguments` the pool was opened with
+    pub fn application_arguments(&self) -> &PsPrimitiveDictionary {
+        &self.application_arguments
+    }
+
+    /// The protocol version negotiated with the server, once it sent its
+    /// `SESSION_CAPABILITY`
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        self.negotiated_version
+    }
+
+    /// Fail unless the negotiated version, or the one of the client before the negotiation,
+    /// has `feature`
+    fn require(
+        &self,
+        feature: &'static str,
+        supported: fn(&ProtocolVersion) -> bool,
+        needed: ProtocolVersion,
+    ) -> Result<(), crate::PwshCoreError> {
+        let negotiated = self.negotiated_version.unwrap_or(self.protocol_version);
+        if supported(&negotiated) {
+            Ok(())
+        } else {
+            Err(crate::PwshCoreError::UnsupportedByServer {
+                feature,
+                needed,
+                negotiated,
+            })
+        }
+    }
+
+    pub fn min_runspaces(&self) -> usize {
+        self.min_runspaces
+    }
+
+    pub fn max_runspaces(&self) -> usize {
+        self.max_runspaces
+    }
+
     #[instrument(skip(self), name = "RunspacePool::open")]
     pub fn open(
         mut self,
@@ -65,12 +161,13 @@ impl RunspacePool {
             ));
         }
 
-        let session_capability = SessionCapability {
-            protocol_version: PROTOCOL_VERSION.to_string(),
-            ps_version: PS_VERSION.to_string(),
-            serialization_version: SERIALIZATION_VERSION.to_string(),
-            time_zone: None,
-        };
+        if self.min_runspaces == 0 || self.min_runspaces > self.max_runspaces {
+            return Err(crate::PwshCoreError::InvalidState(
+                "RunspacePool needs at least 1 runspace, and no more than its maximum",
+            ));
+        }
+
+        let session_capability = SessionCapability::client_with(self.protocol_version);
 
         let init_runspace_pool = InitRunspacePool {
             min_runspaces: self.min_runspaces as i32,
@@ -107,7 +204,8 @@ impl RunspacePool {
             ))
             .map(|bytes| base64::engine::general_purpose::STANDARD.encode(&bytes[..]))?;
 
-        let option_set = OptionSetValue::new().add_option("protocolversion", PROTOCOL_VERSION);
+        let option_set =
+            OptionSetValue::new().add_option("protocolversion", self.protocol_version.to_string());
 
         let result = self
             .shell
@@ -121,6 +219,68 @@ impl RunspacePool {
         ))
     }
 
+    /// The `rsp:Connect` attaching to the disconnected pool of `shell`, from this client or
+    /// another one: its `connectXml` is the `SESSION_CAPABILITY` and `CONNECT_RUNSPACEPOOL`
+    /// of the pool. The commands of `shell` are the pipelines of the pool, still running.
+    #[instrument(skip(self), name = "RunspacePool::connect")]
+    pub fn connect(
+        mut self,
+        shell: DisconnectedShell,
+    ) -> Result<(String, super::expect_shell_connected::ExpectShellConnected), crate::PwshCoreError>
+    {
+        if self.state != RunspacePoolState::BeforeOpen {
+            return Err(crate::PwshCoreError::InvalidState(
+                "RunspacePool must be in BeforeOpen state to connect",
+            ));
+        }
+
+        // The ID of a pool is the one of its shell
+        self.id = uuid::Uuid::parse_str(&shell.shell_id).map_err(|_| {
+            crate::PwshCoreError::InvalidState("The ShellId of a RunspacePool is its ID")
+        })?;
+        self.require(
+            "Connecting to a pool",
+            ProtocolVersion::supports_disconnect,
+            ProtocolVersion::V2_2,
+        )?;
+        self.shell.attach(&shell);
+        for command_id in &shell.command_ids {
+            let mut pipeline = PipelineRepresentation::new(*command_id);
+            pipeline.state = PsInvocationState::Running;
+            self.pipelines.insert(*command_id, pipeline);
+        }
+
+        let session_capability = SessionCapability::client_with(self.protocol_version);
+        let connect_runspace_pool = ConnectRunspacePool::default();
+        let connect_xml = self
+            .fragmenter
+            .fragment_multiple(
+                &[&session_capability, &connect_runspace_pool],
+                self.id,
+                None,
+            )?
+            .into_iter()
+            .next()
+            .ok_or(crate::PwshCoreError::UnlikelyToHappen(
+                "No request group generated for the connection",
+            ))
+            .map(|bytes| base64::engine::general_purpose::STANDARD.encode(&bytes[..]))?;
+
+        self.state = RunspacePoolState::Connecting;
+        let request = self
+            .shell
+            .connect_request(&self.connection, None, Some(&connect_xml))?
+            .into()
+            .to_string();
+
+        Ok((
+            request,
+            super::expect_shell_connected::ExpectShellConnected {
+                runspace_pool: self,
+            },
+        ))
+    }
+
     // We should accept the pipeline id here, but for now let's ignore it
     pub(crate) fn fire_receive(&mut self) -> Result<String, crate::PwshCoreError> {
         Ok(self
@@ -140,8 +300,21 @@ impl RunspacePool {
         soap_envelope: String,
     ) -> Result<AcceptResponsResult, crate::PwshCoreError> {
         let parsed = xml::parser::parse(soap_envelope.as_str())?;
+        if let Some(fault) = SoapFault::find(parsed.root_element()) {
+            let fault = fault.map_err(crate::PwshCoreError::XmlParsingError)?;
+            // A Receive with no output within the OperationTimeout, the pool is still there
+            if fault.kind() == WsmanFaultKind::OperationTimeout {
+                debug!("Receive timed out, receiving again");
+                return Ok(AcceptResponsResult::ReceiveResponse);
+            }
+
+            return Err(fault.into_error(self.connection.locale()).into());
+        }
+
         let soap_envelope = SoapEnvelope::from_node(parsed.root_element())
             .map_err(crate::PwshCoreError::XmlParsingError)?;
+        self.check_session_id(&soap_envelope);
+        soap_envelope.check_must_understand(&self.header_registry)?;
 
         if soap_envelope.body.as_ref().receive_response.is_some() {
             let streams = self.shell.accept_receive_response(&soap_envelope)?;
@@ -152,12 +325,9 @@ impl RunspacePool {
         if soap_envelope.body.as_ref().command_response.is_some() {
             let pipeline_id = self.shell.accept_commannd_response(soap_envelope)?;
             self.pipelines
-                .remove(&PipelineRepresentation::new(pipeline_id));
-
-            self.pipelines.insert(PipelineRepresentation {
-                id: pipeline_id,
-                state: PsInvocationState::Running,
-            });
+                .entry(pipeline_id)
+                .or_insert_with(|| PipelineRepresentation::new(pipeline_id))
+                .state = PsInvocationState::Running;
 
             return Ok(AcceptResponsResult::NewPipeline(PowerShell {
                 id: pipeline_id,
@@ -178,20 +348,50 @@ impl RunspacePool {
         ))
     }
 
-    #[instrument(skip(self))]
-    pub(crate) fn fire_create_pipeline(&mut self) -> Result<String, crate::PwshCoreError> {
-        if self.state != RunspacePoolState::Opened {
+    /// The `wxf:Delete` of the shell closing the pool, the server stops the pipelines still
+    /// running in it
+    pub(crate) fn fire_close(&mut self) -> Result<String, crate::PwshCoreError> {
+        if matches!(
+            self.state,
+            RunspacePoolState::BeforeOpen | RunspacePoolState::Closing | RunspacePoolState::Closed
+        ) {
             return Err(crate::PwshCoreError::InvalidState(
-                "RunspacePool must be in Opened state to create a pipeline",
+                "RunspacePool must be opened or broken to close",
             ));
         }
 
-        let pipeline_id = uuid::Uuid::new_v4();
-        self.pipelines.insert(PipelineRepresentation {
-            id: pipeline_id,
-            state: PsInvocationState::NotStarted,
-        });
+        let request = self.shell.close(&self.connection)?.into().to_string();
+        self.state = RunspacePoolState::Closing;
+        Ok(request)
+    }
+
+    pub(crate) fn accept_close_response(
+        &mut self,
+        response: String,
+    ) -> Result<(), crate::PwshCoreError> {
+        let parsed = xml::parser::parse(response.as_str())?;
+        DeleteResponse::from_node(parsed.root_element(), self.connection.locale())?;
+        self.state = RunspacePoolState::Closed;
+        Ok(())
+    }
 
+    /// The server echoes our `p:SessionId`, a different one means the response belongs to another session
+    pub(super) fn check_session_id(&self, soap_envelope: &SoapEnvelope<'_>) {
+        match soap_envelope.session_id() {
+            Some(session_id) if session_id != self.connection.session_id() => {
+                warn!(
+                    expected = %self.connection.session_id(),
+                    received = %session_id,
+                    "Response carries a different SessionId"
+                );
+            }
+            Some(session_id) => trace!(%session_id, "Response SessionId matches"),
+            None => {}
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub(crate) fn fire_create_pipeline(&mut self) -> Result<String, crate::PwshCoreError> {
         // Create a command to execute instead of empty command list
         let cmd = protocol_powershell_remoting::Command::builder()
             .cmd(r#"Write-Host "Remote System: $($env:COMPUTERNAME) - $(Get-Date)""#)
@@ -204,10 +404,32 @@ impl RunspacePool {
             .cmds(Commands::new(cmd))
             .build();
 
-        debug!(?pipeline_message);
+        let (_, request) = self.fire_invoke_pipeline(pipeline_message, true)?;
+        Ok(request)
+    }
+
+    /// The `rsp:Command` creating a pipeline of `pipeline_message`, its `CREATE_PIPELINE`
+    /// fragments are the arguments of the command. A pipeline created with `no_input` false
+    /// waits for [`fire_send_input`](Self::fire_send_input) to end its input.
+    #[instrument(skip(self))]
+    pub(crate) fn fire_invoke_pipeline(
+        &mut self,
+        pipeline_message: PowerShellPipeline,
+        no_input: bool,
+    ) -> Result<(uuid::Uuid, String), crate::PwshCoreError> {
+        if self.state != RunspacePoolState::Opened {
+            return Err(crate::PwshCoreError::InvalidState(
+                "RunspacePool must be in Opened state to create a pipeline",
+            ));
+        }
+
+        let pipeline_id = uuid::Uuid::new_v4();
+        self.pipelines
+            .insert(pipeline_id, PipelineRepresentation::new(pipeline_id));
 
         let create_pipeline = CreatePipeline::builder()
             .power_shell(pipeline_message)
+            .no_input(no_input)
             .host_info(self.host_info.clone())
             .apartment_state(self.apartment_state)
             .build();
@@ -231,10 +453,360 @@ impl RunspacePool {
             None,
         )?;
 
-        Ok(request.into().to_string())
+        Ok((pipeline_id, request.into().to_string()))
+    }
+
+    /// The `rsp:Send` requests of the `PIPELINE_INPUT` of each of `inputs`, followed by the
+    /// `END_OF_PIPELINE_INPUT` when `end` is set
+    #[instrument(skip(self, inputs))]
+    pub(crate) fn fire_send_input(
+        &mut self,
+        pipeline_id: uuid::Uuid,
+        inputs: &[PipelineInput],
+        end: bool,
+    ) -> Result<Vec<String>, crate::PwshCoreError> {
+        if !self.pipelines.contains_key(&pipeline_id) {
+            return Err(crate::PwshCoreError::InvalidState(
+                "Input can only be sent to a pipeline of the pool",
+            ));
+        }
+
+        let mut messages: Vec<&dyn PsObjectWithType> = inputs
+            .iter()
+            .map(|input| input as &dyn PsObjectWithType)
+            .collect();
+        if end {
+            messages.push(&EndOfPipelineInput);
+        }
+
+        let requests = self
+            .fragmenter
+            .fragment_multiple(&messages, self.id, Some(pipeline_id))?;
+        trace!(count = requests.len(), "Sending pipeline input");
+
+        requests
+            .iter()
+            .map(|data| {
+                Ok(self
+                    .shell
+                    .send_request(&self.connection, Some(pipeline_id), data, false)?
+                    .into()
+                    .to_string())
+            })
+            .collect()
+    }
+
+    pub(crate) fn accept_send_response(
+        &self,
+        response: String,
+    ) -> Result<(), crate::PwshCoreError> {
+        let parsed = xml::parser::parse(response.as_str())?;
+        SendResponse::from_node(parsed.root_element(), self.connection.locale())?;
+        Ok(())
+    }
+
+    /// The `rsp:Receive` of the output of a pipeline
+    pub(crate) fn fire_receive_pipeline(
+        &mut self,
+        pipeline_id: uuid::Uuid,
+    ) -> Result<String, crate::PwshCoreError> {
+        let command_id = pipeline_id.to_string().to_uppercase();
+        Ok(self
+            .shell
+            .fire_receive(&self.connection, Some("stdout"), Some(&command_id))
+            .into()
+            .to_string())
+    }
+
+    /// The `rsp:Signal` releasing the command of a finished pipeline
+    pub(crate) fn fire_release_pipeline(
+        &self,
+        pipeline_id: uuid::Uuid,
+    ) -> Result<String, crate::PwshCoreError> {
+        Ok(self
+            .shell
+            .signal_request(&self.connection, pipeline_id, SignalCode::Terminate)?
+            .into()
+            .to_string())
+    }
+
+    /// The `rsp:Signal` stopping a running pipeline, what Ctrl+C does to a local one. The
+    /// server answers with a `PIPELINE_STATE` of `Stopped` on the output of the pipeline.
+    #[instrument(skip(self))]
+    pub(crate) fn fire_stop_pipeline(
+        &mut self,
+        pipeline_id: uuid::Uuid,
+    ) -> Result<String, crate::PwshCoreError> {
+        let pipeline =
+            self.pipelines
+                .get_mut(&pipeline_id)
+                .ok_or(crate::PwshCoreError::InvalidState(
+                    "Only a pipeline of the pool can be stopped",
+                ))?;
+        if pipeline.state.is_finished() {
+            return Err(crate::PwshCoreError::InvalidState(
+                "The pipeline is already finished",
+            ));
+        }
+        pipeline.state = PsInvocationState::Stopping;
+
+        Ok(self
+            .shell
+            .signal_request(&self.connection, pipeline_id, SignalCode::PsCrtlC)?
+            .into()
+            .to_string())
+    }
+
+    pub(crate) fn accept_signal_response(
+        &self,
+        response: String,
+    ) -> Result<(), crate::PwshCoreError> {
+        let parsed = xml::parser::parse(response.as_str())?;
+        SignalResponse::from_node(parsed.root_element(), self.connection.locale())?;
+        Ok(())
     }
 
-    fn parse_responses(&mut self, responses: Vec<Vec<u8>>) -> Result<(), crate::PwshCoreError> {
+    /// The `rsp:Disconnect` of the pool, the server keeps it and buffers the output of its
+    /// pipelines for `idle_time_out` or its default idle timeout
+    #[instrument(skip(self))]
+    pub(crate) fn fire_disconnect(
+        &mut self,
+        idle_time_out: Option<Duration>,
+    ) -> Result<String, crate::PwshCoreError> {
+        if self.state != RunspacePoolState::Opened {
+            return Err(crate::PwshCoreError::InvalidState(
+                "RunspacePool must be in Opened state to disconnect",
+            ));
+        }
+        self.require(
+            "Disconnecting from a pool",
+            ProtocolVersion::supports_disconnect,
+            ProtocolVersion::V2_2,
+        )?;
+
+        Ok(self
+            .shell
+            .disconnect_request(&self.connection, idle_time_out)?
+            .into()
+            .to_string())
+    }
+
+    /// What identifies the disconnected pool and its running pipelines, to connect to it again
+    /// with [`RunspacePool::connect`]
+    pub(crate) fn accept_disconnect_response(
+        &mut self,
+        response: String,
+    ) -> Result<DisconnectedShell, crate::PwshCoreError> {
+        let parsed = xml::parser::parse(response.as_str())?;
+        DisconnectResponse::from_node(parsed.root_element(), self.connection.locale())?;
+        self.state = RunspacePoolState::Disconnected;
+
+        let running = self
+            .pipelines
+            .values()
+            .filter(|pipeline| !pipeline.state.is_finished())
+            .map(PipelineRepresentation::id)
+            .collect();
+        self.shell.disconnected(running)
+    }
+
+    /// The `rsp:Connect` of a pipeline of a connected pool, its output is received again
+    /// from what the server buffered while disconnected
+    pub(crate) fn fire_connect_pipeline(
+        &self,
+        pipeline_id: uuid::Uuid,
+    ) -> Result<String, crate::PwshCoreError> {
+        Ok(self
+            .shell
+            .connect_request(&self.connection, Some(pipeline_id), None)?
+            .into()
+            .to_string())
+    }
+
+    pub(crate) fn accept_connect_pipeline_response(
+        &self,
+        response: String,
+    ) -> Result<(), crate::PwshCoreError> {
+        let parsed = xml::parser::parse(response.as_str())?;
+        ConnectResponse::from_node(parsed.root_element(), self.connection.locale())?;
+        Ok(())
+    }
+
+    /// The `rsp:Send` requests of the `PUBLIC_KEY` of a new key pair, the server answers with
+    /// an `ENCRYPTED_SESSION_KEY` on the stream of the pool
+    #[instrument(skip(self))]
+    pub(crate) fn fire_public_key(&mut self) -> Result<Vec<String>, crate::PwshCoreError> {
+        let key_exchange = KeyExchange::new()?;
+        let public_key = key_exchange.public_key()?;
+        self.key_exchange = Some(key_exchange);
+
+        let requests = self
+            .fragmenter
+            .fragment_multiple(&[&public_key], self.id, None)?;
+        requests
+            .iter()
+            .map(|data| {
+                Ok(self
+                    .shell
+                    .send_request(&self.connection, None, data, false)?
+                    .into()
+                    .to_string())
+            })
+            .collect()
+    }
+
+    /// The `rsp:Send` requests of `call` and its call ID, the one of the
+    /// `RUNSPACE_AVAILABILITY` the server answers with
+    #[instrument(skip(self))]
+    pub(crate) fn fire_pool_call(
+        &mut self,
+        call: PoolCall,
+    ) -> Result<(i64, Vec<String>), crate::PwshCoreError> {
+        if self.state != RunspacePoolState::Opened {
+            return Err(crate::PwshCoreError::InvalidState(
+                "The runspaces of a RunspacePool can only be changed once it is opened",
+            ));
+        }
+
+        let runspaces = |count: usize| {
+            i32::try_from(count).map_err(|_| {
+                crate::PwshCoreError::InvalidState("Too many runspaces for a RunspacePool")
+            })
+        };
+
+        let call_id = self.next_call_id;
+        let requests = match call {
+            PoolCall::SetMax(max_runspaces) => {
+                if max_runspaces < self.min_runspaces {
+                    return Err(crate::PwshCoreError::InvalidState(
+                        "The maximum runspaces cannot be below the minimum",
+                    ));
+                }
+                let message = SetMaxRunspaces {
+                    max_runspaces: runspaces(max_runspaces)?,
+                    call_id,
+                };
+                self.fragmenter
+                    .fragment_multiple(&[&message], self.id, None)?
+            }
+            PoolCall::SetMin(min_runspaces) => {
+                if min_runspaces == 0 || min_runspaces > self.max_runspaces {
+                    return Err(crate::PwshCoreError::InvalidState(
+                        "The minimum runspaces must be at least 1, and no more than the maximum",
+                    ));
+                }
+                let message = SetMinRunspaces {
+                    min_runspaces: runspaces(min_runspaces)?,
+                    call_id,
+                };
+                self.fragmenter
+                    .fragment_multiple(&[&message], self.id, None)?
+            }
+            PoolCall::GetAvailable => {
+                let message = GetAvailableRunspaces { call_id };
+                self.fragmenter
+                    .fragment_multiple(&[&message], self.id, None)?
+            }
+        };
+        self.next_call_id += 1;
+        self.pool_calls.insert(call_id, call);
+
+        let requests = requests
+            .iter()
+            .map(|data| {
+                Ok(self
+                    .shell
+                    .send_request(&self.connection, None, data, false)?
+                    .into()
+                    .to_string())
+            })
+            .collect::<Result<_, crate::PwshCoreError>>()?;
+        Ok((call_id, requests))
+    }
+
+    /// The `RUNSPACE_AVAILABILITY` of the call `call_id`, once received
+    pub(crate) fn take_availability(&mut self, call_id: i64) -> Option<Availability> {
+        self.availabilities.remove(&call_id)
+    }
+
+    /// Whether the server asked for the public key since the last time
+    pub(crate) fn take_public_key_request(&mut self) -> bool {
+        std::mem::take(&mut self.public_key_requested)
+    }
+
+    /// The host calls received since the last time, in order
+    pub(crate) fn take_host_calls(&mut self) -> Vec<PendingHostCall> {
+        std::mem::take(&mut self.host_calls)
+    }
+
+    /// The changes of state of the pool and its pipelines the server reported since the last
+    /// time, in order
+    pub fn take_state_changes(&mut self) -> Vec<StateChange> {
+        std::mem::take(&mut self.state_changes)
+    }
+
+    /// The events the server forwarded since the last time, in order
+    pub fn take_user_events(&mut self) -> Vec<UserEvent> {
+        std::mem::take(&mut self.user_events)
+    }
+
+    /// The `rsp:Send` requests of the response to a host call: a `PIPELINE_HOST_RESPONSE` to
+    /// the command of the pipeline that made it, else a `RUNSPACEPOOL_HOST_RESPONSE` to the
+    /// shell
+    #[instrument(skip(self, response))]
+    pub(crate) fn fire_host_response(
+        &mut self,
+        pipeline_id: Option<uuid::Uuid>,
+        response: RunspacePoolHostResponse,
+    ) -> Result<Vec<String>, crate::PwshCoreError> {
+        let requests = match pipeline_id {
+            Some(pipeline_id) => self.fragmenter.fragment_multiple(
+                &[&PipelineHostResponse(response)],
+                self.id,
+                Some(pipeline_id),
+            )?,
+            None => self
+                .fragmenter
+                .fragment_multiple(&[&response], self.id, None)?,
+        };
+
+        requests
+            .iter()
+            .map(|data| {
+                Ok(self
+                    .shell
+                    .send_request(&self.connection, pipeline_id, data, false)?
+                    .into()
+                    .to_string())
+            })
+            .collect()
+    }
+
+    pub fn pipeline(&self, pipeline_id: uuid::Uuid) -> Option<&PipelineRepresentation> {
+        self.pipelines.get(&pipeline_id)
+    }
+
+    /// The output the pipeline wrote since the last time, see
+    /// [`PipelineRepresentation::take_output`]
+    pub(crate) fn take_output(&mut self, pipeline_id: uuid::Uuid) -> Vec<PsValue> {
+        self.pipelines
+            .get_mut(&pipeline_id)
+            .map(PipelineRepresentation::take_output)
+            .unwrap_or_default()
+    }
+
+    /// Forget a finished pipeline, handing over what it produced
+    pub(crate) fn take_pipeline(
+        &mut self,
+        pipeline_id: uuid::Uuid,
+    ) -> Option<PipelineRepresentation> {
+        self.pipelines.remove(&pipeline_id)
+    }
+
+    pub(super) fn parse_responses(
+        &mut self,
+        responses: Vec<Vec<u8>>,
+    ) -> Result<(), crate::PwshCoreError> {
         for response in responses {
             let messages = match self.defragmenter.defragment(&response)? {
                 fragment::DefragmentResult::Incomplete => continue,
@@ -256,12 +828,78 @@ impl RunspacePool {
                     protocol_powershell_remoting::MessageType::RunspacepoolState => {
                         self.handle_runspacepool_state(ps_value)?;
                     }
+                    protocol_powershell_remoting::MessageType::PipelineOutput => {
+                        self.pipeline_of(message.pid)?
+                            .write(OwnedStreamRecord::Output(ps_value));
+                    }
+                    protocol_powershell_remoting::MessageType::ErrorRecord => {
+                        let error_record = PsErrorRecord::from_ps_value(&ps_value)?;
+                        debug!(%error_record, "The pipeline wrote an error");
+                        self.pipeline_of(message.pid)?
+                            .write(OwnedStreamRecord::Error(Box::new(error_record)));
+                    }
+                    protocol_powershell_remoting::MessageType::WarningRecord => {
+                        let record = InformationalRecord::from_ps_value(&ps_value)?;
+                        self.pipeline_of(message.pid)?
+                            .write(OwnedStreamRecord::Warning(record));
+                    }
+                    protocol_powershell_remoting::MessageType::VerboseRecord => {
+                        let record = InformationalRecord::from_ps_value(&ps_value)?;
+                        self.pipeline_of(message.pid)?
+                            .write(OwnedStreamRecord::Verbose(record));
+                    }
+                    protocol_powershell_remoting::MessageType::DebugRecord => {
+                        let record = InformationalRecord::from_ps_value(&ps_value)?;
+                        self.pipeline_of(message.pid)?
+                            .write(OwnedStreamRecord::Debug(record));
+                    }
+                    protocol_powershell_remoting::MessageType::InformationRecord => {
+                        let record = InformationRecord::from_ps_value(&ps_value)?;
+                        self.pipeline_of(message.pid)?
+                            .write(OwnedStreamRecord::Information(record));
+                    }
+                    protocol_powershell_remoting::MessageType::ProgressRecord => {
+                        let record = ProgressRecord::from_ps_value(&ps_value)?;
+                        self.pipeline_of(message.pid)?
+                            .write(OwnedStreamRecord::Progress(record));
+                    }
+                    protocol_powershell_remoting::MessageType::RunspacepoolInitData => {
+                        self.handle_runspacepool_init_data(ps_value)?;
+                    }
+                    protocol_powershell_remoting::MessageType::RunspaceAvailability => {
+                        self.handle_runspace_availability(ps_value)?;
+                    }
+                    protocol_powershell_remoting::MessageType::EncryptedSessionKey => {
+                        self.handle_encrypted_session_key(ps_value)?;
+                    }
+                    protocol_powershell_remoting::MessageType::PublicKeyRequest => {
+                        debug!("The server requested the public key");
+                        self.public_key_requested = true;
+                    }
+                    protocol_powershell_remoting::MessageType::RunspacepoolHostCall => {
+                        self.handle_host_call(None, ps_value)?;
+                    }
+                    protocol_powershell_remoting::MessageType::PipelineHostCall => {
+                        self.pipeline_of(message.pid)?;
+                        self.handle_host_call(message.pid, ps_value)?;
+                    }
+                    protocol_powershell_remoting::MessageType::UserEvent => {
+                        let user_event = UserEvent::from_ps_value(&ps_value)?;
+                        debug!(
+                            event_identifier = user_event.event_identifier,
+                            source_identifier = %user_event.source_identifier,
+                            "Received a user event"
+                        );
+                        self.user_events.push(user_event);
+                    }
+                    protocol_powershell_remoting::MessageType::PipelineState => {
+                        self.handle_pipeline_state(message.pid, ps_value)?;
+                    }
                     _ => {
-                        info!(
+                        warn!(
                             "Received message of type {:?}, but no handler implemented",
                             message.message_type
                         );
-                        todo!("Handle other message types as needed");
                     }
                 }
             }
@@ -283,6 +921,11 @@ impl RunspacePool {
 
         let session_capability = SessionCapability::try_from(session_capability)?;
         debug!(?session_capability, "Received SessionCapability");
+        session_capability.check_server()?;
+
+        let negotiated_version = session_capability.negotiate(self.protocol_version)?;
+        debug!(%negotiated_version, "Negotiated the protocol version");
+        self.negotiated_version = Some(negotiated_version);
         self.session_capability = Some(session_capability);
         Ok(())
     }
@@ -304,6 +947,148 @@ impl RunspacePool {
         Ok(())
     }
 
+    fn handle_runspacepool_init_data(
+        &mut self,
+        ps_value: PsValue,
+    ) -> Result<(), crate::PwshCoreError> {
+        let PsValue::Object(init_data) = ps_value else {
+            return Err(PwshCoreError::InvalidResponse(
+                "Expected RunspacePoolInitData as PsValue::Object".into(),
+            ));
+        };
+
+        let init_data = RunspacePoolInitData::try_from(init_data)?;
+        debug!(?init_data, "Received RunspacePoolInitData");
+        let runspaces = |count: i32| {
+            usize::try_from(count).map_err(|_| {
+                PwshCoreError::InvalidResponse("The runspaces of a pool cannot be negative".into())
+            })
+        };
+        self.min_runspaces = runspaces(init_data.min_runspaces)?;
+        self.max_runspaces = runspaces(init_data.max_runspaces)?;
+        Ok(())
+    }
+
+    fn handle_runspace_availability(
+        &mut self,
+        ps_value: PsValue,
+    ) -> Result<(), crate::PwshCoreError> {
+        let PsValue::Object(availability) = ps_value else {
+            return Err(PwshCoreError::InvalidResponse(
+                "Expected RunspaceAvailability as PsValue::Object".into(),
+            ));
+        };
+
+        let RunspaceAvailability {
+            call_id,
+            availability,
+        } = RunspaceAvailability::try_from(availability)?;
+        debug!(call_id, ?availability, "Received RunspaceAvailability");
+
+        match (self.pool_calls.remove(&call_id), availability) {
+            (Some(PoolCall::SetMax(max_runspaces)), Availability::Changed(true)) => {
+                self.max_runspaces = max_runspaces;
+            }
+            (Some(PoolCall::SetMin(min_runspaces)), Availability::Changed(true)) => {
+                self.min_runspaces = min_runspaces;
+            }
+            (Some(_), _) => {}
+            (None, _) => {
+                warn!(call_id, "RunspaceAvailability of an unknown call");
+                return Ok(());
+            }
+        }
+
+        self.availabilities.insert(call_id, availability);
+        Ok(())
+    }
+
+    fn handle_encrypted_session_key(
+        &mut self,
+        ps_value: PsValue,
+    ) -> Result<(), crate::PwshCoreError> {
+        let PsValue::Object(encrypted) = ps_value else {
+            return Err(PwshCoreError::InvalidResponse(
+                "Expected EncryptedSessionKey as PsValue::Object".into(),
+            ));
+        };
+
+        let encrypted = EncryptedSessionKey::try_from(encrypted)?;
+        let key_exchange = self.key_exchange.take().ok_or(PwshCoreError::InvalidState(
+            "Received a session key without sending a public key",
+        ))?;
+        self.session_key = Some(key_exchange.session_key(&encrypted)?);
+        debug!("Received the session key");
+        Ok(())
+    }
+
+    fn handle_host_call(
+        &mut self,
+        pipeline_id: Option<uuid::Uuid>,
+        ps_value: PsValue,
+    ) -> Result<(), crate::PwshCoreError> {
+        let PsValue::Object(call) = ps_value else {
+            return Err(PwshCoreError::InvalidResponse(
+                "Expected a host call as PsValue::Object".into(),
+            ));
+        };
+
+        let call = RunspacePoolHostCall::try_from(call)?;
+        debug!(call_id = call.call_id, method = %call.method_name, "Received a host call");
+        self.host_calls.push(PendingHostCall { pipeline_id, call });
+        Ok(())
+    }
+
+    fn pipeline_of(
+        &mut self,
+        pid: Option<uuid::Uuid>,
+    ) -> Result<&mut PipelineRepresentation, crate::PwshCoreError> {
+        pid.and_then(|pid| self.pipelines.get_mut(&pid))
+            .ok_or_else(|| {
+                PwshCoreError::InvalidResponse(
+                    format!("Pipeline message for unknown pipeline {pid:?}").into(),
+                )
+            })
+    }
+
+    #[instrument(skip(self, ps_value))]
+    fn handle_pipeline_state(
+        &mut self,
+        pid: Option<uuid::Uuid>,
+        ps_value: PsValue,
+    ) -> Result<(), crate::PwshCoreError> {
+        let PsValue::Object(pipeline_state) = ps_value else {
+            return Err(PwshCoreError::InvalidResponse(
+                "Expected PipelineState as PsValue::Object".into(),
+            ));
+        };
+
+        let pipeline_state = PipelineStateMessage::try_from(pipeline_state)?;
+        trace!(?pipeline_state, "Received PipelineState");
+
+        let failure = pipeline_state
+            .exception_as_error_record
+            .as_ref()
+            .map(PsErrorRecord::from_ps_value)
+            .transpose()?;
+
+        let pipeline = self.pipeline_of(pid)?;
+        let previous = pipeline.state;
+        pipeline.state = PsInvocationState::from(&pipeline_state.pipeline_state);
+        pipeline.failure = failure;
+
+        if pipeline.state != previous {
+            let change = StateChange::Pipeline {
+                id: pipeline.id,
+                previous,
+                state: pipeline.state,
+                failure: pipeline.failure.clone(),
+            };
+            self.state_changes.push(change);
+        }
+        Ok(())
+    }
+
     #[instrument(skip(self, ps_value))]
     fn handle_runspacepool_state(&mut self, ps_value: PsValue) -> Result<(), crate::PwshCoreError> {
         let PsValue::Object(runspacepool_state) = ps_value else {
@@ -315,8 +1100,28 @@ impl RunspacePool {
         let runspacepool_state = RunspacePoolStateMessage::try_from(runspacepool_state)?;
         trace!(?runspacepool_state, "Received RunspacePoolState");
 
+        let previous = self.state;
         self.state = RunspacePoolState::from(&runspacepool_state.runspace_state);
+        let reason = runspacepool_state
+            .exception_as_error_record
+            .as_ref()
+            .and_then(|error_record| PsErrorRecord::from_ps_value(error_record).ok());
+        if self.state == RunspacePoolState::Broken {
+            self.broken_reason = Some(
+                reason
+                    .as_ref()
+                    .map(|error_record| error_record.message.clone())
+                    .unwrap_or_else(|| "the server gave no error record".to_owned()),
+            );
+        }
 
+        if self.state != previous {
+            self.state_changes.push(StateChange::RunspacePool {
+                previous,
+                state: self.state,
+                reason,
+            });
+        }
         Ok(())
     }
 }
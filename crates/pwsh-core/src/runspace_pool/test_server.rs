@@ -0,0 +1,443 @@
+//! A server answering the envelopes of a pool in the tests: the shell requests are answered
+//! as WinRM does, the receives with the PSRP messages the test scripted for them.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use base64::Engine;
+use protocol_powershell_remoting::{
+    ComplexObject, DefragmentResult, Defragmenter, Fragmenter, HostInfo, MessageType,
+    PipelineStateMessage, PipelineStateValue, PowerShellRemotingMessage, PsObjectWithType,
+    PsPrimitiveValue, PsProperty, PsType, PsValue, RunspacePoolStateMessage,
+    RunspacePoolStateValue,
+};
+use protocol_winrm::ws_management::WsMan;
+use tokio::sync::Semaphore;
+
+use super::{RunspacePool, RunspacePoolCreator};
+use crate::PwshCoreError;
+
+const SHELL_ID: &str = "0E2B4C8D-61D5-4B0E-9E9B-3C8F9F4C2A11";
+const RESOURCE_URI: &str = "http://schemas.microsoft.com/powershell/Microsoft.PowerShell";
+
+/// What the client sent, in the order the server received it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Request {
+    Create,
+    /// A receive of the output of the pipeline at this index of creation, of the pool without
+    Receive(Option<usize>),
+    Command(usize),
+    /// The PSRP messages of a `rsp:Send` to a pipeline
+    Send(usize, Vec<MessageType>),
+    /// A `rsp:Signal` to a pipeline, with the end of its code
+    Signal(usize, &'static str),
+    Delete,
+}
+
+/// A PSRP message the server sends
+pub(crate) enum Message {
+    PoolState(RunspacePoolStateValue, Option<PsValue>),
+    PipelineState(PipelineStateValue, Option<PsValue>),
+    Output(PsValue),
+}
+
+impl Message {
+    fn to_ps_message(&self) -> Box<dyn PsObjectWithType> {
+        match self {
+            Message::PoolState(state, error) => Box::new(
+                RunspacePoolStateMessage::builder()
+                    .runspace_state(state.clone())
+                    .exception_as_error_record(error.clone())
+                    .build(),
+            ),
+            Message::PipelineState(state, error) => Box::new(
+                PipelineStateMessage::builder()
+                    .pipeline_state(*state)
+                    .exception_as_error_record(error.clone())
+                    .build(),
+            ),
+            Message::Output(value) => Box::new(Output(value.clone())),
+        }
+    }
+}
+
+struct Output(PsValue);
+
+impl PsObjectWithType for Output {
+    fn message_type(&self) -> MessageType {
+        MessageType::PipelineOutput
+    }
+
+    fn to_ps_object(&self) -> PsValue {
+        self.0.clone()
+    }
+}
+
+/// Holds the replies scripted after it until a reply opening it is sent
+#[derive(Clone)]
+pub(crate) struct Gate(Arc<Semaphore>);
+
+impl Gate {
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(Semaphore::new(0)))
+    }
+}
+
+/// The messages answering one receive
+pub(crate) struct Reply {
+    messages: Vec<Message>,
+    after: Option<Gate>,
+    opens: Option<Gate>,
+}
+
+impl Reply {
+    pub(crate) fn new(messages: impl IntoIterator<Item = Message>) -> Self {
+        Self {
+            messages: messages.into_iter().collect(),
+            after: None,
+            opens: None,
+        }
+    }
+
+    /// Answer once `gate` is opened
+    pub(crate) fn after(mut self, gate: &Gate) -> Self {
+        self.after = Some(gate.clone());
+        self
+    }
+
+    /// Open `gate` once answered
+    pub(crate) fn opening(mut self, gate: &Gate) -> Self {
+        self.opens = Some(gate.clone());
+        self
+    }
+}
+
+struct State {
+    pool_id: uuid::Uuid,
+    fragmenter: Fragmenter,
+    defragmenter: Defragmenter,
+    requests: Vec<Request>,
+    pool_replies: VecDeque<Reply>,
+    /// The replies to the receives of each pipeline, by index of creation
+    pipeline_replies: HashMap<usize, VecDeque<Reply>>,
+    /// The `PIPELINE_INPUT`s sent to each pipeline, by index of creation
+    inputs: HashMap<usize, Vec<PsValue>>,
+    pipelines: Vec<uuid::Uuid>,
+}
+
+#[derive(Clone)]
+pub(crate) struct TestServer {
+    state: Arc<Mutex<State>>,
+}
+
+impl TestServer {
+    /// A pool to open against the server, with the server
+    pub(crate) fn new() -> (RunspacePool, Self) {
+        let ws_man = WsMan::builder()
+            .to("http://localhost:5985/wsman".into())
+            .build();
+        let pool = RunspacePoolCreator::builder()
+            .host_info(HostInfo::builder().build())
+            .build()
+            .into_runspace_pool(Arc::new(ws_man));
+
+        let server = Self {
+            state: Arc::new(Mutex::new(State {
+                pool_id: pool.id(),
+                fragmenter: Fragmenter::new(32 * 1024),
+                defragmenter: Defragmenter::new(),
+                requests: Vec::new(),
+                pool_replies: VecDeque::new(),
+                pipeline_replies: HashMap::new(),
+                inputs: HashMap::new(),
+                pipelines: Vec::new(),
+            })),
+        };
+        (pool, server)
+    }
+
+    /// A pool the server opens on its first receive
+    pub(crate) fn opening() -> (RunspacePool, Self) {
+        let (pool, server) = Self::new();
+        server.pool_receive(Reply::new([Message::PoolState(
+            RunspacePoolStateValue::Opened,
+            None,
+        )]));
+        (pool, server)
+    }
+
+    /// Answer the next receive of the pool with `reply`
+    pub(crate) fn pool_receive(&self, reply: Reply) {
+        self.state().pool_replies.push_back(reply);
+    }
+
+    /// Answer the next receive of the pipeline created at `index` with `reply`
+    pub(crate) fn pipeline_receive(&self, index: usize, reply: Reply) {
+        self.state()
+            .pipeline_replies
+            .entry(index)
+            .or_default()
+            .push_back(reply);
+    }
+
+    pub(crate) fn requests(&self) -> Vec<Request> {
+        self.state().requests.clone()
+    }
+
+    /// The input sent to the pipeline created at `index`, in order
+    pub(crate) fn inputs(&self, index: usize) -> Vec<PsValue> {
+        self.state().inputs.get(&index).cloned().unwrap_or_default()
+    }
+
+    /// The exchange of a pool with the server
+    pub(crate) fn exchange(
+        &self,
+    ) -> impl FnMut(String) -> Pin<Box<dyn Future<Output = Result<String, PwshCoreError>> + Send>>
+    + Send
+    + 'static {
+        let server = self.clone();
+        move |request| {
+            let server = server.clone();
+            Box::pin(async move { server.answer(&request).await })
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("the test server is not poisoned")
+    }
+
+    async fn answer(&self, request: &str) -> Result<String, PwshCoreError> {
+        let (action, body, reply) = {
+            let mut state = self.state();
+            let request = state.request(request)?;
+            state.requests.push(request.clone());
+            match request {
+                Request::Create => (
+                    "transfer/CreateResponse",
+                    format!(
+                        r#"<x:ResourceCreated xmlns:x="http://schemas.xmlsoap.org/ws/2004/09/transfer" xmlns:w="http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd">
+                        <a:Address>http://localhost:5985/wsman</a:Address>
+                        <a:ReferenceParameters>
+                        <w:ResourceURI>{RESOURCE_URI}</w:ResourceURI>
+                        <w:SelectorSet><w:Selector Name="ShellId">{SHELL_ID}</w:Selector></w:SelectorSet>
+                        </a:ReferenceParameters>
+                        </x:ResourceCreated>
+                        <rsp:Shell><rsp:ShellId>{SHELL_ID}</rsp:ShellId><rsp:ResourceUri>{RESOURCE_URI}</rsp:ResourceUri></rsp:Shell>"#
+                    ),
+                    None,
+                ),
+                Request::Command(index) => (
+                    "",
+                    format!(
+                        "<rsp:CommandResponse><rsp:CommandId>{}</rsp:CommandId></rsp:CommandResponse>",
+                        state.pipelines[index].to_string().to_uppercase()
+                    ),
+                    None,
+                ),
+                Request::Send(..) => ("", "<rsp:SendResponse/>".to_owned(), None),
+                Request::Signal(..) => ("", "<rsp:SignalResponse/>".to_owned(), None),
+                Request::Delete => ("transfer/DeleteResponse", String::new(), None),
+                Request::Receive(index) => {
+                    let replies = match index {
+                        Some(index) => state.pipeline_replies.entry(index).or_default(),
+                        None => &mut state.pool_replies,
+                    };
+                    let reply = replies.pop_front().ok_or_else(|| {
+                        PwshCoreError::InvalidResponse(
+                            format!("no reply scripted for a receive of {index:?}").into(),
+                        )
+                    })?;
+                    let body = state.receive_response(index, &reply.messages)?;
+                    ("", body, Some(reply))
+                }
+            }
+        };
+
+        if let Some(gate) = reply.as_ref().and_then(|reply| reply.after.as_ref()) {
+            gate.0
+                .acquire()
+                .await
+                .expect("gates are not closed")
+                .forget();
+        }
+        if let Some(gate) = reply.as_ref().and_then(|reply| reply.opens.as_ref()) {
+            gate.0.add_permits(Semaphore::MAX_PERMITS);
+        }
+        Ok(envelope(action, &body))
+    }
+}
+
+impl State {
+    fn request(&mut self, request: &str) -> Result<Request, PwshCoreError> {
+        let document = xml::parser::parse(request)?;
+        let element = |name: &str| {
+            document
+                .descendants()
+                .find(|node| node.is_element() && node.tag_name().name() == name)
+        };
+        let action = element("Action")
+            .and_then(|action| action.text())
+            .unwrap_or_default();
+        let command_id = |name: &str| {
+            element(name)
+                .and_then(|node| node.attribute("CommandId"))
+                .map(|id| id.parse::<uuid::Uuid>().expect("a CommandId is a UUID"))
+        };
+
+        let action = action.rsplit('/').next().unwrap_or_default();
+        Ok(match action {
+            "Create" => Request::Create,
+            "Delete" => Request::Delete,
+            "Command" => {
+                let pipeline_id = command_id("CommandLine").expect("a pipeline has a CommandId");
+                self.pipelines.push(pipeline_id);
+                Request::Command(self.pipelines.len() - 1)
+            }
+            "Receive" => Request::Receive(
+                command_id("DesiredStream").map(|pipeline_id| self.pipeline(pipeline_id)),
+            ),
+            "Send" => {
+                let index = self.pipeline(command_id("Stream").expect("input goes to a command"));
+                let data = base64::engine::general_purpose::STANDARD
+                    .decode(
+                        element("Stream")
+                            .and_then(|node| node.text())
+                            .unwrap_or_default(),
+                    )
+                    .expect("the input is base64");
+                let messages = match self.defragmenter.defragment(&data)? {
+                    DefragmentResult::Complete(messages) => messages,
+                    DefragmentResult::Incomplete => Vec::new(),
+                };
+                for message in &messages {
+                    if message.message_type == MessageType::PipelineInput {
+                        let input = message.parse_ps_message()?;
+                        self.inputs.entry(index).or_default().push(input);
+                    }
+                }
+                Request::Send(
+                    index,
+                    messages
+                        .iter()
+                        .map(|message: &PowerShellRemotingMessage| message.message_type)
+                        .collect(),
+                )
+            }
+            "Signal" => {
+                let index = self.pipeline(command_id("Signal").expect("a signal has a CommandId"));
+                let code = element("Code")
+                    .and_then(|node| node.text())
+                    .unwrap_or_default();
+                let code = ["crtl_c", "terminate"]
+                    .into_iter()
+                    .find(|known| code.ends_with(known))
+                    .expect("a known signal");
+                Request::Signal(index, code)
+            }
+            action => panic!("the test server does not answer {action}"),
+        })
+    }
+
+    fn pipeline(&self, pipeline_id: uuid::Uuid) -> usize {
+        self.pipelines
+            .iter()
+            .position(|id| *id == pipeline_id)
+            .expect("a pipeline of the pool")
+    }
+
+    fn receive_response(
+        &mut self,
+        index: Option<usize>,
+        messages: &[Message],
+    ) -> Result<String, PwshCoreError> {
+        let pipeline_id = index.map(|index| self.pipelines[index]);
+        let mut data = Vec::new();
+        for message in messages {
+            let message = message.to_ps_message();
+            for fragment in
+                self.fragmenter
+                    .fragment(message.as_ref(), self.pool_id, pipeline_id, None)?
+            {
+                data.extend(fragment);
+            }
+        }
+
+        let command_id = pipeline_id.map_or_else(String::new, |pipeline_id| {
+            format!(r#" CommandId="{}""#, pipeline_id.to_string().to_uppercase())
+        });
+        Ok(format!(
+            r#"<rsp:ReceiveResponse><rsp:Stream Name="stdout"{command_id}>{}</rsp:Stream></rsp:ReceiveResponse>"#,
+            base64::engine::general_purpose::STANDARD.encode(data)
+        ))
+    }
+}
+
+fn envelope(action: &str, body: &str) -> String {
+    let action = if action.is_empty() {
+        String::new()
+    } else {
+        format!("<a:Action>http://schemas.xmlsoap.org/ws/2004/09/{action}</a:Action>")
+    };
+    format!(
+        r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:rsp="http://schemas.microsoft.com/wbem/wsman/1/windows/shell"><s:Header>{action}</s:Header><s:Body>{body}</s:Body></s:Envelope>"#
+    )
+}
+
+/// The `ERROR_RECORD` of a `throw`
+pub(crate) fn error_record(message: &str) -> PsValue {
+    let exception = ComplexObject {
+        type_def: Some(PsType {
+            type_names: vec![
+                Cow::Borrowed("System.Management.Automation.RuntimeException"),
+                Cow::Borrowed("System.Exception"),
+                Cow::Borrowed("System.Object"),
+            ],
+        }),
+        adapted_properties: properties([(
+            "Message",
+            PsValue::Primitive(PsPrimitiveValue::Str(message.to_owned())),
+        )]),
+        ..ComplexObject::default()
+    };
+    PsValue::Object(ComplexObject {
+        type_def: Some(PsType {
+            type_names: vec![
+                Cow::Borrowed("System.Management.Automation.ErrorRecord"),
+                Cow::Borrowed("System.Object"),
+            ],
+        }),
+        to_string: Some(message.to_owned()),
+        extended_properties: properties([
+            ("Exception", PsValue::Object(exception)),
+            ("TargetObject", PsValue::Primitive(PsPrimitiveValue::Nil)),
+            (
+                "FullyQualifiedErrorId",
+                PsValue::Primitive(PsPrimitiveValue::Str(message.to_owned())),
+            ),
+            (
+                "ErrorCategory_Category",
+                PsValue::Primitive(PsPrimitiveValue::I32(14)),
+            ),
+            (
+                "SerializeExtendedInfo",
+                PsValue::Primitive(PsPrimitiveValue::Bool(false)),
+            ),
+        ]),
+        ..ComplexObject::default()
+    })
+}
+
+fn properties<const N: usize>(properties: [(&str, PsValue); N]) -> BTreeMap<String, PsProperty> {
+    properties
+        .into_iter()
+        .map(|(name, value)| {
+            let name = name.to_owned();
+            (name.clone(), PsProperty { name, value })
+        })
+        .collect()
+}
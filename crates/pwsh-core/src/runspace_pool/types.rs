@@ -1,11 +1,29 @@
 use std::hash::Hash;
 
-use crate::runspace_pool::PsInvocationState;
+use protocol_powershell_remoting::{
+    FromPsValue, InformationRecord, InformationalRecord, PowerShellRemotingError, ProgressRecord,
+    PsErrorRecord, PsValue,
+};
+
+use crate::runspace_pool::{PsInvocationState, PsStream};
 
 #[derive(Debug, Clone)]
 pub struct PipelineRepresentation {
     pub id: uuid::Uuid,
     pub state: PsInvocationState,
+    /// The objects of its `PIPELINE_OUTPUT` messages
+    pub output: Vec<PsValue>,
+    /// The records of its `ERROR_RECORD` messages
+    pub errors: Vec<PsErrorRecord>,
+    pub warning: Vec<InformationalRecord>,
+    pub verbose: Vec<InformationalRecord>,
+    pub debug: Vec<InformationalRecord>,
+    pub information: Vec<InformationRecord>,
+    pub progress: Vec<ProgressRecord>,
+    /// The stream of every record above, in the order they were received
+    pub streams: Vec<PsStream>,
+    /// Why it failed or was stopped, the error record of its `PIPELINE_STATE`
+    pub failure: Option<PsErrorRecord>,
 }
 
 impl PipelineRepresentation {
@@ -13,12 +31,73 @@ impl PipelineRepresentation {
         PipelineRepresentation {
             id,
             state: PsInvocationState::NotStarted,
+            output: Vec::new(),
+            errors: Vec::new(),
+            warning: Vec::new(),
+            verbose: Vec::new(),
+            debug: Vec::new(),
+            information: Vec::new(),
+            progress: Vec::new(),
+            streams: Vec::new(),
+            failure: None,
         }
     }
 
     pub fn id(&self) -> uuid::Uuid {
         self.id
     }
+
+    pub(crate) fn write(&mut self, record: OwnedStreamRecord) {
+        let stream = match record {
+            OwnedStreamRecord::Output(value) => {
+                self.output.push(value);
+                PsStream::Output
+            }
+            OwnedStreamRecord::Error(record) => {
+                self.errors.push(*record);
+                PsStream::Error
+            }
+            OwnedStreamRecord::Warning(record) => {
+                self.warning.push(record);
+                PsStream::Warning
+            }
+            OwnedStreamRecord::Verbose(record) => {
+                self.verbose.push(record);
+                PsStream::Verbose
+            }
+            OwnedStreamRecord::Debug(record) => {
+                self.debug.push(record);
+                PsStream::Debug
+            }
+            OwnedStreamRecord::Information(record) => {
+                self.information.push(record);
+                PsStream::Information
+            }
+            OwnedStreamRecord::Progress(record) => {
+                self.progress.push(record);
+                PsStream::Progress
+            }
+        };
+        self.streams.push(stream);
+    }
+
+    /// The output written since the last time, for a caller that consumes it as it arrives
+    pub(crate) fn take_output(&mut self) -> Vec<PsValue> {
+        self.streams.retain(|stream| *stream != PsStream::Output);
+        std::mem::take(&mut self.output)
+    }
+}
+
+/// A record the server sent for a pipeline, before it is stored in its stream
+#[derive(Debug)]
+pub(crate) enum OwnedStreamRecord {
+    Output(PsValue),
+    Error(Box<PsErrorRecord>),
+    Warning(InformationalRecord),
+    Verbose(InformationalRecord),
+    Debug(InformationalRecord),
+    Information(InformationRecord),
+    Progress(ProgressRecord),
 }
 
 impl Hash for PipelineRepresentation {
@@ -35,6 +114,100 @@ impl PartialEq for PipelineRepresentation {
 
 impl Eq for PipelineRepresentation {}
 
+/// What a pipeline produced once it finished
+#[derive(Debug, Clone)]
+pub struct PipelineOutput {
+    pub state: PsInvocationState,
+    pub output: Vec<PsValue>,
+    /// The non-terminating errors the pipeline wrote
+    pub errors: Vec<PsErrorRecord>,
+    pub warning: Vec<InformationalRecord>,
+    pub verbose: Vec<InformationalRecord>,
+    pub debug: Vec<InformationalRecord>,
+    /// What `Write-Information` and `Write-Host` wrote
+    pub information: Vec<InformationRecord>,
+    pub progress: Vec<ProgressRecord>,
+    /// The stream of every record, in the order the pipeline wrote them
+    pub streams: Vec<PsStream>,
+    /// Why the pipeline failed or was stopped
+    pub failure: Option<PsErrorRecord>,
+}
+
+/// A record of any stream of a pipeline, see [`PipelineOutput::records`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamRecord<'a> {
+    Output(&'a PsValue),
+    Error(&'a PsErrorRecord),
+    Warning(&'a InformationalRecord),
+    Verbose(&'a InformationalRecord),
+    Debug(&'a InformationalRecord),
+    Information(&'a InformationRecord),
+    Progress(&'a ProgressRecord),
+}
+
+impl StreamRecord<'_> {
+    pub fn stream(&self) -> PsStream {
+        match self {
+            StreamRecord::Output(_) => PsStream::Output,
+            StreamRecord::Error(_) => PsStream::Error,
+            StreamRecord::Warning(_) => PsStream::Warning,
+            StreamRecord::Verbose(_) => PsStream::Verbose,
+            StreamRecord::Debug(_) => PsStream::Debug,
+            StreamRecord::Information(_) => PsStream::Information,
+            StreamRecord::Progress(_) => PsStream::Progress,
+        }
+    }
+}
+
+impl PipelineOutput {
+    /// Whether the pipeline completed, it may still have written non-terminating errors
+    pub fn success(&self) -> bool {
+        self.state == PsInvocationState::Completed
+    }
+
+    /// The output converted into `T`, e.g. the structs of `Get-Service | Select Name,Status`
+    /// with `#[derive(FromPsObject)]`
+    pub fn output_as<T: FromPsValue>(&self) -> Result<Vec<T>, PowerShellRemotingError> {
+        self.output.iter().map(T::from_ps_value).collect()
+    }
+
+    /// The records of every stream merged in the order the pipeline wrote them, what the
+    /// console of `Invoke-Command` shows
+    pub fn records(&self) -> impl Iterator<Item = StreamRecord<'_>> {
+        let mut next = [0usize; 7];
+        self.streams.iter().filter_map(move |stream| {
+            let index = next[*stream as usize];
+            next[*stream as usize] += 1;
+            match stream {
+                PsStream::Output => self.output.get(index).map(StreamRecord::Output),
+                PsStream::Error => self.errors.get(index).map(StreamRecord::Error),
+                PsStream::Warning => self.warning.get(index).map(StreamRecord::Warning),
+                PsStream::Verbose => self.verbose.get(index).map(StreamRecord::Verbose),
+                PsStream::Debug => self.debug.get(index).map(StreamRecord::Debug),
+                PsStream::Information => self.information.get(index).map(StreamRecord::Information),
+                PsStream::Progress => self.progress.get(index).map(StreamRecord::Progress),
+            }
+        })
+    }
+}
+
+impl From<PipelineRepresentation> for PipelineOutput {
+    fn from(pipeline: PipelineRepresentation) -> Self {
+        PipelineOutput {
+            state: pipeline.state,
+            output: pipeline.output,
+            errors: pipeline.errors,
+            warning: pipeline.warning,
+            verbose: pipeline.verbose,
+            debug: pipeline.debug,
+            information: pipeline.information,
+            progress: pipeline.progress,
+            streams: pipeline.streams,
+            failure: pipeline.failure,
+        }
+    }
+}
+
 /// Purely a reference to the underlying PowerShell instance inside the runspace pool.
 #[derive(Debug, Clone)]
 pub struct PowerShell {
@@ -55,3 +228,34 @@ pub struct Runspace {
     pub id: uuid::Uuid,
     pub state: super::enums::RunspacePoolState,
 }
+
+/// A change of state the server reported, of the pool or of one of its pipelines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateChange {
+    /// A `RUNSPACEPOOL_STATE`, with the error record of a pool that broke
+    RunspacePool {
+        previous: super::enums::RunspacePoolState,
+        state: super::enums::RunspacePoolState,
+        reason: Option<PsErrorRecord>,
+    },
+    /// A `PIPELINE_STATE`, with the error record of a pipeline that failed or was stopped
+    Pipeline {
+        id: uuid::Uuid,
+        previous: PsInvocationState,
+        state: PsInvocationState,
+        failure: Option<PsErrorRecord>,
+    },
+}
+
+impl StateChange {
+    /// Whether the pool broke, its shell is gone for the server
+    pub fn is_broken(&self) -> bool {
+        matches!(
+            self,
+            StateChange::RunspacePool {
+                state: super::enums::RunspacePoolState::Broken,
+                ..
+            }
+        )
+    }
+}
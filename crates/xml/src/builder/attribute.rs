@@ -1,5 +1,8 @@
 use std::{borrow::Cow, collections::HashMap};
 
+/// The namespace of the `xml` prefix, bound by definition and never declared.
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
 /// Represents an XML attribute with a name and value.
 #[derive(Debug, Clone)]
 pub struct Attribute<'a> {
@@ -50,6 +53,32 @@ impl<'a> Attribute<'a> {
         self
     }
 
+    pub(crate) fn rename_namespace(&mut self, from: &str, to: &'a str) {
+        if let Some(namespace) = &mut self.namespace {
+            if namespace.url == from {
+                namespace.url = to;
+            }
+        }
+    }
+
+    pub(crate) fn namespace(&self) -> Option<&crate::builder::Namespace<'a>> {
+        self.namespace.as_ref()
+    }
+
+    /// Whether the attribute is in the [`XML_NAMESPACE`], e.g. `xml:lang`
+    fn is_xml(&self) -> bool {
+        self.namespace
+            .as_ref()
+            .is_some_and(|namespace| namespace.url == XML_NAMESPACE)
+    }
+
+    /// Size of ` name="value"` once serialized with `prefix`
+    pub(crate) fn estimated_size(&self, prefix: Option<&str>) -> usize {
+        let prefix = if self.is_xml() { Some("xml") } else { prefix };
+        let prefix = prefix.map_or(0, |prefix| prefix.len() + 1);
+        1 + prefix + self.name.len() + 2 + crate::builder::escaped_len(&self.value) + 1
+    }
+
     pub fn get_namespaces(
         &self,
         namespaces_set: &mut std::collections::HashSet<crate::builder::Namespace<'a>>,
@@ -67,7 +96,9 @@ impl crate::builder::NamespaceFmt for Attribute<'_> {
         f: &mut std::fmt::Formatter<'_>,
         alias_map: Option<&HashMap<super::namespace::Namespace<'_>, Option<&str>>>,
     ) -> std::fmt::Result {
-        let namespace_alias = if let Some(alias_map) = alias_map {
+        let namespace_alias = if self.is_xml() {
+            Some(Some("xml"))
+        } else if let Some(alias_map) = alias_map {
             self.namespace
                 .as_ref()
                 .and_then(|ns| alias_map.get(ns))
@@ -85,7 +116,7 @@ impl crate::builder::NamespaceFmt for Attribute<'_> {
             self.name.to_string()
         };
 
-        write!(f, " {}=\"{}\"", name, self.value)?;
+        write!(f, " {}=\"{}\"", name, crate::builder::escape(&self.value))?;
         Ok(())
     }
 }
@@ -10,10 +10,19 @@ pub enum Content<'a> {
     Text(Cow<'a, str>),
     /// Represents a child element within an XML element.
     Elements(Vec<Element<'a>>),
+    /// Represents text interleaved with child elements within an XML element.
+    Mixed(Vec<Child<'a>>),
 
     None,
 }
 
+/// A child of an element with [`Content::Mixed`] content.
+#[derive(Debug, Clone)]
+pub enum Child<'a> {
+    Text(Cow<'a, str>),
+    Element(Element<'a>),
+}
+
 /// Represents an XML element.
 #[derive(Debug, Clone)]
 pub struct Element<'a> {
@@ -101,6 +110,68 @@ impl<'a> Element<'a> {
         self
     }
 
+    /// Moves the element and all its descendants from namespace `from` to namespace `to`,
+    /// including attributes and namespace declarations. Prefixes are kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use xml::builder::Element;
+    /// let element = Element::new("root")
+    ///     .set_namespace("urn:old")
+    ///     .add_namespace_declaration("urn:old", Some("o"))
+    ///     .rename_namespace("urn:old", "urn:new");
+    /// assert!(element.to_string().contains(r#"xmlns:o="urn:new""#));
+    /// ```
+    pub fn rename_namespace(mut self, from: &str, to: &'a str) -> Self {
+        if let Some(namespace) = &mut self.namespace {
+            if namespace.url == from {
+                namespace.url = to;
+            }
+        }
+
+        for attribute in &mut self.attributes {
+            attribute.rename_namespace(from, to);
+        }
+
+        if let Some(declarations) = &mut self.namespaces_declaration {
+            let renamed = declarations
+                .keys()
+                .find(|namespace| namespace.url == from)
+                .cloned();
+            if let Some(alias) = renamed.and_then(|namespace| declarations.remove(&namespace)) {
+                declarations.insert(Namespace::new(to), alias);
+            }
+        }
+
+        match self.content {
+            Content::Elements(children) => {
+                self.content = Content::Elements(
+                    children
+                        .into_iter()
+                        .map(|child| child.rename_namespace(from, to))
+                        .collect(),
+                );
+            }
+            Content::Mixed(children) => {
+                self.content = Content::Mixed(
+                    children
+                        .into_iter()
+                        .map(|child| match child {
+                            Child::Element(element) => {
+                                Child::Element(element.rename_namespace(from, to))
+                            }
+                            text => text,
+                        })
+                        .collect(),
+                );
+            }
+            content => self.content = content,
+        }
+
+        self
+    }
+
     /// Adds an attribute to the element and returns a modified `Element`.
     ///
     /// # Arguments
@@ -141,6 +212,9 @@ impl<'a> Element<'a> {
             Content::Elements(ref mut children) => {
                 children.push(child);
             }
+            Content::Mixed(ref mut children) => {
+                children.push(Child::Element(child));
+            }
         }
         self
     }
@@ -185,6 +259,121 @@ impl<'a> Element<'a> {
         self.content = Content::Text(std::borrow::Cow::Owned(text));
         self
     }
+
+    /// Sets the content of the element, e.g. text interleaved with child elements, and returns
+    /// a modified `Element`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use xml::builder::{Child, Content, Element};
+    /// let element = Element::new("root").set_content(Content::Mixed(vec![
+    ///     Child::Text("Hello, ".into()),
+    ///     Child::Element(Element::new("b").set_text("world")),
+    ///     Child::Text("!".into()),
+    /// ]));
+    /// assert_eq!(element.to_string(), "<root>Hello, <b>world</b>!</root>");
+    /// ```
+    pub fn set_content(mut self, content: Content<'a>) -> Self {
+        self.content = content;
+        self
+    }
+}
+
+type DeclarationMap<'a> = HashMap<Namespace<'a>, Option<&'a str>>;
+
+/// The alias of `namespace` in the innermost declaration that has it
+fn alias_in_scope<'a>(
+    namespace: Option<&Namespace<'_>>,
+    scopes: &[&DeclarationMap<'a>],
+) -> Option<&'a str> {
+    let namespace = namespace?;
+    scopes
+        .iter()
+        .rev()
+        .find_map(|map| map.get(namespace))
+        .copied()
+        .flatten()
+}
+
+impl<'a> Element<'a> {
+    /// Approximate size in bytes of the serialized element and its descendants.
+    ///
+    /// Text and attribute values are counted escaped, so the estimate is an upper bound of
+    /// [`ToString::to_string`]. When the text still has to be base64 encoded, add
+    /// [`super::base64_len`] of the payload instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use xml::builder::Element;
+    /// let element = Element::new("root")
+    ///     .set_namespace("urn:example")
+    ///     .add_namespace_declaration("urn:example", Some("e"))
+    ///     .add_child(Element::new("child").set_text("some text"));
+    /// assert_eq!(element.estimated_size(), element.to_string().len());
+    /// ```
+    pub fn estimated_size(&self) -> usize {
+        self.estimated_size_in(&mut Vec::new())
+    }
+
+    fn estimated_size_in<'s>(&'s self, scopes: &mut Vec<&'s DeclarationMap<'a>>) -> usize {
+        if let Some(declarations) = &self.namespaces_declaration {
+            scopes.push(declarations);
+        }
+
+        let name_len = alias_in_scope(self.namespace.as_ref(), scopes)
+            .map_or(0, |alias| alias.len() + 1)
+            + self.name.len();
+
+        // <name
+        let mut size = 1 + name_len;
+
+        // xmlns:alias="url"
+        if let Some(declarations) = &self.namespaces_declaration {
+            size += declarations
+                .iter()
+                .map(|(namespace, alias)| {
+                    let alias = alias.map_or(0, |alias| alias.len() + 1);
+                    " xmlns".len() + alias + 2 + namespace.url.len() + 1
+                })
+                .sum::<usize>();
+        }
+
+        for attribute in &self.attributes {
+            size += attribute.estimated_size(alias_in_scope(attribute.namespace(), scopes));
+        }
+
+        // > ... </name>
+        let closing = 1 + 2 + name_len + 1;
+        size += match &self.content {
+            Content::None => 2,
+            Content::Text(text) => super::escaped_len(text) + closing,
+            Content::Elements(children) => {
+                children
+                    .iter()
+                    .map(|child| child.estimated_size_in(scopes))
+                    .sum::<usize>()
+                    + closing
+            }
+            Content::Mixed(children) => {
+                children
+                    .iter()
+                    .map(|child| match child {
+                        Child::Text(text) => super::escaped_len(text),
+                        Child::Element(element) => element.estimated_size_in(scopes),
+                    })
+                    .sum::<usize>()
+                    + closing
+            }
+        };
+
+        if self.namespaces_declaration.is_some() {
+            scopes.pop();
+        }
+
+        size
+    }
 }
 
 impl std::fmt::Display for Element<'_> {
@@ -295,7 +484,7 @@ impl crate::builder::NamespaceFmt for Element<'_> {
                 write!(f, "/>")?;
             }
             Content::Text(value) => {
-                write!(f, ">{value}</{name}>")?;
+                write!(f, ">{}</{name}>", super::escape(value))?;
             }
             Content::Elements(children) => {
                 write!(f, ">")?;
@@ -304,6 +493,18 @@ impl crate::builder::NamespaceFmt for Element<'_> {
                 }
                 write!(f, "</{name}>")?;
             }
+            Content::Mixed(children) => {
+                write!(f, ">")?;
+                for child in children {
+                    match child {
+                        Child::Text(text) => write!(f, "{}", super::escape(text))?,
+                        Child::Element(element) => {
+                            element.ns_fmt(f, namespace_declaration_map.as_deref())?
+                        }
+                    }
+                }
+                write!(f, "</{name}>")?;
+            }
         }
         Ok(())
     }
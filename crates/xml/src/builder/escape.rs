@@ -0,0 +1,31 @@
+use std::borrow::Cow;
+
+/// `text` with the XML special characters replaced by their entity, borrowed when there is
+/// nothing to escape. The escaped length is [`escaped_len`](crate::builder::escaped_len).
+///
+/// # Example
+///
+/// ```
+/// use xml::builder::escape;
+/// assert_eq!(escape("a<b & 'c'"), "a&lt;b &amp; &apos;c&apos;");
+/// assert!(matches!(escape("abc"), std::borrow::Cow::Borrowed("abc")));
+/// ```
+pub fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['<', '>', '&', '"', '\'']) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(crate::builder::escaped_len(text));
+    for character in text.chars() {
+        match character {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
@@ -5,7 +5,9 @@ mod attribute;
 mod builder;
 mod declaration;
 mod element;
+mod escape;
 mod namespace;
+mod size;
 
 use std::collections::HashMap;
 
@@ -13,7 +15,9 @@ pub use self::attribute::*;
 pub use self::builder::*;
 pub use self::declaration::*;
 pub use self::element::*;
+pub use self::escape::*;
 pub use self::namespace::*;
+pub use self::size::*;
 
 pub trait NamespaceFmt {
     fn ns_fmt(
@@ -117,6 +121,20 @@ mod tests {
         assert_eq!(xml_string, r#"<message lang="en">Hello, world!</message>"#);
     }
 
+    #[test]
+    fn test_text_and_attributes_are_escaped() {
+        let element = Element::new("Filter")
+            .add_attribute(Attribute::new("Note", r#"say "hi""#))
+            .set_text("State='Running' AND Size < 10 & Size > 2");
+
+        let builder = Builder::new(None, element);
+        let xml_string = builder.to_string();
+        assert_eq!(
+            xml_string,
+            r#"<Filter Note="say &quot;hi&quot;">State=&apos;Running&apos; AND Size &lt; 10 &amp; Size &gt; 2</Filter>"#
+        );
+    }
+
     #[test]
     fn test_adding_child_overwrites_text() {
         let child = Element::new("item");
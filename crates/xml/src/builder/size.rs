@@ -0,0 +1,45 @@
+/// Length of `text` once the XML special characters are escaped.
+///
+/// # Example
+///
+/// ```
+/// use xml::builder::escaped_len;
+/// assert_eq!(escaped_len("a<b"), "a&lt;b".len());
+/// ```
+pub fn escaped_len(text: &str) -> usize {
+    text.len()
+        + text
+            .bytes()
+            .map(|byte| match byte {
+                b'<' | b'>' => 3,
+                b'&' => 4,
+                b'"' | b'\'' => 5,
+                _ => 0,
+            })
+            .sum::<usize>()
+}
+
+/// Length of `len` bytes once base64 encoded with padding.
+///
+/// # Example
+///
+/// ```
+/// use xml::builder::base64_len;
+/// assert_eq!(base64_len(4), 8);
+/// ```
+pub fn base64_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+/// Largest number of bytes whose base64 encoding fits in `encoded_len`, the inverse of [`base64_len`]
+///
+/// # Example
+///
+/// ```
+/// use xml::builder::{base64_capacity, base64_len};
+/// assert_eq!(base64_capacity(10), 6);
+/// assert!(base64_len(base64_capacity(10)) <= 10);
+/// ```
+pub fn base64_capacity(encoded_len: usize) -> usize {
+    encoded_len / 4 * 3
+}